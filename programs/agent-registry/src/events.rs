@@ -3,7 +3,9 @@
 use serde::{Deserialize, Serialize};
 use solana_program::pubkey::Pubkey;
 use aeamcp_common::{
+    config::RegistryConfigV1,
     constants::HASH_SIZE,
+    revenue::RevenueSplitV1,
     serialization::{ServiceEndpoint, AgentSkill},
     utils::emit_event,
 };
@@ -113,6 +115,83 @@ pub struct DisputeRecordedEvent {
     pub reputation_score: u64,
 }
 
+/// Event emitted when an overflow skill account is initialized, updated or closed
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SkillAccountChangedEvent {
+    pub agent_id: String,
+    pub index: u8,
+    /// Skill now held by the account, `None` once it is closed
+    pub skill_id: Option<String>,
+    pub skill_account_count: u8,
+}
+
+/// Event emitted when the registry config is created or updated
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct RegistryConfigUpdatedEvent {
+    pub admin_authority: Pubkey,
+    pub accepted_mint: Pubkey,
+    pub treasury: Pubkey,
+    pub registration_fee: u64,
+}
+
+/// Event emitted when the registry's token vaults are created
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct VaultsInitializedEvent {
+    pub mint: Pubkey,
+    pub staking_vault: Pubkey,
+    pub fee_vault: Pubkey,
+    pub registration_vault: Pubkey,
+    pub treasury_vault: Pubkey,
+}
+
+/// Event emitted when service earnings are paid out
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct RevenueDistributedEvent {
+    pub agent_entry: Pubkey,
+    /// Protocol take-rate paid into the treasury vault
+    pub protocol_fee: u64,
+    /// Total paid to revenue split recipients
+    pub split_amount: u64,
+    /// Remainder paid to the owner
+    pub owner_amount: u64,
+}
+
+/// Event emitted when an agent's revenue split table changes
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct RevenueSplitUpdatedEvent {
+    pub agent_entry: Pubkey,
+    /// Recipients and their shares in basis points
+    pub recipients: Vec<(Pubkey, u16)>,
+}
+
+/// Event emitted when a fee increase is scheduled to apply after the
+/// registry's notice period
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct FeeChangeScheduledEvent {
+    pub agent_id: String,
+    /// Base fee that will apply
+    pub base_fee: u64,
+    /// Priority multiplier that will apply
+    pub priority_multiplier: u8,
+    /// When the new fees apply
+    pub effective_at: i64,
+}
+
+/// Event emitted when protocol revenue leaves the treasury vault
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct TreasuryWithdrawnEvent {
+    pub destination: Pubkey,
+    pub amount: u64,
+}
+
+/// Event emitted when an entry is rewritten into the current layout
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct AgentEntryMigratedEvent {
+    pub agent_id: String,
+    pub registry_version: u8,
+    pub state_version: u64,
+}
+
 /// Emit an agent registered event
 pub fn emit_agent_registered(event: &AgentRegisteredEvent) {
     let data = serde_json::to_value(event).unwrap();
@@ -173,6 +252,54 @@ pub fn emit_dispute_recorded(event: &DisputeRecordedEvent) {
     emit_event("DisputeRecorded", &data);
 }
 
+/// Emit a skill account changed event
+pub fn emit_skill_account_changed(event: &SkillAccountChangedEvent) {
+    let data = serde_json::to_value(event).unwrap();
+    emit_event("SkillAccountChanged", &data);
+}
+
+/// Emit a registry config updated event
+pub fn emit_registry_config_updated(event: &RegistryConfigUpdatedEvent) {
+    let data = serde_json::to_value(event).unwrap();
+    emit_event("RegistryConfigUpdated", &data);
+}
+
+/// Emit a vaults initialized event
+pub fn emit_vaults_initialized(event: &VaultsInitializedEvent) {
+    let data = serde_json::to_value(event).unwrap();
+    emit_event("VaultsInitialized", &data);
+}
+
+/// Emit a revenue distributed event
+pub fn emit_revenue_distributed(event: &RevenueDistributedEvent) {
+    let data = serde_json::to_value(event).unwrap();
+    emit_event("RevenueDistributed", &data);
+}
+
+/// Emit a revenue split updated event
+pub fn emit_revenue_split_updated(event: &RevenueSplitUpdatedEvent) {
+    let data = serde_json::to_value(event).unwrap();
+    emit_event("RevenueSplitUpdated", &data);
+}
+
+/// Emit a fee change scheduled event
+pub fn emit_fee_change_scheduled(event: &FeeChangeScheduledEvent) {
+    let data = serde_json::to_value(event).unwrap();
+    emit_event("FeeChangeScheduled", &data);
+}
+
+/// Emit a treasury withdrawn event
+pub fn emit_treasury_withdrawn(event: &TreasuryWithdrawnEvent) {
+    let data = serde_json::to_value(event).unwrap();
+    emit_event("TreasuryWithdrawn", &data);
+}
+
+/// Emit an agent entry migrated event
+pub fn emit_agent_entry_migrated(event: &AgentEntryMigratedEvent) {
+    let data = serde_json::to_value(event).unwrap();
+    emit_event("AgentEntryMigrated", &data);
+}
+
 /// Helper function to create an AgentRegisteredEvent from state
 pub fn create_agent_registered_event(
     registry_version: u8,
@@ -353,6 +480,108 @@ pub fn create_dispute_recorded_event(
     }
 }
 
+/// Helper function to create a SkillAccountChangedEvent
+pub fn create_skill_account_changed_event(
+    agent_id: String,
+    index: u8,
+    skill_id: Option<String>,
+    skill_account_count: u8,
+) -> SkillAccountChangedEvent {
+    SkillAccountChangedEvent {
+        agent_id,
+        index,
+        skill_id,
+        skill_account_count,
+    }
+}
+
+/// Helper function to create a RegistryConfigUpdatedEvent
+pub fn create_registry_config_updated_event(config: &RegistryConfigV1) -> RegistryConfigUpdatedEvent {
+    RegistryConfigUpdatedEvent {
+        admin_authority: config.admin_authority,
+        accepted_mint: config.params.accepted_mint,
+        treasury: config.params.treasury,
+        registration_fee: config.params.registration_fee,
+    }
+}
+
+/// Helper function to create a VaultsInitializedEvent
+pub fn create_vaults_initialized_event(
+    mint: Pubkey,
+    staking_vault: Pubkey,
+    fee_vault: Pubkey,
+    registration_vault: Pubkey,
+    treasury_vault: Pubkey,
+) -> VaultsInitializedEvent {
+    VaultsInitializedEvent {
+        mint,
+        staking_vault,
+        fee_vault,
+        registration_vault,
+        treasury_vault,
+    }
+}
+
+/// Helper function to create a RevenueDistributedEvent
+pub fn create_revenue_distributed_event(
+    agent_entry: Pubkey,
+    protocol_fee: u64,
+    split_amount: u64,
+    owner_amount: u64,
+) -> RevenueDistributedEvent {
+    RevenueDistributedEvent {
+        agent_entry,
+        protocol_fee,
+        split_amount,
+        owner_amount,
+    }
+}
+
+/// Helper function to create a RevenueSplitUpdatedEvent
+pub fn create_revenue_split_updated_event(split: &RevenueSplitV1) -> RevenueSplitUpdatedEvent {
+    RevenueSplitUpdatedEvent {
+        agent_entry: split.entry,
+        recipients: split
+            .active_recipients()
+            .iter()
+            .map(|r| (r.recipient, r.bps))
+            .collect(),
+    }
+}
+
+/// Helper function to create a FeeChangeScheduledEvent
+pub fn create_fee_change_scheduled_event(
+    agent_id: String,
+    base_fee: u64,
+    priority_multiplier: u8,
+    effective_at: i64,
+) -> FeeChangeScheduledEvent {
+    FeeChangeScheduledEvent {
+        agent_id,
+        base_fee,
+        priority_multiplier,
+        effective_at,
+    }
+}
+
+/// Helper function to create a TreasuryWithdrawnEvent
+pub fn create_treasury_withdrawn_event(destination: Pubkey, amount: u64) -> TreasuryWithdrawnEvent {
+    TreasuryWithdrawnEvent { destination, amount }
+}
+
+/// Helper function to create an AgentEntryMigratedEvent
+pub fn create_agent_entry_migrated_event(
+    agent_id: String,
+    registry_version: u8,
+    state_version: u64,
+) -> AgentEntryMigratedEvent {
+    AgentEntryMigratedEvent {
+        agent_id,
+        registry_version,
+        state_version,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1,10 +1,12 @@
 //! Instruction types for the Agent Registry program
 
 use borsh::{BorshDeserialize, BorshSerialize};
-use solana_program::program_error::ProgramError;
+use solana_program::{program_error::ProgramError, pubkey::Pubkey};
 use shank::ShankInstruction;
 use aeamcp_common::{
-    constants::HASH_SIZE,
+    config::RegistryConfigParams,
+    constants::{HASH_SIZE, IDEMPOTENCY_KEY_LEN},
+    revenue::SplitRecipient,
     serialization::{ServiceEndpointInput, AgentSkillInput},
 };
 
@@ -40,12 +42,20 @@ pub enum AgentRegistryInstruction {
     },
 
     /// Update the details of an existing agent
+    ///
+    /// The entry account is resized to fit the new details; growth is paid
+    /// by the payer and any rent freed by shrinking is refunded to it.
     /// 
     /// Accounts expected:
     /// 0. `[writable]` Agent entry PDA
     /// 1. `[signer]` Owner authority
+    /// 2. `[writable, signer]` Payer
+    /// 3. `[]` System program
     UpdateAgentDetails {
         details: AgentUpdateDetailsInput,
+        /// Optional compare-and-swap guard; fails with `StateVersionMismatch` if the
+        /// entry's `state_version` differs
+        expected_state_version: Option<u64>,
     },
 
     /// Update the status of an existing agent
@@ -55,6 +65,9 @@ pub enum AgentRegistryInstruction {
     /// 1. `[signer]` Owner authority
     UpdateAgentStatus {
         new_status: u8,
+        /// Optional compare-and-swap guard; fails with `StateVersionMismatch` if the
+        /// entry's `state_version` differs
+        expected_state_version: Option<u64>,
     },
 
     /// Deregister an agent from the Agent Registry
@@ -62,7 +75,11 @@ pub enum AgentRegistryInstruction {
     /// Accounts expected:
     /// 0. `[writable]` Agent entry PDA
     /// 1. `[signer]` Owner authority
-    DeregisterAgent,
+    DeregisterAgent {
+        /// Optional compare-and-swap guard; fails with `StateVersionMismatch` if the
+        /// entry's `state_version` differs
+        expected_state_version: Option<u64>,
+    },
     
     /// Register a new agent with SVMAI token payment
     ///
@@ -76,6 +93,7 @@ pub enum AgentRegistryInstruction {
     /// 6. `[]` Token program
     /// 7. `[]` System program
     /// 8. `[]` Clock sysvar
+    /// 9. `[]` Registry config PDA
     RegisterAgentWithToken {
         agent_id: String,
         name: String,
@@ -106,9 +124,13 @@ pub enum AgentRegistryInstruction {
     /// 3. `[writable]` Staking vault token account
     /// 4. `[]` Token program
     /// 5. `[]` Clock sysvar
+    /// 6. `[]` Registry config PDA
     StakeTokens {
         amount: u64,
         lock_period: i64, // in seconds
+        /// Optional compare-and-swap guard; fails with `StateVersionMismatch` if the
+        /// entry's `state_version` differs
+        expected_state_version: Option<u64>,
     },
     
     /// Unstake tokens after lock period
@@ -120,8 +142,12 @@ pub enum AgentRegistryInstruction {
     /// 3. `[writable]` Agent's token account
     /// 4. `[]` Token program
     /// 5. `[]` Clock sysvar
+    /// 6. `[]` Registry config PDA
     UnstakeTokens {
         amount: u64,
+        /// Optional compare-and-swap guard; fails with `StateVersionMismatch` if the
+        /// entry's `state_version` differs
+        expected_state_version: Option<u64>,
     },
     
     /// Update service fee configuration
@@ -130,22 +156,53 @@ pub enum AgentRegistryInstruction {
     /// 0. `[signer]` Agent owner
     /// 1. `[writable]` Agent registry PDA
     /// 2. `[]` Clock sysvar
+    /// 3. `[]` Registry config PDA
     UpdateServiceFees {
         base_fee: u64,
         priority_multiplier: u8,
         accepts_escrow: bool,
+        /// Optional compare-and-swap guard; fails with `StateVersionMismatch` if the
+        /// entry's `state_version` differs
+        expected_state_version: Option<u64>,
     },
     
     /// Record service completion (called by escrow)
     ///
+    /// If the settlement accounts are passed, `earnings` is also paid out of
+    /// the escrow's token account: the protocol take-rate to the treasury
+    /// vault, then the agent's revenue split, then the rest to the owner.
+    ///
     /// Accounts expected:
-    /// 0. `[signer]` Escrow program
+    /// 0. `[]` Escrow program
     /// 1. `[writable]` Agent registry PDA
     /// 2. `[]` Clock sysvar
+    /// 3. `[signer, writable]` Paying user, who agrees to `max_total_fee` and
+    ///    pays the receipt's rent
+    /// 4. `[writable]` Usage receipt PDA of `idempotency_key` (to be created)
+    /// 5. `[]` System program
+    /// 6. `[signer]` Escrow authority PDA of account 0, signed by the escrow
+    ///    program through `invoke_signed`
+    /// 7. `[writable]` (optional) Escrow token account, owned by account 6
+    /// 8. `[writable]` (optional) Agent owner's token account
+    /// 9. `[writable]` (optional) Treasury vault PDA
+    /// 10. `[]` (optional) Registry config PDA
+    /// 11. `[]` (optional) SPL Token program
+    /// 12. `[]` (optional) Revenue split PDA of the agent
+    /// 13. `[writable]` (optional) Token account of each split recipient, in table order
     RecordServiceCompletion {
         earnings: u64,
         rating: u8,
         response_time: u32,
+        /// Most the paying user agrees to pay; fails with `MaxFeeExceeded` if
+        /// the agent's service fee in force (base fee times priority
+        /// multiplier) or `earnings` is above it
+        max_total_fee: u64,
+        /// Client-generated key of this completion; fails with
+        /// `DuplicateIdempotencyKey` if the escrow already used it on this agent
+        idempotency_key: [u8; 16],
+        /// Optional compare-and-swap guard; fails with `StateVersionMismatch` if the
+        /// entry's `state_version` differs
+        expected_state_version: Option<u64>,
     },
     
     /// Record dispute outcome (called by DDR)
@@ -155,7 +212,198 @@ pub enum AgentRegistryInstruction {
     /// 1. `[writable]` Agent registry PDA
     RecordDisputeOutcome {
         won: bool,
+        /// Optional compare-and-swap guard; fails with `StateVersionMismatch` if the
+        /// entry's `state_version` differs
+        expected_state_version: Option<u64>,
+    },
+
+    /// Create an overflow skill account for skills beyond the inline limit.
+    /// `index` must equal the entry's current `skill_account_count`.
+    ///
+    /// Accounts expected:
+    /// 0. `[writable]` Skill account PDA (to be created)
+    /// 1. `[writable]` Agent entry PDA
+    /// 2. `[signer]` Owner authority
+    /// 3. `[writable, signer]` Payer
+    /// 4. `[]` System program
+    InitializeSkillAccount {
+        index: u8,
+        skill: AgentSkillInput,
+    },
+
+    /// Replace the skill held by an overflow skill account
+    ///
+    /// Accounts expected:
+    /// 0. `[writable]` Skill account PDA
+    /// 1. `[]` Agent entry PDA
+    /// 2. `[signer]` Owner authority
+    /// 3. `[writable, signer]` Payer (funds growth, receives refunds)
+    /// 4. `[]` System program
+    UpdateSkillAccount {
+        index: u8,
+        skill: AgentSkillInput,
+    },
+
+    /// Close the last overflow skill account and reclaim its rent
+    ///
+    /// Accounts expected:
+    /// 0. `[writable]` Skill account PDA
+    /// 1. `[writable]` Agent entry PDA
+    /// 2. `[signer]` Owner authority
+    /// 3. `[writable]` Rent recipient
+    CloseSkillAccount {
+        index: u8,
+    },
+
+    /// Start a chunked registration for agents too large to register in one
+    /// transaction. Creates the entry with the core fields and no skills,
+    /// endpoints or tags; the entry stays `Pending` and accepts the append
+    /// instructions below until `FinalizeAgentRegistration`.
+    ///
+    /// Accounts expected:
+    /// 0. `[writable]` Agent entry PDA (to be created)
+    /// 1. `[signer]` Owner authority
+    /// 2. `[writable, signer]` Payer
+    /// 3. `[]` System program
+    BeginAgentRegistration {
+        agent_id: String,
+        name: String,
+        description: String,
+        agent_version: String,
+        provider_name: Option<String>,
+        provider_url: Option<String>,
+        documentation_url: Option<String>,
+        capabilities_flags: u64,
+        supported_input_modes: Vec<String>,
+        supported_output_modes: Vec<String>,
+        security_info_uri: Option<String>,
+        aea_address: Option<String>,
+        economic_intent_summary: Option<String>,
+        supported_aea_protocols_hash: Option<[u8; HASH_SIZE]>,
+        extended_metadata_uri: Option<String>,
+    },
+
+    /// Append skills to an agent whose registration is still open
+    ///
+    /// Accounts expected:
+    /// 0. `[writable]` Agent entry PDA
+    /// 1. `[signer]` Owner authority
+    /// 2. `[writable, signer]` Payer
+    /// 3. `[]` System program
+    AppendAgentSkills {
+        skills: Vec<AgentSkillInput>,
+    },
+
+    /// Append service endpoints to an agent whose registration is still open.
+    /// The single-default rule is checked on finalize, once all are present.
+    ///
+    /// Accounts expected:
+    /// 0. `[writable]` Agent entry PDA
+    /// 1. `[signer]` Owner authority
+    /// 2. `[writable, signer]` Payer
+    /// 3. `[]` System program
+    AppendAgentServiceEndpoints {
+        service_endpoints: Vec<ServiceEndpointInput>,
+    },
+
+    /// Append tags to an agent whose registration is still open
+    ///
+    /// Accounts expected:
+    /// 0. `[writable]` Agent entry PDA
+    /// 1. `[signer]` Owner authority
+    /// 2. `[writable, signer]` Payer
+    /// 3. `[]` System program
+    AppendAgentTags {
+        tags: Vec<String>,
+    },
+
+    /// Run full validation over the assembled entry, close the registration
+    /// and move the agent from `Pending` to `Active`
+    ///
+    /// Accounts expected:
+    /// 0. `[writable]` Agent entry PDA
+    /// 1. `[signer]` Owner authority
+    FinalizeAgentRegistration,
+
+    /// Create the registry config PDA. Only the program's upgrade authority
+    /// can call this, and it becomes the config's admin.
+    ///
+    /// Accounts expected:
+    /// 0. `[writable]` Registry config PDA (to be created)
+    /// 1. `[signer]` Upgrade authority
+    /// 2. `[writable, signer]` Payer
+    /// 3. `[]` Program data account of this program
+    /// 4. `[]` System program
+    InitializeRegistryConfig {
+        params: RegistryConfigParams,
     },
+
+    /// Replace the registry config parameters, optionally handing the config
+    /// to a new admin
+    ///
+    /// The accepted mint is fixed once the vaults exist, since they hold that
+    /// mint.
+    ///
+    /// Accounts expected:
+    /// 0. `[writable]` Registry config PDA
+    /// 1. `[signer]` Admin authority
+    /// 2. `[]` Staking vault PDA
+    UpdateRegistryConfig {
+        params: RegistryConfigParams,
+        new_admin: Option<Pubkey>,
+    },
+
+    /// Create the staking, fee, registration and treasury vault token
+    /// accounts for the configured mint. Each vault is its own token
+    /// authority. Fails if any vault already exists.
+    ///
+    /// Accounts expected:
+    /// 0. `[]` Registry config PDA
+    /// 1. `[signer]` Admin authority
+    /// 2. `[writable, signer]` Payer
+    /// 3. `[]` Accepted token mint
+    /// 4. `[writable]` Staking vault PDA (to be created)
+    /// 5. `[writable]` Fee vault PDA (to be created)
+    /// 6. `[writable]` Registration vault PDA (to be created)
+    /// 7. `[writable]` Treasury vault PDA (to be created)
+    /// 8. `[]` SPL Token program
+    /// 9. `[]` System program
+    InitializeVaults,
+
+    /// Set the agent's revenue split table. An empty table pays everything
+    /// to the owner.
+    ///
+    /// Accounts expected:
+    /// 0. `[writable]` Revenue split PDA (created on first use)
+    /// 1. `[]` Agent entry PDA
+    /// 2. `[signer]` Owner authority
+    /// 3. `[writable, signer]` Payer
+    /// 4. `[]` System program
+    SetRevenueSplit {
+        recipients: Vec<SplitRecipient>,
+    },
+
+    /// Move protocol revenue out of the treasury vault to the config's
+    /// treasury wallet
+    ///
+    /// Accounts expected:
+    /// 0. `[]` Registry config PDA
+    /// 1. `[signer]` Admin authority
+    /// 2. `[writable]` Treasury vault PDA
+    /// 3. `[writable]` Token account of the config's treasury wallet
+    /// 4. `[]` SPL Token program
+    WithdrawTreasury {
+        amount: u64,
+    },
+
+    /// Rewrite an entry stored in layout version 1 into the current layout.
+    /// Anyone may migrate an entry; its contents do not change.
+    ///
+    /// Accounts expected:
+    /// 0. `[writable]` Agent entry PDA
+    /// 1. `[writable, signer]` Payer of any extra rent
+    /// 2. `[]` System program
+    MigrateAgentEntry,
 }
 
 /// Input struct for updating agent details
@@ -219,16 +467,26 @@ impl AgentRegistryInstruction {
                 }
             }
             1 => {
-                let details = AgentUpdateDetailsInput::try_from_slice(rest)
+                let data = UpdateAgentDetailsData::try_from_slice(rest)
                     .map_err(|_| ProgramError::InvalidInstructionData)?;
-                Self::UpdateAgentDetails { details }
+                Self::UpdateAgentDetails {
+                    details: data.details,
+                    expected_state_version: data.expected_state_version,
+                }
             }
             2 => {
-                let new_status = u8::try_from_slice(rest)
+                let data = UpdateAgentStatusData::try_from_slice(rest)
+                    .map_err(|_| ProgramError::InvalidInstructionData)?;
+                Self::UpdateAgentStatus {
+                    new_status: data.new_status,
+                    expected_state_version: data.expected_state_version,
+                }
+            }
+            3 => {
+                let expected_state_version = Option::<u64>::try_from_slice(rest)
                     .map_err(|_| ProgramError::InvalidInstructionData)?;
-                Self::UpdateAgentStatus { new_status }
+                Self::DeregisterAgent { expected_state_version }
             }
-            3 => Self::DeregisterAgent,
             4 => {
                 let data = RegisterAgentData::try_from_slice(rest)
                     .map_err(|_| ProgramError::InvalidInstructionData)?;
@@ -259,12 +517,16 @@ impl AgentRegistryInstruction {
                 Self::StakeTokens {
                     amount: data.amount,
                     lock_period: data.lock_period,
+                    expected_state_version: data.expected_state_version,
                 }
             }
             6 => {
-                let amount = u64::try_from_slice(rest)
+                let data = UnstakeTokensData::try_from_slice(rest)
                     .map_err(|_| ProgramError::InvalidInstructionData)?;
-                Self::UnstakeTokens { amount }
+                Self::UnstakeTokens {
+                    amount: data.amount,
+                    expected_state_version: data.expected_state_version,
+                }
             }
             7 => {
                 let data = UpdateServiceFeesData::try_from_slice(rest)
@@ -273,6 +535,7 @@ impl AgentRegistryInstruction {
                     base_fee: data.base_fee,
                     priority_multiplier: data.priority_multiplier,
                     accepts_escrow: data.accepts_escrow,
+                    expected_state_version: data.expected_state_version,
                 }
             }
             8 => {
@@ -282,13 +545,102 @@ impl AgentRegistryInstruction {
                     earnings: data.earnings,
                     rating: data.rating,
                     response_time: data.response_time,
+                    max_total_fee: data.max_total_fee,
+                    idempotency_key: data.idempotency_key,
+                    expected_state_version: data.expected_state_version,
                 }
             }
             9 => {
-                let won = bool::try_from_slice(rest)
+                let data = RecordDisputeOutcomeData::try_from_slice(rest)
+                    .map_err(|_| ProgramError::InvalidInstructionData)?;
+                Self::RecordDisputeOutcome {
+                    won: data.won,
+                    expected_state_version: data.expected_state_version,
+                }
+            }
+            10 => {
+                let data = SkillAccountData::try_from_slice(rest)
                     .map_err(|_| ProgramError::InvalidInstructionData)?;
-                Self::RecordDisputeOutcome { won }
+                Self::InitializeSkillAccount {
+                    index: data.index,
+                    skill: data.skill,
+                }
             }
+            11 => {
+                let data = SkillAccountData::try_from_slice(rest)
+                    .map_err(|_| ProgramError::InvalidInstructionData)?;
+                Self::UpdateSkillAccount {
+                    index: data.index,
+                    skill: data.skill,
+                }
+            }
+            12 => {
+                let index = u8::try_from_slice(rest)
+                    .map_err(|_| ProgramError::InvalidInstructionData)?;
+                Self::CloseSkillAccount { index }
+            }
+            13 => {
+                let data = BeginAgentRegistrationData::try_from_slice(rest)
+                    .map_err(|_| ProgramError::InvalidInstructionData)?;
+                Self::BeginAgentRegistration {
+                    agent_id: data.agent_id,
+                    name: data.name,
+                    description: data.description,
+                    agent_version: data.agent_version,
+                    provider_name: data.provider_name,
+                    provider_url: data.provider_url,
+                    documentation_url: data.documentation_url,
+                    capabilities_flags: data.capabilities_flags,
+                    supported_input_modes: data.supported_input_modes,
+                    supported_output_modes: data.supported_output_modes,
+                    security_info_uri: data.security_info_uri,
+                    aea_address: data.aea_address,
+                    economic_intent_summary: data.economic_intent_summary,
+                    supported_aea_protocols_hash: data.supported_aea_protocols_hash,
+                    extended_metadata_uri: data.extended_metadata_uri,
+                }
+            }
+            14 => {
+                let skills = Vec::<AgentSkillInput>::try_from_slice(rest)
+                    .map_err(|_| ProgramError::InvalidInstructionData)?;
+                Self::AppendAgentSkills { skills }
+            }
+            15 => {
+                let service_endpoints = Vec::<ServiceEndpointInput>::try_from_slice(rest)
+                    .map_err(|_| ProgramError::InvalidInstructionData)?;
+                Self::AppendAgentServiceEndpoints { service_endpoints }
+            }
+            16 => {
+                let tags = Vec::<String>::try_from_slice(rest)
+                    .map_err(|_| ProgramError::InvalidInstructionData)?;
+                Self::AppendAgentTags { tags }
+            }
+            17 => Self::FinalizeAgentRegistration,
+            18 => {
+                let params = RegistryConfigParams::try_from_slice(rest)
+                    .map_err(|_| ProgramError::InvalidInstructionData)?;
+                Self::InitializeRegistryConfig { params }
+            }
+            19 => {
+                let data = UpdateRegistryConfigData::try_from_slice(rest)
+                    .map_err(|_| ProgramError::InvalidInstructionData)?;
+                Self::UpdateRegistryConfig {
+                    params: data.params,
+                    new_admin: data.new_admin,
+                }
+            }
+            20 => Self::InitializeVaults,
+            21 => {
+                let recipients = Vec::<SplitRecipient>::try_from_slice(rest)
+                    .map_err(|_| ProgramError::InvalidInstructionData)?;
+                Self::SetRevenueSplit { recipients }
+            }
+            22 => {
+                let amount = u64::try_from_slice(rest)
+                    .map_err(|_| ProgramError::InvalidInstructionData)?;
+                Self::WithdrawTreasury { amount }
+            }
+            23 => Self::MigrateAgentEntry,
             _ => return Err(ProgramError::InvalidInstructionData),
         })
     }
@@ -340,16 +692,25 @@ impl AgentRegistryInstruction {
                 };
                 buf.extend_from_slice(&data.try_to_vec().unwrap());
             }
-            Self::UpdateAgentDetails { details } => {
+            Self::UpdateAgentDetails { details, expected_state_version } => {
                 buf.push(1);
-                buf.extend_from_slice(&details.try_to_vec().unwrap());
+                let data = UpdateAgentDetailsData {
+                    details: details.clone(),
+                    expected_state_version: *expected_state_version,
+                };
+                buf.extend_from_slice(&data.try_to_vec().unwrap());
             }
-            Self::UpdateAgentStatus { new_status } => {
+            Self::UpdateAgentStatus { new_status, expected_state_version } => {
                 buf.push(2);
-                buf.extend_from_slice(&new_status.try_to_vec().unwrap());
+                let data = UpdateAgentStatusData {
+                    new_status: *new_status,
+                    expected_state_version: *expected_state_version,
+                };
+                buf.extend_from_slice(&data.try_to_vec().unwrap());
             }
-            Self::DeregisterAgent => {
+            Self::DeregisterAgent { expected_state_version } => {
                 buf.push(3);
+                buf.extend_from_slice(&expected_state_version.try_to_vec().unwrap());
             }
             Self::RegisterAgentWithToken {
                 agent_id,
@@ -394,39 +755,162 @@ impl AgentRegistryInstruction {
                 };
                 buf.extend_from_slice(&data.try_to_vec().unwrap());
             }
-            Self::StakeTokens { amount, lock_period } => {
+            Self::StakeTokens { amount, lock_period, expected_state_version } => {
                 buf.push(5);
                 let data = StakeTokensData {
                     amount: *amount,
                     lock_period: *lock_period,
+                    expected_state_version: *expected_state_version,
                 };
                 buf.extend_from_slice(&data.try_to_vec().unwrap());
             }
-            Self::UnstakeTokens { amount } => {
+            Self::UnstakeTokens { amount, expected_state_version } => {
                 buf.push(6);
-                buf.extend_from_slice(&amount.try_to_vec().unwrap());
+                let data = UnstakeTokensData {
+                    amount: *amount,
+                    expected_state_version: *expected_state_version,
+                };
+                buf.extend_from_slice(&data.try_to_vec().unwrap());
             }
-            Self::UpdateServiceFees { base_fee, priority_multiplier, accepts_escrow } => {
+            Self::UpdateServiceFees {
+                base_fee,
+                priority_multiplier,
+                accepts_escrow,
+                expected_state_version,
+            } => {
                 buf.push(7);
                 let data = UpdateServiceFeesData {
                     base_fee: *base_fee,
                     priority_multiplier: *priority_multiplier,
                     accepts_escrow: *accepts_escrow,
+                    expected_state_version: *expected_state_version,
                 };
                 buf.extend_from_slice(&data.try_to_vec().unwrap());
             }
-            Self::RecordServiceCompletion { earnings, rating, response_time } => {
+            Self::RecordServiceCompletion {
+                earnings,
+                rating,
+                response_time,
+                max_total_fee,
+                idempotency_key,
+                expected_state_version,
+            } => {
                 buf.push(8);
                 let data = RecordServiceCompletionData {
                     earnings: *earnings,
                     rating: *rating,
                     response_time: *response_time,
+                    max_total_fee: *max_total_fee,
+                    idempotency_key: *idempotency_key,
+                    expected_state_version: *expected_state_version,
                 };
                 buf.extend_from_slice(&data.try_to_vec().unwrap());
             }
-            Self::RecordDisputeOutcome { won } => {
+            Self::RecordDisputeOutcome { won, expected_state_version } => {
                 buf.push(9);
-                buf.extend_from_slice(&won.try_to_vec().unwrap());
+                let data = RecordDisputeOutcomeData {
+                    won: *won,
+                    expected_state_version: *expected_state_version,
+                };
+                buf.extend_from_slice(&data.try_to_vec().unwrap());
+            }
+            Self::InitializeSkillAccount { index, skill } => {
+                buf.push(10);
+                let data = SkillAccountData {
+                    index: *index,
+                    skill: skill.clone(),
+                };
+                buf.extend_from_slice(&data.try_to_vec().unwrap());
+            }
+            Self::UpdateSkillAccount { index, skill } => {
+                buf.push(11);
+                let data = SkillAccountData {
+                    index: *index,
+                    skill: skill.clone(),
+                };
+                buf.extend_from_slice(&data.try_to_vec().unwrap());
+            }
+            Self::CloseSkillAccount { index } => {
+                buf.push(12);
+                buf.push(*index);
+            }
+            Self::BeginAgentRegistration {
+                agent_id,
+                name,
+                description,
+                agent_version,
+                provider_name,
+                provider_url,
+                documentation_url,
+                capabilities_flags,
+                supported_input_modes,
+                supported_output_modes,
+                security_info_uri,
+                aea_address,
+                economic_intent_summary,
+                supported_aea_protocols_hash,
+                extended_metadata_uri,
+            } => {
+                buf.push(13);
+                let data = BeginAgentRegistrationData {
+                    agent_id: agent_id.clone(),
+                    name: name.clone(),
+                    description: description.clone(),
+                    agent_version: agent_version.clone(),
+                    provider_name: provider_name.clone(),
+                    provider_url: provider_url.clone(),
+                    documentation_url: documentation_url.clone(),
+                    capabilities_flags: *capabilities_flags,
+                    supported_input_modes: supported_input_modes.clone(),
+                    supported_output_modes: supported_output_modes.clone(),
+                    security_info_uri: security_info_uri.clone(),
+                    aea_address: aea_address.clone(),
+                    economic_intent_summary: economic_intent_summary.clone(),
+                    supported_aea_protocols_hash: *supported_aea_protocols_hash,
+                    extended_metadata_uri: extended_metadata_uri.clone(),
+                };
+                buf.extend_from_slice(&data.try_to_vec().unwrap());
+            }
+            Self::AppendAgentSkills { skills } => {
+                buf.push(14);
+                buf.extend_from_slice(&skills.try_to_vec().unwrap());
+            }
+            Self::AppendAgentServiceEndpoints { service_endpoints } => {
+                buf.push(15);
+                buf.extend_from_slice(&service_endpoints.try_to_vec().unwrap());
+            }
+            Self::AppendAgentTags { tags } => {
+                buf.push(16);
+                buf.extend_from_slice(&tags.try_to_vec().unwrap());
+            }
+            Self::FinalizeAgentRegistration => {
+                buf.push(17);
+            }
+            Self::InitializeRegistryConfig { params } => {
+                buf.push(18);
+                buf.extend_from_slice(&params.try_to_vec().unwrap());
+            }
+            Self::UpdateRegistryConfig { params, new_admin } => {
+                buf.push(19);
+                let data = UpdateRegistryConfigData {
+                    params: *params,
+                    new_admin: *new_admin,
+                };
+                buf.extend_from_slice(&data.try_to_vec().unwrap());
+            }
+            Self::InitializeVaults => {
+                buf.push(20);
+            }
+            Self::SetRevenueSplit { recipients } => {
+                buf.push(21);
+                buf.extend_from_slice(&recipients.try_to_vec().unwrap());
+            }
+            Self::WithdrawTreasury { amount } => {
+                buf.push(22);
+                buf.extend_from_slice(&amount.to_le_bytes());
+            }
+            Self::MigrateAgentEntry => {
+                buf.push(23);
             }
         }
         buf
@@ -456,11 +940,33 @@ struct RegisterAgentData {
     tags: Vec<String>,
 }
 
+/// Helper struct for UpdateAgentDetails instruction data
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+struct UpdateAgentDetailsData {
+    details: AgentUpdateDetailsInput,
+    expected_state_version: Option<u64>,
+}
+
+/// Helper struct for UpdateAgentStatus instruction data
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+struct UpdateAgentStatusData {
+    new_status: u8,
+    expected_state_version: Option<u64>,
+}
+
 /// Helper struct for StakeTokens instruction data
 #[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
 struct StakeTokensData {
     amount: u64,
     lock_period: i64,
+    expected_state_version: Option<u64>,
+}
+
+/// Helper struct for UnstakeTokens instruction data
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+struct UnstakeTokensData {
+    amount: u64,
+    expected_state_version: Option<u64>,
 }
 
 /// Helper struct for UpdateServiceFees instruction data
@@ -469,6 +975,7 @@ struct UpdateServiceFeesData {
     base_fee: u64,
     priority_multiplier: u8,
     accepts_escrow: bool,
+    expected_state_version: Option<u64>,
 }
 
 /// Helper struct for RecordServiceCompletion instruction data
@@ -477,6 +984,50 @@ struct RecordServiceCompletionData {
     earnings: u64,
     rating: u8,
     response_time: u32,
+    max_total_fee: u64,
+    idempotency_key: [u8; IDEMPOTENCY_KEY_LEN],
+    expected_state_version: Option<u64>,
+}
+
+/// Helper struct for RecordDisputeOutcome instruction data
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+struct RecordDisputeOutcomeData {
+    won: bool,
+    expected_state_version: Option<u64>,
+}
+
+/// Helper struct for InitializeSkillAccount and UpdateSkillAccount instruction data
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+struct SkillAccountData {
+    index: u8,
+    skill: AgentSkillInput,
+}
+
+/// Helper struct for BeginAgentRegistration instruction data
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+struct BeginAgentRegistrationData {
+    agent_id: String,
+    name: String,
+    description: String,
+    agent_version: String,
+    provider_name: Option<String>,
+    provider_url: Option<String>,
+    documentation_url: Option<String>,
+    capabilities_flags: u64,
+    supported_input_modes: Vec<String>,
+    supported_output_modes: Vec<String>,
+    security_info_uri: Option<String>,
+    aea_address: Option<String>,
+    economic_intent_summary: Option<String>,
+    supported_aea_protocols_hash: Option<[u8; HASH_SIZE]>,
+    extended_metadata_uri: Option<String>,
+}
+
+/// Helper struct for UpdateRegistryConfig instruction data
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+struct UpdateRegistryConfigData {
+    params: RegistryConfigParams,
+    new_admin: Option<Pubkey>,
 }
 
 #[cfg(test)]
@@ -514,7 +1065,10 @@ mod tests {
 
     #[test]
     fn test_update_status_instruction() {
-        let instruction = AgentRegistryInstruction::UpdateAgentStatus { new_status: 1 };
+        let instruction = AgentRegistryInstruction::UpdateAgentStatus {
+            new_status: 1,
+            expected_state_version: None,
+        };
         let packed = instruction.pack();
         let unpacked = AgentRegistryInstruction::unpack(&packed).unwrap();
 
@@ -523,10 +1077,146 @@ mod tests {
 
     #[test]
     fn test_deregister_instruction() {
-        let instruction = AgentRegistryInstruction::DeregisterAgent;
+        let instruction = AgentRegistryInstruction::DeregisterAgent {
+            expected_state_version: None,
+        };
         let packed = instruction.pack();
         let unpacked = AgentRegistryInstruction::unpack(&packed).unwrap();
 
         assert_eq!(instruction, unpacked);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_expected_state_version_roundtrip() {
+        let instruction = AgentRegistryInstruction::UpdateServiceFees {
+            base_fee: 1_000_000,
+            priority_multiplier: 150,
+            accepts_escrow: true,
+            expected_state_version: Some(7),
+        };
+        let packed = instruction.pack();
+        let unpacked = AgentRegistryInstruction::unpack(&packed).unwrap();
+
+        assert_eq!(instruction, unpacked);
+
+        let instruction = AgentRegistryInstruction::DeregisterAgent {
+            expected_state_version: Some(3),
+        };
+        let packed = instruction.pack();
+        assert_eq!(packed.len(), 1 + 1 + 8);
+        assert_eq!(AgentRegistryInstruction::unpack(&packed).unwrap(), instruction);
+    }
+
+    #[test]
+    fn test_skill_account_instructions() {
+        let skill = AgentSkillInput {
+            id: "translate".to_string(),
+            name: "Translate".to_string(),
+            description_hash: None,
+            tags: vec!["language".to_string()],
+        };
+
+        for instruction in [
+            AgentRegistryInstruction::InitializeSkillAccount { index: 0, skill: skill.clone() },
+            AgentRegistryInstruction::UpdateSkillAccount { index: 4, skill },
+            AgentRegistryInstruction::CloseSkillAccount { index: 4 },
+        ] {
+            let packed = instruction.pack();
+            assert_eq!(AgentRegistryInstruction::unpack(&packed).unwrap(), instruction);
+        }
+    }
+
+    #[test]
+    fn test_chunked_registration_instructions() {
+        let begin = AgentRegistryInstruction::BeginAgentRegistration {
+            agent_id: "chunked-agent".to_string(),
+            name: "Chunked Agent".to_string(),
+            description: "Registered in several transactions".to_string(),
+            agent_version: "1.0.0".to_string(),
+            provider_name: None,
+            provider_url: None,
+            documentation_url: Some("https://example.com/docs".to_string()),
+            capabilities_flags: 3,
+            supported_input_modes: vec!["text/plain".to_string()],
+            supported_output_modes: vec![],
+            security_info_uri: None,
+            aea_address: None,
+            economic_intent_summary: None,
+            supported_aea_protocols_hash: Some([7u8; HASH_SIZE]),
+            extended_metadata_uri: None,
+        };
+
+        for instruction in [
+            begin,
+            AgentRegistryInstruction::AppendAgentSkills {
+                skills: vec![AgentSkillInput {
+                    id: "summarize".to_string(),
+                    name: "Summarize".to_string(),
+                    description_hash: None,
+                    tags: vec![],
+                }],
+            },
+            AgentRegistryInstruction::AppendAgentServiceEndpoints {
+                service_endpoints: vec![ServiceEndpointInput {
+                    protocol: "a2a_http_jsonrpc".to_string(),
+                    url: "https://example.com/a2a".to_string(),
+                    is_default: true,
+                }],
+            },
+            AgentRegistryInstruction::AppendAgentTags { tags: vec!["nlp".to_string()] },
+            AgentRegistryInstruction::FinalizeAgentRegistration,
+        ] {
+            let packed = instruction.pack();
+            assert_eq!(AgentRegistryInstruction::unpack(&packed).unwrap(), instruction);
+        }
+
+        assert_eq!(AgentRegistryInstruction::FinalizeAgentRegistration.pack(), vec![17]);
+    }
+
+    #[test]
+    fn test_registry_config_instructions() {
+        let params = RegistryConfigParams::agent_defaults(Pubkey::new_unique(), Pubkey::new_unique());
+
+        for instruction in [
+            AgentRegistryInstruction::InitializeRegistryConfig { params },
+            AgentRegistryInstruction::UpdateRegistryConfig {
+                params,
+                new_admin: Some(Pubkey::new_unique()),
+            },
+        ] {
+            let packed = instruction.pack();
+            assert_eq!(AgentRegistryInstruction::unpack(&packed).unwrap(), instruction);
+        }
+
+        assert_eq!(AgentRegistryInstruction::InitializeVaults.pack(), vec![20]);
+        assert_eq!(
+            AgentRegistryInstruction::unpack(&[20]).unwrap(),
+            AgentRegistryInstruction::InitializeVaults
+        );
+    }
+
+    #[test]
+    fn test_revenue_instructions() {
+        for instruction in [
+            AgentRegistryInstruction::SetRevenueSplit {
+                recipients: vec![SplitRecipient {
+                    recipient: Pubkey::new_unique(),
+                    bps: 2_500,
+                }],
+            },
+            AgentRegistryInstruction::WithdrawTreasury { amount: 42 },
+            AgentRegistryInstruction::MigrateAgentEntry,
+            AgentRegistryInstruction::RecordServiceCompletion {
+                earnings: 500,
+                rating: 5,
+                response_time: 120,
+                max_total_fee: 500,
+                idempotency_key: [3; IDEMPOTENCY_KEY_LEN],
+                expected_state_version: Some(2),
+            },
+        ] {
+            let packed = instruction.pack();
+            assert_eq!(AgentRegistryInstruction::unpack(&packed).unwrap(), instruction);
+        }
+    }
+}
@@ -1,14 +1,11 @@
 //! Instruction processing for the Agent Registry program
 
-use borsh::{BorshDeserialize, BorshSerialize};
+use borsh::BorshSerialize;
 use solana_program::{
     account_info::{next_account_info, AccountInfo},
     entrypoint::ProgramResult,
-    program::invoke,
     program_error::ProgramError,
     pubkey::Pubkey,
-    rent::Rent,
-    system_instruction,
     sysvar::{clock::Clock, Sysvar},
 };
 use anchor_lang::prelude::*;
@@ -19,24 +16,29 @@ use aeamcp_common::{
     serialization::{ServiceEndpoint, AgentSkill, ServiceEndpointInput, AgentSkillInput},
     utils::{
         get_agent_pda_secure, verify_account_owner, verify_signer_authority,
-        get_current_timestamp,
+        get_current_timestamp, resize_account, get_skill_account_pda, create_pda_account,
+        close_account, get_registry_config_pda, verify_upgrade_authority,
     },
     token_utils::{
         transfer_tokens_with_pda, transfer_tokens_with_pda_signer, StakingTier,
-        calculate_agent_quality_score, validate_fee_config, is_stake_unlocked,
+        validate_fee_config, is_stake_unlocked,
         derive_staking_vault_pda, derive_registration_vault_pda,
         transfer_tokens_with_account_info, transfer_tokens_with_pda_signer_account_info,
+        verify_token_program, verify_token_mint, verify_user_token_account,
+        verify_vault_token_account, create_vault_token_account, derive_treasury_vault_pda,
     },
     authority::{
         verify_escrow_program_authority, verify_ddr_program_authority, get_authority_registry,
     },
+    config::{RegistryConfigParams, RegistryConfigV1},
+    receipt::{check_max_total_fee, record_usage_receipt},
+    revenue::{bps_share, derive_revenue_split_pda, pay_out_with_split, RevenueSplitV1, SplitRecipient},
     AgentStatus,
-    AGENT_REGISTRATION_FEE, MIN_SERVICE_FEE,
 };
 
 use crate::{
     instruction::{AgentRegistryInstruction, AgentUpdateDetailsInput},
-    state::AgentRegistryEntryV1,
+    state::{AgentEntryPrefixV1, AgentRegistryEntryV1, AgentSkillAccountV1},
     validation::*,
     events::*,
 };
@@ -94,14 +96,14 @@ impl Processor {
                 extended_metadata_uri,
                 tags,
             ),
-            AgentRegistryInstruction::UpdateAgentDetails { details } => {
-                Self::process_update_agent_details(program_id, accounts, details)
+            AgentRegistryInstruction::UpdateAgentDetails { details, expected_state_version } => {
+                Self::process_update_agent_details(program_id, accounts, details, expected_state_version)
             }
-            AgentRegistryInstruction::UpdateAgentStatus { new_status } => {
-                Self::process_update_agent_status(program_id, accounts, new_status)
+            AgentRegistryInstruction::UpdateAgentStatus { new_status, expected_state_version } => {
+                Self::process_update_agent_status(program_id, accounts, new_status, expected_state_version)
             }
-            AgentRegistryInstruction::DeregisterAgent => {
-                Self::process_deregister_agent(program_id, accounts)
+            AgentRegistryInstruction::DeregisterAgent { expected_state_version } => {
+                Self::process_deregister_agent(program_id, accounts, expected_state_version)
             }
             AgentRegistryInstruction::RegisterAgentWithToken {
                 agent_id,
@@ -144,16 +146,17 @@ impl Processor {
                 extended_metadata_uri,
                 tags,
             ),
-            AgentRegistryInstruction::StakeTokens { amount, lock_period } => {
-                Self::process_stake_tokens(program_id, accounts, amount, lock_period)
+            AgentRegistryInstruction::StakeTokens { amount, lock_period, expected_state_version } => {
+                Self::process_stake_tokens(program_id, accounts, amount, lock_period, expected_state_version)
             }
-            AgentRegistryInstruction::UnstakeTokens { amount } => {
-                Self::process_unstake_tokens(program_id, accounts, amount)
+            AgentRegistryInstruction::UnstakeTokens { amount, expected_state_version } => {
+                Self::process_unstake_tokens(program_id, accounts, amount, expected_state_version)
             }
             AgentRegistryInstruction::UpdateServiceFees {
                 base_fee,
                 priority_multiplier,
-                accepts_escrow
+                accepts_escrow,
+                expected_state_version,
             } => {
                 Self::process_update_service_fees(
                     program_id,
@@ -161,12 +164,16 @@ impl Processor {
                     base_fee,
                     priority_multiplier,
                     accepts_escrow,
+                    expected_state_version,
                 )
             }
             AgentRegistryInstruction::RecordServiceCompletion {
                 earnings,
                 rating,
-                response_time
+                response_time,
+                max_total_fee,
+                idempotency_key,
+                expected_state_version,
             } => {
                 Self::process_record_service_completion(
                     program_id,
@@ -174,10 +181,98 @@ impl Processor {
                     earnings,
                     rating,
                     response_time,
+                    max_total_fee,
+                    idempotency_key,
+                    expected_state_version,
                 )
             }
-            AgentRegistryInstruction::RecordDisputeOutcome { won } => {
-                Self::process_record_dispute_outcome(program_id, accounts, won)
+            AgentRegistryInstruction::RecordDisputeOutcome { won, expected_state_version } => {
+                Self::process_record_dispute_outcome(program_id, accounts, won, expected_state_version)
+            }
+            AgentRegistryInstruction::InitializeSkillAccount { index, skill } => {
+                Self::process_initialize_skill_account(program_id, accounts, index, skill)
+            }
+            AgentRegistryInstruction::UpdateSkillAccount { index, skill } => {
+                Self::process_update_skill_account(program_id, accounts, index, skill)
+            }
+            AgentRegistryInstruction::CloseSkillAccount { index } => {
+                Self::process_close_skill_account(program_id, accounts, index)
+            }
+            AgentRegistryInstruction::BeginAgentRegistration {
+                agent_id,
+                name,
+                description,
+                agent_version,
+                provider_name,
+                provider_url,
+                documentation_url,
+                capabilities_flags,
+                supported_input_modes,
+                supported_output_modes,
+                security_info_uri,
+                aea_address,
+                economic_intent_summary,
+                supported_aea_protocols_hash,
+                extended_metadata_uri,
+            } => Self::process_begin_agent_registration(
+                program_id,
+                accounts,
+                agent_id,
+                name,
+                description,
+                agent_version,
+                provider_name,
+                provider_url,
+                documentation_url,
+                capabilities_flags,
+                supported_input_modes,
+                supported_output_modes,
+                security_info_uri,
+                aea_address,
+                economic_intent_summary,
+                supported_aea_protocols_hash,
+                extended_metadata_uri,
+            ),
+            AgentRegistryInstruction::AppendAgentSkills { skills } => {
+                validate_skills(&skills)?;
+                let skills = skills.into_iter().map(AgentSkill::from).collect();
+                Self::process_append_registration_chunk(program_id, accounts, "skills", |entry| {
+                    entry.append_skills(skills)
+                })
+            }
+            AgentRegistryInstruction::AppendAgentServiceEndpoints { service_endpoints } => {
+                validate_service_endpoint_fields(&service_endpoints)?;
+                let endpoints = service_endpoints.into_iter().map(ServiceEndpoint::from).collect();
+                Self::process_append_registration_chunk(program_id, accounts, "service_endpoints", |entry| {
+                    entry.append_service_endpoints(endpoints)
+                })
+            }
+            AgentRegistryInstruction::AppendAgentTags { tags } => {
+                validate_agent_tags(&tags)?;
+                Self::process_append_registration_chunk(program_id, accounts, "tags", |entry| {
+                    entry.append_tags(tags)
+                })
+            }
+            AgentRegistryInstruction::FinalizeAgentRegistration => {
+                Self::process_finalize_agent_registration(program_id, accounts)
+            }
+            AgentRegistryInstruction::InitializeRegistryConfig { params } => {
+                Self::process_initialize_registry_config(program_id, accounts, params)
+            }
+            AgentRegistryInstruction::UpdateRegistryConfig { params, new_admin } => {
+                Self::process_update_registry_config(program_id, accounts, params, new_admin)
+            }
+            AgentRegistryInstruction::InitializeVaults => {
+                Self::process_initialize_vaults(program_id, accounts)
+            }
+            AgentRegistryInstruction::SetRevenueSplit { recipients } => {
+                Self::process_set_revenue_split(program_id, accounts, recipients)
+            }
+            AgentRegistryInstruction::WithdrawTreasury { amount } => {
+                Self::process_withdraw_treasury(program_id, accounts, amount)
+            }
+            AgentRegistryInstruction::MigrateAgentEntry => {
+                Self::process_migrate_agent_entry(program_id, accounts)
             }
         }
     }
@@ -252,26 +347,6 @@ impl Processor {
             return Err(RegistryError::AccountAlreadyExists.into());
         }
 
-        // Calculate rent exemption
-        let rent = Rent::get()?;
-        let required_lamports = rent.minimum_balance(AgentRegistryEntryV1::SPACE);
-
-        // Create the account
-        invoke(
-            &system_instruction::create_account(
-                payer_info.key,
-                agent_entry_info.key,
-                required_lamports,
-                AgentRegistryEntryV1::SPACE as u64,
-                program_id,
-            ),
-            &[
-                payer_info.clone(),
-                agent_entry_info.clone(),
-                system_program_info.clone(),
-            ],
-        )?;
-
         // Convert input types to storage types
         let converted_endpoints: Vec<ServiceEndpoint> = service_endpoints
             .into_iter()
@@ -320,6 +395,21 @@ impl Processor {
             timestamp,
         );
 
+        // Allocate only what this entry needs rather than the worst-case SPACE
+        create_pda_account(
+            payer_info,
+            agent_entry_info,
+            system_program_info,
+            program_id,
+            agent_entry.required_space()?,
+            &[
+                AGENT_REGISTRY_PDA_SEED,
+                agent_id.as_bytes(),
+                owner_authority_info.key.as_ref(),
+                &[bump],
+            ],
+        )?;
+
         // Serialize and store the data
         let mut data = agent_entry_info.try_borrow_mut_data()?;
         agent_entry.serialize(&mut &mut data[..])?;
@@ -360,20 +450,26 @@ impl Processor {
         program_id: &Pubkey,
         accounts: &[AccountInfo],
         details: AgentUpdateDetailsInput,
+        expected_state_version: Option<u64>,
     ) -> ProgramResult {
         let account_info_iter = &mut accounts.iter();
         let agent_entry_info = next_account_info(account_info_iter)?;
         let owner_authority_info = next_account_info(account_info_iter)?;
+        let payer_info = next_account_info(account_info_iter)?;
+        let system_program_info = next_account_info(account_info_iter)?;
 
         // SECURITY FIX: Verify account ownership BEFORE data access
         verify_account_owner(agent_entry_info, program_id)?;
         
         let mut data = agent_entry_info.try_borrow_mut_data()?;
-        let mut agent_entry = AgentRegistryEntryV1::try_from_slice(&data)?;
+        let mut agent_entry = AgentRegistryEntryV1::try_from_account_data(&data)?;
 
         // Verify owner authority
         verify_signer_authority(owner_authority_info, &agent_entry.owner_authority)?;
 
+        // Reject stale writers when the caller pinned a state version
+        agent_entry.check_expected_version(expected_state_version)?;
+
         // SECURITY FIX: Begin operation to prevent reentrancy
         agent_entry.begin_operation()?;
         let current_version = agent_entry.state_version;
@@ -532,7 +628,15 @@ impl Processor {
             return Err(e.into());
         }
 
+        agent_entry.end_operation();
+
+        // Resize the account to fit the updated entry, topping up or refunding rent
+        let space = agent_entry.required_space()?;
+        drop(data);
+        resize_account(agent_entry_info, payer_info, system_program_info, space)?;
+
         // SECURITY FIX: Serialize safely after atomic update
+        let mut data = agent_entry_info.try_borrow_mut_data()?;
         agent_entry.serialize(&mut &mut data[..])?;
 
         // Emit event
@@ -551,6 +655,7 @@ impl Processor {
         program_id: &Pubkey,
         accounts: &[AccountInfo],
         new_status: u8,
+        expected_state_version: Option<u64>,
     ) -> ProgramResult {
         let account_info_iter = &mut accounts.iter();
         let agent_entry_info = next_account_info(account_info_iter)?;
@@ -566,11 +671,19 @@ impl Processor {
         verify_account_owner(agent_entry_info, program_id)?;
         
         let mut data = agent_entry_info.try_borrow_mut_data()?;
-        let mut agent_entry = AgentRegistryEntryV1::try_from_slice(&data)?;
+        let mut agent_entry = AgentRegistryEntryV1::try_from_account_data(&data)?;
 
         // Verify owner authority
         verify_signer_authority(owner_authority_info, &agent_entry.owner_authority)?;
 
+        // Reject stale writers when the caller pinned a state version
+        agent_entry.check_expected_version(expected_state_version)?;
+
+        // A chunked registration only leaves Pending through finalize
+        if agent_entry.registration_open {
+            return Err(RegistryError::RegistrationNotFinalized.into());
+        }
+
         // Check if status is already the same
         if agent_entry.status == new_status {
             return Ok(());
@@ -603,7 +716,11 @@ impl Processor {
     }
 
     /// Process deregister agent instruction
-    fn process_deregister_agent(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    fn process_deregister_agent(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        expected_state_version: Option<u64>,
+    ) -> ProgramResult {
         let account_info_iter = &mut accounts.iter();
         let agent_entry_info = next_account_info(account_info_iter)?;
         let owner_authority_info = next_account_info(account_info_iter)?;
@@ -615,11 +732,14 @@ impl Processor {
         verify_account_owner(agent_entry_info, program_id)?;
         
         let mut data = agent_entry_info.try_borrow_mut_data()?;
-        let mut agent_entry = AgentRegistryEntryV1::try_from_slice(&data)?;
+        let mut agent_entry = AgentRegistryEntryV1::try_from_account_data(&data)?;
 
         // Verify owner authority
         verify_signer_authority(owner_authority_info, &agent_entry.owner_authority)?;
 
+        // Reject stale writers when the caller pinned a state version
+        agent_entry.check_expected_version(expected_state_version)?;
+
         // Check if already deregistered
         if agent_entry.status == AgentStatus::Deregistered as u8 {
             return Ok(());
@@ -686,6 +806,15 @@ impl Processor {
         let token_program_info = next_account_info(account_info_iter)?;
         let system_program_info = next_account_info(account_info_iter)?;
         let clock_info = next_account_info(account_info_iter)?;
+        let config_info = next_account_info(account_info_iter)?;
+
+        // Validate every token account before anything is created
+        let config = RegistryConfigV1::load(config_info, program_id)?.params;
+        verify_token_program(token_program_info)?;
+        verify_token_mint(token_mint_info, &config)?;
+        verify_user_token_account(owner_token_account_info, owner_authority_info.key, &config)?;
+        let (expected_vault, _) = derive_registration_vault_pda(program_id);
+        verify_vault_token_account(registration_vault_info, &expected_vault, &config)?;
 
         // First register the agent using existing logic
         Self::process_register_agent(
@@ -716,39 +845,28 @@ impl Processor {
             tags,
         )?;
 
-        // Now handle the token payment
-        let owner_token_account = TokenAccount::try_deserialize(&mut &owner_token_account_info.data.borrow()[..])?;
-        
-        // Verify registration vault PDA
-        let (expected_vault, _) = derive_registration_vault_pda(program_id);
-        if registration_vault_info.key != &expected_vault {
-            return Err(RegistryError::InvalidPda.into());
-        }
-
         // Transfer registration fee
         transfer_tokens_with_account_info(
             owner_token_account_info,
             registration_vault_info,
             owner_authority_info,
             token_program_info,
-            AGENT_REGISTRATION_FEE,
+            config.registration_fee,
         )?;
 
         // Update agent entry with token info
         let mut data = agent_entry_info.try_borrow_mut_data()?;
-        let mut agent_entry = AgentRegistryEntryV1::try_from_slice(&data)?;
-        
-        agent_entry.token_mint = *token_mint_info.key;
-        agent_entry.registration_fee_paid = AGENT_REGISTRATION_FEE;
-        agent_entry.total_fees_collected += AGENT_REGISTRATION_FEE;
+        let economics = &mut AgentEntryPrefixV1::load_mut(&mut data)?.economics;
         
-        agent_entry.serialize(&mut &mut data[..])?;
+        economics.token_mint = config.accepted_mint;
+        economics.registration_fee_paid = config.registration_fee;
+        economics.total_fees_collected += config.registration_fee;
 
         // Emit event
         let event = create_agent_registered_with_token_event(
             agent_id,
             *owner_authority_info.key,
-            AGENT_REGISTRATION_FEE,
+            config.registration_fee,
         );
         emit_agent_registered_with_token(&event);
 
@@ -761,6 +879,7 @@ impl Processor {
         accounts: &[AccountInfo],
         amount: u64,
         lock_period: i64,
+        expected_state_version: Option<u64>,
     ) -> ProgramResult {
         let account_info_iter = &mut accounts.iter();
         let owner_info = next_account_info(account_info_iter)?;
@@ -769,28 +888,41 @@ impl Processor {
         let staking_vault_info = next_account_info(account_info_iter)?;
         let token_program_info = next_account_info(account_info_iter)?;
         let clock_info = next_account_info(account_info_iter)?;
+        let config_info = next_account_info(account_info_iter)?;
 
         // Verify account ownership
         verify_account_owner(agent_entry_info, program_id)?;
+        let config = RegistryConfigV1::load(config_info, program_id)?.params;
         
+        // Token fields live in the fixed-size prefix; only the ID is decoded
         let mut data = agent_entry_info.try_borrow_mut_data()?;
-        let mut agent_entry = AgentRegistryEntryV1::try_from_slice(&data)?;
+        let agent_id = AgentRegistryEntryV1::read_agent_id(&data)?;
+        let agent_entry = AgentEntryPrefixV1::load_mut(&mut data)?;
 
         // Verify owner authority
-        verify_signer_authority(owner_info, &agent_entry.owner_authority)?;
+        verify_signer_authority(owner_info, &agent_entry.header.owner_authority)?;
+
+        // Reject stale writers when the caller pinned a state version
+        agent_entry.header.check_expected_version(expected_state_version)?;
 
-        // Verify staking vault PDA
+        // Verify the token accounts and the staking vault
+        verify_token_program(token_program_info)?;
+        verify_user_token_account(owner_token_account_info, owner_info.key, &config)?;
         let (expected_vault, _) = derive_staking_vault_pda(program_id);
-        if staking_vault_info.key != &expected_vault {
-            return Err(RegistryError::InvalidPda.into());
-        }
+        verify_vault_token_account(staking_vault_info, &expected_vault, &config)?;
 
         // Get clock
         let clock = Clock::from_account_info(clock_info)?;
 
         // Calculate new staking tier
-        let new_total_stake = agent_entry.staked_amount + amount;
-        let new_tier = StakingTier::from_amount(new_total_stake);
+        let new_total_stake = agent_entry.economics.staked_amount + amount;
+        let new_tier = StakingTier::from_config(new_total_stake, &config);
+
+        // The lock must fall within the configured bounds and cover the tier's minimum
+        config.check_lock_period(lock_period)?;
+        if lock_period < new_tier.lock_period_in(&config) {
+            return Err(RegistryError::InvalidLockPeriod.into());
+        }
 
         // Transfer tokens to staking vault
         transfer_tokens_with_account_info(
@@ -802,22 +934,21 @@ impl Processor {
         )?;
 
         // Update agent staking info
-        agent_entry.update_staking(
+        agent_entry.economics.update_staking(
             new_total_stake,
             new_tier.value(),
             clock.unix_timestamp + lock_period,
             clock.unix_timestamp,
         );
-
-        agent_entry.serialize(&mut &mut data[..])?;
+        agent_entry.header.bump_state_version();
 
         // Emit event
         let event = create_tokens_staked_event(
-            agent_entry.agent_id.clone(),
+            agent_id,
             *owner_info.key,
             amount,
             new_tier.value(),
-            agent_entry.stake_locked_until,
+            agent_entry.economics.stake_locked_until,
         );
         emit_tokens_staked(&event);
 
@@ -829,6 +960,7 @@ impl Processor {
         program_id: &Pubkey,
         accounts: &[AccountInfo],
         amount: u64,
+        expected_state_version: Option<u64>,
     ) -> ProgramResult {
         let account_info_iter = &mut accounts.iter();
         let owner_info = next_account_info(account_info_iter)?;
@@ -837,34 +969,40 @@ impl Processor {
         let owner_token_account_info = next_account_info(account_info_iter)?;
         let token_program_info = next_account_info(account_info_iter)?;
         let clock_info = next_account_info(account_info_iter)?;
+        let config_info = next_account_info(account_info_iter)?;
 
         // Verify account ownership
         verify_account_owner(agent_entry_info, program_id)?;
+        let config = RegistryConfigV1::load(config_info, program_id)?.params;
         
         let mut data = agent_entry_info.try_borrow_mut_data()?;
-        let mut agent_entry = AgentRegistryEntryV1::try_from_slice(&data)?;
+        let agent_id = AgentRegistryEntryV1::read_agent_id(&data)?;
+        let agent_entry = AgentEntryPrefixV1::load_mut(&mut data)?;
 
         // Verify owner authority
-        verify_signer_authority(owner_info, &agent_entry.owner_authority)?;
+        verify_signer_authority(owner_info, &agent_entry.header.owner_authority)?;
+
+        // Reject stale writers when the caller pinned a state version
+        agent_entry.header.check_expected_version(expected_state_version)?;
 
         // Get clock
         let clock = Clock::from_account_info(clock_info)?;
 
         // Check if stake can be unlocked using the is_stake_unlocked utility
-        if !is_stake_unlocked(agent_entry.stake_locked_until, clock.unix_timestamp) {
+        if !is_stake_unlocked(agent_entry.economics.stake_locked_until, clock.unix_timestamp) {
             return Err(RegistryError::StakeLocked.into());
         }
 
         // Check if sufficient stake available
-        if amount > agent_entry.staked_amount {
+        if amount > agent_entry.economics.staked_amount {
             return Err(RegistryError::InsufficientStake.into());
         }
 
-        // Verify staking vault PDA
+        // Verify the token accounts and the staking vault
+        verify_token_program(token_program_info)?;
+        verify_user_token_account(owner_token_account_info, owner_info.key, &config)?;
         let (expected_vault, vault_bump) = derive_staking_vault_pda(program_id);
-        if staking_vault_info.key != &expected_vault {
-            return Err(RegistryError::InvalidPda.into());
-        }
+        verify_vault_token_account(staking_vault_info, &expected_vault, &config)?;
 
         // Transfer tokens from staking vault
         let vault_seeds = &[b"staking_vault".as_ref(), &[vault_bump]];
@@ -879,31 +1017,24 @@ impl Processor {
         )?;
 
         // Update agent staking info
-        let new_staked_amount = agent_entry.staked_amount - amount;
-        let new_tier = StakingTier::from_amount(new_staked_amount);
+        let economics = &mut agent_entry.economics;
+        let new_staked_amount = economics.staked_amount - amount;
+        let new_tier = StakingTier::from_config(new_staked_amount, &config);
         
-        agent_entry.update_staking(
+        economics.update_staking(
             new_staked_amount,
             new_tier.value(),
-            if new_staked_amount > 0 { agent_entry.stake_locked_until } else { 0 },
+            if new_staked_amount > 0 { economics.stake_locked_until } else { 0 },
             clock.unix_timestamp,
         );
 
         // Update quality score after tier change
-        let quality_score = calculate_agent_quality_score(
-            agent_entry.completed_services,
-            &agent_entry.quality_ratings,
-            agent_entry.dispute_wins,
-            agent_entry.dispute_count,
-            agent_entry.response_time_avg,
-        );
-        agent_entry.reputation_score = quality_score;
-
-        agent_entry.serialize(&mut &mut data[..])?;
+        economics.refresh_reputation_score();
+        agent_entry.header.bump_state_version();
 
         // Emit event
         let event = create_tokens_unstaked_event(
-            agent_entry.agent_id.clone(),
+            agent_id,
             *owner_info.key,
             amount,
             new_tier.value(),
@@ -920,103 +1051,232 @@ impl Processor {
         base_fee: u64,
         priority_multiplier: u8,
         accepts_escrow: bool,
+        expected_state_version: Option<u64>,
     ) -> ProgramResult {
         let account_info_iter = &mut accounts.iter();
         let owner_info = next_account_info(account_info_iter)?;
         let agent_entry_info = next_account_info(account_info_iter)?;
         let clock_info = next_account_info(account_info_iter)?;
+        let config_info = next_account_info(account_info_iter)?;
 
-        // Validate fee configuration
-        validate_fee_config(base_fee, MIN_SERVICE_FEE, priority_multiplier as u16)?;
+        // Validate fee configuration against the registry config
+        let config = RegistryConfigV1::load(config_info, program_id)?.params;
+        validate_fee_config(base_fee, priority_multiplier as u16, &config)?;
 
         // Verify account ownership
         verify_account_owner(agent_entry_info, program_id)?;
         
         let mut data = agent_entry_info.try_borrow_mut_data()?;
-        let mut agent_entry = AgentRegistryEntryV1::try_from_slice(&data)?;
+        let agent_id = AgentRegistryEntryV1::read_agent_id(&data)?;
+        let agent_entry = AgentEntryPrefixV1::load_mut(&mut data)?;
 
         // Verify owner authority
-        verify_signer_authority(owner_info, &agent_entry.owner_authority)?;
+        verify_signer_authority(owner_info, &agent_entry.header.owner_authority)?;
+
+        // Reject stale writers when the caller pinned a state version
+        agent_entry.header.check_expected_version(expected_state_version)?;
 
         // Get clock
         let clock = Clock::from_account_info(clock_info)?;
 
-        // Update service fees
-        agent_entry.update_service_fees(
+        // Decreases apply now, increases after the config's notice period
+        let effective_at = agent_entry.economics.schedule_service_fees(
             base_fee,
             priority_multiplier,
             accepts_escrow,
             clock.unix_timestamp,
+            config.fee_increase_notice,
         );
-
-        agent_entry.serialize(&mut &mut data[..])?;
+        agent_entry.header.bump_state_version();
 
         // Emit event
         let event = create_service_fees_updated_event(
-            agent_entry.agent_id.clone(),
-            base_fee,
-            priority_multiplier,
+            agent_id.clone(),
+            agent_entry.economics.base_service_fee,
+            agent_entry.economics.priority_multiplier,
             accepts_escrow,
         );
         emit_service_fees_updated(&event);
+        if let Some(effective_at) = effective_at {
+            emit_fee_change_scheduled(&create_fee_change_scheduled_event(
+                agent_id,
+                base_fee,
+                priority_multiplier,
+                effective_at,
+            ));
+        }
 
         Ok(())
     }
 
     /// Process record service completion (called by escrow)
+    #[allow(clippy::too_many_arguments)]
     fn process_record_service_completion(
         program_id: &Pubkey,
         accounts: &[AccountInfo],
         earnings: u64,
         rating: u8,
         response_time: u32,
+        max_total_fee: u64,
+        idempotency_key: [u8; IDEMPOTENCY_KEY_LEN],
+        expected_state_version: Option<u64>,
     ) -> ProgramResult {
         let account_info_iter = &mut accounts.iter();
         let escrow_program_info = next_account_info(account_info_iter)?;
         let agent_entry_info = next_account_info(account_info_iter)?;
-        let _clock_info = next_account_info(account_info_iter)?;
+        let clock_info = next_account_info(account_info_iter)?;
+        let payer_info = next_account_info(account_info_iter)?;
+        let receipt_info = next_account_info(account_info_iter)?;
+        let system_program_info = next_account_info(account_info_iter)?;
+        let escrow_authority_info = next_account_info(account_info_iter)?;
 
         // SECURITY FIX: Implement proper escrow program authority verification
         let authority_registry = get_authority_registry();
-        verify_escrow_program_authority(escrow_program_info, &authority_registry)?;
+        verify_escrow_program_authority(escrow_program_info, escrow_authority_info, &authority_registry)?;
 
         // Verify account ownership
         verify_account_owner(agent_entry_info, program_id)?;
-        
+
+        // Reject overcharges and replays before anything is paid or recorded.
+        // The paying user's cap must cover the agent's fee in force, so a fee
+        // increase made after the user agreed cannot be charged.
+        let clock = Clock::from_account_info(clock_info)?;
+        let service_fee = {
+            let data = agent_entry_info.try_borrow_data()?;
+            let mut economics = AgentEntryPrefixV1::load(&data)?.economics;
+            economics.apply_due_fee_change(clock.unix_timestamp);
+            economics.priority_service_fee()
+        };
+        check_max_total_fee(service_fee.max(earnings), max_total_fee)?;
+        if !payer_info.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+        record_usage_receipt(
+            receipt_info,
+            payer_info,
+            system_program_info,
+            agent_entry_info.key,
+            escrow_program_info.key,
+            &idempotency_key,
+            earnings,
+            program_id,
+        )?;
+
+        // Settle the earnings when the escrow passes its token accounts
+        if account_info_iter.len() > 0 {
+            let owner = {
+                let data = agent_entry_info.try_borrow_data()?;
+                let agent_entry = AgentEntryPrefixV1::load(&data)?;
+                agent_entry.header.check_expected_version(expected_state_version)?;
+                agent_entry.header.owner_authority
+            };
+            Self::settle_service_earnings(
+                program_id,
+                escrow_authority_info,
+                agent_entry_info,
+                &owner,
+                account_info_iter,
+                earnings,
+            )?;
+        }
+
         let mut data = agent_entry_info.try_borrow_mut_data()?;
-        let mut agent_entry = AgentRegistryEntryV1::try_from_slice(&data)?;
-
-        // Record service completion
-        agent_entry.record_service_completion(earnings, rating, response_time);
-
-        // Update reputation score
-        agent_entry.reputation_score = calculate_agent_quality_score(
-            agent_entry.completed_services,
-            &agent_entry.quality_ratings,
-            agent_entry.dispute_wins,
-            agent_entry.dispute_count,
-            agent_entry.response_time_avg,
-        );
+        let agent_id = AgentRegistryEntryV1::read_agent_id(&data)?;
+        let agent_entry = AgentEntryPrefixV1::load_mut(&mut data)?;
 
-        agent_entry.serialize(&mut &mut data[..])?;
+        // Reject stale writers when the caller pinned a state version
+        agent_entry.header.check_expected_version(expected_state_version)?;
+
+        // Fee increases that came due take effect
+        agent_entry.economics.apply_due_fee_change(clock.unix_timestamp);
+
+        // Record service completion and update reputation score
+        agent_entry.economics.record_service_completion(earnings, rating, response_time);
+        let reputation_score = agent_entry.economics.refresh_reputation_score();
+        agent_entry.header.bump_state_version();
 
         // Emit event
         let event = create_service_completed_event(
-            agent_entry.agent_id.clone(),
+            agent_id,
             earnings,
             rating,
-            agent_entry.reputation_score,
+            reputation_score,
         );
         emit_service_completed(&event);
 
         Ok(())
     }
 
+    /// Pay service earnings out of the escrow's token account: the protocol
+    /// take-rate to the treasury vault, then the agent's revenue split, then
+    /// the rest to the owner. The escrow's authority PDA signed the call, and
+    /// that signature carries over to the transfers.
+    fn settle_service_earnings<'a, 'b>(
+        program_id: &Pubkey,
+        escrow_authority_info: &AccountInfo<'b>,
+        agent_entry_info: &AccountInfo<'b>,
+        owner: &Pubkey,
+        account_info_iter: &mut std::slice::Iter<'a, AccountInfo<'b>>,
+        earnings: u64,
+    ) -> ProgramResult {
+        let escrow_token_info = next_account_info(account_info_iter)?;
+        let owner_token_info = next_account_info(account_info_iter)?;
+        let treasury_vault_info = next_account_info(account_info_iter)?;
+        let config_info = next_account_info(account_info_iter)?;
+        let token_program_info = next_account_info(account_info_iter)?;
+        let split_info = next_account_info(account_info_iter)?;
+
+        let config = RegistryConfigV1::load(config_info, program_id)?.params;
+        verify_token_program(token_program_info)?;
+        verify_user_token_account(escrow_token_info, escrow_authority_info.key, &config)?;
+        verify_user_token_account(owner_token_info, owner, &config)?;
+        let (expected_treasury, _) = derive_treasury_vault_pda(program_id);
+        verify_vault_token_account(treasury_vault_info, &expected_treasury, &config)?;
+        let mut split = RevenueSplitV1::load(split_info, agent_entry_info.key, program_id)?;
+
+        let protocol_fee = bps_share(earnings, config.protocol_fee_bps);
+        if protocol_fee > 0 {
+            transfer_tokens_with_pda_signer_account_info(
+                escrow_token_info,
+                treasury_vault_info,
+                escrow_authority_info,
+                token_program_info,
+                protocol_fee,
+                &[],
+            )?;
+        }
+
+        let (shares, owner_amount) = pay_out_with_split(
+            escrow_token_info,
+            escrow_authority_info,
+            token_program_info,
+            owner_token_info,
+            // The escrow's account cannot hold shares back, so every
+            // recipient must be payable
+            split.as_mut(),
+            false,
+            account_info_iter,
+            &config,
+            earnings - protocol_fee,
+            &[],
+        )?;
+
+        emit_revenue_distributed(&create_revenue_distributed_event(
+            *agent_entry_info.key,
+            protocol_fee,
+            shares.iter().sum(),
+            owner_amount,
+        ));
+
+        Ok(())
+    }
+
     /// Process record dispute outcome (called by DDR)
     fn process_record_dispute_outcome(
         program_id: &Pubkey,
         accounts: &[AccountInfo],
         won: bool,
+        expected_state_version: Option<u64>,
     ) -> ProgramResult {
         let account_info_iter = &mut accounts.iter();
         let ddr_program_info = next_account_info(account_info_iter)?;
@@ -1030,100 +1290,794 @@ impl Processor {
         verify_account_owner(agent_entry_info, program_id)?;
         
         let mut data = agent_entry_info.try_borrow_mut_data()?;
-        let mut agent_entry = AgentRegistryEntryV1::try_from_slice(&data)?;
-
-        // Record dispute outcome
-        agent_entry.record_dispute_outcome(won);
-
-        // Update reputation score
-        agent_entry.reputation_score = calculate_agent_quality_score(
-            agent_entry.completed_services,
-            &agent_entry.quality_ratings,
-            agent_entry.dispute_wins,
-            agent_entry.dispute_count,
-            agent_entry.response_time_avg,
-        );
+        let agent_id = AgentRegistryEntryV1::read_agent_id(&data)?;
+        let agent_entry = AgentEntryPrefixV1::load_mut(&mut data)?;
 
-        agent_entry.serialize(&mut &mut data[..])?;
+        // Reject stale writers when the caller pinned a state version
+        agent_entry.header.check_expected_version(expected_state_version)?;
+
+        // Record dispute outcome and update reputation score
+        agent_entry.economics.record_dispute_outcome(won);
+        let reputation_score = agent_entry.economics.refresh_reputation_score();
+        agent_entry.header.bump_state_version();
 
         // Emit event
         let event = create_dispute_recorded_event(
-            agent_entry.agent_id.clone(),
+            agent_id,
             won,
-            agent_entry.reputation_score,
+            reputation_score,
         );
         emit_dispute_recorded(&event);
 
         Ok(())
     }
 
-    /// Helper function to update optional fields
-    fn update_optional_field(
-        field: &mut Option<String>,
-        new_value: Option<String>,
-        clear_flag: bool,
-        changed_fields: &mut Vec<String>,
-        field_name: &str,
-    ) {
-        if let Some(val) = new_value {
-            *field = Some(val);
-            changed_fields.push(field_name.to_string());
-        } else if clear_flag {
-            *field = None;
-            changed_fields.push(field_name.to_string());
+    /// Process initialize skill account instruction
+    fn process_initialize_skill_account(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        index: u8,
+        skill: AgentSkillInput,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let skill_account_info = next_account_info(account_info_iter)?;
+        let agent_entry_info = next_account_info(account_info_iter)?;
+        let owner_authority_info = next_account_info(account_info_iter)?;
+        let payer_info = next_account_info(account_info_iter)?;
+        let system_program_info = next_account_info(account_info_iter)?;
+
+        validate_skills(std::slice::from_ref(&skill))?;
+
+        if !payer_info.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
         }
-    }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use solana_program::pubkey::Pubkey;
-    use solana_program_test::*;
-    use solana_sdk::{
-        account::Account,
-        signature::{Keypair, Signer},
-        transaction::Transaction,
-    };
+        verify_account_owner(agent_entry_info, program_id)?;
 
-    #[test]
-    fn test_update_optional_field() {
-        let mut field = Some("original".to_string());
-        let mut changed_fields = Vec::new();
+        let mut data = agent_entry_info.try_borrow_mut_data()?;
+        let mut agent_entry = AgentRegistryEntryV1::try_from_account_data(&data)?;
 
-        // Test updating with new value
-        Processor::update_optional_field(
-            &mut field,
-            Some("new".to_string()),
-            false,
-            &mut changed_fields,
-            "test_field",
-        );
-        assert_eq!(field, Some("new".to_string()));
-        assert_eq!(changed_fields, vec!["test_field"]);
+        verify_signer_authority(owner_authority_info, &agent_entry.owner_authority)?;
 
-        // Test clearing field
-        changed_fields.clear();
-        Processor::update_optional_field(
-            &mut field,
-            None,
-            true,
-            &mut changed_fields,
-            "test_field",
+        // Link the new index first; this rejects gaps and enforces the cap
+        agent_entry.push_skill_account(index)?;
+
+        let (expected_pda, bump) = get_skill_account_pda(agent_entry_info.key, index, program_id);
+        if *skill_account_info.key != expected_pda {
+            return Err(ProgramError::InvalidSeeds);
+        }
+        if !skill_account_info.data_is_empty() {
+            return Err(RegistryError::AccountAlreadyExists.into());
+        }
+
+        let timestamp = get_current_timestamp()?;
+        let skill_account = AgentSkillAccountV1::new(
+            bump,
+            *agent_entry_info.key,
+            index,
+            AgentSkill::from(skill),
+            timestamp,
         );
-        assert_eq!(field, None);
-        assert_eq!(changed_fields, vec!["test_field"]);
 
-        // Test no change
-        changed_fields.clear();
-        Processor::update_optional_field(
-            &mut field,
-            None,
-            false,
-            &mut changed_fields,
-            "test_field",
+        create_pda_account(
+            payer_info,
+            skill_account_info,
+            system_program_info,
+            program_id,
+            skill_account.required_space()?,
+            &[SKILL_ACCOUNT_SEED, agent_entry_info.key.as_ref(), &[index], &[bump]],
+        )?;
+
+        let mut skill_data = skill_account_info.try_borrow_mut_data()?;
+        skill_account.serialize(&mut &mut skill_data[..])?;
+
+        agent_entry.last_update_timestamp = timestamp;
+        agent_entry.serialize(&mut &mut data[..])?;
+
+        let event = create_skill_account_changed_event(
+            agent_entry.agent_id.clone(),
+            index,
+            Some(skill_account.skill.id.clone()),
+            agent_entry.skill_account_count,
         );
-        assert_eq!(field, None);
-        assert!(changed_fields.is_empty());
+        emit_skill_account_changed(&event);
+
+        Ok(())
+    }
+
+    /// Process update skill account instruction
+    fn process_update_skill_account(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        index: u8,
+        skill: AgentSkillInput,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let skill_account_info = next_account_info(account_info_iter)?;
+        let agent_entry_info = next_account_info(account_info_iter)?;
+        let owner_authority_info = next_account_info(account_info_iter)?;
+        let payer_info = next_account_info(account_info_iter)?;
+        let system_program_info = next_account_info(account_info_iter)?;
+
+        validate_skills(std::slice::from_ref(&skill))?;
+
+        verify_account_owner(agent_entry_info, program_id)?;
+        verify_account_owner(skill_account_info, program_id)?;
+
+        let agent_entry = AgentRegistryEntryV1::try_from_account_data(&agent_entry_info.try_borrow_data()?)?;
+        verify_signer_authority(owner_authority_info, &agent_entry.owner_authority)?;
+
+        let skill_data = skill_account_info.try_borrow_data()?;
+        let mut skill_account = AgentSkillAccountV1::try_from_account_data(&skill_data)?;
+        drop(skill_data);
+
+        if skill_account.agent_entry != *agent_entry_info.key || skill_account.index != index {
+            return Err(RegistryError::InvalidPda.into());
+        }
+
+        skill_account.skill = AgentSkill::from(skill);
+        skill_account.last_update_timestamp = get_current_timestamp()?;
+
+        // Resize to fit the new skill, topping up or refunding rent
+        let space = skill_account.required_space()?;
+        resize_account(skill_account_info, payer_info, system_program_info, space)?;
+
+        let mut skill_data = skill_account_info.try_borrow_mut_data()?;
+        skill_account.serialize(&mut &mut skill_data[..])?;
+
+        let event = create_skill_account_changed_event(
+            agent_entry.agent_id.clone(),
+            index,
+            Some(skill_account.skill.id.clone()),
+            agent_entry.skill_account_count,
+        );
+        emit_skill_account_changed(&event);
+
+        Ok(())
+    }
+
+    /// Process close skill account instruction
+    fn process_close_skill_account(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        index: u8,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let skill_account_info = next_account_info(account_info_iter)?;
+        let agent_entry_info = next_account_info(account_info_iter)?;
+        let owner_authority_info = next_account_info(account_info_iter)?;
+        let recipient_info = next_account_info(account_info_iter)?;
+
+        verify_account_owner(agent_entry_info, program_id)?;
+        verify_account_owner(skill_account_info, program_id)?;
+
+        let mut data = agent_entry_info.try_borrow_mut_data()?;
+        let mut agent_entry = AgentRegistryEntryV1::try_from_account_data(&data)?;
+        verify_signer_authority(owner_authority_info, &agent_entry.owner_authority)?;
+
+        let skill_account = AgentSkillAccountV1::try_from_account_data(&skill_account_info.try_borrow_data()?)?;
+        if skill_account.agent_entry != *agent_entry_info.key || skill_account.index != index {
+            return Err(RegistryError::InvalidPda.into());
+        }
+
+        // Only the last account can be closed so the remaining indices stay contiguous
+        agent_entry.pop_skill_account(index)?;
+        agent_entry.last_update_timestamp = get_current_timestamp()?;
+
+        close_account(skill_account_info, recipient_info)?;
+        agent_entry.serialize(&mut &mut data[..])?;
+
+        let event = create_skill_account_changed_event(
+            agent_entry.agent_id.clone(),
+            index,
+            None,
+            agent_entry.skill_account_count,
+        );
+        emit_skill_account_changed(&event);
+
+        Ok(())
+    }
+
+    /// Process begin agent registration instruction
+    #[allow(clippy::too_many_arguments)]
+    fn process_begin_agent_registration(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        agent_id: String,
+        name: String,
+        description: String,
+        agent_version: String,
+        provider_name: Option<String>,
+        provider_url: Option<String>,
+        documentation_url: Option<String>,
+        capabilities_flags: u64,
+        supported_input_modes: Vec<String>,
+        supported_output_modes: Vec<String>,
+        security_info_uri: Option<String>,
+        aea_address: Option<String>,
+        economic_intent_summary: Option<String>,
+        supported_aea_protocols_hash: Option<[u8; HASH_SIZE]>,
+        extended_metadata_uri: Option<String>,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let agent_entry_info = next_account_info(account_info_iter)?;
+        let owner_authority_info = next_account_info(account_info_iter)?;
+        let payer_info = next_account_info(account_info_iter)?;
+        let system_program_info = next_account_info(account_info_iter)?;
+
+        // Skills, endpoints and tags arrive in later chunks
+        validate_register_agent(
+            &agent_id,
+            &name,
+            &description,
+            &agent_version,
+            &provider_name,
+            &provider_url,
+            &documentation_url,
+            &[],
+            &supported_input_modes,
+            &supported_output_modes,
+            &[],
+            &security_info_uri,
+            &aea_address,
+            &economic_intent_summary,
+            &extended_metadata_uri,
+            &[],
+        )?;
+
+        let (expected_pda, bump) = get_agent_pda_secure(&agent_id, owner_authority_info.key, program_id);
+        if agent_entry_info.key != &expected_pda {
+            return Err(RegistryError::InvalidPda.into());
+        }
+
+        verify_signer_authority(owner_authority_info, owner_authority_info.key)?;
+        verify_signer_authority(payer_info, payer_info.key)?;
+
+        if system_program_info.key != &solana_program::system_program::id() {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+
+        if !agent_entry_info.data_is_empty() {
+            return Err(RegistryError::AccountAlreadyExists.into());
+        }
+
+        let timestamp = get_current_timestamp()?;
+        let mut agent_entry = AgentRegistryEntryV1::new(
+            bump,
+            *owner_authority_info.key,
+            agent_id.clone(),
+            name,
+            description,
+            agent_version,
+            provider_name,
+            provider_url,
+            documentation_url,
+            Vec::new(),
+            capabilities_flags,
+            supported_input_modes,
+            supported_output_modes,
+            Vec::new(),
+            security_info_uri,
+            aea_address,
+            economic_intent_summary,
+            supported_aea_protocols_hash,
+            extended_metadata_uri,
+            Vec::new(),
+            timestamp,
+        );
+        agent_entry.registration_open = true;
+
+        create_pda_account(
+            payer_info,
+            agent_entry_info,
+            system_program_info,
+            program_id,
+            agent_entry.required_space()?,
+            &[
+                AGENT_REGISTRY_PDA_SEED,
+                agent_id.as_bytes(),
+                owner_authority_info.key.as_ref(),
+                &[bump],
+            ],
+        )?;
+
+        let mut data = agent_entry_info.try_borrow_mut_data()?;
+        agent_entry.serialize(&mut &mut data[..])?;
+
+        Ok(())
+    }
+
+    /// Apply one append chunk of a chunked registration, growing the entry
+    /// account to fit. `apply` enforces the open registration and limits.
+    fn process_append_registration_chunk<F>(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        field_name: &str,
+        apply: F,
+    ) -> ProgramResult
+    where
+        F: FnOnce(&mut AgentRegistryEntryV1) -> std::result::Result<(), RegistryError>,
+    {
+        let account_info_iter = &mut accounts.iter();
+        let agent_entry_info = next_account_info(account_info_iter)?;
+        let owner_authority_info = next_account_info(account_info_iter)?;
+        let payer_info = next_account_info(account_info_iter)?;
+        let system_program_info = next_account_info(account_info_iter)?;
+
+        verify_account_owner(agent_entry_info, program_id)?;
+
+        let data = agent_entry_info.try_borrow_data()?;
+        let mut agent_entry = AgentRegistryEntryV1::try_from_account_data(&data)?;
+        drop(data);
+
+        verify_signer_authority(owner_authority_info, &agent_entry.owner_authority)?;
+
+        apply(&mut agent_entry)?;
+        agent_entry.last_update_timestamp = get_current_timestamp()?;
+
+        let space = agent_entry.required_space()?;
+        resize_account(agent_entry_info, payer_info, system_program_info, space)?;
+
+        let mut data = agent_entry_info.try_borrow_mut_data()?;
+        agent_entry.serialize(&mut &mut data[..])?;
+
+        let event = create_agent_updated_event(
+            agent_entry.agent_id.clone(),
+            vec![field_name.to_string()],
+            agent_entry.last_update_timestamp,
+        );
+        emit_agent_updated(&event);
+
+        Ok(())
+    }
+
+    /// Process finalize agent registration instruction
+    fn process_finalize_agent_registration(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let agent_entry_info = next_account_info(account_info_iter)?;
+        let owner_authority_info = next_account_info(account_info_iter)?;
+
+        verify_account_owner(agent_entry_info, program_id)?;
+
+        let mut data = agent_entry_info.try_borrow_mut_data()?;
+        let mut agent_entry = AgentRegistryEntryV1::try_from_account_data(&data)?;
+
+        verify_signer_authority(owner_authority_info, &agent_entry.owner_authority)?;
+        agent_entry.require_registration_open()?;
+
+        // Same rules as a single-transaction registration, over the assembled entry
+        validate_agent_entry(&agent_entry)?;
+
+        let timestamp = get_current_timestamp()?;
+        agent_entry.finalize_registration(timestamp)?;
+
+        // Only fixed-size fields changed, so the account size is unchanged
+        agent_entry.serialize(&mut &mut data[..])?;
+
+        let event = create_agent_registered_event(
+            agent_entry.registry_version,
+            agent_entry.owner_authority,
+            agent_entry.agent_id,
+            agent_entry.name,
+            agent_entry.description,
+            agent_entry.agent_version,
+            agent_entry.provider_name,
+            agent_entry.provider_url,
+            agent_entry.documentation_url,
+            agent_entry.service_endpoints,
+            agent_entry.capabilities_flags,
+            agent_entry.supported_input_modes,
+            agent_entry.supported_output_modes,
+            agent_entry.skills,
+            agent_entry.security_info_uri,
+            agent_entry.aea_address,
+            agent_entry.economic_intent_summary,
+            agent_entry.supported_aea_protocols_hash,
+            agent_entry.status,
+            agent_entry.registration_timestamp,
+            agent_entry.last_update_timestamp,
+            agent_entry.extended_metadata_uri,
+            agent_entry.tags,
+        );
+        emit_agent_registered(&event);
+
+        Ok(())
+    }
+
+    /// Process initialize registry config instruction
+    fn process_initialize_registry_config(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        params: RegistryConfigParams,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let config_info = next_account_info(account_info_iter)?;
+        let authority_info = next_account_info(account_info_iter)?;
+        let payer_info = next_account_info(account_info_iter)?;
+        let program_data_info = next_account_info(account_info_iter)?;
+        let system_program_info = next_account_info(account_info_iter)?;
+
+        // Only the upgrade authority may claim the admin role
+        verify_upgrade_authority(program_id, program_data_info, authority_info)?;
+        if !payer_info.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        let (expected_config, bump) = get_registry_config_pda(program_id);
+        if config_info.key != &expected_config {
+            return Err(RegistryError::InvalidPda.into());
+        }
+        if !config_info.data_is_empty() {
+            return Err(RegistryError::AccountAlreadyExists.into());
+        }
+
+        params.validate()?;
+
+        create_pda_account(
+            payer_info,
+            config_info,
+            system_program_info,
+            program_id,
+            RegistryConfigV1::LEN,
+            &[REGISTRY_CONFIG_SEED, &[bump]],
+        )?;
+
+        let config = RegistryConfigV1 {
+            bump,
+            admin_authority: *authority_info.key,
+            params,
+            last_update_timestamp: get_current_timestamp()?,
+        };
+        config.serialize(&mut &mut config_info.try_borrow_mut_data()?[..])?;
+
+        emit_registry_config_updated(&create_registry_config_updated_event(&config));
+
+        Ok(())
+    }
+
+    /// Process update registry config instruction
+    fn process_update_registry_config(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        params: RegistryConfigParams,
+        new_admin: Option<Pubkey>,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let config_info = next_account_info(account_info_iter)?;
+        let admin_info = next_account_info(account_info_iter)?;
+        let staking_vault_info = next_account_info(account_info_iter)?;
+
+        let mut config = RegistryConfigV1::load(config_info, program_id)?;
+        verify_signer_authority(admin_info, &config.admin_authority)?;
+
+        params.validate()?;
+        config.check_mint_change(&params, staking_vault_info, program_id)?;
+
+        config.params = params;
+        if let Some(new_admin) = new_admin {
+            config.admin_authority = new_admin;
+        }
+        config.last_update_timestamp = get_current_timestamp()?;
+        config.serialize(&mut &mut config_info.try_borrow_mut_data()?[..])?;
+
+        emit_registry_config_updated(&create_registry_config_updated_event(&config));
+
+        Ok(())
+    }
+
+    /// Process initialize vaults instruction
+    fn process_initialize_vaults(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let config_info = next_account_info(account_info_iter)?;
+        let admin_info = next_account_info(account_info_iter)?;
+        let payer_info = next_account_info(account_info_iter)?;
+        let mint_info = next_account_info(account_info_iter)?;
+        let staking_vault_info = next_account_info(account_info_iter)?;
+        let fee_vault_info = next_account_info(account_info_iter)?;
+        let registration_vault_info = next_account_info(account_info_iter)?;
+        let treasury_vault_info = next_account_info(account_info_iter)?;
+        let token_program_info = next_account_info(account_info_iter)?;
+        let system_program_info = next_account_info(account_info_iter)?;
+
+        let config = RegistryConfigV1::load(config_info, program_id)?;
+        verify_signer_authority(admin_info, &config.admin_authority)?;
+        if !payer_info.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+        verify_token_program(token_program_info)?;
+        verify_token_mint(mint_info, &config.params)?;
+
+        for (vault_info, seed) in [
+            (staking_vault_info, STAKING_VAULT_SEED),
+            (fee_vault_info, FEE_VAULT_SEED),
+            (registration_vault_info, REGISTRATION_VAULT_SEED),
+            (treasury_vault_info, TREASURY_VAULT_SEED),
+        ] {
+            let (expected_vault, bump) = Pubkey::find_program_address(&[seed], program_id);
+            if vault_info.key != &expected_vault {
+                return Err(RegistryError::InvalidPda.into());
+            }
+            if !vault_info.data_is_empty() {
+                return Err(RegistryError::AccountAlreadyExists.into());
+            }
+            create_vault_token_account(
+                payer_info,
+                vault_info,
+                mint_info,
+                token_program_info,
+                system_program_info,
+                &[seed, &[bump]],
+            )?;
+        }
+
+        emit_vaults_initialized(&create_vaults_initialized_event(
+            *mint_info.key,
+            *staking_vault_info.key,
+            *fee_vault_info.key,
+            *registration_vault_info.key,
+            *treasury_vault_info.key,
+        ));
+
+        Ok(())
+    }
+
+    /// Process set revenue split instruction
+    fn process_set_revenue_split(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        recipients: Vec<SplitRecipient>,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let split_info = next_account_info(account_info_iter)?;
+        let agent_entry_info = next_account_info(account_info_iter)?;
+        let owner_info = next_account_info(account_info_iter)?;
+        let payer_info = next_account_info(account_info_iter)?;
+        let system_program_info = next_account_info(account_info_iter)?;
+
+        verify_account_owner(agent_entry_info, program_id)?;
+        {
+            let data = agent_entry_info.try_borrow_data()?;
+            let agent_entry = AgentEntryPrefixV1::load(&data)?;
+            verify_signer_authority(owner_info, &agent_entry.header.owner_authority)?;
+        }
+
+        let (expected_split, bump) = derive_revenue_split_pda(agent_entry_info.key, program_id);
+        if split_info.key != &expected_split {
+            return Err(RegistryError::InvalidPda.into());
+        }
+        let split = RevenueSplitV1::new(bump, *agent_entry_info.key, &recipients)?;
+
+        if split_info.data_is_empty() {
+            if !payer_info.is_signer {
+                return Err(ProgramError::MissingRequiredSignature);
+            }
+            create_pda_account(
+                payer_info,
+                split_info,
+                system_program_info,
+                program_id,
+                RevenueSplitV1::LEN,
+                &[REVENUE_SPLIT_SEED, agent_entry_info.key.as_ref(), &[bump]],
+            )?;
+        } else {
+            verify_account_owner(split_info, program_id)?;
+        }
+        split.serialize(&mut &mut split_info.try_borrow_mut_data()?[..])?;
+
+        emit_revenue_split_updated(&create_revenue_split_updated_event(&split));
+
+        Ok(())
+    }
+
+    /// Process withdraw treasury instruction
+    fn process_withdraw_treasury(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        amount: u64,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let config_info = next_account_info(account_info_iter)?;
+        let admin_info = next_account_info(account_info_iter)?;
+        let treasury_vault_info = next_account_info(account_info_iter)?;
+        let destination_info = next_account_info(account_info_iter)?;
+        let token_program_info = next_account_info(account_info_iter)?;
+
+        let config = RegistryConfigV1::load(config_info, program_id)?;
+        verify_signer_authority(admin_info, &config.admin_authority)?;
+        verify_token_program(token_program_info)?;
+        verify_user_token_account(destination_info, &config.params.treasury, &config.params)?;
+
+        let (expected_treasury, bump) = derive_treasury_vault_pda(program_id);
+        let treasury = verify_vault_token_account(treasury_vault_info, &expected_treasury, &config.params)?;
+        if treasury.amount < amount {
+            return Err(RegistryError::InsufficientFunds.into());
+        }
+
+        transfer_tokens_with_pda_signer_account_info(
+            treasury_vault_info,
+            destination_info,
+            treasury_vault_info,
+            token_program_info,
+            amount,
+            &[&[TREASURY_VAULT_SEED, &[bump]]],
+        )?;
+
+        emit_treasury_withdrawn(&create_treasury_withdrawn_event(*destination_info.key, amount));
+
+        Ok(())
+    }
+
+    /// Process migrate agent entry instruction
+    fn process_migrate_agent_entry(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let agent_entry_info = next_account_info(account_info_iter)?;
+        let payer_info = next_account_info(account_info_iter)?;
+        let system_program_info = next_account_info(account_info_iter)?;
+
+        verify_account_owner(agent_entry_info, program_id)?;
+        verify_signer_authority(payer_info, payer_info.key)?;
+        if system_program_info.key != &solana_program::system_program::id() {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+
+        let data_len = agent_entry_info.data_len();
+        let mut agent_entry = AgentRegistryEntryV1::migrate_from_v1(&agent_entry_info.try_borrow_data()?)?;
+        let (expected_pda, _) = get_agent_pda_secure(&agent_entry.agent_id, &agent_entry.owner_authority, program_id);
+        if agent_entry_info.key != &expected_pda {
+            return Err(RegistryError::InvalidPda.into());
+        }
+        agent_entry.bump_state_version();
+
+        // Only ever grow the account, so no rent goes back to an arbitrary
+        // payer; trailing bytes past the entry are ignored when decoding
+        let space = agent_entry.required_space()?.max(data_len);
+        resize_account(agent_entry_info, payer_info, system_program_info, space)?;
+        agent_entry.serialize(&mut &mut agent_entry_info.try_borrow_mut_data()?[..])?;
+
+        let event = create_agent_entry_migrated_event(
+            agent_entry.agent_id.clone(),
+            agent_entry.registry_version,
+            agent_entry.state_version,
+        );
+        emit_agent_entry_migrated(&event);
+
+        Ok(())
+    }
+
+    /// Helper function to update optional fields
+    fn update_optional_field(
+        field: &mut Option<String>,
+        new_value: Option<String>,
+        clear_flag: bool,
+        changed_fields: &mut Vec<String>,
+        field_name: &str,
+    ) {
+        if let Some(val) = new_value {
+            *field = Some(val);
+            changed_fields.push(field_name.to_string());
+        } else if clear_flag {
+            *field = None;
+            changed_fields.push(field_name.to_string());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_program::pubkey::Pubkey;
+    use solana_program_test::*;
+    use solana_sdk::{
+        account::Account,
+        signature::{Keypair, Signer},
+        transaction::Transaction,
+    };
+
+    #[test]
+    fn test_update_optional_field() {
+        let mut field = Some("original".to_string());
+        let mut changed_fields = Vec::new();
+
+        // Test updating with new value
+        Processor::update_optional_field(
+            &mut field,
+            Some("new".to_string()),
+            false,
+            &mut changed_fields,
+            "test_field",
+        );
+        assert_eq!(field, Some("new".to_string()));
+        assert_eq!(changed_fields, vec!["test_field"]);
+
+        // Test clearing field
+        changed_fields.clear();
+        Processor::update_optional_field(
+            &mut field,
+            None,
+            true,
+            &mut changed_fields,
+            "test_field",
+        );
+        assert_eq!(field, None);
+        assert_eq!(changed_fields, vec!["test_field"]);
+
+        // Test no change
+        changed_fields.clear();
+        Processor::update_optional_field(
+            &mut field,
+            None,
+            false,
+            &mut changed_fields,
+            "test_field",
+        );
+        assert_eq!(field, None);
+        assert!(changed_fields.is_empty());
+    }
+
+    /// Run `UpdateServiceFees` directly against an entry account holding `data`
+    fn update_service_fees(program_id: &Pubkey, owner: &Pubkey, data: &mut [u8]) -> ProgramResult {
+        let (config_key, bump) = get_registry_config_pda(program_id);
+        let mut config_data = RegistryConfigV1 {
+            bump,
+            admin_authority: *owner,
+            params: RegistryConfigParams::agent_defaults(Pubkey::new_unique(), Pubkey::new_unique()),
+            last_update_timestamp: 0,
+        }
+        .try_to_vec()
+        .unwrap();
+        let mut clock = solana_sdk::account::create_account_for_test(&Clock::default());
+        let entry_key = Pubkey::new_unique();
+        let clock_key = solana_program::sysvar::clock::id();
+        let (mut owner_lamports, mut entry_lamports, mut config_lamports) = (0, 1, 1);
+        let mut owner_data = [];
+        let system_program = solana_program::system_program::id();
+        let accounts = [
+            AccountInfo::new(owner, true, false, &mut owner_lamports, &mut owner_data, &system_program, false, 0),
+            AccountInfo::new(&entry_key, false, true, &mut entry_lamports, data, program_id, false, 0),
+            AccountInfo::new(&clock_key, false, false, &mut clock.lamports, &mut clock.data, &clock.owner, false, 0),
+            AccountInfo::new(&config_key, false, false, &mut config_lamports, &mut config_data, program_id, false, 0),
+        ];
+        let instruction = AgentRegistryInstruction::UpdateServiceFees {
+            base_fee: MIN_SERVICE_FEE,
+            priority_multiplier: MIN_PRIORITY_MULTIPLIER as u8,
+            accepts_escrow: true,
+            expected_state_version: Some(0),
+        };
+        Processor::process(program_id, &accounts, &instruction.pack())
+    }
+
+    #[test]
+    fn test_in_place_path_stays_flat_as_entry_grows() {
+        let program_id = Pubkey::new_unique();
+        let owner = Pubkey::new_unique();
+        let entry = AgentRegistryEntryV1 {
+            owner_authority: owner,
+            agent_id: "agent".to_string(),
+            ..Default::default()
+        };
+        let minimal = entry.try_to_vec().unwrap();
+        let id_end = AgentEntryPrefixV1::LEN + 4 + entry.agent_id.len();
+
+        // Everything after the ID is garbage a full decode would reject, so
+        // the instruction only succeeds if it never reads past the ID. Its
+        // work is then the same for a minimal and a maximal entry.
+        let mut results = Vec::new();
+        for len in [minimal.len(), AgentRegistryEntryV1::SPACE] {
+            let mut data = vec![0xFF; len];
+            data[..id_end].copy_from_slice(&minimal[..id_end]);
+            assert!(AgentRegistryEntryV1::try_from_account_data(&data).is_err());
+
+            update_service_fees(&program_id, &owner, &mut data).unwrap();
+            assert!(data[id_end..].iter().all(|&byte| byte == 0xFF));
+            results.push(data[..id_end].to_vec());
+        }
+        assert_eq!(results[0], results[1]);
+
+        let prefix = AgentEntryPrefixV1::load(&results[0]).unwrap();
+        assert_eq!({ prefix.header.state_version }, 1);
+        assert!(prefix.economics.accepts_escrow());
     }
 }
\ No newline at end of file
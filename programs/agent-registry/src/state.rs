@@ -1,21 +1,272 @@
 //! State definitions for the Agent Registry program
 
 use borsh::{BorshDeserialize, BorshSerialize};
-use solana_program::pubkey::Pubkey;
+use solana_program::{program_error::ProgramError, pubkey::Pubkey};
 use shank::ShankAccount;
 use aeamcp_common::{
     constants::*,
     error::RegistryError,
+    impl_borsh_for_pod,
     serialization::*,
+    token_utils::{calculate_agent_quality_score, calculate_fee_with_priority},
+    zero_copy::{check_entry_layout_version, load, load_mut, EntryHeader, ENTRY_LAYOUT_VERSION},
     AgentStatus,
 };
 
+/// Number of recent service ratings kept in `quality_ratings`
+pub const MAX_QUALITY_RATINGS: usize = 10;
+
+/// Token, fee and reputation fields of an agent entry. Stored at a fixed
+/// offset right after the [`EntryHeader`], so hot-path instructions can
+/// update them in place through [`AgentEntryPrefixV1`].
+#[repr(C, packed)]
+#[derive(Clone, Copy, Debug, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct AgentEconomicsV1 {
+    /// SVMAI Token mint address
+    pub token_mint: Pubkey,
+    /// Amount of SVMAI tokens staked
+    pub staked_amount: u64,
+    /// When tokens were staked
+    pub staking_timestamp: i64,
+    /// Lock period end timestamp
+    pub stake_locked_until: i64,
+    /// Staking tier (0: None, 1: Bronze, 2: Silver, 3: Gold, 4: Platinum)
+    pub staking_tier: u8,
+    /// Total SVMAI earned from services
+    pub total_earnings: u64,
+    /// Current number of active service escrows
+    pub active_escrows: u8,
+    /// Total completed services
+    pub completed_services: u32,
+    /// Total disputes (won + lost)
+    pub dispute_count: u16,
+    /// Disputes won by agent
+    pub dispute_wins: u16,
+    /// Calculated reputation (0-10000)
+    pub reputation_score: u64,
+    /// Most recent service ratings (1-5), oldest first; only the first
+    /// `quality_rating_count` are set
+    pub quality_ratings: [u8; MAX_QUALITY_RATINGS],
+    /// Number of ratings held in `quality_ratings`
+    pub quality_rating_count: u8,
+    /// Average response time in seconds
+    pub response_time_avg: u32,
+    /// Minimum SVMAI fee for services
+    pub base_service_fee: u64,
+    /// Fee multiplier for priority (100 = 1x, 150 = 1.5x, etc.)
+    pub priority_multiplier: u8,
+    /// Whether agent uses escrow system (0 or 1)
+    pub accepts_escrow: u8,
+    /// Amount paid for registration
+    pub registration_fee_paid: u64,
+    /// Timestamp of last fee update
+    pub last_fee_update: i64,
+    /// Total fees collected in SVMAI
+    pub total_fees_collected: u64,
+    /// Base fee applying at `fee_change_effective_at`
+    pub pending_base_service_fee: u64,
+    /// Priority multiplier applying at `fee_change_effective_at`
+    pub pending_priority_multiplier: u8,
+    /// When the pending fees replace the current ones (0: none scheduled)
+    pub fee_change_effective_at: i64,
+}
+
+impl_borsh_for_pod!(AgentEconomicsV1);
+
+impl Default for AgentEconomicsV1 {
+    fn default() -> Self {
+        Self {
+            priority_multiplier: 100,
+            ..bytemuck::Zeroable::zeroed()
+        }
+    }
+}
+
+impl AgentEconomicsV1 {
+    /// Serialized length of the economics block
+    pub const LEN: usize = std::mem::size_of::<Self>();
+
+    /// Recorded service ratings, oldest first
+    pub fn ratings(&self) -> &[u8] {
+        &self.quality_ratings[..self.quality_rating_count as usize]
+    }
+
+    /// Whether the agent accepts escrowed payments
+    pub fn accepts_escrow(&self) -> bool {
+        self.accepts_escrow != 0
+    }
+
+    /// Fee of one service at the agent's priority multiplier
+    pub fn priority_service_fee(&self) -> u64 {
+        calculate_fee_with_priority(self.base_service_fee, self.priority_multiplier, true)
+    }
+
+    /// Update staking information
+    pub fn update_staking(&mut self, amount: u64, tier: u8, lock_until: i64, timestamp: i64) {
+        self.staked_amount = amount;
+        self.staking_tier = tier;
+        self.stake_locked_until = lock_until;
+        self.staking_timestamp = timestamp;
+    }
+
+    /// Update service fees
+    pub fn update_service_fees(
+        &mut self,
+        base_fee: u64,
+        priority_multiplier: u8,
+        accepts_escrow: bool,
+        timestamp: i64,
+    ) {
+        self.base_service_fee = base_fee;
+        self.priority_multiplier = priority_multiplier;
+        self.accepts_escrow = accepts_escrow as u8;
+        self.last_fee_update = timestamp;
+    }
+
+    /// Change the service fees with notice for increases. A lower base fee
+    /// or multiplier applies now; if either rises, both new values are held
+    /// as pending until `timestamp + notice`. A base fee still at zero was
+    /// never set, so its first value applies now. `accepts_escrow` always
+    /// applies now, and a later change replaces a pending one. Returns when
+    /// the pending fees apply, or `None` if the change applied in full.
+    pub fn schedule_service_fees(
+        &mut self,
+        base_fee: u64,
+        priority_multiplier: u8,
+        accepts_escrow: bool,
+        timestamp: i64,
+        notice: i64,
+    ) -> Option<i64> {
+        self.apply_due_fee_change(timestamp);
+
+        let increase = (self.base_service_fee != 0 && base_fee > self.base_service_fee)
+            || priority_multiplier > self.priority_multiplier;
+        let base_fee_now = if self.base_service_fee == 0 {
+            base_fee
+        } else {
+            base_fee.min(self.base_service_fee)
+        };
+        self.update_service_fees(
+            base_fee_now,
+            priority_multiplier.min(self.priority_multiplier),
+            accepts_escrow,
+            timestamp,
+        );
+
+        if !increase {
+            self.fee_change_effective_at = 0;
+            return None;
+        }
+        let effective_at = timestamp.saturating_add(notice);
+        self.pending_base_service_fee = base_fee;
+        self.pending_priority_multiplier = priority_multiplier;
+        self.fee_change_effective_at = effective_at;
+        Some(effective_at)
+    }
+
+    /// Replace the current fees with the pending ones once they are due.
+    /// Returns whether a change was applied.
+    pub fn apply_due_fee_change(&mut self, timestamp: i64) -> bool {
+        if self.fee_change_effective_at == 0 || timestamp < self.fee_change_effective_at {
+            return false;
+        }
+        self.base_service_fee = self.pending_base_service_fee;
+        self.priority_multiplier = self.pending_priority_multiplier;
+        self.last_fee_update = self.fee_change_effective_at;
+        self.fee_change_effective_at = 0;
+        true
+    }
+
+    /// Record service completion
+    pub fn record_service_completion(&mut self, earnings: u64, rating: u8, response_time: u32) {
+        self.completed_services += 1;
+        self.total_earnings += earnings;
+
+        // Keep the most recent MAX_QUALITY_RATINGS, dropping the oldest
+        let count = self.quality_rating_count as usize;
+        if count < MAX_QUALITY_RATINGS {
+            self.quality_ratings[count] = rating;
+            self.quality_rating_count += 1;
+        } else {
+            self.quality_ratings.rotate_left(1);
+            self.quality_ratings[MAX_QUALITY_RATINGS - 1] = rating;
+        }
+
+        // Update average response time
+        if self.response_time_avg == 0 {
+            self.response_time_avg = response_time;
+        } else {
+            self.response_time_avg = (self.response_time_avg + response_time) / 2;
+        }
+    }
+
+    /// Record dispute outcome
+    pub fn record_dispute_outcome(&mut self, won: bool) {
+        self.dispute_count += 1;
+        if won {
+            self.dispute_wins += 1;
+        }
+    }
+
+    /// Recompute `reputation_score` from the service and dispute history
+    pub fn refresh_reputation_score(&mut self) -> u64 {
+        self.reputation_score = calculate_agent_quality_score(
+            self.completed_services,
+            self.ratings(),
+            self.dispute_wins,
+            self.dispute_count,
+            self.response_time_avg,
+        );
+        self.reputation_score
+    }
+
+    /// Check if stake can be unlocked
+    pub fn can_unstake(&self, current_timestamp: i64) -> bool {
+        current_timestamp >= self.stake_locked_until
+    }
+}
+
+/// Fixed-size prefix of an agent entry's account data. Token and metrics
+/// instructions borrow it in place instead of decoding the whole entry.
+#[repr(C, packed)]
+#[derive(Clone, Copy, Debug, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct AgentEntryPrefixV1 {
+    /// Fields shared by all registry entries
+    pub header: EntryHeader,
+    /// Token, fee and reputation fields
+    pub economics: AgentEconomicsV1,
+}
+
+impl AgentEntryPrefixV1 {
+    /// Length of the prefix; the variable-length part of the entry starts here
+    pub const LEN: usize = std::mem::size_of::<Self>();
+
+    /// Borrow the prefix of an agent entry's account data. Entries in an
+    /// older layout are rejected, since their economics are elsewhere.
+    pub fn load(data: &[u8]) -> Result<&Self, ProgramError> {
+        let prefix: &Self = load(data)?;
+        prefix.header.check_layout_version()?;
+        Ok(prefix)
+    }
+
+    /// Mutably borrow the prefix of an agent entry's account data
+    pub fn load_mut(data: &mut [u8]) -> Result<&mut Self, ProgramError> {
+        let prefix: &mut Self = load_mut(data)?;
+        prefix.header.check_layout_version()?;
+        Ok(prefix)
+    }
+}
+
 /// Agent Registry Entry (V1) - Solana account structure for storing agent data on-chain
+///
+/// The entry starts with a fixed-size prefix ([`AgentEntryPrefixV1`]): the
+/// common header fields, `status` and `economics`. Strings and vectors follow,
+/// so their lengths never shift the offsets of the prefix.
 #[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq, ShankAccount)]
 pub struct AgentRegistryEntryV1 {
     /// Bump seed used for this PDA's derivation
     pub bump: u8,
-    /// Schema version of this entry (e.g., 1)
+    /// Layout version of this entry ([`ENTRY_LAYOUT_VERSION`])
     pub registry_version: u8,
     /// State version for optimistic locking (prevents race conditions)
     pub state_version: u64,
@@ -23,6 +274,10 @@ pub struct AgentRegistryEntryV1 {
     pub operation_in_progress: bool,
     /// Solana public key of the entry's owner/manager
     pub owner_authority: Pubkey,
+    /// Agent status (0:Pending, 1:Active, 2:Inactive, 3:Deregistered)
+    pub status: u8,
+    /// Token, fee and reputation fields
+    pub economics: AgentEconomicsV1,
     /// Unique identifier for the agent
     pub agent_id: String,
     /// Human-readable name of the agent
@@ -55,66 +310,31 @@ pub struct AgentRegistryEntryV1 {
     pub economic_intent_summary: Option<String>,
     /// Optional SHA256 hash of a list of supported AEA protocol IDs
     pub supported_aea_protocols_hash: Option<[u8; HASH_SIZE]>,
-    /// Agent status (0:Pending, 1:Active, 2:Inactive, 3:Deregistered)
-    pub status: u8,
     /// Timestamp of initial registration
     pub registration_timestamp: i64,
-    /// Timestamp of the last update
+    /// Timestamp of the last metadata update. Token and metrics updates keep
+    /// their own timestamps in `economics`.
     pub last_update_timestamp: i64,
     /// Optional URI to extensive off-chain metadata (e.g., full AgentCard JSON)
     pub extended_metadata_uri: Option<String>,
     /// General discoverability tags for the agent
     pub tags: Vec<String>,
-    
-    // Token-related fields for Phase 1
-    /// SVMAI Token mint address
-    pub token_mint: Pubkey,
-    /// Amount of SVMAI tokens staked
-    pub staked_amount: u64,
-    /// When tokens were staked
-    pub staking_timestamp: i64,
-    /// Lock period end timestamp
-    pub stake_locked_until: i64,
-    /// Staking tier (0: None, 1: Bronze, 2: Silver, 3: Gold, 4: Platinum)
-    pub staking_tier: u8,
-    /// Total SVMAI earned from services
-    pub total_earnings: u64,
-    /// Current number of active service escrows
-    pub active_escrows: u8,
-    /// Total completed services
-    pub completed_services: u32,
-    /// Total disputes (won + lost)
-    pub dispute_count: u16,
-    /// Disputes won by agent
-    pub dispute_wins: u16,
-    /// Calculated reputation (0-10000)
-    pub reputation_score: u64,
-    /// Last 10 service ratings (1-5)
-    pub quality_ratings: Vec<u8>,
-    /// Average response time in seconds
-    pub response_time_avg: u32,
-    /// Minimum SVMAI fee for services
-    pub base_service_fee: u64,
-    /// Fee multiplier for priority (100 = 1x, 150 = 1.5x, etc.)
-    pub priority_multiplier: u8,
-    /// Whether agent uses escrow system
-    pub accepts_escrow: bool,
-    /// Amount paid for registration
-    pub registration_fee_paid: u64,
-    /// Timestamp of last fee update
-    pub last_fee_update: i64,
-    /// Total fees collected in SVMAI
-    pub total_fees_collected: u64,
+    /// Number of overflow skill accounts linked to this entry (indices `0..count`)
+    pub skill_account_count: u8,
+    /// Set while a chunked registration is still accepting appended data
+    pub registration_open: bool,
 }
 
 impl AgentRegistryEntryV1 {
-    /// Calculate the space required for this account
+    /// Upper bound on the account size, with every string and vector at its maximum
     pub const SPACE: usize = 8 // Discriminator
         + 1  // bump
         + 1  // registry_version
         + 8  // state_version
         + 1  // operation_in_progress
         + 32 // owner_authority
+        + 1  // status
+        + AgentEconomicsV1::LEN
         + borsh_size_string(MAX_AGENT_ID_LEN)
         + borsh_size_string(MAX_AGENT_NAME_LEN)
         + borsh_size_string(MAX_AGENT_DESCRIPTION_LEN)
@@ -131,31 +351,12 @@ impl AgentRegistryEntryV1 {
         + borsh_size_option_string(MAX_AEA_ADDRESS_LEN)
         + borsh_size_option_string(MAX_ECONOMIC_INTENT_LEN)
         + borsh_size_option_hash() // supported_aea_protocols_hash
-        + 1  // status
         + 8  // registration_timestamp
         + 8  // last_update_timestamp
         + borsh_size_option_string(MAX_EXTENDED_METADATA_URI_LEN)
         + borsh_size_vec_string(MAX_AGENT_TAGS, MAX_AGENT_TAG_LEN)
-        // Token-related fields
-        + 32 // token_mint
-        + 8  // staked_amount
-        + 8  // staking_timestamp
-        + 8  // stake_locked_until
-        + 1  // staking_tier
-        + 8  // total_earnings
-        + 1  // active_escrows
-        + 4  // completed_services
-        + 2  // dispute_count
-        + 2  // dispute_wins
-        + 8  // reputation_score
-        + STRING_LEN_PREFIX_SIZE + (10 * 1) // quality_ratings (Vec<u8> max 10)
-        + 4  // response_time_avg
-        + 8  // base_service_fee
-        + 1  // priority_multiplier
-        + 1  // accepts_escrow
-        + 8  // registration_fee_paid
-        + 8  // last_fee_update
-        + 8; // total_fees_collected
+        + 1  // skill_account_count
+        + 1; // registration_open
 
     /// Create a new agent registry entry
     pub fn new(
@@ -183,10 +384,12 @@ impl AgentRegistryEntryV1 {
     ) -> Self {
         Self {
             bump,
-            registry_version: 1,
+            registry_version: ENTRY_LAYOUT_VERSION,
             state_version: 0,
             operation_in_progress: false,
             owner_authority,
+            status: AgentStatus::Pending as u8,
+            economics: AgentEconomicsV1::default(),
             agent_id,
             name,
             description,
@@ -203,55 +406,88 @@ impl AgentRegistryEntryV1 {
             aea_address,
             economic_intent_summary,
             supported_aea_protocols_hash,
-            status: AgentStatus::Pending as u8,
             registration_timestamp: timestamp,
             last_update_timestamp: timestamp,
             extended_metadata_uri,
             tags,
-            // Initialize token fields with defaults
-            token_mint: Pubkey::default(),
-            staked_amount: 0,
-            staking_timestamp: 0,
-            stake_locked_until: 0,
-            staking_tier: 0,
-            total_earnings: 0,
-            active_escrows: 0,
-            completed_services: 0,
-            dispute_count: 0,
-            dispute_wins: 0,
-            reputation_score: 0,
-            quality_ratings: Vec::new(),
-            response_time_avg: 0,
-            base_service_fee: 0,
-            priority_multiplier: 100,
-            accepts_escrow: false,
-            registration_fee_paid: 0,
-            last_fee_update: 0,
-            total_fees_collected: 0,
+            skill_account_count: 0,
+            registration_open: false,
+        }
+    }
+
+    /// Deserialize an entry from account data, ignoring any unused trailing
+    /// bytes. Entries in an older layout are rejected.
+    pub fn try_from_account_data(data: &[u8]) -> Result<Self, ProgramError> {
+        check_entry_layout_version(data)?;
+        Self::deserialize(&mut &data[..]).map_err(|_| ProgramError::InvalidAccountData)
+    }
+
+    /// Decode an entry stored in layout version 1 into the current layout
+    pub fn migrate_from_v1(data: &[u8]) -> Result<Self, ProgramError> {
+        if load::<EntryHeader>(data)?.registry_version != 1 {
+            return Err(RegistryError::UnsupportedEntryVersion.into());
         }
+        let legacy = AgentRegistryEntryLayoutV1::deserialize(&mut &data[..])
+            .map_err(|_| ProgramError::InvalidAccountData)?;
+        Ok(legacy.into())
+    }
+
+    /// Bytes the account needs to hold this entry (its exact serialized
+    /// length). The rating history is fixed-size, so service completions
+    /// never change it.
+    pub fn required_space(&self) -> Result<usize, ProgramError> {
+        Ok(self.try_to_vec()?.len())
+    }
+
+    /// Read the agent ID from account data without decoding the rest of the
+    /// entry; it is the first field after the fixed-size prefix
+    pub fn read_agent_id(data: &[u8]) -> Result<String, ProgramError> {
+        let mut id_bytes = data.get(AgentEntryPrefixV1::LEN..).ok_or(ProgramError::AccountDataTooSmall)?;
+        String::deserialize(&mut id_bytes).map_err(|_| ProgramError::InvalidAccountData)
     }
 
     /// Update the last update timestamp with version check
     pub fn update_timestamp(&mut self, timestamp: i64, expected_version: u64) -> Result<(), RegistryError> {
-        if self.state_version != expected_version {
-            return Err(RegistryError::StateVersionMismatch);
-        }
+        self.check_expected_version(Some(expected_version))?;
         self.last_update_timestamp = timestamp;
-        self.state_version += 1;
+        self.bump_state_version();
         Ok(())
     }
 
     /// Update the status with version check
     pub fn update_status(&mut self, status: u8, timestamp: i64, expected_version: u64) -> Result<(), RegistryError> {
-        if self.state_version != expected_version {
-            return Err(RegistryError::StateVersionMismatch);
-        }
+        self.check_expected_version(Some(expected_version))?;
         self.status = status;
         self.last_update_timestamp = timestamp;
-        self.state_version += 1;
+        self.bump_state_version();
         Ok(())
     }
 
+    /// The fixed header fields, as laid out at the start of the account
+    pub fn header(&self) -> EntryHeader {
+        EntryHeader {
+            bump: self.bump,
+            registry_version: self.registry_version,
+            state_version: self.state_version,
+            operation_in_progress: self.operation_in_progress as u8,
+            owner_authority: self.owner_authority,
+            status: self.status,
+        }
+    }
+
+    /// Compare a caller-supplied `state_version` against the stored one.
+    /// `None` skips the check for callers that do not need compare-and-swap.
+    pub fn check_expected_version(&self, expected_state_version: Option<u64>) -> Result<(), RegistryError> {
+        self.header().check_expected_version(expected_state_version)
+    }
+
+    /// Record a state change
+    pub fn bump_state_version(&mut self) {
+        let mut header = self.header();
+        header.bump_state_version();
+        self.state_version = header.state_version;
+    }
+
     /// Begin an operation (reentrancy guard)
     pub fn begin_operation(&mut self) -> Result<(), RegistryError> {
         if self.operation_in_progress {
@@ -271,15 +507,13 @@ impl AgentRegistryEntryV1 {
     where
         F: FnOnce(&mut Self) -> Result<(), RegistryError>
     {
-        if self.state_version != expected_version {
-            return Err(RegistryError::StateVersionMismatch);
-        }
+        self.check_expected_version(Some(expected_version))?;
         self.begin_operation()?;
         
         let result = update_fn(self);
         
         if result.is_ok() {
-            self.state_version += 1;
+            self.bump_state_version();
         }
         self.end_operation();
         result
@@ -332,77 +566,242 @@ impl AgentRegistryEntryV1 {
     pub fn has_skill(&self, skill_id: &str) -> bool {
         self.skills.iter().any(|skill| skill.id == skill_id)
     }
-    
-    /// Update staking information
-    pub fn update_staking(
-        &mut self,
-        amount: u64,
-        tier: u8,
-        lock_until: i64,
-        timestamp: i64,
-    ) {
-        self.staked_amount = amount;
-        self.staking_tier = tier;
-        self.stake_locked_until = lock_until;
-        self.staking_timestamp = timestamp;
-        self.last_update_timestamp = timestamp;
-        self.state_version += 1;
+
+    /// Link a new overflow skill account; accounts are appended in index order
+    pub fn push_skill_account(&mut self, index: u8) -> Result<(), RegistryError> {
+        if index != self.skill_account_count {
+            return Err(RegistryError::InvalidOverflowIndex);
+        }
+        if self.skill_account_count as usize >= MAX_SKILL_ACCOUNTS {
+            return Err(RegistryError::TooManySkillAccounts);
+        }
+        self.skill_account_count += 1;
+        self.bump_state_version();
+        Ok(())
     }
-    
-    /// Update service fees
-    pub fn update_service_fees(
-        &mut self,
-        base_fee: u64,
-        priority_multiplier: u8,
-        accepts_escrow: bool,
-        timestamp: i64,
-    ) {
-        self.base_service_fee = base_fee;
-        self.priority_multiplier = priority_multiplier;
-        self.accepts_escrow = accepts_escrow;
-        self.last_fee_update = timestamp;
-        self.last_update_timestamp = timestamp;
-        self.state_version += 1;
+
+    /// Unlink an overflow skill account; only the last one can be removed so
+    /// the remaining indices stay contiguous
+    pub fn pop_skill_account(&mut self, index: u8) -> Result<(), RegistryError> {
+        if self.skill_account_count == 0 || index != self.skill_account_count - 1 {
+            return Err(RegistryError::InvalidOverflowIndex);
+        }
+        self.skill_account_count -= 1;
+        self.bump_state_version();
+        Ok(())
     }
-    
-    /// Record service completion
-    pub fn record_service_completion(
-        &mut self,
-        earnings: u64,
-        rating: u8,
-        response_time: u32,
-    ) {
-        self.completed_services += 1;
-        self.total_earnings += earnings;
-        
-        // Update quality ratings (keep last 10)
-        self.quality_ratings.push(rating);
-        if self.quality_ratings.len() > 10 {
-            self.quality_ratings.remove(0);
+
+    /// Fail unless a chunked registration is in progress on this entry
+    pub fn require_registration_open(&self) -> Result<(), RegistryError> {
+        if !self.registration_open {
+            return Err(RegistryError::RegistrationNotOpen);
         }
-        
-        // Update average response time
-        if self.response_time_avg == 0 {
-            self.response_time_avg = response_time;
-        } else {
-            self.response_time_avg = (self.response_time_avg + response_time) / 2;
+        Ok(())
+    }
+
+    /// Append skills while the registration is open
+    pub fn append_skills(&mut self, skills: Vec<AgentSkill>) -> Result<(), RegistryError> {
+        self.require_registration_open()?;
+        if self.skills.len() + skills.len() > MAX_SKILLS {
+            return Err(RegistryError::TooManySkills);
         }
-        
-        self.state_version += 1;
+        self.skills.extend(skills);
+        self.bump_state_version();
+        Ok(())
     }
-    
-    /// Record dispute outcome
-    pub fn record_dispute_outcome(&mut self, won: bool) {
-        self.dispute_count += 1;
-        if won {
-            self.dispute_wins += 1;
+
+    /// Append service endpoints while the registration is open
+    pub fn append_service_endpoints(&mut self, endpoints: Vec<ServiceEndpoint>) -> Result<(), RegistryError> {
+        self.require_registration_open()?;
+        if self.service_endpoints.len() + endpoints.len() > MAX_SERVICE_ENDPOINTS {
+            return Err(RegistryError::TooManyServiceEndpoints);
         }
-        self.state_version += 1;
+        self.service_endpoints.extend(endpoints);
+        self.bump_state_version();
+        Ok(())
     }
-    
-    /// Check if stake can be unlocked
-    pub fn can_unstake(&self, current_timestamp: i64) -> bool {
-        current_timestamp >= self.stake_locked_until
+
+    /// Append tags while the registration is open
+    pub fn append_tags(&mut self, tags: Vec<String>) -> Result<(), RegistryError> {
+        self.require_registration_open()?;
+        if self.tags.len() + tags.len() > MAX_AGENT_TAGS {
+            return Err(RegistryError::TooManyAgentTags);
+        }
+        self.tags.extend(tags);
+        self.bump_state_version();
+        Ok(())
+    }
+
+    /// Close the registration and activate the agent. Callers validate the
+    /// assembled entry first.
+    pub fn finalize_registration(&mut self, timestamp: i64) -> Result<(), RegistryError> {
+        self.require_registration_open()?;
+        self.registration_open = false;
+        self.status = AgentStatus::Active as u8;
+        self.last_update_timestamp = timestamp;
+        self.bump_state_version();
+        Ok(())
+    }
+}
+
+/// Overflow skill account (V1) - holds one skill beyond the `MAX_SKILLS` kept
+/// inline, derived from `[SKILL_ACCOUNT_SEED, agent_entry, index]`
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq, ShankAccount)]
+pub struct AgentSkillAccountV1 {
+    /// Bump seed used for this PDA's derivation
+    pub bump: u8,
+    /// Agent entry this skill belongs to
+    pub agent_entry: Pubkey,
+    /// Position of this account among the entry's overflow skills
+    pub index: u8,
+    /// The skill itself
+    pub skill: AgentSkill,
+    /// Timestamp of the last update
+    pub last_update_timestamp: i64,
+}
+
+impl AgentSkillAccountV1 {
+    /// Upper bound on the account size
+    pub const SPACE: usize = 1 // bump
+        + 32 // agent_entry
+        + 1  // index
+        + AgentSkill::SERIALIZED_SIZE
+        + 8; // last_update_timestamp
+
+    /// Create a new overflow skill account
+    pub fn new(bump: u8, agent_entry: Pubkey, index: u8, skill: AgentSkill, timestamp: i64) -> Self {
+        Self {
+            bump,
+            agent_entry,
+            index,
+            skill,
+            last_update_timestamp: timestamp,
+        }
+    }
+
+    /// Deserialize from account data, ignoring any unused trailing bytes
+    pub fn try_from_account_data(data: &[u8]) -> Result<Self, ProgramError> {
+        Self::deserialize(&mut &data[..]).map_err(|_| ProgramError::InvalidAccountData)
+    }
+
+    /// Bytes the account needs to hold this skill
+    pub fn required_space(&self) -> Result<usize, ProgramError> {
+        Ok(self.try_to_vec()?.len())
+    }
+}
+
+/// Agent entry as stored in layout version 1, with the economic fields after
+/// the variable-length part. Only read to migrate such entries.
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq)]
+pub struct AgentRegistryEntryLayoutV1 {
+    pub bump: u8,
+    pub registry_version: u8,
+    pub state_version: u64,
+    pub operation_in_progress: bool,
+    pub owner_authority: Pubkey,
+    pub agent_id: String,
+    pub name: String,
+    pub description: String,
+    pub agent_version: String,
+    pub provider_name: Option<String>,
+    pub provider_url: Option<String>,
+    pub documentation_url: Option<String>,
+    pub service_endpoints: Vec<ServiceEndpoint>,
+    pub capabilities_flags: u64,
+    pub supported_input_modes: Vec<String>,
+    pub supported_output_modes: Vec<String>,
+    pub skills: Vec<AgentSkill>,
+    pub security_info_uri: Option<String>,
+    pub aea_address: Option<String>,
+    pub economic_intent_summary: Option<String>,
+    pub supported_aea_protocols_hash: Option<[u8; HASH_SIZE]>,
+    pub status: u8,
+    pub registration_timestamp: i64,
+    pub last_update_timestamp: i64,
+    pub extended_metadata_uri: Option<String>,
+    pub tags: Vec<String>,
+    pub token_mint: Pubkey,
+    pub staked_amount: u64,
+    pub staking_timestamp: i64,
+    pub stake_locked_until: i64,
+    pub staking_tier: u8,
+    pub total_earnings: u64,
+    pub active_escrows: u8,
+    pub completed_services: u32,
+    pub dispute_count: u16,
+    pub dispute_wins: u16,
+    pub reputation_score: u64,
+    pub quality_ratings: Vec<u8>,
+    pub response_time_avg: u32,
+    pub base_service_fee: u64,
+    pub priority_multiplier: u8,
+    pub accepts_escrow: bool,
+    pub registration_fee_paid: u64,
+    pub last_fee_update: i64,
+    pub total_fees_collected: u64,
+    pub skill_account_count: u8,
+    pub registration_open: bool,
+}
+
+impl From<AgentRegistryEntryLayoutV1> for AgentRegistryEntryV1 {
+    fn from(legacy: AgentRegistryEntryLayoutV1) -> Self {
+        let mut economics = AgentEconomicsV1 {
+            token_mint: legacy.token_mint,
+            staked_amount: legacy.staked_amount,
+            staking_timestamp: legacy.staking_timestamp,
+            stake_locked_until: legacy.stake_locked_until,
+            staking_tier: legacy.staking_tier,
+            total_earnings: legacy.total_earnings,
+            active_escrows: legacy.active_escrows,
+            completed_services: legacy.completed_services,
+            dispute_count: legacy.dispute_count,
+            dispute_wins: legacy.dispute_wins,
+            reputation_score: legacy.reputation_score,
+            response_time_avg: legacy.response_time_avg,
+            base_service_fee: legacy.base_service_fee,
+            priority_multiplier: legacy.priority_multiplier,
+            accepts_escrow: legacy.accepts_escrow as u8,
+            registration_fee_paid: legacy.registration_fee_paid,
+            last_fee_update: legacy.last_fee_update,
+            total_fees_collected: legacy.total_fees_collected,
+            ..AgentEconomicsV1::default()
+        };
+        // Keep the most recent ratings
+        let ratings = &legacy.quality_ratings[legacy.quality_ratings.len().saturating_sub(MAX_QUALITY_RATINGS)..];
+        economics.quality_ratings[..ratings.len()].copy_from_slice(ratings);
+        economics.quality_rating_count = ratings.len() as u8;
+
+        Self {
+            bump: legacy.bump,
+            registry_version: ENTRY_LAYOUT_VERSION,
+            state_version: legacy.state_version,
+            operation_in_progress: legacy.operation_in_progress,
+            owner_authority: legacy.owner_authority,
+            status: legacy.status,
+            economics,
+            agent_id: legacy.agent_id,
+            name: legacy.name,
+            description: legacy.description,
+            agent_version: legacy.agent_version,
+            provider_name: legacy.provider_name,
+            provider_url: legacy.provider_url,
+            documentation_url: legacy.documentation_url,
+            service_endpoints: legacy.service_endpoints,
+            capabilities_flags: legacy.capabilities_flags,
+            supported_input_modes: legacy.supported_input_modes,
+            supported_output_modes: legacy.supported_output_modes,
+            skills: legacy.skills,
+            security_info_uri: legacy.security_info_uri,
+            aea_address: legacy.aea_address,
+            economic_intent_summary: legacy.economic_intent_summary,
+            supported_aea_protocols_hash: legacy.supported_aea_protocols_hash,
+            registration_timestamp: legacy.registration_timestamp,
+            last_update_timestamp: legacy.last_update_timestamp,
+            extended_metadata_uri: legacy.extended_metadata_uri,
+            tags: legacy.tags,
+            skill_account_count: legacy.skill_account_count,
+            registration_open: legacy.registration_open,
+        }
     }
 }
 
@@ -410,10 +809,12 @@ impl Default for AgentRegistryEntryV1 {
     fn default() -> Self {
         Self {
             bump: 0,
-            registry_version: 1,
+            registry_version: ENTRY_LAYOUT_VERSION,
             state_version: 0,
             operation_in_progress: false,
             owner_authority: Pubkey::default(),
+            status: AgentStatus::Pending as u8,
+            economics: AgentEconomicsV1::default(),
             agent_id: String::new(),
             name: String::new(),
             description: String::new(),
@@ -430,31 +831,12 @@ impl Default for AgentRegistryEntryV1 {
             aea_address: None,
             economic_intent_summary: None,
             supported_aea_protocols_hash: None,
-            status: AgentStatus::Pending as u8,
             registration_timestamp: 0,
             last_update_timestamp: 0,
             extended_metadata_uri: None,
             tags: Vec::new(),
-            // Token fields defaults
-            token_mint: Pubkey::default(),
-            staked_amount: 0,
-            staking_timestamp: 0,
-            stake_locked_until: 0,
-            staking_tier: 0,
-            total_earnings: 0,
-            active_escrows: 0,
-            completed_services: 0,
-            dispute_count: 0,
-            dispute_wins: 0,
-            reputation_score: 0,
-            quality_ratings: Vec::new(),
-            response_time_avg: 0,
-            base_service_fee: 0,
-            priority_multiplier: 100,
-            accepts_escrow: false,
-            registration_fee_paid: 0,
-            last_fee_update: 0,
-            total_fees_collected: 0,
+            skill_account_count: 0,
+            registration_open: false,
         }
     }
 }
@@ -462,6 +844,7 @@ impl Default for AgentRegistryEntryV1 {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use aeamcp_common::zero_copy::ENTRY_HEADER_LEN;
 
     #[test]
     fn test_agent_registry_entry_creation() {
@@ -493,7 +876,7 @@ mod tests {
         );
 
         assert_eq!(entry.bump, 255);
-        assert_eq!(entry.registry_version, 1);
+        assert_eq!(entry.registry_version, ENTRY_LAYOUT_VERSION);
         assert_eq!(entry.owner_authority, owner);
         assert_eq!(entry.agent_id, "test-agent");
         assert_eq!(entry.name, "Test Agent");
@@ -562,6 +945,230 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_check_expected_version() {
+        let mut entry = AgentRegistryEntryV1::default();
+        entry.state_version = 5;
+
+        assert!(entry.check_expected_version(None).is_ok());
+        assert!(entry.check_expected_version(Some(5)).is_ok());
+        assert_eq!(
+            entry.check_expected_version(Some(4)),
+            Err(RegistryError::StateVersionMismatch)
+        );
+    }
+
+    #[test]
+    fn test_rating_history_is_fixed_size() {
+        let mut entry = AgentRegistryEntryV1::default();
+        let space = entry.required_space().unwrap();
+        assert!(space < AgentRegistryEntryV1::SPACE);
+
+        // Only the most recent ratings are kept, and recording them never
+        // changes the required space
+        for rating in 1..=MAX_QUALITY_RATINGS as u8 + 2 {
+            entry.economics.record_service_completion(0, rating, 1);
+        }
+        assert_eq!(entry.required_space().unwrap(), space);
+        assert_eq!(entry.economics.ratings().len(), MAX_QUALITY_RATINGS);
+        assert_eq!(entry.economics.ratings()[0], 3);
+        assert_eq!({ entry.economics.completed_services }, MAX_QUALITY_RATINGS as u32 + 2);
+
+        // Trailing bytes are ignored when reading the entry back
+        let mut data = entry.try_to_vec().unwrap();
+        data.resize(space + 4, 0);
+        assert_eq!(AgentRegistryEntryV1::try_from_account_data(&data).unwrap(), entry);
+    }
+
+    #[test]
+    fn test_fee_increases_wait_for_notice() {
+        let mut economics = AgentEconomicsV1::default();
+        assert_eq!(economics.schedule_service_fees(1_000, 100, true, 0, 600), None);
+        assert_eq!({ economics.base_service_fee }, 1_000);
+
+        assert_eq!(economics.schedule_service_fees(2_000, 100, true, 100, 600), Some(700));
+        assert_eq!({ economics.base_service_fee }, 1_000);
+        assert!(!economics.apply_due_fee_change(699));
+        assert!(economics.apply_due_fee_change(700));
+        assert_eq!({ economics.base_service_fee }, 2_000);
+
+        // A lower fee with a higher multiplier lowers the fee now and
+        // schedules the multiplier
+        assert_eq!(economics.schedule_service_fees(1_500, 150, false, 800, 600), Some(1_400));
+        assert_eq!({ economics.base_service_fee }, 1_500);
+        assert_eq!({ economics.priority_multiplier }, 100);
+        assert!(!economics.accepts_escrow());
+        assert_eq!(economics.priority_service_fee(), 1_500);
+        assert!(economics.apply_due_fee_change(1_400));
+        assert_eq!(economics.priority_service_fee(), 2_250);
+    }
+
+    #[test]
+    fn test_prefix_matches_borsh_layout() {
+        assert_eq!(AgentEntryPrefixV1::LEN, ENTRY_HEADER_LEN + AgentEconomicsV1::LEN);
+
+        let economics = AgentEconomicsV1 {
+            staked_amount: 1_000,
+            ..Default::default()
+        };
+        let entry = AgentRegistryEntryV1 {
+            owner_authority: Pubkey::new_unique(),
+            agent_id: "agent".to_string(),
+            state_version: 3,
+            status: AgentStatus::Active as u8,
+            economics,
+            tags: vec!["nlp".to_string()],
+            ..Default::default()
+        };
+        let mut data = entry.try_to_vec().unwrap();
+
+        assert_eq!(AgentRegistryEntryV1::read_agent_id(&data).unwrap(), "agent");
+        let prefix = AgentEntryPrefixV1::load_mut(&mut data).unwrap();
+        assert_eq!(prefix.header.owner_authority, entry.owner_authority);
+        assert_eq!(prefix.header.status, AgentStatus::Active as u8);
+        assert_eq!(prefix.economics, entry.economics);
+
+        // In-place updates are visible to a full decode
+        prefix.economics.record_service_completion(500, 4, 60);
+        prefix.header.bump_state_version();
+        let decoded = AgentRegistryEntryV1::try_from_account_data(&data).unwrap();
+        assert_eq!(decoded.state_version, 4);
+        assert_eq!({ decoded.economics.total_earnings }, 500);
+        assert_eq!(decoded.economics.ratings(), &[4]);
+        assert_eq!(decoded.tags, entry.tags);
+    }
+
+    #[test]
+    fn test_layout_v1_entries_migrate() {
+        let legacy = AgentRegistryEntryLayoutV1 {
+            bump: 254,
+            registry_version: 1,
+            state_version: 7,
+            operation_in_progress: false,
+            owner_authority: Pubkey::new_unique(),
+            agent_id: "agent".to_string(),
+            name: "Agent".to_string(),
+            description: String::new(),
+            agent_version: "1.0.0".to_string(),
+            provider_name: None,
+            provider_url: None,
+            documentation_url: None,
+            service_endpoints: vec![],
+            capabilities_flags: 0,
+            supported_input_modes: vec![],
+            supported_output_modes: vec![],
+            skills: vec![],
+            security_info_uri: None,
+            aea_address: None,
+            economic_intent_summary: None,
+            supported_aea_protocols_hash: None,
+            status: AgentStatus::Active as u8,
+            registration_timestamp: 10,
+            last_update_timestamp: 20,
+            extended_metadata_uri: None,
+            tags: vec!["nlp".to_string()],
+            token_mint: Pubkey::new_unique(),
+            staked_amount: 1_000,
+            staking_timestamp: 30,
+            stake_locked_until: 40,
+            staking_tier: 2,
+            total_earnings: 500,
+            active_escrows: 1,
+            completed_services: 12,
+            dispute_count: 2,
+            dispute_wins: 1,
+            reputation_score: 9_000,
+            quality_ratings: vec![1, 1, 5, 4, 5, 4, 5, 4, 5, 4, 5, 3],
+            response_time_avg: 60,
+            base_service_fee: 100,
+            priority_multiplier: 150,
+            accepts_escrow: true,
+            registration_fee_paid: 50,
+            last_fee_update: 50,
+            total_fees_collected: 70,
+            skill_account_count: 1,
+            registration_open: false,
+        };
+        let mut data = legacy.try_to_vec().unwrap();
+
+        // Neither path reads the old layout as if it were the current one
+        assert_eq!(
+            AgentEntryPrefixV1::load(&data).unwrap_err(),
+            RegistryError::UnsupportedEntryVersion.into()
+        );
+        assert_eq!(
+            AgentEntryPrefixV1::load_mut(&mut data).unwrap_err(),
+            RegistryError::UnsupportedEntryVersion.into()
+        );
+        assert_eq!(
+            AgentRegistryEntryV1::try_from_account_data(&data).unwrap_err(),
+            RegistryError::UnsupportedEntryVersion.into()
+        );
+
+        let entry = AgentRegistryEntryV1::migrate_from_v1(&data).unwrap();
+        assert_eq!(entry.registry_version, ENTRY_LAYOUT_VERSION);
+        assert_eq!(entry.state_version, 7);
+        assert_eq!(entry.owner_authority, legacy.owner_authority);
+        assert_eq!(entry.status, AgentStatus::Active as u8);
+        assert_eq!(entry.tags, legacy.tags);
+        assert_eq!(entry.skill_account_count, 1);
+        assert_eq!(entry.economics.token_mint, legacy.token_mint);
+        assert_eq!({ entry.economics.staked_amount }, 1_000);
+        assert_eq!({ entry.economics.priority_multiplier }, 150);
+        assert!(entry.economics.accepts_escrow());
+        assert_eq!(entry.economics.ratings(), &[5, 4, 5, 4, 5, 4, 5, 4, 5, 3]);
+
+        // The migrated entry reads back through both paths
+        let data = entry.try_to_vec().unwrap();
+        assert_eq!(AgentEntryPrefixV1::load(&data).unwrap().economics, entry.economics);
+        assert_eq!(AgentRegistryEntryV1::try_from_account_data(&data).unwrap(), entry);
+        assert_eq!(
+            AgentRegistryEntryV1::migrate_from_v1(&data).unwrap_err(),
+            RegistryError::UnsupportedEntryVersion.into()
+        );
+    }
+
+    #[test]
+    fn test_skill_accounts_stay_contiguous() {
+        let mut entry = AgentRegistryEntryV1::default();
+
+        // Indices must be appended in order
+        assert_eq!(entry.push_skill_account(1), Err(RegistryError::InvalidOverflowIndex));
+        entry.push_skill_account(0).unwrap();
+        entry.push_skill_account(1).unwrap();
+        assert_eq!(entry.skill_account_count, 2);
+
+        // Only the last account can be removed
+        assert_eq!(entry.pop_skill_account(0), Err(RegistryError::InvalidOverflowIndex));
+        entry.pop_skill_account(1).unwrap();
+        entry.pop_skill_account(0).unwrap();
+        assert_eq!(entry.pop_skill_account(0), Err(RegistryError::InvalidOverflowIndex));
+
+        entry.skill_account_count = MAX_SKILL_ACCOUNTS as u8;
+        assert_eq!(
+            entry.push_skill_account(MAX_SKILL_ACCOUNTS as u8),
+            Err(RegistryError::TooManySkillAccounts)
+        );
+    }
+
+    #[test]
+    fn test_skill_account_space() {
+        let skill = AgentSkill {
+            id: "summarize".to_string(),
+            name: "Summarize".to_string(),
+            description_hash: None,
+            tags: vec!["text".to_string()],
+        };
+        let account = AgentSkillAccountV1::new(255, Pubkey::new_unique(), 3, skill, 1640995200);
+
+        let space = account.required_space().unwrap();
+        assert!(space <= AgentSkillAccountV1::SPACE);
+
+        let mut data = account.try_to_vec().unwrap();
+        data.resize(AgentSkillAccountV1::SPACE, 0);
+        assert_eq!(AgentSkillAccountV1::try_from_account_data(&data).unwrap(), account);
+    }
+
     #[test]
     fn test_serialization() {
         let entry = AgentRegistryEntryV1::default();
@@ -593,4 +1200,34 @@ mod tests {
         assert_eq!(ai_skills.len(), 1);
         assert_eq!(ai_skills[0].id, "skill1");
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_chunked_registration_appends() {
+        let mut entry = AgentRegistryEntryV1::default();
+        let tags = vec!["nlp".to_string()];
+
+        // Appends are rejected unless a chunked registration is open
+        assert_eq!(entry.append_tags(tags.clone()), Err(RegistryError::RegistrationNotOpen));
+
+        entry.registration_open = true;
+        entry.append_tags(tags).unwrap();
+        entry
+            .append_service_endpoints(vec![ServiceEndpoint {
+                protocol: "http".to_string(),
+                url: "https://example.com".to_string(),
+                is_default: true,
+            }])
+            .unwrap();
+        assert_eq!(entry.state_version, 2);
+
+        let too_many = vec!["tag".to_string(); MAX_AGENT_TAGS];
+        assert_eq!(entry.append_tags(too_many), Err(RegistryError::TooManyAgentTags));
+        assert_eq!(entry.tags.len(), 1);
+
+        entry.finalize_registration(1_700_000_000).unwrap();
+        assert!(entry.is_active());
+        assert!(!entry.registration_open);
+        assert_eq!(entry.last_update_timestamp, 1_700_000_000);
+        assert_eq!(entry.finalize_registration(0), Err(RegistryError::RegistrationNotOpen));
+    }
+}
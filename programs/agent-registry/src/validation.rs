@@ -6,6 +6,7 @@ use aeamcp_common::{
     serialization::{ServiceEndpointInput, AgentSkillInput},
     utils::{validate_string_field, validate_optional_string_field, validate_vec_length},
 };
+use crate::state::AgentRegistryEntryV1;
 
 /// Validate agent registration data
 pub fn validate_register_agent(
@@ -59,21 +60,13 @@ pub fn validate_register_agent(
 
 /// Validate service endpoints
 pub fn validate_service_endpoints(endpoints: &[ServiceEndpointInput]) -> Result<(), RegistryError> {
-    validate_vec_length(endpoints, MAX_SERVICE_ENDPOINTS, RegistryError::TooManyServiceEndpoints)?;
+    validate_service_endpoint_fields(endpoints)?;
 
     if endpoints.is_empty() {
         return Ok(());
     }
 
-    let mut default_count = 0;
-    for endpoint in endpoints {
-        validate_string_field(&endpoint.protocol, MAX_ENDPOINT_PROTOCOL_LEN, false, RegistryError::InvalidEndpointProtocolLength)?;
-        validate_string_field(&endpoint.url, MAX_ENDPOINT_URL_LEN, false, RegistryError::InvalidEndpointUrlLength)?;
-        
-        if endpoint.is_default {
-            default_count += 1;
-        }
-    }
+    let default_count = endpoints.iter().filter(|endpoint| endpoint.is_default).count();
 
     if default_count == 0 {
         return Err(RegistryError::MissingDefaultEndpoint);
@@ -85,6 +78,62 @@ pub fn validate_service_endpoints(endpoints: &[ServiceEndpointInput]) -> Result<
     Ok(())
 }
 
+/// Validate service endpoint count and field lengths, without the
+/// single-default rule (used for endpoints appended in chunks)
+pub fn validate_service_endpoint_fields(endpoints: &[ServiceEndpointInput]) -> Result<(), RegistryError> {
+    validate_vec_length(endpoints, MAX_SERVICE_ENDPOINTS, RegistryError::TooManyServiceEndpoints)?;
+
+    for endpoint in endpoints {
+        validate_string_field(&endpoint.protocol, MAX_ENDPOINT_PROTOCOL_LEN, false, RegistryError::InvalidEndpointProtocolLength)?;
+        validate_string_field(&endpoint.url, MAX_ENDPOINT_URL_LEN, false, RegistryError::InvalidEndpointUrlLength)?;
+    }
+
+    Ok(())
+}
+
+/// Validate an entry assembled by chunked registration with the same rules
+/// as a single-transaction `RegisterAgent`
+pub fn validate_agent_entry(entry: &AgentRegistryEntryV1) -> Result<(), RegistryError> {
+    let service_endpoints: Vec<ServiceEndpointInput> = entry
+        .service_endpoints
+        .iter()
+        .map(|ep| ServiceEndpointInput {
+            protocol: ep.protocol.clone(),
+            url: ep.url.clone(),
+            is_default: ep.is_default,
+        })
+        .collect();
+    let skills: Vec<AgentSkillInput> = entry
+        .skills
+        .iter()
+        .map(|skill| AgentSkillInput {
+            id: skill.id.clone(),
+            name: skill.name.clone(),
+            description_hash: skill.description_hash,
+            tags: skill.tags.clone(),
+        })
+        .collect();
+
+    validate_register_agent(
+        &entry.agent_id,
+        &entry.name,
+        &entry.description,
+        &entry.agent_version,
+        &entry.provider_name,
+        &entry.provider_url,
+        &entry.documentation_url,
+        &service_endpoints,
+        &entry.supported_input_modes,
+        &entry.supported_output_modes,
+        &skills,
+        &entry.security_info_uri,
+        &entry.aea_address,
+        &entry.economic_intent_summary,
+        &entry.extended_metadata_uri,
+        &entry.tags,
+    )
+}
+
 /// Validate supported modes (input or output)
 pub fn validate_supported_modes(modes: &[String]) -> Result<(), RegistryError> {
     validate_vec_length(modes, MAX_SUPPORTED_MODES, RegistryError::TooManySupportedModes)?;
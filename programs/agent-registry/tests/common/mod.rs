@@ -0,0 +1,79 @@
+//! Helpers shared by the `ProgramTest` suites
+
+#![allow(dead_code)]
+
+use aeamcp_common::{authority::derive_escrow_authority_pda, constants::ESCROW_AUTHORITY_SEED};
+use solana_program::{
+    account_info::AccountInfo,
+    entrypoint::ProgramResult,
+    instruction::{AccountMeta, Instruction},
+    program::invoke_signed,
+    program_pack::Pack,
+    pubkey::Pubkey,
+    rent::Rent,
+};
+use solana_program_test::BanksClient;
+use solana_sdk::account::Account;
+
+/// A minimal escrow program: forwards its instruction data to the registry
+/// passed as its first account, with the remaining accounts, signing for its
+/// authority PDA
+pub fn stub_escrow(program_id: &Pubkey, accounts: &[AccountInfo], input: &[u8]) -> ProgramResult {
+    let (registry_info, accounts) = accounts.split_first().unwrap();
+    let (authority, bump) = derive_escrow_authority_pda(program_id);
+    let instruction = Instruction {
+        program_id: *registry_info.key,
+        accounts: accounts
+            .iter()
+            .map(|account| AccountMeta {
+                pubkey: *account.key,
+                is_signer: account.is_signer || account.key == &authority,
+                is_writable: account.is_writable,
+            })
+            .collect(),
+        data: input.to_vec(),
+    };
+    invoke_signed(&instruction, accounts, &[&[ESCROW_AUTHORITY_SEED, &[bump]]])
+}
+
+/// An account holding `data` owned by `owner`, rent exempt
+pub fn rent_exempt_account(data: Vec<u8>, owner: Pubkey) -> Account {
+    Account {
+        lamports: Rent::default().minimum_balance(data.len()),
+        data,
+        owner,
+        executable: false,
+        rent_epoch: 0,
+    }
+}
+
+pub fn mint_account() -> Account {
+    let mut data = vec![0; spl_token::state::Mint::LEN];
+    spl_token::state::Mint {
+        decimals: 9,
+        is_initialized: true,
+        ..Default::default()
+    }
+    .pack_into_slice(&mut data);
+    rent_exempt_account(data, spl_token::id())
+}
+
+pub fn token_account(mint: Pubkey, owner: Pubkey, amount: u64) -> Account {
+    let mut data = vec![0; spl_token::state::Account::LEN];
+    spl_token::state::Account {
+        mint,
+        owner,
+        amount,
+        state: spl_token::state::AccountState::Initialized,
+        ..Default::default()
+    }
+    .pack_into_slice(&mut data);
+    rent_exempt_account(data, spl_token::id())
+}
+
+pub async fn token_balance(banks_client: &mut BanksClient, address: Pubkey) -> u64 {
+    let account = banks_client.get_account(address).await.unwrap().unwrap();
+    spl_token::state::Account::unpack(&account.data)
+        .unwrap()
+        .amount
+}
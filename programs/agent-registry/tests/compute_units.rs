@@ -0,0 +1,161 @@
+//! Compute units of the in-place hot path on an SBF build
+//!
+//! Needs `target/deploy/solana_a2a.so`, so it is ignored by `cargo test`.
+//! Run it with `cargo test-sbf -p solana-a2a --test compute_units -- --ignored --nocapture`.
+//! It prints `units_consumed` of `StakeTokens` and `RecordServiceCompletion`
+//! against a minimal entry and one with its description and tags at their
+//! maximum length. `StakeTokens` must cost the same for both.
+//! `RecordServiceCompletion` arrives through CPI, and the runtime charges for
+//! the account bytes a CPI passes, so its count still grows with the entry.
+
+mod common;
+
+use aeamcp_common::{
+    authority::derive_escrow_authority_pda,
+    config::{RegistryConfigParams, RegistryConfigV1},
+    constants::{
+        AUTHORIZED_ESCROW_PROGRAM_ID, IDEMPOTENCY_KEY_LEN, MAX_AGENT_DESCRIPTION_LEN, MAX_AGENT_TAGS,
+        MAX_AGENT_TAG_LEN,
+    },
+    receipt::derive_usage_receipt_pda,
+    token_utils::derive_staking_vault_pda,
+    utils::get_registry_config_pda,
+};
+use borsh::BorshSerialize;
+use common::{mint_account, rent_exempt_account, stub_escrow, token_account};
+use solana_a2a::{instruction::AgentRegistryInstruction, state::AgentRegistryEntryV1};
+use solana_program::{
+    instruction::{AccountMeta, Instruction},
+    pubkey::Pubkey,
+};
+use solana_program_test::*;
+use solana_sdk::{
+    signature::{Keypair, Signer},
+    transaction::Transaction,
+};
+
+/// `units_consumed` of a transaction holding `instruction`, which must succeed
+async fn units_consumed(
+    banks_client: &mut BanksClient,
+    payer: &Keypair,
+    signers: &[&Keypair],
+    instruction: Instruction,
+) -> u64 {
+    let recent_blockhash = banks_client.get_latest_blockhash().await.unwrap();
+    let mut transaction = Transaction::new_with_payer(&[instruction], Some(&payer.pubkey()));
+    let mut all_signers = vec![payer];
+    all_signers.extend_from_slice(signers);
+    transaction.sign(&all_signers, recent_blockhash);
+    let simulation = banks_client.simulate_transaction(transaction).await.unwrap();
+    assert_eq!(simulation.result, Some(Ok(())));
+    simulation.simulation_details.unwrap().units_consumed
+}
+
+#[tokio::test]
+#[ignore = "needs the SBF build of the program; run with cargo test-sbf"]
+async fn measure_hot_path_compute_units() {
+    let program_id = Pubkey::new_unique();
+    let escrow_id: Pubkey = AUTHORIZED_ESCROW_PROGRAM_ID.parse().unwrap();
+    let (escrow_authority, _) = derive_escrow_authority_pda(&escrow_id);
+    let owner = Keypair::new();
+    let (mint, owner_tokens) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let (staking_vault, _) = derive_staking_vault_pda(&program_id);
+    let (config_key, config_bump) = get_registry_config_pda(&program_id);
+    let params = RegistryConfigParams::agent_defaults(mint, Pubkey::new_unique());
+
+    // `None` loads the program from its SBF build instead of running it natively
+    let mut program_test = ProgramTest::new("solana_a2a", program_id, None);
+    program_test.add_program("stub_escrow", escrow_id, processor!(stub_escrow));
+    program_test.add_account(mint, mint_account());
+    let config = RegistryConfigV1 {
+        bump: config_bump,
+        admin_authority: owner.pubkey(),
+        params,
+        last_update_timestamp: 0,
+    };
+    program_test.add_account(
+        config_key,
+        rent_exempt_account(config.try_to_vec().unwrap(), program_id),
+    );
+    program_test.add_account(owner_tokens, token_account(mint, owner.pubkey(), 1_000_000));
+    program_test.add_account(staking_vault, token_account(mint, staking_vault, 0));
+
+    let minimal = AgentRegistryEntryV1 {
+        owner_authority: owner.pubkey(),
+        agent_id: "agent".to_string(),
+        ..Default::default()
+    };
+    let maximal = AgentRegistryEntryV1 {
+        description: "d".repeat(MAX_AGENT_DESCRIPTION_LEN),
+        tags: vec!["t".repeat(MAX_AGENT_TAG_LEN); MAX_AGENT_TAGS],
+        ..minimal.clone()
+    };
+    let entries: Vec<_> = [minimal, maximal]
+        .into_iter()
+        .map(|entry| (Pubkey::new_unique(), entry.try_to_vec().unwrap()))
+        .collect();
+    for (key, data) in &entries {
+        program_test.add_account(*key, rent_exempt_account(data.clone(), program_id));
+    }
+    let (mut banks_client, payer, _) = program_test.start().await;
+
+    let mut measured = Vec::new();
+    for (i, (agent_key, data)) in entries.iter().enumerate() {
+        let stake = Instruction::new_with_bytes(
+            program_id,
+            &AgentRegistryInstruction::StakeTokens {
+                amount: 1_000,
+                lock_period: params.max_lock_period,
+                expected_state_version: None,
+            }
+            .pack(),
+            vec![
+                AccountMeta::new_readonly(owner.pubkey(), true),
+                AccountMeta::new(*agent_key, false),
+                AccountMeta::new(owner_tokens, false),
+                AccountMeta::new(staking_vault, false),
+                AccountMeta::new_readonly(spl_token::id(), false),
+                AccountMeta::new_readonly(solana_program::sysvar::clock::id(), false),
+                AccountMeta::new_readonly(config_key, false),
+            ],
+        );
+
+        // Issued by the escrow through CPI, without settlement accounts; the
+        // count includes the stub escrow's forwarding
+        let idempotency_key = [i as u8; IDEMPOTENCY_KEY_LEN];
+        let (receipt, _) = derive_usage_receipt_pda(agent_key, &escrow_id, &idempotency_key, &program_id);
+        let complete = Instruction::new_with_bytes(
+            escrow_id,
+            &AgentRegistryInstruction::RecordServiceCompletion {
+                earnings: 1_000,
+                rating: 5,
+                response_time: 100,
+                max_total_fee: 1_000,
+                idempotency_key,
+                expected_state_version: None,
+            }
+            .pack(),
+            vec![
+                AccountMeta::new_readonly(program_id, false),
+                AccountMeta::new_readonly(escrow_id, false),
+                AccountMeta::new(*agent_key, false),
+                AccountMeta::new_readonly(solana_program::sysvar::clock::id(), false),
+                AccountMeta::new(payer.pubkey(), true),
+                AccountMeta::new(receipt, false),
+                AccountMeta::new_readonly(solana_program::system_program::id(), false),
+                AccountMeta::new_readonly(escrow_authority, false),
+            ],
+        );
+
+        let stake_units = units_consumed(&mut banks_client, &payer, &[&owner], stake).await;
+        let complete_units = units_consumed(&mut banks_client, &payer, &[], complete).await;
+        println!(
+            "entry of {} bytes: StakeTokens {} CU, RecordServiceCompletion {} CU",
+            data.len(),
+            stake_units,
+            complete_units
+        );
+        measured.push(stake_units);
+    }
+    assert_eq!(measured[0], measured[1]);
+}
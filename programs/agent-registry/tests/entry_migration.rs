@@ -0,0 +1,146 @@
+//! Migrating an entry stored in layout version 1 through the program
+//!
+//! Runs in its own test binary: `ProgramTest` installs process-wide syscall
+//! stubs that the processor unit tests, which call `Processor::process`
+//! directly, must not see.
+
+use aeamcp_common::{error::RegistryError, utils::get_agent_pda_secure, zero_copy::ENTRY_LAYOUT_VERSION};
+use borsh::BorshSerialize;
+use solana_a2a::{
+    instruction::AgentRegistryInstruction,
+    state::{AgentEntryPrefixV1, AgentRegistryEntryLayoutV1, AgentRegistryEntryV1},
+};
+use solana_program::{
+    instruction::{AccountMeta, Instruction, InstructionError},
+    pubkey::Pubkey,
+    rent::Rent,
+};
+use solana_program_test::*;
+use solana_sdk::{
+    account::Account,
+    signature::Signer,
+    transaction::{Transaction, TransactionError},
+};
+
+#[tokio::test]
+async fn test_layout_v1_entry_migrates_in_place() {
+    let program_id = Pubkey::new_unique();
+    let owner = Pubkey::new_unique();
+    let (entry_key, bump) = get_agent_pda_secure("agent", &owner, &program_id);
+
+    let legacy = AgentRegistryEntryLayoutV1 {
+        bump,
+        registry_version: 1,
+        state_version: 3,
+        operation_in_progress: false,
+        owner_authority: owner,
+        agent_id: "agent".to_string(),
+        name: "Agent".to_string(),
+        description: String::new(),
+        agent_version: "1.0.0".to_string(),
+        provider_name: None,
+        provider_url: None,
+        documentation_url: None,
+        service_endpoints: vec![],
+        capabilities_flags: 0,
+        supported_input_modes: vec![],
+        supported_output_modes: vec![],
+        skills: vec![],
+        security_info_uri: None,
+        aea_address: None,
+        economic_intent_summary: None,
+        supported_aea_protocols_hash: None,
+        status: 1,
+        registration_timestamp: 0,
+        last_update_timestamp: 0,
+        extended_metadata_uri: None,
+        tags: vec![],
+        token_mint: Pubkey::new_unique(),
+        staked_amount: 1_000,
+        staking_timestamp: 0,
+        stake_locked_until: 0,
+        staking_tier: 1,
+        total_earnings: 0,
+        active_escrows: 0,
+        completed_services: 0,
+        dispute_count: 0,
+        dispute_wins: 0,
+        reputation_score: 0,
+        quality_ratings: vec![],
+        response_time_avg: 0,
+        base_service_fee: 100,
+        priority_multiplier: 100,
+        accepts_escrow: true,
+        registration_fee_paid: 0,
+        last_fee_update: 0,
+        total_fees_collected: 0,
+        skill_account_count: 0,
+        registration_open: false,
+    };
+    let data = legacy.try_to_vec().unwrap();
+
+    let mut program_test = ProgramTest::new(
+        "solana_a2a",
+        program_id,
+        processor!(solana_a2a::process_instruction),
+    );
+    program_test.add_account(
+        entry_key,
+        Account {
+            lamports: Rent::default().minimum_balance(data.len()),
+            data,
+            owner: program_id,
+            executable: false,
+            rent_epoch: 0,
+        },
+    );
+    let (mut banks_client, payer, recent_blockhash) = program_test.start().await;
+
+    let migrate = Instruction::new_with_bytes(
+        program_id,
+        &AgentRegistryInstruction::MigrateAgentEntry.pack(),
+        vec![
+            AccountMeta::new(entry_key, false),
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new_readonly(solana_program::system_program::id(), false),
+        ],
+    );
+    let transaction = Transaction::new_signed_with_payer(
+        std::slice::from_ref(&migrate),
+        Some(&payer.pubkey()),
+        &[&payer],
+        recent_blockhash,
+    );
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    // The account grew to the new layout and reads through the prefix
+    let account = banks_client.get_account(entry_key).await.unwrap().unwrap();
+    assert!(Rent::default().is_exempt(account.lamports, account.data.len()));
+    let prefix = AgentEntryPrefixV1::load(&account.data).unwrap();
+    assert_eq!(prefix.header.registry_version, ENTRY_LAYOUT_VERSION);
+    assert_eq!({ prefix.header.state_version }, 4);
+    assert_eq!({ prefix.economics.staked_amount }, 1_000);
+    let entry = AgentRegistryEntryV1::try_from_account_data(&account.data).unwrap();
+    assert_eq!(entry.agent_id, "agent");
+    assert_eq!(entry.owner_authority, owner);
+
+    // An entry already in the current layout is left alone
+    let transaction = Transaction::new_signed_with_payer(
+        &[migrate],
+        Some(&payer.pubkey()),
+        &[&payer],
+        banks_client.get_new_latest_blockhash(&recent_blockhash).await.unwrap(),
+    );
+    let error = banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap_err()
+        .unwrap();
+    assert_eq!(
+        error,
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(RegistryError::UnsupportedEntryVersion as u32)
+        )
+    );
+}
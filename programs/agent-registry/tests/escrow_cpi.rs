@@ -0,0 +1,153 @@
+//! Settling service earnings through a real CPI from an escrow program
+//!
+//! Runs in its own test binary: `ProgramTest` installs process-wide syscall
+//! stubs that the processor unit tests, which call `Processor::process`
+//! directly, must not see.
+
+mod common;
+
+use aeamcp_common::{
+    authority::derive_escrow_authority_pda,
+    config::{RegistryConfigParams, RegistryConfigV1},
+    constants::{AUTHORIZED_ESCROW_PROGRAM_ID, IDEMPOTENCY_KEY_LEN},
+    error::RegistryError,
+    receipt::derive_usage_receipt_pda,
+    revenue::{bps_share, derive_revenue_split_pda},
+    token_utils::derive_treasury_vault_pda,
+    utils::get_registry_config_pda,
+};
+use borsh::BorshSerialize;
+use common::{mint_account, rent_exempt_account, stub_escrow, token_account, token_balance};
+use solana_a2a::{instruction::AgentRegistryInstruction, state::AgentRegistryEntryV1};
+use solana_program::{
+    instruction::{AccountMeta, Instruction, InstructionError},
+    pubkey::Pubkey,
+};
+use solana_program_test::*;
+use solana_sdk::{
+    signature::Signer,
+    transaction::{Transaction, TransactionError},
+};
+
+#[tokio::test]
+async fn test_escrow_settles_service_earnings_through_cpi() {
+    let program_id = Pubkey::new_unique();
+    let escrow_id: Pubkey = AUTHORIZED_ESCROW_PROGRAM_ID.parse().unwrap();
+    let (escrow_authority, _) = derive_escrow_authority_pda(&escrow_id);
+    let (owner, mint, agent_key) = (
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+    );
+    let (escrow_tokens, owner_tokens) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let (treasury_vault, _) = derive_treasury_vault_pda(&program_id);
+    let (config_key, config_bump) = get_registry_config_pda(&program_id);
+    let params = RegistryConfigParams::agent_defaults(mint, Pubkey::new_unique());
+
+    let mut program_test = ProgramTest::new(
+        "solana_a2a",
+        program_id,
+        processor!(solana_a2a::process_instruction),
+    );
+    program_test.add_program("stub_escrow", escrow_id, processor!(stub_escrow));
+    program_test.add_account(mint, mint_account());
+    let config = RegistryConfigV1 {
+        bump: config_bump,
+        admin_authority: owner,
+        params,
+        last_update_timestamp: 0,
+    };
+    program_test.add_account(
+        config_key,
+        rent_exempt_account(config.try_to_vec().unwrap(), program_id),
+    );
+    let entry = AgentRegistryEntryV1 {
+        owner_authority: owner,
+        agent_id: "agent".to_string(),
+        ..Default::default()
+    };
+    program_test.add_account(
+        agent_key,
+        rent_exempt_account(entry.try_to_vec().unwrap(), program_id),
+    );
+    program_test.add_account(escrow_tokens, token_account(mint, escrow_authority, 1_000));
+    program_test.add_account(owner_tokens, token_account(mint, owner, 0));
+    program_test.add_account(treasury_vault, token_account(mint, treasury_vault, 0));
+    let (mut banks_client, payer, recent_blockhash) = program_test.start().await;
+
+    // The registry instruction, with the escrow authority not signing
+    let record = |idempotency_key: [u8; IDEMPOTENCY_KEY_LEN]| {
+        let (receipt, _) =
+            derive_usage_receipt_pda(&agent_key, &escrow_id, &idempotency_key, &program_id);
+        let (split, _) = derive_revenue_split_pda(&agent_key, &program_id);
+        let accounts = vec![
+            AccountMeta::new_readonly(escrow_id, false),
+            AccountMeta::new(agent_key, false),
+            AccountMeta::new_readonly(solana_program::sysvar::clock::id(), false),
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new(receipt, false),
+            AccountMeta::new_readonly(solana_program::system_program::id(), false),
+            AccountMeta::new_readonly(escrow_authority, false),
+            AccountMeta::new(escrow_tokens, false),
+            AccountMeta::new(owner_tokens, false),
+            AccountMeta::new(treasury_vault, false),
+            AccountMeta::new_readonly(config_key, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new_readonly(split, false),
+        ];
+        let data = AgentRegistryInstruction::RecordServiceCompletion {
+            earnings: 1_000,
+            rating: 5,
+            response_time: 100,
+            max_total_fee: 1_000,
+            idempotency_key,
+            expected_state_version: None,
+        }
+        .pack();
+        (accounts, data)
+    };
+
+    // The escrow's authority PDA signs through the CPI and owns the escrow
+    // token account, so the earnings are paid out of it
+    let (accounts, data) = record([1; IDEMPOTENCY_KEY_LEN]);
+    let mut escrow_accounts = vec![AccountMeta::new_readonly(program_id, false)];
+    escrow_accounts.extend(accounts);
+    let transaction = Transaction::new_signed_with_payer(
+        &[Instruction::new_with_bytes(escrow_id, &data, escrow_accounts)],
+        Some(&payer.pubkey()),
+        &[&payer],
+        recent_blockhash,
+    );
+    banks_client.process_transaction(transaction).await.unwrap();
+    let protocol_fee = bps_share(1_000, params.protocol_fee_bps);
+    assert_eq!(token_balance(&mut banks_client, escrow_tokens).await, 0);
+    assert_eq!(
+        token_balance(&mut banks_client, treasury_vault).await,
+        protocol_fee
+    );
+    assert_eq!(
+        token_balance(&mut banks_client, owner_tokens).await,
+        1_000 - protocol_fee
+    );
+
+    // Called directly, nothing can sign for the escrow's authority
+    let (accounts, data) = record([2; IDEMPOTENCY_KEY_LEN]);
+    let transaction = Transaction::new_signed_with_payer(
+        &[Instruction::new_with_bytes(program_id, &data, accounts)],
+        Some(&payer.pubkey()),
+        &[&payer],
+        recent_blockhash,
+    );
+    let error = banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap_err()
+        .unwrap();
+    assert_eq!(
+        error,
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(RegistryError::MissingRequiredSignature as u32)
+        )
+    );
+}
@@ -68,18 +68,30 @@ impl Default for AuthorityRegistry {
     }
 }
 
+/// Derive the authority PDA an escrow program signs settlements with
+pub fn derive_escrow_authority_pda(escrow_program_id: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[crate::constants::ESCROW_AUTHORITY_SEED], escrow_program_id)
+}
+
 /// Verify escrow program authority for CPI calls
 /// 
 /// This function implements comprehensive authority verification:
-/// 1. Checks if the account is a signer
+/// 1. Checks that the escrow's authority PDA signed, which only the escrow
+///    program itself can do through `invoke_signed`
 /// 2. Verifies the program ID is in the authorized list
 /// 3. Ensures the account is executable (valid program account)
 pub fn verify_escrow_program_authority(
     escrow_program_info: &AccountInfo,
+    escrow_authority_info: &AccountInfo,
     authority_registry: &AuthorityRegistry,
 ) -> Result<(), RegistryError> {
-    // Check if account is a signer
-    if !escrow_program_info.is_signer {
+    // Check that the escrow signed with its authority PDA
+    let (expected_authority, _) = derive_escrow_authority_pda(escrow_program_info.key);
+    if escrow_authority_info.key != &expected_authority {
+        msg!("Escrow program authority verification failed: wrong authority PDA");
+        return Err(RegistryError::InvalidPda);
+    }
+    if !escrow_authority_info.is_signer {
         msg!("Escrow program authority verification failed: missing signature");
         return Err(RegistryError::MissingRequiredSignature);
     }
@@ -0,0 +1,325 @@
+//! Governable registry configuration
+//!
+//! Each registry program keeps its economic parameters (fees, stake tiers,
+//! lock periods, minimum fees, the accepted mint and the treasury) in a single
+//! PDA derived from [`REGISTRY_CONFIG_SEED`]. An admin authority can change
+//! them without a program upgrade. The compile-time constants in
+//! [`crate::constants`] are only used as the initial values.
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use solana_program::{account_info::AccountInfo, program_error::ProgramError, pubkey::Pubkey};
+use crate::{
+    constants::*,
+    error::RegistryError,
+    token_utils::derive_staking_vault_pda,
+    utils::{get_registry_config_pda, verify_account_owner},
+};
+
+/// Number of stake tiers a config can describe
+pub const MAX_STAKE_TIERS: usize = 4;
+
+/// Default agent tiers: Bronze, Silver, Gold, Platinum
+pub const DEFAULT_AGENT_TIER_THRESHOLDS: [u64; MAX_STAKE_TIERS] = [
+    BRONZE_TIER_STAKE,
+    SILVER_TIER_STAKE,
+    GOLD_TIER_STAKE,
+    PLATINUM_TIER_STAKE,
+];
+
+/// Default agent lock periods, per tier
+pub const DEFAULT_AGENT_TIER_LOCK_PERIODS: [i64; MAX_STAKE_TIERS] = [
+    BRONZE_LOCK_PERIOD,
+    SILVER_LOCK_PERIOD,
+    GOLD_LOCK_PERIOD,
+    PLATINUM_LOCK_PERIOD,
+];
+
+/// Default MCP server tiers: Basic, Verified, Premium (the fourth tier is unused)
+pub const DEFAULT_SERVER_TIER_THRESHOLDS: [u64; MAX_STAKE_TIERS] = [
+    BASIC_SERVER_STAKE,
+    VERIFIED_SERVER_STAKE,
+    PREMIUM_SERVER_STAKE,
+    u64::MAX,
+];
+
+/// Parameters an admin can set on a registry config
+#[derive(BorshSerialize, BorshDeserialize, Clone, Copy, Debug, PartialEq)]
+pub struct RegistryConfigParams {
+    /// Mint of the token used for fees and stakes
+    pub accepted_mint: Pubkey,
+    /// Wallet that treasury vault withdrawals are paid to
+    pub treasury: Pubkey,
+    /// Fee charged by the with-token registration instructions
+    pub registration_fee: u64,
+    /// Minimum stake of each tier, lowest tier first. Unused tiers are `u64::MAX`.
+    pub tier_thresholds: [u64; MAX_STAKE_TIERS],
+    /// Minimum lock period of each tier, in seconds
+    pub tier_lock_periods: [i64; MAX_STAKE_TIERS],
+    /// Shortest lock period a stake may request, in seconds
+    pub min_lock_period: i64,
+    /// Longest lock period a stake may request, in seconds
+    pub max_lock_period: i64,
+    /// Minimum agent service fee
+    pub min_service_fee: u64,
+    /// Minimum MCP tool fee
+    pub min_tool_fee: u64,
+    /// Minimum MCP resource fee
+    pub min_resource_fee: u64,
+    /// Minimum MCP prompt fee
+    pub min_prompt_fee: u64,
+    /// Maximum bulk discount, in percent
+    pub max_bulk_discount: u8,
+    /// Lowest priority multiplier (100 = 1.0x)
+    pub min_priority_multiplier: u16,
+    /// Highest priority multiplier (100 = 1.0x)
+    pub max_priority_multiplier: u16,
+    /// Protocol take-rate on collected fees, in basis points, paid into the
+    /// treasury vault
+    pub protocol_fee_bps: u16,
+    /// Seconds between an entry raising its fees and the raise applying;
+    /// decreases apply immediately
+    pub fee_increase_notice: i64,
+}
+
+impl RegistryConfigParams {
+    /// Serialized length
+    pub const LEN: usize = 32 + 32 + 8 + 8 * MAX_STAKE_TIERS * 2 + 8 + 8 + 8 * 4 + 1 + 2 + 2 + 2 + 8;
+
+    /// The agent registry's compile-time defaults
+    pub fn agent_defaults(accepted_mint: Pubkey, treasury: Pubkey) -> Self {
+        Self {
+            registration_fee: AGENT_REGISTRATION_FEE,
+            tier_thresholds: DEFAULT_AGENT_TIER_THRESHOLDS,
+            tier_lock_periods: DEFAULT_AGENT_TIER_LOCK_PERIODS,
+            ..Self::common_defaults(accepted_mint, treasury)
+        }
+    }
+
+    /// The MCP server registry's compile-time defaults
+    pub fn mcp_defaults(accepted_mint: Pubkey, treasury: Pubkey) -> Self {
+        Self {
+            registration_fee: MCP_REGISTRATION_FEE,
+            tier_thresholds: DEFAULT_SERVER_TIER_THRESHOLDS,
+            tier_lock_periods: [MIN_LOCK_PERIOD; MAX_STAKE_TIERS],
+            ..Self::common_defaults(accepted_mint, treasury)
+        }
+    }
+
+    fn common_defaults(accepted_mint: Pubkey, treasury: Pubkey) -> Self {
+        Self {
+            accepted_mint,
+            treasury,
+            registration_fee: 0,
+            tier_thresholds: [u64::MAX; MAX_STAKE_TIERS],
+            tier_lock_periods: [0; MAX_STAKE_TIERS],
+            min_lock_period: MIN_LOCK_PERIOD,
+            max_lock_period: MAX_LOCK_PERIOD,
+            min_service_fee: MIN_SERVICE_FEE,
+            min_tool_fee: MIN_TOOL_FEE,
+            min_resource_fee: MIN_RESOURCE_FEE,
+            min_prompt_fee: MIN_PROMPT_FEE,
+            max_bulk_discount: MAX_BULK_DISCOUNT,
+            min_priority_multiplier: MIN_PRIORITY_MULTIPLIER,
+            max_priority_multiplier: MAX_PRIORITY_MULTIPLIER,
+            protocol_fee_bps: DEFAULT_PROTOCOL_FEE_BPS,
+            fee_increase_notice: DEFAULT_FEE_INCREASE_NOTICE,
+        }
+    }
+
+    /// Reject parameter sets the processors could not apply consistently
+    pub fn validate(&self) -> Result<(), RegistryError> {
+        let ascending = |w: &[u64]| w[0] <= w[1];
+        if self.tier_thresholds[0] == 0 || !self.tier_thresholds.windows(2).all(ascending) {
+            return Err(RegistryError::InvalidRegistryConfig);
+        }
+        if self.min_lock_period < 0
+            || self.min_lock_period > self.max_lock_period
+            || self.tier_lock_periods.iter().any(|&p| p < 0)
+        {
+            return Err(RegistryError::InvalidRegistryConfig);
+        }
+        if self.max_bulk_discount > 100
+            || self.min_priority_multiplier == 0
+            || self.min_priority_multiplier > self.max_priority_multiplier
+            || self.protocol_fee_bps > MAX_PROTOCOL_FEE_BPS
+            || self.fee_increase_notice < 0
+        {
+            return Err(RegistryError::InvalidRegistryConfig);
+        }
+        if self.accepted_mint == Pubkey::default() || self.treasury == Pubkey::default() {
+            return Err(RegistryError::InvalidRegistryConfig);
+        }
+        Ok(())
+    }
+
+    /// Number of tiers whose threshold `amount` reaches
+    pub fn tiers_reached(&self, amount: u64) -> usize {
+        self.tier_thresholds
+            .iter()
+            .take_while(|&&threshold| amount >= threshold)
+            .count()
+    }
+
+    /// Check a requested lock period against the configured bounds
+    pub fn check_lock_period(&self, lock_period: i64) -> Result<(), RegistryError> {
+        if lock_period < self.min_lock_period || lock_period > self.max_lock_period {
+            return Err(RegistryError::InvalidLockPeriod);
+        }
+        Ok(())
+    }
+}
+
+/// Registry config account, stored at [`get_registry_config_pda`]
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq)]
+pub struct RegistryConfigV1 {
+    /// Bump seed used for the config PDA derivation
+    pub bump: u8,
+    /// Authority allowed to update the config
+    pub admin_authority: Pubkey,
+    /// Current parameters
+    pub params: RegistryConfigParams,
+    /// Timestamp of the last update
+    pub last_update_timestamp: i64,
+}
+
+impl RegistryConfigV1 {
+    /// Serialized length
+    pub const LEN: usize = 1 + 32 + RegistryConfigParams::LEN + 8;
+
+    /// Load the config of `program_id` from `config_info`, checking its
+    /// address and owner
+    pub fn load(config_info: &AccountInfo, program_id: &Pubkey) -> Result<Self, ProgramError> {
+        let (expected_config, _) = get_registry_config_pda(program_id);
+        if config_info.key != &expected_config {
+            return Err(RegistryError::InvalidPda.into());
+        }
+        verify_account_owner(config_info, program_id)?;
+        Self::try_from_slice(&config_info.try_borrow_data()?)
+            .map_err(|_| ProgramError::InvalidAccountData)
+    }
+
+    /// Reject a change of accepted mint once the vaults exist; they hold the
+    /// current mint and cannot be recreated. `staking_vault_info` must be the
+    /// staking vault PDA of `program_id`; the vaults are created together.
+    pub fn check_mint_change(
+        &self,
+        params: &RegistryConfigParams,
+        staking_vault_info: &AccountInfo,
+        program_id: &Pubkey,
+    ) -> Result<(), RegistryError> {
+        if staking_vault_info.key != &derive_staking_vault_pda(program_id).0 {
+            return Err(RegistryError::InvalidPda);
+        }
+        if params.accepted_mint != self.params.accepted_mint && !staking_vault_info.data_is_empty() {
+            return Err(RegistryError::AcceptedMintLocked);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_program::program_pack::Pack;
+
+    fn params() -> RegistryConfigParams {
+        RegistryConfigParams::agent_defaults(Pubkey::new_unique(), Pubkey::new_unique())
+    }
+
+    #[test]
+    fn test_config_len_matches_borsh() {
+        let config = RegistryConfigV1 {
+            bump: 255,
+            admin_authority: Pubkey::new_unique(),
+            params: params(),
+            last_update_timestamp: 0,
+        };
+        assert_eq!(config.try_to_vec().unwrap().len(), RegistryConfigV1::LEN);
+    }
+
+    #[test]
+    fn test_validate_params() {
+        assert!(params().validate().is_ok());
+        assert!(RegistryConfigParams::mcp_defaults(Pubkey::new_unique(), Pubkey::new_unique())
+            .validate()
+            .is_ok());
+
+        let descending = RegistryConfigParams {
+            tier_thresholds: [10, 5, 20, 30],
+            ..params()
+        };
+        assert_eq!(descending.validate(), Err(RegistryError::InvalidRegistryConfig));
+
+        let inverted_locks = RegistryConfigParams {
+            min_lock_period: 10,
+            max_lock_period: 5,
+            ..params()
+        };
+        assert_eq!(inverted_locks.validate(), Err(RegistryError::InvalidRegistryConfig));
+
+        let no_mint = RegistryConfigParams {
+            accepted_mint: Pubkey::default(),
+            ..params()
+        };
+        assert_eq!(no_mint.validate(), Err(RegistryError::InvalidRegistryConfig));
+
+        let greedy = RegistryConfigParams {
+            protocol_fee_bps: MAX_PROTOCOL_FEE_BPS + 1,
+            ..params()
+        };
+        assert_eq!(greedy.validate(), Err(RegistryError::InvalidRegistryConfig));
+
+        let backdated = RegistryConfigParams {
+            fee_increase_notice: -1,
+            ..params()
+        };
+        assert_eq!(backdated.validate(), Err(RegistryError::InvalidRegistryConfig));
+    }
+
+    #[test]
+    fn test_mint_fixed_once_vaults_exist() {
+        let program_id = Pubkey::new_unique();
+        let config = RegistryConfigV1 {
+            bump: 255,
+            admin_authority: Pubkey::new_unique(),
+            params: params(),
+            last_update_timestamp: 0,
+        };
+        let new_mint = RegistryConfigParams {
+            accepted_mint: Pubkey::new_unique(),
+            ..config.params
+        };
+        let vault = derive_staking_vault_pda(&program_id).0;
+        let token_program = spl_token::id();
+        let mut lamports = 0;
+        let mut data = vec![0; spl_token::state::Account::LEN];
+        let mut no_data = vec![];
+
+        let missing = AccountInfo::new(&vault, false, false, &mut lamports, &mut no_data, &token_program, false, 0);
+        assert_eq!(config.check_mint_change(&new_mint, &missing, &program_id), Ok(()));
+
+        let mut lamports = 1;
+        let created = AccountInfo::new(&vault, false, false, &mut lamports, &mut data, &token_program, false, 0);
+        assert_eq!(
+            config.check_mint_change(&new_mint, &created, &program_id),
+            Err(RegistryError::AcceptedMintLocked)
+        );
+        assert_eq!(config.check_mint_change(&config.params, &created, &program_id), Ok(()));
+        assert_eq!(
+            config.check_mint_change(&config.params, &created, &Pubkey::new_unique()),
+            Err(RegistryError::InvalidPda)
+        );
+    }
+
+    #[test]
+    fn test_tiers_reached() {
+        let params = RegistryConfigParams {
+            tier_thresholds: [100, 200, 300, u64::MAX],
+            ..params()
+        };
+        assert_eq!(params.tiers_reached(99), 0);
+        assert_eq!(params.tiers_reached(100), 1);
+        assert_eq!(params.tiers_reached(299), 2);
+        assert_eq!(params.tiers_reached(u64::MAX - 1), 3);
+    }
+}
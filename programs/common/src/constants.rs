@@ -47,6 +47,8 @@ pub const MAX_EXTENDED_METADATA_URI_LEN: usize = 256;
 pub const MAX_AGENT_TAGS: usize = 10;
 /// Maximum allowed length for agent tags
 pub const MAX_AGENT_TAG_LEN: usize = 32;
+/// Maximum number of overflow skill accounts linked to an agent entry
+pub const MAX_SKILL_ACCOUNTS: usize = 64;
 
 /// ## MCP Server Registry Constants
 /// Maximum allowed length for MCP server identifiers
@@ -67,6 +69,8 @@ pub const MAX_TOOL_NAME_LEN: usize = 64;
 pub const MAX_TOOL_TAGS: usize = 3;
 /// Maximum allowed length for tool tags
 pub const MAX_TOOL_TAG_LEN: usize = 32;
+/// Maximum number of overflow tool accounts linked to an MCP server entry
+pub const MAX_TOOL_ACCOUNTS: usize = 64;
 /// Maximum number of on-chain resource definitions per MCP server
 pub const MAX_ONCHAIN_RESOURCE_DEFINITIONS: usize = 5;
 /// Maximum allowed length for resource URI patterns
@@ -102,6 +106,23 @@ pub const OPTION_DISCRIMINATOR_SIZE: usize = 1;
 pub const AGENT_REGISTRY_PDA_SEED: &[u8] = b"agent_reg_v1";
 /// Seed prefix used to derive MCP Server Registry PDAs
 pub const MCP_SERVER_REGISTRY_PDA_SEED: &[u8] = b"mcp_srv_reg_v1";
+/// Seed prefix used to derive overflow skill accounts of an agent entry
+pub const SKILL_ACCOUNT_SEED: &[u8] = b"skill";
+/// Seed prefix used to derive overflow tool accounts of an MCP server entry
+pub const TOOL_ACCOUNT_SEED: &[u8] = b"tool";
+/// Seed prefix used to derive the per-item pricing table of an MCP server entry
+pub const PRICING_TABLE_SEED: &[u8] = b"pricing";
+/// Most items a pricing table can price: every inline tool, resource and prompt
+pub const MAX_PRICED_ITEMS: usize =
+    MAX_ONCHAIN_TOOL_DEFINITIONS + MAX_ONCHAIN_RESOURCE_DEFINITIONS + MAX_ONCHAIN_PROMPT_DEFINITIONS;
+/// Seed prefix used to derive the subscription plans of an MCP server entry
+pub const SUBSCRIPTION_PLAN_SEED: &[u8] = b"subscription_plan";
+/// Seed prefix used to derive a user's subscription to an MCP server entry
+pub const SUBSCRIPTION_SEED: &[u8] = b"subscription";
+/// Seed prefix used to derive a user's usage record on an MCP server entry
+pub const USAGE_RECORD_SEED: &[u8] = b"usage_record";
+/// Seed of the registry config PDA (one per registry program)
+pub const REGISTRY_CONFIG_SEED: &[u8] = b"registry_config";
 
 /// ## Status Enums
 /// Agent status values
@@ -186,6 +207,22 @@ pub const MIN_TOOL_FEE: u64 = 1_000_000_000; // 1 A2AMPL
 pub const MIN_RESOURCE_FEE: u64 = 500_000_000; // 0.5 A2AMPL
 pub const MIN_PROMPT_FEE: u64 = 2_000_000_000; // 2 A2AMPL
 pub const MAX_BULK_DISCOUNT: u8 = 50; // 50% maximum discount
+pub const MAX_DISCOUNT_TIERS: usize = 4;
+pub const DEFAULT_FEE_INCREASE_NOTICE: i64 = 3 * 24 * 60 * 60; // 3 days before a raised fee applies
+
+// Subscription plan limits
+pub const MIN_SUBSCRIPTION_PERIOD: i64 = 24 * 60 * 60; // 1 day
+pub const MAX_SUBSCRIPTION_PERIOD: i64 = 366 * 24 * 60 * 60; // 1 year
+
+// Free tier limits
+pub const MIN_FREE_TIER_PERIOD: i64 = 60 * 60; // 1 hour
+pub const MAX_FREE_TIER_PERIOD: i64 = 366 * 24 * 60 * 60; // 1 year
+
+// Protocol take-rate and revenue splits
+pub const BPS_DENOMINATOR: u64 = 10_000; // 100%
+pub const DEFAULT_PROTOCOL_FEE_BPS: u16 = 0; // No take-rate until governance sets one
+pub const MAX_PROTOCOL_FEE_BPS: u16 = 2_000; // 20% maximum take-rate
+pub const MAX_SPLIT_RECIPIENTS: usize = 8;
 
 // Quality metrics
 pub const QUALITY_UPDATE_INTERVAL: i64 = 24 * 60 * 60; // 24 hours
@@ -207,8 +244,18 @@ pub const A2AMPL_TOKEN_MINT_DEVNET: &str = "A2AMPLyncKHwfSnwRNsJ2qsjsetgo9fGkP8Y
 pub const STAKING_VAULT_SEED: &[u8] = b"staking_vault";
 pub const FEE_VAULT_SEED: &[u8] = b"fee_vault";
 pub const REGISTRATION_VAULT_SEED: &[u8] = b"registration_vault";
+pub const TREASURY_VAULT_SEED: &[u8] = b"treasury_vault";
+pub const REVENUE_SPLIT_SEED: &[u8] = b"revenue_split";
+pub const USAGE_RECEIPT_SEED: &[u8] = b"usage_receipt";
+
+/// Seed of the PDA an escrow program signs settlements with, derived under
+/// the escrow program's ID; it owns the escrow's token accounts
+pub const ESCROW_AUTHORITY_SEED: &[u8] = b"escrow_authority";
+
+/// Length of the client-generated idempotency key of a fee-charging instruction
+pub const IDEMPOTENCY_KEY_LEN: usize = 16;
 
 // Authorized External Program IDs
 // TODO: Replace these placeholder program IDs with actual production program IDs
-pub const AUTHORIZED_ESCROW_PROGRAM_ID: &str = "11111111111111111111111111111111";
+pub const AUTHORIZED_ESCROW_PROGRAM_ID: &str = "Escrow1111111111111111111111111111111111111";
 pub const AUTHORIZED_DDR_PROGRAM_ID: &str = "11111111111111111111111111111111";
\ No newline at end of file
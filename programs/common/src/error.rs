@@ -152,6 +152,68 @@ pub enum RegistryError {
     ProgramSignatureVerificationFailed,
     #[error("Cross-program invocation authority mismatch")]
     CpiAuthorityMismatch,
+
+    // Overflow Account Errors
+    #[error("Too many overflow skill accounts")]
+    TooManySkillAccounts,
+    #[error("Too many overflow tool accounts")]
+    TooManyToolAccounts,
+    #[error("Overflow account index is out of sequence")]
+    InvalidOverflowIndex,
+
+    // Chunked Registration Errors
+    #[error("Entry is not accepting registration chunks")]
+    RegistrationNotOpen,
+    #[error("Entry registration has not been finalized")]
+    RegistrationNotFinalized,
+
+    // Registry Config Errors
+    #[error("Registry config parameters are inconsistent")]
+    InvalidRegistryConfig,
+
+    // Token Account Validation Errors
+    #[error("Token program account is not the SPL Token program")]
+    InvalidTokenProgram,
+    #[error("Token account does not hold the accepted mint")]
+    TokenAccountMintMismatch,
+    #[error("Token account does not belong to the signer")]
+    TokenAccountOwnerMismatch,
+    #[error("Vault token account is not owned by its vault PDA")]
+    InvalidVaultAuthority,
+
+    // Fee Accounting Errors
+    #[error("Fee vault balance is below the fees owed from it")]
+    FeeVaultUndercollateralized,
+    #[error("Revenue split recipients are invalid or exceed 100%")]
+    InvalidRevenueSplit,
+
+    // Pricing Errors
+    #[error("Priced item is not among the server's on-chain definitions")]
+    UnknownPricedItem,
+    #[error("Pricing table has duplicate items or invalid discount tiers")]
+    InvalidPricingTable,
+    #[error("Fee exceeds the maximum the caller agreed to")]
+    MaxFeeExceeded,
+    #[error("A charge with this idempotency key was already recorded")]
+    DuplicateIdempotencyKey,
+
+    // Subscription Errors
+    #[error("Subscription plan has no price, no included calls or an invalid period")]
+    InvalidSubscriptionPlan,
+    #[error("Subscription plan does not accept subscriptions or renewals")]
+    SubscriptionPlanInactive,
+    #[error("User already holds a subscription to this server")]
+    SubscriptionAlreadyExists,
+    #[error("Free tier allows calls without a valid period")]
+    InvalidFreeTier,
+
+    // Registry Config Errors
+    #[error("Accepted mint cannot change once the vaults exist")]
+    AcceptedMintLocked,
+
+    // Entry Layout Errors
+    #[error("Entry is stored in an older layout and must be migrated first")]
+    UnsupportedEntryVersion,
 }
 
 impl From<RegistryError> for ProgramError {
@@ -1,19 +1,27 @@
 //! Common utilities and types for the Solana AI Registries
 
 pub mod authority;
+pub mod config;
 pub mod constants;
 pub mod error;
+pub mod receipt;
+pub mod revenue;
 pub mod serialization;
 pub mod token_utils;
 pub mod utils;
+pub mod zero_copy;
 
 // Re-export commonly used items
 pub use authority::*;
+pub use config::*;
 pub use constants::*;
 pub use error::*;
+pub use receipt::*;
+pub use revenue::*;
 pub use serialization::*;
 pub use token_utils::*;
 pub use utils::*;
+pub use zero_copy::*;
 
 /// Status of an agent in the Agent Registry
 #[repr(u8)]
@@ -0,0 +1,138 @@
+//! Slippage guard and idempotency receipts for fee-charging instructions
+//!
+//! A caller paying a fee names the most it agrees to pay, so a fee raised
+//! between quote and send fails the charge instead of overcharging. Each
+//! charge also carries a client-generated idempotency key; a
+//! [`UsageReceiptV1`] is created at the key's PDA, so a retried transaction
+//! that reuses the key is rejected rather than charged twice.
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use solana_program::{account_info::AccountInfo, entrypoint::ProgramResult, pubkey::Pubkey};
+use crate::{
+    constants::*,
+    error::RegistryError,
+    utils::{create_pda_account, get_current_timestamp},
+};
+
+/// Derive the receipt PDA of a charge by `caller` against `entry`
+pub fn derive_usage_receipt_pda(
+    entry: &Pubkey,
+    caller: &Pubkey,
+    idempotency_key: &[u8; IDEMPOTENCY_KEY_LEN],
+    program_id: &Pubkey,
+) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[USAGE_RECEIPT_SEED, entry.as_ref(), caller.as_ref(), idempotency_key],
+        program_id,
+    )
+}
+
+/// Reject a charge of `total_fee` above the caller's `max_total_fee`
+pub fn check_max_total_fee(total_fee: u64, max_total_fee: u64) -> Result<(), RegistryError> {
+    if total_fee > max_total_fee {
+        return Err(RegistryError::MaxFeeExceeded);
+    }
+    Ok(())
+}
+
+/// Record of one charge, stored at [`derive_usage_receipt_pda`]
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq)]
+pub struct UsageReceiptV1 {
+    /// Bump seed used for the PDA derivation
+    pub bump: u8,
+    /// Entry that was charged for
+    pub entry: Pubkey,
+    /// Account that authorized the charge
+    pub caller: Pubkey,
+    /// Client-generated key of the charge
+    pub idempotency_key: [u8; IDEMPOTENCY_KEY_LEN],
+    /// Amount charged
+    pub amount: u64,
+    /// Timestamp of the charge
+    pub timestamp: i64,
+}
+
+impl UsageReceiptV1 {
+    /// Serialized length
+    pub const LEN: usize = 1 + 32 + 32 + IDEMPOTENCY_KEY_LEN + 8 + 8;
+}
+
+/// Create the receipt of a charge, failing with `DuplicateIdempotencyKey`
+/// if `caller` already used `idempotency_key` against `entry`
+#[allow(clippy::too_many_arguments)]
+pub fn record_usage_receipt<'a>(
+    receipt_info: &AccountInfo<'a>,
+    payer_info: &AccountInfo<'a>,
+    system_program_info: &AccountInfo<'a>,
+    entry: &Pubkey,
+    caller: &Pubkey,
+    idempotency_key: &[u8; IDEMPOTENCY_KEY_LEN],
+    amount: u64,
+    program_id: &Pubkey,
+) -> ProgramResult {
+    let (expected_receipt, bump) = derive_usage_receipt_pda(entry, caller, idempotency_key, program_id);
+    if receipt_info.key != &expected_receipt {
+        return Err(RegistryError::InvalidPda.into());
+    }
+    if !receipt_info.data_is_empty() {
+        return Err(RegistryError::DuplicateIdempotencyKey.into());
+    }
+
+    create_pda_account(
+        payer_info,
+        receipt_info,
+        system_program_info,
+        program_id,
+        UsageReceiptV1::LEN,
+        &[USAGE_RECEIPT_SEED, entry.as_ref(), caller.as_ref(), idempotency_key, &[bump]],
+    )?;
+    let receipt = UsageReceiptV1 {
+        bump,
+        entry: *entry,
+        caller: *caller,
+        idempotency_key: *idempotency_key,
+        amount,
+        timestamp: get_current_timestamp()?,
+    };
+    receipt.serialize(&mut &mut receipt_info.try_borrow_mut_data()?[..])?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_receipt_len_matches_borsh() {
+        let receipt = UsageReceiptV1 {
+            bump: 255,
+            entry: Pubkey::new_unique(),
+            caller: Pubkey::new_unique(),
+            idempotency_key: [7; IDEMPOTENCY_KEY_LEN],
+            amount: 42,
+            timestamp: 0,
+        };
+        assert_eq!(receipt.try_to_vec().unwrap().len(), UsageReceiptV1::LEN);
+    }
+
+    #[test]
+    fn test_receipts_are_per_key_and_caller() {
+        let program_id = Pubkey::new_unique();
+        let entry = Pubkey::new_unique();
+        let caller = Pubkey::new_unique();
+        let (first, _) = derive_usage_receipt_pda(&entry, &caller, &[1; IDEMPOTENCY_KEY_LEN], &program_id);
+        let (retry, _) = derive_usage_receipt_pda(&entry, &caller, &[1; IDEMPOTENCY_KEY_LEN], &program_id);
+        let (next, _) = derive_usage_receipt_pda(&entry, &caller, &[2; IDEMPOTENCY_KEY_LEN], &program_id);
+        let (other, _) =
+            derive_usage_receipt_pda(&entry, &Pubkey::new_unique(), &[1; IDEMPOTENCY_KEY_LEN], &program_id);
+        assert_eq!(first, retry);
+        assert_ne!(first, next);
+        assert_ne!(first, other);
+    }
+
+    #[test]
+    fn test_max_total_fee() {
+        assert!(check_max_total_fee(100, 100).is_ok());
+        assert_eq!(check_max_total_fee(101, 100), Err(RegistryError::MaxFeeExceeded));
+    }
+}
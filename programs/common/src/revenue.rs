@@ -0,0 +1,311 @@
+//! Protocol take-rate and per-entry revenue splits
+//!
+//! Fees an entry earns are divided in two steps. The protocol's share,
+//! `protocol_fee_bps` of the registry config, goes to the treasury vault when
+//! the fee is collected. What the entry keeps can be shared with up to
+//! [`MAX_SPLIT_RECIPIENTS`] co-builders through a [`RevenueSplitV1`] account,
+//! applied when the earnings are paid out. The owner receives the remainder,
+//! including any rounding dust. A share that cannot be paid because the
+//! recipient's token account is closed or frozen can be held back and owed to
+//! that recipient instead of failing the payout.
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    program_error::ProgramError,
+    program_pack::Pack,
+    pubkey::Pubkey,
+};
+use crate::{
+    config::RegistryConfigParams,
+    constants::*,
+    error::RegistryError,
+    token_utils::{transfer_tokens_with_pda_signer_account_info, verify_user_token_account},
+    utils::verify_account_owner,
+};
+
+/// Share of `amount` given by `bps` basis points, rounded down
+pub fn bps_share(amount: u64, bps: u16) -> u64 {
+    (amount as u128 * bps as u128 / BPS_DENOMINATOR as u128) as u64
+}
+
+/// Derive the revenue split PDA of a registry entry
+pub fn derive_revenue_split_pda(entry: &Pubkey, program_id: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[REVENUE_SPLIT_SEED, entry.as_ref()], program_id)
+}
+
+/// One recipient of a revenue split
+#[derive(BorshSerialize, BorshDeserialize, Clone, Copy, Debug, Default, PartialEq)]
+pub struct SplitRecipient {
+    /// Wallet whose token account receives the share
+    pub recipient: Pubkey,
+    /// Share of the payout, in basis points
+    pub bps: u16,
+}
+
+/// Revenue split table of a registry entry, stored at [`derive_revenue_split_pda`]
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq)]
+pub struct RevenueSplitV1 {
+    /// Bump seed used for the PDA derivation
+    pub bump: u8,
+    /// Entry the split belongs to
+    pub entry: Pubkey,
+    /// Number of recipients in use
+    pub recipient_count: u8,
+    /// Recipients, only the first `recipient_count` are used
+    pub recipients: [SplitRecipient; MAX_SPLIT_RECIPIENTS],
+    /// Shares held back for each recipient, in table order, because its
+    /// token account could not be paid. They stay in the payout source.
+    pub owed: [u64; MAX_SPLIT_RECIPIENTS],
+}
+
+impl RevenueSplitV1 {
+    /// Serialized length
+    pub const LEN: usize = 1 + 32 + 1 + (32 + 2 + 8) * MAX_SPLIT_RECIPIENTS;
+
+    /// Build a split table, rejecting more than [`MAX_SPLIT_RECIPIENTS`]
+    /// recipients, zero shares, duplicates or shares above 100%
+    pub fn new(bump: u8, entry: Pubkey, recipients: &[SplitRecipient]) -> Result<Self, RegistryError> {
+        if recipients.len() > MAX_SPLIT_RECIPIENTS {
+            return Err(RegistryError::InvalidRevenueSplit);
+        }
+        let total_bps: u64 = recipients.iter().map(|r| r.bps as u64).sum();
+        if total_bps > BPS_DENOMINATOR || recipients.iter().any(|r| r.bps == 0) {
+            return Err(RegistryError::InvalidRevenueSplit);
+        }
+        for (i, recipient) in recipients.iter().enumerate() {
+            if recipients[..i].iter().any(|r| r.recipient == recipient.recipient) {
+                return Err(RegistryError::InvalidRevenueSplit);
+            }
+        }
+
+        let mut table = [SplitRecipient::default(); MAX_SPLIT_RECIPIENTS];
+        table[..recipients.len()].copy_from_slice(recipients);
+        Ok(Self {
+            bump,
+            entry,
+            recipient_count: recipients.len() as u8,
+            recipients: table,
+            owed: [0; MAX_SPLIT_RECIPIENTS],
+        })
+    }
+
+    /// Keep what `previous` owes its recipients in this replacement table,
+    /// rejecting a table that drops a recipient who is still owed
+    pub fn carry_owed(mut self, previous: &RevenueSplitV1) -> Result<Self, RegistryError> {
+        for (recipient, &owed) in previous.active_recipients().iter().zip(&previous.owed) {
+            if owed == 0 {
+                continue;
+            }
+            let index = self
+                .active_recipients()
+                .iter()
+                .position(|r| r.recipient == recipient.recipient)
+                .ok_or(RegistryError::InvalidRevenueSplit)?;
+            self.owed[index] = owed;
+        }
+        Ok(self)
+    }
+
+    /// Shares held back for all recipients
+    pub fn total_owed(&self) -> u64 {
+        self.owed.iter().sum()
+    }
+
+    /// Recipients in use
+    pub fn active_recipients(&self) -> &[SplitRecipient] {
+        &self.recipients[..(self.recipient_count as usize).min(MAX_SPLIT_RECIPIENTS)]
+    }
+
+    /// Divide `amount` into one share per recipient and the owner's remainder
+    pub fn payouts(&self, amount: u64) -> (Vec<u64>, u64) {
+        let shares: Vec<u64> = self
+            .active_recipients()
+            .iter()
+            .map(|r| bps_share(amount, r.bps))
+            .collect();
+        let owner_amount = amount - shares.iter().sum::<u64>();
+        (shares, owner_amount)
+    }
+
+    /// Load the split of `entry` from `split_info`, or `None` if the entry
+    /// never set one
+    pub fn load(
+        split_info: &AccountInfo,
+        entry: &Pubkey,
+        program_id: &Pubkey,
+    ) -> Result<Option<Self>, ProgramError> {
+        let (expected_split, _) = derive_revenue_split_pda(entry, program_id);
+        if split_info.key != &expected_split {
+            return Err(RegistryError::InvalidPda.into());
+        }
+        if split_info.data_is_empty() {
+            return Ok(None);
+        }
+        verify_account_owner(split_info, program_id)?;
+        Self::try_from_slice(&split_info.try_borrow_data()?)
+            .map(Some)
+            .map_err(|_| ProgramError::InvalidAccountData)
+    }
+}
+
+/// Whether `token_account_info` can be paid into: it is an initialized token
+/// account that is not frozen
+fn is_payable(token_account_info: &AccountInfo) -> bool {
+    if token_account_info.owner != &spl_token::id() {
+        return false;
+    }
+    match token_account_info.try_borrow_data() {
+        Ok(data) => spl_token::state::Account::unpack(&data).is_ok_and(|account| !account.is_frozen()),
+        Err(_) => false,
+    }
+}
+
+/// Pay `amount` out of `source_info`. With a split, each recipient's share,
+/// plus anything it is owed, goes to the next account of `recipient_infos`,
+/// which must be that recipient's token account, in table order. The
+/// remainder goes to `owner_token_info`.
+///
+/// With `hold_back_unpayable`, a recipient whose token account is closed or
+/// frozen does not block the payout: its share is added to `split.owed` and
+/// stays in `source_info`, and the caller must store the split. Otherwise such
+/// an account fails the payout. Returns what each recipient was paid and the
+/// owner's amount.
+#[allow(clippy::too_many_arguments)]
+pub fn pay_out_with_split<'a, 'b>(
+    source_info: &AccountInfo<'b>,
+    authority_info: &AccountInfo<'b>,
+    token_program_info: &AccountInfo<'b>,
+    owner_token_info: &AccountInfo<'b>,
+    split: Option<&mut RevenueSplitV1>,
+    hold_back_unpayable: bool,
+    recipient_infos: &mut std::slice::Iter<'a, AccountInfo<'b>>,
+    config: &RegistryConfigParams,
+    amount: u64,
+    signer_seeds: &[&[&[u8]]],
+) -> Result<(Vec<u64>, u64), ProgramError> {
+    let (shares, owner_amount) = match split.as_deref() {
+        Some(split) => split.payouts(amount),
+        None => (Vec::new(), amount),
+    };
+
+    let mut paid = Vec::with_capacity(shares.len());
+    if let Some(split) = split {
+        for (index, &share) in shares.iter().enumerate() {
+            let recipient_token_info = next_account_info(recipient_infos)?;
+            if hold_back_unpayable && !is_payable(recipient_token_info) {
+                split.owed[index] += share;
+                paid.push(0);
+                continue;
+            }
+            verify_user_token_account(recipient_token_info, &split.recipients[index].recipient, config)?;
+
+            let due = share + split.owed[index];
+            if due > 0 {
+                transfer_tokens_with_pda_signer_account_info(
+                    source_info,
+                    recipient_token_info,
+                    authority_info,
+                    token_program_info,
+                    due,
+                    signer_seeds,
+                )?;
+            }
+            split.owed[index] = 0;
+            paid.push(due);
+        }
+    }
+
+    if owner_amount > 0 {
+        transfer_tokens_with_pda_signer_account_info(
+            source_info,
+            owner_token_info,
+            authority_info,
+            token_program_info,
+            owner_amount,
+            signer_seeds,
+        )?;
+    }
+
+    Ok((paid, owner_amount))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn recipient(bps: u16) -> SplitRecipient {
+        SplitRecipient {
+            recipient: Pubkey::new_unique(),
+            bps,
+        }
+    }
+
+    #[test]
+    fn test_split_len_matches_borsh() {
+        let split = RevenueSplitV1::new(255, Pubkey::new_unique(), &[recipient(100)]).unwrap();
+        assert_eq!(split.try_to_vec().unwrap().len(), RevenueSplitV1::LEN);
+    }
+
+    #[test]
+    fn test_split_validation() {
+        let entry = Pubkey::new_unique();
+        assert!(RevenueSplitV1::new(0, entry, &[]).is_ok());
+        assert!(RevenueSplitV1::new(0, entry, &[recipient(6_000), recipient(4_000)]).is_ok());
+        assert_eq!(
+            RevenueSplitV1::new(0, entry, &[recipient(6_000), recipient(4_001)]),
+            Err(RegistryError::InvalidRevenueSplit)
+        );
+        assert_eq!(
+            RevenueSplitV1::new(0, entry, &[recipient(0)]),
+            Err(RegistryError::InvalidRevenueSplit)
+        );
+
+        let duplicate = recipient(100);
+        assert_eq!(
+            RevenueSplitV1::new(0, entry, &[duplicate, duplicate]),
+            Err(RegistryError::InvalidRevenueSplit)
+        );
+        assert_eq!(
+            RevenueSplitV1::new(0, entry, &[recipient(1); MAX_SPLIT_RECIPIENTS + 1]),
+            Err(RegistryError::InvalidRevenueSplit)
+        );
+    }
+
+    #[test]
+    fn test_payouts_give_dust_to_owner() {
+        let split = RevenueSplitV1::new(
+            0,
+            Pubkey::new_unique(),
+            &[recipient(3_333), recipient(3_333)],
+        )
+        .unwrap();
+        let (shares, owner_amount) = split.payouts(100);
+        assert_eq!(shares, vec![33, 33]);
+        assert_eq!(owner_amount, 34);
+
+        assert_eq!(bps_share(1_000, 250), 25);
+        assert_eq!(bps_share(u64::MAX, 10_000), u64::MAX);
+    }
+
+    #[test]
+    fn test_replacement_keeps_owed_shares() {
+        let entry = Pubkey::new_unique();
+        let (kept, dropped) = (recipient(1_000), recipient(2_000));
+        let mut previous = RevenueSplitV1::new(0, entry, &[kept, dropped]).unwrap();
+        previous.owed[0] = 50;
+
+        let replacement = RevenueSplitV1::new(0, entry, &[recipient(500), kept])
+            .unwrap()
+            .carry_owed(&previous)
+            .unwrap();
+        assert_eq!(replacement.owed[..2], [0, 50]);
+        assert_eq!(replacement.total_owed(), 50);
+
+        previous.owed[1] = 10;
+        assert_eq!(
+            RevenueSplitV1::new(0, entry, &[kept]).unwrap().carry_owed(&previous),
+            Err(RegistryError::InvalidRevenueSplit)
+        );
+    }
+}
@@ -1,6 +1,7 @@
 use anchor_lang::prelude::*;
 use anchor_spl::token::{self, Token, TokenAccount, Transfer};
 use solana_program::pubkey::Pubkey;
+use crate::config::{RegistryConfigParams, DEFAULT_AGENT_TIER_THRESHOLDS};
 
 /// Token utility functions for SVMAI token integration
 /// Provides helpers for transfers, fee calculations, staking validation, and quality scores
@@ -134,14 +135,29 @@ pub enum StakingTier {
 }
 
 impl StakingTier {
-    /// Get tier from staked amount (in base units with 9 decimals)
+    /// Get tier from staked amount (in base units with 9 decimals), using the
+    /// default thresholds
     pub fn from_amount(amount: u64) -> Self {
-        match amount {
-            0..=999_999_999_999 => StakingTier::None, // < 1,000 SVMAI
-            1_000_000_000_000..=9_999_999_999_999 => StakingTier::Bronze, // 1K - 10K
-            10_000_000_000_000..=49_999_999_999_999 => StakingTier::Silver, // 10K - 50K
-            50_000_000_000_000..=99_999_999_999_999 => StakingTier::Gold, // 50K - 100K
-            _ => StakingTier::Platinum, // >= 100K
+        Self::from_tiers_reached(
+            DEFAULT_AGENT_TIER_THRESHOLDS
+                .iter()
+                .take_while(|&&threshold| amount >= threshold)
+                .count(),
+        )
+    }
+
+    /// Get tier from staked amount using a registry config's thresholds
+    pub fn from_config(amount: u64, config: &RegistryConfigParams) -> Self {
+        Self::from_tiers_reached(config.tiers_reached(amount))
+    }
+
+    fn from_tiers_reached(tiers: usize) -> Self {
+        match tiers {
+            0 => StakingTier::None,
+            1 => StakingTier::Bronze,
+            2 => StakingTier::Silver,
+            3 => StakingTier::Gold,
+            _ => StakingTier::Platinum,
         }
     }
     
@@ -155,6 +171,14 @@ impl StakingTier {
             StakingTier::Platinum => 100_000 * 1_000_000_000, // 100,000 SVMAI
         }
     }
+
+    /// Get minimum stake required for tier under a registry config
+    pub fn min_stake_in(&self, config: &RegistryConfigParams) -> u64 {
+        match self {
+            StakingTier::None => 0,
+            tier => config.tier_thresholds[tier.value() as usize - 1],
+        }
+    }
     
     /// Get lock period in seconds
     pub fn lock_period(&self) -> i64 {
@@ -166,6 +190,14 @@ impl StakingTier {
             StakingTier::Platinum => 365 * 24 * 60 * 60, // 365 days
         }
     }
+
+    /// Get lock period in seconds under a registry config
+    pub fn lock_period_in(&self, config: &RegistryConfigParams) -> i64 {
+        match self {
+            StakingTier::None => 0,
+            tier => config.tier_lock_periods[tier.value() as usize - 1],
+        }
+    }
     
     /// Get tier value as u8
     pub fn value(&self) -> u8 {
@@ -193,6 +225,19 @@ impl VerificationTier {
             _ => Ok(VerificationTier::Premium), // >= 25K
         }
     }
+
+    /// Get tier from staked amount using a registry config's thresholds
+    pub fn from_config(
+        amount: u64,
+        config: &RegistryConfigParams,
+    ) -> std::result::Result<Self, crate::error::RegistryError> {
+        match config.tiers_reached(amount) {
+            0 => Err(crate::error::RegistryError::InsufficientStake),
+            1 => Ok(VerificationTier::Basic),
+            2 => Ok(VerificationTier::Verified),
+            _ => Ok(VerificationTier::Premium),
+        }
+    }
     
     /// Get minimum stake required for tier
     pub fn min_stake(&self) -> u64 {
@@ -294,23 +339,131 @@ pub fn calculate_bulk_discount(
     }
 }
 
-/// Validate fee configuration
+/// Validate an agent's service fee configuration against the registry config
 pub fn validate_fee_config(
     base_fee: u64,
-    min_fee: u64,
     priority_multiplier: u16,
+    config: &RegistryConfigParams,
 ) -> std::result::Result<(), crate::error::RegistryError> {
-    if base_fee < min_fee {
+    if base_fee < config.min_service_fee {
         return Err(crate::error::RegistryError::FeeTooLow);
     }
     
-    if priority_multiplier < crate::constants::MIN_PRIORITY_MULTIPLIER || priority_multiplier > crate::constants::MAX_PRIORITY_MULTIPLIER {
+    if priority_multiplier < config.min_priority_multiplier || priority_multiplier > config.max_priority_multiplier {
         return Err(crate::error::RegistryError::InvalidMultiplier);
     }
     
     Ok(())
 }
 
+/// Verify that `token_program_info` is the SPL Token program
+pub fn verify_token_program(
+    token_program_info: &AccountInfo,
+) -> std::result::Result<(), crate::error::RegistryError> {
+    if token_program_info.key != &spl_token::id() {
+        return Err(crate::error::RegistryError::InvalidTokenProgram);
+    }
+    Ok(())
+}
+
+/// Verify that `mint_info` is the mint accepted by the registry config
+pub fn verify_token_mint(
+    mint_info: &AccountInfo,
+    config: &RegistryConfigParams,
+) -> std::result::Result<(), crate::error::RegistryError> {
+    if mint_info.key != &config.accepted_mint {
+        return Err(crate::error::RegistryError::InvalidTokenMint);
+    }
+    Ok(())
+}
+
+/// Unpack an SPL token account, requiring that it is owned by the token program
+fn unpack_token_account(
+    token_account_info: &AccountInfo,
+) -> std::result::Result<spl_token::state::Account, crate::error::RegistryError> {
+    use solana_program::program_pack::Pack;
+
+    if token_account_info.owner != &spl_token::id() {
+        return Err(crate::error::RegistryError::TokenAccountNotFound);
+    }
+    let data = token_account_info
+        .try_borrow_data()
+        .map_err(|_| crate::error::RegistryError::TokenAccountNotFound)?;
+    spl_token::state::Account::unpack(&data)
+        .map_err(|_| crate::error::RegistryError::TokenAccountNotFound)
+}
+
+/// Verify a user's token account: it must hold the accepted mint and belong
+/// to `authority`, the signer moving or receiving the tokens
+pub fn verify_user_token_account(
+    token_account_info: &AccountInfo,
+    authority: &Pubkey,
+    config: &RegistryConfigParams,
+) -> std::result::Result<spl_token::state::Account, crate::error::RegistryError> {
+    let token_account = unpack_token_account(token_account_info)?;
+    if token_account.mint != config.accepted_mint {
+        return Err(crate::error::RegistryError::TokenAccountMintMismatch);
+    }
+    if &token_account.owner != authority {
+        return Err(crate::error::RegistryError::TokenAccountOwnerMismatch);
+    }
+    Ok(token_account)
+}
+
+/// Verify a vault token account: it must sit at `expected_vault`, hold the
+/// accepted mint and be owned by the vault PDA itself, so only the program
+/// can sign transfers out of it
+pub fn verify_vault_token_account(
+    vault_info: &AccountInfo,
+    expected_vault: &Pubkey,
+    config: &RegistryConfigParams,
+) -> std::result::Result<spl_token::state::Account, crate::error::RegistryError> {
+    if vault_info.key != expected_vault {
+        return Err(crate::error::RegistryError::InvalidPda);
+    }
+    let vault = unpack_token_account(vault_info)?;
+    if vault.mint != config.accepted_mint {
+        return Err(crate::error::RegistryError::TokenAccountMintMismatch);
+    }
+    if &vault.owner != expected_vault {
+        return Err(crate::error::RegistryError::InvalidVaultAuthority);
+    }
+    Ok(vault)
+}
+
+/// Create the SPL token account for `mint` at the vault PDA `vault_info`.
+/// The vault is its own token authority, so only the program can sign
+/// transfers out of it.
+pub fn create_vault_token_account<'a>(
+    payer_info: &AccountInfo<'a>,
+    vault_info: &AccountInfo<'a>,
+    mint_info: &AccountInfo<'a>,
+    token_program_info: &AccountInfo<'a>,
+    system_program_info: &AccountInfo<'a>,
+    signer_seeds: &[&[u8]],
+) -> solana_program::entrypoint::ProgramResult {
+    use solana_program::{program::invoke, program_pack::Pack};
+
+    crate::utils::create_pda_account(
+        payer_info,
+        vault_info,
+        system_program_info,
+        &spl_token::id(),
+        spl_token::state::Account::LEN,
+        signer_seeds,
+    )?;
+
+    invoke(
+        &spl_token::instruction::initialize_account3(
+            &spl_token::id(),
+            vault_info.key,
+            mint_info.key,
+            vault_info.key,
+        )?,
+        &[vault_info.clone(), mint_info.clone(), token_program_info.clone()],
+    )
+}
+
 /// Derive PDA for token vaults
 pub fn derive_staking_vault_pda(program_id: &Pubkey) -> (Pubkey, u8) {
     Pubkey::find_program_address(&[b"staking_vault"], program_id)
@@ -324,6 +477,28 @@ pub fn derive_registration_vault_pda(program_id: &Pubkey) -> (Pubkey, u8) {
     Pubkey::find_program_address(&[b"registration_vault"], program_id)
 }
 
+/// Derive the treasury vault PDA, which collects the protocol take-rate
+pub fn derive_treasury_vault_pda(program_id: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[crate::constants::TREASURY_VAULT_SEED], program_id)
+}
+
+/// Derive the fee vault of a single MCP server entry. Each server's fees are
+/// held apart, so one entry's accounting cannot spend another's.
+pub fn derive_server_fee_vault_pda(server_entry: &Pubkey, program_id: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[crate::constants::FEE_VAULT_SEED, server_entry.as_ref()], program_id)
+}
+
+/// Check that a fee vault holds at least the fees recorded as owed from it
+pub fn check_fee_vault_solvency(
+    vault_balance: u64,
+    pending_fees: u64,
+) -> std::result::Result<(), crate::error::RegistryError> {
+    if vault_balance < pending_fees {
+        return Err(crate::error::RegistryError::FeeVaultUndercollateralized);
+    }
+    Ok(())
+}
+
 /// Check if stake lock period has expired
 pub fn is_stake_unlocked(stake_locked_until: i64, current_timestamp: i64) -> bool {
     current_timestamp >= stake_locked_until
@@ -372,6 +547,83 @@ mod tests {
         assert_eq!(StakingTier::from_amount(100_000_000_000_000), StakingTier::Platinum);
     }
 
+    #[test]
+    fn test_tiers_from_config() {
+        let mut config = RegistryConfigParams::agent_defaults(Pubkey::new_unique(), Pubkey::new_unique());
+        config.tier_thresholds = [10, 20, 30, 40];
+        config.tier_lock_periods = [1, 2, 3, 4];
+
+        assert_eq!(StakingTier::from_config(9, &config), StakingTier::None);
+        assert_eq!(StakingTier::from_config(25, &config), StakingTier::Silver);
+        assert_eq!(StakingTier::from_config(40, &config), StakingTier::Platinum);
+        assert_eq!(StakingTier::Gold.min_stake_in(&config), 30);
+        assert_eq!(StakingTier::Gold.lock_period_in(&config), 3);
+        assert_eq!(StakingTier::None.lock_period_in(&config), 0);
+
+        let config = RegistryConfigParams::mcp_defaults(Pubkey::new_unique(), Pubkey::new_unique());
+        assert!(VerificationTier::from_config(crate::constants::BASIC_SERVER_STAKE - 1, &config).is_err());
+        assert_eq!(
+            VerificationTier::from_config(crate::constants::PREMIUM_SERVER_STAKE, &config),
+            Ok(VerificationTier::Premium)
+        );
+        assert_eq!(validate_fee_config(0, 100, &config), Err(crate::error::RegistryError::FeeTooLow));
+        assert!(validate_fee_config(config.min_service_fee, 100, &config).is_ok());
+    }
+
+    #[test]
+    fn test_token_account_validation() {
+        use solana_program::program_pack::Pack;
+
+        let config = RegistryConfigParams::agent_defaults(Pubkey::new_unique(), Pubkey::new_unique());
+        let user = Pubkey::new_unique();
+        let (vault, _) = derive_staking_vault_pda(&Pubkey::new_unique());
+        let token_program = spl_token::id();
+
+        let pack = |mint: Pubkey, owner: Pubkey| {
+            let mut data = vec![0u8; spl_token::state::Account::LEN];
+            spl_token::state::Account::pack(
+                spl_token::state::Account {
+                    mint,
+                    owner,
+                    state: spl_token::state::AccountState::Initialized,
+                    ..Default::default()
+                },
+                &mut data,
+            )
+            .unwrap();
+            data
+        };
+        let check_user = |mint: Pubkey, owner: Pubkey| {
+            let key = Pubkey::new_unique();
+            let (mut lamports, mut data) = (0, pack(mint, owner));
+            let info = AccountInfo::new(&key, false, true, &mut lamports, &mut data, &token_program, false, 0);
+            verify_user_token_account(&info, &user, &config).map(|_| ())
+        };
+        let check_vault = |key: Pubkey, owner: Pubkey| {
+            let (mut lamports, mut data) = (0, pack(config.accepted_mint, owner));
+            let info = AccountInfo::new(&key, false, true, &mut lamports, &mut data, &token_program, false, 0);
+            verify_vault_token_account(&info, &vault, &config).map(|_| ())
+        };
+
+        use crate::error::RegistryError;
+        assert!(check_user(config.accepted_mint, user).is_ok());
+        assert_eq!(check_user(Pubkey::new_unique(), user), Err(RegistryError::TokenAccountMintMismatch));
+        assert_eq!(check_user(config.accepted_mint, Pubkey::new_unique()), Err(RegistryError::TokenAccountOwnerMismatch));
+        assert!(check_vault(vault, vault).is_ok());
+        assert_eq!(check_vault(Pubkey::new_unique(), vault), Err(RegistryError::InvalidPda));
+        assert_eq!(check_vault(vault, user), Err(RegistryError::InvalidVaultAuthority));
+
+        // Accounts not owned by the token program are rejected before unpacking
+        let (key, system_program) = (Pubkey::new_unique(), Pubkey::default());
+        let (mut lamports, mut data) = (0, pack(config.accepted_mint, user));
+        let info = AccountInfo::new(&key, false, true, &mut lamports, &mut data, &system_program, false, 0);
+        assert_eq!(
+            verify_user_token_account(&info, &user, &config).map(|_| ()),
+            Err(RegistryError::TokenAccountNotFound)
+        );
+        assert_eq!(verify_token_program(&info), Err(RegistryError::InvalidTokenProgram));
+    }
+
     #[test]
     fn test_quality_score_calculation() {
         // Test perfect score
@@ -405,4 +657,19 @@ mod tests {
         let fee = calculate_bulk_discount(1_000_000_000, 25, 20, 10);
         assert_eq!(fee, 22_500_000_000); // 10% discount
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_server_fee_vaults() {
+        let program_id = Pubkey::new_unique();
+        let (first, _) = derive_server_fee_vault_pda(&Pubkey::new_unique(), &program_id);
+        let (second, _) = derive_server_fee_vault_pda(&Pubkey::new_unique(), &program_id);
+        assert_ne!(first, second);
+        assert_ne!(first, derive_fee_vault_pda(&program_id).0);
+
+        assert!(check_fee_vault_solvency(100, 100).is_ok());
+        assert_eq!(
+            check_fee_vault_solvency(99, 100),
+            Err(crate::error::RegistryError::FeeVaultUndercollateralized)
+        );
+    }
+}
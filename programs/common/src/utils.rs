@@ -3,8 +3,12 @@
 use solana_program::{
     account_info::AccountInfo,
     clock::Clock,
+    entrypoint::ProgramResult,
+    program::{invoke, invoke_signed},
     program_error::ProgramError,
     pubkey::Pubkey,
+    rent::Rent,
+    system_instruction,
     sysvar::Sysvar,
     msg,
 };
@@ -101,6 +105,63 @@ pub fn get_mcp_server_pda_secure(
     )
 }
 
+/// Get the PDA of an agent entry's overflow skill account at `index`
+pub fn get_skill_account_pda(
+    agent_entry: &Pubkey,
+    index: u8,
+    program_id: &Pubkey,
+) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[SKILL_ACCOUNT_SEED, agent_entry.as_ref(), &[index]],
+        program_id,
+    )
+}
+
+/// Get the PDA of an MCP server entry's overflow tool account at `index`
+pub fn get_tool_account_pda(
+    server_entry: &Pubkey,
+    index: u8,
+    program_id: &Pubkey,
+) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[TOOL_ACCOUNT_SEED, server_entry.as_ref(), &[index]],
+        program_id,
+    )
+}
+
+/// Get the PDA of a registry program's config account
+pub fn get_registry_config_pda(program_id: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[REGISTRY_CONFIG_SEED], program_id)
+}
+
+/// Verify that `authority_info` signed and is the upgrade authority recorded
+/// in `program_data_info`, the ProgramData account of `program_id`
+pub fn verify_upgrade_authority(
+    program_id: &Pubkey,
+    program_data_info: &AccountInfo,
+    authority_info: &AccountInfo,
+) -> Result<(), ProgramError> {
+    use solana_program::bpf_loader_upgradeable;
+
+    let (expected_program_data, _) =
+        Pubkey::find_program_address(&[program_id.as_ref()], &bpf_loader_upgradeable::id());
+    if program_data_info.key != &expected_program_data {
+        return Err(RegistryError::InvalidPda.into());
+    }
+    verify_account_owner(program_data_info, &bpf_loader_upgradeable::id())?;
+
+    // ProgramData header: u32 variant (3), u64 slot, Option<Pubkey> authority
+    let data = program_data_info.try_borrow_data()?;
+    let upgrade_authority = match data.get(..45) {
+        Some(header) if header[..4] == 3u32.to_le_bytes() && header[12] == 1 => {
+            Pubkey::new_from_array(header[13..45].try_into().unwrap())
+        }
+        // Immutable programs have no upgrade authority to defer to
+        _ => return Err(RegistryError::Unauthorized.into()),
+    };
+    verify_signer_authority(authority_info, &upgrade_authority)
+}
+
 /// Verify PDA derivation
 pub fn verify_pda(
     account_info: &AccountInfo,
@@ -161,6 +222,127 @@ pub fn verify_rent_exemption(
     Ok(())
 }
 
+/// Lamports to move so an account of `current_lamports` stays exactly rent exempt at `new_len`.
+/// Positive values must be paid in, negative values can be refunded.
+pub fn rent_delta(rent: &Rent, current_lamports: u64, new_len: usize) -> i128 {
+    rent.minimum_balance(new_len) as i128 - current_lamports as i128
+}
+
+/// Resize a program-owned account to `new_len` bytes, keeping it rent exempt.
+///
+/// Growth is funded by `payer` through the system program; when the account
+/// shrinks, lamports above the new rent-exempt minimum are refunded to `payer`.
+pub fn resize_account<'a>(
+    account_info: &AccountInfo<'a>,
+    payer_info: &AccountInfo<'a>,
+    system_program_info: &AccountInfo<'a>,
+    new_len: usize,
+) -> ProgramResult {
+    if account_info.data_len() == new_len {
+        return Ok(());
+    }
+
+    let rent = Rent::get()?;
+    let delta = rent_delta(&rent, account_info.lamports(), new_len);
+
+    if delta > 0 {
+        if !payer_info.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+        if system_program_info.key != &solana_program::system_program::id() {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+        invoke(
+            &system_instruction::transfer(payer_info.key, account_info.key, delta as u64),
+            &[
+                payer_info.clone(),
+                account_info.clone(),
+                system_program_info.clone(),
+            ],
+        )?;
+    }
+
+    account_info.realloc(new_len, false)?;
+
+    if delta < 0 {
+        let refund = delta.unsigned_abs() as u64;
+        **account_info.try_borrow_mut_lamports()? -= refund;
+        **payer_info.try_borrow_mut_lamports()? += refund;
+    }
+
+    Ok(())
+}
+
+/// Create a rent-exempt PDA account of `space` bytes owned by `owner`, signing
+/// for the PDA with `signer_seeds`. Anyone can send lamports to a PDA before
+/// it is created, which would make `create_account` fail for good, so an
+/// address that already holds lamports is topped up to the rent-exempt
+/// minimum, then allocated and assigned instead.
+pub fn create_pda_account<'a>(
+    payer_info: &AccountInfo<'a>,
+    new_account_info: &AccountInfo<'a>,
+    system_program_info: &AccountInfo<'a>,
+    owner: &Pubkey,
+    space: usize,
+    signer_seeds: &[&[u8]],
+) -> ProgramResult {
+    let required_lamports = Rent::get()?.minimum_balance(space);
+    let current_lamports = new_account_info.lamports();
+    if current_lamports == 0 {
+        return invoke_signed(
+            &system_instruction::create_account(
+                payer_info.key,
+                new_account_info.key,
+                required_lamports,
+                space as u64,
+                owner,
+            ),
+            &[
+                payer_info.clone(),
+                new_account_info.clone(),
+                system_program_info.clone(),
+            ],
+            &[signer_seeds],
+        );
+    }
+
+    let shortfall = required_lamports.saturating_sub(current_lamports);
+    if shortfall > 0 {
+        invoke(
+            &system_instruction::transfer(payer_info.key, new_account_info.key, shortfall),
+            &[
+                payer_info.clone(),
+                new_account_info.clone(),
+                system_program_info.clone(),
+            ],
+        )?;
+    }
+    invoke_signed(
+        &system_instruction::allocate(new_account_info.key, space as u64),
+        &[new_account_info.clone(), system_program_info.clone()],
+        &[signer_seeds],
+    )?;
+    invoke_signed(
+        &system_instruction::assign(new_account_info.key, owner),
+        &[new_account_info.clone(), system_program_info.clone()],
+        &[signer_seeds],
+    )
+}
+
+/// Close a program-owned account, moving all of its lamports to `destination`
+/// and clearing its data
+pub fn close_account(account_info: &AccountInfo, destination_info: &AccountInfo) -> ProgramResult {
+    let lamports = account_info.lamports();
+    **destination_info.try_borrow_mut_lamports()? = destination_info
+        .lamports()
+        .checked_add(lamports)
+        .ok_or(ProgramError::ArithmeticOverflow)?;
+    **account_info.try_borrow_mut_lamports()? = 0;
+
+    account_info.try_borrow_mut_data()?.fill(0);
+    account_info.realloc(0, false)
+}
+
 /// Get current timestamp with validation
 pub fn get_current_timestamp() -> Result<i64, ProgramError> {
     let clock = Clock::get()?;
@@ -238,6 +420,22 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_rent_delta() {
+        let rent = Rent::default();
+        let funded = rent.minimum_balance(100);
+
+        assert_eq!(rent_delta(&rent, funded, 100), 0);
+        assert_eq!(
+            rent_delta(&rent, funded, 150),
+            (rent.minimum_balance(150) - funded) as i128
+        );
+        assert_eq!(
+            rent_delta(&rent, funded, 50),
+            -((funded - rent.minimum_balance(50)) as i128)
+        );
+    }
+
     #[test]
     fn test_pda_generation() {
         let program_id = Pubkey::new_unique();
@@ -251,4 +449,18 @@ mod tests {
         assert_eq!(bump1, bump2);
     }
 
-}
\ No newline at end of file
+    #[test]
+    fn test_overflow_account_pdas() {
+        let program_id = Pubkey::new_unique();
+        let entry = Pubkey::new_unique();
+
+        let (skill0, _) = get_skill_account_pda(&entry, 0, &program_id);
+        let (skill1, _) = get_skill_account_pda(&entry, 1, &program_id);
+        let (tool0, _) = get_tool_account_pda(&entry, 0, &program_id);
+
+        assert_ne!(skill0, skill1);
+        assert_ne!(skill0, tool0);
+        assert_eq!(skill0, get_skill_account_pda(&entry, 0, &program_id).0);
+    }
+
+}
@@ -0,0 +1,154 @@
+//! Zero-copy access to the fixed-size prefix of registry entries
+//!
+//! Every registry entry starts with an [`EntryHeader`] followed by a
+//! per-registry block of economic and metrics fields. Both have a fixed size,
+//! so their offsets never move when the variable-length part of the entry
+//! (strings and vectors) grows or shrinks. Token and metrics instructions
+//! mutate that prefix in place instead of borsh-decoding the whole entry.
+
+use bytemuck::Pod;
+use solana_program::{program_error::ProgramError, pubkey::Pubkey};
+use crate::error::RegistryError;
+
+/// Layout version stored in every entry's `registry_version`. Version 1 kept
+/// the economic fields after the variable-length part; such entries must be
+/// migrated before the prefix can be read in place.
+pub const ENTRY_LAYOUT_VERSION: u8 = 2;
+
+/// Fields shared by every registry entry, in the order they are serialized
+#[repr(C, packed)]
+#[derive(Clone, Copy, Debug, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct EntryHeader {
+    /// Bump seed used for the entry's PDA derivation
+    pub bump: u8,
+    /// Schema version of the entry
+    pub registry_version: u8,
+    /// State version for optimistic locking
+    pub state_version: u64,
+    /// Reentrancy guard (0 or 1)
+    pub operation_in_progress: u8,
+    /// Owner of the entry
+    pub owner_authority: Pubkey,
+    /// Entry status
+    pub status: u8,
+}
+
+/// Serialized length of [`EntryHeader`]
+pub const ENTRY_HEADER_LEN: usize = std::mem::size_of::<EntryHeader>();
+
+impl EntryHeader {
+    /// Compare a caller-supplied `state_version` against the stored one.
+    /// `None` skips the check.
+    pub fn check_expected_version(&self, expected_state_version: Option<u64>) -> Result<(), RegistryError> {
+        match expected_state_version {
+            Some(expected) if expected != self.state_version => Err(RegistryError::StateVersionMismatch),
+            _ => Ok(()),
+        }
+    }
+
+    /// Reject entries stored in any layout other than [`ENTRY_LAYOUT_VERSION`]
+    pub fn check_layout_version(&self) -> Result<(), RegistryError> {
+        check_layout_version(self.registry_version)
+    }
+
+    /// Record a state change
+    pub fn bump_state_version(&mut self) {
+        self.state_version += 1;
+    }
+}
+
+fn check_layout_version(registry_version: u8) -> Result<(), RegistryError> {
+    if registry_version != ENTRY_LAYOUT_VERSION {
+        return Err(RegistryError::UnsupportedEntryVersion);
+    }
+    Ok(())
+}
+
+/// Reject entry account data stored in any layout other than
+/// [`ENTRY_LAYOUT_VERSION`], before decoding the rest of the entry
+pub fn check_entry_layout_version(data: &[u8]) -> Result<(), ProgramError> {
+    let header: &EntryHeader = load(data)?;
+    Ok(header.check_layout_version()?)
+}
+
+/// Borrow the `T` stored at the start of `data` without copying
+pub fn load<T: Pod>(data: &[u8]) -> Result<&T, ProgramError> {
+    let bytes = data
+        .get(..std::mem::size_of::<T>())
+        .ok_or(ProgramError::AccountDataTooSmall)?;
+    bytemuck::try_from_bytes(bytes).map_err(|_| ProgramError::InvalidAccountData)
+}
+
+/// Mutably borrow the `T` stored at the start of `data` without copying
+pub fn load_mut<T: Pod>(data: &mut [u8]) -> Result<&mut T, ProgramError> {
+    let bytes = data
+        .get_mut(..std::mem::size_of::<T>())
+        .ok_or(ProgramError::AccountDataTooSmall)?;
+    bytemuck::try_from_bytes_mut(bytes).map_err(|_| ProgramError::InvalidAccountData)
+}
+
+/// Implement borsh for a packed `Pod` type as its raw bytes, so a struct
+/// embedded in a borsh entry has the same layout there as in place
+#[macro_export]
+macro_rules! impl_borsh_for_pod {
+    ($ty:ty) => {
+        impl ::borsh::BorshSerialize for $ty {
+            fn serialize<W: ::std::io::Write>(&self, writer: &mut W) -> ::std::io::Result<()> {
+                writer.write_all(::bytemuck::bytes_of(self))
+            }
+        }
+
+        impl ::borsh::BorshDeserialize for $ty {
+            fn deserialize_reader<R: ::std::io::Read>(reader: &mut R) -> ::std::io::Result<Self> {
+                let mut value = <$ty as ::bytemuck::Zeroable>::zeroed();
+                reader.read_exact(::bytemuck::bytes_of_mut(&mut value))?;
+                Ok(value)
+            }
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use borsh::BorshSerialize;
+
+    #[test]
+    fn test_header_matches_borsh_layout() {
+        let owner = Pubkey::new_unique();
+        let mut borsh_bytes = (7u8, 1u8, 42u64, true, owner, 1u8).try_to_vec().unwrap();
+        assert_eq!(borsh_bytes.len(), ENTRY_HEADER_LEN);
+
+        let header: &EntryHeader = load(&borsh_bytes).unwrap();
+        assert_eq!({ header.state_version }, 42);
+        assert_eq!(header.owner_authority, owner);
+        assert_eq!(header.status, 1);
+
+        let header: &mut EntryHeader = load_mut(&mut borsh_bytes).unwrap();
+        header.bump_state_version();
+        assert_eq!(header.check_expected_version(Some(42)), Err(RegistryError::StateVersionMismatch));
+        assert!(header.check_expected_version(Some(43)).is_ok());
+        assert_eq!(borsh_bytes[2], 43);
+
+        assert_eq!(
+            load::<EntryHeader>(&borsh_bytes[1..]).unwrap_err(),
+            ProgramError::AccountDataTooSmall
+        );
+    }
+
+    #[test]
+    fn test_older_layouts_are_rejected() {
+        let mut bytes = (7u8, ENTRY_LAYOUT_VERSION, 0u64, false, Pubkey::new_unique(), 0u8).try_to_vec().unwrap();
+        assert!(check_entry_layout_version(&bytes).is_ok());
+
+        bytes[1] = 1;
+        assert_eq!(
+            check_entry_layout_version(&bytes).unwrap_err(),
+            RegistryError::UnsupportedEntryVersion.into()
+        );
+        assert_eq!(
+            load::<EntryHeader>(&bytes).unwrap().check_layout_version(),
+            Err(RegistryError::UnsupportedEntryVersion)
+        );
+    }
+}
@@ -1,7 +1,11 @@
 //! Instruction definitions for the MCP Server Registry program
 
 use borsh::{BorshDeserialize, BorshSerialize};
-use solana_program::program_error::ProgramError;
+use solana_program::{program_error::ProgramError, pubkey::Pubkey};
+use aeamcp_common::config::RegistryConfigParams;
+use aeamcp_common::constants::IDEMPOTENCY_KEY_LEN;
+use aeamcp_common::revenue::SplitRecipient;
+use crate::state::{DiscountTier, FreeTier, SubscriptionQuota};
 use aeamcp_common::serialization::{McpToolDefinitionOnChainInput, McpResourceDefinitionOnChainInput, McpPromptDefinitionOnChainInput};
 
 /// Instructions supported by the MCP Server Registry program
@@ -47,12 +51,20 @@ pub enum McpServerRegistryInstruction {
 
     /// Update details of an existing MCP server
     ///
+    /// The entry account is resized to fit the new details; the payer covers
+    /// any extra rent and receives the refund when the entry shrinks.
+    ///
     /// Accounts expected:
     /// 0. `[writable]` MCP server entry account (PDA)
     /// 1. `[signer]` Owner authority
+    /// 2. `[writable, signer]` Payer
+    /// 3. `[]` System program
     UpdateMcpServerDetails {
         /// Update details input
         details: McpServerUpdateDetailsInput,
+        /// Optional compare-and-swap guard; fails with `StateVersionMismatch` if the
+        /// entry's `state_version` differs
+        expected_state_version: Option<u64>,
     },
 
     /// Update the status of an existing MCP server
@@ -63,6 +75,9 @@ pub enum McpServerRegistryInstruction {
     UpdateMcpServerStatus {
         /// New status value (0: Pending, 1: Active, 2: Inactive, 3: Deregistered)
         new_status: u8,
+        /// Optional compare-and-swap guard; fails with `StateVersionMismatch` if the
+        /// entry's `state_version` differs
+        expected_state_version: Option<u64>,
     },
 
     /// Deregister an MCP server
@@ -70,7 +85,11 @@ pub enum McpServerRegistryInstruction {
     /// Accounts expected:
     /// 0. `[writable]` MCP server entry account (PDA)
     /// 1. `[signer]` Owner authority
-    DeregisterMcpServer,
+    DeregisterMcpServer {
+        /// Optional compare-and-swap guard; fails with `StateVersionMismatch` if the
+        /// entry's `state_version` differs
+        expected_state_version: Option<u64>,
+    },
     
     /// Register a new MCP server with SVMAI token payment
     ///
@@ -84,6 +103,7 @@ pub enum McpServerRegistryInstruction {
     /// 6. `[]` Token program
     /// 7. `[]` System program
     /// 8. `[]` Clock sysvar
+    /// 9. `[]` Registry config PDA
     RegisterMcpServerWithToken {
         server_id: String,
         name: String,
@@ -104,43 +124,74 @@ pub enum McpServerRegistryInstruction {
     /// Stake tokens for server verification
     ///
     /// Accounts expected:
-    /// 0. `[signer]` Server owner
-    /// 1. `[writable]` Server registry PDA
+    /// 0. `[writable]` Server registry PDA
+    /// 1. `[signer]` Server owner
     /// 2. `[writable]` Owner's token account
-    /// 3. `[writable]` Verification vault token account
+    /// 3. `[writable]` Staking vault token account
     /// 4. `[]` Token program
-    /// 5. `[]` Clock sysvar
+    /// 5. `[]` Registry config PDA
     StakeForVerification {
         amount: u64,
         lock_period: i64,
+        /// Optional compare-and-swap guard; fails with `StateVersionMismatch` if the
+        /// entry's `state_version` differs
+        expected_state_version: Option<u64>,
     },
     
     /// Configure usage fees
     ///
     /// Accounts expected:
-    /// 0. `[signer]` Server owner
-    /// 1. `[writable]` Server registry PDA
-    /// 2. `[]` Clock sysvar
+    /// 0. `[writable]` Server registry PDA
+    /// 1. `[signer]` Server owner
+    /// 2. `[]` Registry config PDA
     ConfigureUsageFees {
         tool_base_fee: u64,
         resource_base_fee: u64,
         prompt_base_fee: u64,
         bulk_discount_threshold: u32,
         bulk_discount_percentage: u8,
+        /// Free calls each user gets per window, applying immediately
+        free_tier: FreeTier,
+        /// Optional compare-and-swap guard; fails with `StateVersionMismatch` if the
+        /// entry's `state_version` differs
+        expected_state_version: Option<u64>,
     },
     
     /// Record usage and collect fees
     ///
     /// Accounts expected:
-    /// 0. `[signer]` User (caller)
-    /// 1. `[writable]` Server registry PDA
+    /// 0. `[writable]` Server registry PDA
+    /// 1. `[signer, writable]` User (caller), who pays the receipt's rent
     /// 2. `[writable]` User's token account
-    /// 3. `[writable]` Server's token account
+    /// 3. `[writable]` Server fee vault PDA
     /// 4. `[]` Token program
-    /// 5. `[]` Clock sysvar
+    /// 5. `[]` Registry config PDA
+    /// 6. `[writable]` Treasury vault PDA, which receives the protocol take-rate
+    /// 7. `[]` Pricing table PDA of the server, which may be uninitialized
+    /// 8. `[writable]` Usage receipt PDA of `idempotency_key` (to be created)
+    /// 9. `[]` System program
+    /// 10. `[writable]` User's subscription PDA, which may be uninitialized;
+    ///     calls left in an active subscription are used before fees apply
+    /// 11. `[writable]` User's usage record PDA (created on first use);
+    ///     free-tier calls left in its window are used before the subscription
+    /// 12. `[]` Revenue split PDA of the server, which may be uninitialized;
+    ///     amounts it owes count toward the fee vault's solvency
     RecordUsageAndCollectFee {
         usage_type: UsageType,
         count: u32,
+        /// Tool or prompt name, or resource URI pattern, charged at its price
+        /// in the pricing table. Required when the table prices `usage_type`,
+        /// and fails with `UnknownPricedItem` if the table does not price it;
+        /// types the table does not price pay the base fee.
+        item_name: Option<String>,
+        /// Most the user agrees to pay; fails with `MaxFeeExceeded` above it
+        max_total_fee: u64,
+        /// Client-generated key of this charge; fails with
+        /// `DuplicateIdempotencyKey` if the user already used it on this server
+        idempotency_key: [u8; IDEMPOTENCY_KEY_LEN],
+        /// Optional compare-and-swap guard; fails with `StateVersionMismatch` if the
+        /// entry's `state_version` differs
+        expected_state_version: Option<u64>,
     },
     
     /// Update quality metrics (oracle/monitoring service)
@@ -152,17 +203,335 @@ pub enum McpServerRegistryInstruction {
         uptime_percentage: u8,
         avg_response_time: u32,
         error_rate: u8,
+        /// Optional compare-and-swap guard; fails with `StateVersionMismatch` if the
+        /// entry's `state_version` differs
+        expected_state_version: Option<u64>,
     },
     
     /// Withdraw pending fees
     ///
+    /// A split recipient whose token account is closed or frozen is skipped;
+    /// its share stays in the fee vault, owed to it, and is paid by a later
+    /// withdrawal once its account can receive tokens.
+    ///
     /// Accounts expected:
-    /// 0. `[signer]` Server owner
-    /// 1. `[writable]` Server registry PDA
-    /// 2. `[writable]` Fee vault token account
+    /// 0. `[writable]` Server registry PDA
+    /// 1. `[signer]` Server owner
+    /// 2. `[writable]` Server fee vault PDA
     /// 3. `[writable]` Owner's token account
     /// 4. `[]` Token program
-    WithdrawPendingFees,
+    /// 5. `[]` Registry config PDA
+    /// 6. `[writable]` Revenue split PDA of the server
+    /// 7. `[writable]` Token account of each split recipient, in table order
+    WithdrawPendingFees {
+        /// Optional compare-and-swap guard; fails with `StateVersionMismatch` if the
+        /// entry's `state_version` differs
+        expected_state_version: Option<u64>,
+    },
+
+    /// Create an overflow tool account for tools beyond the inline limit.
+    /// `index` must equal the entry's current `tool_account_count`.
+    ///
+    /// Accounts expected:
+    /// 0. `[writable]` Tool account PDA (to be created)
+    /// 1. `[writable]` MCP server entry account (PDA)
+    /// 2. `[signer]` Owner authority
+    /// 3. `[writable, signer]` Payer
+    /// 4. `[]` System program
+    InitializeToolAccount {
+        /// Position of the new account among the server's overflow tools
+        index: u8,
+        /// Tool definition to store
+        tool: McpToolDefinitionOnChainInput,
+    },
+
+    /// Replace the tool definition held by an overflow tool account
+    ///
+    /// Accounts expected:
+    /// 0. `[writable]` Tool account PDA
+    /// 1. `[]` MCP server entry account (PDA)
+    /// 2. `[signer]` Owner authority
+    /// 3. `[writable, signer]` Payer (funds growth, receives refunds)
+    /// 4. `[]` System program
+    UpdateToolAccount {
+        /// Position of the account among the server's overflow tools
+        index: u8,
+        /// Replacement tool definition
+        tool: McpToolDefinitionOnChainInput,
+    },
+
+    /// Close the last overflow tool account and reclaim its rent
+    ///
+    /// Accounts expected:
+    /// 0. `[writable]` Tool account PDA
+    /// 1. `[writable]` MCP server entry account (PDA)
+    /// 2. `[signer]` Owner authority
+    /// 3. `[writable]` Rent recipient
+    CloseToolAccount {
+        /// Position of the account; must be the last one
+        index: u8,
+    },
+
+    /// Start a chunked registration for servers too large to register in one
+    /// transaction. Creates the entry without on-chain definitions; it stays
+    /// `Pending` and accepts the append instructions below until
+    /// `FinalizeMcpServerRegistration`.
+    ///
+    /// Accounts expected:
+    /// 0. `[writable]` MCP server entry account (PDA)
+    /// 1. `[signer]` Owner authority
+    /// 2. `[signer, writable]` Payer account
+    /// 3. `[]` System program
+    BeginMcpServerRegistration {
+        server_id: String,
+        name: String,
+        server_version: String,
+        service_endpoint: String,
+        documentation_url: Option<String>,
+        server_capabilities_summary: Option<String>,
+        supports_resources: bool,
+        supports_tools: bool,
+        supports_prompts: bool,
+        full_capabilities_uri: Option<String>,
+        tags: Vec<String>,
+    },
+
+    /// Append tool definitions to a server whose registration is still open
+    ///
+    /// Accounts expected:
+    /// 0. `[writable]` MCP server entry account (PDA)
+    /// 1. `[signer]` Owner authority
+    /// 2. `[signer, writable]` Payer account
+    /// 3. `[]` System program
+    AppendMcpToolDefinitions {
+        tools: Vec<McpToolDefinitionOnChainInput>,
+    },
+
+    /// Append resource definitions to a server whose registration is still open
+    ///
+    /// Accounts expected:
+    /// 0. `[writable]` MCP server entry account (PDA)
+    /// 1. `[signer]` Owner authority
+    /// 2. `[signer, writable]` Payer account
+    /// 3. `[]` System program
+    AppendMcpResourceDefinitions {
+        resources: Vec<McpResourceDefinitionOnChainInput>,
+    },
+
+    /// Append prompt definitions to a server whose registration is still open
+    ///
+    /// Accounts expected:
+    /// 0. `[writable]` MCP server entry account (PDA)
+    /// 1. `[signer]` Owner authority
+    /// 2. `[signer, writable]` Payer account
+    /// 3. `[]` System program
+    AppendMcpPromptDefinitions {
+        prompts: Vec<McpPromptDefinitionOnChainInput>,
+    },
+
+    /// Run full validation over the assembled entry, close the registration
+    /// and move the server from `Pending` to `Active`
+    ///
+    /// Accounts expected:
+    /// 0. `[writable]` MCP server entry account (PDA)
+    /// 1. `[signer]` Owner authority
+    FinalizeMcpServerRegistration,
+
+    /// Create the registry config PDA. Only the program's upgrade authority
+    /// can call this, and it becomes the config's admin.
+    ///
+    /// Accounts expected:
+    /// 0. `[writable]` Registry config PDA (to be created)
+    /// 1. `[signer]` Upgrade authority
+    /// 2. `[signer, writable]` Payer account
+    /// 3. `[]` Program data account of this program
+    /// 4. `[]` System program
+    InitializeRegistryConfig {
+        params: RegistryConfigParams,
+    },
+
+    /// Replace the registry config parameters, optionally handing the config
+    /// to a new admin
+    ///
+    /// The accepted mint is fixed once the vaults exist, since they hold that
+    /// mint.
+    ///
+    /// Accounts expected:
+    /// 0. `[writable]` Registry config PDA
+    /// 1. `[signer]` Admin authority
+    /// 2. `[]` Staking vault PDA
+    UpdateRegistryConfig {
+        params: RegistryConfigParams,
+        new_admin: Option<Pubkey>,
+    },
+
+    /// Create the staking, fee, registration and treasury vault token
+    /// accounts for the configured mint. Each vault is its own token
+    /// authority. Fails if any vault already exists.
+    ///
+    /// Accounts expected:
+    /// 0. `[]` Registry config PDA
+    /// 1. `[signer]` Admin authority
+    /// 2. `[signer, writable]` Payer account
+    /// 3. `[]` Accepted token mint
+    /// 4. `[writable]` Staking vault PDA (to be created)
+    /// 5. `[writable]` Fee vault PDA (to be created)
+    /// 6. `[writable]` Registration vault PDA (to be created)
+    /// 7. `[writable]` Treasury vault PDA (to be created)
+    /// 8. `[]` SPL Token program
+    /// 9. `[]` System program
+    InitializeVaults,
+
+    /// Create the fee vault of a single server, which holds the fees its
+    /// usage collects until the owner withdraws them
+    ///
+    /// Accounts expected:
+    /// 0. `[]` MCP server entry account (PDA)
+    /// 1. `[signer]` Owner authority
+    /// 2. `[signer, writable]` Payer account
+    /// 3. `[]` Registry config PDA
+    /// 4. `[]` Accepted token mint
+    /// 5. `[writable]` Server fee vault PDA (to be created)
+    /// 6. `[]` SPL Token program
+    /// 7. `[]` System program
+    InitializeServerFeeVault,
+
+    /// Set the server's revenue split table, applied when pending fees are
+    /// withdrawn. An empty table pays everything to the owner. Shares still
+    /// owed to a recipient carry over, so a table dropping that recipient is
+    /// rejected until it is paid.
+    ///
+    /// Accounts expected:
+    /// 0. `[writable]` Revenue split PDA (created on first use)
+    /// 1. `[]` MCP server entry account (PDA)
+    /// 2. `[signer]` Owner authority
+    /// 3. `[signer, writable]` Payer account
+    /// 4. `[]` System program
+    SetRevenueSplit {
+        recipients: Vec<SplitRecipient>,
+    },
+
+    /// Move protocol revenue out of the treasury vault to the config's
+    /// treasury wallet
+    ///
+    /// Accounts expected:
+    /// 0. `[]` Registry config PDA
+    /// 1. `[signer]` Admin authority
+    /// 2. `[writable]` Treasury vault PDA
+    /// 3. `[writable]` Token account of the config's treasury wallet
+    /// 4. `[]` SPL Token program
+    WithdrawTreasury {
+        amount: u64,
+    },
+
+    /// Set per-item prices and tiered bulk discounts, replacing the server's
+    /// pricing table. Priced items must be among the entry's on-chain
+    /// definitions, and a usage type with priced items can only be charged
+    /// for those items. Tiers override the entry's single bulk discount.
+    /// A table that makes any use cost more applies after the config's
+    /// `fee_increase_notice`, with the current prices kept until then.
+    ///
+    /// Accounts expected:
+    /// 0. `[writable]` Pricing table PDA (created on first use)
+    /// 1. `[]` MCP server entry account (PDA)
+    /// 2. `[signer]` Owner authority
+    /// 3. `[signer, writable]` Payer account
+    /// 4. `[]` System program
+    /// 5. `[]` Registry config PDA
+    SetUsagePricing {
+        item_prices: Vec<ItemPriceInput>,
+        discount_tiers: Vec<DiscountTier>,
+    },
+
+    /// Create or update one of the server's subscription plans. Updates
+    /// apply to later subscriptions and renewals; an inactive plan accepts
+    /// neither. A higher price, shorter period or fewer included calls
+    /// apply after the config's `fee_increase_notice`.
+    ///
+    /// Accounts expected:
+    /// 0. `[writable]` Subscription plan PDA (created on first use)
+    /// 1. `[]` MCP server entry account (PDA)
+    /// 2. `[signer]` Owner authority
+    /// 3. `[signer, writable]` Payer account
+    /// 4. `[]` System program
+    /// 5. `[]` Registry config PDA
+    SetSubscriptionPlan {
+        plan_id: u8,
+        price: u64,
+        /// Length of one period in seconds
+        period: i64,
+        /// Calls included in each period
+        included: SubscriptionQuota,
+        active: bool,
+    },
+
+    /// Buy one period of a subscription plan
+    ///
+    /// Accounts expected:
+    /// 0. `[writable]` Subscription PDA of the user (to be created)
+    /// 1. `[writable]` MCP server entry account (PDA)
+    /// 2. `[]` Subscription plan PDA
+    /// 3. `[signer, writable]` User, who pays the subscription's rent
+    /// 4. `[writable]` User's token account
+    /// 5. `[writable]` Server fee vault PDA
+    /// 6. `[writable]` Treasury vault PDA, which receives the protocol take-rate
+    /// 7. `[]` Token program
+    /// 8. `[]` Registry config PDA
+    /// 9. `[]` System program
+    /// 10. `[]` Revenue split PDA of the server, which may be uninitialized
+    Subscribe {
+        plan_id: u8,
+        /// Most the user agrees to pay; fails with `MaxFeeExceeded` above it
+        max_price: u64,
+    },
+
+    /// Pay for another period of the subscription's plan. A running
+    /// subscription is extended and keeps its unused calls; an expired one
+    /// restarts now.
+    ///
+    /// Accounts expected:
+    /// 0. `[writable]` Subscription PDA of the user
+    /// 1. `[writable]` MCP server entry account (PDA)
+    /// 2. `[]` Subscription plan PDA
+    /// 3. `[signer]` User
+    /// 4. `[writable]` User's token account
+    /// 5. `[writable]` Server fee vault PDA
+    /// 6. `[writable]` Treasury vault PDA, which receives the protocol take-rate
+    /// 7. `[]` Token program
+    /// 8. `[]` Registry config PDA
+    /// 9. `[]` Revenue split PDA of the server, which may be uninitialized
+    RenewSubscription {
+        /// Most the user agrees to pay; fails with `MaxFeeExceeded` above it
+        max_price: u64,
+    },
+
+    /// Close the user's subscription and return its rent. Paid periods are
+    /// not refunded and the remaining calls are forfeited.
+    ///
+    /// Accounts expected:
+    /// 0. `[writable]` Subscription PDA of the user
+    /// 1. `[signer, writable]` User, who receives the rent
+    CancelSubscription,
+
+    /// Rewrite an entry stored in layout version 1 into the current layout.
+    /// Anyone may migrate an entry; its contents do not change.
+    ///
+    /// Accounts expected:
+    /// 0. `[writable]` MCP server entry account (PDA)
+    /// 1. `[writable, signer]` Payer of any extra rent
+    /// 2. `[]` System program
+    MigrateMcpServerEntry,
+}
+
+/// Price of one on-chain tool, resource or prompt
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
+pub struct ItemPriceInput {
+    /// Usage type of the item
+    pub usage_type: UsageType,
+    /// Tool or prompt name, or resource URI pattern
+    pub name: String,
+    /// Fee per use
+    pub price: u64,
 }
 
 /// Usage type for tracking different service calls
@@ -267,13 +636,17 @@ mod tests {
 
     #[test]
     fn test_update_status_instruction() {
-        let instruction = McpServerRegistryInstruction::UpdateMcpServerStatus { new_status: 1 };
+        let instruction = McpServerRegistryInstruction::UpdateMcpServerStatus {
+            new_status: 1,
+            expected_state_version: None,
+        };
         let packed = instruction.pack();
         let unpacked = McpServerRegistryInstruction::unpack(&packed).unwrap();
 
         match unpacked {
-            McpServerRegistryInstruction::UpdateMcpServerStatus { new_status } => {
+            McpServerRegistryInstruction::UpdateMcpServerStatus { new_status, expected_state_version } => {
                 assert_eq!(new_status, 1);
+                assert_eq!(expected_state_version, None);
             }
             _ => panic!("Wrong instruction type"),
         }
@@ -281,13 +654,205 @@ mod tests {
 
     #[test]
     fn test_deregister_instruction() {
-        let instruction = McpServerRegistryInstruction::DeregisterMcpServer;
+        let instruction = McpServerRegistryInstruction::DeregisterMcpServer {
+            expected_state_version: Some(4),
+        };
         let packed = instruction.pack();
         let unpacked = McpServerRegistryInstruction::unpack(&packed).unwrap();
 
         match unpacked {
-            McpServerRegistryInstruction::DeregisterMcpServer => {}
+            McpServerRegistryInstruction::DeregisterMcpServer { expected_state_version } => {
+                assert_eq!(expected_state_version, Some(4));
+            }
+            _ => panic!("Wrong instruction type"),
+        }
+    }
+
+    #[test]
+    fn test_tool_account_instructions() {
+        let tool = McpToolDefinitionOnChainInput {
+            name: "search".to_string(),
+            description_hash: [0; 32],
+            input_schema_hash: [0; 32],
+            output_schema_hash: [0; 32],
+            tags: vec![],
+        };
+
+        let instruction = McpServerRegistryInstruction::InitializeToolAccount { index: 5, tool };
+        let unpacked = McpServerRegistryInstruction::unpack(&instruction.pack()).unwrap();
+        match unpacked {
+            McpServerRegistryInstruction::InitializeToolAccount { index, tool } => {
+                assert_eq!(index, 5);
+                assert_eq!(tool.name, "search");
+            }
+            _ => panic!("Wrong instruction type"),
+        }
+
+        let packed = McpServerRegistryInstruction::CloseToolAccount { index: 5 }.pack();
+        assert!(matches!(
+            McpServerRegistryInstruction::unpack(&packed).unwrap(),
+            McpServerRegistryInstruction::CloseToolAccount { index: 5 }
+        ));
+    }
+
+    #[test]
+    fn test_chunked_registration_instructions() {
+        let instruction = McpServerRegistryInstruction::AppendMcpPromptDefinitions {
+            prompts: vec![McpPromptDefinitionOnChainInput {
+                name: "review".to_string(),
+                description_hash: [1; 32],
+                tags: vec!["code".to_string()],
+            }],
+        };
+        match McpServerRegistryInstruction::unpack(&instruction.pack()).unwrap() {
+            McpServerRegistryInstruction::AppendMcpPromptDefinitions { prompts } => {
+                assert_eq!(prompts.len(), 1);
+                assert_eq!(prompts[0].name, "review");
+            }
+            _ => panic!("Wrong instruction type"),
+        }
+
+        let packed = McpServerRegistryInstruction::FinalizeMcpServerRegistration.pack();
+        assert_eq!(packed, vec![17]);
+        assert!(matches!(
+            McpServerRegistryInstruction::unpack(&packed).unwrap(),
+            McpServerRegistryInstruction::FinalizeMcpServerRegistration
+        ));
+    }
+
+    #[test]
+    fn test_registry_config_instructions() {
+        let params = RegistryConfigParams::mcp_defaults(Pubkey::new_unique(), Pubkey::new_unique());
+        let new_admin = Pubkey::new_unique();
+
+        let instruction = McpServerRegistryInstruction::UpdateRegistryConfig {
+            params,
+            new_admin: Some(new_admin),
+        };
+        let packed = instruction.pack();
+        assert_eq!(packed[0], 19);
+        match McpServerRegistryInstruction::unpack(&packed).unwrap() {
+            McpServerRegistryInstruction::UpdateRegistryConfig { params: unpacked, new_admin: admin } => {
+                assert_eq!(unpacked, params);
+                assert_eq!(admin, Some(new_admin));
+            }
             _ => panic!("Wrong instruction type"),
         }
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_initialize_vaults_instruction() {
+        let packed = McpServerRegistryInstruction::InitializeVaults.pack();
+        assert_eq!(packed, vec![20]);
+        assert!(matches!(
+            McpServerRegistryInstruction::unpack(&packed).unwrap(),
+            McpServerRegistryInstruction::InitializeVaults
+        ));
+
+        let packed = McpServerRegistryInstruction::InitializeServerFeeVault.pack();
+        assert_eq!(packed, vec![21]);
+        assert!(matches!(
+            McpServerRegistryInstruction::unpack(&packed).unwrap(),
+            McpServerRegistryInstruction::InitializeServerFeeVault
+        ));
+    }
+
+    #[test]
+    fn test_revenue_instructions() {
+        let recipient = SplitRecipient {
+            recipient: Pubkey::new_unique(),
+            bps: 1_000,
+        };
+        let packed = McpServerRegistryInstruction::SetRevenueSplit {
+            recipients: vec![recipient],
+        }
+        .pack();
+        assert_eq!(packed[0], 22);
+        match McpServerRegistryInstruction::unpack(&packed).unwrap() {
+            McpServerRegistryInstruction::SetRevenueSplit { recipients } => {
+                assert_eq!(recipients, vec![recipient]);
+            }
+            _ => panic!("Wrong instruction type"),
+        }
+
+        let packed = McpServerRegistryInstruction::WithdrawTreasury { amount: 7 }.pack();
+        assert_eq!(packed, vec![23, 7, 0, 0, 0, 0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn test_usage_pricing_instructions() {
+        let item = ItemPriceInput {
+            usage_type: UsageType::Tool,
+            name: "lookup".to_string(),
+            price: 5,
+        };
+        let tier = DiscountTier { threshold: 10, percentage: 5 };
+        let packed = McpServerRegistryInstruction::SetUsagePricing {
+            item_prices: vec![item.clone()],
+            discount_tiers: vec![tier],
+        }
+        .pack();
+        assert_eq!(packed[0], 24);
+        match McpServerRegistryInstruction::unpack(&packed).unwrap() {
+            McpServerRegistryInstruction::SetUsagePricing { item_prices, discount_tiers } => {
+                assert_eq!(item_prices, vec![item]);
+                assert_eq!(discount_tiers, vec![tier]);
+            }
+            _ => panic!("Wrong instruction type"),
+        }
+
+        let packed = McpServerRegistryInstruction::RecordUsageAndCollectFee {
+            usage_type: UsageType::Prompt,
+            count: 3,
+            item_name: Some("summarize".to_string()),
+            max_total_fee: 300,
+            idempotency_key: [9; IDEMPOTENCY_KEY_LEN],
+            expected_state_version: None,
+        }
+        .pack();
+        match McpServerRegistryInstruction::unpack(&packed).unwrap() {
+            McpServerRegistryInstruction::RecordUsageAndCollectFee {
+                count,
+                item_name,
+                max_total_fee,
+                idempotency_key,
+                ..
+            } => {
+                assert_eq!(count, 3);
+                assert_eq!(item_name.as_deref(), Some("summarize"));
+                assert_eq!(max_total_fee, 300);
+                assert_eq!(idempotency_key, [9; IDEMPOTENCY_KEY_LEN]);
+            }
+            _ => panic!("Wrong instruction type"),
+        }
+    }
+
+    #[test]
+    fn test_subscription_instructions() {
+        let included = SubscriptionQuota { tool_calls: 100, resource_calls: 0, prompt_calls: 10 };
+        let packed = McpServerRegistryInstruction::SetSubscriptionPlan {
+            plan_id: 2,
+            price: 1_000,
+            period: 30 * 24 * 60 * 60,
+            included,
+            active: true,
+        }
+        .pack();
+        assert_eq!(packed[0], 25);
+        match McpServerRegistryInstruction::unpack(&packed).unwrap() {
+            McpServerRegistryInstruction::SetSubscriptionPlan { plan_id, included: unpacked, active, .. } => {
+                assert_eq!(plan_id, 2);
+                assert_eq!(unpacked, included);
+                assert!(active);
+            }
+            _ => panic!("Wrong instruction type"),
+        }
+
+        let packed = McpServerRegistryInstruction::Subscribe { plan_id: 2, max_price: 1_000 }.pack();
+        assert_eq!(packed[..2], [26, 2]);
+        let packed = McpServerRegistryInstruction::RenewSubscription { max_price: 1_000 }.pack();
+        assert_eq!(packed[0], 27);
+        assert_eq!(McpServerRegistryInstruction::CancelSubscription.pack(), vec![28]);
+        assert_eq!(McpServerRegistryInstruction::MigrateMcpServerEntry.pack(), vec![29]);
+    }
+}
@@ -5,10 +5,6 @@ use solana_program::{
     entrypoint::ProgramResult,
     program_error::ProgramError,
     pubkey::Pubkey,
-    rent::Rent,
-    system_instruction,
-    sysvar::Sysvar,
-    program::invoke,
     msg,
 };
 use borsh::{BorshDeserialize, BorshSerialize};
@@ -16,7 +12,14 @@ use spl_token::state::Account as TokenAccount;
 use aeamcp_common::{
     constants::*,
     error::RegistryError,
-    utils::{get_current_timestamp, get_mcp_server_pda_secure, verify_account_owner},
+    utils::{
+        close_account, create_pda_account, get_current_timestamp, get_mcp_server_pda_secure,
+        get_registry_config_pda, get_tool_account_pda, resize_account, verify_account_owner,
+        verify_signer_authority, verify_upgrade_authority,
+    },
+    config::{RegistryConfigParams, RegistryConfigV1},
+    receipt::{check_max_total_fee, record_usage_receipt},
+    revenue::{bps_share, derive_revenue_split_pda, pay_out_with_split, RevenueSplitV1, SplitRecipient},
     McpServerStatus,
     serialization::{
         McpToolDefinitionOnChainInput,
@@ -25,13 +28,20 @@ use aeamcp_common::{
     token_utils::{
         transfer_tokens_with_pda_signer_account_info, transfer_tokens_with_account_info,
         StakingTier, is_stake_unlocked, derive_staking_vault_pda, derive_registration_vault_pda,
-        calculate_server_quality_score, validate_fee_config,
+        calculate_server_quality_score, validate_fee_config, VerificationTier,
+        derive_server_fee_vault_pda, check_fee_vault_solvency, verify_token_program, verify_token_mint,
+        verify_user_token_account, verify_vault_token_account, create_vault_token_account,
+        derive_treasury_vault_pda,
     },
 };
 
 use crate::{
-    instruction::{McpServerRegistryInstruction, McpServerUpdateDetailsInput, UsageType},
-    state::{McpServerRegistryEntryV1, UsageType as StateUsageType},
+    instruction::{ItemPriceInput, McpServerRegistryInstruction, McpServerUpdateDetailsInput, UsageType},
+    state::{
+        discounted_total, hash_item_name, DiscountTier, ItemPriceV1, McpPricingTableV1, McpServerEntryPrefixV1,
+        FreeTier, McpServerRegistryEntryV1, McpSubscriptionPlanV1, McpSubscriptionV1, McpToolAccountV1,
+        McpUsageRecordV1, SubscriptionQuota, UsageType as StateUsageType,
+    },
     validation::*,
 };
 
@@ -80,14 +90,14 @@ pub fn process_instruction(
                 tags,
             )
         }
-        McpServerRegistryInstruction::UpdateMcpServerDetails { details } => {
-            process_update_mcp_server_details(program_id, accounts, details)
+        McpServerRegistryInstruction::UpdateMcpServerDetails { details, expected_state_version } => {
+            process_update_mcp_server_details(program_id, accounts, details, expected_state_version)
         }
-        McpServerRegistryInstruction::UpdateMcpServerStatus { new_status } => {
-            process_update_mcp_server_status(program_id, accounts, new_status)
+        McpServerRegistryInstruction::UpdateMcpServerStatus { new_status, expected_state_version } => {
+            process_update_mcp_server_status(program_id, accounts, new_status, expected_state_version)
         }
-        McpServerRegistryInstruction::DeregisterMcpServer => {
-            process_deregister_mcp_server(program_id, accounts)
+        McpServerRegistryInstruction::DeregisterMcpServer { expected_state_version } => {
+            process_deregister_mcp_server(program_id, accounts, expected_state_version)
         }
         McpServerRegistryInstruction::RegisterMcpServerWithToken {
             server_id,
@@ -124,33 +134,145 @@ pub fn process_instruction(
                 tags,
             )
         }
-        McpServerRegistryInstruction::StakeForVerification { amount, lock_period } => {
-            process_stake_for_verification(program_id, accounts, amount, lock_period)
+        McpServerRegistryInstruction::StakeForVerification { amount, lock_period, expected_state_version } => {
+            process_stake_for_verification(program_id, accounts, amount, lock_period, expected_state_version)
         }
         McpServerRegistryInstruction::ConfigureUsageFees {
             tool_base_fee,
             resource_base_fee,
             prompt_base_fee,
             bulk_discount_threshold,
-            bulk_discount_percentage
+            bulk_discount_percentage,
+            free_tier,
+            expected_state_version,
         } => {
-            process_configure_usage_fees(program_id, accounts, tool_base_fee, resource_base_fee, prompt_base_fee, bulk_discount_threshold, bulk_discount_percentage)
+            process_configure_usage_fees(program_id, accounts, tool_base_fee, resource_base_fee, prompt_base_fee, bulk_discount_threshold, bulk_discount_percentage, free_tier, expected_state_version)
         }
         McpServerRegistryInstruction::RecordUsageAndCollectFee {
             usage_type,
-            count
+            count,
+            item_name,
+            max_total_fee,
+            idempotency_key,
+            expected_state_version,
         } => {
-            process_record_usage_and_collect_fee(program_id, accounts, usage_type, count)
+            process_record_usage_and_collect_fee(
+                program_id,
+                accounts,
+                usage_type,
+                count,
+                item_name,
+                max_total_fee,
+                idempotency_key,
+                expected_state_version,
+            )
         }
         McpServerRegistryInstruction::UpdateQualityMetrics {
             uptime_percentage,
             avg_response_time,
-            error_rate
+            error_rate,
+            expected_state_version,
         } => {
-            process_update_quality_metrics(program_id, accounts, uptime_percentage, avg_response_time, error_rate)
+            process_update_quality_metrics(program_id, accounts, uptime_percentage, avg_response_time, error_rate, expected_state_version)
+        }
+        McpServerRegistryInstruction::WithdrawPendingFees { expected_state_version } => {
+            process_withdraw_pending_fees(program_id, accounts, expected_state_version)
+        }
+        McpServerRegistryInstruction::InitializeToolAccount { index, tool } => {
+            process_initialize_tool_account(program_id, accounts, index, tool)
+        }
+        McpServerRegistryInstruction::UpdateToolAccount { index, tool } => {
+            process_update_tool_account(program_id, accounts, index, tool)
+        }
+        McpServerRegistryInstruction::CloseToolAccount { index } => {
+            process_close_tool_account(program_id, accounts, index)
+        }
+        McpServerRegistryInstruction::BeginMcpServerRegistration {
+            server_id,
+            name,
+            server_version,
+            service_endpoint,
+            documentation_url,
+            server_capabilities_summary,
+            supports_resources,
+            supports_tools,
+            supports_prompts,
+            full_capabilities_uri,
+            tags,
+        } => process_begin_mcp_server_registration(
+            program_id,
+            accounts,
+            server_id,
+            name,
+            server_version,
+            service_endpoint,
+            documentation_url,
+            server_capabilities_summary,
+            supports_resources,
+            supports_tools,
+            supports_prompts,
+            full_capabilities_uri,
+            tags,
+        ),
+        McpServerRegistryInstruction::AppendMcpToolDefinitions { tools } => {
+            validate_tool_definitions(&tools)?;
+            let tools = tools.into_iter().map(Into::into).collect();
+            process_append_registration_chunk(program_id, accounts, "onchain_tool_definitions", |entry| {
+                entry.append_tool_definitions(tools)
+            })
+        }
+        McpServerRegistryInstruction::AppendMcpResourceDefinitions { resources } => {
+            validate_resource_definitions(&resources)?;
+            let resources = resources.into_iter().map(Into::into).collect();
+            process_append_registration_chunk(program_id, accounts, "onchain_resource_definitions", |entry| {
+                entry.append_resource_definitions(resources)
+            })
+        }
+        McpServerRegistryInstruction::AppendMcpPromptDefinitions { prompts } => {
+            validate_prompt_definitions(&prompts)?;
+            let prompts = prompts.into_iter().map(Into::into).collect();
+            process_append_registration_chunk(program_id, accounts, "onchain_prompt_definitions", |entry| {
+                entry.append_prompt_definitions(prompts)
+            })
+        }
+        McpServerRegistryInstruction::FinalizeMcpServerRegistration => {
+            process_finalize_mcp_server_registration(program_id, accounts)
+        }
+        McpServerRegistryInstruction::InitializeRegistryConfig { params } => {
+            process_initialize_registry_config(program_id, accounts, params)
+        }
+        McpServerRegistryInstruction::UpdateRegistryConfig { params, new_admin } => {
+            process_update_registry_config(program_id, accounts, params, new_admin)
+        }
+        McpServerRegistryInstruction::InitializeVaults => {
+            process_initialize_vaults(program_id, accounts)
+        }
+        McpServerRegistryInstruction::InitializeServerFeeVault => {
+            process_initialize_server_fee_vault(program_id, accounts)
+        }
+        McpServerRegistryInstruction::SetRevenueSplit { recipients } => {
+            process_set_revenue_split(program_id, accounts, recipients)
+        }
+        McpServerRegistryInstruction::WithdrawTreasury { amount } => {
+            process_withdraw_treasury(program_id, accounts, amount)
+        }
+        McpServerRegistryInstruction::SetUsagePricing { item_prices, discount_tiers } => {
+            process_set_usage_pricing(program_id, accounts, item_prices, discount_tiers)
+        }
+        McpServerRegistryInstruction::SetSubscriptionPlan { plan_id, price, period, included, active } => {
+            process_set_subscription_plan(program_id, accounts, plan_id, price, period, included, active)
+        }
+        McpServerRegistryInstruction::Subscribe { plan_id, max_price } => {
+            process_subscribe(program_id, accounts, plan_id, max_price)
+        }
+        McpServerRegistryInstruction::RenewSubscription { max_price } => {
+            process_renew_subscription(program_id, accounts, max_price)
+        }
+        McpServerRegistryInstruction::CancelSubscription => {
+            process_cancel_subscription(program_id, accounts)
         }
-        McpServerRegistryInstruction::WithdrawPendingFees => {
-            process_withdraw_pending_fees(program_id, accounts)
+        McpServerRegistryInstruction::MigrateMcpServerEntry => {
+            process_migrate_mcp_server_entry(program_id, accounts)
         }
     }
 }
@@ -212,26 +334,6 @@ fn process_register_mcp_server(
         return Err(ProgramError::InvalidSeeds);
     }
 
-    // Create account
-    let space = McpServerRegistryEntryV1::SPACE;
-    let rent = Rent::get()?;
-    let lamports = rent.minimum_balance(space);
-
-    invoke(
-        &system_instruction::create_account(
-            payer_info.key,
-            mcp_server_entry_info.key,
-            lamports,
-            space as u64,
-            program_id,
-        ),
-        &[
-            payer_info.clone(),
-            mcp_server_entry_info.clone(),
-            system_program_info.clone(),
-        ],
-    )?;
-
     // Initialize account data
     let timestamp = get_current_timestamp()?;
     let mcp_server_entry = McpServerRegistryEntryV1::new(
@@ -254,6 +356,21 @@ fn process_register_mcp_server(
         timestamp,
     );
 
+    // Create account sized to the serialized entry rather than the worst-case SPACE
+    create_pda_account(
+        payer_info,
+        mcp_server_entry_info,
+        system_program_info,
+        program_id,
+        mcp_server_entry.required_space()?,
+        &[
+            MCP_SERVER_REGISTRY_PDA_SEED,
+            server_id.as_bytes(),
+            owner_authority_info.key.as_ref(),
+            &[bump],
+        ],
+    )?;
+
     // Serialize and store
     let mut data = mcp_server_entry_info.try_borrow_mut_data()?;
     mcp_server_entry.serialize(&mut &mut data[..])?;
@@ -269,10 +386,13 @@ fn process_update_mcp_server_details(
     program_id: &Pubkey,
     accounts: &[AccountInfo],
     details: McpServerUpdateDetailsInput,
+    expected_state_version: Option<u64>,
 ) -> ProgramResult {
     let accounts_iter = &mut accounts.iter();
     let mcp_server_entry_info = next_account_info(accounts_iter)?;
     let owner_authority_info = next_account_info(accounts_iter)?;
+    let payer_info = next_account_info(accounts_iter)?;
+    let system_program_info = next_account_info(accounts_iter)?;
 
     // Verify owner authority is signer
     if !owner_authority_info.is_signer {
@@ -283,13 +403,16 @@ fn process_update_mcp_server_details(
     verify_account_owner(mcp_server_entry_info, program_id)?;
     
     let mut data = mcp_server_entry_info.try_borrow_mut_data()?;
-    let mut mcp_server_entry = McpServerRegistryEntryV1::try_from_slice(&data)?;
+    let mut mcp_server_entry = McpServerRegistryEntryV1::try_from_account_data(&data)?;
 
     // Verify ownership
     if mcp_server_entry.owner_authority != *owner_authority_info.key {
         return Err(RegistryError::Unauthorized.into());
     }
 
+    // Reject stale writers when the caller pinned a state version
+    mcp_server_entry.check_expected_version(expected_state_version)?;
+
     // SECURITY FIX: Begin operation to prevent reentrancy
     mcp_server_entry.begin_operation()?;
     let current_version = mcp_server_entry.state_version;
@@ -399,7 +522,15 @@ fn process_update_mcp_server_details(
         return Err(e.into());
     }
 
+    mcp_server_entry.end_operation();
+
+    // Resize the account to fit the updated entry, topping up or refunding rent
+    let space = mcp_server_entry.required_space()?;
+    drop(data);
+    resize_account(mcp_server_entry_info, payer_info, system_program_info, space)?;
+
     // SECURITY FIX: Serialize safely after atomic update
+    let mut data = mcp_server_entry_info.try_borrow_mut_data()?;
     mcp_server_entry.serialize(&mut &mut data[..])?;
 
     // Log event
@@ -413,6 +544,7 @@ fn process_update_mcp_server_status(
     program_id: &Pubkey,
     accounts: &[AccountInfo],
     new_status: u8,
+    expected_state_version: Option<u64>,
 ) -> ProgramResult {
     let accounts_iter = &mut accounts.iter();
     let mcp_server_entry_info = next_account_info(accounts_iter)?;
@@ -433,13 +565,21 @@ fn process_update_mcp_server_status(
     verify_account_owner(mcp_server_entry_info, program_id)?;
     
     let mut data = mcp_server_entry_info.try_borrow_mut_data()?;
-    let mut mcp_server_entry = McpServerRegistryEntryV1::try_from_slice(&data)?;
+    let mut mcp_server_entry = McpServerRegistryEntryV1::try_from_account_data(&data)?;
 
     // Verify ownership
     if mcp_server_entry.owner_authority != *owner_authority_info.key {
         return Err(RegistryError::Unauthorized.into());
     }
 
+    // Reject stale writers when the caller pinned a state version
+    mcp_server_entry.check_expected_version(expected_state_version)?;
+
+    // A chunked registration only leaves Pending through finalize
+    if mcp_server_entry.registration_open {
+        return Err(RegistryError::RegistrationNotFinalized.into());
+    }
+
     // Return early if status is the same
     if mcp_server_entry.status == new_status {
         return Ok(());
@@ -493,6 +633,9 @@ fn process_register_mcp_server_with_token(
     let token_program_info = next_account_info(accounts_iter)?;
     let system_program_info = next_account_info(accounts_iter)?;
     let clock_info = next_account_info(accounts_iter)?;
+    let config_info = next_account_info(accounts_iter)?;
+
+    let config = RegistryConfigV1::load(config_info, program_id)?.params;
 
     // Validate input
     validate_register_mcp_server(
@@ -517,25 +660,17 @@ fn process_register_mcp_server_with_token(
         return Err(ProgramError::MissingRequiredSignature);
     }
 
-    // Verify registration vault PDA
+    // Verify the token program, mint, owner token account and registration vault
+    verify_token_program(token_program_info)?;
+    verify_token_mint(token_mint_info, &config)?;
+    let owner_token_account =
+        verify_user_token_account(owner_token_account_info, owner_authority_info.key, &config)?;
     let (expected_vault, _) = derive_registration_vault_pda(program_id);
-    if registration_vault_info.key != &expected_vault {
-        return Err(RegistryError::InvalidPda.into());
-    }
-
-    // Verify token program
-    if token_program_info.key != &spl_token::id() {
-        return Err(ProgramError::IncorrectProgramId);
-    }
+    verify_vault_token_account(registration_vault_info, &expected_vault, &config)?;
 
     // Verify owner has sufficient balance for registration fee
-    // Read token account data manually
-    use spl_token::state::Account;
-    use solana_program::program_pack::Pack;
-    let owner_token_data = owner_token_account_info.try_borrow_data()?;
-    let owner_token_account = Account::unpack(&owner_token_data)?;
-    if owner_token_account.amount < MCP_REGISTRATION_FEE {
-        return Err(RegistryError::InsufficientStake.into());
+    if owner_token_account.amount < config.registration_fee {
+        return Err(RegistryError::InsufficientTokenBalance.into());
     }
 
     // First register the MCP server using existing logic
@@ -569,31 +704,29 @@ fn process_register_mcp_server_with_token(
         registration_vault_info,
         owner_authority_info,
         token_program_info,
-        MCP_REGISTRATION_FEE,
+        config.registration_fee,
     )?;
 
     // Update server entry with token info
     let mut data = mcp_server_entry_info.try_borrow_mut_data()?;
-    let mut server_entry = McpServerRegistryEntryV1::try_from_slice(&data)?;
+    let economics = &mut McpServerEntryPrefixV1::load_mut(&mut data)?.economics;
     
     // Update token-related fields
-    server_entry.token_mint = *token_mint_info.key;
-    server_entry.registration_fee_paid = MCP_REGISTRATION_FEE;
-    server_entry.total_fees_collected = MCP_REGISTRATION_FEE;
+    economics.token_mint = config.accepted_mint;
+    economics.registration_fee_paid = config.registration_fee;
+    economics.total_fees_collected = config.registration_fee;
     
     // Set default fee configuration
-    server_entry.tool_base_fee = MIN_TOOL_FEE;
-    server_entry.resource_base_fee = MIN_RESOURCE_FEE;
-    server_entry.prompt_base_fee = MIN_PROMPT_FEE;
-    server_entry.bulk_discount_threshold = 10; // Default to 10 calls for discount
-    server_entry.bulk_discount_percentage = 10; // Default to 10% discount
-    
-    server_entry.serialize(&mut &mut data[..])?;
+    economics.tool_base_fee = config.min_tool_fee;
+    economics.resource_base_fee = config.min_resource_fee;
+    economics.prompt_base_fee = config.min_prompt_fee;
+    economics.bulk_discount_threshold = 10; // Default to 10 calls for discount
+    economics.bulk_discount_percentage = 10; // Default to 10% discount
 
     // Emit event
     msg!(
         "EVENT: McpServerRegisteredWithToken server_id={} fee={}",
-        server_id, MCP_REGISTRATION_FEE
+        server_id, config.registration_fee
     );
 
     Ok(())
@@ -605,6 +738,7 @@ fn process_stake_for_verification(
     accounts: &[AccountInfo],
     amount: u64,
     lock_period: i64,
+    expected_state_version: Option<u64>,
 ) -> ProgramResult {
     let accounts_iter = &mut accounts.iter();
     let mcp_server_entry_info = next_account_info(accounts_iter)?;
@@ -612,35 +746,35 @@ fn process_stake_for_verification(
     let user_token_account_info = next_account_info(accounts_iter)?;
     let staking_vault_info = next_account_info(accounts_iter)?;
     let token_program_info = next_account_info(accounts_iter)?;
+    let config_info = next_account_info(accounts_iter)?;
 
     // Verify accounts and authority
     verify_account_owner(mcp_server_entry_info, program_id)?;
     if !owner_authority_info.is_signer {
         return Err(RegistryError::Unauthorized.into());
     }
+    let config = RegistryConfigV1::load(config_info, program_id)?.params;
 
-    // Load and verify server entry
+    // Token fields live in the fixed-size prefix; only the ID is decoded
     let mut data = mcp_server_entry_info.try_borrow_mut_data()?;
-    let mut server_entry = McpServerRegistryEntryV1::try_from_slice(&data)?;
+    let server_id = McpServerRegistryEntryV1::read_server_id(&data)?;
+    let server_entry = McpServerEntryPrefixV1::load_mut(&mut data)?;
 
-    if server_entry.owner_authority != *owner_authority_info.key {
+    if server_entry.header.owner_authority != *owner_authority_info.key {
         return Err(RegistryError::Unauthorized.into());
     }
 
-    // Validate staking parameters
-    if amount < MIN_STAKE_AMOUNT {
-        return Err(RegistryError::InsufficientStake.into());
-    }
+    // Reject stale writers when the caller pinned a state version
+    server_entry.header.check_expected_version(expected_state_version)?;
 
-    if lock_period < MIN_LOCK_PERIOD || lock_period > MAX_LOCK_PERIOD {
-        return Err(RegistryError::InvalidLockPeriod.into());
-    }
+    // Validate staking parameters; the tier check below enforces the minimum stake
+    config.check_lock_period(lock_period)?;
 
-    // Verify staking vault PDA (using standard staking vault)
+    // Verify the token accounts and the staking vault (using standard staking vault)
+    verify_token_program(token_program_info)?;
+    verify_user_token_account(user_token_account_info, owner_authority_info.key, &config)?;
     let (expected_vault, _) = derive_staking_vault_pda(program_id);
-    if staking_vault_info.key != &expected_vault {
-        return Err(RegistryError::InvalidPda.into());
-    }
+    verify_vault_token_account(staking_vault_info, &expected_vault, &config)?;
 
     // Transfer tokens to staking vault
     transfer_tokens_with_account_info(
@@ -655,50 +789,40 @@ fn process_stake_for_verification(
     let current_timestamp = get_current_timestamp()?;
     let stake_locked_until = current_timestamp + lock_period;
     
-    // Calculate verification tier based on staked amount (using correct A2AMPL values)
-    let verification_tier = if amount >= PREMIUM_SERVER_STAKE {
-        2 // Premium: 25,000 A2AMPL
-    } else if amount >= VERIFIED_SERVER_STAKE {
-        1 // Verified: 5,000 A2AMPL
-    } else if amount >= BASIC_SERVER_STAKE {
-        0 // Basic: 500 A2AMPL
-    } else {
-        return Err(RegistryError::InsufficientStake.into());
-    };
+    // Calculate verification tier from the configured thresholds
+    let verification_tier = VerificationTier::from_config(amount, &config)? as u8;
 
     // Calculate new total stake
-    let new_total_stake = server_entry.verification_stake + amount;
+    let economics = &mut server_entry.economics;
+    let new_total_stake = economics.verification_stake + amount;
 
     // Update server staking info
-    server_entry.verification_stake = new_total_stake;
-    server_entry.verification_tier = verification_tier;
-    server_entry.staking_timestamp = current_timestamp;
-    server_entry.stake_locked_until = stake_locked_until;
-    server_entry.last_update_timestamp = current_timestamp;
+    economics.update_verification_stake(
+        new_total_stake,
+        verification_tier,
+        stake_locked_until,
+        current_timestamp,
+    );
 
     // Calculate and update quality score with all uses
-    let total_uses = server_entry.total_tool_calls +
-                    server_entry.total_resource_accesses +
-                    server_entry.total_prompt_uses;
-    let quality_score = calculate_server_quality_score(
-        server_entry.uptime_percentage,
-        server_entry.avg_response_time,
-        server_entry.error_rate,
-        total_uses,
+    economics.quality_score = calculate_server_quality_score(
+        economics.uptime_percentage,
+        economics.avg_response_time,
+        economics.error_rate,
+        economics.total_uses(),
     );
-    server_entry.quality_score = quality_score;
-
-    server_entry.serialize(&mut &mut data[..])?;
+    server_entry.header.bump_state_version();
 
     msg!(
         "EVENT: StakeForVerification server_id={} amount={} lock_period={} tier={}",
-        server_entry.server_id, amount, lock_period, verification_tier
+        server_id, amount, lock_period, verification_tier
     );
 
     Ok(())
 }
 
 /// Process configure usage fees instruction
+#[allow(clippy::too_many_arguments)]
 fn process_configure_usage_fees(
     program_id: &Pubkey,
     accounts: &[AccountInfo],
@@ -707,25 +831,33 @@ fn process_configure_usage_fees(
     prompt_base_fee: u64,
     bulk_discount_threshold: u32,
     bulk_discount_percentage: u8,
+    free_tier: FreeTier,
+    expected_state_version: Option<u64>,
 ) -> ProgramResult {
     let accounts_iter = &mut accounts.iter();
     let mcp_server_entry_info = next_account_info(accounts_iter)?;
     let owner_authority_info = next_account_info(accounts_iter)?;
+    let config_info = next_account_info(accounts_iter)?;
 
     // Verify authority
     verify_account_owner(mcp_server_entry_info, program_id)?;
     if !owner_authority_info.is_signer {
         return Err(RegistryError::Unauthorized.into());
     }
+    let config = RegistryConfigV1::load(config_info, program_id)?.params;
 
     // Load server entry
     let mut data = mcp_server_entry_info.try_borrow_mut_data()?;
-    let mut server_entry = McpServerRegistryEntryV1::try_from_slice(&data)?;
+    let server_id = McpServerRegistryEntryV1::read_server_id(&data)?;
+    let server_entry = McpServerEntryPrefixV1::load_mut(&mut data)?;
 
-    if server_entry.owner_authority != *owner_authority_info.key {
+    if server_entry.header.owner_authority != *owner_authority_info.key {
         return Err(RegistryError::Unauthorized.into());
     }
 
+    // Reject stale writers when the caller pinned a state version
+    server_entry.header.check_expected_version(expected_state_version)?;
+
     // Validate fee parameters
     validate_fee_configuration(
         tool_base_fee,
@@ -733,35 +865,57 @@ fn process_configure_usage_fees(
         prompt_base_fee,
         bulk_discount_threshold,
         bulk_discount_percentage,
+        &config,
     )?;
+    free_tier.validate()?;
 
-    // Update fee configuration
+    // Decreases apply now, increases after the config's notice period
     let current_timestamp = get_current_timestamp()?;
-    server_entry.update_usage_fees(
+    let effective_at = server_entry.economics.schedule_usage_fees(
         tool_base_fee,
         resource_base_fee,
         prompt_base_fee,
         bulk_discount_threshold,
         bulk_discount_percentage,
         current_timestamp,
+        config.fee_increase_notice,
     );
+    server_entry.economics.set_free_tier(free_tier);
+    server_entry.header.bump_state_version();
 
-    server_entry.serialize(&mut &mut data[..])?;
-
+    let economics = &server_entry.economics;
     msg!(
         "EVENT: FeeConfigurationUpdated server_id={} tool_fee={} resource_fee={} prompt_fee={}",
-        server_entry.server_id, tool_base_fee, resource_base_fee, prompt_base_fee
+        server_id, { economics.tool_base_fee }, { economics.resource_base_fee }, { economics.prompt_base_fee }
     );
+    if free_tier.is_enabled() {
+        msg!(
+            "EVENT: FreeTierUpdated server_id={} tool_calls={} resource_calls={} prompt_calls={} period={}",
+            server_id, free_tier.tool_calls, free_tier.resource_calls, free_tier.prompt_calls, free_tier.period
+        );
+    }
+    if let Some(effective_at) = effective_at {
+        msg!(
+            "EVENT: FeeChangeScheduled server_id={} tool_fee={} resource_fee={} prompt_fee={} bulk_discount_threshold={} bulk_discount_percentage={} effective_at={}",
+            server_id, tool_base_fee, resource_base_fee, prompt_base_fee,
+            bulk_discount_threshold, bulk_discount_percentage, effective_at
+        );
+    }
 
     Ok(())
 }
 
 /// Process record usage and collect fee instruction
+#[allow(clippy::too_many_arguments)]
 fn process_record_usage_and_collect_fee(
     program_id: &Pubkey,
     accounts: &[AccountInfo],
     usage_type: UsageType,
     count: u32,
+    item_name: Option<String>,
+    max_total_fee: u64,
+    idempotency_key: [u8; IDEMPOTENCY_KEY_LEN],
+    expected_state_version: Option<u64>,
 ) -> ProgramResult {
     let accounts_iter = &mut accounts.iter();
     let mcp_server_entry_info = next_account_info(accounts_iter)?;
@@ -769,6 +923,14 @@ fn process_record_usage_and_collect_fee(
     let user_token_account_info = next_account_info(accounts_iter)?;
     let server_fee_vault_info = next_account_info(accounts_iter)?;
     let token_program_info = next_account_info(accounts_iter)?;
+    let config_info = next_account_info(accounts_iter)?;
+    let treasury_vault_info = next_account_info(accounts_iter)?;
+    let pricing_table_info = next_account_info(accounts_iter)?;
+    let receipt_info = next_account_info(accounts_iter)?;
+    let system_program_info = next_account_info(accounts_iter)?;
+    let subscription_info = next_account_info(accounts_iter)?;
+    let usage_record_info = next_account_info(accounts_iter)?;
+    let split_info = next_account_info(accounts_iter)?;
 
     // Verify accounts
     verify_account_owner(mcp_server_entry_info, program_id)?;
@@ -776,75 +938,187 @@ fn process_record_usage_and_collect_fee(
         return Err(RegistryError::MissingRequiredSignature.into());
     }
 
+    // Verify the token accounts and the fee vault
+    let config = RegistryConfigV1::load(config_info, program_id)?.params;
+    verify_token_program(token_program_info)?;
+    verify_user_token_account(user_token_account_info, user_authority_info.key, &config)?;
+    let (expected_vault, _) = derive_server_fee_vault_pda(mcp_server_entry_info.key, program_id);
+    let vault_balance = verify_vault_token_account(server_fee_vault_info, &expected_vault, &config)?.amount;
+    let (expected_treasury, _) = derive_treasury_vault_pda(program_id);
+    verify_vault_token_account(treasury_vault_info, &expected_treasury, &config)?;
+    let current_timestamp = get_current_timestamp()?;
+    let mut pricing_table = load_pricing_table(pricing_table_info, mcp_server_entry_info.key, program_id)?;
+    if let Some(table) = pricing_table.as_mut() {
+        // Price increases that came due take effect before pricing this charge
+        table.apply_due_price_change(current_timestamp);
+    }
+    let mut subscription = load_subscription(
+        subscription_info,
+        mcp_server_entry_info.key,
+        user_authority_info.key,
+        program_id,
+    )?;
+    let (expected_record, record_bump) =
+        derive_usage_record_pda(mcp_server_entry_info.key, user_authority_info.key, program_id);
+    if usage_record_info.key != &expected_record {
+        return Err(RegistryError::InvalidPda.into());
+    }
+    let mut usage_record = if usage_record_info.data_is_empty() {
+        None
+    } else {
+        verify_account_owner(usage_record_info, program_id)?;
+        Some(McpUsageRecordV1::try_from_account_data(&usage_record_info.try_borrow_data()?)?)
+    };
+    let record_is_new = usage_record.is_none();
+    let usage_record = usage_record.get_or_insert_with(|| {
+        McpUsageRecordV1::new(record_bump, *mcp_server_entry_info.key, *user_authority_info.key, current_timestamp)
+    });
+
     // Load server entry
     let mut data = mcp_server_entry_info.try_borrow_mut_data()?;
-    let mut server_entry = McpServerRegistryEntryV1::try_from_slice(&data)?;
+    let server_id = McpServerRegistryEntryV1::read_server_id(&data)?;
+    let server_entry = McpServerEntryPrefixV1::load_mut(&mut data)?;
+
+    // Reject stale writers when the caller pinned a state version
+    server_entry.header.check_expected_version(expected_state_version)?;
 
     // Ensure server is active
     if !server_entry.is_active() {
         return Err(RegistryError::InvalidMcpServerStatus.into());
     }
 
-    // Calculate fee based on usage type and count
-    let base_fee = match usage_type {
-        UsageType::Tool => server_entry.tool_base_fee,
-        UsageType::Resource => server_entry.resource_base_fee,
-        UsageType::Prompt => server_entry.prompt_base_fee,
+    // Fee increases that came due take effect before pricing this charge
+    server_entry.economics.apply_due_fee_change(current_timestamp);
+
+    // Free-tier calls left in the user's window come first, then calls left
+    // in an active subscription, and the rest pay fees
+    let state_usage_type = to_state_usage_type(usage_type);
+    let free_tier = server_entry.economics.free_tier();
+    usage_record.roll_window(free_tier.period, current_timestamp);
+    let free = usage_record.free_calls_left(state_usage_type, &free_tier).min(count);
+    let covered = subscription
+        .as_mut()
+        .map_or(0, |subscription| subscription.consume(state_usage_type, count - free, current_timestamp));
+    let charged = count - free - covered;
+
+    // A type the pricing table prices is charged only for its priced items,
+    // which must be named; other types pay the entry's base fee
+    let economics = &mut server_entry.economics;
+    let unit_fee = match &pricing_table {
+        Some(table) if table.prices_usage_type(state_usage_type) => item_name
+            .as_deref()
+            .and_then(|name| table.item_price(state_usage_type, &hash_item_name(name)))
+            .ok_or(RegistryError::UnknownPricedItem)?,
+        _ => economics.base_fee(state_usage_type),
     };
 
-    if base_fee == 0 {
+    if charged > 0 && unit_fee == 0 {
         return Err(RegistryError::FeeTooLow.into());
     }
 
-    // Calculate total fee with bulk discount
-    let mut total_fee = base_fee * count as u64;
-    
-    // Apply bulk discount if applicable
-    if count >= server_entry.bulk_discount_threshold && server_entry.bulk_discount_percentage > 0 {
-        let discount = (total_fee * server_entry.bulk_discount_percentage as u64) / 100;
-        total_fee = total_fee.saturating_sub(discount);
-    }
-
-    // Transfer fee from user to server vault
-    transfer_tokens_with_pda_signer_account_info(
-        user_token_account_info,
-        server_fee_vault_info,
+    // Discount tiers of the pricing table replace the single bulk discount:
+    // with tiers, a charge below the lowest one gets no discount at all. The
+    // bulk discount only applies when there is no table or it has no tiers.
+    let discount_percentage = match pricing_table.as_ref().and_then(|table| table.discount_percentage(charged)) {
+        Some(tier_percentage) => tier_percentage,
+        None if charged >= economics.bulk_discount_threshold => economics.bulk_discount_percentage,
+        None => 0,
+    };
+    let total_fee = discounted_total(unit_fee, charged, discount_percentage)
+        .ok_or(ProgramError::ArithmeticOverflow)?;
+    check_max_total_fee(total_fee, max_total_fee)?;
+    record_usage_receipt(
+        receipt_info,
         user_authority_info,
-        token_program_info,
+        system_program_info,
+        mcp_server_entry_info.key,
+        user_authority_info.key,
+        &idempotency_key,
         total_fee,
-        &[], // No PDA signing needed for user-to-vault transfer
+        program_id,
     )?;
 
-    // Record usage and update metrics (convert instruction::UsageType to state::UsageType)
-    use crate::instruction::UsageType as InstructionUsageType;
-    use crate::state::UsageType as StateUsageType;
-    
-    let state_usage_type = match usage_type {
-        InstructionUsageType::Tool => StateUsageType::Tool,
-        InstructionUsageType::Resource => StateUsageType::Resource,
-        InstructionUsageType::Prompt => StateUsageType::Prompt,
-    };
-    server_entry.record_usage(state_usage_type, count, total_fee);
+    // The protocol take-rate goes to the treasury, the rest to the server vault
+    let protocol_fee = bps_share(total_fee, config.protocol_fee_bps);
+    let server_fee = total_fee - protocol_fee;
+    if protocol_fee > 0 {
+        transfer_tokens_with_pda_signer_account_info(
+            user_token_account_info,
+            treasury_vault_info,
+            user_authority_info,
+            token_program_info,
+            protocol_fee,
+            &[],
+        )?;
+    }
+
+    // Transfer fee from user to server vault
+    if server_fee > 0 {
+        transfer_tokens_with_pda_signer_account_info(
+            user_token_account_info,
+            server_fee_vault_info,
+            user_authority_info,
+            token_program_info,
+            server_fee,
+            &[], // No PDA signing needed for user-to-vault transfer
+        )?;
+    }
+    if let (Some(subscription), true) = (&subscription, covered > 0) {
+        subscription.serialize(&mut &mut subscription_info.try_borrow_mut_data()?[..])?;
+    }
+
+    // Track the user's own usage next to the server-wide counters
+    usage_record.record(state_usage_type, count, free, total_fee, current_timestamp);
+    if record_is_new {
+        create_pda_account(
+            user_authority_info,
+            usage_record_info,
+            system_program_info,
+            program_id,
+            McpUsageRecordV1::SPACE,
+            &[
+                USAGE_RECORD_SEED,
+                mcp_server_entry_info.key.as_ref(),
+                user_authority_info.key.as_ref(),
+                &[record_bump],
+            ],
+        )?;
+    }
+    usage_record.serialize(&mut &mut usage_record_info.try_borrow_mut_data()?[..])?;
+
+    // Record usage and update metrics
+    economics.record_usage(state_usage_type, count, server_fee);
+    let owed = RevenueSplitV1::load(split_info, mcp_server_entry_info.key, program_id)?
+        .as_ref()
+        .map_or(0, RevenueSplitV1::total_owed);
+    check_fee_vault_solvency(vault_balance.saturating_add(server_fee), economics.pending_fees + owed)?;
 
     // Update quality score based on successful usage
-    let quality_score = calculate_mcp_quality_score(
-        server_entry.uptime_percentage,
-        server_entry.avg_response_time,
-        server_entry.error_rate,
-        server_entry.verification_tier,
+    economics.quality_score = calculate_mcp_quality_score(
+        economics.uptime_percentage,
+        economics.avg_response_time,
+        economics.error_rate,
+        economics.verification_tier,
     );
-    server_entry.quality_score = quality_score;
-
-    server_entry.serialize(&mut &mut data[..])?;
+    server_entry.header.bump_state_version();
 
     msg!(
-        "EVENT: UsageRecorded server_id={} usage_type={:?} count={} fee_collected={}",
-        server_entry.server_id, usage_type, count, total_fee
+        "EVENT: UsageRecorded server_id={} usage_type={:?} item={} count={} free={} covered_by_subscription={} fee_collected={} protocol_fee={}",
+        server_id, usage_type, item_name.as_deref().unwrap_or("-"), count, free, covered, server_fee, protocol_fee
     );
 
     Ok(())
 }
 
+/// Convert instruction::UsageType to state::UsageType
+fn to_state_usage_type(usage_type: UsageType) -> StateUsageType {
+    match usage_type {
+        UsageType::Tool => StateUsageType::Tool,
+        UsageType::Resource => StateUsageType::Resource,
+        UsageType::Prompt => StateUsageType::Prompt,
+    }
+}
+
 /// Process update quality metrics instruction
 fn process_update_quality_metrics(
     program_id: &Pubkey,
@@ -852,6 +1126,7 @@ fn process_update_quality_metrics(
     uptime_percentage: u8,
     avg_response_time: u32,
     error_rate: u8,
+    expected_state_version: Option<u64>,
 ) -> ProgramResult {
     let accounts_iter = &mut accounts.iter();
     let mcp_server_entry_info = next_account_info(accounts_iter)?;
@@ -865,12 +1140,16 @@ fn process_update_quality_metrics(
 
     // Load server entry
     let mut data = mcp_server_entry_info.try_borrow_mut_data()?;
-    let mut server_entry = McpServerRegistryEntryV1::try_from_slice(&data)?;
+    let server_id = McpServerRegistryEntryV1::read_server_id(&data)?;
+    let server_entry = McpServerEntryPrefixV1::load_mut(&mut data)?;
 
-    if server_entry.owner_authority != *owner_authority_info.key {
+    if server_entry.header.owner_authority != *owner_authority_info.key {
         return Err(RegistryError::Unauthorized.into());
     }
 
+    // Reject stale writers when the caller pinned a state version
+    server_entry.header.check_expected_version(expected_state_version)?;
+
     // Validate metrics parameters
     if uptime_percentage > 100 {
         return Err(RegistryError::InvalidAccountData.into());
@@ -885,22 +1164,21 @@ fn process_update_quality_metrics(
         uptime_percentage,
         avg_response_time,
         error_rate,
-        server_entry.verification_tier,
+        server_entry.economics.verification_tier,
     );
 
     // Update quality metrics
-    server_entry.update_quality_metrics(
+    server_entry.economics.update_quality_metrics(
         uptime_percentage,
         avg_response_time,
         error_rate,
         quality_score,
     );
-
-    server_entry.serialize(&mut &mut data[..])?;
+    server_entry.header.bump_state_version();
 
     msg!(
         "EVENT: QualityMetricsUpdated server_id={} uptime={}% response_time={}ms error_rate={}% quality_score={}",
-        server_entry.server_id, uptime_percentage, avg_response_time, error_rate, quality_score
+        server_id, uptime_percentage, avg_response_time, error_rate, quality_score
     );
 
     Ok(())
@@ -910,6 +1188,7 @@ fn process_update_quality_metrics(
 fn process_withdraw_pending_fees(
     program_id: &Pubkey,
     accounts: &[AccountInfo],
+    expected_state_version: Option<u64>,
 ) -> ProgramResult {
     let accounts_iter = &mut accounts.iter();
     let mcp_server_entry_info = next_account_info(accounts_iter)?;
@@ -917,64 +1196,79 @@ fn process_withdraw_pending_fees(
     let server_fee_vault_info = next_account_info(accounts_iter)?;
     let owner_token_account_info = next_account_info(accounts_iter)?;
     let token_program_info = next_account_info(accounts_iter)?;
+    let config_info = next_account_info(accounts_iter)?;
+    let split_info = next_account_info(accounts_iter)?;
 
     // Verify authority
     verify_account_owner(mcp_server_entry_info, program_id)?;
     if !owner_authority_info.is_signer {
         return Err(RegistryError::Unauthorized.into());
     }
+    let config = RegistryConfigV1::load(config_info, program_id)?.params;
+    verify_token_program(token_program_info)?;
+    verify_user_token_account(owner_token_account_info, owner_authority_info.key, &config)?;
 
     // Load server entry
     let mut data = mcp_server_entry_info.try_borrow_mut_data()?;
-    let mut server_entry = McpServerRegistryEntryV1::try_from_slice(&data)?;
+    let server_id = McpServerRegistryEntryV1::read_server_id(&data)?;
+    let server_entry = McpServerEntryPrefixV1::load_mut(&mut data)?;
 
-    if server_entry.owner_authority != *owner_authority_info.key {
+    if server_entry.header.owner_authority != *owner_authority_info.key {
         return Err(RegistryError::Unauthorized.into());
     }
 
-    // Check if there are any pending fees to withdraw
-    if server_entry.pending_fees == 0 {
+    // Reject stale writers when the caller pinned a state version
+    server_entry.header.check_expected_version(expected_state_version)?;
+
+    // Shares held back from earlier withdrawals are still in the vault
+    let mut split = RevenueSplitV1::load(split_info, mcp_server_entry_info.key, program_id)?;
+    let owed = split.as_ref().map_or(0, RevenueSplitV1::total_owed);
+
+    // Check if there is anything to withdraw
+    if server_entry.economics.pending_fees == 0 && owed == 0 {
         return Err(RegistryError::InsufficientFunds.into());
     }
 
-    let withdrawal_amount = server_entry.withdraw_pending_fees();
+    // Fees are paid out of this server's own vault, which must cover them
+    let (vault_pda, vault_bump) = derive_server_fee_vault_pda(mcp_server_entry_info.key, program_id);
+    let vault = verify_vault_token_account(server_fee_vault_info, &vault_pda, &config)?;
+    check_fee_vault_solvency(vault.amount, server_entry.economics.pending_fees + owed)?;
 
-    // Transfer fees from server vault to owner account
-    // Note: This requires the server vault PDA to be properly derived and authorized
-    let (vault_pda, vault_bump) = derive_mcp_staking_vault_pda(
-        &server_entry.server_id,
-        owner_authority_info.key,
-        program_id,
-    );
-
-    if *server_fee_vault_info.key != vault_pda {
-        return Err(RegistryError::InvalidPda.into());
-    }
+    let withdrawal_amount = server_entry.economics.withdraw_pending_fees();
+    server_entry.header.bump_state_version();
 
-    // Create proper seeds for PDA signing
     let vault_seeds = &[
-        b"staking_vault",
-        server_entry.server_id.as_bytes(),
-        owner_authority_info.key.as_ref(),
+        FEE_VAULT_SEED,
+        mcp_server_entry_info.key.as_ref(),
         &[vault_bump],
     ];
-    
-    transfer_tokens_with_pda_signer_account_info(
+
+    // Co-builders named in the revenue split are paid first, the owner gets
+    // the rest. Shares of recipients whose token account is closed or frozen
+    // stay in the vault, owed to them, so they cannot block the owner.
+    let (shares, owner_amount) = pay_out_with_split(
         server_fee_vault_info,
-        owner_token_account_info,
         server_fee_vault_info, // Vault is the authority
         token_program_info,
+        owner_token_account_info,
+        split.as_mut(),
+        true,
+        accounts_iter,
+        &config,
         withdrawal_amount,
         &[vault_seeds],
     )?;
+    if let Some(split) = &split {
+        split.serialize(&mut &mut split_info.try_borrow_mut_data()?[..])?;
+    }
 
     // Update last fee collection timestamp
-    server_entry.last_fee_collection = get_current_timestamp()?;
-    server_entry.serialize(&mut &mut data[..])?;
+    server_entry.economics.last_fee_collection = get_current_timestamp()?;
 
     msg!(
-        "EVENT: FeesWithdrawn server_id={} amount={}",
-        server_entry.server_id, withdrawal_amount
+        "EVENT: FeesWithdrawn server_id={} amount={} split_amount={} owner_amount={} owed={}",
+        server_id, withdrawal_amount, shares.iter().sum::<u64>(), owner_amount,
+        split.as_ref().map_or(0, RevenueSplitV1::total_owed)
     );
 
     Ok(())
@@ -984,6 +1278,7 @@ fn process_withdraw_pending_fees(
 fn process_deregister_mcp_server(
     program_id: &Pubkey,
     accounts: &[AccountInfo],
+    expected_state_version: Option<u64>,
 ) -> ProgramResult {
     let accounts_iter = &mut accounts.iter();
     let mcp_server_entry_info = next_account_info(accounts_iter)?;
@@ -1001,13 +1296,16 @@ fn process_deregister_mcp_server(
     verify_account_owner(mcp_server_entry_info, program_id)?;
     
     let mut data = mcp_server_entry_info.try_borrow_mut_data()?;
-    let mut mcp_server_entry = McpServerRegistryEntryV1::try_from_slice(&data)?;
+    let mut mcp_server_entry = McpServerRegistryEntryV1::try_from_account_data(&data)?;
 
     // Verify ownership
     if mcp_server_entry.owner_authority != *owner_authority_info.key {
         return Err(RegistryError::Unauthorized.into());
     }
 
+    // Reject stale writers when the caller pinned a state version
+    mcp_server_entry.check_expected_version(expected_state_version)?;
+
     // Return early if already deregistered
     if mcp_server_entry.status == McpServerStatus::Deregistered as u8 {
         return Ok(());
@@ -1035,101 +1333,1269 @@ fn process_deregister_mcp_server(
 
     Ok(())
 }
-// Utility functions for MCP Server Registry token integration
 
-/// Derive MCP server staking vault PDA
-fn derive_mcp_staking_vault_pda(
-    server_id: &str,
-    owner: &Pubkey,
+/// Process initialize tool account instruction
+fn process_initialize_tool_account(
     program_id: &Pubkey,
-) -> (Pubkey, u8) {
-    Pubkey::find_program_address(
-        &[
-            STAKING_VAULT_SEED,
-            server_id.as_bytes(),
-            owner.as_ref(),
-        ],
+    accounts: &[AccountInfo],
+    index: u8,
+    tool: McpToolDefinitionOnChainInput,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    let tool_account_info = next_account_info(accounts_iter)?;
+    let mcp_server_entry_info = next_account_info(accounts_iter)?;
+    let owner_authority_info = next_account_info(accounts_iter)?;
+    let payer_info = next_account_info(accounts_iter)?;
+    let system_program_info = next_account_info(accounts_iter)?;
+
+    validate_tool_definition(&tool)?;
+
+    // Verify owner authority and payer are signers
+    if !owner_authority_info.is_signer {
+        return Err(RegistryError::Unauthorized.into());
+    }
+    if !payer_info.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    verify_account_owner(mcp_server_entry_info, program_id)?;
+
+    let mut data = mcp_server_entry_info.try_borrow_mut_data()?;
+    let mut mcp_server_entry = McpServerRegistryEntryV1::try_from_account_data(&data)?;
+
+    if mcp_server_entry.owner_authority != *owner_authority_info.key {
+        return Err(RegistryError::Unauthorized.into());
+    }
+
+    // Link the new index first; this rejects gaps and enforces the cap
+    mcp_server_entry.push_tool_account(index)?;
+
+    let (expected_pda, bump) = get_tool_account_pda(mcp_server_entry_info.key, index, program_id);
+    if *tool_account_info.key != expected_pda {
+        return Err(ProgramError::InvalidSeeds);
+    }
+    if !tool_account_info.data_is_empty() {
+        return Err(RegistryError::AccountAlreadyExists.into());
+    }
+
+    let timestamp = get_current_timestamp()?;
+    let tool_account = McpToolAccountV1::new(
+        bump,
+        *mcp_server_entry_info.key,
+        index,
+        tool.into(),
+        timestamp,
+    );
+
+    create_pda_account(
+        payer_info,
+        tool_account_info,
+        system_program_info,
         program_id,
-    )
-}
+        tool_account.required_space()?,
+        &[TOOL_ACCOUNT_SEED, mcp_server_entry_info.key.as_ref(), &[index], &[bump]],
+    )?;
 
-/// Calculate MCP server quality score based on performance metrics
-fn calculate_mcp_quality_score(
-    uptime_percentage: u8,
-    avg_response_time: u32,
-    error_rate: u8,
-    verification_tier: u8,
-) -> u64 {
-    let mut score = 0u64;
-    
-    // Uptime component (40% of score)
-    score += (uptime_percentage as u64 * 40) / 100;
-    
-    // Response time component (30% of score) - lower is better
-    let response_score = if avg_response_time <= 100 {
-        30
-    } else if avg_response_time <= 250 {
-        25
-    } else if avg_response_time <= 500 {
-        20
-    } else if avg_response_time <= 1000 {
-        15
-    } else {
-        10
-    };
-    score += response_score;
-    
-    // Error rate component (20% of score) - lower is better
-    let error_score = if error_rate == 0 {
-        20
-    } else if error_rate <= 1 {
-        18
-    } else if error_rate <= 2 {
-        15
-    } else if error_rate <= 5 {
-        10
-    } else {
-        5
-    };
-    score += error_score;
-    
-    // Verification tier bonus (10% of score)
-    let tier_bonus = match verification_tier {
-        2 => 10, // Premium
-        1 => 7,  // Verified
-        _ => 3,  // Basic
-    };
-    score += tier_bonus;
-    
-    // Convert to basis points (score * 100 for 2 decimal precision)
-    score * 100
+    let mut tool_data = tool_account_info.try_borrow_mut_data()?;
+    tool_account.serialize(&mut &mut tool_data[..])?;
+
+    mcp_server_entry.last_update_timestamp = timestamp;
+    mcp_server_entry.serialize(&mut &mut data[..])?;
+
+    msg!(
+        "EVENT: ToolAccountInitialized server_id={} index={} tool={}",
+        mcp_server_entry.server_id, index, tool_account.tool.name
+    );
+
+    Ok(())
 }
 
-/// Validate fee configuration parameters
-fn validate_fee_configuration(
-    tool_base_fee: u64,
-    resource_base_fee: u64,
-    prompt_base_fee: u64,
-    bulk_discount_threshold: u32,
-    bulk_discount_percentage: u8,
-) -> Result<(), RegistryError> {
-    use aeamcp_common::constants::{MIN_TOOL_FEE, MIN_RESOURCE_FEE, MIN_PROMPT_FEE, MAX_BULK_DISCOUNT};
-    
+/// Process update tool account instruction
+fn process_update_tool_account(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    index: u8,
+    tool: McpToolDefinitionOnChainInput,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    let tool_account_info = next_account_info(accounts_iter)?;
+    let mcp_server_entry_info = next_account_info(accounts_iter)?;
+    let owner_authority_info = next_account_info(accounts_iter)?;
+    let payer_info = next_account_info(accounts_iter)?;
+    let system_program_info = next_account_info(accounts_iter)?;
+
+    validate_tool_definition(&tool)?;
+
+    if !owner_authority_info.is_signer {
+        return Err(RegistryError::Unauthorized.into());
+    }
+
+    verify_account_owner(mcp_server_entry_info, program_id)?;
+    verify_account_owner(tool_account_info, program_id)?;
+
+    let mcp_server_entry = McpServerRegistryEntryV1::try_from_account_data(&mcp_server_entry_info.try_borrow_data()?)?;
+    if mcp_server_entry.owner_authority != *owner_authority_info.key {
+        return Err(RegistryError::Unauthorized.into());
+    }
+
+    let mut tool_account = McpToolAccountV1::try_from_account_data(&tool_account_info.try_borrow_data()?)?;
+    if tool_account.server_entry != *mcp_server_entry_info.key || tool_account.index != index {
+        return Err(RegistryError::InvalidPda.into());
+    }
+
+    tool_account.tool = tool.into();
+    tool_account.last_update_timestamp = get_current_timestamp()?;
+
+    // Resize to fit the new definition, topping up or refunding rent
+    let space = tool_account.required_space()?;
+    resize_account(tool_account_info, payer_info, system_program_info, space)?;
+
+    let mut tool_data = tool_account_info.try_borrow_mut_data()?;
+    tool_account.serialize(&mut &mut tool_data[..])?;
+
+    msg!(
+        "EVENT: ToolAccountUpdated server_id={} index={} tool={}",
+        mcp_server_entry.server_id, index, tool_account.tool.name
+    );
+
+    Ok(())
+}
+
+/// Process close tool account instruction
+fn process_close_tool_account(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    index: u8,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    let tool_account_info = next_account_info(accounts_iter)?;
+    let mcp_server_entry_info = next_account_info(accounts_iter)?;
+    let owner_authority_info = next_account_info(accounts_iter)?;
+    let recipient_info = next_account_info(accounts_iter)?;
+
+    if !owner_authority_info.is_signer {
+        return Err(RegistryError::Unauthorized.into());
+    }
+
+    verify_account_owner(mcp_server_entry_info, program_id)?;
+    verify_account_owner(tool_account_info, program_id)?;
+
+    let mut data = mcp_server_entry_info.try_borrow_mut_data()?;
+    let mut mcp_server_entry = McpServerRegistryEntryV1::try_from_account_data(&data)?;
+    if mcp_server_entry.owner_authority != *owner_authority_info.key {
+        return Err(RegistryError::Unauthorized.into());
+    }
+
+    let tool_account = McpToolAccountV1::try_from_account_data(&tool_account_info.try_borrow_data()?)?;
+    if tool_account.server_entry != *mcp_server_entry_info.key || tool_account.index != index {
+        return Err(RegistryError::InvalidPda.into());
+    }
+
+    // Only the last account can be closed so the remaining indices stay contiguous
+    mcp_server_entry.pop_tool_account(index)?;
+    mcp_server_entry.last_update_timestamp = get_current_timestamp()?;
+
+    close_account(tool_account_info, recipient_info)?;
+    mcp_server_entry.serialize(&mut &mut data[..])?;
+
+    msg!(
+        "EVENT: ToolAccountClosed server_id={} index={} remaining={}",
+        mcp_server_entry.server_id, index, mcp_server_entry.tool_account_count
+    );
+
+    Ok(())
+}
+/// Process begin MCP server registration instruction
+#[allow(clippy::too_many_arguments)]
+fn process_begin_mcp_server_registration(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    server_id: String,
+    name: String,
+    server_version: String,
+    service_endpoint: String,
+    documentation_url: Option<String>,
+    server_capabilities_summary: Option<String>,
+    supports_resources: bool,
+    supports_tools: bool,
+    supports_prompts: bool,
+    full_capabilities_uri: Option<String>,
+    tags: Vec<String>,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    let mcp_server_entry_info = next_account_info(accounts_iter)?;
+    let owner_authority_info = next_account_info(accounts_iter)?;
+    let payer_info = next_account_info(accounts_iter)?;
+    let system_program_info = next_account_info(accounts_iter)?;
+
+    // Definitions arrive in later chunks
+    validate_register_mcp_server(
+        &server_id,
+        &name,
+        &server_version,
+        &service_endpoint,
+        &documentation_url,
+        &server_capabilities_summary,
+        &[],
+        &[],
+        &[],
+        &full_capabilities_uri,
+        &tags,
+    )?;
+
+    if !owner_authority_info.is_signer {
+        return Err(RegistryError::Unauthorized.into());
+    }
+    if !payer_info.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    let (expected_pda, bump) = get_mcp_server_pda_secure(&server_id, owner_authority_info.key, program_id);
+    if *mcp_server_entry_info.key != expected_pda {
+        return Err(ProgramError::InvalidSeeds);
+    }
+
+    let timestamp = get_current_timestamp()?;
+    let mut mcp_server_entry = McpServerRegistryEntryV1::new(
+        bump,
+        *owner_authority_info.key,
+        server_id.clone(),
+        name,
+        server_version,
+        service_endpoint,
+        documentation_url,
+        server_capabilities_summary,
+        supports_resources,
+        supports_tools,
+        supports_prompts,
+        Vec::new(),
+        Vec::new(),
+        Vec::new(),
+        full_capabilities_uri,
+        tags,
+        timestamp,
+    );
+    mcp_server_entry.registration_open = true;
+
+    create_pda_account(
+        payer_info,
+        mcp_server_entry_info,
+        system_program_info,
+        program_id,
+        mcp_server_entry.required_space()?,
+        &[
+            MCP_SERVER_REGISTRY_PDA_SEED,
+            server_id.as_bytes(),
+            owner_authority_info.key.as_ref(),
+            &[bump],
+        ],
+    )?;
+
+    let mut data = mcp_server_entry_info.try_borrow_mut_data()?;
+    mcp_server_entry.serialize(&mut &mut data[..])?;
+
+    msg!("EVENT: McpServerRegistrationStarted server_id={}", server_id);
+
+    Ok(())
+}
+
+/// Apply one append chunk of a chunked registration, growing the entry
+/// account to fit. `apply` enforces the open registration and limits.
+fn process_append_registration_chunk<F>(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    field_name: &str,
+    apply: F,
+) -> ProgramResult
+where
+    F: FnOnce(&mut McpServerRegistryEntryV1) -> Result<(), RegistryError>,
+{
+    let accounts_iter = &mut accounts.iter();
+    let mcp_server_entry_info = next_account_info(accounts_iter)?;
+    let owner_authority_info = next_account_info(accounts_iter)?;
+    let payer_info = next_account_info(accounts_iter)?;
+    let system_program_info = next_account_info(accounts_iter)?;
+
+    if !owner_authority_info.is_signer {
+        return Err(RegistryError::Unauthorized.into());
+    }
+
+    verify_account_owner(mcp_server_entry_info, program_id)?;
+
+    let data = mcp_server_entry_info.try_borrow_data()?;
+    let mut mcp_server_entry = McpServerRegistryEntryV1::try_from_account_data(&data)?;
+    drop(data);
+
+    if mcp_server_entry.owner_authority != *owner_authority_info.key {
+        return Err(RegistryError::Unauthorized.into());
+    }
+
+    apply(&mut mcp_server_entry)?;
+    mcp_server_entry.last_update_timestamp = get_current_timestamp()?;
+
+    let space = mcp_server_entry.required_space()?;
+    resize_account(mcp_server_entry_info, payer_info, system_program_info, space)?;
+
+    let mut data = mcp_server_entry_info.try_borrow_mut_data()?;
+    mcp_server_entry.serialize(&mut &mut data[..])?;
+
+    msg!("EVENT: McpServerUpdated server_id={} fields={:?}", mcp_server_entry.server_id, [field_name]);
+
+    Ok(())
+}
+
+/// Process finalize MCP server registration instruction
+fn process_finalize_mcp_server_registration(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    let mcp_server_entry_info = next_account_info(accounts_iter)?;
+    let owner_authority_info = next_account_info(accounts_iter)?;
+
+    if !owner_authority_info.is_signer {
+        return Err(RegistryError::Unauthorized.into());
+    }
+
+    verify_account_owner(mcp_server_entry_info, program_id)?;
+
+    let mut data = mcp_server_entry_info.try_borrow_mut_data()?;
+    let mut mcp_server_entry = McpServerRegistryEntryV1::try_from_account_data(&data)?;
+
+    if mcp_server_entry.owner_authority != *owner_authority_info.key {
+        return Err(RegistryError::Unauthorized.into());
+    }
+    mcp_server_entry.require_registration_open()?;
+
+    // Same rules as a single-transaction registration, over the assembled entry
+    validate_mcp_server_entry(&mcp_server_entry)?;
+
+    mcp_server_entry.finalize_registration(get_current_timestamp()?)?;
+
+    // Only fixed-size fields changed, so the account size is unchanged
+    mcp_server_entry.serialize(&mut &mut data[..])?;
+
+    msg!(
+        "EVENT: McpServerRegistered server_id={} name={}",
+        mcp_server_entry.server_id, mcp_server_entry.name
+    );
+
+    Ok(())
+}
+
+// Utility functions for MCP Server Registry token integration
+
+/// Calculate MCP server quality score based on performance metrics
+fn calculate_mcp_quality_score(
+    uptime_percentage: u8,
+    avg_response_time: u32,
+    error_rate: u8,
+    verification_tier: u8,
+) -> u64 {
+    let mut score = 0u64;
+    
+    // Uptime component (40% of score)
+    score += (uptime_percentage as u64 * 40) / 100;
+    
+    // Response time component (30% of score) - lower is better
+    let response_score = if avg_response_time <= 100 {
+        30
+    } else if avg_response_time <= 250 {
+        25
+    } else if avg_response_time <= 500 {
+        20
+    } else if avg_response_time <= 1000 {
+        15
+    } else {
+        10
+    };
+    score += response_score;
+    
+    // Error rate component (20% of score) - lower is better
+    let error_score = if error_rate == 0 {
+        20
+    } else if error_rate <= 1 {
+        18
+    } else if error_rate <= 2 {
+        15
+    } else if error_rate <= 5 {
+        10
+    } else {
+        5
+    };
+    score += error_score;
+    
+    // Verification tier bonus (10% of score)
+    let tier_bonus = match verification_tier {
+        2 => 10, // Premium
+        1 => 7,  // Verified
+        _ => 3,  // Basic
+    };
+    score += tier_bonus;
+    
+    // Convert to basis points (score * 100 for 2 decimal precision)
+    score * 100
+}
+
+/// Process initialize registry config instruction
+fn process_initialize_registry_config(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    params: RegistryConfigParams,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    let config_info = next_account_info(accounts_iter)?;
+    let authority_info = next_account_info(accounts_iter)?;
+    let payer_info = next_account_info(accounts_iter)?;
+    let program_data_info = next_account_info(accounts_iter)?;
+    let system_program_info = next_account_info(accounts_iter)?;
+
+    // Only the upgrade authority may claim the admin role
+    verify_upgrade_authority(program_id, program_data_info, authority_info)?;
+    if !payer_info.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    let (expected_config, bump) = get_registry_config_pda(program_id);
+    if config_info.key != &expected_config {
+        return Err(RegistryError::InvalidPda.into());
+    }
+    if !config_info.data_is_empty() {
+        return Err(RegistryError::AccountAlreadyExists.into());
+    }
+
+    params.validate()?;
+
+    create_pda_account(
+        payer_info,
+        config_info,
+        system_program_info,
+        program_id,
+        RegistryConfigV1::LEN,
+        &[REGISTRY_CONFIG_SEED, &[bump]],
+    )?;
+
+    let config = RegistryConfigV1 {
+        bump,
+        admin_authority: *authority_info.key,
+        params,
+        last_update_timestamp: get_current_timestamp()?,
+    };
+    config.serialize(&mut &mut config_info.try_borrow_mut_data()?[..])?;
+
+    msg!(
+        "EVENT: RegistryConfigUpdated admin={} mint={} treasury={} registration_fee={}",
+        config.admin_authority, params.accepted_mint, params.treasury, params.registration_fee
+    );
+
+    Ok(())
+}
+
+/// Process update registry config instruction
+fn process_update_registry_config(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    params: RegistryConfigParams,
+    new_admin: Option<Pubkey>,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    let config_info = next_account_info(accounts_iter)?;
+    let admin_info = next_account_info(accounts_iter)?;
+    let staking_vault_info = next_account_info(accounts_iter)?;
+
+    let mut config = RegistryConfigV1::load(config_info, program_id)?;
+    verify_signer_authority(admin_info, &config.admin_authority)?;
+
+    params.validate()?;
+    config.check_mint_change(&params, staking_vault_info, program_id)?;
+
+    config.params = params;
+    if let Some(new_admin) = new_admin {
+        config.admin_authority = new_admin;
+    }
+    config.last_update_timestamp = get_current_timestamp()?;
+    config.serialize(&mut &mut config_info.try_borrow_mut_data()?[..])?;
+
+    msg!(
+        "EVENT: RegistryConfigUpdated admin={} mint={} treasury={} registration_fee={}",
+        config.admin_authority, params.accepted_mint, params.treasury, params.registration_fee
+    );
+
+    Ok(())
+}
+
+/// Process initialize vaults instruction
+fn process_initialize_vaults(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    let config_info = next_account_info(accounts_iter)?;
+    let admin_info = next_account_info(accounts_iter)?;
+    let payer_info = next_account_info(accounts_iter)?;
+    let mint_info = next_account_info(accounts_iter)?;
+    let staking_vault_info = next_account_info(accounts_iter)?;
+    let fee_vault_info = next_account_info(accounts_iter)?;
+    let registration_vault_info = next_account_info(accounts_iter)?;
+    let treasury_vault_info = next_account_info(accounts_iter)?;
+    let token_program_info = next_account_info(accounts_iter)?;
+    let system_program_info = next_account_info(accounts_iter)?;
+
+    let config = RegistryConfigV1::load(config_info, program_id)?;
+    verify_signer_authority(admin_info, &config.admin_authority)?;
+    if !payer_info.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    verify_token_program(token_program_info)?;
+    verify_token_mint(mint_info, &config.params)?;
+
+    for (vault_info, seed) in [
+        (staking_vault_info, STAKING_VAULT_SEED),
+        (fee_vault_info, FEE_VAULT_SEED),
+        (registration_vault_info, REGISTRATION_VAULT_SEED),
+        (treasury_vault_info, TREASURY_VAULT_SEED),
+    ] {
+        let (expected_vault, bump) = Pubkey::find_program_address(&[seed], program_id);
+        if vault_info.key != &expected_vault {
+            return Err(RegistryError::InvalidPda.into());
+        }
+        if !vault_info.data_is_empty() {
+            return Err(RegistryError::AccountAlreadyExists.into());
+        }
+        create_vault_token_account(
+            payer_info,
+            vault_info,
+            mint_info,
+            token_program_info,
+            system_program_info,
+            &[seed, &[bump]],
+        )?;
+    }
+
+    msg!(
+        "EVENT: VaultsInitialized mint={} staking_vault={} fee_vault={} registration_vault={} treasury_vault={}",
+        mint_info.key, staking_vault_info.key, fee_vault_info.key, registration_vault_info.key,
+        treasury_vault_info.key
+    );
+
+    Ok(())
+}
+
+/// Process initialize server fee vault instruction
+fn process_initialize_server_fee_vault(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    let mcp_server_entry_info = next_account_info(accounts_iter)?;
+    let owner_authority_info = next_account_info(accounts_iter)?;
+    let payer_info = next_account_info(accounts_iter)?;
+    let config_info = next_account_info(accounts_iter)?;
+    let mint_info = next_account_info(accounts_iter)?;
+    let server_fee_vault_info = next_account_info(accounts_iter)?;
+    let token_program_info = next_account_info(accounts_iter)?;
+    let system_program_info = next_account_info(accounts_iter)?;
+
+    verify_account_owner(mcp_server_entry_info, program_id)?;
+    if !payer_info.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    {
+        let data = mcp_server_entry_info.try_borrow_data()?;
+        let server_entry = McpServerEntryPrefixV1::load(&data)?;
+        verify_signer_authority(owner_authority_info, &server_entry.header.owner_authority)?;
+    }
+
+    let config = RegistryConfigV1::load(config_info, program_id)?.params;
+    verify_token_program(token_program_info)?;
+    verify_token_mint(mint_info, &config)?;
+
+    let (expected_vault, bump) = derive_server_fee_vault_pda(mcp_server_entry_info.key, program_id);
+    if server_fee_vault_info.key != &expected_vault {
+        return Err(RegistryError::InvalidPda.into());
+    }
+    if !server_fee_vault_info.data_is_empty() {
+        return Err(RegistryError::AccountAlreadyExists.into());
+    }
+    create_vault_token_account(
+        payer_info,
+        server_fee_vault_info,
+        mint_info,
+        token_program_info,
+        system_program_info,
+        &[FEE_VAULT_SEED, mcp_server_entry_info.key.as_ref(), &[bump]],
+    )?;
+
+    msg!(
+        "EVENT: ServerFeeVaultInitialized server_entry={} fee_vault={}",
+        mcp_server_entry_info.key, server_fee_vault_info.key
+    );
+
+    Ok(())
+}
+
+/// Process set revenue split instruction
+fn process_set_revenue_split(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    recipients: Vec<SplitRecipient>,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    let split_info = next_account_info(accounts_iter)?;
+    let mcp_server_entry_info = next_account_info(accounts_iter)?;
+    let owner_authority_info = next_account_info(accounts_iter)?;
+    let payer_info = next_account_info(accounts_iter)?;
+    let system_program_info = next_account_info(accounts_iter)?;
+
+    verify_account_owner(mcp_server_entry_info, program_id)?;
+    {
+        let data = mcp_server_entry_info.try_borrow_data()?;
+        let server_entry = McpServerEntryPrefixV1::load(&data)?;
+        verify_signer_authority(owner_authority_info, &server_entry.header.owner_authority)?;
+    }
+
+    let (expected_split, bump) = derive_revenue_split_pda(mcp_server_entry_info.key, program_id);
+    if split_info.key != &expected_split {
+        return Err(RegistryError::InvalidPda.into());
+    }
+    let mut split = RevenueSplitV1::new(bump, *mcp_server_entry_info.key, &recipients)?;
+
+    if split_info.data_is_empty() {
+        if !payer_info.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+        create_pda_account(
+            payer_info,
+            split_info,
+            system_program_info,
+            program_id,
+            RevenueSplitV1::LEN,
+            &[REVENUE_SPLIT_SEED, mcp_server_entry_info.key.as_ref(), &[bump]],
+        )?;
+    } else {
+        // Shares still owed to recipients carry over to the new table
+        if let Some(previous) = RevenueSplitV1::load(split_info, mcp_server_entry_info.key, program_id)? {
+            split = split.carry_owed(&previous)?;
+        }
+    }
+    split.serialize(&mut &mut split_info.try_borrow_mut_data()?[..])?;
+
+    msg!(
+        "EVENT: RevenueSplitUpdated server_entry={} recipients={}",
+        mcp_server_entry_info.key, split.recipient_count
+    );
+
+    Ok(())
+}
+
+/// Process set usage pricing instruction
+fn process_set_usage_pricing(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    item_prices: Vec<ItemPriceInput>,
+    discount_tiers: Vec<DiscountTier>,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    let pricing_table_info = next_account_info(accounts_iter)?;
+    let mcp_server_entry_info = next_account_info(accounts_iter)?;
+    let owner_authority_info = next_account_info(accounts_iter)?;
+    let payer_info = next_account_info(accounts_iter)?;
+    let system_program_info = next_account_info(accounts_iter)?;
+    let config_info = next_account_info(accounts_iter)?;
+
+    verify_account_owner(mcp_server_entry_info, program_id)?;
+    let config = RegistryConfigV1::load(config_info, program_id)?.params;
+
+    // Only items the server defines on-chain can be priced
+    let current_timestamp = get_current_timestamp()?;
+    let (items, economics) = {
+        let data = mcp_server_entry_info.try_borrow_data()?;
+        let server_entry = McpServerRegistryEntryV1::try_from_account_data(&data)?;
+        verify_signer_authority(owner_authority_info, &server_entry.owner_authority)?;
+
+        let items = item_prices
+            .iter()
+            .map(|input| {
+                let (defined, min_fee) = match input.usage_type {
+                    UsageType::Tool => (
+                        server_entry.onchain_tool_definitions.iter().any(|t| t.name == input.name),
+                        config.min_tool_fee,
+                    ),
+                    UsageType::Resource => (
+                        server_entry.onchain_resource_definitions.iter().any(|r| r.uri_pattern == input.name),
+                        config.min_resource_fee,
+                    ),
+                    UsageType::Prompt => (
+                        server_entry.onchain_prompt_definitions.iter().any(|p| p.name == input.name),
+                        config.min_prompt_fee,
+                    ),
+                };
+                if !defined {
+                    return Err(RegistryError::UnknownPricedItem);
+                }
+                if input.price < min_fee {
+                    return Err(RegistryError::FeeTooLow);
+                }
+                Ok(ItemPriceV1 {
+                    usage_type: to_state_usage_type(input.usage_type).to_u8(),
+                    name_hash: hash_item_name(&input.name),
+                    price: input.price,
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        // Increases are judged against the base fees in force
+        let mut economics = server_entry.economics;
+        economics.apply_due_fee_change(current_timestamp);
+        (items, economics)
+    };
+
+    let (expected_table, bump) = derive_pricing_table_pda(mcp_server_entry_info.key, program_id);
+    if pricing_table_info.key != &expected_table {
+        return Err(RegistryError::InvalidPda.into());
+    }
+    let proposed = McpPricingTableV1::new(
+        bump,
+        *mcp_server_entry_info.key,
+        &items,
+        &discount_tiers,
+        config.max_bulk_discount,
+        current_timestamp,
+    )?;
+
+    // A server without a table charges base fees, as an empty table does
+    let mut table = match load_pricing_table(pricing_table_info, mcp_server_entry_info.key, program_id)? {
+        Some(table) => table,
+        None => McpPricingTableV1::new(
+            bump,
+            *mcp_server_entry_info.key,
+            &[],
+            &[],
+            config.max_bulk_discount,
+            current_timestamp,
+        )?,
+    };
+
+    // Decreases apply now, increases after the config's notice period
+    let effective_at =
+        table.schedule_prices(&proposed, &economics, current_timestamp, config.fee_increase_notice);
+
+    if pricing_table_info.data_is_empty() {
+        if !payer_info.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+        create_pda_account(
+            payer_info,
+            pricing_table_info,
+            system_program_info,
+            program_id,
+            McpPricingTableV1::SPACE,
+            &[PRICING_TABLE_SEED, mcp_server_entry_info.key.as_ref(), &[bump]],
+        )?;
+    } else {
+        verify_account_owner(pricing_table_info, program_id)?;
+    }
+    table.serialize(&mut &mut pricing_table_info.try_borrow_mut_data()?[..])?;
+
+    msg!(
+        "EVENT: UsagePricingUpdated server_entry={} items={} tiers={}",
+        mcp_server_entry_info.key, table.item_count, table.tier_count
+    );
+    if let Some(effective_at) = effective_at {
+        msg!(
+            "EVENT: UsagePricingChangeScheduled server_entry={} items={} tiers={} effective_at={}",
+            mcp_server_entry_info.key, proposed.item_count, proposed.tier_count, effective_at
+        );
+    }
+
+    Ok(())
+}
+
+/// Derive the pricing table PDA of an MCP server entry
+fn derive_pricing_table_pda(server_entry: &Pubkey, program_id: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[PRICING_TABLE_SEED, server_entry.as_ref()], program_id)
+}
+
+/// Load the pricing table of `server_entry`, or `None` if the server never
+/// set one. Pending prices are left for the caller to apply when due.
+fn load_pricing_table(
+    pricing_table_info: &AccountInfo,
+    server_entry: &Pubkey,
+    program_id: &Pubkey,
+) -> Result<Option<McpPricingTableV1>, ProgramError> {
+    let (expected_table, _) = derive_pricing_table_pda(server_entry, program_id);
+    if pricing_table_info.key != &expected_table {
+        return Err(RegistryError::InvalidPda.into());
+    }
+    if pricing_table_info.data_is_empty() {
+        return Ok(None);
+    }
+    verify_account_owner(pricing_table_info, program_id)?;
+    McpPricingTableV1::try_from_account_data(&pricing_table_info.try_borrow_data()?).map(Some)
+}
+
+/// Process set subscription plan instruction
+fn process_set_subscription_plan(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    plan_id: u8,
+    price: u64,
+    period: i64,
+    included: SubscriptionQuota,
+    active: bool,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    let plan_info = next_account_info(accounts_iter)?;
+    let mcp_server_entry_info = next_account_info(accounts_iter)?;
+    let owner_authority_info = next_account_info(accounts_iter)?;
+    let payer_info = next_account_info(accounts_iter)?;
+    let system_program_info = next_account_info(accounts_iter)?;
+    let config_info = next_account_info(accounts_iter)?;
+
+    verify_account_owner(mcp_server_entry_info, program_id)?;
+    let config = RegistryConfigV1::load(config_info, program_id)?.params;
+    {
+        let data = mcp_server_entry_info.try_borrow_data()?;
+        let server_entry = McpServerEntryPrefixV1::load(&data)?;
+        verify_signer_authority(owner_authority_info, &{ server_entry.header.owner_authority })?;
+    }
+
+    let (expected_plan, bump) = derive_subscription_plan_pda(mcp_server_entry_info.key, plan_id, program_id);
+    if plan_info.key != &expected_plan {
+        return Err(RegistryError::InvalidPda.into());
+    }
+    let current_timestamp = get_current_timestamp()?;
+    let proposed = McpSubscriptionPlanV1 {
+        bump,
+        server_entry: *mcp_server_entry_info.key,
+        plan_id,
+        price,
+        period,
+        included,
+        active,
+        last_update_timestamp: current_timestamp,
+        pending_price: 0,
+        pending_period: 0,
+        pending_included: SubscriptionQuota::default(),
+        change_effective_at: 0,
+    };
+    proposed.validate()?;
+
+    // A new plan has no subscribers to give notice to; on an existing one,
+    // decreases apply now and increases after the config's notice period
+    let (plan, effective_at) = if plan_info.data_is_empty() {
+        (proposed, None)
+    } else {
+        verify_account_owner(plan_info, program_id)?;
+        let mut plan = McpSubscriptionPlanV1::try_from_account_data(&plan_info.try_borrow_data()?)?;
+        let effective_at =
+            plan.schedule_terms(price, period, included, current_timestamp, config.fee_increase_notice);
+        plan.active = active;
+        (plan, effective_at)
+    };
+
+    if plan_info.data_is_empty() {
+        if !payer_info.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+        create_pda_account(
+            payer_info,
+            plan_info,
+            system_program_info,
+            program_id,
+            McpSubscriptionPlanV1::SPACE,
+            &[SUBSCRIPTION_PLAN_SEED, mcp_server_entry_info.key.as_ref(), &[plan_id], &[bump]],
+        )?;
+    }
+    plan.serialize(&mut &mut plan_info.try_borrow_mut_data()?[..])?;
+
+    msg!(
+        "EVENT: SubscriptionPlanUpdated server_entry={} plan_id={} price={} period={} tool_calls={} resource_calls={} prompt_calls={} active={}",
+        mcp_server_entry_info.key, plan_id, plan.price, plan.period,
+        plan.included.tool_calls, plan.included.resource_calls, plan.included.prompt_calls, active
+    );
+    if let Some(effective_at) = effective_at {
+        msg!(
+            "EVENT: SubscriptionPlanChangeScheduled server_entry={} plan_id={} price={} period={} tool_calls={} resource_calls={} prompt_calls={} effective_at={}",
+            mcp_server_entry_info.key, plan_id, price, period,
+            included.tool_calls, included.resource_calls, included.prompt_calls, effective_at
+        );
+    }
+
+    Ok(())
+}
+
+/// Process subscribe instruction
+fn process_subscribe(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    plan_id: u8,
+    max_price: u64,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    let subscription_info = next_account_info(accounts_iter)?;
+    let mcp_server_entry_info = next_account_info(accounts_iter)?;
+    let plan_info = next_account_info(accounts_iter)?;
+    let user_authority_info = next_account_info(accounts_iter)?;
+    let user_token_account_info = next_account_info(accounts_iter)?;
+    let server_fee_vault_info = next_account_info(accounts_iter)?;
+    let treasury_vault_info = next_account_info(accounts_iter)?;
+    let token_program_info = next_account_info(accounts_iter)?;
+    let config_info = next_account_info(accounts_iter)?;
+    let system_program_info = next_account_info(accounts_iter)?;
+    let split_info = next_account_info(accounts_iter)?;
+
+    verify_account_owner(mcp_server_entry_info, program_id)?;
+    if !user_authority_info.is_signer {
+        return Err(RegistryError::MissingRequiredSignature.into());
+    }
+    let plan = load_subscription_plan(plan_info, mcp_server_entry_info.key, plan_id, program_id)?;
+
+    // A user holds one subscription per server; renew or cancel it instead
+    let (expected_subscription, bump) =
+        derive_subscription_pda(mcp_server_entry_info.key, user_authority_info.key, program_id);
+    if subscription_info.key != &expected_subscription {
+        return Err(RegistryError::InvalidPda.into());
+    }
+    if !subscription_info.data_is_empty() {
+        return Err(RegistryError::SubscriptionAlreadyExists.into());
+    }
+
+    let protocol_fee = collect_subscription_payment(
+        program_id,
+        mcp_server_entry_info,
+        user_authority_info,
+        user_token_account_info,
+        server_fee_vault_info,
+        treasury_vault_info,
+        token_program_info,
+        config_info,
+        split_info,
+        plan.price,
+        max_price,
+    )?;
+
+    let subscription = McpSubscriptionV1::new(bump, *user_authority_info.key, &plan, get_current_timestamp()?);
+    create_pda_account(
+        user_authority_info,
+        subscription_info,
+        system_program_info,
+        program_id,
+        McpSubscriptionV1::SPACE,
+        &[
+            SUBSCRIPTION_SEED,
+            mcp_server_entry_info.key.as_ref(),
+            user_authority_info.key.as_ref(),
+            &[bump],
+        ],
+    )?;
+    subscription.serialize(&mut &mut subscription_info.try_borrow_mut_data()?[..])?;
+
+    msg!(
+        "EVENT: Subscribed server_entry={} subscriber={} plan_id={} price={} protocol_fee={} expires_at={}",
+        mcp_server_entry_info.key, user_authority_info.key, plan_id, plan.price, protocol_fee, subscription.expires_at
+    );
+
+    Ok(())
+}
+
+/// Process renew subscription instruction
+fn process_renew_subscription(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    max_price: u64,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    let subscription_info = next_account_info(accounts_iter)?;
+    let mcp_server_entry_info = next_account_info(accounts_iter)?;
+    let plan_info = next_account_info(accounts_iter)?;
+    let user_authority_info = next_account_info(accounts_iter)?;
+    let user_token_account_info = next_account_info(accounts_iter)?;
+    let server_fee_vault_info = next_account_info(accounts_iter)?;
+    let treasury_vault_info = next_account_info(accounts_iter)?;
+    let token_program_info = next_account_info(accounts_iter)?;
+    let config_info = next_account_info(accounts_iter)?;
+    let split_info = next_account_info(accounts_iter)?;
+
+    verify_account_owner(mcp_server_entry_info, program_id)?;
+    if !user_authority_info.is_signer {
+        return Err(RegistryError::MissingRequiredSignature.into());
+    }
+    let mut subscription = load_subscription(
+        subscription_info,
+        mcp_server_entry_info.key,
+        user_authority_info.key,
+        program_id,
+    )?
+    .ok_or(RegistryError::AccountNotFound)?;
+    let plan = load_subscription_plan(plan_info, mcp_server_entry_info.key, subscription.plan_id, program_id)?;
+
+    let protocol_fee = collect_subscription_payment(
+        program_id,
+        mcp_server_entry_info,
+        user_authority_info,
+        user_token_account_info,
+        server_fee_vault_info,
+        treasury_vault_info,
+        token_program_info,
+        config_info,
+        split_info,
+        plan.price,
+        max_price,
+    )?;
+
+    subscription.renew(&plan, get_current_timestamp()?);
+    subscription.serialize(&mut &mut subscription_info.try_borrow_mut_data()?[..])?;
+
+    msg!(
+        "EVENT: SubscriptionRenewed server_entry={} subscriber={} plan_id={} price={} protocol_fee={} expires_at={}",
+        mcp_server_entry_info.key, user_authority_info.key, plan.plan_id, plan.price, protocol_fee, subscription.expires_at
+    );
+
+    Ok(())
+}
+
+/// Process cancel subscription instruction
+fn process_cancel_subscription(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    let subscription_info = next_account_info(accounts_iter)?;
+    let user_authority_info = next_account_info(accounts_iter)?;
+
+    verify_account_owner(subscription_info, program_id)?;
+    let subscription = McpSubscriptionV1::try_from_account_data(&subscription_info.try_borrow_data()?)?;
+    verify_signer_authority(user_authority_info, &subscription.subscriber)?;
+
+    close_account(subscription_info, user_authority_info)?;
+
+    msg!(
+        "EVENT: SubscriptionCancelled server_entry={} subscriber={} plan_id={} expires_at={}",
+        subscription.server_entry, subscription.subscriber, subscription.plan_id, subscription.expires_at
+    );
+
+    Ok(())
+}
+
+fn process_migrate_mcp_server_entry(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    let mcp_server_entry_info = next_account_info(accounts_iter)?;
+    let payer_info = next_account_info(accounts_iter)?;
+    let system_program_info = next_account_info(accounts_iter)?;
+
+    verify_account_owner(mcp_server_entry_info, program_id)?;
+    verify_signer_authority(payer_info, payer_info.key)?;
+    if system_program_info.key != &solana_program::system_program::id() {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+
+    let data_len = mcp_server_entry_info.data_len();
+    let mut mcp_server_entry = McpServerRegistryEntryV1::migrate_from_v1(&mcp_server_entry_info.try_borrow_data()?)?;
+    let (expected_pda, _) = get_mcp_server_pda_secure(
+        &mcp_server_entry.server_id,
+        &mcp_server_entry.owner_authority,
+        program_id,
+    );
+    if mcp_server_entry_info.key != &expected_pda {
+        return Err(RegistryError::InvalidPda.into());
+    }
+    mcp_server_entry.bump_state_version();
+
+    // Only ever grow the account, so no rent goes back to an arbitrary
+    // payer; trailing bytes past the entry are ignored when decoding
+    let space = mcp_server_entry.required_space()?.max(data_len);
+    resize_account(mcp_server_entry_info, payer_info, system_program_info, space)?;
+    mcp_server_entry.serialize(&mut &mut mcp_server_entry_info.try_borrow_mut_data()?[..])?;
+
+    msg!(
+        "EVENT: McpServerEntryMigrated server_id={} registry_version={} state_version={}",
+        mcp_server_entry.server_id, mcp_server_entry.registry_version, mcp_server_entry.state_version
+    );
+
+    Ok(())
+}
+
+/// Charge `price` for a subscription period: the protocol take-rate goes to
+/// the treasury, the rest to the server's fee vault and pending fees.
+/// Returns the take-rate.
+#[allow(clippy::too_many_arguments)]
+fn collect_subscription_payment<'a>(
+    program_id: &Pubkey,
+    mcp_server_entry_info: &AccountInfo<'a>,
+    user_authority_info: &AccountInfo<'a>,
+    user_token_account_info: &AccountInfo<'a>,
+    server_fee_vault_info: &AccountInfo<'a>,
+    treasury_vault_info: &AccountInfo<'a>,
+    token_program_info: &AccountInfo<'a>,
+    config_info: &AccountInfo<'a>,
+    split_info: &AccountInfo<'a>,
+    price: u64,
+    max_price: u64,
+) -> Result<u64, ProgramError> {
+    check_max_total_fee(price, max_price)?;
+
+    let config = RegistryConfigV1::load(config_info, program_id)?.params;
+    verify_token_program(token_program_info)?;
+    verify_user_token_account(user_token_account_info, user_authority_info.key, &config)?;
+    let (expected_vault, _) = derive_server_fee_vault_pda(mcp_server_entry_info.key, program_id);
+    let vault_balance = verify_vault_token_account(server_fee_vault_info, &expected_vault, &config)?.amount;
+    let (expected_treasury, _) = derive_treasury_vault_pda(program_id);
+    verify_vault_token_account(treasury_vault_info, &expected_treasury, &config)?;
+
+    let mut data = mcp_server_entry_info.try_borrow_mut_data()?;
+    let server_entry = McpServerEntryPrefixV1::load_mut(&mut data)?;
+    if !server_entry.is_active() {
+        return Err(RegistryError::InvalidMcpServerStatus.into());
+    }
+
+    let protocol_fee = bps_share(price, config.protocol_fee_bps);
+    let server_fee = price - protocol_fee;
+    if protocol_fee > 0 {
+        transfer_tokens_with_pda_signer_account_info(
+            user_token_account_info,
+            treasury_vault_info,
+            user_authority_info,
+            token_program_info,
+            protocol_fee,
+            &[],
+        )?;
+    }
+    transfer_tokens_with_pda_signer_account_info(
+        user_token_account_info,
+        server_fee_vault_info,
+        user_authority_info,
+        token_program_info,
+        server_fee,
+        &[],
+    )?;
+
+    let economics = &mut server_entry.economics;
+    economics.record_subscription_payment(server_fee);
+    let owed = RevenueSplitV1::load(split_info, mcp_server_entry_info.key, program_id)?
+        .as_ref()
+        .map_or(0, RevenueSplitV1::total_owed);
+    check_fee_vault_solvency(vault_balance.saturating_add(server_fee), economics.pending_fees + owed)?;
+    server_entry.header.bump_state_version();
+
+    Ok(protocol_fee)
+}
+
+/// Derive a subscription plan PDA of an MCP server entry
+fn derive_subscription_plan_pda(server_entry: &Pubkey, plan_id: u8, program_id: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[SUBSCRIPTION_PLAN_SEED, server_entry.as_ref(), &[plan_id]], program_id)
+}
+
+/// Derive the subscription PDA of `subscriber` to an MCP server entry
+fn derive_subscription_pda(server_entry: &Pubkey, subscriber: &Pubkey, program_id: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[SUBSCRIPTION_SEED, server_entry.as_ref(), subscriber.as_ref()], program_id)
+}
+
+/// Derive the usage record PDA of `user` on an MCP server entry
+fn derive_usage_record_pda(server_entry: &Pubkey, user: &Pubkey, program_id: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[USAGE_RECORD_SEED, server_entry.as_ref(), user.as_ref()], program_id)
+}
+
+/// Load plan `plan_id` of `server_entry` with any terms that came due
+/// applied, failing with `SubscriptionPlanInactive` if it is missing or
+/// closed to subscribers
+fn load_subscription_plan(
+    plan_info: &AccountInfo,
+    server_entry: &Pubkey,
+    plan_id: u8,
+    program_id: &Pubkey,
+) -> Result<McpSubscriptionPlanV1, ProgramError> {
+    let (expected_plan, _) = derive_subscription_plan_pda(server_entry, plan_id, program_id);
+    if plan_info.key != &expected_plan {
+        return Err(RegistryError::InvalidPda.into());
+    }
+    if plan_info.data_is_empty() {
+        return Err(RegistryError::SubscriptionPlanInactive.into());
+    }
+    verify_account_owner(plan_info, program_id)?;
+    let mut plan = McpSubscriptionPlanV1::try_from_account_data(&plan_info.try_borrow_data()?)?;
+    if !plan.active {
+        return Err(RegistryError::SubscriptionPlanInactive.into());
+    }
+    plan.apply_due_terms_change(get_current_timestamp()?);
+    Ok(plan)
+}
+
+/// Load the subscription of `subscriber` to `server_entry`, or `None` if
+/// they hold none
+fn load_subscription(
+    subscription_info: &AccountInfo,
+    server_entry: &Pubkey,
+    subscriber: &Pubkey,
+    program_id: &Pubkey,
+) -> Result<Option<McpSubscriptionV1>, ProgramError> {
+    let (expected_subscription, _) = derive_subscription_pda(server_entry, subscriber, program_id);
+    if subscription_info.key != &expected_subscription {
+        return Err(RegistryError::InvalidPda.into());
+    }
+    if subscription_info.data_is_empty() {
+        return Ok(None);
+    }
+    verify_account_owner(subscription_info, program_id)?;
+    McpSubscriptionV1::try_from_account_data(&subscription_info.try_borrow_data()?).map(Some)
+}
+
+/// Process withdraw treasury instruction
+fn process_withdraw_treasury(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    amount: u64,
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    let config_info = next_account_info(accounts_iter)?;
+    let admin_info = next_account_info(accounts_iter)?;
+    let treasury_vault_info = next_account_info(accounts_iter)?;
+    let destination_info = next_account_info(accounts_iter)?;
+    let token_program_info = next_account_info(accounts_iter)?;
+
+    let config = RegistryConfigV1::load(config_info, program_id)?;
+    verify_signer_authority(admin_info, &config.admin_authority)?;
+    verify_token_program(token_program_info)?;
+    verify_user_token_account(destination_info, &config.params.treasury, &config.params)?;
+
+    let (expected_treasury, bump) = derive_treasury_vault_pda(program_id);
+    let treasury = verify_vault_token_account(treasury_vault_info, &expected_treasury, &config.params)?;
+    if treasury.amount < amount {
+        return Err(RegistryError::InsufficientFunds.into());
+    }
+
+    transfer_tokens_with_pda_signer_account_info(
+        treasury_vault_info,
+        destination_info,
+        treasury_vault_info,
+        token_program_info,
+        amount,
+        &[&[TREASURY_VAULT_SEED, &[bump]]],
+    )?;
+
+    msg!(
+        "EVENT: TreasuryWithdrawn destination={} amount={}",
+        destination_info.key, amount
+    );
+
+    Ok(())
+}
+
+/// Validate fee configuration parameters
+fn validate_fee_configuration(
+    tool_base_fee: u64,
+    resource_base_fee: u64,
+    prompt_base_fee: u64,
+    bulk_discount_threshold: u32,
+    bulk_discount_percentage: u8,
+    config: &RegistryConfigParams,
+) -> Result<(), RegistryError> {
     // Validate minimum fees
-    if tool_base_fee < MIN_TOOL_FEE {
+    if tool_base_fee < config.min_tool_fee {
         return Err(RegistryError::FeeTooLow);
     }
     
-    if resource_base_fee < MIN_RESOURCE_FEE {
+    if resource_base_fee < config.min_resource_fee {
         return Err(RegistryError::FeeTooLow);
     }
     
-    if prompt_base_fee < MIN_PROMPT_FEE {
+    if prompt_base_fee < config.min_prompt_fee {
         return Err(RegistryError::FeeTooLow);
     }
     
     // Validate bulk discount
-    if bulk_discount_percentage > MAX_BULK_DISCOUNT {
+    if bulk_discount_percentage > config.max_bulk_discount {
         return Err(RegistryError::InvalidMultiplier);
     }
     
@@ -1,13 +1,309 @@
 //! State definitions for the MCP Server Registry program
 
 use borsh::{BorshDeserialize, BorshSerialize};
-use solana_program::pubkey::Pubkey;
+use solana_program::{program_error::ProgramError, pubkey::Pubkey};
 use aeamcp_common::{
     constants::*,
+    error::RegistryError,
+    impl_borsh_for_pod,
     serialization::{McpToolDefinitionOnChain, McpResourceDefinitionOnChain, McpPromptDefinitionOnChain},
+    zero_copy::{check_entry_layout_version, load, load_mut, EntryHeader, ENTRY_LAYOUT_VERSION},
     McpServerStatus,
 };
 
+/// Token, usage, fee and quality fields of an MCP server entry. Stored at a
+/// fixed offset right after the [`EntryHeader`], so hot-path instructions can
+/// update them in place through [`McpServerEntryPrefixV1`].
+#[repr(C, packed)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct McpServerEconomicsV1 {
+    /// SVMAI Token mint address
+    pub token_mint: Pubkey,
+    /// SVMAI staked for verification
+    pub verification_stake: u64,
+    /// When tokens were staked
+    pub staking_timestamp: i64,
+    /// Lock period end
+    pub stake_locked_until: i64,
+    /// Verification tier (0: Basic, 1: Verified, 2: Premium)
+    pub verification_tier: u8,
+    /// Total tool invocations
+    pub total_tool_calls: u64,
+    /// Total resource fetches
+    pub total_resource_accesses: u64,
+    /// Total prompt executions
+    pub total_prompt_uses: u64,
+    /// Total SVMAI earned
+    pub total_fees_collected: u64,
+    /// Server quality metric (0-10000)
+    pub quality_score: u64,
+    /// Last 30 days uptime (0-100)
+    pub uptime_percentage: u8,
+    /// Average response time in ms
+    pub avg_response_time: u32,
+    /// Error percentage (0-100)
+    pub error_rate: u8,
+    /// Base fee per tool call
+    pub tool_base_fee: u64,
+    /// Base fee per resource access
+    pub resource_base_fee: u64,
+    /// Base fee per prompt use
+    pub prompt_base_fee: u64,
+    /// Number of calls for discount
+    pub bulk_discount_threshold: u32,
+    /// Discount percentage (0-50)
+    pub bulk_discount_percentage: u8,
+    /// Amount paid for registration
+    pub registration_fee_paid: u64,
+    /// Timestamp of last fee collection
+    pub last_fee_collection: i64,
+    /// Uncollected fees
+    pub pending_fees: u64,
+    /// Tool fee applying at `fee_change_effective_at`
+    pub pending_tool_base_fee: u64,
+    /// Resource fee applying at `fee_change_effective_at`
+    pub pending_resource_base_fee: u64,
+    /// Prompt fee applying at `fee_change_effective_at`
+    pub pending_prompt_base_fee: u64,
+    /// Discount threshold applying at `fee_change_effective_at`
+    pub pending_bulk_discount_threshold: u32,
+    /// Discount percentage applying at `fee_change_effective_at`
+    pub pending_bulk_discount_percentage: u8,
+    /// When the pending fees replace the current ones (0: none scheduled)
+    pub fee_change_effective_at: i64,
+    /// Free tool calls per user per free-tier window
+    pub free_tool_calls: u32,
+    /// Free resource accesses per user per free-tier window
+    pub free_resource_calls: u32,
+    /// Free prompt uses per user per free-tier window
+    pub free_prompt_calls: u32,
+    /// Length of a free-tier window in seconds (0: no free tier)
+    pub free_tier_period: i64,
+}
+
+impl_borsh_for_pod!(McpServerEconomicsV1);
+
+impl McpServerEconomicsV1 {
+    /// Serialized length of the economics block
+    pub const LEN: usize = std::mem::size_of::<Self>();
+
+    /// Update verification staking
+    pub fn update_verification_stake(
+        &mut self,
+        amount: u64,
+        tier: u8,
+        lock_until: i64,
+        timestamp: i64,
+    ) {
+        self.verification_stake = amount;
+        self.verification_tier = tier;
+        self.stake_locked_until = lock_until;
+        self.staking_timestamp = timestamp;
+    }
+
+    /// Update usage fees
+    pub fn update_usage_fees(
+        &mut self,
+        tool_base_fee: u64,
+        resource_base_fee: u64,
+        prompt_base_fee: u64,
+        bulk_discount_threshold: u32,
+        bulk_discount_percentage: u8,
+        timestamp: i64,
+    ) {
+        self.tool_base_fee = tool_base_fee;
+        self.resource_base_fee = resource_base_fee;
+        self.prompt_base_fee = prompt_base_fee;
+        self.bulk_discount_threshold = bulk_discount_threshold;
+        self.bulk_discount_percentage = bulk_discount_percentage;
+        self.last_fee_collection = timestamp;
+    }
+
+    /// Change the usage fees with notice for increases. Every part of the
+    /// change that lowers what clients pay applies now; if any part raises
+    /// it, the full new schedule is held as pending until `timestamp + notice`.
+    /// A fee still at zero was never set and cannot be charged, so its first
+    /// value applies now. A later change replaces a pending one. Returns when
+    /// the pending schedule applies, or `None` if the change applied in full.
+    #[allow(clippy::too_many_arguments)]
+    pub fn schedule_usage_fees(
+        &mut self,
+        tool_base_fee: u64,
+        resource_base_fee: u64,
+        prompt_base_fee: u64,
+        bulk_discount_threshold: u32,
+        bulk_discount_percentage: u8,
+        timestamp: i64,
+        notice: i64,
+    ) -> Option<i64> {
+        self.apply_due_fee_change(timestamp);
+
+        let raises = |new: u64, current: u64| current != 0 && new > current;
+        let lower = |new: u64, current: u64| if current == 0 { new } else { new.min(current) };
+        let discount_shrinks = bulk_discount_percentage < self.bulk_discount_percentage
+            || (self.bulk_discount_percentage > 0
+                && bulk_discount_threshold > self.bulk_discount_threshold);
+        let increase = raises(tool_base_fee, self.tool_base_fee)
+            || raises(resource_base_fee, self.resource_base_fee)
+            || raises(prompt_base_fee, self.prompt_base_fee)
+            || discount_shrinks;
+        let (threshold_now, percentage_now) = if discount_shrinks {
+            (self.bulk_discount_threshold, self.bulk_discount_percentage)
+        } else {
+            (bulk_discount_threshold, bulk_discount_percentage)
+        };
+        self.update_usage_fees(
+            lower(tool_base_fee, self.tool_base_fee),
+            lower(resource_base_fee, self.resource_base_fee),
+            lower(prompt_base_fee, self.prompt_base_fee),
+            threshold_now,
+            percentage_now,
+            timestamp,
+        );
+
+        if !increase {
+            self.fee_change_effective_at = 0;
+            return None;
+        }
+        let effective_at = timestamp.saturating_add(notice);
+        self.pending_tool_base_fee = tool_base_fee;
+        self.pending_resource_base_fee = resource_base_fee;
+        self.pending_prompt_base_fee = prompt_base_fee;
+        self.pending_bulk_discount_threshold = bulk_discount_threshold;
+        self.pending_bulk_discount_percentage = bulk_discount_percentage;
+        self.fee_change_effective_at = effective_at;
+        Some(effective_at)
+    }
+
+    /// Replace the current fees with the pending ones once they are due.
+    /// Returns whether a change was applied.
+    pub fn apply_due_fee_change(&mut self, timestamp: i64) -> bool {
+        if self.fee_change_effective_at == 0 || timestamp < self.fee_change_effective_at {
+            return false;
+        }
+        self.update_usage_fees(
+            self.pending_tool_base_fee,
+            self.pending_resource_base_fee,
+            self.pending_prompt_base_fee,
+            self.pending_bulk_discount_threshold,
+            self.pending_bulk_discount_percentage,
+            self.fee_change_effective_at,
+        );
+        self.fee_change_effective_at = 0;
+        true
+    }
+
+    /// Free calls each user gets per window
+    pub fn free_tier(&self) -> FreeTier {
+        FreeTier {
+            tool_calls: self.free_tool_calls,
+            resource_calls: self.free_resource_calls,
+            prompt_calls: self.free_prompt_calls,
+            period: self.free_tier_period,
+        }
+    }
+
+    /// Replace the free calls each user gets per window
+    pub fn set_free_tier(&mut self, free_tier: FreeTier) {
+        self.free_tool_calls = free_tier.tool_calls;
+        self.free_resource_calls = free_tier.resource_calls;
+        self.free_prompt_calls = free_tier.prompt_calls;
+        self.free_tier_period = free_tier.period;
+    }
+
+    /// Base fee charged per use of the given type
+    pub fn base_fee(&self, usage_type: UsageType) -> u64 {
+        match usage_type {
+            UsageType::Tool => self.tool_base_fee,
+            UsageType::Resource => self.resource_base_fee,
+            UsageType::Prompt => self.prompt_base_fee,
+        }
+    }
+
+    /// Record usage and update metrics
+    pub fn record_usage(&mut self, usage_type: UsageType, count: u32, fee_collected: u64) {
+        match usage_type {
+            UsageType::Tool => self.total_tool_calls += count as u64,
+            UsageType::Resource => self.total_resource_accesses += count as u64,
+            UsageType::Prompt => self.total_prompt_uses += count as u64,
+        }
+        self.pending_fees += fee_collected;
+    }
+
+    /// Record a subscription payment owed to the server
+    pub fn record_subscription_payment(&mut self, fee_collected: u64) {
+        self.pending_fees += fee_collected;
+    }
+
+    /// Total uses across tools, resources and prompts
+    pub fn total_uses(&self) -> u64 {
+        self.total_tool_calls + self.total_resource_accesses + self.total_prompt_uses
+    }
+
+    /// Withdraw pending fees
+    pub fn withdraw_pending_fees(&mut self) -> u64 {
+        let fees = self.pending_fees;
+        self.pending_fees = 0;
+        self.total_fees_collected += fees;
+        fees
+    }
+
+    /// Update quality metrics
+    pub fn update_quality_metrics(
+        &mut self,
+        uptime_percentage: u8,
+        avg_response_time: u32,
+        error_rate: u8,
+        quality_score: u64,
+    ) {
+        self.uptime_percentage = uptime_percentage;
+        self.avg_response_time = avg_response_time;
+        self.error_rate = error_rate;
+        self.quality_score = quality_score;
+    }
+
+    /// Check if stake can be withdrawn
+    pub fn can_unstake(&self, current_timestamp: i64) -> bool {
+        current_timestamp >= self.stake_locked_until
+    }
+}
+
+/// Fixed-size prefix of an MCP server entry's account data. Token and
+/// metrics instructions borrow it in place instead of decoding the whole entry.
+#[repr(C, packed)]
+#[derive(Clone, Copy, Debug, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct McpServerEntryPrefixV1 {
+    /// Fields shared by all registry entries
+    pub header: EntryHeader,
+    /// Token, usage, fee and quality fields
+    pub economics: McpServerEconomicsV1,
+}
+
+impl McpServerEntryPrefixV1 {
+    /// Length of the prefix; the variable-length part of the entry starts here
+    pub const LEN: usize = std::mem::size_of::<Self>();
+
+    /// Borrow the prefix of an MCP server entry's account data. Entries in
+    /// an older layout are rejected, since their economics are elsewhere.
+    pub fn load(data: &[u8]) -> Result<&Self, ProgramError> {
+        let prefix: &Self = load(data)?;
+        prefix.header.check_layout_version()?;
+        Ok(prefix)
+    }
+
+    /// Mutably borrow the prefix of an MCP server entry's account data
+    pub fn load_mut(data: &mut [u8]) -> Result<&mut Self, ProgramError> {
+        let prefix: &mut Self = load_mut(data)?;
+        prefix.header.check_layout_version()?;
+        Ok(prefix)
+    }
+
+    /// Check if the server is active
+    pub fn is_active(&self) -> bool {
+        self.header.status == McpServerStatus::Active as u8
+    }
+}
+
 /// MCP Server Registry Entry (V1) - Solana account structure for storing MCP server data on-chain
 ///
 /// This structure represents the on-chain data for a registered Model Context Protocol (MCP) server,
@@ -15,11 +311,15 @@ use aeamcp_common::{
 /// - Core server information is stored on-chain
 /// - A limited number of key tools, resources, and prompts are summarized on-chain
 /// - Full definitions are accessible via the full_capabilities_uri, pointing to off-chain storage
+///
+/// The entry starts with a fixed-size prefix ([`McpServerEntryPrefixV1`]): the
+/// common header fields, `status` and `economics`. Strings and vectors follow,
+/// so their lengths never shift the offsets of the prefix.
 #[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
 pub struct McpServerRegistryEntryV1 {
     /// Bump seed used for this PDA's derivation
     pub bump: u8,
-    /// Schema version of this entry (e.g., 1)
+    /// Layout version of this entry ([`ENTRY_LAYOUT_VERSION`])
     pub registry_version: u8,
     /// State version for optimistic locking (prevents race conditions)
     pub state_version: u64,
@@ -27,6 +327,10 @@ pub struct McpServerRegistryEntryV1 {
     pub operation_in_progress: bool,
     /// Solana public key of the entry's owner/manager
     pub owner_authority: Pubkey,
+    /// Server status (0:Pending, 1:Active, 2:Inactive, 3:Deregistered)
+    pub status: u8,
+    /// Token, usage, fee and quality fields
+    pub economics: McpServerEconomicsV1,
     /// Unique identifier for the MCP server
     pub server_id: String,
     /// Human-readable server name
@@ -51,70 +355,31 @@ pub struct McpServerRegistryEntryV1 {
     pub onchain_resource_definitions: Vec<McpResourceDefinitionOnChain>,
     /// Summary of key on-chain advertised prompts
     pub onchain_prompt_definitions: Vec<McpPromptDefinitionOnChain>,
-    /// Server status (0:Pending, 1:Active, 2:Inactive, 3:Deregistered)
-    pub status: u8,
     /// Timestamp of initial registration
     pub registration_timestamp: i64,
-    /// Timestamp of the last update
+    /// Timestamp of the last metadata update. Token and metrics updates keep
+    /// their own timestamps in `economics`.
     pub last_update_timestamp: i64,
     /// Optional URI to off-chain JSON with full tool/resource/prompt definitions
     pub full_capabilities_uri: Option<String>,
     /// General discoverability tags for the server
     pub tags: Vec<String>,
-    
-    // Token-related fields for Phase 1
-    /// SVMAI Token mint address
-    pub token_mint: Pubkey,
-    /// SVMAI staked for verification
-    pub verification_stake: u64,
-    /// When tokens were staked
-    pub staking_timestamp: i64,
-    /// Lock period end
-    pub stake_locked_until: i64,
-    /// Verification tier (0: Basic, 1: Verified, 2: Premium)
-    pub verification_tier: u8,
-    /// Total tool invocations
-    pub total_tool_calls: u64,
-    /// Total resource fetches
-    pub total_resource_accesses: u64,
-    /// Total prompt executions
-    pub total_prompt_uses: u64,
-    /// Total SVMAI earned
-    pub total_fees_collected: u64,
-    /// Server quality metric (0-10000)
-    pub quality_score: u64,
-    /// Last 30 days uptime (0-100)
-    pub uptime_percentage: u8,
-    /// Average response time in ms
-    pub avg_response_time: u32,
-    /// Error percentage (0-100)
-    pub error_rate: u8,
-    /// Base fee per tool call
-    pub tool_base_fee: u64,
-    /// Base fee per resource access
-    pub resource_base_fee: u64,
-    /// Base fee per prompt use
-    pub prompt_base_fee: u64,
-    /// Number of calls for discount
-    pub bulk_discount_threshold: u32,
-    /// Discount percentage (0-50)
-    pub bulk_discount_percentage: u8,
-    /// Amount paid for registration
-    pub registration_fee_paid: u64,
-    /// Timestamp of last fee collection
-    pub last_fee_collection: i64,
-    /// Uncollected fees
-    pub pending_fees: u64,
+    /// Number of overflow tool accounts linked to this entry (indices `0..count`)
+    pub tool_account_count: u8,
+    /// Set while a chunked registration is still accepting appended definitions
+    pub registration_open: bool,
 }
 
 impl McpServerRegistryEntryV1 {
-    /// Calculate the space required for this account
+    /// Upper bound on the account size, with every string and vector at its maximum
     pub const SPACE: usize = 8 // Anchor discriminator
         + 1  // bump
         + 1  // registry_version
         + 8  // state_version
         + 1  // operation_in_progress
         + 32 // owner_authority
+        + 1  // status
+        + McpServerEconomicsV1::LEN
         + 4 + MAX_SERVER_ID_LEN // server_id
         + 4 + MAX_SERVER_NAME_LEN // name
         + 4 + MAX_SERVER_VERSION_LEN // server_version
@@ -127,33 +392,41 @@ impl McpServerRegistryEntryV1 {
         + 4 + (MAX_ONCHAIN_TOOL_DEFINITIONS * McpToolDefinitionOnChain::SPACE) // onchain_tool_definitions
         + 4 + (MAX_ONCHAIN_RESOURCE_DEFINITIONS * McpResourceDefinitionOnChain::SPACE) // onchain_resource_definitions
         + 4 + (MAX_ONCHAIN_PROMPT_DEFINITIONS * McpPromptDefinitionOnChain::SPACE) // onchain_prompt_definitions
-        + 1  // status
         + 8  // registration_timestamp
         + 8  // last_update_timestamp
         + 1 + 4 + MAX_FULL_CAPABILITIES_URI_LEN // full_capabilities_uri (Option)
         + 4 + (MAX_SERVER_TAGS * (4 + MAX_SERVER_TAG_LEN)) // tags
-        // Token-related fields
-        + 32 // token_mint
-        + 8  // verification_stake
-        + 8  // staking_timestamp
-        + 8  // stake_locked_until
-        + 1  // verification_tier
-        + 8  // total_tool_calls
-        + 8  // total_resource_accesses
-        + 8  // total_prompt_uses
-        + 8  // total_fees_collected
-        + 8  // quality_score
-        + 1  // uptime_percentage
-        + 4  // avg_response_time
-        + 1  // error_rate
-        + 8  // tool_base_fee
-        + 8  // resource_base_fee
-        + 8  // prompt_base_fee
-        + 4  // bulk_discount_threshold
-        + 1  // bulk_discount_percentage
-        + 8  // registration_fee_paid
-        + 8  // last_fee_collection
-        + 8; // pending_fees
+        + 1  // tool_account_count
+        + 1; // registration_open
+
+    /// Deserialize an entry from account data, ignoring any unused trailing
+    /// bytes. Entries in an older layout are rejected.
+    pub fn try_from_account_data(data: &[u8]) -> Result<Self, ProgramError> {
+        check_entry_layout_version(data)?;
+        Self::deserialize(&mut &data[..]).map_err(|_| ProgramError::InvalidAccountData)
+    }
+
+    /// Decode an entry stored in layout version 1 into the current layout
+    pub fn migrate_from_v1(data: &[u8]) -> Result<Self, ProgramError> {
+        if load::<EntryHeader>(data)?.registry_version != 1 {
+            return Err(RegistryError::UnsupportedEntryVersion.into());
+        }
+        let legacy = McpServerRegistryEntryLayoutV1::deserialize(&mut &data[..])
+            .map_err(|_| ProgramError::InvalidAccountData)?;
+        Ok(legacy.into())
+    }
+
+    /// Bytes the account needs to hold this entry (its exact serialized length)
+    pub fn required_space(&self) -> Result<usize, ProgramError> {
+        Ok(self.try_to_vec()?.len())
+    }
+
+    /// Read the server ID from account data without decoding the rest of the
+    /// entry; it is the first field after the fixed-size prefix
+    pub fn read_server_id(data: &[u8]) -> Result<String, ProgramError> {
+        let mut id_bytes = data.get(McpServerEntryPrefixV1::LEN..).ok_or(ProgramError::AccountDataTooSmall)?;
+        String::deserialize(&mut id_bytes).map_err(|_| ProgramError::InvalidAccountData)
+    }
 
     /// Create a new MCP server registry entry
     pub fn new(
@@ -177,10 +450,12 @@ impl McpServerRegistryEntryV1 {
     ) -> Self {
         Self {
             bump,
-            registry_version: 1,
+            registry_version: ENTRY_LAYOUT_VERSION,
             state_version: 0,
             operation_in_progress: false,
             owner_authority,
+            status: McpServerStatus::Pending as u8,
+            economics: McpServerEconomicsV1::default(),
             server_id,
             name,
             server_version,
@@ -193,33 +468,12 @@ impl McpServerRegistryEntryV1 {
             onchain_tool_definitions,
             onchain_resource_definitions,
             onchain_prompt_definitions,
-            status: McpServerStatus::Pending as u8,
             registration_timestamp: timestamp,
             last_update_timestamp: timestamp,
             full_capabilities_uri,
             tags,
-            // Initialize token fields with defaults
-            token_mint: Pubkey::default(),
-            verification_stake: 0,
-            staking_timestamp: 0,
-            stake_locked_until: 0,
-            verification_tier: 0,
-            total_tool_calls: 0,
-            total_resource_accesses: 0,
-            total_prompt_uses: 0,
-            total_fees_collected: 0,
-            quality_score: 0,
-            uptime_percentage: 0,
-            avg_response_time: 0,
-            error_rate: 0,
-            tool_base_fee: 0,
-            resource_base_fee: 0,
-            prompt_base_fee: 0,
-            bulk_discount_threshold: 0,
-            bulk_discount_percentage: 0,
-            registration_fee_paid: 0,
-            last_fee_collection: 0,
-            pending_fees: 0,
+            tool_account_count: 0,
+            registration_open: false,
         }
     }
 
@@ -245,14 +499,37 @@ impl McpServerRegistryEntryV1 {
 
     /// Update the last update timestamp with version check
     pub fn touch(&mut self, timestamp: i64, expected_version: u64) -> Result<(), aeamcp_common::error::RegistryError> {
-        if self.state_version != expected_version {
-            return Err(aeamcp_common::error::RegistryError::StateVersionMismatch);
-        }
+        self.check_expected_version(Some(expected_version))?;
         self.last_update_timestamp = timestamp;
-        self.state_version += 1;
+        self.bump_state_version();
         Ok(())
     }
 
+    /// The fixed header fields, as laid out at the start of the account
+    pub fn header(&self) -> EntryHeader {
+        EntryHeader {
+            bump: self.bump,
+            registry_version: self.registry_version,
+            state_version: self.state_version,
+            operation_in_progress: self.operation_in_progress as u8,
+            owner_authority: self.owner_authority,
+            status: self.status,
+        }
+    }
+
+    /// Compare a caller-supplied `state_version` against the stored one.
+    /// `None` skips the check for callers that do not need compare-and-swap.
+    pub fn check_expected_version(&self, expected_state_version: Option<u64>) -> Result<(), aeamcp_common::error::RegistryError> {
+        self.header().check_expected_version(expected_state_version)
+    }
+
+    /// Record a state change
+    pub fn bump_state_version(&mut self) {
+        let mut header = self.header();
+        header.bump_state_version();
+        self.state_version = header.state_version;
+    }
+
     /// Begin an operation (reentrancy guard)
     pub fn begin_operation(&mut self) -> Result<(), aeamcp_common::error::RegistryError> {
         if self.operation_in_progress {
@@ -272,15 +549,13 @@ impl McpServerRegistryEntryV1 {
     where
         F: FnOnce(&mut Self) -> Result<(), aeamcp_common::error::RegistryError>
     {
-        if self.state_version != expected_version {
-            return Err(aeamcp_common::error::RegistryError::StateVersionMismatch);
-        }
+        self.check_expected_version(Some(expected_version))?;
         self.begin_operation()?;
         
         let result = update_fn(self);
         
         if result.is_ok() {
-            self.state_version += 1;
+            self.bump_state_version();
         }
         self.end_operation();
         result
@@ -288,12 +563,10 @@ impl McpServerRegistryEntryV1 {
 
     /// Update status with version checking
     pub fn update_status(&mut self, status: u8, timestamp: i64, expected_version: u64) -> Result<(), aeamcp_common::error::RegistryError> {
-        if self.state_version != expected_version {
-            return Err(aeamcp_common::error::RegistryError::StateVersionMismatch);
-        }
+        self.check_expected_version(Some(expected_version))?;
         self.status = status;
         self.last_update_timestamp = timestamp;
-        self.state_version += 1;
+        self.bump_state_version();
         Ok(())
     }
 
@@ -331,105 +604,915 @@ impl McpServerRegistryEntryV1 {
         }
         capabilities.join(", ")
     }
-    
-    /// Update verification staking
-    pub fn update_verification_stake(
-        &mut self,
-        amount: u64,
-        tier: u8,
-        lock_until: i64,
-        timestamp: i64,
-    ) {
-        self.verification_stake = amount;
-        self.verification_tier = tier;
-        self.stake_locked_until = lock_until;
-        self.staking_timestamp = timestamp;
-        self.last_update_timestamp = timestamp;
-        self.state_version += 1;
+
+    /// Link a new overflow tool account; accounts are appended in index order
+    pub fn push_tool_account(&mut self, index: u8) -> Result<(), aeamcp_common::error::RegistryError> {
+        if index != self.tool_account_count {
+            return Err(aeamcp_common::error::RegistryError::InvalidOverflowIndex);
+        }
+        if self.tool_account_count as usize >= MAX_TOOL_ACCOUNTS {
+            return Err(aeamcp_common::error::RegistryError::TooManyToolAccounts);
+        }
+        self.tool_account_count += 1;
+        self.bump_state_version();
+        Ok(())
     }
-    
-    /// Update usage fees
-    pub fn update_usage_fees(
-        &mut self,
-        tool_base_fee: u64,
-        resource_base_fee: u64,
-        prompt_base_fee: u64,
-        bulk_discount_threshold: u32,
-        bulk_discount_percentage: u8,
-        timestamp: i64,
-    ) {
-        self.tool_base_fee = tool_base_fee;
-        self.resource_base_fee = resource_base_fee;
-        self.prompt_base_fee = prompt_base_fee;
-        self.bulk_discount_threshold = bulk_discount_threshold;
-        self.bulk_discount_percentage = bulk_discount_percentage;
-        self.last_fee_collection = timestamp;
+
+    /// Unlink an overflow tool account; only the last one can be removed so
+    /// the remaining indices stay contiguous
+    pub fn pop_tool_account(&mut self, index: u8) -> Result<(), aeamcp_common::error::RegistryError> {
+        if self.tool_account_count == 0 || index != self.tool_account_count - 1 {
+            return Err(aeamcp_common::error::RegistryError::InvalidOverflowIndex);
+        }
+        self.tool_account_count -= 1;
+        self.bump_state_version();
+        Ok(())
+    }
+
+    /// Fail unless a chunked registration is in progress on this entry
+    pub fn require_registration_open(&self) -> Result<(), aeamcp_common::error::RegistryError> {
+        if !self.registration_open {
+            return Err(aeamcp_common::error::RegistryError::RegistrationNotOpen);
+        }
+        Ok(())
+    }
+
+    /// Append tool definitions while the registration is open
+    pub fn append_tool_definitions(&mut self, tools: Vec<McpToolDefinitionOnChain>) -> Result<(), aeamcp_common::error::RegistryError> {
+        self.require_registration_open()?;
+        if self.onchain_tool_definitions.len() + tools.len() > MAX_ONCHAIN_TOOL_DEFINITIONS {
+            return Err(aeamcp_common::error::RegistryError::TooManyToolDefinitions);
+        }
+        self.onchain_tool_definitions.extend(tools);
+        self.bump_state_version();
+        Ok(())
+    }
+
+    /// Append resource definitions while the registration is open
+    pub fn append_resource_definitions(&mut self, resources: Vec<McpResourceDefinitionOnChain>) -> Result<(), aeamcp_common::error::RegistryError> {
+        self.require_registration_open()?;
+        if self.onchain_resource_definitions.len() + resources.len() > MAX_ONCHAIN_RESOURCE_DEFINITIONS {
+            return Err(aeamcp_common::error::RegistryError::TooManyResourceDefinitions);
+        }
+        self.onchain_resource_definitions.extend(resources);
+        self.bump_state_version();
+        Ok(())
+    }
+
+    /// Append prompt definitions while the registration is open
+    pub fn append_prompt_definitions(&mut self, prompts: Vec<McpPromptDefinitionOnChain>) -> Result<(), aeamcp_common::error::RegistryError> {
+        self.require_registration_open()?;
+        if self.onchain_prompt_definitions.len() + prompts.len() > MAX_ONCHAIN_PROMPT_DEFINITIONS {
+            return Err(aeamcp_common::error::RegistryError::TooManyPromptDefinitions);
+        }
+        self.onchain_prompt_definitions.extend(prompts);
+        self.bump_state_version();
+        Ok(())
+    }
+
+    /// Close the registration and activate the server. Callers validate the
+    /// assembled entry first.
+    pub fn finalize_registration(&mut self, timestamp: i64) -> Result<(), aeamcp_common::error::RegistryError> {
+        self.require_registration_open()?;
+        self.registration_open = false;
+        self.status = McpServerStatus::Active as u8;
         self.last_update_timestamp = timestamp;
-        self.state_version += 1;
+        self.bump_state_version();
+        Ok(())
     }
-    
-    /// Record usage and update metrics
-    pub fn record_usage(
+}
+
+/// Overflow tool account (V1) - holds one tool definition beyond the
+/// `MAX_ONCHAIN_TOOL_DEFINITIONS` kept inline, derived from
+/// `[TOOL_ACCOUNT_SEED, server_entry, index]`
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
+pub struct McpToolAccountV1 {
+    /// Bump seed used for this PDA's derivation
+    pub bump: u8,
+    /// MCP server entry this tool belongs to
+    pub server_entry: Pubkey,
+    /// Position of this account among the entry's overflow tools
+    pub index: u8,
+    /// The tool definition itself
+    pub tool: McpToolDefinitionOnChain,
+    /// Timestamp of the last update
+    pub last_update_timestamp: i64,
+}
+
+impl McpToolAccountV1 {
+    /// Upper bound on the account size
+    pub const SPACE: usize = 1 // bump
+        + 32 // server_entry
+        + 1  // index
+        + McpToolDefinitionOnChain::SERIALIZED_SIZE
+        + 8; // last_update_timestamp
+
+    /// Create a new overflow tool account
+    pub fn new(
+        bump: u8,
+        server_entry: Pubkey,
+        index: u8,
+        tool: McpToolDefinitionOnChain,
+        timestamp: i64,
+    ) -> Self {
+        Self {
+            bump,
+            server_entry,
+            index,
+            tool,
+            last_update_timestamp: timestamp,
+        }
+    }
+
+    /// Deserialize from account data, ignoring any unused trailing bytes
+    pub fn try_from_account_data(data: &[u8]) -> Result<Self, ProgramError> {
+        Self::deserialize(&mut &data[..]).map_err(|_| ProgramError::InvalidAccountData)
+    }
+
+    /// Bytes the account needs to hold this tool
+    pub fn required_space(&self) -> Result<usize, ProgramError> {
+        Ok(self.try_to_vec()?.len())
+    }
+}
+
+/// Usage type enum for tracking different service calls
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum UsageType {
+    Tool,
+    Resource,
+    Prompt,
+}
+
+impl UsageType {
+    /// Byte stored for this type in a pricing table
+    pub fn to_u8(self) -> u8 {
+        match self {
+            UsageType::Tool => 0,
+            UsageType::Resource => 1,
+            UsageType::Prompt => 2,
+        }
+    }
+
+    /// Type stored as `value` in a pricing table
+    pub fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(UsageType::Tool),
+            1 => Some(UsageType::Resource),
+            2 => Some(UsageType::Prompt),
+            _ => None,
+        }
+    }
+}
+
+/// Key of a priced item: the SHA256 of a tool or prompt name or of a
+/// resource URI pattern
+pub fn hash_item_name(name: &str) -> [u8; HASH_SIZE] {
+    solana_program::hash::hash(name.as_bytes()).to_bytes()
+}
+
+/// Fee for `count` uses at `unit_price`, less `discount_percentage` percent
+pub fn discounted_total(unit_price: u64, count: u32, discount_percentage: u8) -> Option<u64> {
+    let total = unit_price.checked_mul(count as u64)?;
+    let discount = (total as u128 * discount_percentage as u128 / 100) as u64;
+    Some(total - discount)
+}
+
+/// Price of one named tool, resource or prompt
+#[derive(BorshSerialize, BorshDeserialize, Clone, Copy, Debug, Default, PartialEq)]
+pub struct ItemPriceV1 {
+    /// Usage type of the item (0: tool, 1: resource, 2: prompt)
+    pub usage_type: u8,
+    /// [`hash_item_name`] of the item
+    pub name_hash: [u8; HASH_SIZE],
+    /// Fee per use, replacing the type's base fee
+    pub price: u64,
+}
+
+/// Bulk discount applied once a single charge covers `threshold` uses
+#[derive(BorshSerialize, BorshDeserialize, Clone, Copy, Debug, Default, PartialEq)]
+pub struct DiscountTier {
+    /// Uses per charge needed to reach the tier
+    pub threshold: u32,
+    /// Discount percentage of the tier
+    pub percentage: u8,
+}
+
+/// Per-item prices and tiered bulk discounts of an MCP server, derived from
+/// `[PRICING_TABLE_SEED, server_entry]`. A usage type with priced items is
+/// charged only for those items; other types keep the entry's base fee.
+/// With tiers, a charge reaching none of them gets no discount; without
+/// tiers the entry's single bulk discount applies.
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq)]
+pub struct McpPricingTableV1 {
+    /// Bump seed used for this PDA's derivation
+    pub bump: u8,
+    /// MCP server entry the prices belong to
+    pub server_entry: Pubkey,
+    /// Number of priced items in use
+    pub item_count: u8,
+    /// Priced items, only the first `item_count` are used
+    pub items: [ItemPriceV1; MAX_PRICED_ITEMS],
+    /// Number of discount tiers in use
+    pub tier_count: u8,
+    /// Discount tiers by ascending threshold, only the first `tier_count` are used
+    pub tiers: [DiscountTier; MAX_DISCOUNT_TIERS],
+    /// Timestamp of the last update
+    pub last_update_timestamp: i64,
+    /// Number of priced items applying at `change_effective_at`
+    pub pending_item_count: u8,
+    /// Priced items applying at `change_effective_at`
+    pub pending_items: [ItemPriceV1; MAX_PRICED_ITEMS],
+    /// Number of discount tiers applying at `change_effective_at`
+    pub pending_tier_count: u8,
+    /// Discount tiers applying at `change_effective_at`
+    pub pending_tiers: [DiscountTier; MAX_DISCOUNT_TIERS],
+    /// When the pending prices replace the current ones (0: none scheduled)
+    pub change_effective_at: i64,
+}
+
+impl McpPricingTableV1 {
+    /// Size of the account
+    pub const SPACE: usize = 1 // bump
+        + 32 // server_entry
+        + 1 + (1 + HASH_SIZE + 8) * MAX_PRICED_ITEMS // items
+        + 1 + (4 + 1) * MAX_DISCOUNT_TIERS // tiers
+        + 8 // last_update_timestamp
+        + 1 + (1 + HASH_SIZE + 8) * MAX_PRICED_ITEMS // pending_items
+        + 1 + (4 + 1) * MAX_DISCOUNT_TIERS // pending_tiers
+        + 8; // change_effective_at
+
+    /// Build a pricing table. Rejects duplicate items, more items or tiers
+    /// than fit, thresholds that do not ascend, and discounts that shrink
+    /// as the threshold grows or exceed `max_discount`.
+    pub fn new(
+        bump: u8,
+        server_entry: Pubkey,
+        items: &[ItemPriceV1],
+        tiers: &[DiscountTier],
+        max_discount: u8,
+        timestamp: i64,
+    ) -> Result<Self, aeamcp_common::error::RegistryError> {
+        use aeamcp_common::error::RegistryError;
+
+        if items.len() > MAX_PRICED_ITEMS || tiers.len() > MAX_DISCOUNT_TIERS {
+            return Err(RegistryError::InvalidPricingTable);
+        }
+        for (i, item) in items.iter().enumerate() {
+            if items[..i]
+                .iter()
+                .any(|other| other.usage_type == item.usage_type && other.name_hash == item.name_hash)
+            {
+                return Err(RegistryError::InvalidPricingTable);
+            }
+        }
+        let tiers_ascend = tiers
+            .windows(2)
+            .all(|w| w[0].threshold < w[1].threshold && w[0].percentage <= w[1].percentage);
+        if !tiers_ascend || tiers.iter().any(|t| t.threshold == 0 || t.percentage > max_discount) {
+            return Err(RegistryError::InvalidPricingTable);
+        }
+
+        let mut item_table = [ItemPriceV1::default(); MAX_PRICED_ITEMS];
+        item_table[..items.len()].copy_from_slice(items);
+        let mut tier_table = [DiscountTier::default(); MAX_DISCOUNT_TIERS];
+        tier_table[..tiers.len()].copy_from_slice(tiers);
+        Ok(Self {
+            bump,
+            server_entry,
+            item_count: items.len() as u8,
+            items: item_table,
+            tier_count: tiers.len() as u8,
+            tiers: tier_table,
+            last_update_timestamp: timestamp,
+            pending_item_count: 0,
+            pending_items: [ItemPriceV1::default(); MAX_PRICED_ITEMS],
+            pending_tier_count: 0,
+            pending_tiers: [DiscountTier::default(); MAX_DISCOUNT_TIERS],
+            change_effective_at: 0,
+        })
+    }
+
+    /// Replace the prices with those of `proposed` (a table from
+    /// [`Self::new`]) with notice for increases. If any item would cost more
+    /// or any charge would get a smaller discount, the whole new table is
+    /// held as pending until `timestamp + notice`; otherwise it applies now.
+    /// Items the current table leaves unpriced cost the base fee in
+    /// `economics`, and a fee still at zero was never set and cannot be
+    /// charged. A later change replaces a pending one. Returns when the
+    /// pending prices apply, or `None` if the change applied now.
+    pub fn schedule_prices(
         &mut self,
-        usage_type: UsageType,
-        count: u32,
-        fee_collected: u64,
-    ) {
+        proposed: &Self,
+        economics: &McpServerEconomicsV1,
+        timestamp: i64,
+        notice: i64,
+    ) -> Option<i64> {
+        self.apply_due_price_change(timestamp);
+
+        if !proposed.charges_more_than(self, economics) {
+            self.item_count = proposed.item_count;
+            self.items = proposed.items;
+            self.tier_count = proposed.tier_count;
+            self.tiers = proposed.tiers;
+            self.last_update_timestamp = timestamp;
+            self.change_effective_at = 0;
+            return None;
+        }
+        let effective_at = timestamp.saturating_add(notice);
+        self.pending_item_count = proposed.item_count;
+        self.pending_items = proposed.items;
+        self.pending_tier_count = proposed.tier_count;
+        self.pending_tiers = proposed.tiers;
+        self.change_effective_at = effective_at;
+        Some(effective_at)
+    }
+
+    /// Replace the current prices with the pending ones once they are due.
+    /// Returns whether a change was applied.
+    pub fn apply_due_price_change(&mut self, timestamp: i64) -> bool {
+        if self.change_effective_at == 0 || timestamp < self.change_effective_at {
+            return false;
+        }
+        self.item_count = self.pending_item_count;
+        self.items = self.pending_items;
+        self.tier_count = self.pending_tier_count;
+        self.tiers = self.pending_tiers;
+        self.last_update_timestamp = self.change_effective_at;
+        self.change_effective_at = 0;
+        true
+    }
+
+    /// Whether some use would cost more under this table than under
+    /// `current`, with `economics` pricing what neither table prices
+    fn charges_more_than(&self, current: &Self, economics: &McpServerEconomicsV1) -> bool {
+        // Unit price of an item, or `None` if it cannot be charged
+        let unit_price = |table: &Self, usage_type: UsageType, name_hash: &[u8; HASH_SIZE]| {
+            if table.prices_usage_type(usage_type) {
+                table.item_price(usage_type, name_hash)
+            } else {
+                Some(economics.base_fee(usage_type)).filter(|fee| *fee > 0)
+            }
+        };
+        let price_raised = self.active_items().iter().chain(current.active_items()).any(|item| {
+            let Some(usage_type) = UsageType::from_u8(item.usage_type) else {
+                return false;
+            };
+            matches!(
+                (unit_price(current, usage_type, &item.name_hash), unit_price(self, usage_type, &item.name_hash)),
+                (Some(old), Some(new)) if new > old
+            )
+        });
+
+        // Discounts only change at a threshold, so comparing them there
+        // covers every count
+        let discount = |table: &Self, count: u32| match table.discount_percentage(count) {
+            Some(percentage) => percentage,
+            None if count >= economics.bulk_discount_threshold => economics.bulk_discount_percentage,
+            None => 0,
+        };
+        let discount_shrinks = self
+            .active_tiers()
+            .iter()
+            .chain(current.active_tiers())
+            .map(|tier| tier.threshold)
+            .chain([0, economics.bulk_discount_threshold])
+            .any(|count| discount(self, count) < discount(current, count));
+
+        price_raised || discount_shrinks
+    }
+
+    /// Deserialize from account data
+    pub fn try_from_account_data(data: &[u8]) -> Result<Self, ProgramError> {
+        Self::deserialize(&mut &data[..]).map_err(|_| ProgramError::InvalidAccountData)
+    }
+
+    /// Priced items in use
+    pub fn active_items(&self) -> &[ItemPriceV1] {
+        &self.items[..(self.item_count as usize).min(MAX_PRICED_ITEMS)]
+    }
+
+    /// Whether any item of `usage_type` has a price
+    pub fn prices_usage_type(&self, usage_type: UsageType) -> bool {
+        self.active_items().iter().any(|item| item.usage_type == usage_type.to_u8())
+    }
+
+    /// Price of the named item, if it has one
+    pub fn item_price(&self, usage_type: UsageType, name_hash: &[u8; HASH_SIZE]) -> Option<u64> {
+        self.active_items()
+            .iter()
+            .find(|item| item.usage_type == usage_type.to_u8() && &item.name_hash == name_hash)
+            .map(|item| item.price)
+    }
+
+    /// Discount tiers in use
+    pub fn active_tiers(&self) -> &[DiscountTier] {
+        &self.tiers[..(self.tier_count as usize).min(MAX_DISCOUNT_TIERS)]
+    }
+
+    /// Discount of the highest tier `count` reaches, or `None` when the
+    /// table has no tiers
+    pub fn discount_percentage(&self, count: u32) -> Option<u8> {
+        let tiers = self.active_tiers();
+        if tiers.is_empty() {
+            return None;
+        }
+        Some(
+            tiers
+                .iter()
+                .take_while(|tier| count >= tier.threshold)
+                .last()
+                .map_or(0, |tier| tier.percentage),
+        )
+    }
+}
+
+/// Calls per usage type each user may make for free in every window of
+/// `period` seconds
+#[derive(BorshSerialize, BorshDeserialize, Clone, Copy, Debug, Default, PartialEq)]
+pub struct FreeTier {
+    pub tool_calls: u32,
+    pub resource_calls: u32,
+    pub prompt_calls: u32,
+    /// Length of a window in seconds
+    pub period: i64,
+}
+
+impl FreeTier {
+    /// Free calls of the given type per window
+    pub fn allowance(&self, usage_type: UsageType) -> u32 {
+        match usage_type {
+            UsageType::Tool => self.tool_calls,
+            UsageType::Resource => self.resource_calls,
+            UsageType::Prompt => self.prompt_calls,
+        }
+    }
+
+    /// Whether any calls are free
+    pub fn is_enabled(&self) -> bool {
+        self.tool_calls > 0 || self.resource_calls > 0 || self.prompt_calls > 0
+    }
+
+    /// Reject allowances without a period within
+    /// `MIN_FREE_TIER_PERIOD..=MAX_FREE_TIER_PERIOD`
+    pub fn validate(&self) -> Result<(), aeamcp_common::error::RegistryError> {
+        if self.is_enabled() && !(MIN_FREE_TIER_PERIOD..=MAX_FREE_TIER_PERIOD).contains(&self.period) {
+            return Err(aeamcp_common::error::RegistryError::InvalidFreeTier);
+        }
+        Ok(())
+    }
+}
+
+/// Usage of one MCP server by one user, derived from
+/// `[USAGE_RECORD_SEED, server_entry, user]`. Calls are counted per window
+/// of the server's free-tier period to grant free calls, and in lifetime
+/// totals as the user's usage history.
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq)]
+pub struct McpUsageRecordV1 {
+    /// Bump seed used for this PDA's derivation
+    pub bump: u8,
+    /// MCP server entry used
+    pub server_entry: Pubkey,
+    /// User whose usage is recorded
+    pub user: Pubkey,
+    /// Start of the current window
+    pub window_start: i64,
+    /// Calls made in the current window
+    pub window_calls: SubscriptionQuota,
+    /// Total tool invocations by the user
+    pub total_tool_calls: u64,
+    /// Total resource fetches by the user
+    pub total_resource_accesses: u64,
+    /// Total prompt executions by the user
+    pub total_prompt_uses: u64,
+    /// Total calls the free tier covered
+    pub total_free_calls: u64,
+    /// Total fees the user paid, take-rate included
+    pub total_fees_paid: u64,
+    /// Timestamp of the first recorded use
+    pub first_use_timestamp: i64,
+    /// Timestamp of the last recorded use
+    pub last_use_timestamp: i64,
+}
+
+impl McpUsageRecordV1 {
+    /// Size of the account
+    pub const SPACE: usize = 1 // bump
+        + 32 // server_entry
+        + 32 // user
+        + 8 // window_start
+        + 4 * 3 // window_calls
+        + 8 * 5 // totals
+        + 8 // first_use_timestamp
+        + 8; // last_use_timestamp
+
+    /// Record of a user yet to make a call
+    pub fn new(bump: u8, server_entry: Pubkey, user: Pubkey, timestamp: i64) -> Self {
+        Self {
+            bump,
+            server_entry,
+            user,
+            window_start: timestamp,
+            window_calls: SubscriptionQuota::default(),
+            total_tool_calls: 0,
+            total_resource_accesses: 0,
+            total_prompt_uses: 0,
+            total_free_calls: 0,
+            total_fees_paid: 0,
+            first_use_timestamp: timestamp,
+            last_use_timestamp: timestamp,
+        }
+    }
+
+    /// Deserialize from account data
+    pub fn try_from_account_data(data: &[u8]) -> Result<Self, ProgramError> {
+        Self::deserialize(&mut &data[..]).map_err(|_| ProgramError::InvalidAccountData)
+    }
+
+    /// Start a new window at `timestamp` once the current one has lasted
+    /// `period` seconds
+    pub fn roll_window(&mut self, period: i64, timestamp: i64) {
+        if timestamp >= self.window_start.saturating_add(period) {
+            self.window_start = timestamp;
+            self.window_calls = SubscriptionQuota::default();
+        }
+    }
+
+    /// Free calls of the given type left in the current window
+    pub fn free_calls_left(&self, usage_type: UsageType, free_tier: &FreeTier) -> u32 {
+        free_tier
+            .allowance(usage_type)
+            .saturating_sub(self.window_calls.get(usage_type))
+    }
+
+    /// Count `count` calls, `free` of them covered by the free tier, for
+    /// which `fees_paid` was charged
+    pub fn record(&mut self, usage_type: UsageType, count: u32, free: u32, fees_paid: u64, timestamp: i64) {
+        let window = self.window_calls.get_mut(usage_type);
+        *window = window.saturating_add(count);
         match usage_type {
             UsageType::Tool => self.total_tool_calls += count as u64,
             UsageType::Resource => self.total_resource_accesses += count as u64,
             UsageType::Prompt => self.total_prompt_uses += count as u64,
         }
-        self.pending_fees += fee_collected;
-        self.state_version += 1;
+        self.total_free_calls += free as u64;
+        self.total_fees_paid = self.total_fees_paid.saturating_add(fees_paid);
+        self.last_use_timestamp = timestamp;
     }
-    
-    /// Withdraw pending fees
-    pub fn withdraw_pending_fees(&mut self) -> u64 {
-        let fees = self.pending_fees;
-        self.pending_fees = 0;
-        self.total_fees_collected += fees;
-        self.state_version += 1;
-        fees
+}
+
+/// Calls per usage type, included in a subscription plan or left in a
+/// subscription
+#[derive(BorshSerialize, BorshDeserialize, Clone, Copy, Debug, Default, PartialEq)]
+pub struct SubscriptionQuota {
+    pub tool_calls: u32,
+    pub resource_calls: u32,
+    pub prompt_calls: u32,
+}
+
+impl SubscriptionQuota {
+    /// Calls of the given type
+    pub fn get(&self, usage_type: UsageType) -> u32 {
+        match usage_type {
+            UsageType::Tool => self.tool_calls,
+            UsageType::Resource => self.resource_calls,
+            UsageType::Prompt => self.prompt_calls,
+        }
     }
-    
-    /// Update quality metrics
-    pub fn update_quality_metrics(
+
+    fn get_mut(&mut self, usage_type: UsageType) -> &mut u32 {
+        match usage_type {
+            UsageType::Tool => &mut self.tool_calls,
+            UsageType::Resource => &mut self.resource_calls,
+            UsageType::Prompt => &mut self.prompt_calls,
+        }
+    }
+
+    /// Whether no calls of any type are left
+    pub fn is_empty(&self) -> bool {
+        self.tool_calls == 0 && self.resource_calls == 0 && self.prompt_calls == 0
+    }
+
+    /// Calls of both quotas added up
+    pub fn saturating_add(self, other: Self) -> Self {
+        Self {
+            tool_calls: self.tool_calls.saturating_add(other.tool_calls),
+            resource_calls: self.resource_calls.saturating_add(other.resource_calls),
+            prompt_calls: self.prompt_calls.saturating_add(other.prompt_calls),
+        }
+    }
+}
+
+/// Subscription plan of an MCP server, derived from
+/// `[SUBSCRIPTION_PLAN_SEED, server_entry, plan_id]`. Subscribers prepay
+/// `price` for `period` seconds of service with the included calls.
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq)]
+pub struct McpSubscriptionPlanV1 {
+    /// Bump seed used for this PDA's derivation
+    pub bump: u8,
+    /// MCP server entry the plan belongs to
+    pub server_entry: Pubkey,
+    /// Server-chosen plan number
+    pub plan_id: u8,
+    /// Price of one period
+    pub price: u64,
+    /// Length of one period in seconds
+    pub period: i64,
+    /// Calls included in each period
+    pub included: SubscriptionQuota,
+    /// Whether the plan accepts subscriptions and renewals
+    pub active: bool,
+    /// Timestamp of the last update
+    pub last_update_timestamp: i64,
+    /// Price applying at `change_effective_at`
+    pub pending_price: u64,
+    /// Period applying at `change_effective_at`
+    pub pending_period: i64,
+    /// Included calls applying at `change_effective_at`
+    pub pending_included: SubscriptionQuota,
+    /// When the pending terms replace the current ones (0: none scheduled)
+    pub change_effective_at: i64,
+}
+
+impl McpSubscriptionPlanV1 {
+    /// Size of the account
+    pub const SPACE: usize = 1 // bump
+        + 32 // server_entry
+        + 1 // plan_id
+        + 8 // price
+        + 8 // period
+        + 4 * 3 // included
+        + 1 // active
+        + 8 // last_update_timestamp
+        + 8 // pending_price
+        + 8 // pending_period
+        + 4 * 3 // pending_included
+        + 8; // change_effective_at
+
+    /// Reject plans that are free, include no calls, or whose period is
+    /// outside `MIN_SUBSCRIPTION_PERIOD..=MAX_SUBSCRIPTION_PERIOD`
+    pub fn validate(&self) -> Result<(), aeamcp_common::error::RegistryError> {
+        if self.price == 0
+            || self.included.is_empty()
+            || !(MIN_SUBSCRIPTION_PERIOD..=MAX_SUBSCRIPTION_PERIOD).contains(&self.period)
+        {
+            return Err(aeamcp_common::error::RegistryError::InvalidSubscriptionPlan);
+        }
+        Ok(())
+    }
+
+    /// Change the price, period and included calls with notice for
+    /// increases. If the price rises, the period shortens or any included
+    /// calls shrink, the new terms are held as pending until
+    /// `timestamp + notice`; otherwise they apply now. A later change
+    /// replaces a pending one. Returns when the pending terms apply, or
+    /// `None` if the change applied now.
+    pub fn schedule_terms(
         &mut self,
-        uptime_percentage: u8,
-        avg_response_time: u32,
-        error_rate: u8,
-        quality_score: u64,
-    ) {
-        self.uptime_percentage = uptime_percentage;
-        self.avg_response_time = avg_response_time;
-        self.error_rate = error_rate;
-        self.quality_score = quality_score;
-        self.state_version += 1;
+        price: u64,
+        period: i64,
+        included: SubscriptionQuota,
+        timestamp: i64,
+        notice: i64,
+    ) -> Option<i64> {
+        self.apply_due_terms_change(timestamp);
+
+        let included_shrinks = included.tool_calls < self.included.tool_calls
+            || included.resource_calls < self.included.resource_calls
+            || included.prompt_calls < self.included.prompt_calls;
+        if price <= self.price && period >= self.period && !included_shrinks {
+            self.price = price;
+            self.period = period;
+            self.included = included;
+            self.last_update_timestamp = timestamp;
+            self.change_effective_at = 0;
+            return None;
+        }
+        let effective_at = timestamp.saturating_add(notice);
+        self.pending_price = price;
+        self.pending_period = period;
+        self.pending_included = included;
+        self.change_effective_at = effective_at;
+        Some(effective_at)
     }
-    
-    /// Check if stake can be withdrawn
-    pub fn can_unstake(&self, current_timestamp: i64) -> bool {
-        current_timestamp >= self.stake_locked_until
+
+    /// Replace the current terms with the pending ones once they are due.
+    /// Returns whether a change was applied.
+    pub fn apply_due_terms_change(&mut self, timestamp: i64) -> bool {
+        if self.change_effective_at == 0 || timestamp < self.change_effective_at {
+            return false;
+        }
+        self.price = self.pending_price;
+        self.period = self.pending_period;
+        self.included = self.pending_included;
+        self.last_update_timestamp = self.change_effective_at;
+        self.change_effective_at = 0;
+        true
+    }
+
+    /// Deserialize from account data
+    pub fn try_from_account_data(data: &[u8]) -> Result<Self, ProgramError> {
+        Self::deserialize(&mut &data[..]).map_err(|_| ProgramError::InvalidAccountData)
     }
 }
 
-/// Usage type enum for tracking different service calls
-#[derive(Debug, Clone, Copy, PartialEq)]
-pub enum UsageType {
-    Tool,
-    Resource,
-    Prompt,
+/// A user's subscription to an MCP server, derived from
+/// `[SUBSCRIPTION_SEED, server_entry, subscriber]`. Usage is taken from the
+/// remaining calls until they run out or the subscription expires.
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq)]
+pub struct McpSubscriptionV1 {
+    /// Bump seed used for this PDA's derivation
+    pub bump: u8,
+    /// MCP server entry subscribed to
+    pub server_entry: Pubkey,
+    /// User holding the subscription
+    pub subscriber: Pubkey,
+    /// Plan the subscription was bought and is renewed on
+    pub plan_id: u8,
+    /// Start of the current uninterrupted run of periods
+    pub period_start: i64,
+    /// End of the last paid period
+    pub expires_at: i64,
+    /// Calls left until `expires_at`
+    pub remaining: SubscriptionQuota,
+}
+
+impl McpSubscriptionV1 {
+    /// Size of the account
+    pub const SPACE: usize = 1 // bump
+        + 32 // server_entry
+        + 32 // subscriber
+        + 1 // plan_id
+        + 8 // period_start
+        + 8 // expires_at
+        + 4 * 3; // remaining
+
+    /// Subscription to one period of `plan` starting at `timestamp`
+    pub fn new(bump: u8, subscriber: Pubkey, plan: &McpSubscriptionPlanV1, timestamp: i64) -> Self {
+        Self {
+            bump,
+            server_entry: plan.server_entry,
+            subscriber,
+            plan_id: plan.plan_id,
+            period_start: timestamp,
+            expires_at: timestamp.saturating_add(plan.period),
+            remaining: plan.included,
+        }
+    }
+
+    /// Deserialize from account data
+    pub fn try_from_account_data(data: &[u8]) -> Result<Self, ProgramError> {
+        Self::deserialize(&mut &data[..]).map_err(|_| ProgramError::InvalidAccountData)
+    }
+
+    /// Whether the last paid period is still running
+    pub fn is_active(&self, timestamp: i64) -> bool {
+        timestamp < self.expires_at
+    }
+
+    /// Pay for one more period of `plan`. A running subscription is extended
+    /// and keeps its unused calls; an expired one restarts at `timestamp`.
+    pub fn renew(&mut self, plan: &McpSubscriptionPlanV1, timestamp: i64) {
+        if self.is_active(timestamp) {
+            self.expires_at = self.expires_at.saturating_add(plan.period);
+            self.remaining = self.remaining.saturating_add(plan.included);
+        } else {
+            self.period_start = timestamp;
+            self.expires_at = timestamp.saturating_add(plan.period);
+            self.remaining = plan.included;
+        }
+    }
+
+    /// Take up to `count` calls from the quota, returning how many it
+    /// covered. An expired subscription covers none.
+    pub fn consume(&mut self, usage_type: UsageType, count: u32, timestamp: i64) -> u32 {
+        if !self.is_active(timestamp) {
+            return 0;
+        }
+        let left = self.remaining.get_mut(usage_type);
+        let covered = (*left).min(count);
+        *left -= covered;
+        covered
+    }
+}
+
+/// MCP server entry as stored in layout version 1, with the economic fields
+/// after the variable-length part. Only read to migrate such entries.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
+pub struct McpServerRegistryEntryLayoutV1 {
+    pub bump: u8,
+    pub registry_version: u8,
+    pub state_version: u64,
+    pub operation_in_progress: bool,
+    pub owner_authority: Pubkey,
+    pub server_id: String,
+    pub name: String,
+    pub server_version: String,
+    pub service_endpoint: String,
+    pub documentation_url: Option<String>,
+    pub server_capabilities_summary: Option<String>,
+    pub supports_resources: bool,
+    pub supports_tools: bool,
+    pub supports_prompts: bool,
+    pub onchain_tool_definitions: Vec<McpToolDefinitionOnChain>,
+    pub onchain_resource_definitions: Vec<McpResourceDefinitionOnChain>,
+    pub onchain_prompt_definitions: Vec<McpPromptDefinitionOnChain>,
+    pub status: u8,
+    pub registration_timestamp: i64,
+    pub last_update_timestamp: i64,
+    pub full_capabilities_uri: Option<String>,
+    pub tags: Vec<String>,
+    pub token_mint: Pubkey,
+    pub verification_stake: u64,
+    pub staking_timestamp: i64,
+    pub stake_locked_until: i64,
+    pub verification_tier: u8,
+    pub total_tool_calls: u64,
+    pub total_resource_accesses: u64,
+    pub total_prompt_uses: u64,
+    pub total_fees_collected: u64,
+    pub quality_score: u64,
+    pub uptime_percentage: u8,
+    pub avg_response_time: u32,
+    pub error_rate: u8,
+    pub tool_base_fee: u64,
+    pub resource_base_fee: u64,
+    pub prompt_base_fee: u64,
+    pub bulk_discount_threshold: u32,
+    pub bulk_discount_percentage: u8,
+    pub registration_fee_paid: u64,
+    pub last_fee_collection: i64,
+    pub pending_fees: u64,
+    pub tool_account_count: u8,
+    pub registration_open: bool,
+}
+
+impl From<McpServerRegistryEntryLayoutV1> for McpServerRegistryEntryV1 {
+    fn from(legacy: McpServerRegistryEntryLayoutV1) -> Self {
+        let economics = McpServerEconomicsV1 {
+            token_mint: legacy.token_mint,
+            verification_stake: legacy.verification_stake,
+            staking_timestamp: legacy.staking_timestamp,
+            stake_locked_until: legacy.stake_locked_until,
+            verification_tier: legacy.verification_tier,
+            total_tool_calls: legacy.total_tool_calls,
+            total_resource_accesses: legacy.total_resource_accesses,
+            total_prompt_uses: legacy.total_prompt_uses,
+            total_fees_collected: legacy.total_fees_collected,
+            quality_score: legacy.quality_score,
+            uptime_percentage: legacy.uptime_percentage,
+            avg_response_time: legacy.avg_response_time,
+            error_rate: legacy.error_rate,
+            tool_base_fee: legacy.tool_base_fee,
+            resource_base_fee: legacy.resource_base_fee,
+            prompt_base_fee: legacy.prompt_base_fee,
+            bulk_discount_threshold: legacy.bulk_discount_threshold,
+            bulk_discount_percentage: legacy.bulk_discount_percentage,
+            registration_fee_paid: legacy.registration_fee_paid,
+            last_fee_collection: legacy.last_fee_collection,
+            pending_fees: legacy.pending_fees,
+            ..McpServerEconomicsV1::default()
+        };
+
+        Self {
+            bump: legacy.bump,
+            registry_version: ENTRY_LAYOUT_VERSION,
+            state_version: legacy.state_version,
+            operation_in_progress: legacy.operation_in_progress,
+            owner_authority: legacy.owner_authority,
+            status: legacy.status,
+            economics,
+            server_id: legacy.server_id,
+            name: legacy.name,
+            server_version: legacy.server_version,
+            service_endpoint: legacy.service_endpoint,
+            documentation_url: legacy.documentation_url,
+            server_capabilities_summary: legacy.server_capabilities_summary,
+            supports_resources: legacy.supports_resources,
+            supports_tools: legacy.supports_tools,
+            supports_prompts: legacy.supports_prompts,
+            onchain_tool_definitions: legacy.onchain_tool_definitions,
+            onchain_resource_definitions: legacy.onchain_resource_definitions,
+            onchain_prompt_definitions: legacy.onchain_prompt_definitions,
+            registration_timestamp: legacy.registration_timestamp,
+            last_update_timestamp: legacy.last_update_timestamp,
+            full_capabilities_uri: legacy.full_capabilities_uri,
+            tags: legacy.tags,
+            tool_account_count: legacy.tool_account_count,
+            registration_open: legacy.registration_open,
+        }
+    }
 }
 
 impl Default for McpServerRegistryEntryV1 {
     fn default() -> Self {
         Self {
             bump: 0,
-            registry_version: 1,
+            registry_version: ENTRY_LAYOUT_VERSION,
             state_version: 0,
             operation_in_progress: false,
             owner_authority: Pubkey::default(),
+            status: McpServerStatus::Pending as u8,
+            economics: McpServerEconomicsV1::default(),
             server_id: String::new(),
             name: String::new(),
             server_version: String::new(),
@@ -442,33 +1525,12 @@ impl Default for McpServerRegistryEntryV1 {
             onchain_tool_definitions: Vec::new(),
             onchain_resource_definitions: Vec::new(),
             onchain_prompt_definitions: Vec::new(),
-            status: McpServerStatus::Pending as u8,
             registration_timestamp: 0,
             last_update_timestamp: 0,
             full_capabilities_uri: None,
             tags: Vec::new(),
-            // Token fields defaults
-            token_mint: Pubkey::default(),
-            verification_stake: 0,
-            staking_timestamp: 0,
-            stake_locked_until: 0,
-            verification_tier: 0,
-            total_tool_calls: 0,
-            total_resource_accesses: 0,
-            total_prompt_uses: 0,
-            total_fees_collected: 0,
-            quality_score: 0,
-            uptime_percentage: 0,
-            avg_response_time: 0,
-            error_rate: 0,
-            tool_base_fee: 0,
-            resource_base_fee: 0,
-            prompt_base_fee: 0,
-            bulk_discount_threshold: 0,
-            bulk_discount_percentage: 0,
-            registration_fee_paid: 0,
-            last_fee_collection: 0,
-            pending_fees: 0,
+            tool_account_count: 0,
+            registration_open: false,
         }
     }
 }
@@ -506,7 +1568,7 @@ mod tests {
         );
 
         assert_eq!(entry.bump, 255);
-        assert_eq!(entry.registry_version, 1);
+        assert_eq!(entry.registry_version, ENTRY_LAYOUT_VERSION);
         assert_eq!(entry.owner_authority, owner);
         assert_eq!(entry.server_id, "test-server");
         assert_eq!(entry.name, "Test Server");
@@ -524,6 +1586,239 @@ mod tests {
         assert_eq!(entry.tags, vec!["test", "example"]);
     }
 
+    #[test]
+    fn test_fee_increases_wait_for_notice() {
+        let mut economics = McpServerEconomicsV1::default();
+        economics.update_usage_fees(100, 100, 100, 10, 20, 0);
+
+        // Lower tool fee applies now; higher prompt fee waits
+        assert_eq!(economics.schedule_usage_fees(50, 100, 300, 10, 20, 1_000, 600), Some(1_600));
+        assert_eq!({ economics.tool_base_fee }, 50);
+        assert_eq!({ economics.prompt_base_fee }, 100);
+
+        assert!(!economics.apply_due_fee_change(1_599));
+        assert!(economics.apply_due_fee_change(1_600));
+        assert_eq!({ economics.prompt_base_fee }, 300);
+        assert_eq!({ economics.fee_change_effective_at }, 0);
+
+        // A smaller discount is an increase too
+        assert_eq!(economics.schedule_usage_fees(50, 100, 300, 10, 5, 2_000, 600), Some(2_600));
+        assert_eq!({ economics.bulk_discount_percentage }, 20);
+
+        // A later decrease replaces the pending increase
+        assert_eq!(economics.schedule_usage_fees(40, 100, 300, 10, 20, 2_100, 600), None);
+        assert_eq!({ economics.tool_base_fee }, 40);
+        assert!(!economics.apply_due_fee_change(5_000));
+        assert_eq!({ economics.bulk_discount_percentage }, 20);
+
+        // Fees that were never set take their first value at once
+        let mut unpriced = McpServerEconomicsV1::default();
+        assert_eq!(unpriced.schedule_usage_fees(10, 20, 30, 5, 10, 0, 600), None);
+        assert_eq!({ unpriced.prompt_base_fee }, 30);
+    }
+
+    #[test]
+    fn test_pricing_table() {
+        let lookup = ItemPriceV1 {
+            usage_type: UsageType::Tool.to_u8(),
+            name_hash: hash_item_name("lookup"),
+            price: 10,
+        };
+        let tiers = [
+            DiscountTier { threshold: 10, percentage: 5 },
+            DiscountTier { threshold: 100, percentage: 20 },
+        ];
+        let table = McpPricingTableV1::new(255, Pubkey::new_unique(), &[lookup], &tiers, 50, 0).unwrap();
+        assert_eq!(table.try_to_vec().unwrap().len(), McpPricingTableV1::SPACE);
+
+        assert_eq!(table.item_price(UsageType::Tool, &hash_item_name("lookup")), Some(10));
+        assert_eq!(table.item_price(UsageType::Prompt, &hash_item_name("lookup")), None);
+        assert!(table.prices_usage_type(UsageType::Tool));
+        assert!(!table.prices_usage_type(UsageType::Prompt));
+        assert_eq!(table.discount_percentage(9), Some(0));
+        assert_eq!(table.discount_percentage(99), Some(5));
+        assert_eq!(table.discount_percentage(100), Some(20));
+        assert_eq!(discounted_total(10, 100, 20), Some(800));
+        assert_eq!(discounted_total(u64::MAX, 2, 0), None);
+
+        let no_tiers = McpPricingTableV1::new(255, Pubkey::new_unique(), &[], &[], 50, 0).unwrap();
+        assert_eq!(no_tiers.discount_percentage(1_000), None);
+
+        use aeamcp_common::error::RegistryError;
+        assert_eq!(
+            McpPricingTableV1::new(0, Pubkey::new_unique(), &[lookup, lookup], &[], 50, 0),
+            Err(RegistryError::InvalidPricingTable)
+        );
+        assert_eq!(
+            McpPricingTableV1::new(0, Pubkey::new_unique(), &[], &[tiers[1], tiers[0]], 50, 0),
+            Err(RegistryError::InvalidPricingTable)
+        );
+        assert_eq!(
+            McpPricingTableV1::new(0, Pubkey::new_unique(), &[], &tiers, 10, 0),
+            Err(RegistryError::InvalidPricingTable)
+        );
+    }
+
+    #[test]
+    fn test_price_increases_wait_for_notice() {
+        let mut economics = McpServerEconomicsV1::default();
+        economics.update_usage_fees(100, 100, 0, 10, 20, 0);
+        let price = |usage_type: UsageType, name: &str, price| ItemPriceV1 {
+            usage_type: usage_type.to_u8(),
+            name_hash: hash_item_name(name),
+            price,
+        };
+        let table = |items: &[ItemPriceV1], tiers: &[DiscountTier]| {
+            McpPricingTableV1::new(255, Pubkey::default(), items, tiers, 50, 0).unwrap()
+        };
+        let mut current = table(&[], &[]);
+
+        // Pricing below the base fee applies now, above it waits
+        let cheaper = table(&[price(UsageType::Tool, "lookup", 80)], &[]);
+        assert_eq!(current.schedule_prices(&cheaper, &economics, 1_000, 600), None);
+        assert_eq!(current.item_price(UsageType::Tool, &hash_item_name("lookup")), Some(80));
+        let dearer = table(&[price(UsageType::Tool, "lookup", 120)], &[]);
+        assert_eq!(current.schedule_prices(&dearer, &economics, 1_000, 600), Some(1_600));
+        assert_eq!(current.item_price(UsageType::Tool, &hash_item_name("lookup")), Some(80));
+
+        assert!(!current.apply_due_price_change(1_599));
+        assert!(current.apply_due_price_change(1_600));
+        assert_eq!(current.item_price(UsageType::Tool, &hash_item_name("lookup")), Some(120));
+        assert_eq!(current.change_effective_at, 0);
+
+        // Dropping the item falls back to the lower base fee at once
+        assert_eq!(current.schedule_prices(&table(&[], &[]), &economics, 2_000, 600), None);
+
+        // Tiers replacing a larger bulk discount are an increase
+        let tiered = table(&[], &[DiscountTier { threshold: 10, percentage: 5 }]);
+        assert_eq!(current.schedule_prices(&tiered, &economics, 2_000, 600), Some(2_600));
+        assert_eq!(current.discount_percentage(10), None);
+
+        // A later decrease replaces the pending increase
+        let generous = table(&[], &[DiscountTier { threshold: 5, percentage: 20 }]);
+        assert_eq!(current.schedule_prices(&generous, &economics, 2_100, 600), None);
+        assert!(!current.apply_due_price_change(5_000));
+        assert_eq!(current.discount_percentage(5), Some(20));
+
+        // Prompts never had a fee, so their first price applies now
+        let prompt = table(&[price(UsageType::Prompt, "summarize", 500)], &[DiscountTier { threshold: 5, percentage: 20 }]);
+        assert_eq!(current.schedule_prices(&prompt, &economics, 3_000, 600), None);
+    }
+
+    #[test]
+    fn test_subscription_terms_wait_for_notice() {
+        let included = SubscriptionQuota { tool_calls: 10, resource_calls: 0, prompt_calls: 5 };
+        let mut plan = McpSubscriptionPlanV1 {
+            bump: 255,
+            server_entry: Pubkey::new_unique(),
+            plan_id: 1,
+            price: 100,
+            period: MIN_SUBSCRIPTION_PERIOD,
+            included,
+            active: true,
+            last_update_timestamp: 0,
+            pending_price: 0,
+            pending_period: 0,
+            pending_included: SubscriptionQuota::default(),
+            change_effective_at: 0,
+        };
+
+        // A cheaper, longer plan applies now
+        assert_eq!(plan.schedule_terms(90, 2 * MIN_SUBSCRIPTION_PERIOD, included, 1_000, 600), None);
+        assert_eq!(plan.price, 90);
+
+        // Fewer included calls wait, like a higher price
+        let fewer = SubscriptionQuota { tool_calls: 5, ..included };
+        assert_eq!(plan.schedule_terms(90, 2 * MIN_SUBSCRIPTION_PERIOD, fewer, 1_000, 600), Some(1_600));
+        assert_eq!(plan.included, included);
+        assert!(!plan.apply_due_terms_change(1_599));
+        assert!(plan.apply_due_terms_change(1_600));
+        assert_eq!(plan.included, fewer);
+
+        assert_eq!(plan.schedule_terms(150, 2 * MIN_SUBSCRIPTION_PERIOD, fewer, 2_000, 600), Some(2_600));
+        assert_eq!(plan.price, 90);
+        assert!(plan.apply_due_terms_change(3_000));
+        assert_eq!(plan.price, 150);
+    }
+
+    #[test]
+    fn test_subscription_quota_and_renewal() {
+        let plan = McpSubscriptionPlanV1 {
+            bump: 255,
+            server_entry: Pubkey::new_unique(),
+            plan_id: 1,
+            price: 100,
+            period: MIN_SUBSCRIPTION_PERIOD,
+            included: SubscriptionQuota { tool_calls: 10, resource_calls: 0, prompt_calls: 5 },
+            active: true,
+            last_update_timestamp: 0,
+            pending_price: 0,
+            pending_period: 0,
+            pending_included: SubscriptionQuota::default(),
+            change_effective_at: 0,
+        };
+        assert!(plan.validate().is_ok());
+        assert_eq!(plan.try_to_vec().unwrap().len(), McpSubscriptionPlanV1::SPACE);
+
+        let mut subscription = McpSubscriptionV1::new(254, Pubkey::new_unique(), &plan, 1_000);
+        assert_eq!(subscription.try_to_vec().unwrap().len(), McpSubscriptionV1::SPACE);
+        assert_eq!(subscription.expires_at, 1_000 + MIN_SUBSCRIPTION_PERIOD);
+
+        // The quota covers what it can, the rest is left to per-call fees
+        assert_eq!(subscription.consume(UsageType::Tool, 4, 1_000), 4);
+        assert_eq!(subscription.consume(UsageType::Tool, 8, 1_000), 6);
+        assert_eq!(subscription.consume(UsageType::Resource, 1, 1_000), 0);
+        assert_eq!(subscription.remaining.get(UsageType::Tool), 0);
+
+        // Renewing early extends the period and keeps unused calls
+        subscription.renew(&plan, 2_000);
+        assert_eq!(subscription.expires_at, 1_000 + 2 * MIN_SUBSCRIPTION_PERIOD);
+        assert_eq!(subscription.remaining.get(UsageType::Prompt), 10);
+
+        // An expired subscription covers nothing until renewed
+        let expired_at = subscription.expires_at;
+        assert_eq!(subscription.consume(UsageType::Prompt, 1, expired_at), 0);
+        subscription.renew(&plan, expired_at + 50);
+        assert_eq!(subscription.period_start, expired_at + 50);
+        assert_eq!(subscription.remaining, plan.included);
+
+        use aeamcp_common::error::RegistryError;
+        let free = McpSubscriptionPlanV1 { price: 0, ..plan.clone() };
+        assert_eq!(free.validate(), Err(RegistryError::InvalidSubscriptionPlan));
+        let empty = McpSubscriptionPlanV1 { included: SubscriptionQuota::default(), ..plan.clone() };
+        assert_eq!(empty.validate(), Err(RegistryError::InvalidSubscriptionPlan));
+        let short = McpSubscriptionPlanV1 { period: MIN_SUBSCRIPTION_PERIOD - 1, ..plan };
+        assert_eq!(short.validate(), Err(RegistryError::InvalidSubscriptionPlan));
+    }
+
+    #[test]
+    fn test_free_tier_windows() {
+        let free_tier = FreeTier { tool_calls: 3, resource_calls: 0, prompt_calls: 0, period: MIN_FREE_TIER_PERIOD };
+        assert!(free_tier.validate().is_ok());
+        let mut economics = McpServerEconomicsV1::default();
+        economics.set_free_tier(free_tier);
+        assert_eq!(economics.free_tier(), free_tier);
+
+        let mut record = McpUsageRecordV1::new(255, Pubkey::new_unique(), Pubkey::new_unique(), 1_000);
+        assert_eq!(record.try_to_vec().unwrap().len(), McpUsageRecordV1::SPACE);
+        assert_eq!(record.free_calls_left(UsageType::Tool, &free_tier), 3);
+        record.record(UsageType::Tool, 5, 3, 200, 1_000);
+        assert_eq!(record.free_calls_left(UsageType::Tool, &free_tier), 0);
+        assert_eq!(record.free_calls_left(UsageType::Prompt, &free_tier), 0);
+
+        // The allowance comes back once the window has run its period
+        record.roll_window(free_tier.period, 1_000 + MIN_FREE_TIER_PERIOD - 1);
+        assert_eq!(record.free_calls_left(UsageType::Tool, &free_tier), 0);
+        record.roll_window(free_tier.period, 1_000 + MIN_FREE_TIER_PERIOD);
+        assert_eq!(record.free_calls_left(UsageType::Tool, &free_tier), 3);
+        assert_eq!((record.total_tool_calls, record.total_free_calls, record.total_fees_paid), (5, 3, 200));
+
+        use aeamcp_common::error::RegistryError;
+        let no_period = FreeTier { period: 0, ..free_tier };
+        assert_eq!(no_period.validate(), Err(RegistryError::InvalidFreeTier));
+        assert!(FreeTier::default().validate().is_ok());
+    }
+
     #[test]
     fn test_status_methods() {
         let mut entry = McpServerRegistryEntryV1::default();
@@ -623,6 +1918,19 @@ mod tests {
         assert_eq!(entry.status, McpServerStatus::Active as u8); // Should not change
     }
 
+    #[test]
+    fn test_check_expected_version() {
+        let mut entry = McpServerRegistryEntryV1::default();
+        entry.state_version = 2;
+
+        assert!(entry.check_expected_version(None).is_ok());
+        assert!(entry.check_expected_version(Some(2)).is_ok());
+        assert_eq!(
+            entry.check_expected_version(Some(3)),
+            Err(aeamcp_common::error::RegistryError::StateVersionMismatch)
+        );
+    }
+
     #[test]
     fn test_serialization() {
         let entry = McpServerRegistryEntryV1::default();
@@ -631,10 +1939,219 @@ mod tests {
         assert_eq!(entry, deserialized);
     }
 
+    #[test]
+    fn test_required_space() {
+        let mut entry = McpServerRegistryEntryV1::default();
+        let base = entry.required_space().unwrap();
+        assert!(base < McpServerRegistryEntryV1::SPACE);
+
+        entry.tags.push("search".to_string());
+        assert_eq!(entry.required_space().unwrap(), base + 4 + "search".len());
+
+        let mut data = entry.try_to_vec().unwrap();
+        data.resize(McpServerRegistryEntryV1::SPACE, 0);
+        assert_eq!(McpServerRegistryEntryV1::try_from_account_data(&data).unwrap(), entry);
+    }
+
+    #[test]
+    fn test_prefix_matches_borsh_layout() {
+        let entry = McpServerRegistryEntryV1 {
+            owner_authority: Pubkey::new_unique(),
+            server_id: "server".to_string(),
+            status: McpServerStatus::Active as u8,
+            economics: McpServerEconomicsV1 {
+                tool_base_fee: 25,
+                ..Default::default()
+            },
+            tags: vec!["search".to_string()],
+            ..Default::default()
+        };
+        let mut data = entry.try_to_vec().unwrap();
+
+        assert_eq!(McpServerRegistryEntryV1::read_server_id(&data).unwrap(), "server");
+        let prefix = McpServerEntryPrefixV1::load_mut(&mut data).unwrap();
+        assert!(prefix.is_active());
+        assert_eq!(prefix.economics, entry.economics);
+        assert_eq!(prefix.economics.base_fee(UsageType::Tool), 25);
+
+        // In-place updates are visible to a full decode
+        prefix.economics.record_usage(UsageType::Tool, 4, 100);
+        prefix.header.bump_state_version();
+        let decoded = McpServerRegistryEntryV1::try_from_account_data(&data).unwrap();
+        assert_eq!(decoded.state_version, 1);
+        assert_eq!({ decoded.economics.total_tool_calls }, 4);
+        assert_eq!({ decoded.economics.pending_fees }, 100);
+        assert_eq!(decoded.tags, entry.tags);
+
+        let mut economics = decoded.economics;
+        assert_eq!(economics.withdraw_pending_fees(), 100);
+        assert_eq!({ economics.total_fees_collected }, 100);
+    }
+
+    #[test]
+    fn test_layout_v1_entries_migrate() {
+        let legacy = McpServerRegistryEntryLayoutV1 {
+            bump: 254,
+            registry_version: 1,
+            state_version: 5,
+            operation_in_progress: false,
+            owner_authority: Pubkey::new_unique(),
+            server_id: "server".to_string(),
+            name: "Server".to_string(),
+            server_version: "1.0.0".to_string(),
+            service_endpoint: "https://example.com/mcp".to_string(),
+            documentation_url: None,
+            server_capabilities_summary: None,
+            supports_resources: false,
+            supports_tools: true,
+            supports_prompts: false,
+            onchain_tool_definitions: vec![],
+            onchain_resource_definitions: vec![],
+            onchain_prompt_definitions: vec![],
+            status: McpServerStatus::Active as u8,
+            registration_timestamp: 10,
+            last_update_timestamp: 20,
+            full_capabilities_uri: None,
+            tags: vec!["search".to_string()],
+            token_mint: Pubkey::new_unique(),
+            verification_stake: 1_000,
+            staking_timestamp: 30,
+            stake_locked_until: 40,
+            verification_tier: 2,
+            total_tool_calls: 12,
+            total_resource_accesses: 0,
+            total_prompt_uses: 0,
+            total_fees_collected: 70,
+            quality_score: 9_000,
+            uptime_percentage: 99,
+            avg_response_time: 60,
+            error_rate: 1,
+            tool_base_fee: 25,
+            resource_base_fee: 0,
+            prompt_base_fee: 0,
+            bulk_discount_threshold: 100,
+            bulk_discount_percentage: 10,
+            registration_fee_paid: 50,
+            last_fee_collection: 50,
+            pending_fees: 300,
+            tool_account_count: 1,
+            registration_open: false,
+        };
+        let mut data = legacy.try_to_vec().unwrap();
+
+        // Neither path reads the old layout as if it were the current one
+        assert_eq!(
+            McpServerEntryPrefixV1::load(&data).unwrap_err(),
+            RegistryError::UnsupportedEntryVersion.into()
+        );
+        assert_eq!(
+            McpServerEntryPrefixV1::load_mut(&mut data).unwrap_err(),
+            RegistryError::UnsupportedEntryVersion.into()
+        );
+        assert_eq!(
+            McpServerRegistryEntryV1::try_from_account_data(&data).unwrap_err(),
+            RegistryError::UnsupportedEntryVersion.into()
+        );
+
+        let entry = McpServerRegistryEntryV1::migrate_from_v1(&data).unwrap();
+        assert_eq!(entry.registry_version, ENTRY_LAYOUT_VERSION);
+        assert_eq!(entry.state_version, 5);
+        assert_eq!(entry.owner_authority, legacy.owner_authority);
+        assert!(entry.is_active());
+        assert_eq!(entry.tags, legacy.tags);
+        assert_eq!(entry.tool_account_count, 1);
+        assert_eq!(entry.economics.token_mint, legacy.token_mint);
+        assert_eq!({ entry.economics.verification_stake }, 1_000);
+        assert_eq!({ entry.economics.pending_fees }, 300);
+        assert_eq!(entry.economics.base_fee(UsageType::Tool), 25);
+        assert_eq!({ entry.economics.fee_change_effective_at }, 0);
+
+        // The migrated entry reads back through both paths
+        let data = entry.try_to_vec().unwrap();
+        assert_eq!(McpServerEntryPrefixV1::load(&data).unwrap().economics, entry.economics);
+        assert_eq!(McpServerRegistryEntryV1::try_from_account_data(&data).unwrap(), entry);
+        assert_eq!(
+            McpServerRegistryEntryV1::migrate_from_v1(&data).unwrap_err(),
+            RegistryError::UnsupportedEntryVersion.into()
+        );
+    }
+
+    #[test]
+    fn test_tool_accounts_stay_contiguous() {
+        use aeamcp_common::error::RegistryError;
+
+        let mut entry = McpServerRegistryEntryV1::default();
+
+        assert_eq!(entry.push_tool_account(2), Err(RegistryError::InvalidOverflowIndex));
+        entry.push_tool_account(0).unwrap();
+        entry.push_tool_account(1).unwrap();
+        assert_eq!(entry.tool_account_count, 2);
+
+        assert_eq!(entry.pop_tool_account(0), Err(RegistryError::InvalidOverflowIndex));
+        entry.pop_tool_account(1).unwrap();
+        entry.pop_tool_account(0).unwrap();
+
+        entry.tool_account_count = MAX_TOOL_ACCOUNTS as u8;
+        assert_eq!(
+            entry.push_tool_account(MAX_TOOL_ACCOUNTS as u8),
+            Err(RegistryError::TooManyToolAccounts)
+        );
+    }
+
+    #[test]
+    fn test_tool_account_space() {
+        let tool = McpToolDefinitionOnChain {
+            name: "search".to_string(),
+            description_hash: [1; HASH_SIZE],
+            input_schema_hash: [2; HASH_SIZE],
+            output_schema_hash: [3; HASH_SIZE],
+            tags: vec!["web".to_string()],
+        };
+        let account = McpToolAccountV1::new(254, Pubkey::new_unique(), 7, tool, 1640995200);
+
+        assert!(account.required_space().unwrap() <= McpToolAccountV1::SPACE);
+
+        let mut data = account.try_to_vec().unwrap();
+        data.resize(McpToolAccountV1::SPACE, 0);
+        assert_eq!(McpToolAccountV1::try_from_account_data(&data).unwrap(), account);
+    }
+
     #[test]
     fn test_space_calculation() {
         // Ensure the SPACE constant is reasonable
         assert!(McpServerRegistryEntryV1::SPACE > 1000);
         assert!(McpServerRegistryEntryV1::SPACE < 10000); // Should be under 10KB
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_chunked_registration_appends() {
+        use aeamcp_common::error::RegistryError;
+
+        let mut entry = McpServerRegistryEntryV1::default();
+        let tool = McpToolDefinitionOnChain {
+            name: "search".to_string(),
+            description_hash: [1; HASH_SIZE],
+            input_schema_hash: [2; HASH_SIZE],
+            output_schema_hash: [3; HASH_SIZE],
+            tags: vec![],
+        };
+
+        assert_eq!(
+            entry.append_tool_definitions(vec![tool.clone()]),
+            Err(RegistryError::RegistrationNotOpen)
+        );
+
+        entry.registration_open = true;
+        entry.append_tool_definitions(vec![tool.clone()]).unwrap();
+        assert_eq!(
+            entry.append_tool_definitions(vec![tool; MAX_ONCHAIN_TOOL_DEFINITIONS]),
+            Err(RegistryError::TooManyToolDefinitions)
+        );
+        assert_eq!(entry.tool_count(), 1);
+
+        entry.finalize_registration(1640995200).unwrap();
+        assert!(entry.is_active());
+        assert!(!entry.registration_open);
+        assert_eq!(entry.finalize_registration(0), Err(RegistryError::RegistrationNotOpen));
+    }
+}
@@ -8,6 +8,7 @@ use aeamcp_common::{
         McpPromptDefinitionOnChainInput
     },
 };
+use crate::state::McpServerRegistryEntryV1;
 
 /// Validate MCP server registration input
 pub fn validate_register_mcp_server(
@@ -63,6 +64,54 @@ pub fn validate_register_mcp_server(
     Ok(())
 }
 
+/// Validate an entry assembled by chunked registration with the same rules
+/// as a single-transaction `RegisterMcpServer`
+pub fn validate_mcp_server_entry(entry: &McpServerRegistryEntryV1) -> Result<(), RegistryError> {
+    let tools: Vec<McpToolDefinitionOnChainInput> = entry
+        .onchain_tool_definitions
+        .iter()
+        .map(|tool| McpToolDefinitionOnChainInput {
+            name: tool.name.clone(),
+            description_hash: tool.description_hash,
+            input_schema_hash: tool.input_schema_hash,
+            output_schema_hash: tool.output_schema_hash,
+            tags: tool.tags.clone(),
+        })
+        .collect();
+    let resources: Vec<McpResourceDefinitionOnChainInput> = entry
+        .onchain_resource_definitions
+        .iter()
+        .map(|resource| McpResourceDefinitionOnChainInput {
+            uri_pattern: resource.uri_pattern.clone(),
+            description_hash: resource.description_hash,
+            tags: resource.tags.clone(),
+        })
+        .collect();
+    let prompts: Vec<McpPromptDefinitionOnChainInput> = entry
+        .onchain_prompt_definitions
+        .iter()
+        .map(|prompt| McpPromptDefinitionOnChainInput {
+            name: prompt.name.clone(),
+            description_hash: prompt.description_hash,
+            tags: prompt.tags.clone(),
+        })
+        .collect();
+
+    validate_register_mcp_server(
+        &entry.server_id,
+        &entry.name,
+        &entry.server_version,
+        &entry.service_endpoint,
+        &entry.documentation_url,
+        &entry.server_capabilities_summary,
+        &tools,
+        &resources,
+        &prompts,
+        &entry.full_capabilities_uri,
+        &entry.tags,
+    )
+}
+
 /// Validate server ID
 pub fn validate_server_id(server_id: &str) -> Result<(), RegistryError> {
     if server_id.is_empty() {
@@ -0,0 +1,180 @@
+//! Compute units of the in-place hot path on an SBF build
+//!
+//! Needs `target/deploy/solana_mcp.so`, so it is ignored by `cargo test`.
+//! Run it with `cargo test-sbf -p solana-mcp --test compute_units -- --ignored --nocapture`.
+//! It prints `units_consumed` of `RecordUsageAndCollectFee` against a minimal
+//! entry and one with its capabilities summary and tags at their maximum
+//! length, and fails if the two differ.
+
+use aeamcp_common::{
+    config::{RegistryConfigParams, RegistryConfigV1},
+    constants::{
+        IDEMPOTENCY_KEY_LEN, MAX_SERVER_CAPABILITIES_SUMMARY_LEN, MAX_SERVER_TAGS, MAX_SERVER_TAG_LEN,
+        PRICING_TABLE_SEED, SUBSCRIPTION_SEED, USAGE_RECORD_SEED,
+    },
+    receipt::derive_usage_receipt_pda,
+    revenue::derive_revenue_split_pda,
+    token_utils::{derive_server_fee_vault_pda, derive_treasury_vault_pda},
+    utils::get_registry_config_pda,
+    McpServerStatus,
+};
+use borsh::BorshSerialize;
+use solana_mcp::{
+    instruction::{McpServerRegistryInstruction, UsageType},
+    McpServerEconomicsV1, McpServerRegistryEntryV1,
+};
+use solana_program::{
+    instruction::{AccountMeta, Instruction},
+    native_token::LAMPORTS_PER_SOL,
+    program_pack::Pack,
+    pubkey::Pubkey,
+    rent::Rent,
+};
+use solana_program_test::*;
+use solana_sdk::{
+    account::Account,
+    signature::{Keypair, Signer},
+    transaction::Transaction,
+};
+
+/// An account holding `data` owned by `owner`, rent exempt
+fn rent_exempt_account(data: Vec<u8>, owner: Pubkey) -> Account {
+    Account {
+        lamports: Rent::default().minimum_balance(data.len()),
+        data,
+        owner,
+        executable: false,
+        rent_epoch: 0,
+    }
+}
+
+fn token_account(mint: Pubkey, owner: Pubkey, amount: u64) -> Account {
+    let mut data = vec![0; spl_token::state::Account::LEN];
+    spl_token::state::Account {
+        mint,
+        owner,
+        amount,
+        state: spl_token::state::AccountState::Initialized,
+        ..Default::default()
+    }
+    .pack_into_slice(&mut data);
+    rent_exempt_account(data, spl_token::id())
+}
+
+#[tokio::test]
+#[ignore = "needs the SBF build of the program; run with cargo test-sbf"]
+async fn measure_hot_path_compute_units() {
+    let program_id = Pubkey::new_unique();
+    let user = Keypair::new();
+    let (mint, user_tokens) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let (treasury_vault, _) = derive_treasury_vault_pda(&program_id);
+    let (config_key, config_bump) = get_registry_config_pda(&program_id);
+
+    // `None` loads the program from its SBF build instead of running it natively
+    let mut program_test = ProgramTest::new("solana_mcp", program_id, None);
+    let mut mint_data = vec![0; spl_token::state::Mint::LEN];
+    spl_token::state::Mint {
+        decimals: 9,
+        is_initialized: true,
+        ..Default::default()
+    }
+    .pack_into_slice(&mut mint_data);
+    program_test.add_account(mint, rent_exempt_account(mint_data, spl_token::id()));
+    let config = RegistryConfigV1 {
+        bump: config_bump,
+        admin_authority: user.pubkey(),
+        params: RegistryConfigParams::mcp_defaults(mint, Pubkey::new_unique()),
+        last_update_timestamp: 0,
+    };
+    program_test.add_account(
+        config_key,
+        rent_exempt_account(config.try_to_vec().unwrap(), program_id),
+    );
+    program_test.add_account(
+        user.pubkey(),
+        Account::new(LAMPORTS_PER_SOL, 0, &solana_program::system_program::id()),
+    );
+    program_test.add_account(user_tokens, token_account(mint, user.pubkey(), 1_000_000));
+    program_test.add_account(treasury_vault, token_account(mint, treasury_vault, 0));
+
+    let minimal = McpServerRegistryEntryV1 {
+        owner_authority: Pubkey::new_unique(),
+        server_id: "server".to_string(),
+        status: McpServerStatus::Active as u8,
+        economics: McpServerEconomicsV1 {
+            tool_base_fee: 100,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let maximal = McpServerRegistryEntryV1 {
+        server_capabilities_summary: Some("s".repeat(MAX_SERVER_CAPABILITIES_SUMMARY_LEN)),
+        tags: vec!["t".repeat(MAX_SERVER_TAG_LEN); MAX_SERVER_TAGS],
+        ..minimal.clone()
+    };
+    let entries: Vec<_> = [minimal, maximal]
+        .into_iter()
+        .map(|entry| (Pubkey::new_unique(), entry.try_to_vec().unwrap()))
+        .collect();
+    for (key, data) in &entries {
+        program_test.add_account(*key, rent_exempt_account(data.clone(), program_id));
+        let (fee_vault, _) = derive_server_fee_vault_pda(key, &program_id);
+        program_test.add_account(fee_vault, token_account(mint, fee_vault, 0));
+    }
+    let (mut banks_client, payer, recent_blockhash) = program_test.start().await;
+
+    let mut measured = Vec::new();
+    for (server_key, data) in &entries {
+        let idempotency_key = [1; IDEMPOTENCY_KEY_LEN];
+        let pda = |seeds: &[&[u8]]| Pubkey::find_program_address(seeds, &program_id).0;
+        let record_usage = Instruction::new_with_bytes(
+            program_id,
+            &McpServerRegistryInstruction::RecordUsageAndCollectFee {
+                usage_type: UsageType::Tool,
+                count: 1,
+                item_name: None,
+                max_total_fee: 100,
+                idempotency_key,
+                expected_state_version: None,
+            }
+            .pack(),
+            vec![
+                AccountMeta::new(*server_key, false),
+                AccountMeta::new(user.pubkey(), true),
+                AccountMeta::new(user_tokens, false),
+                AccountMeta::new(derive_server_fee_vault_pda(server_key, &program_id).0, false),
+                AccountMeta::new_readonly(spl_token::id(), false),
+                AccountMeta::new_readonly(config_key, false),
+                AccountMeta::new(treasury_vault, false),
+                AccountMeta::new_readonly(pda(&[PRICING_TABLE_SEED, server_key.as_ref()]), false),
+                AccountMeta::new(
+                    derive_usage_receipt_pda(server_key, &user.pubkey(), &idempotency_key, &program_id).0,
+                    false,
+                ),
+                AccountMeta::new_readonly(solana_program::system_program::id(), false),
+                AccountMeta::new(
+                    pda(&[SUBSCRIPTION_SEED, server_key.as_ref(), user.pubkey().as_ref()]),
+                    false,
+                ),
+                AccountMeta::new(
+                    pda(&[USAGE_RECORD_SEED, server_key.as_ref(), user.pubkey().as_ref()]),
+                    false,
+                ),
+                AccountMeta::new_readonly(derive_revenue_split_pda(server_key, &program_id).0, false),
+            ],
+        );
+
+        let transaction = Transaction::new_signed_with_payer(
+            &[record_usage],
+            Some(&payer.pubkey()),
+            &[&payer, &user],
+            recent_blockhash,
+        );
+        let simulation = banks_client.simulate_transaction(transaction).await.unwrap();
+        assert_eq!(simulation.result, Some(Ok(())));
+        let units = simulation.simulation_details.unwrap().units_consumed;
+        println!("entry of {} bytes: RecordUsageAndCollectFee {} CU", data.len(), units);
+        measured.push(units);
+    }
+    assert_eq!(measured[0], measured[1]);
+}
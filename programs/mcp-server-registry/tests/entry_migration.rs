@@ -0,0 +1,140 @@
+//! Migrating an entry stored in layout version 1 through the program
+
+use aeamcp_common::{error::RegistryError, utils::get_mcp_server_pda_secure, zero_copy::ENTRY_LAYOUT_VERSION};
+use borsh::BorshSerialize;
+use solana_mcp::{
+    McpServerEntryPrefixV1, McpServerRegistryEntryLayoutV1, McpServerRegistryEntryV1,
+    McpServerRegistryInstruction,
+};
+use solana_program::{
+    instruction::{AccountMeta, Instruction, InstructionError},
+    pubkey::Pubkey,
+    rent::Rent,
+};
+use solana_program_test::*;
+use solana_sdk::{
+    account::Account,
+    signature::Signer,
+    transaction::{Transaction, TransactionError},
+};
+
+#[tokio::test]
+async fn test_layout_v1_entry_migrates_in_place() {
+    let program_id = Pubkey::new_unique();
+    let owner = Pubkey::new_unique();
+    let (entry_key, bump) = get_mcp_server_pda_secure("server", &owner, &program_id);
+
+    let legacy = McpServerRegistryEntryLayoutV1 {
+        bump,
+        registry_version: 1,
+        state_version: 3,
+        operation_in_progress: false,
+        owner_authority: owner,
+        server_id: "server".to_string(),
+        name: "Server".to_string(),
+        server_version: "1.0.0".to_string(),
+        service_endpoint: "https://example.com/mcp".to_string(),
+        documentation_url: None,
+        server_capabilities_summary: None,
+        supports_resources: false,
+        supports_tools: true,
+        supports_prompts: false,
+        onchain_tool_definitions: vec![],
+        onchain_resource_definitions: vec![],
+        onchain_prompt_definitions: vec![],
+        status: 1,
+        registration_timestamp: 0,
+        last_update_timestamp: 0,
+        full_capabilities_uri: None,
+        tags: vec![],
+        token_mint: Pubkey::new_unique(),
+        verification_stake: 1_000,
+        staking_timestamp: 0,
+        stake_locked_until: 0,
+        verification_tier: 1,
+        total_tool_calls: 0,
+        total_resource_accesses: 0,
+        total_prompt_uses: 0,
+        total_fees_collected: 0,
+        quality_score: 0,
+        uptime_percentage: 0,
+        avg_response_time: 0,
+        error_rate: 0,
+        tool_base_fee: 25,
+        resource_base_fee: 0,
+        prompt_base_fee: 0,
+        bulk_discount_threshold: 0,
+        bulk_discount_percentage: 0,
+        registration_fee_paid: 0,
+        last_fee_collection: 0,
+        pending_fees: 300,
+        tool_account_count: 0,
+        registration_open: false,
+    };
+    let data = legacy.try_to_vec().unwrap();
+
+    let mut program_test = ProgramTest::new(
+        "solana_mcp",
+        program_id,
+        processor!(solana_mcp::process_instruction),
+    );
+    program_test.add_account(
+        entry_key,
+        Account {
+            lamports: Rent::default().minimum_balance(data.len()),
+            data,
+            owner: program_id,
+            executable: false,
+            rent_epoch: 0,
+        },
+    );
+    let (mut banks_client, payer, recent_blockhash) = program_test.start().await;
+
+    let migrate = Instruction::new_with_bytes(
+        program_id,
+        &McpServerRegistryInstruction::MigrateMcpServerEntry.pack(),
+        vec![
+            AccountMeta::new(entry_key, false),
+            AccountMeta::new(payer.pubkey(), true),
+            AccountMeta::new_readonly(solana_program::system_program::id(), false),
+        ],
+    );
+    let transaction = Transaction::new_signed_with_payer(
+        std::slice::from_ref(&migrate),
+        Some(&payer.pubkey()),
+        &[&payer],
+        recent_blockhash,
+    );
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    // The account grew to the new layout and reads through the prefix
+    let account = banks_client.get_account(entry_key).await.unwrap().unwrap();
+    assert!(Rent::default().is_exempt(account.lamports, account.data.len()));
+    let prefix = McpServerEntryPrefixV1::load(&account.data).unwrap();
+    assert_eq!(prefix.header.registry_version, ENTRY_LAYOUT_VERSION);
+    assert_eq!({ prefix.header.state_version }, 4);
+    assert_eq!({ prefix.economics.pending_fees }, 300);
+    let entry = McpServerRegistryEntryV1::try_from_account_data(&account.data).unwrap();
+    assert_eq!(entry.server_id, "server");
+    assert_eq!(entry.owner_authority, owner);
+
+    // An entry already in the current layout is left alone
+    let transaction = Transaction::new_signed_with_payer(
+        &[migrate],
+        Some(&payer.pubkey()),
+        &[&payer],
+        banks_client.get_new_latest_blockhash(&recent_blockhash).await.unwrap(),
+    );
+    let error = banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap_err()
+        .unwrap();
+    assert_eq!(
+        error,
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(RegistryError::UnsupportedEntryVersion as u32)
+        )
+    );
+}
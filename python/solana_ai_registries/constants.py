@@ -168,7 +168,7 @@ MCP_SERVER_REGISTRY_PROGRAM_ID: Final[str] = (
 
 # Authorized External Programs
 AUTHORIZED_ESCROW_PROGRAM_ID: Final[str] = (
-    "11111111111111111111111111111111"  # Placeholder
+    "Escrow1111111111111111111111111111111111111"  # Placeholder
 )
 AUTHORIZED_DDR_PROGRAM_ID: Final[str] = (
     "11111111111111111111111111111111"  # Placeholder
@@ -3,7 +3,18 @@
 //! This module provides high-level functions for interacting with the Agent Registry,
 //! including registration, updates, and queries for autonomous agents.
 
+use crate::chunking::{fits_in_transaction, split_to_fit};
+use crate::config::{
+    derive_program_data_address, derive_registry_config_pda, RegistryConfigParams, RegistryVaults,
+};
 use crate::errors::{SdkError, SdkResult};
+use crate::fees::FeeSchedule;
+use crate::receipt::{derive_usage_receipt_pda, IdempotencyKey};
+use crate::rent::RentEstimate;
+use crate::revenue::{derive_revenue_split_pda, validate_split, SplitRecipient};
+use crate::zero_copy::{
+    check_entry_layout_version, impl_borsh_for_pod, read_prefix, EntryHeader, ENTRY_HEADER_LEN,
+};
 use borsh::{BorshDeserialize, BorshSerialize};
 use solana_sdk::{
     instruction::{AccountMeta, Instruction},
@@ -83,11 +94,115 @@ pub enum AgentRegistryInstruction {
     },
     UpdateAgentDetails {
         details: AgentUpdateDetailsInput,
+        expected_state_version: Option<u64>,
     },
     UpdateAgentStatus {
         new_status: u8,
+        expected_state_version: Option<u64>,
+    },
+    DeregisterAgent {
+        expected_state_version: Option<u64>,
+    },
+    RegisterAgentWithToken {
+        agent_id: String,
+        name: String,
+        description: String,
+        agent_version: String,
+        provider_name: Option<String>,
+        provider_url: Option<String>,
+        documentation_url: Option<String>,
+        service_endpoints: Vec<ServiceEndpointInput>,
+        capabilities_flags: u64,
+        supported_input_modes: Vec<String>,
+        supported_output_modes: Vec<String>,
+        skills: Vec<AgentSkillInput>,
+        security_info_uri: Option<String>,
+        aea_address: Option<String>,
+        economic_intent_summary: Option<String>,
+        supported_aea_protocols_hash: Option<[u8; HASH_SIZE]>,
+        extended_metadata_uri: Option<String>,
+        tags: Vec<String>,
+    },
+    StakeTokens {
+        amount: u64,
+        lock_period: i64,
+        expected_state_version: Option<u64>,
+    },
+    UnstakeTokens {
+        amount: u64,
+        expected_state_version: Option<u64>,
+    },
+    UpdateServiceFees {
+        base_fee: u64,
+        priority_multiplier: u8,
+        accepts_escrow: bool,
+        expected_state_version: Option<u64>,
+    },
+    RecordServiceCompletion {
+        earnings: u64,
+        rating: u8,
+        response_time: u32,
+        max_total_fee: u64,
+        idempotency_key: IdempotencyKey,
+        expected_state_version: Option<u64>,
+    },
+    RecordDisputeOutcome {
+        won: bool,
+        expected_state_version: Option<u64>,
+    },
+    InitializeSkillAccount {
+        index: u8,
+        skill: AgentSkillInput,
+    },
+    UpdateSkillAccount {
+        index: u8,
+        skill: AgentSkillInput,
+    },
+    CloseSkillAccount {
+        index: u8,
+    },
+    BeginAgentRegistration {
+        agent_id: String,
+        name: String,
+        description: String,
+        agent_version: String,
+        provider_name: Option<String>,
+        provider_url: Option<String>,
+        documentation_url: Option<String>,
+        capabilities_flags: u64,
+        supported_input_modes: Vec<String>,
+        supported_output_modes: Vec<String>,
+        security_info_uri: Option<String>,
+        aea_address: Option<String>,
+        economic_intent_summary: Option<String>,
+        supported_aea_protocols_hash: Option<[u8; HASH_SIZE]>,
+        extended_metadata_uri: Option<String>,
+    },
+    AppendAgentSkills {
+        skills: Vec<AgentSkillInput>,
     },
-    DeregisterAgent,
+    AppendAgentServiceEndpoints {
+        service_endpoints: Vec<ServiceEndpointInput>,
+    },
+    AppendAgentTags {
+        tags: Vec<String>,
+    },
+    FinalizeAgentRegistration,
+    InitializeRegistryConfig {
+        params: RegistryConfigParams,
+    },
+    UpdateRegistryConfig {
+        params: RegistryConfigParams,
+        new_admin: Option<Pubkey>,
+    },
+    InitializeVaults,
+    SetRevenueSplit {
+        recipients: Vec<SplitRecipient>,
+    },
+    WithdrawTreasury {
+        amount: u64,
+    },
+    MigrateAgentEntry,
 }
 
 /// Maximum length constants (from the on-chain program)
@@ -195,6 +310,10 @@ pub const MAX_AGENT_TAG_LEN: usize = 32;
 /// SHA-256 hash size used for content verification.
 pub const HASH_SIZE: usize = 32;
 
+/// Maximum number of overflow skill accounts per agent (64 accounts)
+/// Each holds one skill beyond the `MAX_SKILLS` stored in the entry itself.
+pub const MAX_SKILL_ACCOUNTS: usize = 64;
+
 /// Agent status values
 #[repr(u8)]
 #[derive(Debug, Clone, Copy, PartialEq, Eq, BorshSerialize, BorshDeserialize)]
@@ -258,6 +377,16 @@ impl From<ServiceEndpoint> for ServiceEndpointInput {
     }
 }
 
+impl From<AgentSkillInput> for AgentSkill {
+    fn from(skill: AgentSkillInput) -> Self {
+        Self {
+            skill_id: skill.skill_id,
+            name: skill.name,
+            tags: skill.tags,
+        }
+    }
+}
+
 impl From<AgentSkill> for AgentSkillInput {
     fn from(skill: AgentSkill) -> Self {
         Self {
@@ -325,6 +454,61 @@ pub struct AgentArgs {
     pub tags: Vec<String>,
 }
 
+/// Registration and last-update timestamps
+const AGENT_ENTRY_TIMESTAMPS_LEN: usize = 8 + 8;
+
+/// Trailing overflow skill account count and chunked registration flag
+const AGENT_ENTRY_TRAILER_LEN: usize = 1 + 1;
+
+impl AgentArgs {
+    /// Size in bytes of the entry account the program allocates when these
+    /// args are registered
+    pub fn account_size(&self) -> SdkResult<usize> {
+        self.variable_fields_len()
+            .map(|len| {
+                AgentEntryPrefix::LEN + len + AGENT_ENTRY_TIMESTAMPS_LEN + AGENT_ENTRY_TRAILER_LEN
+            })
+            .map_err(|e| SdkError::SerializationError(format!("Failed to size agent entry: {}", e)))
+    }
+
+    /// Estimate the rent-exempt deposit for registering these args, using the
+    /// default rent parameters
+    pub fn estimate_rent(&self) -> SdkResult<RentEstimate> {
+        Ok(RentEstimate::from_default_rent(self.account_size()?))
+    }
+
+    fn variable_fields_len(&self) -> std::io::Result<usize> {
+        let service_endpoints: Vec<ServiceEndpointInput> = self
+            .service_endpoints
+            .iter()
+            .cloned()
+            .map(Into::into)
+            .collect();
+        let skills: Vec<AgentSkillInput> = self.skills.iter().cloned().map(Into::into).collect();
+
+        let mut buf = Vec::new();
+        self.agent_id.serialize(&mut buf)?;
+        self.name.serialize(&mut buf)?;
+        self.description.serialize(&mut buf)?;
+        self.agent_version.serialize(&mut buf)?;
+        self.provider_name.serialize(&mut buf)?;
+        self.provider_url.serialize(&mut buf)?;
+        self.documentation_url.serialize(&mut buf)?;
+        service_endpoints.serialize(&mut buf)?;
+        self.capabilities_flags.serialize(&mut buf)?;
+        self.supported_input_modes.serialize(&mut buf)?;
+        self.supported_output_modes.serialize(&mut buf)?;
+        skills.serialize(&mut buf)?;
+        self.security_info_uri.serialize(&mut buf)?;
+        self.aea_address.serialize(&mut buf)?;
+        self.economic_intent_summary.serialize(&mut buf)?;
+        self.supported_aea_protocols_hash.serialize(&mut buf)?;
+        self.extended_metadata_uri.serialize(&mut buf)?;
+        self.tags.serialize(&mut buf)?;
+        Ok(buf.len())
+    }
+}
+
 impl From<AgentPatch> for AgentUpdateDetailsInput {
     fn from(patch: AgentPatch) -> Self {
         Self {
@@ -391,13 +575,16 @@ pub struct AgentPatch {
     pub tags: Option<Vec<String>>,
 }
 
-/// Agent registry entry (account data)
+/// Agent registry entry (account data, matches the on-chain layout)
 #[derive(Debug, Clone, BorshSerialize, BorshDeserialize)]
 pub struct AgentEntry {
     pub bump: u8,
     pub registry_version: u8,
     pub state_version: u64,
+    pub operation_in_progress: bool,
     pub owner_authority: Pubkey,
+    pub status: AgentStatus,
+    pub economics: AgentEconomics,
     pub agent_id: String,
     pub name: String,
     pub description: String,
@@ -405,26 +592,172 @@ pub struct AgentEntry {
     pub provider_name: Option<String>,
     pub provider_url: Option<String>,
     pub documentation_url: Option<String>,
-    pub service_endpoints: Vec<ServiceEndpoint>,
+    pub service_endpoints: Vec<ServiceEndpointInput>,
     pub capabilities_flags: u64,
     pub supported_input_modes: Vec<String>,
     pub supported_output_modes: Vec<String>,
-    pub skills: Vec<AgentSkill>,
+    pub skills: Vec<AgentSkillInput>,
     pub security_info_uri: Option<String>,
     pub aea_address: Option<String>,
     pub economic_intent_summary: Option<String>,
     pub supported_aea_protocols_hash: Option<[u8; HASH_SIZE]>,
-    pub extended_metadata_uri: Option<String>,
-    pub tags: Vec<String>,
-    pub status: AgentStatus,
     pub registration_timestamp: i64,
     pub last_update_timestamp: i64,
+    pub extended_metadata_uri: Option<String>,
+    pub tags: Vec<String>,
+    /// Number of overflow skill accounts linked to this entry
+    pub skill_account_count: u8,
+    /// Set while a chunked registration is still accepting appended data
+    pub registration_open: bool,
 }
 
 impl AgentEntry {
+    /// Try to deserialize from account data. Entries in an older layout are
+    /// rejected with [`SdkError::UnsupportedEntryVersion`].
+    pub fn try_from_account_data(data: &[u8]) -> SdkResult<Self> {
+        check_entry_layout_version(data)?;
+        Self::deserialize(&mut &data[..]).map_err(|e| {
+            SdkError::DeserializationError(format!("Failed to deserialize agent entry: {}", e))
+        })
+    }
+}
+
+/// Number of recent service ratings an agent entry keeps
+pub const MAX_QUALITY_RATINGS: usize = 10;
+
+/// Token, fee and reputation fields of an agent entry (matches the on-chain
+/// layout). They sit at a fixed offset, so they can be read without decoding
+/// the rest of the entry.
+#[repr(C, packed)]
+#[derive(Clone, Copy, Debug, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct AgentEconomics {
+    pub token_mint: Pubkey,
+    pub staked_amount: u64,
+    pub staking_timestamp: i64,
+    pub stake_locked_until: i64,
+    pub staking_tier: u8,
+    pub total_earnings: u64,
+    pub active_escrows: u8,
+    pub completed_services: u32,
+    pub dispute_count: u16,
+    pub dispute_wins: u16,
+    pub reputation_score: u64,
+    /// Most recent ratings, oldest first; see [`AgentEconomics::ratings`]
+    pub quality_ratings: [u8; MAX_QUALITY_RATINGS],
+    pub quality_rating_count: u8,
+    pub response_time_avg: u32,
+    pub base_service_fee: u64,
+    pub priority_multiplier: u8,
+    /// Whether the agent accepts escrow (0 or 1)
+    pub accepts_escrow: u8,
+    pub registration_fee_paid: u64,
+    pub last_fee_update: i64,
+    pub total_fees_collected: u64,
+    pub pending_base_service_fee: u64,
+    pub pending_priority_multiplier: u8,
+    /// When the pending fees apply (0: none scheduled)
+    pub fee_change_effective_at: i64,
+}
+
+impl_borsh_for_pod!(AgentEconomics);
+
+/// Fees an agent charges for a service
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AgentServiceFees {
+    pub base_service_fee: u64,
+    /// Fee multiplier for priority (100 = 1x)
+    pub priority_multiplier: u8,
+}
+
+impl AgentServiceFees {
+    /// Fee of one service at the priority multiplier, the least a paying
+    /// user's `max_total_fee` must cover for a completion to be recorded
+    pub fn priority_service_fee(&self) -> u64 {
+        if self.priority_multiplier > 100 {
+            self.base_service_fee * self.priority_multiplier as u64 / 100
+        } else {
+            self.base_service_fee
+        }
+    }
+}
+
+impl AgentEconomics {
+    /// Current and upcoming service fees as of `now`
+    pub fn fee_schedule(&self, now: i64) -> FeeSchedule<AgentServiceFees> {
+        FeeSchedule::resolve(
+            AgentServiceFees {
+                base_service_fee: self.base_service_fee,
+                priority_multiplier: self.priority_multiplier,
+            },
+            AgentServiceFees {
+                base_service_fee: self.pending_base_service_fee,
+                priority_multiplier: self.pending_priority_multiplier,
+            },
+            self.fee_change_effective_at,
+            now,
+        )
+    }
+
+    /// Recorded service ratings, oldest first
+    pub fn ratings(&self) -> &[u8] {
+        let count = (self.quality_rating_count as usize).min(MAX_QUALITY_RATINGS);
+        &self.quality_ratings[..count]
+    }
+
+    /// Whether the agent accepts escrowed payments
+    pub fn accepts_escrow(&self) -> bool {
+        self.accepts_escrow != 0
+    }
+}
+
+/// Fixed-size prefix of an agent entry: the common header followed by the
+/// agent's economics
+#[repr(C, packed)]
+#[derive(Clone, Copy, Debug, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct AgentEntryPrefix {
+    pub header: EntryHeader,
+    pub economics: AgentEconomics,
+}
+
+impl AgentEntryPrefix {
+    /// Length of the prefix in bytes
+    pub const LEN: usize = std::mem::size_of::<Self>();
+
+    /// Offset of the economics fields within the account data
+    pub const ECONOMICS_OFFSET: usize = ENTRY_HEADER_LEN;
+
+    /// Read the prefix from the start of an agent entry's account data. Only
+    /// the first [`Self::LEN`] bytes are needed. Entries in an older layout
+    /// are rejected with [`SdkError::UnsupportedEntryVersion`].
+    pub fn from_account_data(data: &[u8]) -> SdkResult<Self> {
+        let prefix: Self = read_prefix(data)?;
+        prefix.header.check_layout_version()?;
+        Ok(prefix)
+    }
+
+    /// Agent status, if the stored byte is a known value
+    pub fn status(&self) -> Option<AgentStatus> {
+        AgentStatus::from_u8(self.header.status)
+    }
+}
+
+/// Overflow skill account (account data), one skill per account linked to an
+/// agent entry by `[b"skill", agent_entry, index]`
+#[derive(Debug, Clone, BorshSerialize, BorshDeserialize, PartialEq)]
+pub struct AgentSkillAccount {
+    pub bump: u8,
+    pub agent_entry: Pubkey,
+    pub index: u8,
+    pub skill: AgentSkillInput,
+    pub last_update_timestamp: i64,
+}
+
+impl AgentSkillAccount {
     /// Try to deserialize from account data
     pub fn try_from_account_data(data: &[u8]) -> SdkResult<Self> {
-        crate::client::deserialize_account_data(data, "agent entry")
+        Self::deserialize(&mut &data[..]).map_err(|e| {
+            SdkError::DeserializationError(format!("Failed to deserialize skill account: {}", e))
+        })
     }
 }
 
@@ -672,23 +1005,47 @@ impl AgentBuilder {
 pub struct AgentRegistry;
 
 impl AgentRegistry {
-    /// Create a register agent instruction
+    /// Create a register agent instruction, with `payer` funding the entry
     pub fn register(
         program_id: &Pubkey,
         owner: &Pubkey,
+        payer: &Pubkey,
         args: AgentArgs,
     ) -> SdkResult<Instruction> {
-        create_register_agent_instruction(program_id, owner, args)
+        create_register_agent_instruction(program_id, owner, payer, args)
     }
 
-    /// Create an update agent instruction
+    /// Create a register agent instruction that also charges the registry's
+    /// registration fee from `owner_token_account`, with `payer` funding the
+    /// entry
+    pub fn register_with_token(
+        program_id: &Pubkey,
+        owner: &Pubkey,
+        payer: &Pubkey,
+        owner_token_account: &Pubkey,
+        token_mint: &Pubkey,
+        args: AgentArgs,
+    ) -> SdkResult<Instruction> {
+        create_register_agent_with_token_instruction(
+            program_id,
+            owner,
+            payer,
+            owner_token_account,
+            token_mint,
+            args,
+        )
+    }
+
+    /// Create an update agent instruction, with `payer` funding any growth
+    /// of the entry
     pub fn update(
         program_id: &Pubkey,
         owner: &Pubkey,
+        payer: &Pubkey,
         agent_id: &str,
         patch: AgentPatch,
     ) -> SdkResult<Instruction> {
-        create_update_agent_instruction(program_id, owner, agent_id, patch)
+        create_update_agent_instruction(program_id, owner, payer, agent_id, patch, None)
     }
 
     /// Create an update agent status instruction
@@ -698,7 +1055,7 @@ impl AgentRegistry {
         agent_id: &str,
         status: u8,
     ) -> SdkResult<Instruction> {
-        create_update_agent_status_instruction(program_id, owner, agent_id, status)
+        create_update_agent_status_instruction(program_id, owner, agent_id, status, None)
     }
 
     /// Create a deregister agent instruction
@@ -707,7 +1064,7 @@ impl AgentRegistry {
         owner: &Pubkey,
         agent_id: &str,
     ) -> SdkResult<Instruction> {
-        create_deregister_agent_instruction(program_id, owner, agent_id)
+        create_deregister_agent_instruction(program_id, owner, agent_id, None)
     }
 
     /// Derive the PDA for an agent
@@ -738,10 +1095,31 @@ pub fn derive_agent_pda_with_bump(
     Ok((pda, bump))
 }
 
-/// Create register agent instruction
+/// Derive the PDA of an agent's overflow skill account at `index`
+pub fn derive_skill_account_pda(program_id: &Pubkey, agent_entry: &Pubkey, index: u8) -> Pubkey {
+    let (pda, _) =
+        Pubkey::find_program_address(&[b"skill", agent_entry.as_ref(), &[index]], program_id);
+    pda
+}
+
+/// Seed of the PDA an escrow program signs settlements with; see
+/// [`derive_escrow_authority_pda`]
+pub const ESCROW_AUTHORITY_SEED: &[u8] = b"escrow_authority";
+
+/// Derive the authority PDA of an escrow program. The escrow signs
+/// `RecordServiceCompletion` with it through `invoke_signed`, and it owns the
+/// escrow token accounts earnings are settled from.
+pub fn derive_escrow_authority_pda(escrow_program_id: &Pubkey) -> Pubkey {
+    let (pda, _) = Pubkey::find_program_address(&[ESCROW_AUTHORITY_SEED], escrow_program_id);
+    pda
+}
+
+/// Create register agent instruction. `payer` funds the entry's rent and
+/// may be a different signer than the owner.
 pub fn create_register_agent_instruction(
     program_id: &Pubkey,
     owner: &Pubkey,
+    payer: &Pubkey,
     args: AgentArgs,
 ) -> SdkResult<Instruction> {
     let agent_pda = derive_agent_pda(program_id, owner, &args.agent_id)?;
@@ -749,7 +1127,7 @@ pub fn create_register_agent_instruction(
     let accounts = vec![
         AccountMeta::new(agent_pda, false),
         AccountMeta::new_readonly(*owner, true),
-        AccountMeta::new(*owner, true), // payer
+        AccountMeta::new(*payer, true),
         AccountMeta::new_readonly(system_program::id(), false),
     ];
 
@@ -790,22 +1168,93 @@ pub fn create_register_agent_instruction(
     })
 }
 
+/// Create an instruction that registers an agent and charges the registry's
+/// registration fee from `owner_token_account` into the registration vault.
+/// `token_mint` must be the registry's accepted mint; `payer` funds the
+/// entry's rent.
+pub fn create_register_agent_with_token_instruction(
+    program_id: &Pubkey,
+    owner: &Pubkey,
+    payer: &Pubkey,
+    owner_token_account: &Pubkey,
+    token_mint: &Pubkey,
+    args: AgentArgs,
+) -> SdkResult<Instruction> {
+    let agent_pda = derive_agent_pda(program_id, owner, &args.agent_id)?;
+
+    let accounts = vec![
+        AccountMeta::new(agent_pda, false),
+        AccountMeta::new_readonly(*owner, true),
+        AccountMeta::new(*payer, true),
+        AccountMeta::new(*owner_token_account, false),
+        AccountMeta::new(RegistryVaults::derive(program_id).registration_vault, false),
+        AccountMeta::new_readonly(*token_mint, false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+        AccountMeta::new_readonly(system_program::id(), false),
+        AccountMeta::new_readonly(solana_sdk::sysvar::clock::id(), false),
+        AccountMeta::new_readonly(derive_registry_config_pda(program_id), false),
+    ];
+
+    let instruction = AgentRegistryInstruction::RegisterAgentWithToken {
+        agent_id: args.agent_id,
+        name: args.name,
+        description: args.description,
+        agent_version: args.agent_version,
+        provider_name: args.provider_name,
+        provider_url: args.provider_url,
+        documentation_url: args.documentation_url,
+        service_endpoints: args
+            .service_endpoints
+            .into_iter()
+            .map(|e| e.into())
+            .collect(),
+        capabilities_flags: args.capabilities_flags,
+        supported_input_modes: args.supported_input_modes,
+        supported_output_modes: args.supported_output_modes,
+        skills: args.skills.into_iter().map(|s| s.into()).collect(),
+        security_info_uri: args.security_info_uri,
+        aea_address: args.aea_address,
+        economic_intent_summary: args.economic_intent_summary,
+        supported_aea_protocols_hash: args.supported_aea_protocols_hash,
+        extended_metadata_uri: args.extended_metadata_uri,
+        tags: args.tags,
+    };
+
+    let data = instruction.try_to_vec().map_err(|e| {
+        SdkError::SerializationError(format!("Failed to serialize instruction: {}", e))
+    })?;
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
 /// Create update agent instruction
+///
+/// When `expected_state_version` is set the program rejects the update with
+/// `StateVersionMismatch` if the entry changed since it was read.
 pub fn create_update_agent_instruction(
     program_id: &Pubkey,
     owner: &Pubkey,
+    payer: &Pubkey,
     agent_id: &str,
     patch: AgentPatch,
+    expected_state_version: Option<u64>,
 ) -> SdkResult<Instruction> {
     let agent_pda = derive_agent_pda(program_id, owner, agent_id)?;
 
     let accounts = vec![
         AccountMeta::new(agent_pda, false),
         AccountMeta::new_readonly(*owner, true),
+        AccountMeta::new(*payer, true), // payer for account resizing
+        AccountMeta::new_readonly(system_program::id(), false),
     ];
 
     let instruction = AgentRegistryInstruction::UpdateAgentDetails {
         details: patch.into(),
+        expected_state_version,
     };
 
     let data = instruction.try_to_vec().map_err(|e| {
@@ -825,6 +1274,7 @@ pub fn create_update_agent_status_instruction(
     owner: &Pubkey,
     agent_id: &str,
     status: u8,
+    expected_state_version: Option<u64>,
 ) -> SdkResult<Instruction> {
     let agent_pda = derive_agent_pda(program_id, owner, agent_id)?;
 
@@ -833,7 +1283,10 @@ pub fn create_update_agent_status_instruction(
         AccountMeta::new_readonly(*owner, true),
     ];
 
-    let instruction = AgentRegistryInstruction::UpdateAgentStatus { new_status: status };
+    let instruction = AgentRegistryInstruction::UpdateAgentStatus {
+        new_status: status,
+        expected_state_version,
+    };
 
     let data = instruction.try_to_vec().map_err(|e| {
         SdkError::SerializationError(format!("Failed to serialize instruction: {}", e))
@@ -851,6 +1304,7 @@ pub fn create_deregister_agent_instruction(
     program_id: &Pubkey,
     owner: &Pubkey,
     agent_id: &str,
+    expected_state_version: Option<u64>,
 ) -> SdkResult<Instruction> {
     let agent_pda = derive_agent_pda(program_id, owner, agent_id)?;
 
@@ -859,7 +1313,9 @@ pub fn create_deregister_agent_instruction(
         AccountMeta::new_readonly(*owner, true),
     ];
 
-    let instruction = AgentRegistryInstruction::DeregisterAgent;
+    let instruction = AgentRegistryInstruction::DeregisterAgent {
+        expected_state_version,
+    };
 
     let data = instruction.try_to_vec().map_err(|e| {
         SdkError::SerializationError(format!("Failed to serialize instruction: {}", e))
@@ -872,57 +1328,768 @@ pub fn create_deregister_agent_instruction(
     })
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use solana_sdk::signature::Signer;
-    use solana_sdk::signer::keypair::Keypair;
+/// Create an instruction that adds an overflow skill account
+///
+/// `index` must equal the number of skill accounts the agent already has.
+pub fn create_initialize_skill_account_instruction(
+    program_id: &Pubkey,
+    owner: &Pubkey,
+    payer: &Pubkey,
+    agent_id: &str,
+    index: u8,
+    skill: AgentSkill,
+) -> SdkResult<Instruction> {
+    let agent_pda = derive_agent_pda(program_id, owner, agent_id)?;
 
-    #[test]
-    fn test_agent_builder() {
-        let agent = AgentBuilder::new("test-agent", "Test Agent")
-            .description("A test agent")
-            .version("1.0.0")
-            .build()
-            .unwrap();
+    let accounts = vec![
+        AccountMeta::new(
+            derive_skill_account_pda(program_id, &agent_pda, index),
+            false,
+        ),
+        AccountMeta::new(agent_pda, false),
+        AccountMeta::new_readonly(*owner, true),
+        AccountMeta::new(*payer, true),
+        AccountMeta::new_readonly(system_program::id(), false),
+    ];
 
-        assert_eq!(agent.agent_id, "test-agent");
-        assert_eq!(agent.name, "Test Agent");
-        assert_eq!(agent.description, "A test agent");
-        assert_eq!(agent.agent_version, "1.0.0");
-    }
+    let instruction = AgentRegistryInstruction::InitializeSkillAccount {
+        index,
+        skill: skill.into(),
+    };
 
-    #[test]
-    fn test_agent_builder_with_endpoints() {
-        let agent = AgentBuilder::new("test-agent", "Test Agent")
-            .add_service_endpoint("http", "http://localhost:8080", true)
-            .unwrap()
-            .build()
-            .unwrap();
+    let data = instruction.try_to_vec().map_err(|e| {
+        SdkError::SerializationError(format!("Failed to serialize instruction: {}", e))
+    })?;
 
-        assert_eq!(agent.service_endpoints.len(), 1);
-        assert_eq!(agent.service_endpoints[0].protocol, "http");
-        assert_eq!(agent.service_endpoints[0].url, "http://localhost:8080");
-        assert!(agent.service_endpoints[0].is_default);
-    }
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
 
-    #[test]
-    fn test_agent_builder_validation() {
-        // Test empty agent ID
-        let result = AgentBuilder::new("", "Test Agent").build();
-        assert!(matches!(result, Err(SdkError::InvalidAgentIdLength)));
+/// Create an instruction that replaces the skill held by an overflow skill account
+pub fn create_update_skill_account_instruction(
+    program_id: &Pubkey,
+    owner: &Pubkey,
+    payer: &Pubkey,
+    agent_id: &str,
+    index: u8,
+    skill: AgentSkill,
+) -> SdkResult<Instruction> {
+    let agent_pda = derive_agent_pda(program_id, owner, agent_id)?;
 
-        // Test empty name
-        let result = AgentBuilder::new("test-agent", "").build();
-        assert!(matches!(result, Err(SdkError::InvalidNameLength)));
+    let accounts = vec![
+        AccountMeta::new(
+            derive_skill_account_pda(program_id, &agent_pda, index),
+            false,
+        ),
+        AccountMeta::new_readonly(agent_pda, false),
+        AccountMeta::new_readonly(*owner, true),
+        AccountMeta::new(*payer, true), // payer for account resizing
+        AccountMeta::new_readonly(system_program::id(), false),
+    ];
 
-        // Test too long agent ID
-        let long_id = "a".repeat(MAX_AGENT_ID_LEN + 1);
-        let result = AgentBuilder::new(long_id, "Test Agent").build();
-        assert!(matches!(result, Err(SdkError::InvalidAgentIdLength)));
-    }
+    let instruction = AgentRegistryInstruction::UpdateSkillAccount {
+        index,
+        skill: skill.into(),
+    };
 
-    #[test]
+    let data = instruction.try_to_vec().map_err(|e| {
+        SdkError::SerializationError(format!("Failed to serialize instruction: {}", e))
+    })?;
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Create an instruction that closes the agent's last overflow skill account,
+/// refunding its rent to the owner
+pub fn create_close_skill_account_instruction(
+    program_id: &Pubkey,
+    owner: &Pubkey,
+    agent_id: &str,
+    index: u8,
+) -> SdkResult<Instruction> {
+    let agent_pda = derive_agent_pda(program_id, owner, agent_id)?;
+
+    let accounts = vec![
+        AccountMeta::new(
+            derive_skill_account_pda(program_id, &agent_pda, index),
+            false,
+        ),
+        AccountMeta::new(agent_pda, false),
+        AccountMeta::new_readonly(*owner, true),
+        AccountMeta::new(*owner, false), // rent recipient
+    ];
+
+    let instruction = AgentRegistryInstruction::CloseSkillAccount { index };
+
+    let data = instruction.try_to_vec().map_err(|e| {
+        SdkError::SerializationError(format!("Failed to serialize instruction: {}", e))
+    })?;
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Create the instruction that starts a chunked registration with the core
+/// fields of `args`; skills, service endpoints and tags are not included
+pub fn create_begin_agent_registration_instruction(
+    program_id: &Pubkey,
+    owner: &Pubkey,
+    payer: &Pubkey,
+    args: &AgentArgs,
+) -> SdkResult<Instruction> {
+    let agent_pda = derive_agent_pda(program_id, owner, &args.agent_id)?;
+
+    let accounts = vec![
+        AccountMeta::new(agent_pda, false),
+        AccountMeta::new_readonly(*owner, true),
+        AccountMeta::new(*payer, true),
+        AccountMeta::new_readonly(system_program::id(), false),
+    ];
+
+    let instruction = AgentRegistryInstruction::BeginAgentRegistration {
+        agent_id: args.agent_id.clone(),
+        name: args.name.clone(),
+        description: args.description.clone(),
+        agent_version: args.agent_version.clone(),
+        provider_name: args.provider_name.clone(),
+        provider_url: args.provider_url.clone(),
+        documentation_url: args.documentation_url.clone(),
+        capabilities_flags: args.capabilities_flags,
+        supported_input_modes: args.supported_input_modes.clone(),
+        supported_output_modes: args.supported_output_modes.clone(),
+        security_info_uri: args.security_info_uri.clone(),
+        aea_address: args.aea_address.clone(),
+        economic_intent_summary: args.economic_intent_summary.clone(),
+        supported_aea_protocols_hash: args.supported_aea_protocols_hash,
+        extended_metadata_uri: args.extended_metadata_uri.clone(),
+    };
+
+    registration_chunk_instruction(program_id, accounts, instruction)
+}
+
+/// Create an instruction that appends skills to an open registration
+pub fn create_append_agent_skills_instruction(
+    program_id: &Pubkey,
+    owner: &Pubkey,
+    payer: &Pubkey,
+    agent_id: &str,
+    skills: Vec<AgentSkill>,
+) -> SdkResult<Instruction> {
+    let instruction = AgentRegistryInstruction::AppendAgentSkills {
+        skills: skills.into_iter().map(Into::into).collect(),
+    };
+    registration_chunk_instruction(
+        program_id,
+        append_chunk_accounts(program_id, owner, payer, agent_id)?,
+        instruction,
+    )
+}
+
+/// Create an instruction that appends service endpoints to an open registration
+pub fn create_append_agent_service_endpoints_instruction(
+    program_id: &Pubkey,
+    owner: &Pubkey,
+    payer: &Pubkey,
+    agent_id: &str,
+    service_endpoints: Vec<ServiceEndpoint>,
+) -> SdkResult<Instruction> {
+    let instruction = AgentRegistryInstruction::AppendAgentServiceEndpoints {
+        service_endpoints: service_endpoints.into_iter().map(Into::into).collect(),
+    };
+    registration_chunk_instruction(
+        program_id,
+        append_chunk_accounts(program_id, owner, payer, agent_id)?,
+        instruction,
+    )
+}
+
+/// Create an instruction that appends tags to an open registration
+pub fn create_append_agent_tags_instruction(
+    program_id: &Pubkey,
+    owner: &Pubkey,
+    payer: &Pubkey,
+    agent_id: &str,
+    tags: Vec<String>,
+) -> SdkResult<Instruction> {
+    let instruction = AgentRegistryInstruction::AppendAgentTags { tags };
+    registration_chunk_instruction(
+        program_id,
+        append_chunk_accounts(program_id, owner, payer, agent_id)?,
+        instruction,
+    )
+}
+
+/// Create the instruction that validates and activates a chunked registration
+pub fn create_finalize_agent_registration_instruction(
+    program_id: &Pubkey,
+    owner: &Pubkey,
+    agent_id: &str,
+) -> SdkResult<Instruction> {
+    let agent_pda = derive_agent_pda(program_id, owner, agent_id)?;
+
+    let accounts = vec![
+        AccountMeta::new(agent_pda, false),
+        AccountMeta::new_readonly(*owner, true),
+    ];
+
+    registration_chunk_instruction(
+        program_id,
+        accounts,
+        AgentRegistryInstruction::FinalizeAgentRegistration,
+    )
+}
+
+/// Create an instruction that creates the registry config. `authority` must
+/// be the program's upgrade authority; it becomes the config's admin.
+pub fn create_initialize_registry_config_instruction(
+    program_id: &Pubkey,
+    authority: &Pubkey,
+    payer: &Pubkey,
+    params: RegistryConfigParams,
+) -> SdkResult<Instruction> {
+    let accounts = vec![
+        AccountMeta::new(derive_registry_config_pda(program_id), false),
+        AccountMeta::new_readonly(*authority, true),
+        AccountMeta::new(*payer, true),
+        AccountMeta::new_readonly(derive_program_data_address(program_id), false),
+        AccountMeta::new_readonly(system_program::id(), false),
+    ];
+
+    let instruction = AgentRegistryInstruction::InitializeRegistryConfig { params };
+
+    let data = instruction.try_to_vec().map_err(|e| {
+        SdkError::SerializationError(format!("Failed to serialize instruction: {}", e))
+    })?;
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Create an instruction that replaces the registry config parameters and,
+/// if `new_admin` is set, hands the config to a new admin
+pub fn create_update_registry_config_instruction(
+    program_id: &Pubkey,
+    admin: &Pubkey,
+    params: RegistryConfigParams,
+    new_admin: Option<Pubkey>,
+) -> SdkResult<Instruction> {
+    let accounts = vec![
+        AccountMeta::new(derive_registry_config_pda(program_id), false),
+        AccountMeta::new_readonly(*admin, true),
+        AccountMeta::new_readonly(RegistryVaults::derive(program_id).staking_vault, false),
+    ];
+
+    let instruction = AgentRegistryInstruction::UpdateRegistryConfig { params, new_admin };
+
+    let data = instruction.try_to_vec().map_err(|e| {
+        SdkError::SerializationError(format!("Failed to serialize instruction: {}", e))
+    })?;
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Create an instruction that creates the registry's staking, fee,
+/// registration and treasury vaults for `mint`, which must be the config's
+/// accepted mint
+pub fn create_initialize_vaults_instruction(
+    program_id: &Pubkey,
+    admin: &Pubkey,
+    payer: &Pubkey,
+    mint: &Pubkey,
+) -> SdkResult<Instruction> {
+    let vaults = RegistryVaults::derive(program_id);
+    let accounts = vec![
+        AccountMeta::new_readonly(derive_registry_config_pda(program_id), false),
+        AccountMeta::new_readonly(*admin, true),
+        AccountMeta::new(*payer, true),
+        AccountMeta::new_readonly(*mint, false),
+        AccountMeta::new(vaults.staking_vault, false),
+        AccountMeta::new(vaults.fee_vault, false),
+        AccountMeta::new(vaults.registration_vault, false),
+        AccountMeta::new(vaults.treasury_vault, false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+        AccountMeta::new_readonly(system_program::id(), false),
+    ];
+
+    let data = AgentRegistryInstruction::InitializeVaults
+        .try_to_vec()
+        .map_err(|e| {
+            SdkError::SerializationError(format!("Failed to serialize instruction: {}", e))
+        })?;
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Create an instruction that sets the revenue split of an entry, creating
+/// the split account on first use. An empty `recipients` pays everything to
+/// the owner.
+pub fn create_set_revenue_split_instruction(
+    program_id: &Pubkey,
+    owner: &Pubkey,
+    payer: &Pubkey,
+    agent_id: &str,
+    recipients: Vec<SplitRecipient>,
+) -> SdkResult<Instruction> {
+    validate_split(&recipients)?;
+    let entry_pda = derive_agent_pda(program_id, owner, agent_id)?;
+    let accounts = vec![
+        AccountMeta::new(derive_revenue_split_pda(program_id, &entry_pda), false),
+        AccountMeta::new_readonly(entry_pda, false),
+        AccountMeta::new_readonly(*owner, true),
+        AccountMeta::new(*payer, true),
+        AccountMeta::new_readonly(system_program::id(), false),
+    ];
+
+    let data = AgentRegistryInstruction::SetRevenueSplit { recipients }
+        .try_to_vec()
+        .map_err(|e| {
+            SdkError::SerializationError(format!("Failed to serialize instruction: {}", e))
+        })?;
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Create an instruction that moves `amount` of protocol revenue from the
+/// treasury vault to `destination`, a token account of the config's treasury
+/// wallet
+pub fn create_withdraw_treasury_instruction(
+    program_id: &Pubkey,
+    admin: &Pubkey,
+    destination: &Pubkey,
+    amount: u64,
+) -> SdkResult<Instruction> {
+    let accounts = vec![
+        AccountMeta::new_readonly(derive_registry_config_pda(program_id), false),
+        AccountMeta::new_readonly(*admin, true),
+        AccountMeta::new(RegistryVaults::derive(program_id).treasury_vault, false),
+        AccountMeta::new(*destination, false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+    ];
+
+    let data = AgentRegistryInstruction::WithdrawTreasury { amount }
+        .try_to_vec()
+        .map_err(|e| {
+            SdkError::SerializationError(format!("Failed to serialize instruction: {}", e))
+        })?;
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Create an instruction that rewrites an agent entry stored in layout
+/// version 1 into the current layout. Anyone may send it; `payer` covers any
+/// extra rent.
+pub fn create_migrate_agent_entry_instruction(
+    program_id: &Pubkey,
+    payer: &Pubkey,
+    owner: &Pubkey,
+    agent_id: &str,
+) -> SdkResult<Instruction> {
+    let accounts = vec![
+        AccountMeta::new(derive_agent_pda(program_id, owner, agent_id)?, false),
+        AccountMeta::new(*payer, true),
+        AccountMeta::new_readonly(system_program::id(), false),
+    ];
+
+    let data = AgentRegistryInstruction::MigrateAgentEntry
+        .try_to_vec()
+        .map_err(|e| {
+            SdkError::SerializationError(format!("Failed to serialize instruction: {}", e))
+        })?;
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Create an instruction that records a completed service of `earnings` for
+/// an agent, keyed by `idempotency_key` so a retry is not recorded twice.
+/// `payer` is the paying user and signs off on `max_total_fee`; the program
+/// rejects the completion if the agent's service fee in force
+/// ([`AgentServiceFees::priority_service_fee`]) or `earnings` is above it.
+/// The escrow program issues it through CPI, signing with its
+/// [`derive_escrow_authority_pda`]. To also settle the earnings, append the
+/// escrow token account owned by that PDA, the owner's token account, the
+/// treasury vault, the config, the token program, the revenue split PDA and
+/// any split recipients.
+#[allow(clippy::too_many_arguments)]
+pub fn create_record_service_completion_instruction(
+    program_id: &Pubkey,
+    escrow_program: &Pubkey,
+    payer: &Pubkey,
+    agent_owner: &Pubkey,
+    agent_id: &str,
+    earnings: u64,
+    rating: u8,
+    response_time: u32,
+    max_total_fee: u64,
+    idempotency_key: IdempotencyKey,
+) -> SdkResult<Instruction> {
+    let agent_pda = derive_agent_pda(program_id, agent_owner, agent_id)?;
+    let accounts = vec![
+        AccountMeta::new_readonly(*escrow_program, false),
+        AccountMeta::new(agent_pda, false),
+        AccountMeta::new_readonly(solana_sdk::sysvar::clock::id(), false),
+        AccountMeta::new(*payer, true),
+        AccountMeta::new(
+            derive_usage_receipt_pda(program_id, &agent_pda, escrow_program, &idempotency_key),
+            false,
+        ),
+        AccountMeta::new_readonly(system_program::id(), false),
+        AccountMeta::new_readonly(derive_escrow_authority_pda(escrow_program), true),
+    ];
+
+    let data = AgentRegistryInstruction::RecordServiceCompletion {
+        earnings,
+        rating,
+        response_time,
+        max_total_fee,
+        idempotency_key,
+        expected_state_version: None,
+    }
+    .try_to_vec()
+    .map_err(|e| SdkError::SerializationError(format!("Failed to serialize instruction: {}", e)))?;
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Create an instruction that moves `amount` from the owner's token account
+/// into the registry's staking vault, locked for `lock_period` seconds
+pub fn create_stake_tokens_instruction(
+    program_id: &Pubkey,
+    owner: &Pubkey,
+    owner_token_account: &Pubkey,
+    agent_id: &str,
+    amount: u64,
+    lock_period: i64,
+    expected_state_version: Option<u64>,
+) -> SdkResult<Instruction> {
+    let accounts = vec![
+        AccountMeta::new_readonly(*owner, true),
+        AccountMeta::new(derive_agent_pda(program_id, owner, agent_id)?, false),
+        AccountMeta::new(*owner_token_account, false),
+        AccountMeta::new(RegistryVaults::derive(program_id).staking_vault, false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+        AccountMeta::new_readonly(solana_sdk::sysvar::clock::id(), false),
+        AccountMeta::new_readonly(derive_registry_config_pda(program_id), false),
+    ];
+
+    let data = AgentRegistryInstruction::StakeTokens {
+        amount,
+        lock_period,
+        expected_state_version,
+    }
+    .try_to_vec()
+    .map_err(|e| SdkError::SerializationError(format!("Failed to serialize instruction: {}", e)))?;
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Create an instruction that returns `amount` of unlocked stake from the
+/// registry's staking vault to the owner's token account
+pub fn create_unstake_tokens_instruction(
+    program_id: &Pubkey,
+    owner: &Pubkey,
+    owner_token_account: &Pubkey,
+    agent_id: &str,
+    amount: u64,
+    expected_state_version: Option<u64>,
+) -> SdkResult<Instruction> {
+    let accounts = vec![
+        AccountMeta::new_readonly(*owner, true),
+        AccountMeta::new(derive_agent_pda(program_id, owner, agent_id)?, false),
+        AccountMeta::new(RegistryVaults::derive(program_id).staking_vault, false),
+        AccountMeta::new(*owner_token_account, false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+        AccountMeta::new_readonly(solana_sdk::sysvar::clock::id(), false),
+        AccountMeta::new_readonly(derive_registry_config_pda(program_id), false),
+    ];
+
+    let data = AgentRegistryInstruction::UnstakeTokens {
+        amount,
+        expected_state_version,
+    }
+    .try_to_vec()
+    .map_err(|e| SdkError::SerializationError(format!("Failed to serialize instruction: {}", e)))?;
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Break a registration into instructions that each fit in a transaction
+/// paid for by `payer`: begin, optional-field updates if the core fields
+/// alone are too large, skill, endpoint and tag appends, then finalize
+pub fn create_chunked_register_agent_instructions(
+    program_id: &Pubkey,
+    owner: &Pubkey,
+    payer: &Pubkey,
+    args: AgentArgs,
+) -> SdkResult<Vec<Instruction>> {
+    let mut instructions = Vec::new();
+
+    let begin = create_begin_agent_registration_instruction(program_id, owner, payer, &args)?;
+    if fits_in_transaction(payer, std::slice::from_ref(&begin)) {
+        instructions.push(begin);
+    } else {
+        // Start with the required fields and set the optional ones through
+        // detail updates while the registration is still open
+        let required = AgentArgs {
+            provider_name: None,
+            provider_url: None,
+            documentation_url: None,
+            supported_input_modes: Vec::new(),
+            supported_output_modes: Vec::new(),
+            security_info_uri: None,
+            aea_address: None,
+            economic_intent_summary: None,
+            supported_aea_protocols_hash: None,
+            extended_metadata_uri: None,
+            ..args.clone()
+        };
+        instructions.push(create_begin_agent_registration_instruction(
+            program_id, owner, payer, &required,
+        )?);
+        instructions.extend(split_to_fit(
+            payer,
+            &optional_field_setters(&args),
+            |setters| {
+                let mut patch = AgentPatch::default();
+                for set in setters {
+                    set(&mut patch);
+                }
+                create_update_agent_instruction(
+                    program_id,
+                    owner,
+                    payer,
+                    &args.agent_id,
+                    patch,
+                    None,
+                )
+            },
+        )?);
+    }
+
+    instructions.extend(split_to_fit(payer, &args.skills, |skills| {
+        create_append_agent_skills_instruction(
+            program_id,
+            owner,
+            payer,
+            &args.agent_id,
+            skills.to_vec(),
+        )
+    })?);
+    instructions.extend(split_to_fit(payer, &args.service_endpoints, |endpoints| {
+        create_append_agent_service_endpoints_instruction(
+            program_id,
+            owner,
+            payer,
+            &args.agent_id,
+            endpoints.to_vec(),
+        )
+    })?);
+    instructions.extend(split_to_fit(payer, &args.tags, |tags| {
+        create_append_agent_tags_instruction(
+            program_id,
+            owner,
+            payer,
+            &args.agent_id,
+            tags.to_vec(),
+        )
+    })?);
+    instructions.push(create_finalize_agent_registration_instruction(
+        program_id,
+        owner,
+        &args.agent_id,
+    )?);
+
+    Ok(instructions)
+}
+
+/// Sets one optional field of an [`AgentPatch`]
+type PatchSetter<'a> = Box<dyn Fn(&mut AgentPatch) + 'a>;
+
+/// One setter per optional field present in `args`
+fn optional_field_setters(args: &AgentArgs) -> Vec<PatchSetter<'_>> {
+    let mut setters: Vec<PatchSetter<'_>> = Vec::new();
+    if let Some(value) = &args.provider_name {
+        setters.push(Box::new(move |patch| {
+            patch.provider_name = Some(value.clone())
+        }));
+    }
+    if let Some(value) = &args.provider_url {
+        setters.push(Box::new(move |patch| {
+            patch.provider_url = Some(value.clone())
+        }));
+    }
+    if let Some(value) = &args.documentation_url {
+        setters.push(Box::new(move |patch| {
+            patch.documentation_url = Some(value.clone())
+        }));
+    }
+    if !args.supported_input_modes.is_empty() {
+        let modes = &args.supported_input_modes;
+        setters.push(Box::new(move |patch| {
+            patch.supported_input_modes = Some(modes.clone())
+        }));
+    }
+    if !args.supported_output_modes.is_empty() {
+        let modes = &args.supported_output_modes;
+        setters.push(Box::new(move |patch| {
+            patch.supported_output_modes = Some(modes.clone())
+        }));
+    }
+    if let Some(value) = &args.security_info_uri {
+        setters.push(Box::new(move |patch| {
+            patch.security_info_uri = Some(value.clone())
+        }));
+    }
+    if let Some(value) = &args.aea_address {
+        setters.push(Box::new(move |patch| {
+            patch.aea_address = Some(value.clone())
+        }));
+    }
+    if let Some(value) = &args.economic_intent_summary {
+        setters.push(Box::new(move |patch| {
+            patch.economic_intent_summary = Some(value.clone())
+        }));
+    }
+    if let Some(hash) = args.supported_aea_protocols_hash {
+        setters.push(Box::new(move |patch| {
+            patch.supported_aea_protocols_hash = Some(hash)
+        }));
+    }
+    if let Some(value) = &args.extended_metadata_uri {
+        setters.push(Box::new(move |patch| {
+            patch.extended_metadata_uri = Some(value.clone())
+        }));
+    }
+    setters
+}
+
+/// Accounts shared by the registration append instructions
+fn append_chunk_accounts(
+    program_id: &Pubkey,
+    owner: &Pubkey,
+    payer: &Pubkey,
+    agent_id: &str,
+) -> SdkResult<Vec<AccountMeta>> {
+    let agent_pda = derive_agent_pda(program_id, owner, agent_id)?;
+    Ok(vec![
+        AccountMeta::new(agent_pda, false),
+        AccountMeta::new_readonly(*owner, true),
+        AccountMeta::new(*payer, true), // payer for account resizing
+        AccountMeta::new_readonly(system_program::id(), false),
+    ])
+}
+
+fn registration_chunk_instruction(
+    program_id: &Pubkey,
+    accounts: Vec<AccountMeta>,
+    instruction: AgentRegistryInstruction,
+) -> SdkResult<Instruction> {
+    let data = instruction.try_to_vec().map_err(|e| {
+        SdkError::SerializationError(format!("Failed to serialize instruction: {}", e))
+    })?;
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_sdk::signature::Signer;
+    use solana_sdk::signer::keypair::Keypair;
+
+    #[test]
+    fn test_agent_builder() {
+        let agent = AgentBuilder::new("test-agent", "Test Agent")
+            .description("A test agent")
+            .version("1.0.0")
+            .build()
+            .unwrap();
+
+        assert_eq!(agent.agent_id, "test-agent");
+        assert_eq!(agent.name, "Test Agent");
+        assert_eq!(agent.description, "A test agent");
+        assert_eq!(agent.agent_version, "1.0.0");
+    }
+
+    #[test]
+    fn test_agent_builder_with_endpoints() {
+        let agent = AgentBuilder::new("test-agent", "Test Agent")
+            .add_service_endpoint("http", "http://localhost:8080", true)
+            .unwrap()
+            .build()
+            .unwrap();
+
+        assert_eq!(agent.service_endpoints.len(), 1);
+        assert_eq!(agent.service_endpoints[0].protocol, "http");
+        assert_eq!(agent.service_endpoints[0].url, "http://localhost:8080");
+        assert!(agent.service_endpoints[0].is_default);
+    }
+
+    #[test]
+    fn test_agent_builder_validation() {
+        // Test empty agent ID
+        let result = AgentBuilder::new("", "Test Agent").build();
+        assert!(matches!(result, Err(SdkError::InvalidAgentIdLength)));
+
+        // Test empty name
+        let result = AgentBuilder::new("test-agent", "").build();
+        assert!(matches!(result, Err(SdkError::InvalidNameLength)));
+
+        // Test too long agent ID
+        let long_id = "a".repeat(MAX_AGENT_ID_LEN + 1);
+        let result = AgentBuilder::new(long_id, "Test Agent").build();
+        assert!(matches!(result, Err(SdkError::InvalidAgentIdLength)));
+    }
+
+    #[test]
     fn test_derive_agent_pda() {
         let program_id = Pubkey::new_unique();
         let owner = Keypair::new().pubkey();
@@ -936,6 +2103,206 @@ mod tests {
         assert_eq!(pda, pda2);
     }
 
+    #[test]
+    fn test_status_instruction_carries_expected_version() {
+        let program_id = Pubkey::new_unique();
+        let owner = Keypair::new().pubkey();
+
+        let ix =
+            create_update_agent_status_instruction(&program_id, &owner, "test-agent", 1, Some(9))
+                .unwrap();
+        // variant, new_status, Some tag, version
+        assert_eq!(ix.data[0], 2);
+        assert_eq!(ix.data[1], 1);
+        assert_eq!(ix.data[2], 1);
+        assert_eq!(&ix.data[3..], &9u64.to_le_bytes());
+
+        let ix =
+            create_deregister_agent_instruction(&program_id, &owner, "test-agent", None).unwrap();
+        assert_eq!(ix.data, vec![3, 0]);
+    }
+
+    #[test]
+    fn test_skill_account_instructions() {
+        let program_id = Pubkey::new_unique();
+        let owner = Keypair::new().pubkey();
+        let agent_pda = derive_agent_pda(&program_id, &owner, "test-agent").unwrap();
+        let skill = AgentSkill::new("ocr".to_string(), "OCR".to_string(), vec![]).unwrap();
+
+        let ix = create_initialize_skill_account_instruction(
+            &program_id,
+            &owner,
+            &owner,
+            "test-agent",
+            2,
+            skill,
+        )
+        .unwrap();
+        // Variant index must line up with the on-chain instruction
+        assert_eq!(ix.data[0], 10);
+        assert_eq!(ix.data[1], 2);
+        assert_eq!(
+            ix.accounts[0].pubkey,
+            derive_skill_account_pda(&program_id, &agent_pda, 2)
+        );
+        assert_eq!(ix.accounts[1].pubkey, agent_pda);
+
+        let ix =
+            create_close_skill_account_instruction(&program_id, &owner, "test-agent", 2).unwrap();
+        assert_eq!(ix.data, vec![12, 2]);
+    }
+
+    #[test]
+    fn test_registry_config_instructions() {
+        let program_id = Pubkey::new_unique();
+        let admin = Keypair::new().pubkey();
+        let params = RegistryConfigParams {
+            accepted_mint: Pubkey::new_unique(),
+            treasury: Pubkey::new_unique(),
+            registration_fee: 100,
+            tier_thresholds: [10, 20, 30, 40],
+            tier_lock_periods: [1, 2, 3, 4],
+            min_lock_period: 1,
+            max_lock_period: 10,
+            min_service_fee: 1,
+            min_tool_fee: 1,
+            min_resource_fee: 1,
+            min_prompt_fee: 1,
+            max_bulk_discount: 50,
+            min_priority_multiplier: 100,
+            max_priority_multiplier: 300,
+            protocol_fee_bps: 0,
+            fee_increase_notice: 0,
+        };
+
+        let ix = create_initialize_registry_config_instruction(&program_id, &admin, &admin, params)
+            .unwrap();
+        assert_eq!(ix.data[0], 18);
+        assert_eq!(
+            ix.accounts[0].pubkey,
+            derive_registry_config_pda(&program_id)
+        );
+        assert_eq!(
+            ix.accounts[3].pubkey,
+            derive_program_data_address(&program_id)
+        );
+
+        let ix =
+            create_update_registry_config_instruction(&program_id, &admin, params, None).unwrap();
+        assert_eq!(ix.data[0], 19);
+        assert_eq!(
+            AgentRegistryInstruction::try_from_slice(&ix.data).unwrap(),
+            AgentRegistryInstruction::UpdateRegistryConfig {
+                params,
+                new_admin: None
+            }
+        );
+
+        let mint = Pubkey::new_unique();
+        let ix = create_initialize_vaults_instruction(&program_id, &admin, &admin, &mint).unwrap();
+        assert_eq!(ix.data, vec![20]);
+        assert_eq!(
+            ix.accounts[4].pubkey,
+            RegistryVaults::derive(&program_id).staking_vault
+        );
+        assert_eq!(ix.accounts[8].pubkey, spl_token::id());
+    }
+
+    #[test]
+    fn test_revenue_instructions() {
+        let program_id = Pubkey::new_unique();
+        let owner = Keypair::new().pubkey();
+        let agent_pda = derive_agent_pda(&program_id, &owner, "test-agent").unwrap();
+        let recipients = vec![SplitRecipient {
+            recipient: Pubkey::new_unique(),
+            bps: 2_000,
+        }];
+
+        let ix = create_set_revenue_split_instruction(
+            &program_id,
+            &owner,
+            &owner,
+            "test-agent",
+            recipients.clone(),
+        )
+        .unwrap();
+        assert_eq!(ix.data[0], 21);
+        assert_eq!(
+            ix.accounts[0].pubkey,
+            derive_revenue_split_pda(&program_id, &agent_pda)
+        );
+        assert_eq!(ix.accounts[1].pubkey, agent_pda);
+
+        let too_much = vec![SplitRecipient {
+            recipient: Pubkey::new_unique(),
+            bps: 10_001,
+        }];
+        assert!(create_set_revenue_split_instruction(
+            &program_id,
+            &owner,
+            &owner,
+            "test-agent",
+            too_much
+        )
+        .is_err());
+
+        let ix = create_withdraw_treasury_instruction(&program_id, &owner, &owner, 5).unwrap();
+        assert_eq!(ix.data, vec![22, 5, 0, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(
+            ix.accounts[2].pubkey,
+            RegistryVaults::derive(&program_id).treasury_vault
+        );
+
+        let payer = Pubkey::new_unique();
+        let ix = create_migrate_agent_entry_instruction(&program_id, &payer, &owner, "test-agent")
+            .unwrap();
+        assert_eq!(ix.data, vec![23]);
+        assert_eq!(ix.accounts[0].pubkey, agent_pda);
+        assert!(ix.accounts[1].is_signer && ix.accounts[1].pubkey == payer);
+    }
+
+    #[test]
+    fn test_record_service_completion_instruction() {
+        let program_id = Pubkey::new_unique();
+        let owner = Keypair::new().pubkey();
+        let escrow = Pubkey::new_unique();
+        let agent_pda = derive_agent_pda(&program_id, &owner, "test-agent").unwrap();
+
+        let ix = create_record_service_completion_instruction(
+            &program_id,
+            &escrow,
+            &owner,
+            &owner,
+            "test-agent",
+            250,
+            5,
+            100,
+            300,
+            [4; 16],
+        )
+        .unwrap();
+        assert_eq!(ix.data[0], 8);
+        assert_eq!(
+            ix.accounts[4].pubkey,
+            derive_usage_receipt_pda(&program_id, &agent_pda, &escrow, &[4; 16])
+        );
+        assert!(!ix.accounts[0].is_signer);
+        assert_eq!(ix.accounts[6].pubkey, derive_escrow_authority_pda(&escrow));
+        assert!(ix.accounts[6].is_signer);
+        match AgentRegistryInstruction::try_from_slice(&ix.data).unwrap() {
+            AgentRegistryInstruction::RecordServiceCompletion { max_total_fee, .. } => {
+                assert_eq!(max_total_fee, 300)
+            }
+            _ => panic!("Wrong instruction type"),
+        }
+
+        let fees = AgentServiceFees {
+            base_service_fee: 200,
+            priority_multiplier: 150,
+        };
+        assert_eq!(fees.priority_service_fee(), 300);
+    }
+
     #[test]
     fn test_agent_status() {
         assert_eq!(AgentStatus::from_u8(0), Some(AgentStatus::Pending));
@@ -1006,4 +2373,185 @@ mod tests {
         );
         assert!(matches!(skill, Err(SdkError::TooManySkillTags)));
     }
+
+    /// Agent at every limit the request calls out: full description, ten
+    /// skills, three endpoints and ten tags
+    fn fully_populated_agent() -> AgentArgs {
+        AgentArgs {
+            agent_id: "rich-agent".to_string(),
+            name: "n".repeat(MAX_AGENT_NAME_LEN),
+            description: "d".repeat(MAX_AGENT_DESCRIPTION_LEN),
+            agent_version: "1.0.0".to_string(),
+            provider_name: Some("p".repeat(MAX_PROVIDER_NAME_LEN)),
+            provider_url: Some("https://provider.example/".repeat(10)),
+            documentation_url: Some("https://docs.example/".repeat(12)),
+            service_endpoints: (0..MAX_SERVICE_ENDPOINTS)
+                .map(|i| ServiceEndpoint {
+                    protocol: "a2a_http_jsonrpc".to_string(),
+                    url: format!("https://example.com/{}", "e".repeat(200 + i)),
+                    is_default: i == 0,
+                })
+                .collect(),
+            capabilities_flags: 7,
+            supported_input_modes: vec!["text/plain".to_string(); MAX_SUPPORTED_MODES],
+            supported_output_modes: vec!["application/json".to_string(); MAX_SUPPORTED_MODES],
+            skills: (0..MAX_SKILLS)
+                .map(|i| AgentSkill {
+                    skill_id: format!("skill-{}", i),
+                    name: "s".repeat(MAX_SKILL_NAME_LEN),
+                    tags: vec!["t".repeat(MAX_SKILL_TAG_LEN); MAX_SKILL_TAGS],
+                })
+                .collect(),
+            security_info_uri: Some("https://security.example/".repeat(10)),
+            aea_address: Some("aea".repeat(40)),
+            economic_intent_summary: Some("e".repeat(MAX_ECONOMIC_INTENT_LEN)),
+            supported_aea_protocols_hash: Some([9; HASH_SIZE]),
+            extended_metadata_uri: Some("https://metadata.example/".repeat(10)),
+            tags: (0..MAX_AGENT_TAGS).map(|i| format!("tag-{}", i)).collect(),
+        }
+    }
+
+    #[test]
+    fn test_entry_prefix_layout() {
+        // Header (44 bytes) followed by the economics block (148 bytes)
+        assert_eq!(AgentEntryPrefix::LEN, 192);
+
+        let mut data = vec![0u8; AgentEntryPrefix::LEN];
+        data[1] = crate::zero_copy::ENTRY_LAYOUT_VERSION;
+        data[2..10].copy_from_slice(&7u64.to_le_bytes()); // state_version
+        data[43] = AgentStatus::Active as u8;
+        let staked_offset = AgentEntryPrefix::ECONOMICS_OFFSET + 32; // after token_mint
+        data[staked_offset..staked_offset + 8].copy_from_slice(&5_000u64.to_le_bytes());
+        let ratings_offset = AgentEntryPrefix::ECONOMICS_OFFSET + 82;
+        data[ratings_offset..ratings_offset + 3].copy_from_slice(&[5, 4, 3]);
+        data[ratings_offset + MAX_QUALITY_RATINGS] = 3; // quality_rating_count
+        "agent".to_string().serialize(&mut data).unwrap();
+
+        let prefix = AgentEntryPrefix::from_account_data(&data).unwrap();
+        assert_eq!({ prefix.header.state_version }, 7);
+        assert_eq!(prefix.status(), Some(AgentStatus::Active));
+        assert_eq!({ prefix.economics.staked_amount }, 5_000);
+        assert_eq!(prefix.economics.ratings(), &[5, 4, 3]);
+        assert!(AgentEntryPrefix::from_account_data(&data[..AgentEntryPrefix::LEN - 1]).is_err());
+
+        data[1] = 1;
+        assert!(matches!(
+            AgentEntryPrefix::from_account_data(&data),
+            Err(SdkError::UnsupportedEntryVersion)
+        ));
+    }
+
+    #[test]
+    fn test_chunked_registration_fits_transactions() {
+        let program_id = Pubkey::new_unique();
+        let owner = Pubkey::new_unique();
+        let args = fully_populated_agent();
+
+        let single =
+            create_register_agent_instruction(&program_id, &owner, &owner, args.clone()).unwrap();
+        assert!(!fits_in_transaction(&owner, &[single]));
+
+        let instructions =
+            create_chunked_register_agent_instructions(&program_id, &owner, &owner, args.clone())
+                .unwrap();
+        for instruction in &instructions {
+            assert!(fits_in_transaction(
+                &owner,
+                std::slice::from_ref(instruction)
+            ));
+        }
+
+        let decoded: Vec<AgentRegistryInstruction> = instructions
+            .iter()
+            .map(|ix| AgentRegistryInstruction::try_from_slice(&ix.data).unwrap())
+            .collect();
+        assert!(matches!(
+            decoded.first(),
+            Some(AgentRegistryInstruction::BeginAgentRegistration { .. })
+        ));
+        assert_eq!(
+            decoded.last(),
+            Some(&AgentRegistryInstruction::FinalizeAgentRegistration)
+        );
+
+        // Every skill, endpoint, tag and optional field is carried exactly once
+        let mut skills = 0;
+        let mut endpoints = 0;
+        let mut tags = 0;
+        let mut economic_intent_summary = None;
+        for instruction in &decoded {
+            match instruction {
+                AgentRegistryInstruction::AppendAgentSkills { skills: chunk } => {
+                    skills += chunk.len()
+                }
+                AgentRegistryInstruction::AppendAgentServiceEndpoints { service_endpoints } => {
+                    endpoints += service_endpoints.len()
+                }
+                AgentRegistryInstruction::AppendAgentTags { tags: chunk } => tags += chunk.len(),
+                AgentRegistryInstruction::UpdateAgentDetails { details, .. } => {
+                    if details.economic_intent_summary.is_some() {
+                        economic_intent_summary = details.economic_intent_summary.clone();
+                    }
+                }
+                _ => {}
+            }
+        }
+        assert_eq!(skills, MAX_SKILLS);
+        assert_eq!(endpoints, MAX_SERVICE_ENDPOINTS);
+        assert_eq!(tags, MAX_AGENT_TAGS);
+        assert_eq!(economic_intent_summary, args.economic_intent_summary);
+    }
+
+    #[test]
+    fn test_chunked_registration_small_agent() {
+        let program_id = Pubkey::new_unique();
+        let owner = Pubkey::new_unique();
+        let args = AgentBuilder::new("small-agent", "Small Agent")
+            .tags(vec!["tiny"])
+            .build()
+            .unwrap();
+
+        let instructions =
+            create_chunked_register_agent_instructions(&program_id, &owner, &owner, args).unwrap();
+        // Begin, one tag append, finalize
+        assert_eq!(instructions.len(), 3);
+        assert_eq!(instructions[2].data, vec![17]);
+        assert_eq!(instructions[2].accounts.len(), 2);
+    }
+
+    #[test]
+    fn test_sponsored_registration_accounts() {
+        let program_id = Pubkey::new_unique();
+        let owner = Pubkey::new_unique();
+        let payer = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+        let token_account = Pubkey::new_unique();
+        let args = AgentBuilder::new("sponsored", "Sponsored Agent")
+            .build()
+            .unwrap();
+
+        let ix =
+            create_register_agent_instruction(&program_id, &owner, &payer, args.clone()).unwrap();
+        assert_eq!(ix.accounts[1], AccountMeta::new_readonly(owner, true));
+        assert_eq!(ix.accounts[2], AccountMeta::new(payer, true));
+
+        let ix = create_register_agent_with_token_instruction(
+            &program_id,
+            &owner,
+            &payer,
+            &token_account,
+            &mint,
+            args,
+        )
+        .unwrap();
+        assert!(matches!(
+            AgentRegistryInstruction::try_from_slice(&ix.data).unwrap(),
+            AgentRegistryInstruction::RegisterAgentWithToken { .. }
+        ));
+        assert_eq!(ix.accounts.len(), 10);
+        assert_eq!(ix.accounts[1], AccountMeta::new_readonly(owner, true));
+        assert_eq!(ix.accounts[2], AccountMeta::new(payer, true));
+        assert_eq!(ix.accounts[3].pubkey, token_account);
+        assert_eq!(ix.accounts[5].pubkey, mint);
+    }
 }
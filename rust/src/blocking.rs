@@ -0,0 +1,119 @@
+//! Blocking facade over the async client
+//!
+//! [`BlockingClient`] owns a small Tokio runtime and drives the async
+//! [`SolanaAiRegistriesClient`] on it, for callers without a runtime of
+//! their own. It must not be used from inside an async context, where
+//! blocking on the runtime panics.
+
+use crate::agent::{AgentArgs, AgentEntry};
+use crate::client::SolanaAiRegistriesClient;
+use crate::errors::{SdkError, SdkResult};
+use crate::mcp::{McpServerArgs, McpServerEntry};
+use crate::rpc::RegistryRpc;
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::{
+    commitment_config::CommitmentConfig,
+    pubkey::Pubkey,
+    signature::{Signature, Signer},
+};
+use std::future::Future;
+
+/// Sync wrapper around [`SolanaAiRegistriesClient`]
+pub struct BlockingClient<R: RegistryRpc = RpcClient> {
+    inner: SolanaAiRegistriesClient<R>,
+    runtime: tokio::runtime::Runtime,
+}
+
+impl BlockingClient {
+    /// Create a new blocking client with the specified RPC endpoint
+    pub fn new(rpc_url: &str) -> SdkResult<Self> {
+        Self::new_with_commitment(rpc_url, CommitmentConfig::confirmed())
+    }
+
+    /// Create a new blocking client with custom commitment level
+    pub fn new_with_commitment(rpc_url: &str, commitment: CommitmentConfig) -> SdkResult<Self> {
+        Self::from_client(SolanaAiRegistriesClient::new_with_commitment(
+            rpc_url, commitment,
+        ))
+    }
+}
+
+impl<R: RegistryRpc> BlockingClient<R> {
+    /// Wrap an existing async client
+    pub fn from_client(inner: SolanaAiRegistriesClient<R>) -> SdkResult<Self> {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .map_err(|e| SdkError::ValidationError(format!("Failed to start runtime: {}", e)))?;
+        Ok(Self { inner, runtime })
+    }
+
+    /// Get the wrapped async client
+    pub fn inner(&self) -> &SolanaAiRegistriesClient<R> {
+        &self.inner
+    }
+
+    /// Run any async client call to completion, e.g.
+    /// `client.block_on(|c| c.get_mcp_registry_config())`
+    pub fn block_on<'a, F, Fut, T>(&'a self, call: F) -> T
+    where
+        F: FnOnce(&'a SolanaAiRegistriesClient<R>) -> Fut,
+        Fut: Future<Output = T>,
+    {
+        self.runtime.block_on(call(&self.inner))
+    }
+
+    /// Register a new agent
+    pub fn register_agent<S: Signer>(&self, signer: &S, args: AgentArgs) -> SdkResult<Signature> {
+        self.block_on(|client| client.register_agent(signer, args))
+    }
+
+    /// Get an agent entry by ID
+    pub fn get_agent(&self, owner: &Pubkey, agent_id: &str) -> SdkResult<Option<AgentEntry>> {
+        self.block_on(|client| client.get_agent(owner, agent_id))
+    }
+
+    /// Register a new MCP server
+    pub fn register_mcp_server<S: Signer>(
+        &self,
+        signer: &S,
+        args: McpServerArgs,
+    ) -> SdkResult<Signature> {
+        self.block_on(|client| client.register_mcp_server(signer, args))
+    }
+
+    /// Get an MCP server entry by ID
+    pub fn get_mcp_server(
+        &self,
+        owner: &Pubkey,
+        server_id: &str,
+    ) -> SdkResult<Option<McpServerEntry>> {
+        self.block_on(|client| client.get_mcp_server(owner, server_id))
+    }
+
+    /// Get account balance in lamports
+    pub fn get_balance(&self, pubkey: &Pubkey) -> SdkResult<u64> {
+        self.block_on(|client| client.get_balance(pubkey))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_blocking_client_runs_calls() {
+        let client = BlockingClient::new_with_commitment(
+            "https://api.devnet.solana.com",
+            CommitmentConfig::finalized(),
+        )
+        .unwrap();
+
+        let program_id = client.block_on(|c| async move { *c.agent_registry_program_id() });
+        assert_eq!(&program_id, client.inner().agent_registry_program_id());
+        assert_eq!(
+            client.inner().rpc_client().commitment(),
+            CommitmentConfig::finalized()
+        );
+    }
+}
@@ -0,0 +1,613 @@
+//! Slot-aware cache of registry entry accounts
+//!
+//! [`RegistryCache`] keeps the raw data of agent and MCP server entries in
+//! memory, keyed by PDA and evicted least recently used first. Attach it
+//! with [`crate::SolanaAiRegistriesClient::with_cache`] and `get_agent` /
+//! `get_mcp_server` read through it, while `list_agents` /
+//! `list_mcp_servers` warm it in bulk.
+//!
+//! Every cached value remembers the slot it was read at, and a value only
+//! ever replaces one from the same slot or an earlier one, so a slow read
+//! cannot overwrite a newer state. Values go stale in three ways:
+//!
+//! - transactions the client sends invalidate every writable account they
+//!   touch once confirmed;
+//! - [`RegistryCache::follow`] invalidates entries as account subscriptions
+//!   report changes made by anyone else;
+//! - with [`CacheConfig::max_slot_age`] set, values read more than that many
+//!   slots ago are fetched again.
+//!
+//! With [`CacheConfig::path`] set the cache is loaded from that file when
+//! created and written back by [`RegistryCache::save`]. Loaded values keep
+//! their original slots, so a slot age policy applies to them as well.
+
+use crate::errors::{SdkError, SdkResult};
+use crate::rpc::RegistryRpc;
+use crate::subscription::{RegistryFilter, RegistrySubscriber, RegistryUpdate};
+use borsh::{BorshDeserialize, BorshSerialize};
+use futures::stream::{SelectAll, StreamExt};
+use solana_sdk::{account::Account, clock::Slot, pubkey::Pubkey};
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::task::JoinHandle;
+
+/// Entries kept unless configured otherwise
+pub const DEFAULT_CACHE_CAPACITY: usize = 10_000;
+
+/// How long the current slot is reused before it is fetched again when
+/// checking slot ages; about one slot
+pub const DEFAULT_SLOT_REFRESH: Duration = Duration::from_millis(400);
+
+/// Version of the on-disk format written by [`RegistryCache::save`]
+const CACHE_FILE_VERSION: u8 = 1;
+
+/// How a [`RegistryCache`] sizes itself and decides values are stale
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CacheConfig {
+    /// Most entries kept in memory
+    pub capacity: usize,
+    /// Fetch values again once they were read more than this many slots
+    /// ago; `None` keeps them until invalidated or evicted
+    pub max_slot_age: Option<Slot>,
+    /// How long the current slot is reused when checking slot ages
+    pub slot_refresh: Duration,
+    /// File the cache is loaded from and saved to
+    pub path: Option<PathBuf>,
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        Self {
+            capacity: DEFAULT_CACHE_CAPACITY,
+            max_slot_age: None,
+            slot_refresh: DEFAULT_SLOT_REFRESH,
+            path: None,
+        }
+    }
+}
+
+/// Counters describing how well a [`RegistryCache`] is doing
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct CacheStats {
+    /// Reads answered from the cache
+    pub hits: u64,
+    /// Reads that went to the RPC
+    pub misses: u64,
+    /// Values dropped to stay within capacity
+    pub evictions: u64,
+    /// Values marked stale by sent transactions or subscriptions
+    pub invalidations: u64,
+    /// Entries currently held
+    pub len: usize,
+}
+
+impl CacheStats {
+    /// Fraction of reads answered from the cache, 0 before any read
+    pub fn hit_rate(&self) -> f64 {
+        let reads = self.hits + self.misses;
+        if reads == 0 {
+            0.0
+        } else {
+            self.hits as f64 / reads as f64
+        }
+    }
+}
+
+/// What the cache knows about one address
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Cached {
+    /// The account's data as of `slot`, `None` if it did not exist
+    Account { slot: Slot, data: Option<Vec<u8>> },
+    /// The account changed at `slot`; kept so that reads from before the
+    /// change cannot bring the old state back
+    Invalidated { slot: Slot },
+}
+
+impl Cached {
+    fn slot(&self) -> Slot {
+        match self {
+            Cached::Account { slot, .. } | Cached::Invalidated { slot } => *slot,
+        }
+    }
+}
+
+/// Least recently used map from address to [`Cached`]
+#[derive(Debug)]
+struct Lru {
+    capacity: usize,
+    tick: u64,
+    entries: HashMap<Pubkey, (u64, Cached)>,
+    order: BTreeMap<u64, Pubkey>,
+}
+
+impl Lru {
+    fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            tick: 0,
+            entries: HashMap::new(),
+            order: BTreeMap::new(),
+        }
+    }
+
+    /// Look `address` up, marking it most recently used
+    fn get(&mut self, address: &Pubkey) -> Option<&Cached> {
+        let tick = self.next_tick();
+        let (used, _) = self.entries.get_mut(address)?;
+        self.order.remove(used);
+        self.order.insert(tick, *address);
+        *used = tick;
+        self.entries.get(address).map(|(_, cached)| cached)
+    }
+
+    /// Store `cached` unless what is held is from a later slot. Returns how
+    /// many entries were evicted to make room.
+    fn insert(&mut self, address: Pubkey, cached: Cached) -> u64 {
+        if self.capacity == 0 {
+            return 0;
+        }
+        if let Some((used, held)) = self.entries.get(&address) {
+            if held.slot() > cached.slot() {
+                return 0;
+            }
+            self.order.remove(used);
+        }
+
+        let tick = self.next_tick();
+        self.entries.insert(address, (tick, cached));
+        self.order.insert(tick, address);
+
+        let mut evicted = 0;
+        while self.entries.len() > self.capacity {
+            let Some((_, oldest)) = self.order.pop_first() else {
+                break;
+            };
+            self.entries.remove(&oldest);
+            evicted += 1;
+        }
+        evicted
+    }
+
+    /// Mark `address` stale as of `slot`, unless what is held is from a
+    /// later slot. Addresses not held get a tombstone too, since a read
+    /// still in flight may be about to cache their old value. Returns
+    /// whether a held value went stale and how many entries were evicted to
+    /// make room.
+    fn invalidate(&mut self, address: &Pubkey, slot: Slot) -> (bool, u64) {
+        let stale = matches!(
+            self.entries.get(address),
+            Some((_, held @ Cached::Account { .. })) if held.slot() <= slot
+        );
+        (stale, self.insert(*address, Cached::Invalidated { slot }))
+    }
+
+    /// Entries from least to most recently used
+    fn iter(&self) -> impl Iterator<Item = (&Pubkey, &Cached)> {
+        self.order
+            .values()
+            .filter_map(|address| Some((address, &self.entries.get(address)?.1)))
+    }
+
+    fn clear(&mut self) {
+        self.entries.clear();
+        self.order.clear();
+    }
+
+    fn next_tick(&mut self) -> u64 {
+        self.tick += 1;
+        self.tick
+    }
+}
+
+/// The newest slot seen and when it was last fetched
+#[derive(Debug, Default)]
+struct SlotClock {
+    slot: Slot,
+    fetched_at: Option<Instant>,
+}
+
+/// One cached account as written to disk
+#[derive(BorshSerialize, BorshDeserialize)]
+struct CacheFileEntry {
+    address: [u8; 32],
+    slot: Slot,
+    data: Option<Vec<u8>>,
+}
+
+/// In-memory, optionally persisted, cache of registry entry accounts. Share
+/// it between clients with an [`Arc`].
+#[derive(Debug)]
+pub struct RegistryCache {
+    config: CacheConfig,
+    entries: Mutex<Lru>,
+    clock: Mutex<SlotClock>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+    evictions: AtomicU64,
+    invalidations: AtomicU64,
+}
+
+impl RegistryCache {
+    /// Create a cache, loading the file at [`CacheConfig::path`] if it
+    /// exists
+    pub fn new(config: CacheConfig) -> SdkResult<Self> {
+        let cache = Self {
+            entries: Mutex::new(Lru::new(config.capacity)),
+            clock: Mutex::new(SlotClock::default()),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+            evictions: AtomicU64::new(0),
+            invalidations: AtomicU64::new(0),
+            config,
+        };
+
+        if let Some(path) = cache.config.path.as_ref().filter(|path| path.exists()) {
+            let bytes = std::fs::read(path).map_err(|e| {
+                SdkError::DeserializationError(format!("Failed to read {}: {}", path.display(), e))
+            })?;
+            let entries = decode_cache_file(&bytes)?;
+            let mut lru = cache.lock_entries();
+            for entry in entries {
+                let slot = entry.slot;
+                lru.insert(
+                    Pubkey::new_from_array(entry.address),
+                    Cached::Account {
+                        slot,
+                        data: entry.data,
+                    },
+                );
+                cache.observe_slot(slot);
+            }
+        }
+
+        Ok(cache)
+    }
+
+    /// How the cache was configured
+    pub fn config(&self) -> &CacheConfig {
+        &self.config
+    }
+
+    /// Current counters
+    pub fn stats(&self) -> CacheStats {
+        CacheStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            evictions: self.evictions.load(Ordering::Relaxed),
+            invalidations: self.invalidations.load(Ordering::Relaxed),
+            len: self.len(),
+        }
+    }
+
+    /// Entries currently held, including invalidated ones
+    pub fn len(&self) -> usize {
+        self.lock_entries().entries.len()
+    }
+
+    /// Whether nothing is cached
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Mark `address` stale so the next read fetches it
+    pub fn invalidate(&self, address: &Pubkey) {
+        let slot = self.observed_slot();
+        self.invalidate_at(address, slot);
+    }
+
+    /// Drop every cached value
+    pub fn clear(&self) {
+        self.lock_entries().clear();
+    }
+
+    /// Write the cached accounts to [`CacheConfig::path`], replacing the
+    /// file atomically. Does nothing without a path.
+    pub fn save(&self) -> SdkResult<()> {
+        let Some(path) = &self.config.path else {
+            return Ok(());
+        };
+
+        let entries: Vec<CacheFileEntry> = self
+            .lock_entries()
+            .iter()
+            .filter_map(|(address, cached)| match cached {
+                Cached::Account { slot, data } => Some(CacheFileEntry {
+                    address: address.to_bytes(),
+                    slot: *slot,
+                    data: data.clone(),
+                }),
+                Cached::Invalidated { .. } => None,
+            })
+            .collect();
+        let mut bytes = vec![CACHE_FILE_VERSION];
+        entries
+            .serialize(&mut bytes)
+            .map_err(|e| SdkError::SerializationError(e.to_string()))?;
+
+        let write_error = |e: std::io::Error| {
+            SdkError::SerializationError(format!("Failed to write {}: {}", path.display(), e))
+        };
+        let temporary = path.with_extension("tmp");
+        std::fs::write(&temporary, bytes).map_err(write_error)?;
+        std::fs::rename(&temporary, path).map_err(write_error)
+    }
+
+    /// Keep the cache in step with changes made by anyone, not just this
+    /// process, by following both registries over PubSub. Changed entries
+    /// are invalidated at the slot of the change; if the subscription drops,
+    /// everything is cleared since changes may have been missed. Stops when
+    /// the returned [`CacheWatch`] is dropped.
+    pub fn follow(self: &Arc<Self>, subscriber: &RegistrySubscriber) -> CacheWatch {
+        let mut updates = SelectAll::new();
+        updates.push(subscriber.subscribe_program(RegistryFilter::Agents { owner: None }));
+        updates.push(subscriber.subscribe_program(RegistryFilter::McpServers { owner: None }));
+
+        let cache = Arc::clone(self);
+        let task = tokio::spawn(async move {
+            while let Some(update) = updates.next().await {
+                match update {
+                    Ok(RegistryUpdate::Account { slot, address, .. }) => {
+                        cache.invalidate_at(&address, slot)
+                    }
+                    Ok(RegistryUpdate::Events { slot, .. }) => cache.observe_slot(slot),
+                    Err(_) => cache.clear(),
+                }
+            }
+        });
+
+        CacheWatch { task }
+    }
+
+    /// Read `address` through the cache
+    pub(crate) async fn read<R: RegistryRpc + ?Sized>(
+        &self,
+        rpc: &R,
+        address: &Pubkey,
+    ) -> SdkResult<Option<Vec<u8>>> {
+        let oldest = match self.config.max_slot_age {
+            Some(age) => self.current_slot(rpc).await?.saturating_sub(age),
+            None => 0,
+        };
+
+        let hit = match self.lock_entries().get(address) {
+            Some(Cached::Account { slot, data }) if *slot >= oldest => Some(data.clone()),
+            _ => None,
+        };
+        if let Some(data) = hit {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+            return Ok(data);
+        }
+        self.misses.fetch_add(1, Ordering::Relaxed);
+
+        let (slot, account) = rpc.get_account_with_slot(address).await?;
+        let data = live_data(account);
+        self.insert(*address, slot, data.clone());
+        Ok(data)
+    }
+
+    /// Cache `address` as holding `data` at `slot`
+    pub(crate) fn insert(&self, address: Pubkey, slot: Slot, data: Option<Vec<u8>>) {
+        let evicted = self
+            .lock_entries()
+            .insert(address, Cached::Account { slot, data });
+        self.evictions.fetch_add(evicted, Ordering::Relaxed);
+        self.observe_slot(slot);
+    }
+
+    /// Mark `address` stale as of `slot`, so reads made before that slot
+    /// cannot bring the old value back
+    pub(crate) fn invalidate_at(&self, address: &Pubkey, slot: Slot) {
+        let (stale, evicted) = self.lock_entries().invalidate(address, slot);
+        if stale {
+            self.invalidations.fetch_add(1, Ordering::Relaxed);
+        }
+        self.evictions.fetch_add(evicted, Ordering::Relaxed);
+        self.observe_slot(slot);
+    }
+
+    /// The current slot, fetched at most once per
+    /// [`CacheConfig::slot_refresh`]
+    async fn current_slot<R: RegistryRpc + ?Sized>(&self, rpc: &R) -> SdkResult<Slot> {
+        {
+            let clock = self.lock_clock();
+            if clock
+                .fetched_at
+                .is_some_and(|at| at.elapsed() < self.config.slot_refresh)
+            {
+                return Ok(clock.slot);
+            }
+        }
+
+        let slot = rpc.get_slot().await?;
+        let mut clock = self.lock_clock();
+        clock.slot = clock.slot.max(slot);
+        clock.fetched_at = Some(Instant::now());
+        Ok(clock.slot)
+    }
+
+    fn observed_slot(&self) -> Slot {
+        self.lock_clock().slot
+    }
+
+    /// Note that the cluster has reached `slot`
+    fn observe_slot(&self, slot: Slot) {
+        let mut clock = self.lock_clock();
+        clock.slot = clock.slot.max(slot);
+    }
+
+    fn lock_entries(&self) -> std::sync::MutexGuard<'_, Lru> {
+        self.entries.lock().expect("cache lock poisoned")
+    }
+
+    fn lock_clock(&self) -> std::sync::MutexGuard<'_, SlotClock> {
+        self.clock.lock().expect("cache clock lock poisoned")
+    }
+}
+
+/// Keeps a [`RegistryCache`] following the registries; see
+/// [`RegistryCache::follow`]
+pub struct CacheWatch {
+    task: JoinHandle<()>,
+}
+
+impl Drop for CacheWatch {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+/// An account's data, or `None` if it does not exist or has been closed
+pub(crate) fn live_data(account: Option<Account>) -> Option<Vec<u8>> {
+    account
+        .filter(|account| account.lamports > 0)
+        .map(|account| account.data)
+}
+
+fn decode_cache_file(bytes: &[u8]) -> SdkResult<Vec<CacheFileEntry>> {
+    match bytes.split_first() {
+        Some((&CACHE_FILE_VERSION, mut rest)) => Vec::<CacheFileEntry>::deserialize(&mut rest)
+            .map_err(|e| SdkError::DeserializationError(e.to_string())),
+        _ => Err(SdkError::DeserializationError(
+            "Unsupported cache file version".to_string(),
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cache(capacity: usize) -> RegistryCache {
+        RegistryCache::new(CacheConfig {
+            capacity,
+            ..CacheConfig::default()
+        })
+        .unwrap()
+    }
+
+    fn held(cache: &RegistryCache, address: &Pubkey) -> Option<Cached> {
+        cache.lock_entries().get(address).cloned()
+    }
+
+    #[test]
+    fn test_least_recently_used_is_evicted() {
+        let cache = cache(2);
+        let (a, b, c) = (
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        );
+
+        cache.insert(a, 1, Some(vec![1]));
+        cache.insert(b, 1, Some(vec![2]));
+        held(&cache, &a);
+        cache.insert(c, 1, None);
+
+        assert!(held(&cache, &a).is_some());
+        assert!(held(&cache, &b).is_none());
+        assert!(held(&cache, &c).is_some());
+        assert_eq!(cache.stats().evictions, 1);
+        assert_eq!(cache.len(), 2);
+    }
+
+    #[test]
+    fn test_older_slots_never_replace_newer() {
+        let cache = cache(10);
+        let address = Pubkey::new_unique();
+
+        cache.insert(address, 10, Some(vec![10]));
+        cache.insert(address, 9, Some(vec![9]));
+        assert_eq!(
+            held(&cache, &address),
+            Some(Cached::Account {
+                slot: 10,
+                data: Some(vec![10])
+            })
+        );
+
+        // A read from before the change cannot bring the old state back
+        cache.invalidate_at(&address, 12);
+        cache.insert(address, 11, Some(vec![11]));
+        assert_eq!(
+            held(&cache, &address),
+            Some(Cached::Invalidated { slot: 12 })
+        );
+        assert_eq!(cache.stats().invalidations, 1);
+
+        // Addresses that were never cached are held back from older reads
+        // as well
+        let unheld = Pubkey::new_unique();
+        cache.invalidate_at(&unheld, 12);
+        cache.insert(unheld, 11, Some(vec![11]));
+        assert_eq!(
+            held(&cache, &unheld),
+            Some(Cached::Invalidated { slot: 12 })
+        );
+        assert_eq!(cache.stats().invalidations, 1);
+
+        cache.insert(address, 12, Some(vec![12]));
+        assert_eq!(
+            held(&cache, &address),
+            Some(Cached::Account {
+                slot: 12,
+                data: Some(vec![12])
+            })
+        );
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let path = std::env::temp_dir().join(format!("registry-cache-{}", Pubkey::new_unique()));
+        let config = CacheConfig {
+            path: Some(path.clone()),
+            ..CacheConfig::default()
+        };
+        let (kept, gone, stale) = (
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        );
+
+        let cache = RegistryCache::new(config.clone()).unwrap();
+        cache.insert(kept, 7, Some(vec![1, 2, 3]));
+        cache.insert(gone, 8, None);
+        cache.insert(stale, 5, Some(vec![4]));
+        cache.invalidate_at(&stale, 9);
+        cache.save().unwrap();
+
+        let loaded = RegistryCache::new(config).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(loaded.len(), 2);
+        assert_eq!(
+            held(&loaded, &kept),
+            Some(Cached::Account {
+                slot: 7,
+                data: Some(vec![1, 2, 3])
+            })
+        );
+        assert_eq!(
+            held(&loaded, &gone),
+            Some(Cached::Account {
+                slot: 8,
+                data: None
+            })
+        );
+        assert_eq!(loaded.observed_slot(), 8);
+
+        assert!(decode_cache_file(&[CACHE_FILE_VERSION + 1]).is_err());
+    }
+
+    #[test]
+    fn test_hit_rate() {
+        let stats = CacheStats {
+            hits: 3,
+            misses: 1,
+            ..CacheStats::default()
+        };
+        assert_eq!(stats.hit_rate(), 0.75);
+        assert_eq!(CacheStats::default().hit_rate(), 0.0);
+    }
+}
@@ -0,0 +1,223 @@
+//! Transaction size planning
+//!
+//! A serialized Solana transaction is capped at 1232 bytes. Fully populated
+//! registrations exceed that, so the registries accept them in chunks: a
+//! begin instruction, append instructions and a finalize. This module
+//! measures instructions against the limit and packs them into transactions.
+
+use crate::errors::{SdkError, SdkResult};
+use solana_sdk::{
+    instruction::Instruction, message::Message, packet::PACKET_DATA_SIZE, pubkey::Pubkey,
+};
+
+/// Maximum size of a serialized transaction in bytes
+pub const MAX_TRANSACTION_SIZE: usize = PACKET_DATA_SIZE;
+
+/// Size of one ed25519 signature in a transaction
+const SIGNATURE_LEN: usize = 64;
+
+/// Bytes the client's compute-unit limit and price instructions add to a
+/// transaction: the Compute Budget program key plus both instructions
+pub const COMPUTE_BUDGET_RESERVE: usize = 32 + (3 + 5) + (3 + 9);
+
+/// Serialized size of a legacy transaction carrying `instructions`, with
+/// `payer` as fee payer and every required signature present
+pub fn transaction_size(payer: &Pubkey, instructions: &[Instruction]) -> usize {
+    let message = Message::new(instructions, Some(payer));
+    signed_transaction_size(
+        message.header.num_required_signatures as usize,
+        message.serialize().len(),
+    )
+}
+
+/// Serialized size of a transaction with `num_signatures` signatures over a
+/// message of `message_len` bytes
+pub(crate) fn signed_transaction_size(num_signatures: usize, message_len: usize) -> usize {
+    short_vec_len(num_signatures) + num_signatures * SIGNATURE_LEN + message_len
+}
+
+/// Whether `instructions` fit together in a single transaction, leaving
+/// room for the compute budget instructions the client may prepend
+pub fn fits_in_transaction(payer: &Pubkey, instructions: &[Instruction]) -> bool {
+    transaction_size(payer, instructions) + COMPUTE_BUDGET_RESERVE <= MAX_TRANSACTION_SIZE
+}
+
+/// Pack `instructions`, in order, into as few transactions as possible.
+/// Fails if any single instruction is too large for a transaction.
+pub fn pack_instructions(
+    payer: &Pubkey,
+    instructions: Vec<Instruction>,
+) -> SdkResult<Vec<Vec<Instruction>>> {
+    let mut transactions = Vec::new();
+    let mut current: Vec<Instruction> = Vec::new();
+
+    for instruction in instructions {
+        current.push(instruction);
+        if fits_in_transaction(payer, &current) {
+            continue;
+        }
+
+        let overflow = current.pop().expect("just pushed");
+        if current.is_empty() {
+            return Err(too_large(payer, std::slice::from_ref(&overflow)));
+        }
+        transactions.push(std::mem::replace(&mut current, vec![overflow]));
+        if !fits_in_transaction(payer, &current) {
+            return Err(too_large(payer, &current));
+        }
+    }
+
+    if !current.is_empty() {
+        transactions.push(current);
+    }
+    Ok(transactions)
+}
+
+/// Split `items` into consecutive runs, each as long as possible while the
+/// instruction `build` makes from it still fits in a transaction on its own
+pub(crate) fn split_to_fit<T, F>(
+    payer: &Pubkey,
+    items: &[T],
+    build: F,
+) -> SdkResult<Vec<Instruction>>
+where
+    F: Fn(&[T]) -> SdkResult<Instruction>,
+{
+    let mut instructions = Vec::new();
+    let mut start = 0;
+
+    while start < items.len() {
+        let mut end = start + 1;
+        let mut instruction = build(&items[start..end])?;
+        if !fits_in_transaction(payer, std::slice::from_ref(&instruction)) {
+            return Err(too_large(payer, std::slice::from_ref(&instruction)));
+        }
+
+        while end < items.len() {
+            let candidate = build(&items[start..end + 1])?;
+            if !fits_in_transaction(payer, std::slice::from_ref(&candidate)) {
+                break;
+            }
+            instruction = candidate;
+            end += 1;
+        }
+
+        instructions.push(instruction);
+        start = end;
+    }
+
+    Ok(instructions)
+}
+
+fn too_large(payer: &Pubkey, instructions: &[Instruction]) -> SdkError {
+    SdkError::TransactionTooLarge(transaction_size(payer, instructions), MAX_TRANSACTION_SIZE)
+}
+
+/// Length of the compact-u16 prefix Solana uses for array lengths
+fn short_vec_len(len: usize) -> usize {
+    match len {
+        0..=0x7f => 1,
+        0x80..=0x3fff => 2,
+        _ => 3,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_sdk::{
+        hash::Hash,
+        instruction::AccountMeta,
+        packet::Packet,
+        signature::{Keypair, Signer},
+        transaction::Transaction,
+    };
+
+    fn instruction_with_data(len: usize) -> Instruction {
+        Instruction {
+            program_id: Pubkey::new_unique(),
+            accounts: vec![AccountMeta::new(Pubkey::new_unique(), false)],
+            data: vec![0; len],
+        }
+    }
+
+    #[test]
+    fn test_transaction_size_matches_signed_transaction() {
+        let payer = Keypair::new();
+        let instructions = vec![instruction_with_data(100), instruction_with_data(7)];
+        let transaction = Transaction::new_signed_with_payer(
+            &instructions,
+            Some(&payer.pubkey()),
+            &[&payer],
+            Hash::default(),
+        );
+
+        let packet = Packet::from_data(None, &transaction).unwrap();
+        assert_eq!(
+            transaction_size(&payer.pubkey(), &instructions),
+            packet.meta().size
+        );
+    }
+
+    #[test]
+    fn test_compute_budget_reserve() {
+        use solana_sdk::compute_budget::ComputeBudgetInstruction;
+
+        let payer = Pubkey::new_unique();
+        let instructions = vec![instruction_with_data(100)];
+        let with_budget = [
+            ComputeBudgetInstruction::set_compute_unit_limit(200_000),
+            ComputeBudgetInstruction::set_compute_unit_price(1_000),
+            instruction_with_data(100),
+        ];
+        assert_eq!(
+            transaction_size(&payer, &with_budget),
+            transaction_size(&payer, &instructions) + COMPUTE_BUDGET_RESERVE
+        );
+    }
+
+    #[test]
+    fn test_pack_instructions() {
+        let payer = Pubkey::new_unique();
+        let instructions: Vec<Instruction> = (0..5).map(|_| instruction_with_data(400)).collect();
+
+        let transactions = pack_instructions(&payer, instructions).unwrap();
+        assert!(transactions.len() > 1);
+        assert_eq!(transactions.iter().map(Vec::len).sum::<usize>(), 5);
+        for transaction in &transactions {
+            assert!(fits_in_transaction(&payer, transaction));
+        }
+
+        let oversized = vec![instruction_with_data(MAX_TRANSACTION_SIZE)];
+        assert!(matches!(
+            pack_instructions(&payer, oversized),
+            Err(SdkError::TransactionTooLarge(_, MAX_TRANSACTION_SIZE))
+        ));
+    }
+
+    #[test]
+    fn test_split_to_fit() {
+        let payer = Pubkey::new_unique();
+        let program_id = Pubkey::new_unique();
+        let items = vec![vec![1u8; 300]; 7];
+
+        let instructions = split_to_fit(&payer, &items, |chunk| {
+            Ok(Instruction {
+                program_id,
+                accounts: vec![],
+                data: chunk.concat(),
+            })
+        })
+        .unwrap();
+
+        assert!(instructions.len() > 1);
+        let total: usize = instructions.iter().map(|ix| ix.data.len()).sum();
+        assert_eq!(total, 7 * 300);
+        for instruction in &instructions {
+            assert!(fits_in_transaction(
+                &payer,
+                std::slice::from_ref(instruction)
+            ));
+        }
+    }
+}
@@ -3,79 +3,309 @@
 //! This module provides a high-level client interface for interacting
 //! with the Agent Registry and MCP Server Registry programs.
 
-use crate::agent::{AgentArgs, AgentEntry, AgentPatch};
+use crate::agent::{
+    AgentArgs, AgentEntry, AgentEntryPrefix, AgentPatch, AgentServiceFees, AgentSkillAccount,
+    MAX_SKILL_ACCOUNTS,
+};
+use crate::cache::{live_data, RegistryCache};
+use crate::chunking::{fits_in_transaction, pack_instructions};
+use crate::cluster::{ClientConfig, ComputeUnitPolicy, PriorityFeePolicy, MAX_COMPUTE_UNIT_LIMIT};
+use crate::config::{
+    derive_registry_config_pda, RegistryConfig, RegistryConfigParams, RegistryVaults,
+};
 use crate::errors::{SdkError, SdkResult};
-use crate::mcp::{McpServerArgs, McpServerEntry, McpServerPatch};
-use solana_client::rpc_client::RpcClient;
+use crate::fees::FeeSchedule;
+use crate::lookup_table::{
+    decode_lookup_table, missing_addresses, registry_lookup_addresses, MAX_ADDRESSES_PER_EXTEND,
+};
+use crate::mcp::{
+    estimate_usage_cost, DiscountTier, FeeReconciliation, FreeTier, ItemPriceInput,
+    McpPricingTable, McpServerArgs, McpServerEntry, McpServerEntryPrefix, McpServerPatch,
+    McpSubscription, McpSubscriptionPlan, McpToolAccount, McpUsageCostEstimate, McpUsageFees,
+    McpUsageRecord, SubscriptionQuota, UsageCoverage, UsageType, MAX_TOOL_ACCOUNTS,
+};
+use crate::plan::{missing_signers, nonce_blockhash, TransactionPlan};
+use crate::receipt::new_idempotency_key;
+use crate::rent::RentEstimate;
+use crate::revenue::{
+    derive_revenue_split_pda, preview_payout, PayoutPreview, RevenueSplit, SplitRecipient,
+};
+use crate::rpc::RegistryRpc;
+use crate::simulation::{decode_send_error, decode_transaction_error, SimulationReport};
+use crate::subscription::RegistrySubscriber;
+use solana_client::{
+    nonblocking::rpc_client::RpcClient,
+    rpc_filter::{Memcmp, RpcFilterType},
+};
 use solana_sdk::{
+    account::Account,
+    address_lookup_table,
+    address_lookup_table_account::AddressLookupTableAccount,
     commitment_config::CommitmentConfig,
+    hash::Hash,
+    instruction::Instruction,
+    program_pack::Pack,
     pubkey::Pubkey,
     signature::{Signature, Signer},
-    transaction::Transaction,
+    transaction::{Transaction, VersionedTransaction},
 };
-use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+
+/// Default number of attempts made by [`SolanaAiRegistriesClient::read_modify_write`]
+pub const DEFAULT_READ_MODIFY_WRITE_ATTEMPTS: u32 = 3;
+
+/// Byte offset of `state_version` in registry entry accounts (after `bump` and `registry_version`)
+pub const STATE_VERSION_OFFSET: usize = 2;
 
-/// Program IDs for the registries (placeholders for testing)
-pub const AGENT_REGISTRY_PROGRAM_ID: &str = "11111111111111111111111111111112";
-pub const MCP_SERVER_REGISTRY_PROGRAM_ID: &str = "11111111111111111111111111111113";
+/// Builder of a registry's `InitializeRegistryConfig` instruction
+type InitializeConfigFn =
+    fn(&Pubkey, &Pubkey, &Pubkey, RegistryConfigParams) -> SdkResult<Instruction>;
+
+/// Builder of a registry's `InitializeVaults` instruction
+type InitializeVaultsFn = fn(&Pubkey, &Pubkey, &Pubkey, &Pubkey) -> SdkResult<Instruction>;
 
 /// Main client for interacting with Solana AI Registries
-pub struct SolanaAiRegistriesClient {
-    rpc_client: RpcClient,
-    agent_registry_program_id: Pubkey,
-    mcp_server_registry_program_id: Pubkey,
-    commitment: CommitmentConfig,
+///
+/// Every call goes through a [`RegistryRpc`] transport, the nonblocking RPC
+/// client unless another is given, so the client is cheap to share across
+/// tasks. Sync callers can use `BlockingClient` behind the `blocking` feature.
+pub struct SolanaAiRegistriesClient<R: RegistryRpc = RpcClient> {
+    rpc_client: Arc<R>,
+    config: ClientConfig,
+    cache: Option<Arc<RegistryCache>>,
+    /// Decoded [`ClientConfig::lookup_table`], fetched on first use
+    lookup_table: Mutex<Option<AddressLookupTableAccount>>,
 }
 
 impl SolanaAiRegistriesClient {
-    /// Create a new client with the specified RPC endpoint
+    /// Create a new client with the specified RPC endpoint, for registries
+    /// at their devnet program IDs (see [`ClientConfig::localnet`])
     pub fn new(rpc_url: &str) -> Self {
         Self::new_with_commitment(rpc_url, CommitmentConfig::confirmed())
     }
 
     /// Create a new client with custom commitment level
     pub fn new_with_commitment(rpc_url: &str, commitment: CommitmentConfig) -> Self {
-        let rpc_client = RpcClient::new_with_commitment(rpc_url.to_string(), commitment);
+        Self::with_config(ClientConfig {
+            rpc_url: rpc_url.to_string(),
+            commitment,
+            ..ClientConfig::localnet()
+        })
+    }
+
+    /// Create a client for the deployment described by `config`, connecting
+    /// to its endpoint with its commitment and timeouts
+    pub fn with_config(config: ClientConfig) -> Self {
+        let rpc_client = RpcClient::new_with_timeouts_and_commitment(
+            config.rpc_url.clone(),
+            config.timeouts.request,
+            config.commitment,
+            config.timeouts.confirmation,
+        );
+        Self::from_rpc_client_with_config(Arc::new(rpc_client), config)
+    }
+
+    /// Pay-as-you-go payment client sharing this client's connection
+    #[cfg(feature = "pyg")]
+    pub fn pyg_payments(&self) -> crate::payments::pyg::PygPaymentClient {
+        crate::payments::pyg::PygPaymentClient::from_rpc_client(self.rpc_client.clone())
+    }
+
+    /// Streaming payment client for `program_id` sharing this client's
+    /// connection
+    #[cfg(feature = "stream")]
+    pub fn streaming_payments(
+        &self,
+        program_id: Pubkey,
+    ) -> crate::payments::stream::StreamingPaymentClient {
+        crate::payments::stream::StreamingPaymentClient::from_rpc_client(
+            self.rpc_client.clone(),
+            program_id,
+        )
+    }
+
+    /// Prepaid payment client for `program_id` sharing this client's
+    /// connection
+    #[cfg(feature = "prepay")]
+    pub fn prepaid_payments(
+        &self,
+        program_id: Pubkey,
+    ) -> crate::payments::prepay::PrepaidPaymentClient {
+        crate::payments::prepay::PrepaidPaymentClient::from_rpc_client(
+            self.rpc_client.clone(),
+            program_id,
+        )
+    }
+}
 
+impl<R: RegistryRpc> SolanaAiRegistriesClient<R> {
+    /// Create a client on an existing transport, using its commitment, for
+    /// registries at their devnet program IDs
+    pub fn from_rpc_client(rpc_client: Arc<R>) -> Self {
+        let config = ClientConfig {
+            commitment: rpc_client.commitment(),
+            ..ClientConfig::localnet()
+        };
+        Self::from_rpc_client_with_config(rpc_client, config)
+    }
+
+    /// Create a client on an existing transport for the deployment described
+    /// by `config`. The transport's own endpoint, commitment and request
+    /// timeout take precedence over the config's.
+    pub fn from_rpc_client_with_config(rpc_client: Arc<R>, config: ClientConfig) -> Self {
         Self {
             rpc_client,
-            agent_registry_program_id: Pubkey::from_str(AGENT_REGISTRY_PROGRAM_ID)
-                .expect("Invalid agent registry program ID"),
-            mcp_server_registry_program_id: Pubkey::from_str(MCP_SERVER_REGISTRY_PROGRAM_ID)
-                .expect("Invalid MCP server registry program ID"),
-            commitment,
+            config,
+            cache: None,
+            lookup_table: Mutex::new(None),
         }
     }
 
-    /// Get the underlying RPC client
-    pub fn rpc_client(&self) -> &RpcClient {
+    /// Read agent and MCP server entries through `cache`; see
+    /// [`crate::cache`]. The cache can be shared with other clients of the
+    /// same deployment.
+    pub fn with_cache(mut self, cache: Arc<RegistryCache>) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    /// Get the entry cache, if one is attached
+    pub fn cache(&self) -> Option<&Arc<RegistryCache>> {
+        self.cache.as_ref()
+    }
+
+    /// Get the deployment this client talks to
+    pub fn config(&self) -> &ClientConfig {
+        &self.config
+    }
+
+    /// Get the underlying transport, which can be cloned to share the
+    /// connection
+    pub fn rpc_client(&self) -> &Arc<R> {
         &self.rpc_client
     }
 
+    /// Commitment reads are made and transactions confirmed at
+    pub fn commitment(&self) -> CommitmentConfig {
+        self.rpc_client.commitment()
+    }
+
     /// Get the agent registry program ID
     pub fn agent_registry_program_id(&self) -> &Pubkey {
-        &self.agent_registry_program_id
+        &self.config.agent_registry_program_id
     }
 
     /// Get the MCP server registry program ID  
     pub fn mcp_server_registry_program_id(&self) -> &Pubkey {
-        &self.mcp_server_registry_program_id
+        &self.config.mcp_server_registry_program_id
+    }
+
+    /// Live subscriptions to this deployment's registry entries, over its
+    /// PubSub endpoint
+    pub fn subscriptions(&self) -> RegistrySubscriber {
+        RegistrySubscriber::new(&self.config)
     }
 
     /// Register a new agent
+    ///
+    /// Agents too large for a single transaction are registered in chunks
+    /// sent one after another; the signature of the last one is returned.
     pub async fn register_agent<S: Signer>(
         &self,
         signer: &S,
         args: AgentArgs,
     ) -> SdkResult<Signature> {
+        self.register_agent_sponsored(signer, signer, args).await
+    }
+
+    /// Register a new agent owned by `owner`, with `payer` funding the
+    /// entry's rent and the transaction fees. Both must sign.
+    pub async fn register_agent_sponsored<O: Signer, P: Signer>(
+        &self,
+        owner: &O,
+        payer: &P,
+        args: AgentArgs,
+    ) -> SdkResult<Signature> {
+        let plans = self.plan_register_agent(&owner.pubkey(), &payer.pubkey(), args)?;
+        self.send_plans(&plans, &[payer, owner]).await
+    }
+
+    /// [`Self::register_agent_sponsored`] as unsigned plans. Registrations
+    /// too large for one transaction give several plans, which must land in
+    /// order; each is sized for `payer` as fee payer and without a durable
+    /// nonce.
+    pub fn plan_register_agent(
+        &self,
+        owner: &Pubkey,
+        payer: &Pubkey,
+        args: AgentArgs,
+    ) -> SdkResult<Vec<TransactionPlan>> {
         let instruction = crate::agent::create_register_agent_instruction(
-            &self.agent_registry_program_id,
-            &signer.pubkey(),
+            &self.config.agent_registry_program_id,
+            owner,
+            payer,
+            args.clone(),
+        )?;
+        if fits_in_transaction(payer, std::slice::from_ref(&instruction)) {
+            return Ok(vec![TransactionPlan::new(vec![instruction], *payer)]);
+        }
+
+        let instructions = crate::agent::create_chunked_register_agent_instructions(
+            &self.config.agent_registry_program_id,
+            owner,
+            payer,
             args,
         )?;
+        Ok(pack_instructions(payer, instructions)?
+            .into_iter()
+            .map(|instructions| TransactionPlan::new(instructions, *payer))
+            .collect())
+    }
 
-        self.send_and_confirm_transaction(signer, vec![instruction])
-            .await
+    /// Register a new agent, paying the registry's registration fee from
+    /// `owner_token_account`. `payer` funds the entry's rent and the
+    /// transaction fee; it may be the owner.
+    pub async fn register_agent_with_token<O: Signer, P: Signer>(
+        &self,
+        owner: &O,
+        payer: &P,
+        owner_token_account: &Pubkey,
+        args: AgentArgs,
+    ) -> SdkResult<Signature> {
+        let plan = self
+            .plan_register_agent_with_token(
+                &owner.pubkey(),
+                &payer.pubkey(),
+                owner_token_account,
+                args,
+            )
+            .await?;
+        self.send_plan(&plan, &[payer, owner]).await
+    }
+
+    /// [`Self::register_agent_with_token`] as an unsigned plan, with `payer`
+    /// paying the fee. The registration must fit in one transaction.
+    pub async fn plan_register_agent_with_token(
+        &self,
+        owner: &Pubkey,
+        payer: &Pubkey,
+        owner_token_account: &Pubkey,
+        args: AgentArgs,
+    ) -> SdkResult<TransactionPlan> {
+        let config = self
+            .get_agent_registry_config()
+            .await?
+            .ok_or(SdkError::AccountNotFound)?;
+
+        let instruction = crate::agent::create_register_agent_with_token_instruction(
+            &self.config.agent_registry_program_id,
+            owner,
+            payer,
+            owner_token_account,
+            &config.params.accepted_mint,
+            args,
+        )?;
+        Ok(TransactionPlan::new(vec![instruction], *payer))
     }
 
     /// Update an existing agent
@@ -85,15 +315,41 @@ impl SolanaAiRegistriesClient {
         agent_id: &str,
         patch: AgentPatch,
     ) -> SdkResult<Signature> {
+        self.update_agent_sponsored(signer, signer, agent_id, patch)
+            .await
+    }
+
+    /// Update an existing agent, with `payer` funding any growth of the
+    /// entry and the transaction fee
+    pub async fn update_agent_sponsored<O: Signer, P: Signer>(
+        &self,
+        owner: &O,
+        payer: &P,
+        agent_id: &str,
+        patch: AgentPatch,
+    ) -> SdkResult<Signature> {
+        let plan = self.plan_update_agent(&owner.pubkey(), &payer.pubkey(), agent_id, patch)?;
+        self.send_plan(&plan, &[payer, owner]).await
+    }
+
+    /// [`Self::update_agent_sponsored`] as an unsigned plan, with `payer`
+    /// paying the fee
+    pub fn plan_update_agent(
+        &self,
+        owner: &Pubkey,
+        payer: &Pubkey,
+        agent_id: &str,
+        patch: AgentPatch,
+    ) -> SdkResult<TransactionPlan> {
         let instruction = crate::agent::create_update_agent_instruction(
-            &self.agent_registry_program_id,
-            &signer.pubkey(),
+            &self.config.agent_registry_program_id,
+            owner,
+            payer,
             agent_id,
             patch,
+            None,
         )?;
-
-        self.send_and_confirm_transaction(signer, vec![instruction])
-            .await
+        Ok(TransactionPlan::new(vec![instruction], *payer))
     }
 
     /// Update agent status
@@ -103,15 +359,25 @@ impl SolanaAiRegistriesClient {
         agent_id: &str,
         status: u8,
     ) -> SdkResult<Signature> {
+        let plan = self.plan_update_agent_status(&signer.pubkey(), agent_id, status)?;
+        self.send_plan(&plan, &[signer]).await
+    }
+
+    /// [`Self::update_agent_status`] as an unsigned plan, with `owner` paying the fee
+    pub fn plan_update_agent_status(
+        &self,
+        owner: &Pubkey,
+        agent_id: &str,
+        status: u8,
+    ) -> SdkResult<TransactionPlan> {
         let instruction = crate::agent::create_update_agent_status_instruction(
-            &self.agent_registry_program_id,
-            &signer.pubkey(),
+            &self.config.agent_registry_program_id,
+            owner,
             agent_id,
             status,
+            None,
         )?;
-
-        self.send_and_confirm_transaction(signer, vec![instruction])
-            .await
+        Ok(TransactionPlan::new(vec![instruction], *owner))
     }
 
     /// Deregister an agent
@@ -120,51 +386,288 @@ impl SolanaAiRegistriesClient {
         signer: &S,
         agent_id: &str,
     ) -> SdkResult<Signature> {
+        let plan = self.plan_deregister_agent(&signer.pubkey(), agent_id)?;
+        self.send_plan(&plan, &[signer]).await
+    }
+
+    /// [`Self::deregister_agent`] as an unsigned plan, with `owner` paying the fee
+    pub fn plan_deregister_agent(
+        &self,
+        owner: &Pubkey,
+        agent_id: &str,
+    ) -> SdkResult<TransactionPlan> {
         let instruction = crate::agent::create_deregister_agent_instruction(
-            &self.agent_registry_program_id,
-            &signer.pubkey(),
+            &self.config.agent_registry_program_id,
+            owner,
             agent_id,
+            None,
         )?;
+        Ok(TransactionPlan::new(vec![instruction], *owner))
+    }
 
-        self.send_and_confirm_transaction(signer, vec![instruction])
-            .await
+    /// Stake `amount` from `owner_token_account` on an agent, locked for
+    /// `lock_period` seconds
+    pub async fn stake_agent_tokens<S: Signer>(
+        &self,
+        owner: &S,
+        owner_token_account: &Pubkey,
+        agent_id: &str,
+        amount: u64,
+        lock_period: i64,
+    ) -> SdkResult<Signature> {
+        let plan = self.plan_stake_agent_tokens(
+            &owner.pubkey(),
+            owner_token_account,
+            agent_id,
+            amount,
+            lock_period,
+        )?;
+        self.send_plan(&plan, &[owner]).await
+    }
+
+    /// [`Self::stake_agent_tokens`] as an unsigned plan, with `owner` paying the fee
+    pub fn plan_stake_agent_tokens(
+        &self,
+        owner: &Pubkey,
+        owner_token_account: &Pubkey,
+        agent_id: &str,
+        amount: u64,
+        lock_period: i64,
+    ) -> SdkResult<TransactionPlan> {
+        let instruction = crate::agent::create_stake_tokens_instruction(
+            &self.config.agent_registry_program_id,
+            owner,
+            owner_token_account,
+            agent_id,
+            amount,
+            lock_period,
+            None,
+        )?;
+        Ok(TransactionPlan::new(vec![instruction], *owner))
+    }
+
+    /// Return `amount` of an agent's unlocked stake to `owner_token_account`
+    pub async fn unstake_agent_tokens<S: Signer>(
+        &self,
+        owner: &S,
+        owner_token_account: &Pubkey,
+        agent_id: &str,
+        amount: u64,
+    ) -> SdkResult<Signature> {
+        let plan =
+            self.plan_unstake_agent_tokens(&owner.pubkey(), owner_token_account, agent_id, amount)?;
+        self.send_plan(&plan, &[owner]).await
+    }
+
+    /// [`Self::unstake_agent_tokens`] as an unsigned plan, with `owner` paying the fee
+    pub fn plan_unstake_agent_tokens(
+        &self,
+        owner: &Pubkey,
+        owner_token_account: &Pubkey,
+        agent_id: &str,
+        amount: u64,
+    ) -> SdkResult<TransactionPlan> {
+        let instruction = crate::agent::create_unstake_tokens_instruction(
+            &self.config.agent_registry_program_id,
+            owner,
+            owner_token_account,
+            agent_id,
+            amount,
+            None,
+        )?;
+        Ok(TransactionPlan::new(vec![instruction], *owner))
     }
 
     /// Get an agent entry by ID
     pub async fn get_agent(&self, owner: &Pubkey, agent_id: &str) -> SdkResult<Option<AgentEntry>> {
-        let agent_pda =
-            crate::agent::derive_agent_pda(&self.agent_registry_program_id, owner, agent_id)?;
+        let agent_pda = crate::agent::derive_agent_pda(
+            &self.config.agent_registry_program_id,
+            owner,
+            agent_id,
+        )?;
 
-        match self.rpc_client.get_account(&agent_pda) {
-            Ok(account) => {
-                let entry = AgentEntry::try_from_account_data(&account.data)?;
-                Ok(Some(entry))
-            }
-            Err(solana_client::client_error::ClientError {
-                kind:
-                    solana_client::client_error::ClientErrorKind::RpcError(
-                        solana_client::rpc_request::RpcError::RpcResponseError { .. },
-                    ),
-                ..
-            }) => Ok(None), // Account not found
-            Err(e) => Err(SdkError::ClientError(e)),
-        }
+        self.read_entry(&agent_pda)
+            .await?
+            .map(|data| AgentEntry::try_from_account_data(&data))
+            .transpose()
+    }
+
+    /// Get every agent entry with its address. With a cache attached, the
+    /// entries are cached as of a slot read before the scan.
+    pub async fn list_agents(&self) -> SdkResult<Vec<(Pubkey, AgentEntry)>> {
+        self.list_entries(
+            &self.config.agent_registry_program_id,
+            AgentEntry::try_from_account_data,
+        )
+        .await
+    }
+
+    /// Get the fixed-size prefix of an agent entry (header, status and token
+    /// and reputation fields). Only the prefix bytes are fetched, so this is
+    /// cheaper than [`Self::get_agent`] for dashboards that poll metrics.
+    pub async fn get_agent_prefix(
+        &self,
+        owner: &Pubkey,
+        agent_id: &str,
+    ) -> SdkResult<Option<AgentEntryPrefix>> {
+        let agent_pda = crate::agent::derive_agent_pda(
+            &self.config.agent_registry_program_id,
+            owner,
+            agent_id,
+        )?;
+
+        self.rpc_client
+            .get_account_prefix(&agent_pda, AgentEntryPrefix::LEN)
+            .await?
+            .map(|data| AgentEntryPrefix::from_account_data(&data))
+            .transpose()
+    }
+
+    /// Fetch the overflow skill accounts linked to an agent, in index order
+    pub async fn get_agent_skill_accounts(
+        &self,
+        owner: &Pubkey,
+        agent_id: &str,
+    ) -> SdkResult<Vec<AgentSkillAccount>> {
+        let program_id = &self.config.agent_registry_program_id;
+        let agent_pda = crate::agent::derive_agent_pda(program_id, owner, agent_id)?;
+        let addresses: Vec<Pubkey> = (0..MAX_SKILL_ACCOUNTS as u8)
+            .map(|index| crate::agent::derive_skill_account_pda(program_id, &agent_pda, index))
+            .collect();
+
+        let accounts = self.rpc_client.get_multiple_accounts(&addresses).await?;
+
+        // Indices are kept contiguous on-chain, so the first gap ends the
+        // list; an address that was only sent lamports is still a gap
+        accounts
+            .into_iter()
+            .map_while(|account| account.filter(|account| account.owner == *program_id))
+            .map(|account| AgentSkillAccount::try_from_account_data(&account.data))
+            .collect()
+    }
+
+    /// Get an agent entry with the skills from its overflow skill accounts
+    /// appended after the inline ones
+    pub async fn get_agent_with_skills(
+        &self,
+        owner: &Pubkey,
+        agent_id: &str,
+    ) -> SdkResult<Option<AgentEntry>> {
+        let (entry, overflow) = futures::try_join!(
+            self.get_agent(owner, agent_id),
+            self.get_agent_skill_accounts(owner, agent_id),
+        )?;
+        let Some(mut entry) = entry else {
+            return Ok(None);
+        };
+
+        entry
+            .skills
+            .extend(overflow.into_iter().map(|account| account.skill));
+        Ok(Some(entry))
     }
 
     /// Register a new MCP server
+    ///
+    /// Servers too large for a single transaction are registered in chunks
+    /// sent one after another; the signature of the last one is returned.
     pub async fn register_mcp_server<S: Signer>(
         &self,
         signer: &S,
         args: McpServerArgs,
     ) -> SdkResult<Signature> {
+        self.register_mcp_server_sponsored(signer, signer, args)
+            .await
+    }
+
+    /// Register a new MCP server owned by `owner`, with `payer` funding the
+    /// entry's rent and the transaction fees. Both must sign.
+    pub async fn register_mcp_server_sponsored<O: Signer, P: Signer>(
+        &self,
+        owner: &O,
+        payer: &P,
+        args: McpServerArgs,
+    ) -> SdkResult<Signature> {
+        let plans = self.plan_register_mcp_server(&owner.pubkey(), &payer.pubkey(), args)?;
+        self.send_plans(&plans, &[payer, owner]).await
+    }
+
+    /// [`Self::register_mcp_server_sponsored`] as unsigned plans.
+    /// Registrations too large for one transaction give several plans, which
+    /// must land in order; each is sized for `payer` as fee payer and without
+    /// a durable nonce.
+    pub fn plan_register_mcp_server(
+        &self,
+        owner: &Pubkey,
+        payer: &Pubkey,
+        args: McpServerArgs,
+    ) -> SdkResult<Vec<TransactionPlan>> {
         let instruction = crate::mcp::create_register_mcp_server_instruction(
-            &self.mcp_server_registry_program_id,
-            &signer.pubkey(),
+            &self.config.mcp_server_registry_program_id,
+            owner,
+            payer,
+            args.clone(),
+        )?;
+        if fits_in_transaction(payer, std::slice::from_ref(&instruction)) {
+            return Ok(vec![TransactionPlan::new(vec![instruction], *payer)]);
+        }
+
+        let instructions = crate::mcp::create_chunked_register_mcp_server_instructions(
+            &self.config.mcp_server_registry_program_id,
+            owner,
+            payer,
             args,
         )?;
+        Ok(pack_instructions(payer, instructions)?
+            .into_iter()
+            .map(|instructions| TransactionPlan::new(instructions, *payer))
+            .collect())
+    }
 
-        self.send_and_confirm_transaction(signer, vec![instruction])
-            .await
+    /// Register a new MCP server, paying the registry's registration fee
+    /// from `owner_token_account`. `payer` funds the entry's rent and the
+    /// transaction fee; it may be the owner.
+    pub async fn register_mcp_server_with_token<O: Signer, P: Signer>(
+        &self,
+        owner: &O,
+        payer: &P,
+        owner_token_account: &Pubkey,
+        args: McpServerArgs,
+    ) -> SdkResult<Signature> {
+        let plan = self
+            .plan_register_mcp_server_with_token(
+                &owner.pubkey(),
+                &payer.pubkey(),
+                owner_token_account,
+                args,
+            )
+            .await?;
+        self.send_plan(&plan, &[payer, owner]).await
+    }
+
+    /// [`Self::register_mcp_server_with_token`] as an unsigned plan, with
+    /// `payer` paying the fee. The registration must fit in one transaction.
+    pub async fn plan_register_mcp_server_with_token(
+        &self,
+        owner: &Pubkey,
+        payer: &Pubkey,
+        owner_token_account: &Pubkey,
+        args: McpServerArgs,
+    ) -> SdkResult<TransactionPlan> {
+        let config = self
+            .get_mcp_registry_config()
+            .await?
+            .ok_or(SdkError::AccountNotFound)?;
+
+        let instruction = crate::mcp::create_register_mcp_server_with_token_instruction(
+            &self.config.mcp_server_registry_program_id,
+            owner,
+            payer,
+            owner_token_account,
+            &config.params.accepted_mint,
+            args,
+        )?;
+        Ok(TransactionPlan::new(vec![instruction], *payer))
     }
 
     /// Update an existing MCP server
@@ -174,15 +677,42 @@ impl SolanaAiRegistriesClient {
         server_id: &str,
         patch: McpServerPatch,
     ) -> SdkResult<Signature> {
+        self.update_mcp_server_sponsored(signer, signer, server_id, patch)
+            .await
+    }
+
+    /// Update an existing MCP server, with `payer` funding any growth of the
+    /// entry and the transaction fee
+    pub async fn update_mcp_server_sponsored<O: Signer, P: Signer>(
+        &self,
+        owner: &O,
+        payer: &P,
+        server_id: &str,
+        patch: McpServerPatch,
+    ) -> SdkResult<Signature> {
+        let plan =
+            self.plan_update_mcp_server(&owner.pubkey(), &payer.pubkey(), server_id, patch)?;
+        self.send_plan(&plan, &[payer, owner]).await
+    }
+
+    /// [`Self::update_mcp_server_sponsored`] as an unsigned plan, with
+    /// `payer` paying the fee
+    pub fn plan_update_mcp_server(
+        &self,
+        owner: &Pubkey,
+        payer: &Pubkey,
+        server_id: &str,
+        patch: McpServerPatch,
+    ) -> SdkResult<TransactionPlan> {
         let instruction = crate::mcp::create_update_mcp_server_instruction(
-            &self.mcp_server_registry_program_id,
-            &signer.pubkey(),
+            &self.config.mcp_server_registry_program_id,
+            owner,
+            payer,
             server_id,
             patch,
+            None,
         )?;
-
-        self.send_and_confirm_transaction(signer, vec![instruction])
-            .await
+        Ok(TransactionPlan::new(vec![instruction], *payer))
     }
 
     /// Update MCP server status
@@ -192,15 +722,25 @@ impl SolanaAiRegistriesClient {
         server_id: &str,
         status: u8,
     ) -> SdkResult<Signature> {
+        let plan = self.plan_update_mcp_server_status(&signer.pubkey(), server_id, status)?;
+        self.send_plan(&plan, &[signer]).await
+    }
+
+    /// [`Self::update_mcp_server_status`] as an unsigned plan, with `owner` paying the fee
+    pub fn plan_update_mcp_server_status(
+        &self,
+        owner: &Pubkey,
+        server_id: &str,
+        status: u8,
+    ) -> SdkResult<TransactionPlan> {
         let instruction = crate::mcp::create_update_mcp_server_status_instruction(
-            &self.mcp_server_registry_program_id,
-            &signer.pubkey(),
+            &self.config.mcp_server_registry_program_id,
+            owner,
             server_id,
             status,
+            None,
         )?;
-
-        self.send_and_confirm_transaction(signer, vec![instruction])
-            .await
+        Ok(TransactionPlan::new(vec![instruction], *owner))
     }
 
     /// Deregister an MCP server
@@ -209,14 +749,23 @@ impl SolanaAiRegistriesClient {
         signer: &S,
         server_id: &str,
     ) -> SdkResult<Signature> {
+        let plan = self.plan_deregister_mcp_server(&signer.pubkey(), server_id)?;
+        self.send_plan(&plan, &[signer]).await
+    }
+
+    /// [`Self::deregister_mcp_server`] as an unsigned plan, with `owner` paying the fee
+    pub fn plan_deregister_mcp_server(
+        &self,
+        owner: &Pubkey,
+        server_id: &str,
+    ) -> SdkResult<TransactionPlan> {
         let instruction = crate::mcp::create_deregister_mcp_server_instruction(
-            &self.mcp_server_registry_program_id,
-            &signer.pubkey(),
+            &self.config.mcp_server_registry_program_id,
+            owner,
             server_id,
+            None,
         )?;
-
-        self.send_and_confirm_transaction(signer, vec![instruction])
-            .await
+        Ok(TransactionPlan::new(vec![instruction], *owner))
     }
 
     /// Get an MCP server entry by ID
@@ -226,85 +775,2020 @@ impl SolanaAiRegistriesClient {
         server_id: &str,
     ) -> SdkResult<Option<McpServerEntry>> {
         let server_pda = crate::mcp::derive_mcp_server_pda(
-            &self.mcp_server_registry_program_id,
+            &self.config.mcp_server_registry_program_id,
             owner,
             server_id,
         )?;
 
-        match self.rpc_client.get_account(&server_pda) {
-            Ok(account) => {
-                let entry = McpServerEntry::try_from_account_data(&account.data)?;
-                Ok(Some(entry))
-            }
-            Err(solana_client::client_error::ClientError {
-                kind:
-                    solana_client::client_error::ClientErrorKind::RpcError(
-                        solana_client::rpc_request::RpcError::RpcResponseError { .. },
-                    ),
-                ..
-            }) => Ok(None), // Account not found
-            Err(e) => Err(SdkError::ClientError(e)),
-        }
+        self.read_entry(&server_pda)
+            .await?
+            .map(|data| McpServerEntry::try_from_account_data(&data))
+            .transpose()
     }
 
-    /// Check if an account exists
-    pub async fn account_exists(&self, pubkey: &Pubkey) -> SdkResult<bool> {
-        match self.rpc_client.get_account(pubkey) {
-            Ok(_) => Ok(true),
-            Err(solana_client::client_error::ClientError {
-                kind:
-                    solana_client::client_error::ClientErrorKind::RpcError(
-                        solana_client::rpc_request::RpcError::RpcResponseError { .. },
-                    ),
-                ..
-            }) => Ok(false),
-            Err(e) => Err(SdkError::ClientError(e)),
-        }
+    /// Get every MCP server entry with its address. With a cache attached,
+    /// the entries are cached as of a slot read before the scan.
+    pub async fn list_mcp_servers(&self) -> SdkResult<Vec<(Pubkey, McpServerEntry)>> {
+        self.list_entries(
+            &self.config.mcp_server_registry_program_id,
+            McpServerEntry::try_from_account_data,
+        )
+        .await
     }
 
-    /// Get account balance in lamports
-    pub async fn get_balance(&self, pubkey: &Pubkey) -> SdkResult<u64> {
+    /// Get the fixed-size prefix of an MCP server entry (header, status and
+    /// token, usage and fee fields). Only the prefix bytes are fetched.
+    pub async fn get_mcp_server_prefix(
+        &self,
+        owner: &Pubkey,
+        server_id: &str,
+    ) -> SdkResult<Option<McpServerEntryPrefix>> {
+        let server_pda = crate::mcp::derive_mcp_server_pda(
+            &self.config.mcp_server_registry_program_id,
+            owner,
+            server_id,
+        )?;
+
         self.rpc_client
-            .get_balance(pubkey)
-            .map_err(SdkError::ClientError)
+            .get_account_prefix(&server_pda, McpServerEntryPrefix::LEN)
+            .await?
+            .map(|data| McpServerEntryPrefix::from_account_data(&data))
+            .transpose()
     }
 
-    /// Send and confirm a transaction
-    async fn send_and_confirm_transaction<S: Signer>(
+    /// Create the fee vault of an MCP server, for the registry's accepted mint
+    pub async fn initialize_mcp_server_fee_vault<S: Signer>(
         &self,
-        signer: &S,
-        instructions: Vec<solana_sdk::instruction::Instruction>,
+        owner: &S,
+        server_id: &str,
     ) -> SdkResult<Signature> {
-        // Get recent blockhash
-        let recent_blockhash = self
-            .rpc_client
-            .get_latest_blockhash()
-            .map_err(SdkError::ClientError)?;
-
-        // Create transaction
-        let transaction = Transaction::new_signed_with_payer(
-            &instructions,
-            Some(&signer.pubkey()),
-            &[signer],
-            recent_blockhash,
-        );
-
-        // Send and confirm transaction
-        self.rpc_client
-            .send_and_confirm_transaction_with_spinner(&transaction)
-            .map_err(SdkError::ClientError)
+        let plan = self
+            .plan_initialize_mcp_server_fee_vault(&owner.pubkey(), server_id)
+            .await?;
+        self.send_plan(&plan, &[owner]).await
     }
 
-    /// Get the minimum rent exemption for an account of the given size
-    pub async fn get_minimum_rent_exemption(&self, size: usize) -> SdkResult<u64> {
-        self.rpc_client
-            .get_minimum_balance_for_rent_exemption(size)
-            .map_err(SdkError::ClientError)
+    /// [`Self::initialize_mcp_server_fee_vault`] as an unsigned plan, with `owner` paying the fee
+    pub async fn plan_initialize_mcp_server_fee_vault(
+        &self,
+        owner: &Pubkey,
+        server_id: &str,
+    ) -> SdkResult<TransactionPlan> {
+        let config = self
+            .get_mcp_registry_config()
+            .await?
+            .ok_or(SdkError::AccountNotFound)?;
+
+        let instruction = crate::mcp::create_initialize_server_fee_vault_instruction(
+            &self.config.mcp_server_registry_program_id,
+            owner,
+            owner,
+            server_id,
+            &config.params.accepted_mint,
+        )?;
+        Ok(TransactionPlan::new(vec![instruction], *owner))
     }
-}
 
-impl Default for SolanaAiRegistriesClient {
-    fn default() -> Self {
+    /// Withdraw an MCP server's pending fees to `owner_token_account`, paying
+    /// its revenue split recipients first. `recipient_token_accounts` holds
+    /// one token account per split recipient, in table order. A recipient
+    /// whose account is closed or frozen is skipped and its share stays in
+    /// the fee vault, owed to it until a later withdrawal.
+    pub async fn withdraw_mcp_pending_fees<S: Signer>(
+        &self,
+        owner: &S,
+        server_id: &str,
+        owner_token_account: &Pubkey,
+        recipient_token_accounts: &[Pubkey],
+    ) -> SdkResult<Signature> {
+        let plan = self.plan_withdraw_mcp_pending_fees(
+            &owner.pubkey(),
+            server_id,
+            owner_token_account,
+            recipient_token_accounts,
+        )?;
+        self.send_plan(&plan, &[owner]).await
+    }
+
+    /// [`Self::withdraw_mcp_pending_fees`] as an unsigned plan, with `owner` paying the fee
+    pub fn plan_withdraw_mcp_pending_fees(
+        &self,
+        owner: &Pubkey,
+        server_id: &str,
+        owner_token_account: &Pubkey,
+        recipient_token_accounts: &[Pubkey],
+    ) -> SdkResult<TransactionPlan> {
+        let instruction = crate::mcp::create_withdraw_pending_fees_instruction(
+            &self.config.mcp_server_registry_program_id,
+            owner,
+            server_id,
+            owner_token_account,
+            recipient_token_accounts,
+            None,
+        )?;
+        Ok(TransactionPlan::new(vec![instruction], *owner))
+    }
+
+    /// Compare an MCP server's pending fees against its fee vault balance
+    pub async fn reconcile_mcp_server_fees(
+        &self,
+        owner: &Pubkey,
+        server_id: &str,
+    ) -> SdkResult<FeeReconciliation> {
+        let server_pda = crate::mcp::derive_mcp_server_pda(
+            &self.config.mcp_server_registry_program_id,
+            owner,
+            server_id,
+        )?;
+        self.reconcile_mcp_fees(&[server_pda])
+            .await?
+            .pop()
+            .ok_or(SdkError::AccountNotFound)
+    }
+
+    /// Compare the pending fees of several MCP server entries against their
+    /// fee vault balances, one report per entry in input order
+    pub async fn reconcile_mcp_fees(
+        &self,
+        server_entries: &[Pubkey],
+    ) -> SdkResult<Vec<FeeReconciliation>> {
+        let fee_vaults: Vec<Pubkey> = server_entries
+            .iter()
+            .map(|entry| {
+                crate::mcp::derive_server_fee_vault_pda(
+                    &self.config.mcp_server_registry_program_id,
+                    entry,
+                )
+            })
+            .collect();
+        let addresses: Vec<Pubkey> = server_entries.iter().chain(&fee_vaults).copied().collect();
+
+        let accounts = self.rpc_client.get_multiple_accounts(&addresses).await?;
+        let (entry_accounts, vault_accounts) = accounts.split_at(server_entries.len());
+
+        server_entries
+            .iter()
+            .zip(&fee_vaults)
+            .zip(entry_accounts.iter().zip(vault_accounts))
+            .map(
+                |((server_entry, fee_vault), (entry_account, vault_account))| {
+                    let entry_account = entry_account.as_ref().ok_or(SdkError::AccountNotFound)?;
+                    let prefix = McpServerEntryPrefix::from_account_data(&entry_account.data)?;
+                    let vault_balance = vault_account
+                        .as_ref()
+                        .map(|account| {
+                            spl_token::state::Account::unpack(&account.data)
+                                .map(|vault| vault.amount)
+                                .map_err(|_| SdkError::InvalidAccountData)
+                        })
+                        .transpose()?;
+
+                    Ok(FeeReconciliation {
+                        server_entry: *server_entry,
+                        fee_vault: *fee_vault,
+                        pending_fees: prefix.economics.pending_fees,
+                        vault_balance,
+                    })
+                },
+            )
+            .collect()
+    }
+
+    /// Fetch the overflow tool accounts linked to an MCP server, in index order
+    pub async fn get_mcp_server_tool_accounts(
+        &self,
+        owner: &Pubkey,
+        server_id: &str,
+    ) -> SdkResult<Vec<McpToolAccount>> {
+        let program_id = &self.config.mcp_server_registry_program_id;
+        let server_pda = crate::mcp::derive_mcp_server_pda(program_id, owner, server_id)?;
+        let addresses: Vec<Pubkey> = (0..MAX_TOOL_ACCOUNTS as u8)
+            .map(|index| crate::mcp::derive_tool_account_pda(program_id, &server_pda, index))
+            .collect();
+
+        let accounts = self.rpc_client.get_multiple_accounts(&addresses).await?;
+
+        // Indices are kept contiguous on-chain, so the first gap ends the
+        // list; an address that was only sent lamports is still a gap
+        accounts
+            .into_iter()
+            .map_while(|account| account.filter(|account| account.owner == *program_id))
+            .map(|account| McpToolAccount::try_from_account_data(&account.data))
+            .collect()
+    }
+
+    /// Get an MCP server entry with the tools from its overflow tool accounts
+    /// appended after the inline ones
+    pub async fn get_mcp_server_with_tools(
+        &self,
+        owner: &Pubkey,
+        server_id: &str,
+    ) -> SdkResult<Option<McpServerEntry>> {
+        let (entry, overflow) = futures::try_join!(
+            self.get_mcp_server(owner, server_id),
+            self.get_mcp_server_tool_accounts(owner, server_id),
+        )?;
+        let Some(mut entry) = entry else {
+            return Ok(None);
+        };
+
+        entry
+            .onchain_tool_definitions
+            .extend(overflow.into_iter().map(|account| account.tool));
+        Ok(Some(entry))
+    }
+
+    /// Read an entry, build a mutation pinned to its current `state_version`,
+    /// and retry from a fresh read if another writer changed the entry first.
+    ///
+    /// `build` receives the raw account data and the version it was read at;
+    /// the instructions it returns should carry that version as their
+    /// `expected_state_version`. Gives up after `max_attempts` conflicts.
+    pub async fn read_modify_write<S, F>(
+        &self,
+        signer: &S,
+        entry: &Pubkey,
+        max_attempts: u32,
+        mut build: F,
+    ) -> SdkResult<Signature>
+    where
+        S: Signer,
+        F: FnMut(&[u8], u64) -> SdkResult<Vec<Instruction>>,
+    {
+        let mut attempt = 0;
+        loop {
+            attempt += 1;
+
+            let account = self
+                .rpc_client
+                .get_account(entry)
+                .await?
+                .ok_or(SdkError::AccountNotFound)?;
+            let state_version = read_state_version(&account.data)?;
+            let instructions = build(&account.data, state_version)?;
+
+            let plan = TransactionPlan::new(instructions, signer.pubkey());
+            match self.send_plan(&plan, &[signer]).await {
+                Err(e) if e.is_state_version_mismatch() && attempt < max_attempts => continue,
+                result => return result,
+            }
+        }
+    }
+
+    /// Apply an agent patch only if the entry has not changed since it was read,
+    /// retrying on conflicts
+    pub async fn update_agent_with_retry<S, F>(
+        &self,
+        signer: &S,
+        agent_id: &str,
+        mut patch: F,
+    ) -> SdkResult<Signature>
+    where
+        S: Signer,
+        F: FnMut(&[u8]) -> SdkResult<AgentPatch>,
+    {
+        let program_id = self.config.agent_registry_program_id;
+        let owner = signer.pubkey();
+        let agent_pda = crate::agent::derive_agent_pda(&program_id, &owner, agent_id)?;
+
+        self.read_modify_write(
+            signer,
+            &agent_pda,
+            DEFAULT_READ_MODIFY_WRITE_ATTEMPTS,
+            |data, state_version| {
+                let instruction = crate::agent::create_update_agent_instruction(
+                    &program_id,
+                    &owner,
+                    &owner,
+                    agent_id,
+                    patch(data)?,
+                    Some(state_version),
+                )?;
+                Ok(vec![instruction])
+            },
+        )
+        .await
+    }
+
+    /// Apply an MCP server patch only if the entry has not changed since it was
+    /// read, retrying on conflicts
+    pub async fn update_mcp_server_with_retry<S, F>(
+        &self,
+        signer: &S,
+        server_id: &str,
+        mut patch: F,
+    ) -> SdkResult<Signature>
+    where
+        S: Signer,
+        F: FnMut(&[u8]) -> SdkResult<McpServerPatch>,
+    {
+        let program_id = self.config.mcp_server_registry_program_id;
+        let owner = signer.pubkey();
+        let server_pda = crate::mcp::derive_mcp_server_pda(&program_id, &owner, server_id)?;
+
+        self.read_modify_write(
+            signer,
+            &server_pda,
+            DEFAULT_READ_MODIFY_WRITE_ATTEMPTS,
+            |data, state_version| {
+                let instruction = crate::mcp::create_update_mcp_server_instruction(
+                    &program_id,
+                    &owner,
+                    &owner,
+                    server_id,
+                    patch(data)?,
+                    Some(state_version),
+                )?;
+                Ok(vec![instruction])
+            },
+        )
+        .await
+    }
+
+    /// Get the agent registry's config, or `None` before it is initialized
+    pub async fn get_agent_registry_config(&self) -> SdkResult<Option<RegistryConfig>> {
+        self.get_registry_config(&self.config.agent_registry_program_id)
+            .await
+    }
+
+    /// Get the MCP server registry's config, or `None` before it is initialized
+    pub async fn get_mcp_registry_config(&self) -> SdkResult<Option<RegistryConfig>> {
+        self.get_registry_config(&self.config.mcp_server_registry_program_id)
+            .await
+    }
+
+    /// Create the agent registry's config. `authority` must be the program's
+    /// upgrade authority; it becomes the config's admin and pays the rent.
+    pub async fn initialize_agent_registry_config<S: Signer>(
+        &self,
+        authority: &S,
+        params: RegistryConfigParams,
+    ) -> SdkResult<Signature> {
+        let plan = self.plan_initialize_agent_registry_config(&authority.pubkey(), params)?;
+        self.send_plan(&plan, &[authority]).await
+    }
+
+    /// [`Self::initialize_agent_registry_config`] as an unsigned plan, with `authority` paying the fee
+    pub fn plan_initialize_agent_registry_config(
+        &self,
+        authority: &Pubkey,
+        params: RegistryConfigParams,
+    ) -> SdkResult<TransactionPlan> {
+        let instruction = crate::agent::create_initialize_registry_config_instruction(
+            &self.config.agent_registry_program_id,
+            authority,
+            authority,
+            params,
+        )?;
+        Ok(TransactionPlan::new(vec![instruction], *authority))
+    }
+
+    /// Create the MCP server registry's config. `authority` must be the
+    /// program's upgrade authority; it becomes the config's admin and pays the rent.
+    pub async fn initialize_mcp_registry_config<S: Signer>(
+        &self,
+        authority: &S,
+        params: RegistryConfigParams,
+    ) -> SdkResult<Signature> {
+        let plan = self.plan_initialize_mcp_registry_config(&authority.pubkey(), params)?;
+        self.send_plan(&plan, &[authority]).await
+    }
+
+    /// [`Self::initialize_mcp_registry_config`] as an unsigned plan, with `authority` paying the fee
+    pub fn plan_initialize_mcp_registry_config(
+        &self,
+        authority: &Pubkey,
+        params: RegistryConfigParams,
+    ) -> SdkResult<TransactionPlan> {
+        let instruction = crate::mcp::create_initialize_registry_config_instruction(
+            &self.config.mcp_server_registry_program_id,
+            authority,
+            authority,
+            params,
+        )?;
+        Ok(TransactionPlan::new(vec![instruction], *authority))
+    }
+
+    /// Change the agent registry's config parameters
+    ///
+    /// `update` is applied to the current on-chain parameters, so callers
+    /// only touch the fields they mean to change. `admin` must be the
+    /// config's admin.
+    pub async fn update_agent_registry_config<S, F>(
+        &self,
+        admin: &S,
+        update: F,
+    ) -> SdkResult<Signature>
+    where
+        S: Signer,
+        F: FnOnce(&mut RegistryConfigParams),
+    {
+        let plan = self
+            .plan_update_agent_registry_config(&admin.pubkey(), update)
+            .await?;
+        self.send_plan(&plan, &[admin]).await
+    }
+
+    /// [`Self::update_agent_registry_config`] as an unsigned plan, with `admin`
+    /// paying the fee
+    pub async fn plan_update_agent_registry_config<F>(
+        &self,
+        admin: &Pubkey,
+        update: F,
+    ) -> SdkResult<TransactionPlan>
+    where
+        F: FnOnce(&mut RegistryConfigParams),
+    {
+        let mut params = self
+            .get_agent_registry_config()
+            .await?
+            .ok_or(SdkError::AccountNotFound)?
+            .params;
+        update(&mut params);
+
+        let instruction = crate::agent::create_update_registry_config_instruction(
+            &self.config.agent_registry_program_id,
+            admin,
+            params,
+            None,
+        )?;
+        Ok(TransactionPlan::new(vec![instruction], *admin))
+    }
+
+    /// Change the MCP server registry's config parameters
+    ///
+    /// `update` is applied to the current on-chain parameters, so callers
+    /// only touch the fields they mean to change. `admin` must be the
+    /// config's admin.
+    pub async fn update_mcp_registry_config<S, F>(
+        &self,
+        admin: &S,
+        update: F,
+    ) -> SdkResult<Signature>
+    where
+        S: Signer,
+        F: FnOnce(&mut RegistryConfigParams),
+    {
+        let plan = self
+            .plan_update_mcp_registry_config(&admin.pubkey(), update)
+            .await?;
+        self.send_plan(&plan, &[admin]).await
+    }
+
+    /// [`Self::update_mcp_registry_config`] as an unsigned plan, with `admin`
+    /// paying the fee
+    pub async fn plan_update_mcp_registry_config<F>(
+        &self,
+        admin: &Pubkey,
+        update: F,
+    ) -> SdkResult<TransactionPlan>
+    where
+        F: FnOnce(&mut RegistryConfigParams),
+    {
+        let mut params = self
+            .get_mcp_registry_config()
+            .await?
+            .ok_or(SdkError::AccountNotFound)?
+            .params;
+        update(&mut params);
+
+        let instruction = crate::mcp::create_update_registry_config_instruction(
+            &self.config.mcp_server_registry_program_id,
+            admin,
+            params,
+            None,
+        )?;
+        Ok(TransactionPlan::new(vec![instruction], *admin))
+    }
+
+    /// Hand the agent registry's config to a new admin, keeping its parameters
+    pub async fn transfer_agent_registry_admin<S: Signer>(
+        &self,
+        admin: &S,
+        new_admin: &Pubkey,
+    ) -> SdkResult<Signature> {
+        let plan = self
+            .plan_transfer_agent_registry_admin(&admin.pubkey(), new_admin)
+            .await?;
+        self.send_plan(&plan, &[admin]).await
+    }
+
+    /// [`Self::transfer_agent_registry_admin`] as an unsigned plan, with `admin` paying the fee
+    pub async fn plan_transfer_agent_registry_admin(
+        &self,
+        admin: &Pubkey,
+        new_admin: &Pubkey,
+    ) -> SdkResult<TransactionPlan> {
+        let config = self
+            .get_agent_registry_config()
+            .await?
+            .ok_or(SdkError::AccountNotFound)?;
+
+        let instruction = crate::agent::create_update_registry_config_instruction(
+            &self.config.agent_registry_program_id,
+            admin,
+            config.params,
+            Some(*new_admin),
+        )?;
+        Ok(TransactionPlan::new(vec![instruction], *admin))
+    }
+
+    /// Hand the MCP server registry's config to a new admin, keeping its parameters
+    pub async fn transfer_mcp_registry_admin<S: Signer>(
+        &self,
+        admin: &S,
+        new_admin: &Pubkey,
+    ) -> SdkResult<Signature> {
+        let plan = self
+            .plan_transfer_mcp_registry_admin(&admin.pubkey(), new_admin)
+            .await?;
+        self.send_plan(&plan, &[admin]).await
+    }
+
+    /// [`Self::transfer_mcp_registry_admin`] as an unsigned plan, with `admin` paying the fee
+    pub async fn plan_transfer_mcp_registry_admin(
+        &self,
+        admin: &Pubkey,
+        new_admin: &Pubkey,
+    ) -> SdkResult<TransactionPlan> {
+        let config = self
+            .get_mcp_registry_config()
+            .await?
+            .ok_or(SdkError::AccountNotFound)?;
+
+        let instruction = crate::mcp::create_update_registry_config_instruction(
+            &self.config.mcp_server_registry_program_id,
+            admin,
+            config.params,
+            Some(*new_admin),
+        )?;
+        Ok(TransactionPlan::new(vec![instruction], *admin))
+    }
+
+    /// Bring the agent registry to a usable state: create its config with
+    /// `params` if it does not exist yet, then its token vaults if they do
+    /// not exist yet. `authority` must be the program's upgrade authority (or,
+    /// once the config exists, its admin) and pays the rent.
+    ///
+    /// Steps that are already done are skipped, so this is safe to re-run.
+    /// Returns `None` when there was nothing left to do.
+    pub async fn bootstrap_agent_registry<S: Signer>(
+        &self,
+        authority: &S,
+        params: RegistryConfigParams,
+    ) -> SdkResult<Option<Signature>> {
+        match self
+            .plan_bootstrap_agent_registry(&authority.pubkey(), params)
+            .await?
+        {
+            Some(plan) => self.send_plan(&plan, &[authority]).await.map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// [`Self::bootstrap_agent_registry`] as an unsigned plan, with
+    /// `authority` paying the fee and rent, or `None` when there is nothing
+    /// left to do
+    pub async fn plan_bootstrap_agent_registry(
+        &self,
+        authority: &Pubkey,
+        params: RegistryConfigParams,
+    ) -> SdkResult<Option<TransactionPlan>> {
+        self.plan_bootstrap_registry(
+            &self.config.agent_registry_program_id,
+            authority,
+            params,
+            crate::agent::create_initialize_registry_config_instruction,
+            crate::agent::create_initialize_vaults_instruction,
+        )
+        .await
+    }
+
+    /// Bring the MCP server registry to a usable state: create its config
+    /// with `params` if it does not exist yet, then its token vaults if they
+    /// do not exist yet. `authority` must be the program's upgrade authority
+    /// (or, once the config exists, its admin) and pays the rent.
+    ///
+    /// Steps that are already done are skipped, so this is safe to re-run.
+    /// Returns `None` when there was nothing left to do.
+    pub async fn bootstrap_mcp_registry<S: Signer>(
+        &self,
+        authority: &S,
+        params: RegistryConfigParams,
+    ) -> SdkResult<Option<Signature>> {
+        match self
+            .plan_bootstrap_mcp_registry(&authority.pubkey(), params)
+            .await?
+        {
+            Some(plan) => self.send_plan(&plan, &[authority]).await.map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// [`Self::bootstrap_mcp_registry`] as an unsigned plan, with
+    /// `authority` paying the fee and rent, or `None` when there is nothing
+    /// left to do
+    pub async fn plan_bootstrap_mcp_registry(
+        &self,
+        authority: &Pubkey,
+        params: RegistryConfigParams,
+    ) -> SdkResult<Option<TransactionPlan>> {
+        self.plan_bootstrap_registry(
+            &self.config.mcp_server_registry_program_id,
+            authority,
+            params,
+            crate::mcp::create_initialize_registry_config_instruction,
+            crate::mcp::create_initialize_vaults_instruction,
+        )
+        .await
+    }
+
+    /// Create an address lookup table holding the registry's static
+    /// accounts (see [`registry_lookup_addresses`]), with `authority` able to
+    /// extend it and paying its rent. Returns the table's address; name it in
+    /// [`ClientConfig::lookup_table`] to send v0 transactions through it from
+    /// the next slot on.
+    pub async fn create_registry_lookup_table<S: Signer>(
+        &self,
+        authority: &S,
+    ) -> SdkResult<Pubkey> {
+        let (table, plans) = self
+            .plan_create_registry_lookup_table(&authority.pubkey())
+            .await?;
+        self.send_plans(&plans, &[authority]).await?;
+        Ok(table)
+    }
+
+    /// [`Self::create_registry_lookup_table`] as unsigned plans, to be sent
+    /// in order, with `authority` paying the fees and rent
+    pub async fn plan_create_registry_lookup_table(
+        &self,
+        authority: &Pubkey,
+    ) -> SdkResult<(Pubkey, Vec<TransactionPlan>)> {
+        let recent_slot = self.rpc_client.get_slot().await?;
+        let (create, table) = address_lookup_table::instruction::create_lookup_table(
+            *authority,
+            *authority,
+            recent_slot,
+        );
+
+        let mut plans = self.plan_extend_lookup_table(
+            authority,
+            &table,
+            registry_lookup_addresses(&self.config),
+        );
+        match plans.first_mut() {
+            Some(first) => {
+                let mut instructions = vec![create];
+                instructions.extend(first.instructions());
+                *first = TransactionPlan::new(instructions, *authority);
+            }
+            None => plans.push(TransactionPlan::new(vec![create], *authority)),
+        }
+        Ok((table, plans))
+    }
+
+    /// Add the registry static accounts `table` is missing, e.g. after the
+    /// token mint changed. `authority` must be the table's authority and
+    /// pays the rent. Returns `None` when the table was already complete.
+    pub async fn extend_registry_lookup_table<S: Signer>(
+        &self,
+        authority: &S,
+        table: &Pubkey,
+    ) -> SdkResult<Option<Signature>> {
+        let plans = self
+            .plan_extend_registry_lookup_table(&authority.pubkey(), table)
+            .await?;
+        if plans.is_empty() {
+            return Ok(None);
+        }
+        let signature = self.send_plans(&plans, &[authority]).await?;
+        if self.config.lookup_table.as_ref() == Some(table) {
+            *self.lock_lookup_table() = None;
+        }
+        Ok(Some(signature))
+    }
+
+    /// [`Self::extend_registry_lookup_table`] as unsigned plans, to be sent
+    /// in order, with `authority` paying the fees and rent; empty when the
+    /// table is complete
+    pub async fn plan_extend_registry_lookup_table(
+        &self,
+        authority: &Pubkey,
+        table: &Pubkey,
+    ) -> SdkResult<Vec<TransactionPlan>> {
+        let current = self
+            .get_lookup_table(table)
+            .await?
+            .ok_or(SdkError::AccountNotFound)?;
+        let missing = missing_addresses(&current, &registry_lookup_addresses(&self.config));
+        Ok(self.plan_extend_lookup_table(authority, table, missing))
+    }
+
+    /// Get an address lookup table, or `None` if it does not exist
+    pub async fn get_lookup_table(
+        &self,
+        address: &Pubkey,
+    ) -> SdkResult<Option<AddressLookupTableAccount>> {
+        self.rpc_client
+            .get_account(address)
+            .await?
+            .map(|account| decode_lookup_table(*address, &account))
+            .transpose()
+    }
+
+    /// The lookup tables transactions are compiled against: the one named in
+    /// [`ClientConfig::lookup_table`], or none, in which case transactions
+    /// are legacy ones. The table is fetched once and kept until
+    /// [`Self::extend_registry_lookup_table`] changes it or a plan needs
+    /// registry accounts the kept copy lacks.
+    pub async fn lookup_tables(&self) -> SdkResult<Vec<AddressLookupTableAccount>> {
+        let kept = self.lock_lookup_table().clone();
+        match kept {
+            Some(table) => Ok(vec![table]),
+            None => self.refresh_lookup_tables().await,
+        }
+    }
+
+    /// Fetch [`ClientConfig::lookup_table`] again, replacing the kept copy
+    pub async fn refresh_lookup_tables(&self) -> SdkResult<Vec<AddressLookupTableAccount>> {
+        let Some(address) = &self.config.lookup_table else {
+            return Ok(Vec::new());
+        };
+        let table = self
+            .get_lookup_table(address)
+            .await?
+            .ok_or(SdkError::AccountNotFound)?;
+        *self.lock_lookup_table() = Some(table.clone());
+        Ok(vec![table])
+    }
+
+    /// [`Self::lookup_tables`] to compile `plan` against, fetched again when
+    /// the plan uses registry static accounts the kept copy lacks, e.g.
+    /// after the table was extended by another client
+    async fn lookup_tables_for(
+        &self,
+        plan: &TransactionPlan,
+    ) -> SdkResult<Vec<AddressLookupTableAccount>> {
+        let lookup_tables = self.lookup_tables().await?;
+        let Some(table) = lookup_tables.first() else {
+            return Ok(lookup_tables);
+        };
+        let registry_addresses = registry_lookup_addresses(&self.config);
+        let misses = plan.instructions().iter().any(|instruction| {
+            instruction.accounts.iter().any(|meta| {
+                registry_addresses.contains(&meta.pubkey) && !table.addresses.contains(&meta.pubkey)
+            })
+        });
+        if misses {
+            return self.refresh_lookup_tables().await;
+        }
+        Ok(lookup_tables)
+    }
+
+    fn lock_lookup_table(&self) -> std::sync::MutexGuard<'_, Option<AddressLookupTableAccount>> {
+        self.lookup_table
+            .lock()
+            .expect("lookup table lock poisoned")
+    }
+
+    /// Set the revenue split of an agent. An empty `recipients` pays
+    /// everything to the owner.
+    pub async fn set_agent_revenue_split<S: Signer>(
+        &self,
+        owner: &S,
+        agent_id: &str,
+        recipients: Vec<SplitRecipient>,
+    ) -> SdkResult<Signature> {
+        let plan = self.plan_set_agent_revenue_split(&owner.pubkey(), agent_id, recipients)?;
+        self.send_plan(&plan, &[owner]).await
+    }
+
+    /// [`Self::set_agent_revenue_split`] as an unsigned plan, with `owner` paying the fee
+    pub fn plan_set_agent_revenue_split(
+        &self,
+        owner: &Pubkey,
+        agent_id: &str,
+        recipients: Vec<SplitRecipient>,
+    ) -> SdkResult<TransactionPlan> {
+        let instruction = crate::agent::create_set_revenue_split_instruction(
+            &self.config.agent_registry_program_id,
+            owner,
+            owner,
+            agent_id,
+            recipients,
+        )?;
+        Ok(TransactionPlan::new(vec![instruction], *owner))
+    }
+
+    /// Set the revenue split of an MCP server. An empty `recipients` pays
+    /// everything to the owner.
+    pub async fn set_mcp_revenue_split<S: Signer>(
+        &self,
+        owner: &S,
+        server_id: &str,
+        recipients: Vec<SplitRecipient>,
+    ) -> SdkResult<Signature> {
+        let plan = self.plan_set_mcp_revenue_split(&owner.pubkey(), server_id, recipients)?;
+        self.send_plan(&plan, &[owner]).await
+    }
+
+    /// [`Self::set_mcp_revenue_split`] as an unsigned plan, with `owner` paying the fee
+    pub fn plan_set_mcp_revenue_split(
+        &self,
+        owner: &Pubkey,
+        server_id: &str,
+        recipients: Vec<SplitRecipient>,
+    ) -> SdkResult<TransactionPlan> {
+        let instruction = crate::mcp::create_set_revenue_split_instruction(
+            &self.config.mcp_server_registry_program_id,
+            owner,
+            owner,
+            server_id,
+            recipients,
+        )?;
+        Ok(TransactionPlan::new(vec![instruction], *owner))
+    }
+
+    /// Get an agent's current service fees and any scheduled increase
+    pub async fn get_agent_fee_schedule(
+        &self,
+        owner: &Pubkey,
+        agent_id: &str,
+    ) -> SdkResult<FeeSchedule<AgentServiceFees>> {
+        let (prefix, now) = futures::try_join!(
+            self.get_agent_prefix(owner, agent_id),
+            self.get_cluster_time(),
+        )?;
+        let prefix = prefix.ok_or(SdkError::AccountNotFound)?;
+        Ok(prefix.economics.fee_schedule(now))
+    }
+
+    /// Get an MCP server's current usage fees and any scheduled increase
+    pub async fn get_mcp_fee_schedule(
+        &self,
+        owner: &Pubkey,
+        server_id: &str,
+    ) -> SdkResult<FeeSchedule<McpUsageFees>> {
+        let (prefix, now) = futures::try_join!(
+            self.get_mcp_server_prefix(owner, server_id),
+            self.get_cluster_time(),
+        )?;
+        let prefix = prefix.ok_or(SdkError::AccountNotFound)?;
+        Ok(prefix.economics.fee_schedule(now))
+    }
+
+    /// Set an MCP server's base fees, bulk discount and free tier. Fee
+    /// increases apply after the registry's notice period.
+    pub async fn configure_mcp_usage_fees<S: Signer>(
+        &self,
+        owner: &S,
+        server_id: &str,
+        fees: McpUsageFees,
+        free_tier: FreeTier,
+    ) -> SdkResult<Signature> {
+        let plan =
+            self.plan_configure_mcp_usage_fees(&owner.pubkey(), server_id, fees, free_tier)?;
+        self.send_plan(&plan, &[owner]).await
+    }
+
+    /// [`Self::configure_mcp_usage_fees`] as an unsigned plan, with `owner` paying the fee
+    pub fn plan_configure_mcp_usage_fees(
+        &self,
+        owner: &Pubkey,
+        server_id: &str,
+        fees: McpUsageFees,
+        free_tier: FreeTier,
+    ) -> SdkResult<TransactionPlan> {
+        let instruction = crate::mcp::create_configure_usage_fees_instruction(
+            &self.config.mcp_server_registry_program_id,
+            owner,
+            server_id,
+            fees,
+            free_tier,
+        )?;
+        Ok(TransactionPlan::new(vec![instruction], *owner))
+    }
+
+    /// Get `user`'s usage record on an MCP server, or `None` if they never
+    /// used it
+    pub async fn get_mcp_usage_record(
+        &self,
+        user: &Pubkey,
+        server_owner: &Pubkey,
+        server_id: &str,
+    ) -> SdkResult<Option<McpUsageRecord>> {
+        let program_id = &self.config.mcp_server_registry_program_id;
+        let server_pda = crate::mcp::derive_mcp_server_pda(program_id, server_owner, server_id)?;
+        self.rpc_client
+            .get_account(&crate::mcp::derive_usage_record_pda(
+                program_id,
+                &server_pda,
+                user,
+            ))
+            .await?
+            .filter(|account| account.owner == *program_id)
+            .map(|account| McpUsageRecord::try_from_account_data(&account.data))
+            .transpose()
+    }
+
+    /// Get the usage records of every user of an MCP server
+    pub async fn get_mcp_server_usage_records(
+        &self,
+        owner: &Pubkey,
+        server_id: &str,
+    ) -> SdkResult<Vec<McpUsageRecord>> {
+        let program_id = &self.config.mcp_server_registry_program_id;
+        let server_pda = crate::mcp::derive_mcp_server_pda(program_id, owner, server_id)?;
+        let filters = vec![
+            RpcFilterType::DataSize(McpUsageRecord::LEN as u64),
+            // server_entry follows the bump
+            RpcFilterType::Memcmp(Memcmp::new_base58_encoded(1, server_pda.as_ref())),
+        ];
+
+        self.rpc_client
+            .get_program_accounts(program_id, filters)
+            .await?
+            .into_iter()
+            .map(|(_, account)| McpUsageRecord::try_from_account_data(&account.data))
+            .collect()
+    }
+
+    /// Replace an MCP server's per-item prices and discount tiers. A table
+    /// that makes any use cost more applies after the registry's
+    /// fee-increase notice.
+    pub async fn set_mcp_usage_pricing<S: Signer>(
+        &self,
+        owner: &S,
+        server_id: &str,
+        item_prices: Vec<ItemPriceInput>,
+        discount_tiers: Vec<DiscountTier>,
+    ) -> SdkResult<Signature> {
+        let plan = self.plan_set_mcp_usage_pricing(
+            &owner.pubkey(),
+            server_id,
+            item_prices,
+            discount_tiers,
+        )?;
+        self.send_plan(&plan, &[owner]).await
+    }
+
+    /// [`Self::set_mcp_usage_pricing`] as an unsigned plan, with `owner` paying the fee
+    pub fn plan_set_mcp_usage_pricing(
+        &self,
+        owner: &Pubkey,
+        server_id: &str,
+        item_prices: Vec<ItemPriceInput>,
+        discount_tiers: Vec<DiscountTier>,
+    ) -> SdkResult<TransactionPlan> {
+        let instruction = crate::mcp::create_set_usage_pricing_instruction(
+            &self.config.mcp_server_registry_program_id,
+            owner,
+            owner,
+            server_id,
+            item_prices,
+            discount_tiers,
+        )?;
+        Ok(TransactionPlan::new(vec![instruction], *owner))
+    }
+
+    /// Pay for `count` uses of an MCP server item from `user_token_account`,
+    /// using calls left in the user's subscription first.
+    /// The fee is quoted from the server's on-chain prices and passed as the
+    /// most the user agrees to pay, so a price raised before the transaction
+    /// lands fails it instead of overcharging. A fresh idempotency key makes
+    /// the charge safe to resend.
+    #[allow(clippy::too_many_arguments)]
+    pub async fn record_mcp_usage<S: Signer>(
+        &self,
+        user: &S,
+        user_token_account: &Pubkey,
+        server_owner: &Pubkey,
+        server_id: &str,
+        usage_type: UsageType,
+        item_name: Option<&str>,
+        count: u32,
+    ) -> SdkResult<Signature> {
+        let plan = self
+            .plan_record_mcp_usage(
+                &user.pubkey(),
+                user_token_account,
+                server_owner,
+                server_id,
+                usage_type,
+                item_name,
+                count,
+            )
+            .await?;
+        self.send_plan(&plan, &[user]).await
+    }
+
+    /// [`Self::record_mcp_usage`] as an unsigned plan, with `user` paying the fee
+    #[allow(clippy::too_many_arguments)]
+    pub async fn plan_record_mcp_usage(
+        &self,
+        user: &Pubkey,
+        user_token_account: &Pubkey,
+        server_owner: &Pubkey,
+        server_id: &str,
+        usage_type: UsageType,
+        item_name: Option<&str>,
+        count: u32,
+    ) -> SdkResult<TransactionPlan> {
+        let quote = self
+            .estimate_mcp_usage_cost(
+                server_owner,
+                server_id,
+                Some(user),
+                usage_type,
+                item_name,
+                count,
+            )
+            .await?;
+        let instruction = crate::mcp::create_record_usage_instruction(
+            &self.config.mcp_server_registry_program_id,
+            user,
+            user_token_account,
+            server_owner,
+            server_id,
+            usage_type,
+            count,
+            item_name.map(str::to_string),
+            quote.total_fee,
+            new_idempotency_key(),
+        )?;
+        Ok(TransactionPlan::new(vec![instruction], *user))
+    }
+
+    /// Get an MCP server's pricing table, or `None` if it never set one
+    pub async fn get_mcp_pricing_table(
+        &self,
+        owner: &Pubkey,
+        server_id: &str,
+    ) -> SdkResult<Option<McpPricingTable>> {
+        let program_id = &self.config.mcp_server_registry_program_id;
+        let server_pda = crate::mcp::derive_mcp_server_pda(program_id, owner, server_id)?;
+        self.rpc_client
+            .get_account(&crate::mcp::derive_pricing_table_pda(
+                program_id,
+                &server_pda,
+            ))
+            .await?
+            .filter(|account| account.owner == *program_id)
+            .map(|account| McpPricingTable::try_from_account_data(&account.data))
+            .transpose()
+    }
+
+    /// Estimate what `count` uses of an MCP server item would cost, from the
+    /// server's on-chain base fees, pricing table and the registry's
+    /// protocol take-rate. With `caller`, their free-tier calls and calls
+    /// left in their subscription are deducted first.
+    ///
+    /// Every account involved is read in a single `getMultipleAccounts` call.
+    pub async fn estimate_mcp_usage_cost(
+        &self,
+        owner: &Pubkey,
+        server_id: &str,
+        caller: Option<&Pubkey>,
+        usage_type: UsageType,
+        item_name: Option<&str>,
+        count: u32,
+    ) -> SdkResult<McpUsageCostEstimate> {
+        let program_id = &self.config.mcp_server_registry_program_id;
+        let server_pda = crate::mcp::derive_mcp_server_pda(program_id, owner, server_id)?;
+        let mut addresses = vec![
+            server_pda,
+            crate::mcp::derive_pricing_table_pda(program_id, &server_pda),
+            derive_registry_config_pda(program_id),
+            solana_sdk::sysvar::clock::id(),
+        ];
+        if let Some(caller) = caller {
+            addresses.push(crate::mcp::derive_usage_record_pda(
+                program_id,
+                &server_pda,
+                caller,
+            ));
+            addresses.push(crate::mcp::derive_subscription_pda(
+                program_id,
+                &server_pda,
+                caller,
+            ));
+        }
+        let mut accounts = self
+            .rpc_client
+            .get_multiple_accounts(&addresses)
+            .await?
+            .into_iter();
+        let mut next = || accounts.next().flatten();
+
+        let prefix = McpServerEntryPrefix::from_account_data(
+            &next().ok_or(SdkError::AccountNotFound)?.data,
+        )?;
+        let pricing_table = next()
+            .filter(|account| account.owner == *program_id)
+            .map(|account| McpPricingTable::try_from_account_data(&account.data))
+            .transpose()?;
+        let protocol_fee_bps = next()
+            .map(|account| RegistryConfig::try_from_account_data(&account.data))
+            .transpose()?
+            .map_or(0, |config| config.params.protocol_fee_bps);
+        let now = decode_clock(&next().ok_or(SdkError::AccountNotFound)?)?;
+        let fees = prefix.economics.fee_schedule(now).current;
+        let pricing_table = pricing_table.map(|mut table| {
+            table.apply_due_price_change(now);
+            table
+        });
+        let coverage = match caller {
+            Some(_) => {
+                let free_tier = prefix.economics.free_tier();
+                let free_calls = next()
+                    .filter(|account| account.owner == *program_id)
+                    .map(|account| McpUsageRecord::try_from_account_data(&account.data))
+                    .transpose()?
+                    .map_or(free_tier.allowance(usage_type), |record| {
+                        record.free_calls_left(usage_type, &free_tier, now)
+                    })
+                    .min(count);
+                let subscription_calls = next()
+                    .filter(|account| account.owner == *program_id)
+                    .map(|account| McpSubscription::try_from_account_data(&account.data))
+                    .transpose()?
+                    .map_or(0, |subscription| {
+                        subscription.covered_calls(usage_type, count - free_calls, now)
+                    });
+                UsageCoverage {
+                    free_calls,
+                    subscription_calls,
+                }
+            }
+            None => UsageCoverage::default(),
+        };
+
+        estimate_usage_cost(
+            &fees,
+            pricing_table.as_ref(),
+            usage_type,
+            item_name,
+            count,
+            coverage,
+            protocol_fee_bps,
+        )
+    }
+
+    /// Create or update one of an MCP server's subscription plans. A higher
+    /// price, shorter period or fewer included calls apply after the
+    /// registry's fee-increase notice.
+    #[allow(clippy::too_many_arguments)]
+    pub async fn set_mcp_subscription_plan<S: Signer>(
+        &self,
+        owner: &S,
+        server_id: &str,
+        plan_id: u8,
+        price: u64,
+        period: i64,
+        included: SubscriptionQuota,
+        active: bool,
+    ) -> SdkResult<Signature> {
+        let plan = self.plan_set_mcp_subscription_plan(
+            &owner.pubkey(),
+            server_id,
+            plan_id,
+            price,
+            period,
+            included,
+            active,
+        )?;
+        self.send_plan(&plan, &[owner]).await
+    }
+
+    /// [`Self::set_mcp_subscription_plan`] as an unsigned plan, with `owner` paying the fee
+    #[allow(clippy::too_many_arguments)]
+    pub fn plan_set_mcp_subscription_plan(
+        &self,
+        owner: &Pubkey,
+        server_id: &str,
+        plan_id: u8,
+        price: u64,
+        period: i64,
+        included: SubscriptionQuota,
+        active: bool,
+    ) -> SdkResult<TransactionPlan> {
+        let instruction = crate::mcp::create_set_subscription_plan_instruction(
+            &self.config.mcp_server_registry_program_id,
+            owner,
+            owner,
+            server_id,
+            plan_id,
+            price,
+            period,
+            included,
+            active,
+        )?;
+        Ok(TransactionPlan::new(vec![instruction], *owner))
+    }
+
+    /// Buy one period of an MCP server's subscription plan from
+    /// `user_token_account`. The plan's current price is passed as the most
+    /// the user agrees to pay.
+    pub async fn subscribe_mcp_server<S: Signer>(
+        &self,
+        user: &S,
+        user_token_account: &Pubkey,
+        server_owner: &Pubkey,
+        server_id: &str,
+        plan_id: u8,
+    ) -> SdkResult<Signature> {
+        let plan = self
+            .plan_subscribe_mcp_server(
+                &user.pubkey(),
+                user_token_account,
+                server_owner,
+                server_id,
+                plan_id,
+            )
+            .await?;
+        self.send_plan(&plan, &[user]).await
+    }
+
+    /// [`Self::subscribe_mcp_server`] as an unsigned plan, with `user` paying the fee
+    pub async fn plan_subscribe_mcp_server(
+        &self,
+        user: &Pubkey,
+        user_token_account: &Pubkey,
+        server_owner: &Pubkey,
+        server_id: &str,
+        plan_id: u8,
+    ) -> SdkResult<TransactionPlan> {
+        let (plan, now) = futures::try_join!(
+            self.get_mcp_subscription_plan(server_owner, server_id, plan_id),
+            self.get_cluster_time(),
+        )?;
+        let mut plan = plan.ok_or(SdkError::AccountNotFound)?;
+        plan.apply_due_terms_change(now);
+        let instruction = crate::mcp::create_subscribe_instruction(
+            &self.config.mcp_server_registry_program_id,
+            user,
+            user_token_account,
+            server_owner,
+            server_id,
+            plan_id,
+            plan.price,
+        )?;
+        Ok(TransactionPlan::new(vec![instruction], *user))
+    }
+
+    /// Pay for another period of the user's subscription to an MCP server at
+    /// its plan's current price
+    pub async fn renew_mcp_subscription<S: Signer>(
+        &self,
+        user: &S,
+        user_token_account: &Pubkey,
+        server_owner: &Pubkey,
+        server_id: &str,
+    ) -> SdkResult<Signature> {
+        let plan = self
+            .plan_renew_mcp_subscription(
+                &user.pubkey(),
+                user_token_account,
+                server_owner,
+                server_id,
+            )
+            .await?;
+        self.send_plan(&plan, &[user]).await
+    }
+
+    /// [`Self::renew_mcp_subscription`] as an unsigned plan, with `user` paying the fee
+    pub async fn plan_renew_mcp_subscription(
+        &self,
+        user: &Pubkey,
+        user_token_account: &Pubkey,
+        server_owner: &Pubkey,
+        server_id: &str,
+    ) -> SdkResult<TransactionPlan> {
+        let subscription = self
+            .get_mcp_subscription(user, server_owner, server_id)
+            .await?
+            .ok_or(SdkError::AccountNotFound)?;
+        let (plan, now) = futures::try_join!(
+            self.get_mcp_subscription_plan(server_owner, server_id, subscription.plan_id),
+            self.get_cluster_time(),
+        )?;
+        let mut plan = plan.ok_or(SdkError::AccountNotFound)?;
+        plan.apply_due_terms_change(now);
+        let instruction = crate::mcp::create_renew_subscription_instruction(
+            &self.config.mcp_server_registry_program_id,
+            user,
+            user_token_account,
+            server_owner,
+            server_id,
+            plan.plan_id,
+            plan.price,
+        )?;
+        Ok(TransactionPlan::new(vec![instruction], *user))
+    }
+
+    /// Close the user's subscription to an MCP server, returning its rent.
+    /// Paid periods are not refunded.
+    pub async fn cancel_mcp_subscription<S: Signer>(
+        &self,
+        user: &S,
+        server_owner: &Pubkey,
+        server_id: &str,
+    ) -> SdkResult<Signature> {
+        let plan = self.plan_cancel_mcp_subscription(&user.pubkey(), server_owner, server_id)?;
+        self.send_plan(&plan, &[user]).await
+    }
+
+    /// [`Self::cancel_mcp_subscription`] as an unsigned plan, with `user` paying the fee
+    pub fn plan_cancel_mcp_subscription(
+        &self,
+        user: &Pubkey,
+        server_owner: &Pubkey,
+        server_id: &str,
+    ) -> SdkResult<TransactionPlan> {
+        let instruction = crate::mcp::create_cancel_subscription_instruction(
+            &self.config.mcp_server_registry_program_id,
+            user,
+            server_owner,
+            server_id,
+        )?;
+        Ok(TransactionPlan::new(vec![instruction], *user))
+    }
+
+    /// Get one of an MCP server's subscription plans, or `None` if it was
+    /// never created
+    pub async fn get_mcp_subscription_plan(
+        &self,
+        owner: &Pubkey,
+        server_id: &str,
+        plan_id: u8,
+    ) -> SdkResult<Option<McpSubscriptionPlan>> {
+        let program_id = &self.config.mcp_server_registry_program_id;
+        let server_pda = crate::mcp::derive_mcp_server_pda(program_id, owner, server_id)?;
+        self.rpc_client
+            .get_account(&crate::mcp::derive_subscription_plan_pda(
+                program_id,
+                &server_pda,
+                plan_id,
+            ))
+            .await?
+            .filter(|account| account.owner == *program_id)
+            .map(|account| McpSubscriptionPlan::try_from_account_data(&account.data))
+            .transpose()
+    }
+
+    /// Get `subscriber`'s subscription to an MCP server, or `None` if they
+    /// hold none
+    pub async fn get_mcp_subscription(
+        &self,
+        subscriber: &Pubkey,
+        server_owner: &Pubkey,
+        server_id: &str,
+    ) -> SdkResult<Option<McpSubscription>> {
+        let program_id = &self.config.mcp_server_registry_program_id;
+        let server_pda = crate::mcp::derive_mcp_server_pda(program_id, server_owner, server_id)?;
+        self.rpc_client
+            .get_account(&crate::mcp::derive_subscription_pda(
+                program_id,
+                &server_pda,
+                subscriber,
+            ))
+            .await?
+            .filter(|account| account.owner == *program_id)
+            .map(|account| McpSubscription::try_from_account_data(&account.data))
+            .transpose()
+    }
+
+    /// Get an agent's revenue split, or `None` if it never set one
+    pub async fn get_agent_revenue_split(
+        &self,
+        owner: &Pubkey,
+        agent_id: &str,
+    ) -> SdkResult<Option<RevenueSplit>> {
+        let program_id = &self.config.agent_registry_program_id;
+        let agent_pda = crate::agent::derive_agent_pda(program_id, owner, agent_id)?;
+        self.get_revenue_split(program_id, &agent_pda).await
+    }
+
+    /// Get an MCP server's revenue split, or `None` if it never set one
+    pub async fn get_mcp_revenue_split(
+        &self,
+        owner: &Pubkey,
+        server_id: &str,
+    ) -> SdkResult<Option<RevenueSplit>> {
+        let program_id = &self.config.mcp_server_registry_program_id;
+        let server_pda = crate::mcp::derive_mcp_server_pda(program_id, owner, server_id)?;
+        self.get_revenue_split(program_id, &server_pda).await
+    }
+
+    /// Preview how service earnings of `earnings` would be paid out for an
+    /// agent: the protocol take-rate, each split recipient, then the owner
+    pub async fn preview_agent_payout(
+        &self,
+        owner: &Pubkey,
+        agent_id: &str,
+        earnings: u64,
+    ) -> SdkResult<PayoutPreview> {
+        let (config, split) = futures::try_join!(
+            self.get_agent_registry_config(),
+            self.get_agent_revenue_split(owner, agent_id),
+        )?;
+        let config = config.ok_or(SdkError::AccountNotFound)?;
+        let recipients = split.as_ref().map_or(&[][..], |s| s.active_recipients());
+
+        Ok(preview_payout(
+            earnings,
+            config.params.protocol_fee_bps,
+            recipients,
+        ))
+    }
+
+    /// Preview how an MCP server's pending fees would be paid out by
+    /// `WithdrawPendingFees`. The protocol take-rate was already taken when
+    /// the fees were collected, so only the split applies.
+    pub async fn preview_mcp_withdrawal(
+        &self,
+        owner: &Pubkey,
+        server_id: &str,
+    ) -> SdkResult<PayoutPreview> {
+        let (prefix, split) = futures::try_join!(
+            self.get_mcp_server_prefix(owner, server_id),
+            self.get_mcp_revenue_split(owner, server_id),
+        )?;
+        let prefix = prefix.ok_or(SdkError::AccountNotFound)?;
+        let recipients = split.as_ref().map_or(&[][..], |s| s.active_recipients());
+
+        Ok(preview_payout(prefix.economics.pending_fees, 0, recipients))
+    }
+
+    /// Move protocol revenue from the agent registry's treasury vault to
+    /// `destination`, a token account of the config's treasury wallet
+    pub async fn withdraw_agent_treasury<S: Signer>(
+        &self,
+        admin: &S,
+        destination: &Pubkey,
+        amount: u64,
+    ) -> SdkResult<Signature> {
+        let plan = self.plan_withdraw_agent_treasury(&admin.pubkey(), destination, amount)?;
+        self.send_plan(&plan, &[admin]).await
+    }
+
+    /// [`Self::withdraw_agent_treasury`] as an unsigned plan, with `admin` paying the fee
+    pub fn plan_withdraw_agent_treasury(
+        &self,
+        admin: &Pubkey,
+        destination: &Pubkey,
+        amount: u64,
+    ) -> SdkResult<TransactionPlan> {
+        let instruction = crate::agent::create_withdraw_treasury_instruction(
+            &self.config.agent_registry_program_id,
+            admin,
+            destination,
+            amount,
+        )?;
+        Ok(TransactionPlan::new(vec![instruction], *admin))
+    }
+
+    /// Move protocol revenue from the MCP server registry's treasury vault to
+    /// `destination`, a token account of the config's treasury wallet
+    pub async fn withdraw_mcp_treasury<S: Signer>(
+        &self,
+        admin: &S,
+        destination: &Pubkey,
+        amount: u64,
+    ) -> SdkResult<Signature> {
+        let plan = self.plan_withdraw_mcp_treasury(&admin.pubkey(), destination, amount)?;
+        self.send_plan(&plan, &[admin]).await
+    }
+
+    /// [`Self::withdraw_mcp_treasury`] as an unsigned plan, with `admin` paying the fee
+    pub fn plan_withdraw_mcp_treasury(
+        &self,
+        admin: &Pubkey,
+        destination: &Pubkey,
+        amount: u64,
+    ) -> SdkResult<TransactionPlan> {
+        let instruction = crate::mcp::create_withdraw_treasury_instruction(
+            &self.config.mcp_server_registry_program_id,
+            admin,
+            destination,
+            amount,
+        )?;
+        Ok(TransactionPlan::new(vec![instruction], *admin))
+    }
+
+    /// Rewrite an agent entry stored in layout version 1 into the current
+    /// layout, so it can be read again. Anyone may migrate an entry; `payer`
+    /// covers any extra rent.
+    pub async fn migrate_agent_entry<S: Signer>(
+        &self,
+        payer: &S,
+        owner: &Pubkey,
+        agent_id: &str,
+    ) -> SdkResult<Signature> {
+        let plan = self.plan_migrate_agent_entry(&payer.pubkey(), owner, agent_id)?;
+        self.send_plan(&plan, &[payer]).await
+    }
+
+    /// [`Self::migrate_agent_entry`] as an unsigned plan
+    pub fn plan_migrate_agent_entry(
+        &self,
+        payer: &Pubkey,
+        owner: &Pubkey,
+        agent_id: &str,
+    ) -> SdkResult<TransactionPlan> {
+        let instruction = crate::agent::create_migrate_agent_entry_instruction(
+            &self.config.agent_registry_program_id,
+            payer,
+            owner,
+            agent_id,
+        )?;
+        Ok(TransactionPlan::new(vec![instruction], *payer))
+    }
+
+    /// Rewrite an MCP server entry stored in layout version 1 into the
+    /// current layout, so it can be read again. Anyone may migrate an entry;
+    /// `payer` covers any extra rent.
+    pub async fn migrate_mcp_server_entry<S: Signer>(
+        &self,
+        payer: &S,
+        owner: &Pubkey,
+        server_id: &str,
+    ) -> SdkResult<Signature> {
+        let plan = self.plan_migrate_mcp_server_entry(&payer.pubkey(), owner, server_id)?;
+        self.send_plan(&plan, &[payer]).await
+    }
+
+    /// [`Self::migrate_mcp_server_entry`] as an unsigned plan
+    pub fn plan_migrate_mcp_server_entry(
+        &self,
+        payer: &Pubkey,
+        owner: &Pubkey,
+        server_id: &str,
+    ) -> SdkResult<TransactionPlan> {
+        let instruction = crate::mcp::create_migrate_mcp_server_entry_instruction(
+            &self.config.mcp_server_registry_program_id,
+            payer,
+            owner,
+            server_id,
+        )?;
+        Ok(TransactionPlan::new(vec![instruction], *payer))
+    }
+
+    /// Check if an account exists
+    pub async fn account_exists(&self, pubkey: &Pubkey) -> SdkResult<bool> {
+        Ok(self.rpc_client.get_account(pubkey).await?.is_some())
+    }
+
+    /// Get account balance in lamports
+    pub async fn get_balance(&self, pubkey: &Pubkey) -> SdkResult<u64> {
+        Ok(self
+            .rpc_client
+            .get_account(pubkey)
+            .await?
+            .map_or(0, |account| account.lamports))
+    }
+
+    /// Fetch and decode the config PDA of `program_id`
+    async fn get_registry_config(&self, program_id: &Pubkey) -> SdkResult<Option<RegistryConfig>> {
+        let address = derive_registry_config_pda(program_id);
+        self.rpc_client
+            .get_account(&address)
+            .await?
+            .map(|account| RegistryConfig::try_from_account_data(&account.data))
+            .transpose()
+    }
+
+    /// Unix timestamp of the cluster's clock, which fee schedules are
+    /// resolved against
+    async fn get_cluster_time(&self) -> SdkResult<i64> {
+        let account = self
+            .rpc_client
+            .get_account(&solana_sdk::sysvar::clock::id())
+            .await?
+            .ok_or(SdkError::AccountNotFound)?;
+        decode_clock(&account)
+    }
+
+    /// Fetch and decode the revenue split of `entry`
+    async fn get_revenue_split(
+        &self,
+        program_id: &Pubkey,
+        entry: &Pubkey,
+    ) -> SdkResult<Option<RevenueSplit>> {
+        let address = derive_revenue_split_pda(program_id, entry);
+        self.rpc_client
+            .get_account(&address)
+            .await?
+            .filter(|account| account.owner == *program_id)
+            .map(|account| RevenueSplit::try_from_account_data(&account.data))
+            .transpose()
+    }
+
+    /// Shared body of the `plan_bootstrap_*_registry` helpers
+    async fn plan_bootstrap_registry(
+        &self,
+        program_id: &Pubkey,
+        authority: &Pubkey,
+        params: RegistryConfigParams,
+        initialize_config: InitializeConfigFn,
+        initialize_vaults: InitializeVaultsFn,
+    ) -> SdkResult<Option<TransactionPlan>> {
+        let mut instructions = Vec::new();
+
+        // An existing config keeps its mint; `params` only seeds a new one
+        let mint = match self.get_registry_config(program_id).await? {
+            Some(config) => config.params.accepted_mint,
+            None => {
+                instructions.push(initialize_config(program_id, authority, authority, params)?);
+                params.accepted_mint
+            }
+        };
+
+        let vaults = RegistryVaults::derive(program_id);
+        if !self.account_exists(&vaults.staking_vault).await? {
+            instructions.push(initialize_vaults(program_id, authority, authority, &mint)?);
+        }
+
+        if instructions.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(TransactionPlan::new(instructions, *authority)))
+    }
+
+    /// Blockhash to build `plan` on: the value stored in its durable nonce
+    /// account, or the latest blockhash when it has none
+    pub async fn plan_blockhash(&self, plan: &TransactionPlan) -> SdkResult<Hash> {
+        match plan.durable_nonce() {
+            Some(nonce) => {
+                let account = self
+                    .rpc_client
+                    .get_account(&nonce.account)
+                    .await?
+                    .ok_or(SdkError::AccountNotFound)?;
+                nonce_blockhash(&account)
+            }
+            None => self.rpc_client.latest_blockhash().await,
+        }
+    }
+
+    /// Compile `plan` into an unsigned legacy transaction for signing
+    /// outside the SDK
+    pub async fn unsigned_transaction(&self, plan: &TransactionPlan) -> SdkResult<Transaction> {
+        plan.transaction(self.plan_blockhash(plan).await?)
+    }
+
+    /// Compile `plan` into an unsigned v0 transaction for signing outside
+    /// the SDK, resolving accounts through `lookup_tables`, e.g. those from
+    /// [`Self::lookup_tables`]
+    pub async fn unsigned_versioned_transaction(
+        &self,
+        plan: &TransactionPlan,
+        lookup_tables: &[AddressLookupTableAccount],
+    ) -> SdkResult<VersionedTransaction> {
+        plan.versioned_transaction(self.plan_blockhash(plan).await?, lookup_tables)
+    }
+
+    /// Compile `plan` into the serialized message its signers sign: a v0
+    /// message through [`ClientConfig::lookup_table`] when one is set, a
+    /// legacy message otherwise
+    pub async fn serialized_message(&self, plan: &TransactionPlan) -> SdkResult<Vec<u8>> {
+        let lookup_tables = self.lookup_tables_for(plan).await?;
+        if lookup_tables.is_empty() {
+            return plan.serialized_message(self.plan_blockhash(plan).await?);
+        }
+        Ok(self
+            .unsigned_versioned_transaction(plan, &lookup_tables)
+            .await?
+            .message
+            .serialize())
+    }
+
+    /// Send a transaction signed outside the SDK and wait until it is
+    /// confirmed. Nothing is sent while a required signature is missing.
+    pub async fn submit_transaction(
+        &self,
+        transaction: impl Into<VersionedTransaction>,
+    ) -> SdkResult<Signature> {
+        let transaction = transaction.into();
+        if !missing_signers(&transaction).is_empty() {
+            return Err(SdkError::MissingRequiredSignature);
+        }
+        self.send_and_confirm(&transaction).await
+    }
+
+    /// Sign `plan` with `signers`, which must be exactly its required
+    /// signers, then send it and wait until it is confirmed.
+    ///
+    /// The configured priority-fee and compute-unit policies fill in
+    /// whatever the plan does not set itself. A transaction whose recent
+    /// blockhash expires before it lands is signed again over a fresh one,
+    /// up to the retry policy's attempts; durable-nonce plans are sent once.
+    /// With [`ClientConfig::lookup_table`] set, it is sent as a v0
+    /// transaction resolving accounts through that table.
+    pub async fn send_plan(
+        &self,
+        plan: &TransactionPlan,
+        signers: &[&dyn Signer],
+    ) -> SdkResult<Signature> {
+        let lookup_tables = self.lookup_tables_for(plan).await?;
+        let plan = self
+            .apply_send_policies(plan, &lookup_tables, signers)
+            .await?;
+        let mut attempt = 0;
+        loop {
+            attempt += 1;
+            let blockhash = self.plan_blockhash(&plan).await?;
+            let transaction = Self::sign_plan(&plan, blockhash, &lookup_tables, signers)?;
+            match self.send_and_confirm(&transaction).await {
+                Err(e)
+                    if e.is_blockhash_expired()
+                        && plan.durable_nonce().is_none()
+                        && attempt < self.config.retry.max_attempts =>
+                {
+                    continue
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// Price the configured priority-fee policy bids for `plan`, from the
+    /// fees recently paid for the accounts it writes
+    pub async fn priority_fee_for(&self, plan: &TransactionPlan) -> SdkResult<Option<u64>> {
+        let recent_fees = match self.config.priority_fee {
+            PriorityFeePolicy::Percentile { .. } => {
+                let message = plan.message(Hash::default());
+                let writable: Vec<Pubkey> = message
+                    .account_keys
+                    .iter()
+                    .enumerate()
+                    .filter(|(index, _)| message.is_writable(*index))
+                    .map(|(_, key)| *key)
+                    .collect();
+                self.rpc_client
+                    .recent_prioritization_fees(&writable)
+                    .await?
+            }
+            _ => Vec::new(),
+        };
+        Ok(self.config.priority_fee.price(&recent_fees))
+    }
+
+    /// Send `plans` one after another with the same signers, stopping at the
+    /// first failure, and return the signature of the last
+    pub async fn send_plans(
+        &self,
+        plans: &[TransactionPlan],
+        signers: &[&dyn Signer],
+    ) -> SdkResult<Signature> {
+        let mut signature = Signature::default();
+        for plan in plans {
+            signature = self.send_plan(plan, signers).await?;
+        }
+        Ok(signature)
+    }
+
+    /// Send `instructions` in one transaction with `payer` paying the fee,
+    /// signed by `payer` and whichever of `signers` the instructions require.
+    /// Signers the transaction does not need are left out, so one list of
+    /// owners and sponsors can serve several calls.
+    pub async fn send_instructions(
+        &self,
+        instructions: Vec<Instruction>,
+        payer: &dyn Signer,
+        signers: &[&dyn Signer],
+    ) -> SdkResult<Signature> {
+        let plan = TransactionPlan::new(instructions, payer.pubkey());
+        let required = plan.required_signers();
+        let mut plan_signers: Vec<&dyn Signer> = vec![payer];
+        for signer in signers {
+            let key = signer.pubkey();
+            if required.contains(&key) && plan_signers.iter().all(|s| s.pubkey() != key) {
+                plan_signers.push(*signer);
+            }
+        }
+        self.send_plan(&plan, &plan_signers).await
+    }
+
+    /// `plan` with the compute-unit price and limit the config's policies
+    /// choose, unless the plan already sets them. Simulating for the limit
+    /// needs `signers`, and surfaces a failing transaction before it is sent.
+    async fn apply_send_policies(
+        &self,
+        plan: &TransactionPlan,
+        lookup_tables: &[AddressLookupTableAccount],
+        signers: &[&dyn Signer],
+    ) -> SdkResult<TransactionPlan> {
+        let mut plan = plan.clone();
+        if plan.compute_unit_price().is_none() {
+            if let Some(price) = self.priority_fee_for(&plan).await? {
+                plan = plan.with_compute_unit_price(price);
+            }
+        }
+        if plan.compute_unit_limit().is_some() {
+            return Ok(plan);
+        }
+
+        match self.config.compute_units {
+            ComputeUnitPolicy::Default => Ok(plan),
+            ComputeUnitPolicy::Fixed { units } => Ok(plan.with_compute_unit_limit(units)),
+            ComputeUnitPolicy::Simulate { margin_percent } => {
+                let probe = plan.clone().with_compute_unit_limit(MAX_COMPUTE_UNIT_LIMIT);
+                let blockhash = self.plan_blockhash(&probe).await?;
+                let transaction = Self::sign_plan(&probe, blockhash, lookup_tables, signers)?;
+                let simulation = self.rpc_client.simulate_transaction(&transaction).await?;
+                if let Some(err) = simulation.err {
+                    return Err(decode_transaction_error(
+                        err,
+                        &transaction.message,
+                        &self.registry_programs(),
+                        simulation.logs,
+                    ));
+                }
+                Ok(match simulation.units_consumed {
+                    Some(units) => plan.with_compute_unit_limit(
+                        ComputeUnitPolicy::simulated_limit(margin_percent, units),
+                    ),
+                    None => plan,
+                })
+            }
+        }
+    }
+
+    /// Simulate `plan` as it stands, without signing or sending it. The
+    /// send policies are not applied, so a plan without a compute-unit limit
+    /// runs under the runtime default.
+    pub async fn simulate_plan(&self, plan: &TransactionPlan) -> SdkResult<SimulationReport> {
+        let lookup_tables = self.lookup_tables_for(plan).await?;
+        let transaction = if lookup_tables.is_empty() {
+            self.unsigned_transaction(plan).await?.into()
+        } else {
+            self.unsigned_versioned_transaction(plan, &lookup_tables)
+                .await?
+        };
+        let simulation = self.rpc_client.simulate_transaction(&transaction).await?;
+        Ok(SimulationReport::new(
+            simulation,
+            &transaction.message,
+            &self.registry_programs(),
+        ))
+    }
+
+    /// Send a signed transaction, giving up once the confirmation timeout
+    /// passes. Instruction failures are decoded into registry errors.
+    async fn send_and_confirm(&self, transaction: &VersionedTransaction) -> SdkResult<Signature> {
+        let timeout = self.config.timeouts.confirmation;
+        let result = tokio::time::timeout(timeout, self.rpc_client.send_transaction(transaction))
+            .await
+            .map_err(|_| SdkError::ConfirmationTimeout(transaction.signatures[0], timeout))
+            .and_then(|result| result)
+            .map_err(|e| decode_send_error(e, &transaction.message, &self.registry_programs()));
+
+        // Even a failed send may have landed, so cached copies of anything
+        // it could have written are dropped either way, and addresses not
+        // cached get a tombstone so a read already in flight cannot cache
+        // the old value. The last slot the cache saw may predate the write,
+        // so both are marked as of a slot read after confirmation; reads
+        // from earlier slots are then turned away.
+        if let Some(cache) = &self.cache {
+            let slot = self.rpc_client.get_slot().await.ok();
+            let message = &transaction.message;
+            message
+                .static_account_keys()
+                .iter()
+                .enumerate()
+                .filter(|(index, _)| message.is_maybe_writable(*index))
+                .for_each(|(_, address)| match slot {
+                    Some(slot) => cache.invalidate_at(address, slot),
+                    None => cache.invalidate(address),
+                });
+        }
+        result
+    }
+
+    /// Raw data of an entry account, through the cache if one is attached
+    async fn read_entry(&self, address: &Pubkey) -> SdkResult<Option<Vec<u8>>> {
+        match &self.cache {
+            Some(cache) => cache.read(self.rpc_client.as_ref(), address).await,
+            None => Ok(live_data(self.rpc_client.get_account(address).await?)),
+        }
+    }
+
+    /// Every account of `program_id` that decodes as an entry, warming the
+    /// cache with them
+    async fn list_entries<T>(
+        &self,
+        program_id: &Pubkey,
+        decode: fn(&[u8]) -> SdkResult<T>,
+    ) -> SdkResult<Vec<(Pubkey, T)>> {
+        let slot = match &self.cache {
+            Some(_) => self.rpc_client.get_slot().await?,
+            None => 0,
+        };
+        let accounts = self
+            .rpc_client
+            .get_program_accounts(program_id, Vec::new())
+            .await?;
+
+        // Skill and tool accounts share the program; they are skipped
+        let mut entries = Vec::new();
+        for (address, account) in accounts {
+            let Ok(entry) = decode(&account.data) else {
+                continue;
+            };
+            if let Some(cache) = &self.cache {
+                cache.insert(address, slot, Some(account.data));
+            }
+            entries.push((address, entry));
+        }
+        Ok(entries)
+    }
+
+    /// Plans adding `addresses` to `table`, a bounded number per transaction
+    fn plan_extend_lookup_table(
+        &self,
+        authority: &Pubkey,
+        table: &Pubkey,
+        addresses: Vec<Pubkey>,
+    ) -> Vec<TransactionPlan> {
+        addresses
+            .chunks(MAX_ADDRESSES_PER_EXTEND)
+            .map(|chunk| {
+                let instruction = address_lookup_table::instruction::extend_lookup_table(
+                    *table,
+                    *authority,
+                    Some(*authority),
+                    chunk.to_vec(),
+                );
+                TransactionPlan::new(vec![instruction], *authority)
+            })
+            .collect()
+    }
+
+    /// Sign `plan` as a v0 transaction through `lookup_tables`, or as a
+    /// legacy one when there are none
+    fn sign_plan(
+        plan: &TransactionPlan,
+        blockhash: Hash,
+        lookup_tables: &[AddressLookupTableAccount],
+        signers: &[&dyn Signer],
+    ) -> SdkResult<VersionedTransaction> {
+        if lookup_tables.is_empty() {
+            Ok(plan.sign(blockhash, signers)?.into())
+        } else {
+            plan.sign_versioned(blockhash, lookup_tables, signers)
+        }
+    }
+
+    /// Programs whose custom error codes are registry errors
+    fn registry_programs(&self) -> [Pubkey; 2] {
+        [
+            self.config.agent_registry_program_id,
+            self.config.mcp_server_registry_program_id,
+        ]
+    }
+
+    /// Get the minimum rent exemption for an account of the given size,
+    /// from the cluster's Rent sysvar
+    pub async fn get_minimum_rent_exemption(&self, size: usize) -> SdkResult<u64> {
+        let account = self
+            .rpc_client
+            .get_account(&solana_sdk::sysvar::rent::id())
+            .await?
+            .ok_or(SdkError::AccountNotFound)?;
+        let rent: solana_sdk::rent::Rent =
+            solana_sdk::account::from_account(&account).ok_or_else(|| {
+                SdkError::DeserializationError("Failed to deserialize Rent sysvar".to_string())
+            })?;
+        Ok(rent.minimum_balance(size))
+    }
+
+    /// Report the account size and rent deposit for registering an agent,
+    /// using the cluster's current rent parameters
+    pub async fn estimate_agent_rent(&self, args: &AgentArgs) -> SdkResult<RentEstimate> {
+        let account_size = args.account_size()?;
+        let lamports = self.get_minimum_rent_exemption(account_size).await?;
+        Ok(RentEstimate {
+            account_size,
+            lamports,
+        })
+    }
+
+    /// Report the account size and rent deposit for registering an MCP server,
+    /// using the cluster's current rent parameters
+    pub async fn estimate_mcp_server_rent(&self, args: &McpServerArgs) -> SdkResult<RentEstimate> {
+        let account_size = args.account_size()?;
+        let lamports = self.get_minimum_rent_exemption(account_size).await?;
+        Ok(RentEstimate {
+            account_size,
+            lamports,
+        })
+    }
+}
+
+impl Default for SolanaAiRegistriesClient {
+    fn default() -> Self {
         Self::new("https://api.devnet.solana.com")
     }
 }
@@ -317,7 +2801,7 @@ mod tests {
     #[test]
     fn test_client_creation() {
         let client = SolanaAiRegistriesClient::new("https://api.devnet.solana.com");
-        assert_eq!(client.commitment, CommitmentConfig::confirmed());
+        assert_eq!(client.commitment(), CommitmentConfig::confirmed());
     }
 
     #[test]
@@ -326,7 +2810,42 @@ mod tests {
             "https://api.devnet.solana.com",
             CommitmentConfig::finalized(),
         );
-        assert_eq!(client.commitment, CommitmentConfig::finalized());
+        assert_eq!(client.commitment(), CommitmentConfig::finalized());
+    }
+
+    #[test]
+    fn test_from_rpc_client_shares_connection() {
+        let rpc_client = Arc::new(RpcClient::new_with_commitment(
+            "https://api.devnet.solana.com".to_string(),
+            CommitmentConfig::processed(),
+        ));
+        let client = SolanaAiRegistriesClient::from_rpc_client(rpc_client.clone());
+
+        assert!(Arc::ptr_eq(client.rpc_client(), &rpc_client));
+        assert_eq!(client.commitment(), CommitmentConfig::processed());
+    }
+
+    #[test]
+    fn test_decode_clock() {
+        let clock = solana_sdk::clock::Clock {
+            unix_timestamp: 1_700_000_000,
+            ..Default::default()
+        };
+        let account = solana_sdk::account::create_account_for_test(&clock);
+        assert_eq!(decode_clock(&account).unwrap(), 1_700_000_000);
+
+        assert!(decode_clock(&Account::default()).is_err());
+    }
+
+    #[test]
+    fn test_read_state_version() {
+        let mut data = vec![0u8; 64];
+        data[0] = 255; // bump
+        data[1] = 1; // registry_version
+        data[STATE_VERSION_OFFSET..STATE_VERSION_OFFSET + 8].copy_from_slice(&42u64.to_le_bytes());
+
+        assert_eq!(read_state_version(&data).unwrap(), 42);
+        assert!(read_state_version(&data[..4]).is_err());
     }
 
     #[test]
@@ -372,3 +2891,25 @@ where
         SdkError::DeserializationError(format!("Failed to deserialize {}: {}", type_name, e))
     })
 }
+
+/// Read the optimistic-locking `state_version` of an agent or MCP server entry
+///
+/// Only the fixed-size header is touched, so this works regardless of how the
+/// variable-length fields that follow are laid out.
+pub fn read_state_version(data: &[u8]) -> SdkResult<u64> {
+    let bytes = data
+        .get(STATE_VERSION_OFFSET..STATE_VERSION_OFFSET + 8)
+        .ok_or(SdkError::InvalidAccountData)?;
+    let mut buf = [0u8; 8];
+    buf.copy_from_slice(bytes);
+    Ok(u64::from_le_bytes(buf))
+}
+
+/// Unix timestamp of a fetched Clock sysvar account
+fn decode_clock(account: &Account) -> SdkResult<i64> {
+    let clock: solana_sdk::clock::Clock =
+        solana_sdk::account::from_account(account).ok_or_else(|| {
+            SdkError::DeserializationError("Failed to deserialize Clock sysvar".to_string())
+        })?;
+    Ok(clock.unix_timestamp)
+}
@@ -0,0 +1,814 @@
+//! Cluster-aware client configuration
+//!
+//! [`ClientConfig`] gathers everything that differs between deployments:
+//! endpoints, commitment, registry program IDs, the A2AMPL mint, registry
+//! vaults, the send pipeline's priority-fee, compute-unit and retry
+//! policies, and timeouts. Presets cover mainnet, devnet and localnet;
+//! configs can also be loaded from the `deployment-info-*.json` files
+//! written by the deploy scripts or from a TOML file.
+
+use crate::config::RegistryVaults;
+use crate::errors::{SdkError, SdkResult};
+use serde::{Deserialize, Serialize};
+use solana_sdk::{
+    commitment_config::{CommitmentConfig, CommitmentLevel},
+    pubkey::Pubkey,
+};
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
+use std::time::Duration;
+
+/// A2AMPL token mint address on Solana mainnet
+/// The native utility token for the Solana AI Registries ecosystem.
+pub const A2AMPL_TOKEN_MINT_MAINNET: &str = "Cpzvdx6pppc9TNArsGsqgShCsKC9NCCjA2gtzHvUpump";
+
+/// A2AMPL token mint address on Solana devnet
+/// Used for development and testing purposes.
+pub const A2AMPL_TOKEN_MINT_DEVNET: &str = "A2AMPLyncKHwfSnwRNsJ2qsjsetgo9fGkP8YZPsDZ9mE";
+
+/// Agent registry program deployed on devnet (see `deployment-info-devnet-final.json`)
+pub const DEVNET_AGENT_REGISTRY_PROGRAM_ID: &str = "2CyuaQMyxJNg637bYSR1ZhwfDFd3ssCvTJHMBTbCH8D4";
+
+/// MCP server registry program deployed on devnet
+pub const DEVNET_MCP_SERVER_REGISTRY_PROGRAM_ID: &str =
+    "FYu2V5y6vGjsra7rqCKs5Z4paMLQB2mT4iTc9KABGCSM";
+
+/// Default timeout of a single RPC request
+pub const DEFAULT_REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Default time to wait for a sent transaction to be confirmed
+pub const DEFAULT_CONFIRMATION_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Default number of times a transaction is sent, each with a fresh
+/// blockhash after the previous one expired
+pub const DEFAULT_SEND_ATTEMPTS: u32 = 3;
+
+/// Most compute units a transaction may request
+pub const MAX_COMPUTE_UNIT_LIMIT: u32 = 1_400_000;
+
+/// Get the appropriate token mint for the network
+pub fn get_token_mint_for_network(is_mainnet: bool) -> SdkResult<Pubkey> {
+    let mint_str = if is_mainnet {
+        A2AMPL_TOKEN_MINT_MAINNET
+    } else {
+        A2AMPL_TOKEN_MINT_DEVNET
+    };
+
+    mint_str
+        .parse()
+        .map_err(|_| SdkError::InvalidConfiguration("Invalid token mint address".to_string()))
+}
+
+/// Solana cluster the registries are deployed on
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum Cluster {
+    Mainnet,
+    Devnet,
+    Localnet,
+}
+
+impl Cluster {
+    /// Public RPC endpoint of the cluster
+    pub fn rpc_url(&self) -> &'static str {
+        match self {
+            Cluster::Mainnet => "https://api.mainnet-beta.solana.com",
+            Cluster::Devnet => "https://api.devnet.solana.com",
+            Cluster::Localnet => "http://127.0.0.1:8899",
+        }
+    }
+
+    /// Whether the cluster holds real funds
+    pub fn is_mainnet(&self) -> bool {
+        matches!(self, Cluster::Mainnet)
+    }
+
+    /// Registry program IDs fixed for the cluster, as `(agent, mcp)`.
+    /// Mainnet IDs are generated by `scripts/deploy-mainnet.sh` and are not
+    /// known to the SDK; localnet deploys the devnet program keypairs.
+    pub fn program_ids(&self) -> Option<(Pubkey, Pubkey)> {
+        match self {
+            Cluster::Mainnet => None,
+            Cluster::Devnet | Cluster::Localnet => Some((
+                Pubkey::from_str(DEVNET_AGENT_REGISTRY_PROGRAM_ID)
+                    .expect("Invalid agent registry program ID"),
+                Pubkey::from_str(DEVNET_MCP_SERVER_REGISTRY_PROGRAM_ID)
+                    .expect("Invalid MCP server registry program ID"),
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Cluster {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Cluster::Mainnet => "mainnet-beta",
+            Cluster::Devnet => "devnet",
+            Cluster::Localnet => "localnet",
+        })
+    }
+}
+
+impl FromStr for Cluster {
+    type Err = SdkError;
+
+    fn from_str(name: &str) -> SdkResult<Self> {
+        match name {
+            "mainnet" | "mainnet-beta" => Ok(Cluster::Mainnet),
+            "devnet" => Ok(Cluster::Devnet),
+            "localnet" | "localhost" => Ok(Cluster::Localnet),
+            other => Err(SdkError::InvalidConfiguration(format!(
+                "Unknown cluster: {}",
+                other
+            ))),
+        }
+    }
+}
+
+impl TryFrom<String> for Cluster {
+    type Error = SdkError;
+
+    fn try_from(name: String) -> SdkResult<Self> {
+        name.parse()
+    }
+}
+
+impl From<Cluster> for String {
+    fn from(cluster: Cluster) -> Self {
+        cluster.to_string()
+    }
+}
+
+/// How much to bid for priority, in micro-lamports per compute unit
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum PriorityFeePolicy {
+    /// No priority fee
+    #[default]
+    None,
+    /// Always bid the same price
+    Fixed { micro_lamports: u64 },
+    /// Bid the given percentile of recent prioritization fees paid for the
+    /// accounts a transaction writes, capped at `max_micro_lamports`
+    Percentile {
+        percentile: u8,
+        max_micro_lamports: u64,
+    },
+}
+
+impl PriorityFeePolicy {
+    /// Price to bid given the prioritization fees recently paid for the
+    /// accounts a transaction writes, or `None` to bid nothing
+    pub fn price(&self, recent_fees: &[u64]) -> Option<u64> {
+        match *self {
+            PriorityFeePolicy::None => None,
+            PriorityFeePolicy::Fixed { micro_lamports } => Some(micro_lamports),
+            PriorityFeePolicy::Percentile {
+                percentile,
+                max_micro_lamports,
+            } => {
+                if recent_fees.is_empty() {
+                    return None;
+                }
+                let mut fees = recent_fees.to_vec();
+                fees.sort_unstable();
+                let rank = (fees.len() - 1) * usize::from(percentile.min(100)) / 100;
+                Some(fees[rank].min(max_micro_lamports))
+            }
+        }
+    }
+}
+
+/// How the compute-unit limit of sent transactions is set
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ComputeUnitPolicy {
+    /// Leave the runtime's default limit
+    #[default]
+    Default,
+    /// Always request the same limit
+    Fixed { units: u32 },
+    /// Simulate the transaction first and request the units it consumed
+    /// plus `margin_percent`
+    Simulate { margin_percent: u16 },
+}
+
+impl ComputeUnitPolicy {
+    /// Limit to request for a transaction that consumed `units_consumed`
+    /// in simulation
+    pub fn simulated_limit(margin_percent: u16, units_consumed: u64) -> u32 {
+        let limit = units_consumed.saturating_mul(100 + u64::from(margin_percent)) / 100;
+        limit.min(u64::from(MAX_COMPUTE_UNIT_LIMIT)) as u32
+    }
+}
+
+/// How often a transaction is rebroadcast when its blockhash expires
+/// before it is confirmed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Sends in total, each signed over a fresh blockhash
+    pub max_attempts: u32,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: DEFAULT_SEND_ATTEMPTS,
+        }
+    }
+}
+
+/// Timeouts applied to RPC requests and transaction confirmation
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Timeouts {
+    pub request: Duration,
+    pub confirmation: Duration,
+}
+
+impl Default for Timeouts {
+    fn default() -> Self {
+        Self {
+            request: DEFAULT_REQUEST_TIMEOUT,
+            confirmation: DEFAULT_CONFIRMATION_TIMEOUT,
+        }
+    }
+}
+
+/// Everything the client needs to know about a registry deployment
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClientConfig {
+    pub cluster: Cluster,
+    pub rpc_url: String,
+    /// PubSub endpoint for subscriptions; derived from `rpc_url` when unset
+    pub ws_url: Option<String>,
+    pub commitment: CommitmentConfig,
+    pub agent_registry_program_id: Pubkey,
+    pub mcp_server_registry_program_id: Pubkey,
+    /// A2AMPL mint registry fees and stakes are paid in
+    pub token_mint: Pubkey,
+    /// Address lookup table holding the registry's static accounts; when
+    /// set, transactions are compiled as v0 messages resolving through it
+    pub lookup_table: Option<Pubkey>,
+    pub priority_fee: PriorityFeePolicy,
+    pub compute_units: ComputeUnitPolicy,
+    pub retry: RetryPolicy,
+    pub timeouts: Timeouts,
+}
+
+impl ClientConfig {
+    /// Start building a config from `cluster`'s defaults
+    pub fn builder(cluster: Cluster) -> ClientConfigBuilder {
+        ClientConfigBuilder::new(cluster)
+    }
+
+    /// Mainnet, with the registry program IDs of the mainnet deployment
+    pub fn mainnet(
+        agent_registry_program_id: Pubkey,
+        mcp_server_registry_program_id: Pubkey,
+    ) -> Self {
+        Self::builder(Cluster::Mainnet)
+            .program_ids(agent_registry_program_id, mcp_server_registry_program_id)
+            .build()
+            .expect("Mainnet preset is complete")
+    }
+
+    /// Devnet, with the deployed devnet registries
+    pub fn devnet() -> Self {
+        Self::builder(Cluster::Devnet)
+            .build()
+            .expect("Devnet preset is complete")
+    }
+
+    /// A local validator running the registries at their devnet program IDs
+    pub fn localnet() -> Self {
+        Self::builder(Cluster::Localnet)
+            .build()
+            .expect("Localnet preset is complete")
+    }
+
+    /// Vaults of the agent registry, derived from its program ID
+    pub fn agent_registry_vaults(&self) -> RegistryVaults {
+        RegistryVaults::derive(&self.agent_registry_program_id)
+    }
+
+    /// Vaults of the MCP server registry, derived from its program ID
+    pub fn mcp_server_registry_vaults(&self) -> RegistryVaults {
+        RegistryVaults::derive(&self.mcp_server_registry_program_id)
+    }
+
+    /// PubSub endpoint: `ws_url` if set, otherwise `rpc_url` with a
+    /// websocket scheme and, when it names a port, the next port up, which
+    /// is where validators serve PubSub
+    pub fn websocket_url(&self) -> String {
+        if let Some(ws_url) = &self.ws_url {
+            return ws_url.clone();
+        }
+
+        let (scheme, rest) = match self.rpc_url.split_once("://") {
+            Some(("https", rest)) => ("wss", rest),
+            Some((_, rest)) => ("ws", rest),
+            None => ("ws", self.rpc_url.as_str()),
+        };
+        let (authority, path) = rest.split_at(rest.find('/').unwrap_or(rest.len()));
+        let authority = match authority.rsplit_once(':') {
+            Some((host, port)) => match port.parse::<u16>() {
+                Ok(port) => format!("{}:{}", host, port.saturating_add(1)),
+                Err(_) => authority.to_string(),
+            },
+            None => authority.to_string(),
+        };
+        format!("{}://{}{}", scheme, authority, path)
+    }
+
+    /// Load a `deployment-info-<network>.json` file written by the deploy
+    /// scripts
+    pub fn from_deployment_info(path: impl AsRef<Path>) -> SdkResult<Self> {
+        Self::from_deployment_info_str(&read_file(path.as_ref())?)
+    }
+
+    /// Parse the contents of a `deployment-info-<network>.json` file
+    pub fn from_deployment_info_str(json: &str) -> SdkResult<Self> {
+        let info: DeploymentInfo = serde_json::from_str(json).map_err(|e| {
+            SdkError::InvalidConfiguration(format!("Invalid deployment info: {}", e))
+        })?;
+
+        let mut builder = Self::builder(info.network).program_ids(
+            parse_pubkey(&info.programs.agent_registry.program_id)?,
+            parse_pubkey(&info.programs.mcp_server_registry.program_id)?,
+        );
+        if let Some(rpc_url) = info.rpc_url {
+            builder = builder.rpc_url(rpc_url);
+        }
+        if let Some(mint) = info.svmai_token_mint {
+            builder = builder.token_mint(parse_pubkey(&mint)?);
+        }
+        builder.build()
+    }
+
+    /// Load a TOML config file; see [`Self::from_toml_str`] for the format
+    pub fn from_toml_file(path: impl AsRef<Path>) -> SdkResult<Self> {
+        Self::from_toml_str(&read_file(path.as_ref())?)
+    }
+
+    /// Parse a TOML config. Only `cluster` is required; every other key
+    /// overrides the cluster's default:
+    ///
+    /// ```toml
+    /// cluster = "devnet"
+    /// rpc_url = "https://my-rpc.example.com"
+    /// ws_url = "wss://my-rpc.example.com/ws"
+    /// commitment = "finalized"
+    /// agent_registry_program_id = "..."
+    /// mcp_server_registry_program_id = "..."
+    /// token_mint = "..."
+    /// lookup_table = "..."
+    ///
+    /// [priority_fee]
+    /// kind = "percentile"
+    /// percentile = 75
+    /// max_micro_lamports = 100000
+    ///
+    /// [compute_units]
+    /// kind = "simulate"
+    /// margin_percent = 10
+    ///
+    /// [retry]
+    /// max_attempts = 5
+    ///
+    /// [timeouts]
+    /// request_secs = 30
+    /// confirmation_secs = 90
+    /// ```
+    pub fn from_toml_str(toml: &str) -> SdkResult<Self> {
+        let file: ConfigFile = toml::from_str(toml)
+            .map_err(|e| SdkError::InvalidConfiguration(format!("Invalid TOML config: {}", e)))?;
+
+        let mut builder = Self::builder(file.cluster);
+        if let Some(rpc_url) = file.rpc_url {
+            builder = builder.rpc_url(rpc_url);
+        }
+        if let Some(ws_url) = file.ws_url {
+            builder = builder.ws_url(ws_url);
+        }
+        if let Some(commitment) = file.commitment {
+            let level = CommitmentLevel::from_str(&commitment).map_err(|_| {
+                SdkError::InvalidConfiguration(format!("Unknown commitment: {}", commitment))
+            })?;
+            builder = builder.commitment(CommitmentConfig { commitment: level });
+        }
+        match (
+            file.agent_registry_program_id,
+            file.mcp_server_registry_program_id,
+        ) {
+            (Some(agent), Some(mcp)) => {
+                builder = builder.program_ids(parse_pubkey(&agent)?, parse_pubkey(&mcp)?);
+            }
+            (None, None) => {}
+            _ => {
+                return Err(SdkError::InvalidConfiguration(
+                    "Both registry program IDs must be set together".to_string(),
+                ))
+            }
+        }
+        if let Some(mint) = file.token_mint {
+            builder = builder.token_mint(parse_pubkey(&mint)?);
+        }
+        if let Some(lookup_table) = file.lookup_table {
+            builder = builder.lookup_table(parse_pubkey(&lookup_table)?);
+        }
+        if let Some(priority_fee) = file.priority_fee {
+            builder = builder.priority_fee(priority_fee);
+        }
+        if let Some(compute_units) = file.compute_units {
+            builder = builder.compute_units(compute_units);
+        }
+        if let Some(retry) = file.retry {
+            builder = builder.retry(RetryPolicy {
+                max_attempts: retry.max_attempts,
+            });
+        }
+        if let Some(timeouts) = file.timeouts {
+            let defaults = Timeouts::default();
+            builder = builder.timeouts(Timeouts {
+                request: timeouts
+                    .request_secs
+                    .map_or(defaults.request, Duration::from_secs),
+                confirmation: timeouts
+                    .confirmation_secs
+                    .map_or(defaults.confirmation, Duration::from_secs),
+            });
+        }
+        builder.build()
+    }
+}
+
+/// Builder for [`ClientConfig`]
+pub struct ClientConfigBuilder {
+    cluster: Cluster,
+    rpc_url: Option<String>,
+    ws_url: Option<String>,
+    commitment: CommitmentConfig,
+    program_ids: Option<(Pubkey, Pubkey)>,
+    token_mint: Option<Pubkey>,
+    lookup_table: Option<Pubkey>,
+    priority_fee: PriorityFeePolicy,
+    compute_units: ComputeUnitPolicy,
+    retry: RetryPolicy,
+    timeouts: Timeouts,
+}
+
+impl ClientConfigBuilder {
+    /// Start from `cluster`'s defaults
+    pub fn new(cluster: Cluster) -> Self {
+        Self {
+            cluster,
+            rpc_url: None,
+            ws_url: None,
+            commitment: CommitmentConfig::confirmed(),
+            program_ids: cluster.program_ids(),
+            token_mint: None,
+            lookup_table: None,
+            priority_fee: PriorityFeePolicy::default(),
+            compute_units: ComputeUnitPolicy::default(),
+            retry: RetryPolicy::default(),
+            timeouts: Timeouts::default(),
+        }
+    }
+
+    /// Use a private RPC endpoint instead of the cluster's public one
+    pub fn rpc_url(mut self, rpc_url: impl Into<String>) -> Self {
+        self.rpc_url = Some(rpc_url.into());
+        self
+    }
+
+    /// Use a PubSub endpoint other than the one derived from the RPC URL
+    pub fn ws_url(mut self, ws_url: impl Into<String>) -> Self {
+        self.ws_url = Some(ws_url.into());
+        self
+    }
+
+    /// Set the commitment reads are made and transactions confirmed at
+    pub fn commitment(mut self, commitment: CommitmentConfig) -> Self {
+        self.commitment = commitment;
+        self
+    }
+
+    /// Set the agent and MCP server registry program IDs
+    pub fn program_ids(
+        mut self,
+        agent_registry_program_id: Pubkey,
+        mcp_server_registry_program_id: Pubkey,
+    ) -> Self {
+        self.program_ids = Some((agent_registry_program_id, mcp_server_registry_program_id));
+        self
+    }
+
+    /// Set the token mint, e.g. one created for a local validator
+    pub fn token_mint(mut self, token_mint: Pubkey) -> Self {
+        self.token_mint = Some(token_mint);
+        self
+    }
+
+    /// Compile transactions as v0 messages resolving accounts through
+    /// `lookup_table`; see [`crate::lookup_table`]
+    pub fn lookup_table(mut self, lookup_table: Pubkey) -> Self {
+        self.lookup_table = Some(lookup_table);
+        self
+    }
+
+    /// Set the priority-fee policy
+    pub fn priority_fee(mut self, priority_fee: PriorityFeePolicy) -> Self {
+        self.priority_fee = priority_fee;
+        self
+    }
+
+    /// Set how the compute-unit limit of sent transactions is chosen
+    pub fn compute_units(mut self, compute_units: ComputeUnitPolicy) -> Self {
+        self.compute_units = compute_units;
+        self
+    }
+
+    /// Set how often transactions are rebroadcast after their blockhash
+    /// expires
+    pub fn retry(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// Set request and confirmation timeouts
+    pub fn timeouts(mut self, timeouts: Timeouts) -> Self {
+        self.timeouts = timeouts;
+        self
+    }
+
+    /// Build the config. Fails on mainnet unless program IDs were set.
+    pub fn build(self) -> SdkResult<ClientConfig> {
+        let (agent_registry_program_id, mcp_server_registry_program_id) =
+            self.program_ids.ok_or_else(|| {
+                SdkError::InvalidConfiguration(format!(
+                    "No registry program IDs known for {}; set them explicitly",
+                    self.cluster
+                ))
+            })?;
+        if let PriorityFeePolicy::Percentile { percentile, .. } = self.priority_fee {
+            if percentile > 100 {
+                return Err(SdkError::InvalidConfiguration(format!(
+                    "Priority fee percentile {} exceeds 100",
+                    percentile
+                )));
+            }
+        }
+        if let ComputeUnitPolicy::Fixed { units } = self.compute_units {
+            if units == 0 || units > MAX_COMPUTE_UNIT_LIMIT {
+                return Err(SdkError::InvalidConfiguration(format!(
+                    "Compute unit limit {} is outside 1..={}",
+                    units, MAX_COMPUTE_UNIT_LIMIT
+                )));
+            }
+        }
+        if self.retry.max_attempts == 0 {
+            return Err(SdkError::InvalidConfiguration(
+                "Retry policy must allow at least one attempt".to_string(),
+            ));
+        }
+        let token_mint = match self.token_mint {
+            Some(mint) => mint,
+            None => get_token_mint_for_network(self.cluster.is_mainnet())?,
+        };
+
+        Ok(ClientConfig {
+            cluster: self.cluster,
+            rpc_url: self
+                .rpc_url
+                .unwrap_or_else(|| self.cluster.rpc_url().to_string()),
+            ws_url: self.ws_url,
+            commitment: self.commitment,
+            agent_registry_program_id,
+            mcp_server_registry_program_id,
+            token_mint,
+            lookup_table: self.lookup_table,
+            priority_fee: self.priority_fee,
+            compute_units: self.compute_units,
+            retry: self.retry,
+            timeouts: self.timeouts,
+        })
+    }
+}
+
+/// The parts of a `deployment-info-*.json` file the SDK reads
+#[derive(Deserialize)]
+struct DeploymentInfo {
+    network: Cluster,
+    programs: DeployedPrograms,
+    rpc_url: Option<String>,
+    svmai_token_mint: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct DeployedPrograms {
+    agent_registry: DeployedProgram,
+    mcp_server_registry: DeployedProgram,
+}
+
+#[derive(Deserialize)]
+struct DeployedProgram {
+    program_id: String,
+}
+
+/// TOML config file layout
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ConfigFile {
+    cluster: Cluster,
+    rpc_url: Option<String>,
+    ws_url: Option<String>,
+    commitment: Option<String>,
+    agent_registry_program_id: Option<String>,
+    mcp_server_registry_program_id: Option<String>,
+    token_mint: Option<String>,
+    lookup_table: Option<String>,
+    priority_fee: Option<PriorityFeePolicy>,
+    compute_units: Option<ComputeUnitPolicy>,
+    retry: Option<RetryFile>,
+    timeouts: Option<TimeoutsFile>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RetryFile {
+    max_attempts: u32,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TimeoutsFile {
+    request_secs: Option<u64>,
+    confirmation_secs: Option<u64>,
+}
+
+fn read_file(path: &Path) -> SdkResult<String> {
+    std::fs::read_to_string(path).map_err(|e| {
+        SdkError::InvalidConfiguration(format!("Failed to read {}: {}", path.display(), e))
+    })
+}
+
+fn parse_pubkey(value: &str) -> SdkResult<Pubkey> {
+    Pubkey::from_str(value)
+        .map_err(|_| SdkError::InvalidConfiguration(format!("Invalid address: {}", value)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_presets() {
+        let devnet = ClientConfig::devnet();
+        assert_eq!(devnet.rpc_url, "https://api.devnet.solana.com");
+        assert_eq!(
+            devnet.agent_registry_program_id.to_string(),
+            DEVNET_AGENT_REGISTRY_PROGRAM_ID
+        );
+        assert_eq!(
+            devnet.token_mint,
+            get_token_mint_for_network(false).unwrap()
+        );
+        assert_eq!(
+            devnet.mcp_server_registry_vaults(),
+            RegistryVaults::derive(&devnet.mcp_server_registry_program_id)
+        );
+
+        let localnet = ClientConfig::localnet();
+        assert_eq!(localnet.rpc_url, "http://127.0.0.1:8899");
+        assert_eq!(localnet.websocket_url(), "ws://127.0.0.1:8900");
+        assert_eq!(devnet.websocket_url(), "wss://api.devnet.solana.com");
+
+        assert!(matches!(
+            ClientConfig::builder(Cluster::Mainnet).build(),
+            Err(SdkError::InvalidConfiguration(_))
+        ));
+        let (agent, mcp) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let mainnet = ClientConfig::mainnet(agent, mcp);
+        assert_eq!(
+            mainnet.token_mint,
+            get_token_mint_for_network(true).unwrap()
+        );
+        assert_eq!(mainnet.agent_registry_program_id, agent);
+    }
+
+    #[test]
+    fn test_from_deployment_info() {
+        let json = include_str!("../../deployment-info-devnet-final.json");
+        let config = ClientConfig::from_deployment_info_str(json).unwrap();
+
+        assert_eq!(config.cluster, Cluster::Devnet);
+        assert_eq!(
+            config.mcp_server_registry_program_id.to_string(),
+            "FYu2V5y6vGjsra7rqCKs5Z4paMLQB2mT4iTc9KABGCSM"
+        );
+        assert_eq!(
+            config.token_mint.to_string(),
+            "FEgE1LhC1NyxYxNGL2b5cjC8Pbm1Hehk7CDBHjkR1wcu"
+        );
+
+        let json = include_str!("../../deployment-info-devnet.json");
+        let config = ClientConfig::from_deployment_info_str(json).unwrap();
+        assert_eq!(
+            config.agent_registry_program_id.to_string(),
+            "BCBVehUHR3yhbDbvhV3QHS3s27k3LTbpX5CrXQ2sR2SR"
+        );
+    }
+
+    #[test]
+    fn test_from_toml() {
+        let config = ClientConfig::from_toml_str(
+            r#"
+            cluster = "localnet"
+            rpc_url = "http://localhost:9000"
+            commitment = "finalized"
+            lookup_table = "AddressLookupTab1e1111111111111111111111111"
+
+            [priority_fee]
+            kind = "percentile"
+            percentile = 75
+            max_micro_lamports = 50000
+
+            [compute_units]
+            kind = "simulate"
+            margin_percent = 20
+
+            [retry]
+            max_attempts = 5
+
+            [timeouts]
+            confirmation_secs = 5
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(config.rpc_url, "http://localhost:9000");
+        assert_eq!(config.websocket_url(), "ws://localhost:9001");
+        assert_eq!(config.commitment, CommitmentConfig::finalized());
+        assert_eq!(
+            config.lookup_table,
+            Some(solana_sdk::address_lookup_table::program::id())
+        );
+        assert_eq!(
+            config.priority_fee,
+            PriorityFeePolicy::Percentile {
+                percentile: 75,
+                max_micro_lamports: 50_000
+            }
+        );
+        assert_eq!(
+            config.compute_units,
+            ComputeUnitPolicy::Simulate { margin_percent: 20 }
+        );
+        assert_eq!(config.retry.max_attempts, 5);
+        assert_eq!(config.timeouts.confirmation, Duration::from_secs(5));
+        assert_eq!(config.timeouts.request, DEFAULT_REQUEST_TIMEOUT);
+
+        assert!(ClientConfig::from_toml_str("cluster = \"mainnet\"").is_err());
+        assert!(ClientConfig::from_toml_str("cluster = \"testnet\"").is_err());
+        assert!(
+            ClientConfig::from_toml_str("cluster = \"devnet\"\n[retry]\nmax_attempts = 0").is_err()
+        );
+        assert!(ClientConfig::from_toml_str(
+            "cluster = \"devnet\"\nagent_registry_program_id = \"11111111111111111111111111111112\""
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_send_policies() {
+        let fees = [400, 100, 300, 200, 0];
+        assert_eq!(PriorityFeePolicy::None.price(&fees), None);
+        assert_eq!(
+            PriorityFeePolicy::Fixed { micro_lamports: 7 }.price(&[]),
+            Some(7)
+        );
+
+        let median = PriorityFeePolicy::Percentile {
+            percentile: 50,
+            max_micro_lamports: 1_000,
+        };
+        assert_eq!(median.price(&fees), Some(200));
+        assert_eq!(median.price(&[]), None);
+        let capped = PriorityFeePolicy::Percentile {
+            percentile: 100,
+            max_micro_lamports: 250,
+        };
+        assert_eq!(capped.price(&fees), Some(250));
+
+        assert_eq!(ComputeUnitPolicy::simulated_limit(10, 50_000), 55_000);
+        assert_eq!(
+            ComputeUnitPolicy::simulated_limit(50, u64::from(MAX_COMPUTE_UNIT_LIMIT)),
+            MAX_COMPUTE_UNIT_LIMIT
+        );
+        assert!(ClientConfig::builder(Cluster::Devnet)
+            .compute_units(ComputeUnitPolicy::Fixed { units: 0 })
+            .build()
+            .is_err());
+    }
+}
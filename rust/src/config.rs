@@ -0,0 +1,194 @@
+//! Registry config accounts and vaults
+//!
+//! Each registry program stores its fees, stake tiers, lock periods, minimum
+//! fees, accepted mint and treasury in a config PDA that an admin can update
+//! without a program upgrade. These types mirror the on-chain layout.
+//!
+//! Stakes, fees, registration payments and protocol revenue are held in token
+//! vaults at fixed PDAs of each registry, created once by `InitializeVaults`.
+
+use crate::errors::{SdkError, SdkResult};
+use borsh::{BorshDeserialize, BorshSerialize};
+use solana_sdk::{bpf_loader_upgradeable, pubkey::Pubkey};
+
+/// Seed of the registry config PDA
+pub const REGISTRY_CONFIG_SEED: &[u8] = b"registry_config";
+
+/// Seed of the staking vault PDA
+pub const STAKING_VAULT_SEED: &[u8] = b"staking_vault";
+
+/// Seed of the fee vault PDA
+pub const FEE_VAULT_SEED: &[u8] = b"fee_vault";
+
+/// Seed of the registration vault PDA
+pub const REGISTRATION_VAULT_SEED: &[u8] = b"registration_vault";
+
+/// Seed of the treasury vault PDA, which collects the protocol take-rate
+pub const TREASURY_VAULT_SEED: &[u8] = b"treasury_vault";
+
+/// Number of stake tiers a config can describe
+pub const MAX_STAKE_TIERS: usize = 4;
+
+/// Parameters an admin can set on a registry config
+#[derive(Debug, Clone, Copy, BorshSerialize, BorshDeserialize, PartialEq)]
+pub struct RegistryConfigParams {
+    /// Mint of the token used for fees and stakes
+    pub accepted_mint: Pubkey,
+    /// Wallet that treasury vault withdrawals are paid to
+    pub treasury: Pubkey,
+    /// Fee charged by the with-token registration instructions
+    pub registration_fee: u64,
+    /// Minimum stake of each tier, lowest tier first. Unused tiers are `u64::MAX`.
+    pub tier_thresholds: [u64; MAX_STAKE_TIERS],
+    /// Minimum lock period of each tier, in seconds
+    pub tier_lock_periods: [i64; MAX_STAKE_TIERS],
+    /// Shortest lock period a stake may request, in seconds
+    pub min_lock_period: i64,
+    /// Longest lock period a stake may request, in seconds
+    pub max_lock_period: i64,
+    /// Minimum agent service fee
+    pub min_service_fee: u64,
+    /// Minimum MCP tool fee
+    pub min_tool_fee: u64,
+    /// Minimum MCP resource fee
+    pub min_resource_fee: u64,
+    /// Minimum MCP prompt fee
+    pub min_prompt_fee: u64,
+    /// Maximum bulk discount, in percent
+    pub max_bulk_discount: u8,
+    /// Lowest priority multiplier (100 = 1.0x)
+    pub min_priority_multiplier: u16,
+    /// Highest priority multiplier (100 = 1.0x)
+    pub max_priority_multiplier: u16,
+    /// Protocol take-rate on collected fees, in basis points
+    pub protocol_fee_bps: u16,
+    /// Seconds between an entry raising its fees and the raise applying
+    pub fee_increase_notice: i64,
+}
+
+/// Registry config account
+#[derive(Debug, Clone, BorshSerialize, BorshDeserialize, PartialEq)]
+pub struct RegistryConfig {
+    pub bump: u8,
+    /// Authority allowed to update the config
+    pub admin_authority: Pubkey,
+    pub params: RegistryConfigParams,
+    pub last_update_timestamp: i64,
+}
+
+impl RegistryConfig {
+    /// Size of the config account in bytes
+    pub const LEN: usize = 1 + 32 + 199 + 8;
+
+    /// Deserialize a config from account data
+    pub fn try_from_account_data(data: &[u8]) -> SdkResult<Self> {
+        Self::try_from_slice(data).map_err(|e| {
+            SdkError::DeserializationError(format!("Failed to deserialize RegistryConfig: {}", e))
+        })
+    }
+
+    /// Index of the highest tier `amount` reaches (0 = below the first tier)
+    pub fn tier_for_stake(&self, amount: u64) -> u8 {
+        self.params
+            .tier_thresholds
+            .iter()
+            .take_while(|&&threshold| amount >= threshold)
+            .count() as u8
+    }
+}
+
+/// Derive the registry config PDA of a registry program
+pub fn derive_registry_config_pda(program_id: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[REGISTRY_CONFIG_SEED], program_id).0
+}
+
+/// Token vaults of a registry program
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RegistryVaults {
+    pub staking_vault: Pubkey,
+    pub fee_vault: Pubkey,
+    pub registration_vault: Pubkey,
+    pub treasury_vault: Pubkey,
+}
+
+impl RegistryVaults {
+    /// Derive the vault PDAs of a registry program
+    pub fn derive(program_id: &Pubkey) -> Self {
+        let derive = |seed: &[u8]| Pubkey::find_program_address(&[seed], program_id).0;
+        Self {
+            staking_vault: derive(STAKING_VAULT_SEED),
+            fee_vault: derive(FEE_VAULT_SEED),
+            registration_vault: derive(REGISTRATION_VAULT_SEED),
+            treasury_vault: derive(TREASURY_VAULT_SEED),
+        }
+    }
+}
+
+/// Derive the ProgramData account of an upgradeable program, whose upgrade
+/// authority must sign the config initialization
+pub fn derive_program_data_address(program_id: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[program_id.as_ref()], &bpf_loader_upgradeable::id()).0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> RegistryConfig {
+        RegistryConfig {
+            bump: 254,
+            admin_authority: Pubkey::new_unique(),
+            params: RegistryConfigParams {
+                accepted_mint: Pubkey::new_unique(),
+                treasury: Pubkey::new_unique(),
+                registration_fee: 100,
+                tier_thresholds: [10, 20, 30, u64::MAX],
+                tier_lock_periods: [1, 2, 3, 4],
+                min_lock_period: 1,
+                max_lock_period: 10,
+                min_service_fee: 1,
+                min_tool_fee: 1,
+                min_resource_fee: 1,
+                min_prompt_fee: 1,
+                max_bulk_discount: 50,
+                min_priority_multiplier: 100,
+                max_priority_multiplier: 300,
+                protocol_fee_bps: 250,
+                fee_increase_notice: 3_600,
+            },
+            last_update_timestamp: 0,
+        }
+    }
+
+    #[test]
+    fn test_config_roundtrip() {
+        let config = config();
+        let data = config.try_to_vec().unwrap();
+        assert_eq!(data.len(), RegistryConfig::LEN);
+        assert_eq!(
+            RegistryConfig::try_from_account_data(&data).unwrap(),
+            config
+        );
+        assert!(RegistryConfig::try_from_account_data(&data[..10]).is_err());
+    }
+
+    #[test]
+    fn test_tier_for_stake() {
+        let config = config();
+        assert_eq!(config.tier_for_stake(9), 0);
+        assert_eq!(config.tier_for_stake(20), 2);
+        assert_eq!(config.tier_for_stake(u64::MAX - 1), 3);
+    }
+
+    #[test]
+    fn test_derive_vaults() {
+        let program_id = Pubkey::new_unique();
+        let vaults = RegistryVaults::derive(&program_id);
+        assert_eq!(
+            vaults.fee_vault,
+            Pubkey::find_program_address(&[b"fee_vault"], &program_id).0
+        );
+        assert_ne!(vaults.staking_vault, vaults.registration_vault);
+        assert_eq!(vaults, RegistryVaults::derive(&program_id));
+    }
+}
@@ -4,12 +4,19 @@
 //! and additional SDK-specific error handling.
 
 use solana_client::client_error::ClientError;
-use solana_sdk::program_error::ProgramError;
+use solana_sdk::{
+    instruction::InstructionError, program_error::ProgramError, signature::Signature,
+    transaction::TransactionError,
+};
+use std::time::Duration;
 use thiserror::Error;
 
 /// Result type alias for SDK operations
 pub type SdkResult<T> = Result<T, SdkError>;
 
+/// Custom program error code of `RegistryError::StateVersionMismatch` in both registries
+pub const STATE_VERSION_MISMATCH_CODE: u32 = 52;
+
 /// Main error type for the SDK
 #[derive(Error, Debug)]
 pub enum SdkError {
@@ -179,6 +186,15 @@ pub enum SdkError {
     #[error("Invalid MCP server status")]
     InvalidMcpServerStatus,
 
+    #[error("Too many supported modes (input/output) provided")]
+    TooManySupportedModes,
+
+    #[error("Supported mode string length is invalid")]
+    InvalidModeLength,
+
+    #[error("Bump seed not found in hash map")]
+    BumpSeedNotInHashMap,
+
     #[error("Server ID format is invalid (only alphanumeric, hyphens, and underscores allowed)")]
     InvalidServerIdFormat,
 
@@ -253,6 +269,87 @@ pub enum SdkError {
     #[error("Lock period too long")]
     LockPeriodTooLong,
 
+    #[error("Invalid lock period specified")]
+    InvalidLockPeriod,
+
+    #[error("Token program account is not the SPL Token program")]
+    InvalidTokenProgram,
+
+    #[error("Token account does not belong to the signer")]
+    TokenAccountOwnerMismatch,
+
+    #[error("Vault token account is not owned by its vault PDA")]
+    InvalidVaultAuthority,
+
+    #[error("Fee vault balance is below the fees owed from it")]
+    FeeVaultUndercollateralized,
+
+    #[error("Revenue split recipients are invalid or exceed 100%")]
+    InvalidRevenueSplit,
+
+    // Program authorization errors
+    #[error("Unauthorized program - not in authorized list")]
+    UnauthorizedProgram,
+
+    #[error("Invalid program account - not executable")]
+    InvalidProgramAccount,
+
+    #[error("Program signature verification failed")]
+    ProgramSignatureVerificationFailed,
+
+    #[error("Cross-program invocation authority mismatch")]
+    CpiAuthorityMismatch,
+
+    // Chunked registration errors
+    #[error("Too many overflow skill accounts")]
+    TooManySkillAccounts,
+
+    #[error("Too many overflow tool accounts")]
+    TooManyToolAccounts,
+
+    #[error("Overflow account index is out of sequence")]
+    InvalidOverflowIndex,
+
+    #[error("Entry is not accepting registration chunks")]
+    RegistrationNotOpen,
+
+    #[error("Entry registration has not been finalized")]
+    RegistrationNotFinalized,
+
+    #[error("Registry config parameters are inconsistent")]
+    InvalidRegistryConfig,
+
+    // Usage pricing and subscription errors
+    #[error("Priced item is not among the server's on-chain definitions")]
+    UnknownPricedItem,
+
+    #[error("Pricing table has duplicate items or invalid discount tiers")]
+    InvalidPricingTable,
+
+    #[error("Fee exceeds the maximum the caller agreed to")]
+    MaxFeeExceeded,
+
+    #[error("A charge with this idempotency key was already recorded")]
+    DuplicateIdempotencyKey,
+
+    #[error("Subscription plan has no price, no included calls or an invalid period")]
+    InvalidSubscriptionPlan,
+
+    #[error("Subscription plan does not accept subscriptions or renewals")]
+    SubscriptionPlanInactive,
+
+    #[error("User already holds a subscription to this server")]
+    SubscriptionAlreadyExists,
+
+    #[error("Free tier allows calls without a valid period")]
+    InvalidFreeTier,
+
+    #[error("Accepted mint cannot change once the vaults exist")]
+    AcceptedMintLocked,
+
+    #[error("Entry is stored in an older layout and must be migrated first")]
+    UnsupportedEntryVersion,
+
     // SDK-specific errors
     #[error("Invalid configuration: {0}")]
     InvalidConfiguration(String),
@@ -266,6 +363,26 @@ pub enum SdkError {
     #[error("Validation error: {0}")]
     ValidationError(String),
 
+    #[error("Transaction too large: {0} bytes exceeds the {1} byte limit")]
+    TransactionTooLarge(usize, usize),
+
+    /// The transaction may still land; check its signature before resending
+    #[error("Transaction {0} was not confirmed within {1:?}")]
+    ConfirmationTimeout(Signature, Duration),
+
+    #[error("Blockhash expired before transaction {0} was confirmed")]
+    BlockhashExpired(Signature),
+
+    /// A transaction's instruction failed. `error` is the registry error for
+    /// custom codes raised by a registry program; `logs` are the program
+    /// logs up to the failure, when the backend returned them.
+    #[error("Instruction {index} failed: {error}")]
+    InstructionFailed {
+        index: u8,
+        error: Box<SdkError>,
+        logs: Vec<String>,
+    },
+
     /// Unknown program error code - used for safe error catching
     #[error("Unknown program error code: {0}")]
     UnknownError(u32),
@@ -273,6 +390,8 @@ pub enum SdkError {
 
 impl SdkError {
     /// Convert a program error code to the corresponding SDK error
+    ///
+    /// Codes are the ordinals of the programs' shared `RegistryError`.
     pub fn from_program_error_code(code: u32) -> Self {
         match code {
             0 => SdkError::InvalidAgentIdLength,
@@ -285,10 +404,10 @@ impl SdkError {
             7 => SdkError::TooManyServiceEndpoints,
             8 => SdkError::InvalidEndpointProtocolLength,
             9 => SdkError::InvalidEndpointUrlLength,
-            10 => SdkError::TooManySupportedInputModes,
-            11 => SdkError::InvalidInputModeLength,
-            12 => SdkError::TooManySupportedOutputModes,
-            13 => SdkError::InvalidOutputModeLength,
+            10 => SdkError::MultipleDefaultEndpoints,
+            11 => SdkError::NoDefaultEndpoint,
+            12 => SdkError::TooManySupportedModes,
+            13 => SdkError::InvalidModeLength,
             14 => SdkError::TooManySkills,
             15 => SdkError::InvalidSkillIdLength,
             16 => SdkError::InvalidSkillNameLength,
@@ -300,34 +419,121 @@ impl SdkError {
             22 => SdkError::InvalidExtendedMetadataUriLength,
             23 => SdkError::TooManyTags,
             24 => SdkError::InvalidTagLength,
-            25 => SdkError::MultipleDefaultEndpoints,
-            26 => SdkError::NoDefaultEndpoint,
-            27 => SdkError::InvalidUrlFormat,
-            28 => SdkError::InvalidAgentStatus,
-            29 => SdkError::Unauthorized,
-            30 => SdkError::AccountAlreadyExists,
-            31 => SdkError::AccountNotFound,
-            32 => SdkError::InvalidAccountData,
-            33 => SdkError::InsufficientFunds,
-            34 => SdkError::InvalidPda,
-            35 => SdkError::MissingRequiredSignature,
-            36 => SdkError::StateVersionMismatch,
-            37 => SdkError::OperationInProgress,
-            38 => SdkError::IncorrectAccountOwner,
-            39 => SdkError::PdaCollision,
-            40 => SdkError::InsufficientStake,
-            41 => SdkError::StakeLocked,
-            42 => SdkError::FeeTooLow,
-            43 => SdkError::InvalidPriorityMultiplier,
-            44 => SdkError::TokenMintMismatch,
-            45 => SdkError::InsufficientTokenBalance,
-            46 => SdkError::InvalidTokenAccount,
-            47 => SdkError::TokenTransferFailed,
-            48 => SdkError::StakingPeriodNotExpired,
-            49 => SdkError::InvalidStakingTier,
-            50 => SdkError::LockPeriodTooShort,
-            51 => SdkError::LockPeriodTooLong,
+            25 => SdkError::InvalidServerIdLength,
+            26 => SdkError::InvalidServerCapabilitiesSummaryLength,
+            27 => SdkError::TooManyOnChainToolDefinitions,
+            28 => SdkError::InvalidToolNameLength,
+            29 => SdkError::TooManyToolTags,
+            30 => SdkError::InvalidToolTagLength,
+            31 => SdkError::TooManyOnChainResourceDefinitions,
+            32 => SdkError::InvalidResourceUriPatternLength,
+            33 => SdkError::TooManyResourceTags,
+            34 => SdkError::InvalidResourceTagLength,
+            35 => SdkError::TooManyOnChainPromptDefinitions,
+            36 => SdkError::InvalidPromptNameLength,
+            37 => SdkError::TooManyPromptTags,
+            38 => SdkError::InvalidPromptTagLength,
+            39 => SdkError::InvalidFullCapabilitiesUriLength,
+            40 => SdkError::TooManyTags,
+            41 => SdkError::InvalidTagLength,
+            42 => SdkError::InvalidAgentStatus,
+            43 => SdkError::InvalidMcpServerStatus,
+            44 => SdkError::BumpSeedNotInHashMap,
+            45 => SdkError::Unauthorized,
+            46 => SdkError::AccountAlreadyExists,
+            47 => SdkError::AccountNotFound,
+            48 => SdkError::InvalidAccountData,
+            49 => SdkError::InsufficientFunds,
+            50 => SdkError::InvalidPda,
+            51 => SdkError::MissingRequiredSignature,
+            STATE_VERSION_MISMATCH_CODE => SdkError::StateVersionMismatch,
+            53 => SdkError::OperationInProgress,
+            54 => SdkError::IncorrectAccountOwner,
+            55 => SdkError::PdaCollision,
+            56 => SdkError::InsufficientStake,
+            57 => SdkError::StakeLocked,
+            58 => SdkError::FeeTooLow,
+            59 => SdkError::InvalidPriorityMultiplier,
+            60 => SdkError::InvalidStakingTier,
+            61 => SdkError::InvalidTokenAccount,
+            62 => SdkError::TokenMintMismatch,
+            63 => SdkError::InsufficientTokenBalance,
+            64 => SdkError::InvalidLockPeriod,
+            65 => SdkError::UnauthorizedProgram,
+            66 => SdkError::InvalidProgramAccount,
+            67 => SdkError::ProgramSignatureVerificationFailed,
+            68 => SdkError::CpiAuthorityMismatch,
+            69 => SdkError::TooManySkillAccounts,
+            70 => SdkError::TooManyToolAccounts,
+            71 => SdkError::InvalidOverflowIndex,
+            72 => SdkError::RegistrationNotOpen,
+            73 => SdkError::RegistrationNotFinalized,
+            74 => SdkError::InvalidRegistryConfig,
+            75 => SdkError::InvalidTokenProgram,
+            76 => SdkError::TokenMintMismatch,
+            77 => SdkError::TokenAccountOwnerMismatch,
+            78 => SdkError::InvalidVaultAuthority,
+            79 => SdkError::FeeVaultUndercollateralized,
+            80 => SdkError::InvalidRevenueSplit,
+            81 => SdkError::UnknownPricedItem,
+            82 => SdkError::InvalidPricingTable,
+            83 => SdkError::MaxFeeExceeded,
+            84 => SdkError::DuplicateIdempotencyKey,
+            85 => SdkError::InvalidSubscriptionPlan,
+            86 => SdkError::SubscriptionPlanInactive,
+            87 => SdkError::SubscriptionAlreadyExists,
+            88 => SdkError::InvalidFreeTier,
+            89 => SdkError::AcceptedMintLocked,
+            90 => SdkError::UnsupportedEntryVersion,
             _ => SdkError::UnknownError(code),
         }
     }
+
+    /// Whether this error means the entry changed after it was read, so the
+    /// caller should re-read it and rebuild the instruction
+    pub fn is_state_version_mismatch(&self) -> bool {
+        match self {
+            SdkError::StateVersionMismatch => true,
+            SdkError::InstructionFailed { error, .. } => error.is_state_version_mismatch(),
+            SdkError::ClientError(e) => matches!(
+                e.get_transaction_error(),
+                Some(TransactionError::InstructionError(
+                    _,
+                    InstructionError::Custom(STATE_VERSION_MISMATCH_CODE)
+                ))
+            ),
+            _ => false,
+        }
+    }
+
+    /// Whether the transaction was dropped because its blockhash expired, so
+    /// it can be signed again over a fresh one
+    pub fn is_blockhash_expired(&self) -> bool {
+        match self {
+            SdkError::BlockhashExpired(_) => true,
+            SdkError::InstructionFailed { error, .. } => error.is_blockhash_expired(),
+            SdkError::ClientError(e) => matches!(
+                e.get_transaction_error(),
+                Some(TransactionError::BlockhashNotFound)
+            ),
+            _ => false,
+        }
+    }
+
+    /// Index of the failing instruction and its program logs, for errors
+    /// raised while executing a transaction
+    pub fn instruction_failure(&self) -> Option<(u8, &[String])> {
+        match self {
+            SdkError::InstructionFailed { index, logs, .. } => Some((*index, logs)),
+            _ => None,
+        }
+    }
+
+    /// The error behind an instruction failure, or `self` for any other error
+    pub fn root_cause(&self) -> &SdkError {
+        match self {
+            SdkError::InstructionFailed { error, .. } => error.root_cause(),
+            other => other,
+        }
+    }
 }
@@ -0,0 +1,73 @@
+//! Current and upcoming entry fees
+//!
+//! Fee increases on agent and MCP server entries are scheduled: the entry
+//! keeps charging its current fees until the registry's notice period has
+//! passed, then the pending fees replace them. Decreases apply at once. The
+//! program swaps in a due change the next time the entry is written, so the
+//! stored fees can lag; [`FeeSchedule::resolve`] reads them the way the
+//! program would at a given time.
+
+/// Fees an entry charges now and any increase scheduled to replace them
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FeeSchedule<T> {
+    /// Fees in force
+    pub current: T,
+    /// Fees that will replace them, if an increase is scheduled
+    pub upcoming: Option<T>,
+    /// When `upcoming` applies
+    pub effective_at: Option<i64>,
+}
+
+impl<T: Copy> FeeSchedule<T> {
+    /// Resolve stored fees at `now`. `effective_at` is 0 when no change is
+    /// pending; a pending change already due counts as current.
+    pub fn resolve(current: T, pending: T, effective_at: i64, now: i64) -> Self {
+        match effective_at {
+            0 => Self {
+                current,
+                upcoming: None,
+                effective_at: None,
+            },
+            at if now >= at => Self {
+                current: pending,
+                upcoming: None,
+                effective_at: None,
+            },
+            at => Self {
+                current,
+                upcoming: Some(pending),
+                effective_at: Some(at),
+            },
+        }
+    }
+
+    /// Fees in force at `timestamp`
+    pub fn at(&self, timestamp: i64) -> T {
+        match (self.upcoming, self.effective_at) {
+            (Some(upcoming), Some(at)) if timestamp >= at => upcoming,
+            _ => self.current,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_fee_schedule() {
+        let none = FeeSchedule::resolve(10, 20, 0, 500);
+        assert_eq!(none.current, 10);
+        assert_eq!(none.upcoming, None);
+
+        let scheduled = FeeSchedule::resolve(10, 20, 600, 500);
+        assert_eq!(scheduled.current, 10);
+        assert_eq!(scheduled.upcoming, Some(20));
+        assert_eq!(scheduled.at(599), 10);
+        assert_eq!(scheduled.at(600), 20);
+
+        let due = FeeSchedule::resolve(10, 20, 600, 600);
+        assert_eq!(due.current, 20);
+        assert_eq!(due.effective_at, None);
+    }
+}
@@ -16,6 +16,8 @@
 //! - `stream`: Enable streaming payment functionality
 //! - `pyg`: Enable pay-as-you-go payment functionality  
 //! - `prepay`: Enable prepaid payment functionality
+//! - `blocking`: Enable `BlockingClient`, a sync wrapper around the async client
+//! - `in-process`: Enable `InProcessRegistry`, both registry programs running in memory
 //!
 //! ## Example Usage
 //!
@@ -43,32 +45,73 @@
 //! ```
 
 // Core modules
+pub mod cache;
+pub mod chunking;
 pub mod client;
+pub mod cluster;
+pub mod config;
 pub mod errors;
+pub mod fees;
 pub mod idl;
+pub mod lookup_table;
+pub mod plan;
+pub mod receipt;
+pub mod rent;
+pub mod revenue;
+pub mod rpc;
+pub mod simulation;
+pub mod subscription;
+pub mod zero_copy;
 
 // Registry modules
 pub mod agent;
 pub mod mcp;
 
+// Sync facade
+#[cfg(feature = "blocking")]
+pub mod blocking;
+
 // Payment modules
 #[cfg(any(feature = "stream", feature = "pyg", feature = "prepay"))]
 pub mod payments;
 
 // Re-export commonly used types
+#[cfg(feature = "blocking")]
+pub use blocking::BlockingClient;
+pub use cache::{CacheConfig, CacheStats, CacheWatch, RegistryCache};
 pub use client::{deserialize_account_data, SolanaAiRegistriesClient};
+pub use cluster::{
+    ClientConfig, ClientConfigBuilder, Cluster, ComputeUnitPolicy, PriorityFeePolicy, RetryPolicy,
+    Timeouts,
+};
+pub use config::{RegistryConfig, RegistryConfigParams};
 pub use errors::{SdkError, SdkResult};
+pub use fees::FeeSchedule;
+pub use plan::{DurableNonce, TransactionPlan};
+pub use receipt::{new_idempotency_key, IdempotencyKey, UsageReceipt};
+pub use rent::RentEstimate;
+pub use revenue::{PayoutPreview, RevenueSplit, SplitRecipient};
+#[cfg(feature = "in-process")]
+pub use rpc::in_process::InProcessRegistry;
+pub use rpc::{RegistryRpc, SimulationResult};
+pub use simulation::{parse_events, RegistryEvent, SimulationReport};
+pub use subscription::{
+    RegistryEntry, RegistryFilter, RegistrySubscriber, RegistrySubscription, RegistryUpdate,
+};
 
 // Re-export agent types
 pub use agent::{
-    AgentArgs, AgentBuilder, AgentEntry, AgentPatch, AgentRegistry, AgentSkill, AgentStatus,
-    ServiceEndpoint,
+    AgentArgs, AgentBuilder, AgentEconomics, AgentEntry, AgentEntryPrefix, AgentPatch,
+    AgentRegistry, AgentServiceFees, AgentSkill, AgentSkillAccount, AgentStatus, ServiceEndpoint,
 };
 
 // Re-export MCP types
 pub use mcp::{
-    McpPromptDefinition, McpResourceDefinition, McpServerArgs, McpServerBuilder, McpServerEntry,
-    McpServerPatch, McpServerRegistry, McpServerStatus, McpToolDefinition,
+    DiscountTier, FeeReconciliation, FreeTier, ItemPriceInput, McpPricingTable,
+    McpPromptDefinition, McpResourceDefinition, McpServerArgs, McpServerBuilder,
+    McpServerEconomics, McpServerEntry, McpServerEntryPrefix, McpServerPatch, McpServerRegistry,
+    McpServerStatus, McpSubscription, McpSubscriptionPlan, McpToolAccount, McpToolDefinition,
+    McpUsageCostEstimate, McpUsageFees, McpUsageRecord, SubscriptionQuota, UsageCoverage,
 };
 
 // Re-export payment types conditionally
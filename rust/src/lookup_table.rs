@@ -0,0 +1,150 @@
+//! Address lookup tables for registry transactions
+//!
+//! Combined flows touch the registry configs, their vaults, the token mint
+//! and the token, system and sysvar accounts on top of the entry itself,
+//! which quickly outgrows a legacy transaction. A lookup table holding these
+//! static accounts lets v0 transactions refer to each with a one-byte index.
+//!
+//! [`registry_lookup_addresses`] lists what a registry table should hold.
+//! The client creates and extends such a table
+//! ([`crate::SolanaAiRegistriesClient::create_registry_lookup_table`],
+//! [`crate::SolanaAiRegistriesClient::extend_registry_lookup_table`]), and
+//! once [`ClientConfig::lookup_table`] names it, every transaction the client
+//! compiles is a v0 transaction resolving accounts through it. Addresses
+//! become usable the slot after they are added.
+
+use crate::cluster::ClientConfig;
+use crate::config::{derive_registry_config_pda, RegistryVaults};
+use crate::errors::{SdkError, SdkResult};
+use solana_sdk::{
+    account::Account,
+    address_lookup_table::{self, state::AddressLookupTable},
+    address_lookup_table_account::AddressLookupTableAccount,
+    pubkey::Pubkey,
+    system_program, sysvar,
+};
+
+/// Most addresses added by one extend instruction, so that the transaction
+/// stays within the size limit
+pub const MAX_ADDRESSES_PER_EXTEND: usize = 20;
+
+/// The static accounts registry instructions share: both registries' config
+/// and vault accounts, the token mint, and the token, associated token and
+/// system programs and the clock and rent sysvars. Program IDs that are
+/// invoked directly cannot be looked up, so the registry programs themselves
+/// are left out.
+pub fn registry_lookup_addresses(config: &ClientConfig) -> Vec<Pubkey> {
+    let registry = |program_id: &Pubkey| {
+        let vaults = RegistryVaults::derive(program_id);
+        [
+            derive_registry_config_pda(program_id),
+            vaults.staking_vault,
+            vaults.fee_vault,
+            vaults.registration_vault,
+            vaults.treasury_vault,
+        ]
+    };
+
+    let mut addresses: Vec<Pubkey> = Vec::new();
+    let candidates = registry(&config.agent_registry_program_id)
+        .into_iter()
+        .chain(registry(&config.mcp_server_registry_program_id))
+        .chain([
+            config.token_mint,
+            spl_token::id(),
+            spl_associated_token_account::id(),
+            system_program::id(),
+            sysvar::clock::id(),
+            sysvar::rent::id(),
+        ]);
+    for address in candidates {
+        if !addresses.contains(&address) {
+            addresses.push(address);
+        }
+    }
+    addresses
+}
+
+/// Decode the lookup table stored at `address`
+pub fn decode_lookup_table(
+    address: Pubkey,
+    account: &Account,
+) -> SdkResult<AddressLookupTableAccount> {
+    if account.owner != address_lookup_table::program::id() {
+        return Err(SdkError::IncorrectAccountOwner);
+    }
+    let table = AddressLookupTable::deserialize(&account.data).map_err(|e| {
+        SdkError::DeserializationError(format!("Failed to decode lookup table: {}", e))
+    })?;
+    Ok(AddressLookupTableAccount {
+        key: address,
+        addresses: table.addresses.to_vec(),
+    })
+}
+
+/// Addresses of `wanted` that `table` does not hold yet, in order
+pub fn missing_addresses(table: &AddressLookupTableAccount, wanted: &[Pubkey]) -> Vec<Pubkey> {
+    wanted
+        .iter()
+        .filter(|address| !table.addresses.contains(address))
+        .copied()
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cluster::Cluster;
+    use solana_sdk::address_lookup_table::state::LookupTableMeta;
+    use std::borrow::Cow;
+
+    #[test]
+    fn test_registry_lookup_addresses() {
+        let config = ClientConfig::builder(Cluster::Localnet)
+            .token_mint(Pubkey::new_unique())
+            .build()
+            .unwrap();
+        let addresses = registry_lookup_addresses(&config);
+
+        assert_eq!(addresses.len(), 16);
+        assert!(addresses.contains(&config.token_mint));
+        assert!(addresses.contains(&config.mcp_server_registry_vaults().fee_vault));
+        assert!(addresses.contains(&derive_registry_config_pda(
+            &config.agent_registry_program_id
+        )));
+        assert!(!addresses.contains(&config.agent_registry_program_id));
+    }
+
+    #[test]
+    fn test_decode_and_diff_lookup_table() {
+        let held = [Pubkey::new_unique(), Pubkey::new_unique()];
+        let table = AddressLookupTable {
+            meta: LookupTableMeta::new(Pubkey::new_unique()),
+            addresses: Cow::Borrowed(&held),
+        };
+        let mut account = Account {
+            lamports: 1,
+            data: table.serialize_for_tests().unwrap(),
+            owner: address_lookup_table::program::id(),
+            executable: false,
+            rent_epoch: 0,
+        };
+        let address = Pubkey::new_unique();
+
+        let decoded = decode_lookup_table(address, &account).unwrap();
+        assert_eq!(decoded.key, address);
+        assert_eq!(decoded.addresses, held);
+
+        let new = Pubkey::new_unique();
+        assert_eq!(
+            missing_addresses(&decoded, &[held[1], new, held[0]]),
+            vec![new]
+        );
+
+        account.owner = system_program::id();
+        assert!(matches!(
+            decode_lookup_table(address, &account),
+            Err(SdkError::IncorrectAccountOwner)
+        ));
+    }
+}
@@ -3,7 +3,19 @@
 //! This module provides high-level functions for interacting with the MCP Server Registry,
 //! including registration, updates, and queries for Model Context Protocol servers.
 
+use crate::chunking::{fits_in_transaction, split_to_fit};
+use crate::config::{
+    derive_program_data_address, derive_registry_config_pda, RegistryConfigParams, RegistryVaults,
+    FEE_VAULT_SEED,
+};
 use crate::errors::{SdkError, SdkResult};
+use crate::fees::FeeSchedule;
+use crate::receipt::{derive_usage_receipt_pda, IdempotencyKey};
+use crate::rent::RentEstimate;
+use crate::revenue::{bps_share, derive_revenue_split_pda, validate_split, SplitRecipient};
+use crate::zero_copy::{
+    check_entry_layout_version, impl_borsh_for_pod, read_prefix, EntryHeader, ENTRY_HEADER_LEN,
+};
 use borsh::{BorshDeserialize, BorshSerialize};
 use solana_sdk::{
     instruction::{AccountMeta, Instruction},
@@ -82,11 +94,561 @@ pub enum McpServerRegistryInstruction {
     },
     UpdateMcpServerDetails {
         details: McpServerUpdateDetailsInput,
+        expected_state_version: Option<u64>,
     },
     UpdateMcpServerStatus {
         new_status: u8,
+        expected_state_version: Option<u64>,
+    },
+    DeregisterMcpServer {
+        expected_state_version: Option<u64>,
+    },
+    RegisterMcpServerWithToken {
+        server_id: String,
+        name: String,
+        server_version: String,
+        service_endpoint: String,
+        documentation_url: Option<String>,
+        server_capabilities_summary: Option<String>,
+        supports_resources: bool,
+        supports_tools: bool,
+        supports_prompts: bool,
+        onchain_tool_definitions: Vec<McpToolDefinitionOnChainInput>,
+        onchain_resource_definitions: Vec<McpResourceDefinitionOnChainInput>,
+        onchain_prompt_definitions: Vec<McpPromptDefinitionOnChainInput>,
+        full_capabilities_uri: Option<String>,
+        tags: Vec<String>,
+    },
+    StakeForVerification {
+        amount: u64,
+        lock_period: i64,
+        expected_state_version: Option<u64>,
+    },
+    ConfigureUsageFees {
+        tool_base_fee: u64,
+        resource_base_fee: u64,
+        prompt_base_fee: u64,
+        bulk_discount_threshold: u32,
+        bulk_discount_percentage: u8,
+        free_tier: FreeTier,
+        expected_state_version: Option<u64>,
+    },
+    RecordUsageAndCollectFee {
+        usage_type: UsageType,
+        count: u32,
+        item_name: Option<String>,
+        max_total_fee: u64,
+        idempotency_key: IdempotencyKey,
+        expected_state_version: Option<u64>,
+    },
+    UpdateQualityMetrics {
+        uptime_percentage: u8,
+        avg_response_time: u32,
+        error_rate: u8,
+        expected_state_version: Option<u64>,
+    },
+    WithdrawPendingFees {
+        expected_state_version: Option<u64>,
+    },
+    InitializeToolAccount {
+        index: u8,
+        tool: McpToolDefinitionOnChainInput,
+    },
+    UpdateToolAccount {
+        index: u8,
+        tool: McpToolDefinitionOnChainInput,
+    },
+    CloseToolAccount {
+        index: u8,
+    },
+    BeginMcpServerRegistration {
+        server_id: String,
+        name: String,
+        server_version: String,
+        service_endpoint: String,
+        documentation_url: Option<String>,
+        server_capabilities_summary: Option<String>,
+        supports_resources: bool,
+        supports_tools: bool,
+        supports_prompts: bool,
+        full_capabilities_uri: Option<String>,
+        tags: Vec<String>,
+    },
+    AppendMcpToolDefinitions {
+        tools: Vec<McpToolDefinitionOnChainInput>,
+    },
+    AppendMcpResourceDefinitions {
+        resources: Vec<McpResourceDefinitionOnChainInput>,
+    },
+    AppendMcpPromptDefinitions {
+        prompts: Vec<McpPromptDefinitionOnChainInput>,
+    },
+    FinalizeMcpServerRegistration,
+    InitializeRegistryConfig {
+        params: RegistryConfigParams,
+    },
+    UpdateRegistryConfig {
+        params: RegistryConfigParams,
+        new_admin: Option<Pubkey>,
+    },
+    InitializeVaults,
+    InitializeServerFeeVault,
+    SetRevenueSplit {
+        recipients: Vec<SplitRecipient>,
+    },
+    WithdrawTreasury {
+        amount: u64,
+    },
+    SetUsagePricing {
+        item_prices: Vec<ItemPriceInput>,
+        discount_tiers: Vec<DiscountTier>,
+    },
+    SetSubscriptionPlan {
+        plan_id: u8,
+        price: u64,
+        period: i64,
+        included: SubscriptionQuota,
+        active: bool,
+    },
+    Subscribe {
+        plan_id: u8,
+        max_price: u64,
     },
-    DeregisterMcpServer,
+    RenewSubscription {
+        max_price: u64,
+    },
+    CancelSubscription,
+    MigrateMcpServerEntry,
+}
+
+/// Kind of MCP usage being paid for (matches on-chain format)
+#[derive(Debug, Clone, Copy, BorshSerialize, BorshDeserialize, PartialEq, Eq)]
+pub enum UsageType {
+    Tool,
+    Resource,
+    Prompt,
+}
+
+impl UsageType {
+    /// Byte stored for this type in a pricing table
+    pub fn to_u8(self) -> u8 {
+        match self {
+            UsageType::Tool => 0,
+            UsageType::Resource => 1,
+            UsageType::Prompt => 2,
+        }
+    }
+}
+
+/// Seed of an MCP server's pricing table PDA
+pub const PRICING_TABLE_SEED: &[u8] = b"pricing";
+
+/// Most items a pricing table can price: every inline tool, resource and prompt
+pub const MAX_PRICED_ITEMS: usize = MAX_ONCHAIN_TOOL_DEFINITIONS
+    + MAX_ONCHAIN_RESOURCE_DEFINITIONS
+    + MAX_ONCHAIN_PROMPT_DEFINITIONS;
+
+/// Most bulk discount tiers a pricing table can hold
+pub const MAX_DISCOUNT_TIERS: usize = 4;
+
+/// Price of one on-chain tool, resource or prompt (matches on-chain format)
+#[derive(Debug, Clone, BorshSerialize, BorshDeserialize, PartialEq, Eq)]
+pub struct ItemPriceInput {
+    pub usage_type: UsageType,
+    /// Tool or prompt name, or resource URI pattern
+    pub name: String,
+    /// Fee per use
+    pub price: u64,
+}
+
+/// Bulk discount applied once a single charge covers `threshold` uses
+#[derive(Debug, Clone, Copy, Default, BorshSerialize, BorshDeserialize, PartialEq, Eq)]
+pub struct DiscountTier {
+    pub threshold: u32,
+    pub percentage: u8,
+}
+
+/// Stored price of one item, keyed by [`hash_item_name`]
+#[derive(Debug, Clone, Copy, Default, BorshSerialize, BorshDeserialize, PartialEq, Eq)]
+pub struct ItemPrice {
+    pub usage_type: u8,
+    pub name_hash: [u8; HASH_SIZE],
+    pub price: u64,
+}
+
+/// Per-item prices and discount tiers of an MCP server (account data).
+/// A change that makes any use cost more is held in the `pending_` fields
+/// until `change_effective_at`.
+#[derive(Debug, Clone, BorshSerialize, BorshDeserialize, PartialEq)]
+pub struct McpPricingTable {
+    pub bump: u8,
+    pub server_entry: Pubkey,
+    pub item_count: u8,
+    pub items: [ItemPrice; MAX_PRICED_ITEMS],
+    pub tier_count: u8,
+    pub tiers: [DiscountTier; MAX_DISCOUNT_TIERS],
+    pub last_update_timestamp: i64,
+    pub pending_item_count: u8,
+    pub pending_items: [ItemPrice; MAX_PRICED_ITEMS],
+    pub pending_tier_count: u8,
+    pub pending_tiers: [DiscountTier; MAX_DISCOUNT_TIERS],
+    /// When the pending prices apply (0: none scheduled)
+    pub change_effective_at: i64,
+}
+
+impl McpPricingTable {
+    /// Try to deserialize from account data
+    pub fn try_from_account_data(data: &[u8]) -> SdkResult<Self> {
+        Self::deserialize(&mut &data[..]).map_err(|e| {
+            SdkError::DeserializationError(format!("Failed to deserialize pricing table: {}", e))
+        })
+    }
+
+    /// Replace the current prices with the pending ones if they are due at
+    /// `now`, as the program does before charging. Returns whether a change
+    /// was applied.
+    pub fn apply_due_price_change(&mut self, now: i64) -> bool {
+        if self.change_effective_at == 0 || now < self.change_effective_at {
+            return false;
+        }
+        self.item_count = self.pending_item_count;
+        self.items = self.pending_items;
+        self.tier_count = self.pending_tier_count;
+        self.tiers = self.pending_tiers;
+        self.last_update_timestamp = self.change_effective_at;
+        self.change_effective_at = 0;
+        true
+    }
+
+    /// Priced items in use
+    pub fn active_items(&self) -> &[ItemPrice] {
+        &self.items[..(self.item_count as usize).min(MAX_PRICED_ITEMS)]
+    }
+
+    /// Whether any item of `usage_type` has a price; such a type is charged
+    /// only for its priced items
+    pub fn prices_usage_type(&self, usage_type: UsageType) -> bool {
+        self.active_items()
+            .iter()
+            .any(|item| item.usage_type == usage_type.to_u8())
+    }
+
+    /// Price of the named item, if it has one
+    pub fn item_price(&self, usage_type: UsageType, name: &str) -> Option<u64> {
+        let name_hash = hash_item_name(name);
+        self.active_items()
+            .iter()
+            .find(|item| item.usage_type == usage_type.to_u8() && item.name_hash == name_hash)
+            .map(|item| item.price)
+    }
+
+    /// Discount tiers in use
+    pub fn active_tiers(&self) -> &[DiscountTier] {
+        &self.tiers[..(self.tier_count as usize).min(MAX_DISCOUNT_TIERS)]
+    }
+
+    /// Discount of the highest tier `count` reaches, or `None` when the
+    /// table has no tiers
+    pub fn discount_percentage(&self, count: u32) -> Option<u8> {
+        let tiers = self.active_tiers();
+        if tiers.is_empty() {
+            return None;
+        }
+        Some(
+            tiers
+                .iter()
+                .take_while(|tier| count >= tier.threshold)
+                .last()
+                .map_or(0, |tier| tier.percentage),
+        )
+    }
+}
+
+/// Seed of a user's usage record PDA on an MCP server
+pub const USAGE_RECORD_SEED: &[u8] = b"usage_record";
+
+/// Calls per usage type each user may make for free in every window of
+/// `period` seconds (matches on-chain format)
+#[derive(Debug, Clone, Copy, Default, BorshSerialize, BorshDeserialize, PartialEq, Eq)]
+pub struct FreeTier {
+    pub tool_calls: u32,
+    pub resource_calls: u32,
+    pub prompt_calls: u32,
+    /// Length of a window in seconds
+    pub period: i64,
+}
+
+impl FreeTier {
+    /// Free calls of the given type per window
+    pub fn allowance(&self, usage_type: UsageType) -> u32 {
+        match usage_type {
+            UsageType::Tool => self.tool_calls,
+            UsageType::Resource => self.resource_calls,
+            UsageType::Prompt => self.prompt_calls,
+        }
+    }
+}
+
+/// Usage of one MCP server by one user (account data)
+#[derive(Debug, Clone, BorshSerialize, BorshDeserialize, PartialEq)]
+pub struct McpUsageRecord {
+    pub bump: u8,
+    pub server_entry: Pubkey,
+    pub user: Pubkey,
+    /// Start of the current free-tier window
+    pub window_start: i64,
+    /// Calls made in the current window
+    pub window_calls: SubscriptionQuota,
+    pub total_tool_calls: u64,
+    pub total_resource_accesses: u64,
+    pub total_prompt_uses: u64,
+    /// Total calls the free tier covered
+    pub total_free_calls: u64,
+    /// Total fees the user paid, take-rate included
+    pub total_fees_paid: u64,
+    pub first_use_timestamp: i64,
+    pub last_use_timestamp: i64,
+}
+
+impl McpUsageRecord {
+    /// Size of the account
+    pub const LEN: usize = 1 + 32 + 32 + 8 + 4 * 3 + 8 * 5 + 8 + 8;
+
+    /// Try to deserialize from account data
+    pub fn try_from_account_data(data: &[u8]) -> SdkResult<Self> {
+        Self::deserialize(&mut &data[..]).map_err(|e| {
+            SdkError::DeserializationError(format!("Failed to deserialize usage record: {}", e))
+        })
+    }
+
+    /// Free calls of the given type left at `now`, counting a window that
+    /// has run its period as restarted
+    pub fn free_calls_left(&self, usage_type: UsageType, free_tier: &FreeTier, now: i64) -> u32 {
+        if now >= self.window_start.saturating_add(free_tier.period) {
+            return free_tier.allowance(usage_type);
+        }
+        free_tier
+            .allowance(usage_type)
+            .saturating_sub(self.window_calls.get(usage_type))
+    }
+}
+
+/// Uses of one charge that are not paid for
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct UsageCoverage {
+    /// Uses left in the caller's free-tier window, see
+    /// [`McpUsageRecord::free_calls_left`]
+    pub free_calls: u32,
+    /// Uses left in the caller's subscription, see
+    /// [`McpSubscription::covered_calls`]
+    pub subscription_calls: u32,
+}
+
+/// Seed of an MCP server's subscription plan PDAs
+pub const SUBSCRIPTION_PLAN_SEED: &[u8] = b"subscription_plan";
+
+/// Seed of a user's subscription PDA to an MCP server
+pub const SUBSCRIPTION_SEED: &[u8] = b"subscription";
+
+/// Calls per usage type, included in a subscription plan or left in a
+/// subscription (matches on-chain format)
+#[derive(Debug, Clone, Copy, Default, BorshSerialize, BorshDeserialize, PartialEq, Eq)]
+pub struct SubscriptionQuota {
+    pub tool_calls: u32,
+    pub resource_calls: u32,
+    pub prompt_calls: u32,
+}
+
+impl SubscriptionQuota {
+    /// Calls of the given type
+    pub fn get(&self, usage_type: UsageType) -> u32 {
+        match usage_type {
+            UsageType::Tool => self.tool_calls,
+            UsageType::Resource => self.resource_calls,
+            UsageType::Prompt => self.prompt_calls,
+        }
+    }
+}
+
+/// Subscription plan of an MCP server (account data). A higher price,
+/// shorter period or fewer included calls are held in the `pending_` fields
+/// until `change_effective_at`.
+#[derive(Debug, Clone, BorshSerialize, BorshDeserialize, PartialEq)]
+pub struct McpSubscriptionPlan {
+    pub bump: u8,
+    pub server_entry: Pubkey,
+    pub plan_id: u8,
+    /// Price of one period
+    pub price: u64,
+    /// Length of one period in seconds
+    pub period: i64,
+    /// Calls included in each period
+    pub included: SubscriptionQuota,
+    /// Whether the plan accepts subscriptions and renewals
+    pub active: bool,
+    pub last_update_timestamp: i64,
+    pub pending_price: u64,
+    pub pending_period: i64,
+    pub pending_included: SubscriptionQuota,
+    /// When the pending terms apply (0: none scheduled)
+    pub change_effective_at: i64,
+}
+
+impl McpSubscriptionPlan {
+    /// Try to deserialize from account data
+    pub fn try_from_account_data(data: &[u8]) -> SdkResult<Self> {
+        Self::deserialize(&mut &data[..]).map_err(|e| {
+            SdkError::DeserializationError(format!(
+                "Failed to deserialize subscription plan: {}",
+                e
+            ))
+        })
+    }
+
+    /// Replace the current terms with the pending ones if they are due at
+    /// `now`, as the program does before charging. Returns whether a change
+    /// was applied.
+    pub fn apply_due_terms_change(&mut self, now: i64) -> bool {
+        if self.change_effective_at == 0 || now < self.change_effective_at {
+            return false;
+        }
+        self.price = self.pending_price;
+        self.period = self.pending_period;
+        self.included = self.pending_included;
+        self.last_update_timestamp = self.change_effective_at;
+        self.change_effective_at = 0;
+        true
+    }
+}
+
+/// A user's subscription to an MCP server (account data)
+#[derive(Debug, Clone, BorshSerialize, BorshDeserialize, PartialEq)]
+pub struct McpSubscription {
+    pub bump: u8,
+    pub server_entry: Pubkey,
+    pub subscriber: Pubkey,
+    /// Plan the subscription is renewed on
+    pub plan_id: u8,
+    /// Start of the current uninterrupted run of periods
+    pub period_start: i64,
+    /// End of the last paid period
+    pub expires_at: i64,
+    /// Calls left until `expires_at`
+    pub remaining: SubscriptionQuota,
+}
+
+impl McpSubscription {
+    /// Try to deserialize from account data
+    pub fn try_from_account_data(data: &[u8]) -> SdkResult<Self> {
+        Self::deserialize(&mut &data[..]).map_err(|e| {
+            SdkError::DeserializationError(format!("Failed to deserialize subscription: {}", e))
+        })
+    }
+
+    /// Whether the last paid period is still running at `now`
+    pub fn is_active(&self, now: i64) -> bool {
+        now < self.expires_at
+    }
+
+    /// How many of `count` uses the subscription would cover at `now`; the
+    /// rest pay per-call fees
+    pub fn covered_calls(&self, usage_type: UsageType, count: u32, now: i64) -> u32 {
+        if !self.is_active(now) {
+            return 0;
+        }
+        self.remaining.get(usage_type).min(count)
+    }
+}
+
+/// Key of a priced item: the SHA256 of a tool or prompt name or of a
+/// resource URI pattern
+pub fn hash_item_name(name: &str) -> [u8; HASH_SIZE] {
+    solana_sdk::hash::hash(name.as_bytes()).to_bytes()
+}
+
+/// What one `RecordUsageAndCollectFee` would charge
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct McpUsageCostEstimate {
+    /// Fee per use before discounts
+    pub unit_price: u64,
+    /// Whether `unit_price` comes from the pricing table rather than the
+    /// entry's base fee
+    pub item_priced: bool,
+    /// Uses taken from the free tier or the caller's subscription rather
+    /// than paid for
+    pub coverage: UsageCoverage,
+    /// Bulk discount applied, in percent
+    pub discount_percentage: u8,
+    /// Total paid by the caller
+    pub total_fee: u64,
+    /// Part of the total paid into the treasury vault
+    pub protocol_fee: u64,
+    /// Part of the total credited to the server's pending fees
+    pub server_fee: u64,
+}
+
+/// Price `count` uses of an item the way the program does: the item's price
+/// from `pricing_table` if the table prices its usage type, else the entry's
+/// base fee, less the table's tiered discount or, without tiers, the entry's
+/// bulk discount. A usage type the table prices needs an `item_name` the
+/// table has a price for, or the charge fails with
+/// [`SdkError::UnknownPricedItem`].
+/// Pass the fees and prices in force when the charge lands, see
+/// [`McpServerEconomics::fee_schedule`] and
+/// [`McpPricingTable::apply_due_price_change`]. Free-tier calls, then subscription
+/// calls in `coverage` are used before fees apply.
+pub fn estimate_usage_cost(
+    fees: &McpUsageFees,
+    pricing_table: Option<&McpPricingTable>,
+    usage_type: UsageType,
+    item_name: Option<&str>,
+    count: u32,
+    coverage: UsageCoverage,
+    protocol_fee_bps: u16,
+) -> SdkResult<McpUsageCostEstimate> {
+    let free_calls = coverage.free_calls.min(count);
+    let subscription_calls = coverage.subscription_calls.min(count - free_calls);
+    let charged = count - free_calls - subscription_calls;
+    let item_price = match pricing_table {
+        Some(table) if table.prices_usage_type(usage_type) => Some(
+            item_name
+                .and_then(|name| table.item_price(usage_type, name))
+                .ok_or(SdkError::UnknownPricedItem)?,
+        ),
+        _ => None,
+    };
+    let unit_price = item_price.unwrap_or_else(|| fees.base_fee(usage_type));
+    if charged > 0 && unit_price == 0 {
+        return Err(SdkError::ValidationError(
+            "The server charges no fee for this usage".to_string(),
+        ));
+    }
+
+    // With tiers, a charge below the lowest one gets no discount at all
+    let discount_percentage =
+        match pricing_table.and_then(|table| table.discount_percentage(charged)) {
+            Some(tier_percentage) => tier_percentage,
+            None if charged >= fees.bulk_discount_threshold => fees.bulk_discount_percentage,
+            None => 0,
+        };
+    let gross = unit_price
+        .checked_mul(charged as u64)
+        .ok_or_else(|| SdkError::ValidationError("Usage fee overflows u64".to_string()))?;
+    let total_fee = gross - (gross as u128 * discount_percentage as u128 / 100) as u64;
+    let protocol_fee = bps_share(total_fee, protocol_fee_bps);
+
+    Ok(McpUsageCostEstimate {
+        unit_price,
+        item_priced: item_price.is_some(),
+        coverage: UsageCoverage {
+            free_calls,
+            subscription_calls,
+        },
+        discount_percentage,
+        total_fee,
+        protocol_fee,
+        server_fee: total_fee - protocol_fee,
+    })
 }
 
 /// Maximum length constants (from the on-chain program)
@@ -182,6 +744,10 @@ pub const MAX_SERVER_TAGS: usize = 10;
 /// e.g., "nlp", "database", "api", "workflow"
 pub const MAX_SERVER_TAG_LEN: usize = 32;
 
+/// Maximum number of overflow tool accounts per MCP server (64 accounts)
+/// Each holds one tool beyond the `MAX_ONCHAIN_TOOL_DEFINITIONS` stored in the entry itself.
+pub const MAX_TOOL_ACCOUNTS: usize = 64;
+
 /// MCP Server status values
 #[repr(u8)]
 #[derive(Debug, Clone, Copy, PartialEq, Eq, BorshSerialize, BorshDeserialize)]
@@ -298,6 +864,15 @@ impl From<McpToolDefinition> for McpToolDefinitionOnChainInput {
     }
 }
 
+impl From<McpToolDefinitionOnChainInput> for McpToolDefinition {
+    fn from(tool: McpToolDefinitionOnChainInput) -> Self {
+        Self {
+            name: tool.name,
+            tags: tool.tags,
+        }
+    }
+}
+
 impl From<McpResourceDefinition> for McpResourceDefinitionOnChainInput {
     fn from(resource: McpResourceDefinition) -> Self {
         Self {
@@ -366,6 +941,73 @@ pub struct McpServerArgs {
     pub tags: Vec<String>,
 }
 
+/// Registration and last-update timestamps
+const MCP_SERVER_ENTRY_TIMESTAMPS_LEN: usize = 8 + 8;
+
+/// Trailing overflow tool account count and chunked registration flag
+const MCP_SERVER_ENTRY_TRAILER_LEN: usize = 1 + 1;
+
+impl McpServerArgs {
+    /// Size in bytes of the entry account the program allocates when these
+    /// args are registered
+    pub fn account_size(&self) -> SdkResult<usize> {
+        self.variable_fields_len()
+            .map(|len| {
+                McpServerEntryPrefix::LEN
+                    + len
+                    + MCP_SERVER_ENTRY_TIMESTAMPS_LEN
+                    + MCP_SERVER_ENTRY_TRAILER_LEN
+            })
+            .map_err(|e| {
+                SdkError::SerializationError(format!("Failed to size MCP server entry: {}", e))
+            })
+    }
+
+    /// Estimate the rent-exempt deposit for registering these args, using the
+    /// default rent parameters
+    pub fn estimate_rent(&self) -> SdkResult<RentEstimate> {
+        Ok(RentEstimate::from_default_rent(self.account_size()?))
+    }
+
+    fn variable_fields_len(&self) -> std::io::Result<usize> {
+        let tools: Vec<McpToolDefinitionOnChainInput> = self
+            .onchain_tool_definitions
+            .iter()
+            .cloned()
+            .map(Into::into)
+            .collect();
+        let resources: Vec<McpResourceDefinitionOnChainInput> = self
+            .onchain_resource_definitions
+            .iter()
+            .cloned()
+            .map(Into::into)
+            .collect();
+        let prompts: Vec<McpPromptDefinitionOnChainInput> = self
+            .onchain_prompt_definitions
+            .iter()
+            .cloned()
+            .map(Into::into)
+            .collect();
+
+        let mut buf = Vec::new();
+        self.server_id.serialize(&mut buf)?;
+        self.name.serialize(&mut buf)?;
+        self.server_version.serialize(&mut buf)?;
+        self.service_endpoint.serialize(&mut buf)?;
+        self.documentation_url.serialize(&mut buf)?;
+        self.server_capabilities_summary.serialize(&mut buf)?;
+        self.supports_resources.serialize(&mut buf)?;
+        self.supports_tools.serialize(&mut buf)?;
+        self.supports_prompts.serialize(&mut buf)?;
+        tools.serialize(&mut buf)?;
+        resources.serialize(&mut buf)?;
+        prompts.serialize(&mut buf)?;
+        self.full_capabilities_uri.serialize(&mut buf)?;
+        self.tags.serialize(&mut buf)?;
+        Ok(buf.len())
+    }
+}
+
 /// Patch for updating MCP server details
 #[derive(Debug, Clone, Default)]
 pub struct McpServerPatch {
@@ -387,13 +1029,16 @@ pub struct McpServerPatch {
     pub tags: Option<Vec<String>>,
 }
 
-/// MCP Server registry entry (account data)
+/// MCP server registry entry (account data, matches the on-chain layout)
 #[derive(Debug, Clone, BorshSerialize, BorshDeserialize)]
 pub struct McpServerEntry {
     pub bump: u8,
     pub registry_version: u8,
     pub state_version: u64,
+    pub operation_in_progress: bool,
     pub owner_authority: Pubkey,
+    pub status: McpServerStatus,
+    pub economics: McpServerEconomics,
     pub server_id: String,
     pub name: String,
     pub server_version: String,
@@ -403,20 +1048,178 @@ pub struct McpServerEntry {
     pub supports_resources: bool,
     pub supports_tools: bool,
     pub supports_prompts: bool,
-    pub onchain_tool_definitions: Vec<McpToolDefinition>,
-    pub onchain_resource_definitions: Vec<McpResourceDefinition>,
-    pub onchain_prompt_definitions: Vec<McpPromptDefinition>,
-    pub full_capabilities_uri: Option<String>,
-    pub tags: Vec<String>,
-    pub status: McpServerStatus,
+    pub onchain_tool_definitions: Vec<McpToolDefinitionOnChainInput>,
+    pub onchain_resource_definitions: Vec<McpResourceDefinitionOnChainInput>,
+    pub onchain_prompt_definitions: Vec<McpPromptDefinitionOnChainInput>,
     pub registration_timestamp: i64,
     pub last_update_timestamp: i64,
+    pub full_capabilities_uri: Option<String>,
+    pub tags: Vec<String>,
+    /// Number of overflow tool accounts linked to this entry
+    pub tool_account_count: u8,
+    /// Set while a chunked registration is still accepting appended data
+    pub registration_open: bool,
 }
 
 impl McpServerEntry {
+    /// Try to deserialize from account data. Entries in an older layout are
+    /// rejected with [`SdkError::UnsupportedEntryVersion`].
+    pub fn try_from_account_data(data: &[u8]) -> SdkResult<Self> {
+        check_entry_layout_version(data)?;
+        Self::deserialize(&mut &data[..]).map_err(|e| {
+            SdkError::DeserializationError(format!("Failed to deserialize MCP server entry: {}", e))
+        })
+    }
+}
+
+/// Token, usage, fee and quality fields of an MCP server entry (matches the
+/// on-chain layout). They sit at a fixed offset, so they can be read without
+/// decoding the rest of the entry.
+#[repr(C, packed)]
+#[derive(Clone, Copy, Debug, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct McpServerEconomics {
+    pub token_mint: Pubkey,
+    pub verification_stake: u64,
+    pub staking_timestamp: i64,
+    pub stake_locked_until: i64,
+    pub verification_tier: u8,
+    pub total_tool_calls: u64,
+    pub total_resource_accesses: u64,
+    pub total_prompt_uses: u64,
+    pub total_fees_collected: u64,
+    pub quality_score: u64,
+    pub uptime_percentage: u8,
+    pub avg_response_time: u32,
+    pub error_rate: u8,
+    pub tool_base_fee: u64,
+    pub resource_base_fee: u64,
+    pub prompt_base_fee: u64,
+    pub bulk_discount_threshold: u32,
+    pub bulk_discount_percentage: u8,
+    pub registration_fee_paid: u64,
+    pub last_fee_collection: i64,
+    pub pending_fees: u64,
+    pub pending_tool_base_fee: u64,
+    pub pending_resource_base_fee: u64,
+    pub pending_prompt_base_fee: u64,
+    pub pending_bulk_discount_threshold: u32,
+    pub pending_bulk_discount_percentage: u8,
+    /// When the pending fees apply (0: none scheduled)
+    pub fee_change_effective_at: i64,
+    pub free_tool_calls: u32,
+    pub free_resource_calls: u32,
+    pub free_prompt_calls: u32,
+    /// Length of a free-tier window in seconds (0: no free tier)
+    pub free_tier_period: i64,
+}
+
+impl_borsh_for_pod!(McpServerEconomics);
+
+/// Base fees and bulk discount of an MCP server
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct McpUsageFees {
+    pub tool_base_fee: u64,
+    pub resource_base_fee: u64,
+    pub prompt_base_fee: u64,
+    /// Uses per charge needed for the bulk discount
+    pub bulk_discount_threshold: u32,
+    /// Bulk discount, in percent
+    pub bulk_discount_percentage: u8,
+}
+
+impl McpUsageFees {
+    /// Base fee per use of the given type
+    pub fn base_fee(&self, usage_type: UsageType) -> u64 {
+        match usage_type {
+            UsageType::Tool => self.tool_base_fee,
+            UsageType::Resource => self.resource_base_fee,
+            UsageType::Prompt => self.prompt_base_fee,
+        }
+    }
+}
+
+impl McpServerEconomics {
+    /// Free calls each user gets per window
+    pub fn free_tier(&self) -> FreeTier {
+        FreeTier {
+            tool_calls: self.free_tool_calls,
+            resource_calls: self.free_resource_calls,
+            prompt_calls: self.free_prompt_calls,
+            period: self.free_tier_period,
+        }
+    }
+
+    /// Current and upcoming usage fees as of `now`
+    pub fn fee_schedule(&self, now: i64) -> FeeSchedule<McpUsageFees> {
+        FeeSchedule::resolve(
+            McpUsageFees {
+                tool_base_fee: self.tool_base_fee,
+                resource_base_fee: self.resource_base_fee,
+                prompt_base_fee: self.prompt_base_fee,
+                bulk_discount_threshold: self.bulk_discount_threshold,
+                bulk_discount_percentage: self.bulk_discount_percentage,
+            },
+            McpUsageFees {
+                tool_base_fee: self.pending_tool_base_fee,
+                resource_base_fee: self.pending_resource_base_fee,
+                prompt_base_fee: self.pending_prompt_base_fee,
+                bulk_discount_threshold: self.pending_bulk_discount_threshold,
+                bulk_discount_percentage: self.pending_bulk_discount_percentage,
+            },
+            self.fee_change_effective_at,
+            now,
+        )
+    }
+}
+
+/// Fixed-size prefix of an MCP server entry: the common header followed by
+/// the server's economics
+#[repr(C, packed)]
+#[derive(Clone, Copy, Debug, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct McpServerEntryPrefix {
+    pub header: EntryHeader,
+    pub economics: McpServerEconomics,
+}
+
+impl McpServerEntryPrefix {
+    /// Length of the prefix in bytes
+    pub const LEN: usize = std::mem::size_of::<Self>();
+
+    /// Offset of the economics fields within the account data
+    pub const ECONOMICS_OFFSET: usize = ENTRY_HEADER_LEN;
+
+    /// Read the prefix from the start of an MCP server entry's account data.
+    /// Only the first [`Self::LEN`] bytes are needed. Entries in an older
+    /// layout are rejected with [`SdkError::UnsupportedEntryVersion`].
+    pub fn from_account_data(data: &[u8]) -> SdkResult<Self> {
+        let prefix: Self = read_prefix(data)?;
+        prefix.header.check_layout_version()?;
+        Ok(prefix)
+    }
+
+    /// Server status, if the stored byte is a known value
+    pub fn status(&self) -> Option<McpServerStatus> {
+        McpServerStatus::from_u8(self.header.status)
+    }
+}
+
+/// Overflow tool account (account data), one tool per account linked to an
+/// MCP server entry by `[b"tool", server_entry, index]`
+#[derive(Debug, Clone, BorshSerialize, BorshDeserialize, PartialEq)]
+pub struct McpToolAccount {
+    pub bump: u8,
+    pub server_entry: Pubkey,
+    pub index: u8,
+    pub tool: McpToolDefinitionOnChainInput,
+    pub last_update_timestamp: i64,
+}
+
+impl McpToolAccount {
     /// Try to deserialize from account data
     pub fn try_from_account_data(data: &[u8]) -> SdkResult<Self> {
-        crate::client::deserialize_account_data(data, "MCP server entry")
+        Self::deserialize(&mut &data[..]).map_err(|e| {
+            SdkError::DeserializationError(format!("Failed to deserialize tool account: {}", e))
+        })
     }
 }
 
@@ -630,23 +1433,48 @@ impl McpServerBuilder {
 pub struct McpServerRegistry;
 
 impl McpServerRegistry {
-    /// Create a register MCP server instruction
+    /// Create a register MCP server instruction, with `payer` funding the
+    /// entry
     pub fn register(
         program_id: &Pubkey,
         owner: &Pubkey,
+        payer: &Pubkey,
+        args: McpServerArgs,
+    ) -> SdkResult<Instruction> {
+        create_register_mcp_server_instruction(program_id, owner, payer, args)
+    }
+
+    /// Create a register MCP server instruction that also charges the
+    /// registry's registration fee from `owner_token_account`, with `payer`
+    /// funding the entry
+    pub fn register_with_token(
+        program_id: &Pubkey,
+        owner: &Pubkey,
+        payer: &Pubkey,
+        owner_token_account: &Pubkey,
+        token_mint: &Pubkey,
         args: McpServerArgs,
     ) -> SdkResult<Instruction> {
-        create_register_mcp_server_instruction(program_id, owner, args)
+        create_register_mcp_server_with_token_instruction(
+            program_id,
+            owner,
+            payer,
+            owner_token_account,
+            token_mint,
+            args,
+        )
     }
 
-    /// Create an update MCP server instruction
+    /// Create an update MCP server instruction, with `payer` funding any
+    /// growth of the entry
     pub fn update(
         program_id: &Pubkey,
         owner: &Pubkey,
+        payer: &Pubkey,
         server_id: &str,
         patch: McpServerPatch,
     ) -> SdkResult<Instruction> {
-        create_update_mcp_server_instruction(program_id, owner, server_id, patch)
+        create_update_mcp_server_instruction(program_id, owner, payer, server_id, patch, None)
     }
 
     /// Create an update MCP server status instruction
@@ -656,7 +1484,7 @@ impl McpServerRegistry {
         server_id: &str,
         status: u8,
     ) -> SdkResult<Instruction> {
-        create_update_mcp_server_status_instruction(program_id, owner, server_id, status)
+        create_update_mcp_server_status_instruction(program_id, owner, server_id, status, None)
     }
 
     /// Create a deregister MCP server instruction
@@ -665,7 +1493,7 @@ impl McpServerRegistry {
         owner: &Pubkey,
         server_id: &str,
     ) -> SdkResult<Instruction> {
-        create_deregister_mcp_server_instruction(program_id, owner, server_id)
+        create_deregister_mcp_server_instruction(program_id, owner, server_id, None)
     }
 
     /// Derive the PDA for an MCP server
@@ -678,6 +1506,73 @@ impl McpServerRegistry {
     }
 }
 
+/// Derive the PDA of an MCP server's overflow tool account at `index`
+pub fn derive_tool_account_pda(program_id: &Pubkey, server_entry: &Pubkey, index: u8) -> Pubkey {
+    let (pda, _) =
+        Pubkey::find_program_address(&[b"tool", server_entry.as_ref(), &[index]], program_id);
+    pda
+}
+
+/// Derive the fee vault of an MCP server entry, `[b"fee_vault", server_entry]`
+pub fn derive_server_fee_vault_pda(program_id: &Pubkey, server_entry: &Pubkey) -> Pubkey {
+    let (pda, _) =
+        Pubkey::find_program_address(&[FEE_VAULT_SEED, server_entry.as_ref()], program_id);
+    pda
+}
+
+/// Derive the pricing table of an MCP server entry, `[b"pricing", server_entry]`
+pub fn derive_pricing_table_pda(program_id: &Pubkey, server_entry: &Pubkey) -> Pubkey {
+    let (pda, _) =
+        Pubkey::find_program_address(&[PRICING_TABLE_SEED, server_entry.as_ref()], program_id);
+    pda
+}
+
+/// Derive a subscription plan of an MCP server entry,
+/// `[b"subscription_plan", server_entry, plan_id]`
+pub fn derive_subscription_plan_pda(
+    program_id: &Pubkey,
+    server_entry: &Pubkey,
+    plan_id: u8,
+) -> Pubkey {
+    let (pda, _) = Pubkey::find_program_address(
+        &[SUBSCRIPTION_PLAN_SEED, server_entry.as_ref(), &[plan_id]],
+        program_id,
+    );
+    pda
+}
+
+/// Derive the subscription of `subscriber` to an MCP server entry,
+/// `[b"subscription", server_entry, subscriber]`
+pub fn derive_subscription_pda(
+    program_id: &Pubkey,
+    server_entry: &Pubkey,
+    subscriber: &Pubkey,
+) -> Pubkey {
+    let (pda, _) = Pubkey::find_program_address(
+        &[
+            SUBSCRIPTION_SEED,
+            server_entry.as_ref(),
+            subscriber.as_ref(),
+        ],
+        program_id,
+    );
+    pda
+}
+
+/// Derive the usage record of `user` on an MCP server entry,
+/// `[b"usage_record", server_entry, user]`
+pub fn derive_usage_record_pda(
+    program_id: &Pubkey,
+    server_entry: &Pubkey,
+    user: &Pubkey,
+) -> Pubkey {
+    let (pda, _) = Pubkey::find_program_address(
+        &[USAGE_RECORD_SEED, server_entry.as_ref(), user.as_ref()],
+        program_id,
+    );
+    pda
+}
+
 /// Derive MCP server PDA
 pub fn derive_mcp_server_pda(
     program_id: &Pubkey,
@@ -700,10 +1595,44 @@ pub fn derive_mcp_server_pda_with_bump(
     Ok((pda, bump))
 }
 
-/// Create register MCP server instruction
+/// Fee position of one MCP server: what its entry says it is owed against
+/// what its fee vault actually holds
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FeeReconciliation {
+    pub server_entry: Pubkey,
+    pub fee_vault: Pubkey,
+    /// Fees recorded in the entry and not yet withdrawn
+    pub pending_fees: u64,
+    /// Token balance of the fee vault, or `None` if the vault does not exist
+    pub vault_balance: Option<u64>,
+}
+
+impl FeeReconciliation {
+    /// Whether the vault holds at least the pending fees
+    pub fn is_solvent(&self) -> bool {
+        self.shortfall() == 0
+    }
+
+    /// Pending fees the vault cannot cover
+    pub fn shortfall(&self) -> u64 {
+        self.pending_fees
+            .saturating_sub(self.vault_balance.unwrap_or(0))
+    }
+
+    /// Vault balance beyond the pending fees
+    pub fn surplus(&self) -> u64 {
+        self.vault_balance
+            .unwrap_or(0)
+            .saturating_sub(self.pending_fees)
+    }
+}
+
+/// Create register MCP server instruction. `payer` funds the entry's rent
+/// and may be a different signer than the owner.
 pub fn create_register_mcp_server_instruction(
     program_id: &Pubkey,
     owner: &Pubkey,
+    payer: &Pubkey,
     args: McpServerArgs,
 ) -> SdkResult<Instruction> {
     let server_pda = derive_mcp_server_pda(program_id, owner, &args.server_id)?;
@@ -711,7 +1640,7 @@ pub fn create_register_mcp_server_instruction(
     let accounts = vec![
         AccountMeta::new(server_pda, false),
         AccountMeta::new_readonly(*owner, true),
-        AccountMeta::new(*owner, true), // payer
+        AccountMeta::new(*payer, true),
         AccountMeta::new_readonly(system_program::id(), false),
     ];
 
@@ -756,22 +1685,235 @@ pub fn create_register_mcp_server_instruction(
     })
 }
 
-/// Create update MCP server instruction
-pub fn create_update_mcp_server_instruction(
+/// Create an instruction that registers an MCP server and charges the
+/// registry's registration fee from `owner_token_account` into the
+/// registration vault. `token_mint` must be the registry's accepted mint;
+/// `payer` funds the entry's rent.
+pub fn create_register_mcp_server_with_token_instruction(
     program_id: &Pubkey,
     owner: &Pubkey,
-    server_id: &str,
-    patch: McpServerPatch,
+    payer: &Pubkey,
+    owner_token_account: &Pubkey,
+    token_mint: &Pubkey,
+    args: McpServerArgs,
 ) -> SdkResult<Instruction> {
-    let server_pda = derive_mcp_server_pda(program_id, owner, server_id)?;
+    let server_pda = derive_mcp_server_pda(program_id, owner, &args.server_id)?;
 
     let accounts = vec![
         AccountMeta::new(server_pda, false),
         AccountMeta::new_readonly(*owner, true),
+        AccountMeta::new(*payer, true),
+        AccountMeta::new(*owner_token_account, false),
+        AccountMeta::new(RegistryVaults::derive(program_id).registration_vault, false),
+        AccountMeta::new_readonly(*token_mint, false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+        AccountMeta::new_readonly(system_program::id(), false),
+        AccountMeta::new_readonly(solana_sdk::sysvar::clock::id(), false),
+        AccountMeta::new_readonly(derive_registry_config_pda(program_id), false),
     ];
 
-    let instruction = McpServerRegistryInstruction::UpdateMcpServerDetails {
+    let instruction = McpServerRegistryInstruction::RegisterMcpServerWithToken {
+        server_id: args.server_id,
+        name: args.name,
+        server_version: args.server_version,
+        service_endpoint: args.service_endpoint,
+        documentation_url: args.documentation_url,
+        server_capabilities_summary: args.server_capabilities_summary,
+        supports_resources: args.supports_resources,
+        supports_tools: args.supports_tools,
+        supports_prompts: args.supports_prompts,
+        onchain_tool_definitions: args
+            .onchain_tool_definitions
+            .into_iter()
+            .map(|t| t.into())
+            .collect(),
+        onchain_resource_definitions: args
+            .onchain_resource_definitions
+            .into_iter()
+            .map(|r| r.into())
+            .collect(),
+        onchain_prompt_definitions: args
+            .onchain_prompt_definitions
+            .into_iter()
+            .map(|p| p.into())
+            .collect(),
+        full_capabilities_uri: args.full_capabilities_uri,
+        tags: args.tags,
+    };
+
+    let data = instruction.try_to_vec().map_err(|e| {
+        SdkError::SerializationError(format!("Failed to serialize instruction: {}", e))
+    })?;
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Create update MCP server instruction
+///
+/// When `expected_state_version` is set the program rejects the update with
+/// `StateVersionMismatch` if the entry changed since it was read.
+pub fn create_update_mcp_server_instruction(
+    program_id: &Pubkey,
+    owner: &Pubkey,
+    payer: &Pubkey,
+    server_id: &str,
+    patch: McpServerPatch,
+    expected_state_version: Option<u64>,
+) -> SdkResult<Instruction> {
+    let server_pda = derive_mcp_server_pda(program_id, owner, server_id)?;
+
+    let accounts = vec![
+        AccountMeta::new(server_pda, false),
+        AccountMeta::new_readonly(*owner, true),
+        AccountMeta::new(*payer, true), // payer for account resizing
+        AccountMeta::new_readonly(system_program::id(), false),
+    ];
+
+    let instruction = McpServerRegistryInstruction::UpdateMcpServerDetails {
         details: patch.into(),
+        expected_state_version,
+    };
+
+    let data = instruction.try_to_vec().map_err(|e| {
+        SdkError::SerializationError(format!("Failed to serialize instruction: {}", e))
+    })?;
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Create update MCP server status instruction
+pub fn create_update_mcp_server_status_instruction(
+    program_id: &Pubkey,
+    owner: &Pubkey,
+    server_id: &str,
+    status: u8,
+    expected_state_version: Option<u64>,
+) -> SdkResult<Instruction> {
+    let server_pda = derive_mcp_server_pda(program_id, owner, server_id)?;
+
+    let accounts = vec![
+        AccountMeta::new(server_pda, false),
+        AccountMeta::new_readonly(*owner, true),
+    ];
+
+    let instruction = McpServerRegistryInstruction::UpdateMcpServerStatus {
+        new_status: status,
+        expected_state_version,
+    };
+
+    let data = instruction.try_to_vec().map_err(|e| {
+        SdkError::SerializationError(format!("Failed to serialize instruction: {}", e))
+    })?;
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Create deregister MCP server instruction
+pub fn create_deregister_mcp_server_instruction(
+    program_id: &Pubkey,
+    owner: &Pubkey,
+    server_id: &str,
+    expected_state_version: Option<u64>,
+) -> SdkResult<Instruction> {
+    let server_pda = derive_mcp_server_pda(program_id, owner, server_id)?;
+
+    let accounts = vec![
+        AccountMeta::new(server_pda, false),
+        AccountMeta::new_readonly(*owner, true),
+    ];
+
+    let instruction = McpServerRegistryInstruction::DeregisterMcpServer {
+        expected_state_version,
+    };
+
+    let data = instruction.try_to_vec().map_err(|e| {
+        SdkError::SerializationError(format!("Failed to serialize instruction: {}", e))
+    })?;
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Create an instruction that adds an overflow tool account
+///
+/// `index` must equal the number of tool accounts the server already has.
+pub fn create_initialize_tool_account_instruction(
+    program_id: &Pubkey,
+    owner: &Pubkey,
+    payer: &Pubkey,
+    server_id: &str,
+    index: u8,
+    tool: McpToolDefinition,
+) -> SdkResult<Instruction> {
+    let server_pda = derive_mcp_server_pda(program_id, owner, server_id)?;
+
+    let accounts = vec![
+        AccountMeta::new(
+            derive_tool_account_pda(program_id, &server_pda, index),
+            false,
+        ),
+        AccountMeta::new(server_pda, false),
+        AccountMeta::new_readonly(*owner, true),
+        AccountMeta::new(*payer, true),
+        AccountMeta::new_readonly(system_program::id(), false),
+    ];
+
+    let instruction = McpServerRegistryInstruction::InitializeToolAccount {
+        index,
+        tool: tool.into(),
+    };
+
+    let data = instruction.try_to_vec().map_err(|e| {
+        SdkError::SerializationError(format!("Failed to serialize instruction: {}", e))
+    })?;
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Create an instruction that replaces the tool held by an overflow tool account
+pub fn create_update_tool_account_instruction(
+    program_id: &Pubkey,
+    owner: &Pubkey,
+    payer: &Pubkey,
+    server_id: &str,
+    index: u8,
+    tool: McpToolDefinition,
+) -> SdkResult<Instruction> {
+    let server_pda = derive_mcp_server_pda(program_id, owner, server_id)?;
+
+    let accounts = vec![
+        AccountMeta::new(
+            derive_tool_account_pda(program_id, &server_pda, index),
+            false,
+        ),
+        AccountMeta::new_readonly(server_pda, false),
+        AccountMeta::new_readonly(*owner, true),
+        AccountMeta::new(*payer, true), // payer for account resizing
+        AccountMeta::new_readonly(system_program::id(), false),
+    ];
+
+    let instruction = McpServerRegistryInstruction::UpdateToolAccount {
+        index,
+        tool: tool.into(),
     };
 
     let data = instruction.try_to_vec().map_err(|e| {
@@ -785,25 +1927,466 @@ pub fn create_update_mcp_server_instruction(
     })
 }
 
-/// Create update MCP server status instruction
-pub fn create_update_mcp_server_status_instruction(
+/// Create an instruction that closes the server's last overflow tool account,
+/// refunding its rent to the owner
+pub fn create_close_tool_account_instruction(
+    program_id: &Pubkey,
+    owner: &Pubkey,
+    server_id: &str,
+    index: u8,
+) -> SdkResult<Instruction> {
+    let server_pda = derive_mcp_server_pda(program_id, owner, server_id)?;
+
+    let accounts = vec![
+        AccountMeta::new(
+            derive_tool_account_pda(program_id, &server_pda, index),
+            false,
+        ),
+        AccountMeta::new(server_pda, false),
+        AccountMeta::new_readonly(*owner, true),
+        AccountMeta::new(*owner, false), // rent recipient
+    ];
+
+    let instruction = McpServerRegistryInstruction::CloseToolAccount { index };
+
+    let data = instruction.try_to_vec().map_err(|e| {
+        SdkError::SerializationError(format!("Failed to serialize instruction: {}", e))
+    })?;
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Create the instruction that starts a chunked registration with the core
+/// fields and tags of `args`; on-chain definitions are not included
+pub fn create_begin_mcp_server_registration_instruction(
+    program_id: &Pubkey,
+    owner: &Pubkey,
+    payer: &Pubkey,
+    args: &McpServerArgs,
+) -> SdkResult<Instruction> {
+    let server_pda = derive_mcp_server_pda(program_id, owner, &args.server_id)?;
+
+    let accounts = vec![
+        AccountMeta::new(server_pda, false),
+        AccountMeta::new_readonly(*owner, true),
+        AccountMeta::new(*payer, true),
+        AccountMeta::new_readonly(system_program::id(), false),
+    ];
+
+    let instruction = McpServerRegistryInstruction::BeginMcpServerRegistration {
+        server_id: args.server_id.clone(),
+        name: args.name.clone(),
+        server_version: args.server_version.clone(),
+        service_endpoint: args.service_endpoint.clone(),
+        documentation_url: args.documentation_url.clone(),
+        server_capabilities_summary: args.server_capabilities_summary.clone(),
+        supports_resources: args.supports_resources,
+        supports_tools: args.supports_tools,
+        supports_prompts: args.supports_prompts,
+        full_capabilities_uri: args.full_capabilities_uri.clone(),
+        tags: args.tags.clone(),
+    };
+
+    registration_chunk_instruction(program_id, accounts, instruction)
+}
+
+/// Create an instruction that appends tool definitions to an open registration
+pub fn create_append_mcp_tool_definitions_instruction(
+    program_id: &Pubkey,
+    owner: &Pubkey,
+    payer: &Pubkey,
+    server_id: &str,
+    tools: Vec<McpToolDefinition>,
+) -> SdkResult<Instruction> {
+    let instruction = McpServerRegistryInstruction::AppendMcpToolDefinitions {
+        tools: tools.into_iter().map(Into::into).collect(),
+    };
+    registration_chunk_instruction(
+        program_id,
+        append_chunk_accounts(program_id, owner, payer, server_id)?,
+        instruction,
+    )
+}
+
+/// Create an instruction that appends resource definitions to an open registration
+pub fn create_append_mcp_resource_definitions_instruction(
+    program_id: &Pubkey,
+    owner: &Pubkey,
+    payer: &Pubkey,
+    server_id: &str,
+    resources: Vec<McpResourceDefinition>,
+) -> SdkResult<Instruction> {
+    let instruction = McpServerRegistryInstruction::AppendMcpResourceDefinitions {
+        resources: resources.into_iter().map(Into::into).collect(),
+    };
+    registration_chunk_instruction(
+        program_id,
+        append_chunk_accounts(program_id, owner, payer, server_id)?,
+        instruction,
+    )
+}
+
+/// Create an instruction that appends prompt definitions to an open registration
+pub fn create_append_mcp_prompt_definitions_instruction(
+    program_id: &Pubkey,
+    owner: &Pubkey,
+    payer: &Pubkey,
+    server_id: &str,
+    prompts: Vec<McpPromptDefinition>,
+) -> SdkResult<Instruction> {
+    let instruction = McpServerRegistryInstruction::AppendMcpPromptDefinitions {
+        prompts: prompts.into_iter().map(Into::into).collect(),
+    };
+    registration_chunk_instruction(
+        program_id,
+        append_chunk_accounts(program_id, owner, payer, server_id)?,
+        instruction,
+    )
+}
+
+/// Create the instruction that validates and activates a chunked registration
+pub fn create_finalize_mcp_server_registration_instruction(
+    program_id: &Pubkey,
+    owner: &Pubkey,
+    server_id: &str,
+) -> SdkResult<Instruction> {
+    let server_pda = derive_mcp_server_pda(program_id, owner, server_id)?;
+
+    let accounts = vec![
+        AccountMeta::new(server_pda, false),
+        AccountMeta::new_readonly(*owner, true),
+    ];
+
+    registration_chunk_instruction(
+        program_id,
+        accounts,
+        McpServerRegistryInstruction::FinalizeMcpServerRegistration,
+    )
+}
+
+/// Create an instruction that creates the registry config. `authority` must
+/// be the program's upgrade authority; it becomes the config's admin.
+pub fn create_initialize_registry_config_instruction(
+    program_id: &Pubkey,
+    authority: &Pubkey,
+    payer: &Pubkey,
+    params: RegistryConfigParams,
+) -> SdkResult<Instruction> {
+    let accounts = vec![
+        AccountMeta::new(derive_registry_config_pda(program_id), false),
+        AccountMeta::new_readonly(*authority, true),
+        AccountMeta::new(*payer, true),
+        AccountMeta::new_readonly(derive_program_data_address(program_id), false),
+        AccountMeta::new_readonly(system_program::id(), false),
+    ];
+
+    let instruction = McpServerRegistryInstruction::InitializeRegistryConfig { params };
+
+    let data = instruction.try_to_vec().map_err(|e| {
+        SdkError::SerializationError(format!("Failed to serialize instruction: {}", e))
+    })?;
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Create an instruction that replaces the registry config parameters and,
+/// if `new_admin` is set, hands the config to a new admin
+pub fn create_update_registry_config_instruction(
+    program_id: &Pubkey,
+    admin: &Pubkey,
+    params: RegistryConfigParams,
+    new_admin: Option<Pubkey>,
+) -> SdkResult<Instruction> {
+    let accounts = vec![
+        AccountMeta::new(derive_registry_config_pda(program_id), false),
+        AccountMeta::new_readonly(*admin, true),
+        AccountMeta::new_readonly(RegistryVaults::derive(program_id).staking_vault, false),
+    ];
+
+    let instruction = McpServerRegistryInstruction::UpdateRegistryConfig { params, new_admin };
+
+    let data = instruction.try_to_vec().map_err(|e| {
+        SdkError::SerializationError(format!("Failed to serialize instruction: {}", e))
+    })?;
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Create an instruction that creates the registry's staking, fee,
+/// registration and treasury vaults for `mint`, which must be the config's
+/// accepted mint
+pub fn create_initialize_vaults_instruction(
+    program_id: &Pubkey,
+    admin: &Pubkey,
+    payer: &Pubkey,
+    mint: &Pubkey,
+) -> SdkResult<Instruction> {
+    let vaults = RegistryVaults::derive(program_id);
+    let accounts = vec![
+        AccountMeta::new_readonly(derive_registry_config_pda(program_id), false),
+        AccountMeta::new_readonly(*admin, true),
+        AccountMeta::new(*payer, true),
+        AccountMeta::new_readonly(*mint, false),
+        AccountMeta::new(vaults.staking_vault, false),
+        AccountMeta::new(vaults.fee_vault, false),
+        AccountMeta::new(vaults.registration_vault, false),
+        AccountMeta::new(vaults.treasury_vault, false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+        AccountMeta::new_readonly(system_program::id(), false),
+    ];
+
+    let data = McpServerRegistryInstruction::InitializeVaults
+        .try_to_vec()
+        .map_err(|e| {
+            SdkError::SerializationError(format!("Failed to serialize instruction: {}", e))
+        })?;
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Create an instruction that creates the fee vault of an MCP server, which
+/// must exist before usage fees can be collected
+pub fn create_initialize_server_fee_vault_instruction(
+    program_id: &Pubkey,
+    owner: &Pubkey,
+    payer: &Pubkey,
+    server_id: &str,
+    mint: &Pubkey,
+) -> SdkResult<Instruction> {
+    let server_pda = derive_mcp_server_pda(program_id, owner, server_id)?;
+    let accounts = vec![
+        AccountMeta::new_readonly(server_pda, false),
+        AccountMeta::new_readonly(*owner, true),
+        AccountMeta::new(*payer, true),
+        AccountMeta::new_readonly(derive_registry_config_pda(program_id), false),
+        AccountMeta::new_readonly(*mint, false),
+        AccountMeta::new(derive_server_fee_vault_pda(program_id, &server_pda), false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+        AccountMeta::new_readonly(system_program::id(), false),
+    ];
+
+    let data = McpServerRegistryInstruction::InitializeServerFeeVault
+        .try_to_vec()
+        .map_err(|e| {
+            SdkError::SerializationError(format!("Failed to serialize instruction: {}", e))
+        })?;
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Create an instruction that pays a server's pending fees out of its fee
+/// vault: each split recipient's share to the matching account of
+/// `recipient_token_accounts`, in table order, and the rest to
+/// `owner_token_account`. Shares of recipients whose account is closed or
+/// frozen stay in the vault, owed to them.
+pub fn create_withdraw_pending_fees_instruction(
+    program_id: &Pubkey,
+    owner: &Pubkey,
+    server_id: &str,
+    owner_token_account: &Pubkey,
+    recipient_token_accounts: &[Pubkey],
+    expected_state_version: Option<u64>,
+) -> SdkResult<Instruction> {
+    let server_pda = derive_mcp_server_pda(program_id, owner, server_id)?;
+    let mut accounts = vec![
+        AccountMeta::new(server_pda, false),
+        AccountMeta::new_readonly(*owner, true),
+        AccountMeta::new(derive_server_fee_vault_pda(program_id, &server_pda), false),
+        AccountMeta::new(*owner_token_account, false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+        AccountMeta::new_readonly(derive_registry_config_pda(program_id), false),
+        AccountMeta::new(derive_revenue_split_pda(program_id, &server_pda), false),
+    ];
+    accounts.extend(
+        recipient_token_accounts
+            .iter()
+            .map(|account| AccountMeta::new(*account, false)),
+    );
+
+    let data = McpServerRegistryInstruction::WithdrawPendingFees {
+        expected_state_version,
+    }
+    .try_to_vec()
+    .map_err(|e| SdkError::SerializationError(format!("Failed to serialize instruction: {}", e)))?;
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Create an instruction that sets the revenue split of an entry, creating
+/// the split account on first use. An empty `recipients` pays everything to
+/// the owner.
+pub fn create_set_revenue_split_instruction(
     program_id: &Pubkey,
     owner: &Pubkey,
+    payer: &Pubkey,
     server_id: &str,
-    status: u8,
+    recipients: Vec<SplitRecipient>,
 ) -> SdkResult<Instruction> {
-    let server_pda = derive_mcp_server_pda(program_id, owner, server_id)?;
+    validate_split(&recipients)?;
+    let entry_pda = derive_mcp_server_pda(program_id, owner, server_id)?;
+    let accounts = vec![
+        AccountMeta::new(derive_revenue_split_pda(program_id, &entry_pda), false),
+        AccountMeta::new_readonly(entry_pda, false),
+        AccountMeta::new_readonly(*owner, true),
+        AccountMeta::new(*payer, true),
+        AccountMeta::new_readonly(system_program::id(), false),
+    ];
+
+    let data = McpServerRegistryInstruction::SetRevenueSplit { recipients }
+        .try_to_vec()
+        .map_err(|e| {
+            SdkError::SerializationError(format!("Failed to serialize instruction: {}", e))
+        })?;
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
 
+/// Create an instruction that moves `amount` of protocol revenue from the
+/// treasury vault to `destination`, a token account of the config's treasury
+/// wallet
+pub fn create_withdraw_treasury_instruction(
+    program_id: &Pubkey,
+    admin: &Pubkey,
+    destination: &Pubkey,
+    amount: u64,
+) -> SdkResult<Instruction> {
     let accounts = vec![
-        AccountMeta::new(server_pda, false),
+        AccountMeta::new_readonly(derive_registry_config_pda(program_id), false),
+        AccountMeta::new_readonly(*admin, true),
+        AccountMeta::new(RegistryVaults::derive(program_id).treasury_vault, false),
+        AccountMeta::new(*destination, false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+    ];
+
+    let data = McpServerRegistryInstruction::WithdrawTreasury { amount }
+        .try_to_vec()
+        .map_err(|e| {
+            SdkError::SerializationError(format!("Failed to serialize instruction: {}", e))
+        })?;
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Create an instruction that replaces an MCP server's per-item prices and
+/// discount tiers, creating the pricing table on first use
+pub fn create_set_usage_pricing_instruction(
+    program_id: &Pubkey,
+    owner: &Pubkey,
+    payer: &Pubkey,
+    server_id: &str,
+    item_prices: Vec<ItemPriceInput>,
+    discount_tiers: Vec<DiscountTier>,
+) -> SdkResult<Instruction> {
+    if item_prices.len() > MAX_PRICED_ITEMS || discount_tiers.len() > MAX_DISCOUNT_TIERS {
+        return Err(SdkError::ValidationError(format!(
+            "A pricing table holds at most {} items and {} discount tiers",
+            MAX_PRICED_ITEMS, MAX_DISCOUNT_TIERS
+        )));
+    }
+    let entry_pda = derive_mcp_server_pda(program_id, owner, server_id)?;
+    let accounts = vec![
+        AccountMeta::new(derive_pricing_table_pda(program_id, &entry_pda), false),
+        AccountMeta::new_readonly(entry_pda, false),
         AccountMeta::new_readonly(*owner, true),
+        AccountMeta::new(*payer, true),
+        AccountMeta::new_readonly(system_program::id(), false),
+        AccountMeta::new_readonly(derive_registry_config_pda(program_id), false),
     ];
 
-    let instruction = McpServerRegistryInstruction::UpdateMcpServerStatus { new_status: status };
+    let data = McpServerRegistryInstruction::SetUsagePricing {
+        item_prices,
+        discount_tiers,
+    }
+    .try_to_vec()
+    .map_err(|e| SdkError::SerializationError(format!("Failed to serialize instruction: {}", e)))?;
 
-    let data = instruction.try_to_vec().map_err(|e| {
-        SdkError::SerializationError(format!("Failed to serialize instruction: {}", e))
-    })?;
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Create an instruction that pays for `count` uses of an MCP server from
+/// `user_token_account`. Free-tier calls, then calls left in the user's
+/// subscription are used first. With `item_name`, the item's price from the server's pricing
+/// table applies. The charge fails if it would exceed
+/// `max_total_fee` or if `user` already used `idempotency_key` on the server.
+#[allow(clippy::too_many_arguments)]
+pub fn create_record_usage_instruction(
+    program_id: &Pubkey,
+    user: &Pubkey,
+    user_token_account: &Pubkey,
+    server_owner: &Pubkey,
+    server_id: &str,
+    usage_type: UsageType,
+    count: u32,
+    item_name: Option<String>,
+    max_total_fee: u64,
+    idempotency_key: IdempotencyKey,
+) -> SdkResult<Instruction> {
+    let entry_pda = derive_mcp_server_pda(program_id, server_owner, server_id)?;
+    let accounts = vec![
+        AccountMeta::new(entry_pda, false),
+        AccountMeta::new(*user, true),
+        AccountMeta::new(*user_token_account, false),
+        AccountMeta::new(derive_server_fee_vault_pda(program_id, &entry_pda), false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+        AccountMeta::new_readonly(derive_registry_config_pda(program_id), false),
+        AccountMeta::new(RegistryVaults::derive(program_id).treasury_vault, false),
+        AccountMeta::new_readonly(derive_pricing_table_pda(program_id, &entry_pda), false),
+        AccountMeta::new(
+            derive_usage_receipt_pda(program_id, &entry_pda, user, &idempotency_key),
+            false,
+        ),
+        AccountMeta::new_readonly(system_program::id(), false),
+        AccountMeta::new(derive_subscription_pda(program_id, &entry_pda, user), false),
+        AccountMeta::new(derive_usage_record_pda(program_id, &entry_pda, user), false),
+        AccountMeta::new_readonly(derive_revenue_split_pda(program_id, &entry_pda), false),
+    ];
+
+    let data = McpServerRegistryInstruction::RecordUsageAndCollectFee {
+        usage_type,
+        count,
+        item_name,
+        max_total_fee,
+        idempotency_key,
+        expected_state_version: None,
+    }
+    .try_to_vec()
+    .map_err(|e| SdkError::SerializationError(format!("Failed to serialize instruction: {}", e)))?;
 
     Ok(Instruction {
         program_id: *program_id,
@@ -812,21 +2395,383 @@ pub fn create_update_mcp_server_status_instruction(
     })
 }
 
-/// Create deregister MCP server instruction
-pub fn create_deregister_mcp_server_instruction(
+/// Create an instruction that sets an MCP server's base fees, bulk discount
+/// and free tier. Fee increases apply after the registry's notice period,
+/// the free tier immediately.
+pub fn create_configure_usage_fees_instruction(
     program_id: &Pubkey,
     owner: &Pubkey,
     server_id: &str,
+    fees: McpUsageFees,
+    free_tier: FreeTier,
 ) -> SdkResult<Instruction> {
-    let server_pda = derive_mcp_server_pda(program_id, owner, server_id)?;
+    let entry_pda = derive_mcp_server_pda(program_id, owner, server_id)?;
+    let accounts = vec![
+        AccountMeta::new(entry_pda, false),
+        AccountMeta::new_readonly(*owner, true),
+        AccountMeta::new_readonly(derive_registry_config_pda(program_id), false),
+    ];
+
+    let data = McpServerRegistryInstruction::ConfigureUsageFees {
+        tool_base_fee: fees.tool_base_fee,
+        resource_base_fee: fees.resource_base_fee,
+        prompt_base_fee: fees.prompt_base_fee,
+        bulk_discount_threshold: fees.bulk_discount_threshold,
+        bulk_discount_percentage: fees.bulk_discount_percentage,
+        free_tier,
+        expected_state_version: None,
+    }
+    .try_to_vec()
+    .map_err(|e| SdkError::SerializationError(format!("Failed to serialize instruction: {}", e)))?;
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
 
+/// Create an instruction that creates or updates one of an MCP server's
+/// subscription plans
+#[allow(clippy::too_many_arguments)]
+pub fn create_set_subscription_plan_instruction(
+    program_id: &Pubkey,
+    owner: &Pubkey,
+    payer: &Pubkey,
+    server_id: &str,
+    plan_id: u8,
+    price: u64,
+    period: i64,
+    included: SubscriptionQuota,
+    active: bool,
+) -> SdkResult<Instruction> {
+    if price == 0 || included == SubscriptionQuota::default() {
+        return Err(SdkError::ValidationError(
+            "A subscription plan needs a price and included calls".to_string(),
+        ));
+    }
+    let entry_pda = derive_mcp_server_pda(program_id, owner, server_id)?;
     let accounts = vec![
-        AccountMeta::new(server_pda, false),
+        AccountMeta::new(
+            derive_subscription_plan_pda(program_id, &entry_pda, plan_id),
+            false,
+        ),
+        AccountMeta::new_readonly(entry_pda, false),
         AccountMeta::new_readonly(*owner, true),
+        AccountMeta::new(*payer, true),
+        AccountMeta::new_readonly(system_program::id(), false),
+        AccountMeta::new_readonly(derive_registry_config_pda(program_id), false),
+    ];
+
+    let data = McpServerRegistryInstruction::SetSubscriptionPlan {
+        plan_id,
+        price,
+        period,
+        included,
+        active,
+    }
+    .try_to_vec()
+    .map_err(|e| SdkError::SerializationError(format!("Failed to serialize instruction: {}", e)))?;
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Accounts shared by `Subscribe` and `RenewSubscription`
+fn subscription_payment_accounts(
+    program_id: &Pubkey,
+    user: &Pubkey,
+    user_token_account: &Pubkey,
+    entry_pda: &Pubkey,
+    plan_id: u8,
+) -> Vec<AccountMeta> {
+    vec![
+        AccountMeta::new(derive_subscription_pda(program_id, entry_pda, user), false),
+        AccountMeta::new(*entry_pda, false),
+        AccountMeta::new_readonly(
+            derive_subscription_plan_pda(program_id, entry_pda, plan_id),
+            false,
+        ),
+        AccountMeta::new(*user, true),
+        AccountMeta::new(*user_token_account, false),
+        AccountMeta::new(derive_server_fee_vault_pda(program_id, entry_pda), false),
+        AccountMeta::new(RegistryVaults::derive(program_id).treasury_vault, false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+        AccountMeta::new_readonly(derive_registry_config_pda(program_id), false),
+    ]
+}
+
+/// Create an instruction that buys one period of an MCP server's
+/// subscription plan from `user_token_account`. Fails if the plan's price
+/// exceeds `max_price` or the user already holds a subscription.
+pub fn create_subscribe_instruction(
+    program_id: &Pubkey,
+    user: &Pubkey,
+    user_token_account: &Pubkey,
+    server_owner: &Pubkey,
+    server_id: &str,
+    plan_id: u8,
+    max_price: u64,
+) -> SdkResult<Instruction> {
+    let entry_pda = derive_mcp_server_pda(program_id, server_owner, server_id)?;
+    let mut accounts =
+        subscription_payment_accounts(program_id, user, user_token_account, &entry_pda, plan_id);
+    accounts.push(AccountMeta::new_readonly(system_program::id(), false));
+    accounts.push(AccountMeta::new_readonly(
+        derive_revenue_split_pda(program_id, &entry_pda),
+        false,
+    ));
+
+    let data = McpServerRegistryInstruction::Subscribe { plan_id, max_price }
+        .try_to_vec()
+        .map_err(|e| {
+            SdkError::SerializationError(format!("Failed to serialize instruction: {}", e))
+        })?;
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Create an instruction that pays for another period of the user's
+/// subscription on `plan_id`, the plan it was bought on
+pub fn create_renew_subscription_instruction(
+    program_id: &Pubkey,
+    user: &Pubkey,
+    user_token_account: &Pubkey,
+    server_owner: &Pubkey,
+    server_id: &str,
+    plan_id: u8,
+    max_price: u64,
+) -> SdkResult<Instruction> {
+    let entry_pda = derive_mcp_server_pda(program_id, server_owner, server_id)?;
+    let mut accounts =
+        subscription_payment_accounts(program_id, user, user_token_account, &entry_pda, plan_id);
+    accounts.push(AccountMeta::new_readonly(
+        derive_revenue_split_pda(program_id, &entry_pda),
+        false,
+    ));
+
+    let data = McpServerRegistryInstruction::RenewSubscription { max_price }
+        .try_to_vec()
+        .map_err(|e| {
+            SdkError::SerializationError(format!("Failed to serialize instruction: {}", e))
+        })?;
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Create an instruction that closes the user's subscription to an MCP
+/// server and returns its rent. Remaining calls are forfeited.
+pub fn create_cancel_subscription_instruction(
+    program_id: &Pubkey,
+    user: &Pubkey,
+    server_owner: &Pubkey,
+    server_id: &str,
+) -> SdkResult<Instruction> {
+    let entry_pda = derive_mcp_server_pda(program_id, server_owner, server_id)?;
+    let accounts = vec![
+        AccountMeta::new(derive_subscription_pda(program_id, &entry_pda, user), false),
+        AccountMeta::new(*user, true),
+    ];
+
+    let data = McpServerRegistryInstruction::CancelSubscription
+        .try_to_vec()
+        .map_err(|e| {
+            SdkError::SerializationError(format!("Failed to serialize instruction: {}", e))
+        })?;
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Create an instruction that rewrites an MCP server entry stored in layout
+/// version 1 into the current layout. Anyone may send it; `payer` covers any
+/// extra rent.
+pub fn create_migrate_mcp_server_entry_instruction(
+    program_id: &Pubkey,
+    payer: &Pubkey,
+    server_owner: &Pubkey,
+    server_id: &str,
+) -> SdkResult<Instruction> {
+    let accounts = vec![
+        AccountMeta::new(
+            derive_mcp_server_pda(program_id, server_owner, server_id)?,
+            false,
+        ),
+        AccountMeta::new(*payer, true),
+        AccountMeta::new_readonly(system_program::id(), false),
     ];
 
-    let instruction = McpServerRegistryInstruction::DeregisterMcpServer;
+    let data = McpServerRegistryInstruction::MigrateMcpServerEntry
+        .try_to_vec()
+        .map_err(|e| {
+            SdkError::SerializationError(format!("Failed to serialize instruction: {}", e))
+        })?;
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Break a registration into instructions that each fit in a transaction
+/// paid for by `payer`: begin, optional-field updates if the core fields
+/// alone are too large, tool, resource and prompt appends, then finalize
+pub fn create_chunked_register_mcp_server_instructions(
+    program_id: &Pubkey,
+    owner: &Pubkey,
+    payer: &Pubkey,
+    args: McpServerArgs,
+) -> SdkResult<Vec<Instruction>> {
+    let mut instructions = Vec::new();
+
+    let begin = create_begin_mcp_server_registration_instruction(program_id, owner, payer, &args)?;
+    if fits_in_transaction(payer, std::slice::from_ref(&begin)) {
+        instructions.push(begin);
+    } else {
+        // Start with the required fields and set the optional ones through
+        // detail updates while the registration is still open
+        let required = McpServerArgs {
+            documentation_url: None,
+            server_capabilities_summary: None,
+            full_capabilities_uri: None,
+            tags: Vec::new(),
+            ..args.clone()
+        };
+        instructions.push(create_begin_mcp_server_registration_instruction(
+            program_id, owner, payer, &required,
+        )?);
+        instructions.extend(split_to_fit(
+            payer,
+            &optional_field_setters(&args),
+            |setters| {
+                let mut patch = McpServerPatch::default();
+                for set in setters {
+                    set(&mut patch);
+                }
+                create_update_mcp_server_instruction(
+                    program_id,
+                    owner,
+                    payer,
+                    &args.server_id,
+                    patch,
+                    None,
+                )
+            },
+        )?);
+    }
+
+    instructions.extend(split_to_fit(
+        payer,
+        &args.onchain_tool_definitions,
+        |tools| {
+            create_append_mcp_tool_definitions_instruction(
+                program_id,
+                owner,
+                payer,
+                &args.server_id,
+                tools.to_vec(),
+            )
+        },
+    )?);
+    instructions.extend(split_to_fit(
+        payer,
+        &args.onchain_resource_definitions,
+        |resources| {
+            create_append_mcp_resource_definitions_instruction(
+                program_id,
+                owner,
+                payer,
+                &args.server_id,
+                resources.to_vec(),
+            )
+        },
+    )?);
+    instructions.extend(split_to_fit(
+        payer,
+        &args.onchain_prompt_definitions,
+        |prompts| {
+            create_append_mcp_prompt_definitions_instruction(
+                program_id,
+                owner,
+                payer,
+                &args.server_id,
+                prompts.to_vec(),
+            )
+        },
+    )?);
+    instructions.push(create_finalize_mcp_server_registration_instruction(
+        program_id,
+        owner,
+        &args.server_id,
+    )?);
+
+    Ok(instructions)
+}
+
+/// Sets one optional field of an [`McpServerPatch`]
+type PatchSetter<'a> = Box<dyn Fn(&mut McpServerPatch) + 'a>;
+
+/// One setter per optional field present in `args`
+fn optional_field_setters(args: &McpServerArgs) -> Vec<PatchSetter<'_>> {
+    let mut setters: Vec<PatchSetter<'_>> = Vec::new();
+    if let Some(value) = &args.documentation_url {
+        setters.push(Box::new(move |patch| {
+            patch.documentation_url = Some(value.clone())
+        }));
+    }
+    if let Some(value) = &args.server_capabilities_summary {
+        setters.push(Box::new(move |patch| {
+            patch.server_capabilities_summary = Some(value.clone())
+        }));
+    }
+    if let Some(value) = &args.full_capabilities_uri {
+        setters.push(Box::new(move |patch| {
+            patch.full_capabilities_uri = Some(value.clone())
+        }));
+    }
+    if !args.tags.is_empty() {
+        let tags = &args.tags;
+        setters.push(Box::new(move |patch| patch.tags = Some(tags.clone())));
+    }
+    setters
+}
+
+/// Accounts shared by the registration append instructions
+fn append_chunk_accounts(
+    program_id: &Pubkey,
+    owner: &Pubkey,
+    payer: &Pubkey,
+    server_id: &str,
+) -> SdkResult<Vec<AccountMeta>> {
+    let server_pda = derive_mcp_server_pda(program_id, owner, server_id)?;
+    Ok(vec![
+        AccountMeta::new(server_pda, false),
+        AccountMeta::new_readonly(*owner, true),
+        AccountMeta::new(*payer, true), // payer for account resizing
+        AccountMeta::new_readonly(system_program::id(), false),
+    ])
+}
 
+fn registration_chunk_instruction(
+    program_id: &Pubkey,
+    accounts: Vec<AccountMeta>,
+    instruction: McpServerRegistryInstruction,
+) -> SdkResult<Instruction> {
     let data = instruction.try_to_vec().map_err(|e| {
         SdkError::SerializationError(format!("Failed to serialize instruction: {}", e))
     })?;
@@ -1001,4 +2946,591 @@ mod tests {
         let prompt = McpPromptDefinition::new("code-review".to_string(), too_many_tags);
         assert!(matches!(prompt, Err(SdkError::TooManyPromptTags)));
     }
+
+    #[test]
+    fn test_tool_account_instructions() {
+        let program_id = Pubkey::new_unique();
+        let owner = Keypair::new().pubkey();
+        let server_pda = derive_mcp_server_pda(&program_id, &owner, "test-server").unwrap();
+        let tool = McpToolDefinition::new("search".to_string(), vec![]).unwrap();
+
+        let ix = create_initialize_tool_account_instruction(
+            &program_id,
+            &owner,
+            &owner,
+            "test-server",
+            0,
+            tool,
+        )
+        .unwrap();
+        // Variant index must line up with the on-chain instruction
+        assert_eq!(ix.data[0], 10);
+        assert_eq!(ix.data[1], 0);
+        assert_eq!(
+            ix.accounts[0].pubkey,
+            derive_tool_account_pda(&program_id, &server_pda, 0)
+        );
+
+        let ix =
+            create_close_tool_account_instruction(&program_id, &owner, "test-server", 0).unwrap();
+        assert_eq!(ix.data, vec![12, 0]);
+    }
+
+    #[test]
+    fn test_initialize_vaults_instruction() {
+        let program_id = Pubkey::new_unique();
+        let admin = Keypair::new().pubkey();
+        let mint = Pubkey::new_unique();
+        let vaults = RegistryVaults::derive(&program_id);
+
+        let ix = create_initialize_vaults_instruction(&program_id, &admin, &admin, &mint).unwrap();
+        assert_eq!(ix.data, vec![20]);
+        assert_eq!(ix.accounts.len(), 10);
+        assert_eq!(ix.accounts[3].pubkey, mint);
+        assert_eq!(ix.accounts[4].pubkey, vaults.staking_vault);
+        assert_eq!(ix.accounts[5].pubkey, vaults.fee_vault);
+        assert_eq!(ix.accounts[6].pubkey, vaults.registration_vault);
+        assert!(ix.accounts[6].is_writable);
+
+        let server_pda = derive_mcp_server_pda(&program_id, &admin, "test-server").unwrap();
+        let fee_vault = derive_server_fee_vault_pda(&program_id, &server_pda);
+        assert_ne!(fee_vault, vaults.fee_vault);
+
+        let ix = create_initialize_server_fee_vault_instruction(
+            &program_id,
+            &admin,
+            &admin,
+            "test-server",
+            &mint,
+        )
+        .unwrap();
+        assert_eq!(ix.data, vec![21]);
+        assert_eq!(ix.accounts[0].pubkey, server_pda);
+        assert_eq!(ix.accounts[5].pubkey, fee_vault);
+    }
+
+    #[test]
+    fn test_revenue_instructions() {
+        let program_id = Pubkey::new_unique();
+        let owner = Keypair::new().pubkey();
+        let server_pda = derive_mcp_server_pda(&program_id, &owner, "test-server").unwrap();
+
+        let ix = create_set_revenue_split_instruction(
+            &program_id,
+            &owner,
+            &owner,
+            "test-server",
+            vec![],
+        )
+        .unwrap();
+        assert_eq!(ix.data, vec![22, 0, 0, 0, 0]);
+        assert_eq!(
+            ix.accounts[0].pubkey,
+            derive_revenue_split_pda(&program_id, &server_pda)
+        );
+
+        let ix = create_withdraw_treasury_instruction(&program_id, &owner, &owner, 1).unwrap();
+        assert_eq!(ix.data[0], 23);
+    }
+
+    #[test]
+    fn test_usage_pricing_instructions() {
+        let program_id = Pubkey::new_unique();
+        let owner = Keypair::new().pubkey();
+        let server_pda = derive_mcp_server_pda(&program_id, &owner, "test-server").unwrap();
+        let pricing_pda = derive_pricing_table_pda(&program_id, &server_pda);
+
+        let ix = create_set_usage_pricing_instruction(
+            &program_id,
+            &owner,
+            &owner,
+            "test-server",
+            vec![],
+            vec![],
+        )
+        .unwrap();
+        assert_eq!(ix.data, vec![24, 0, 0, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(ix.accounts[0].pubkey, pricing_pda);
+
+        let ix = create_record_usage_instruction(
+            &program_id,
+            &owner,
+            &owner,
+            &owner,
+            "test-server",
+            UsageType::Tool,
+            1,
+            Some("lookup".to_string()),
+            100,
+            [5; 16],
+        )
+        .unwrap();
+        assert_eq!(ix.data[0], 7);
+        assert_eq!(ix.accounts[7].pubkey, pricing_pda);
+        assert_eq!(
+            ix.accounts[8].pubkey,
+            derive_usage_receipt_pda(&program_id, &server_pda, &owner, &[5; 16])
+        );
+        assert_eq!(
+            ix.accounts[10].pubkey,
+            derive_subscription_pda(&program_id, &server_pda, &owner)
+        );
+        assert_eq!(
+            ix.accounts[11].pubkey,
+            derive_usage_record_pda(&program_id, &server_pda, &owner)
+        );
+        assert_eq!(
+            ix.accounts[12].pubkey,
+            derive_revenue_split_pda(&program_id, &server_pda)
+        );
+        assert!(ix.accounts[1].is_writable && ix.accounts[1].is_signer);
+
+        let too_many = vec![DiscountTier::default(); MAX_DISCOUNT_TIERS + 1];
+        assert!(create_set_usage_pricing_instruction(
+            &program_id,
+            &owner,
+            &owner,
+            "test-server",
+            vec![],
+            too_many,
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_estimate_usage_cost() {
+        let mut economics: McpServerEconomics = bytemuck::Zeroable::zeroed();
+        economics.tool_base_fee = 100;
+        economics.resource_base_fee = 20;
+        economics.bulk_discount_threshold = 10;
+        economics.bulk_discount_percentage = 10;
+        let economics = economics.fee_schedule(0).current;
+
+        let base = estimate_usage_cost(
+            &economics,
+            None,
+            UsageType::Tool,
+            None,
+            10,
+            UsageCoverage::default(),
+            0,
+        )
+        .unwrap();
+        assert!(!base.item_priced);
+        assert_eq!(base.total_fee, 900);
+
+        let mut items = [ItemPrice::default(); MAX_PRICED_ITEMS];
+        items[0] = ItemPrice {
+            usage_type: UsageType::Tool.to_u8(),
+            name_hash: hash_item_name("lookup"),
+            price: 40,
+        };
+        let mut tiers = [DiscountTier::default(); MAX_DISCOUNT_TIERS];
+        tiers[0] = DiscountTier {
+            threshold: 12,
+            percentage: 25,
+        };
+        let table = McpPricingTable {
+            bump: 255,
+            server_entry: Pubkey::new_unique(),
+            item_count: 1,
+            items,
+            tier_count: 1,
+            tiers,
+            last_update_timestamp: 0,
+            pending_item_count: 0,
+            pending_items: [ItemPrice::default(); MAX_PRICED_ITEMS],
+            pending_tier_count: 0,
+            pending_tiers: [DiscountTier::default(); MAX_DISCOUNT_TIERS],
+            change_effective_at: 0,
+        };
+
+        let priced = estimate_usage_cost(
+            &economics,
+            Some(&table),
+            UsageType::Tool,
+            Some("lookup"),
+            12,
+            UsageCoverage::default(),
+            1_000,
+        )
+        .unwrap();
+        assert!(priced.item_priced);
+        assert_eq!(priced.discount_percentage, 25);
+        assert_eq!(priced.total_fee, 360);
+        assert_eq!(priced.protocol_fee, 36);
+        assert_eq!(priced.server_fee, 324);
+
+        // A type with priced items only charges for those items
+        for name in [Some("other"), None] {
+            assert!(matches!(
+                estimate_usage_cost(
+                    &economics,
+                    Some(&table),
+                    UsageType::Tool,
+                    name,
+                    2,
+                    UsageCoverage::default(),
+                    0,
+                ),
+                Err(SdkError::UnknownPricedItem)
+            ));
+        }
+
+        // Other types pay the base fee, and a charge below the table's
+        // lowest tier gets no discount even past the bulk threshold
+        let unpriced = estimate_usage_cost(
+            &economics,
+            Some(&table),
+            UsageType::Resource,
+            None,
+            10,
+            UsageCoverage::default(),
+            0,
+        )
+        .unwrap();
+        assert!(!unpriced.item_priced);
+        assert_eq!(unpriced.discount_percentage, 0);
+        assert_eq!(unpriced.total_fee, 200);
+        assert!(estimate_usage_cost(
+            &economics,
+            None,
+            UsageType::Prompt,
+            None,
+            1,
+            UsageCoverage::default(),
+            0
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_subscription_instructions_and_coverage() {
+        let program_id = Pubkey::new_unique();
+        let owner = Pubkey::new_unique();
+        let user = Pubkey::new_unique();
+        let server_pda = derive_mcp_server_pda(&program_id, &owner, "test-server").unwrap();
+        let included = SubscriptionQuota {
+            tool_calls: 10,
+            resource_calls: 0,
+            prompt_calls: 0,
+        };
+
+        let ix = create_set_subscription_plan_instruction(
+            &program_id,
+            &owner,
+            &owner,
+            "test-server",
+            3,
+            500,
+            86_400,
+            included,
+            true,
+        )
+        .unwrap();
+        assert_eq!(ix.data[..2], [25, 3]);
+        assert_eq!(
+            ix.accounts[0].pubkey,
+            derive_subscription_plan_pda(&program_id, &server_pda, 3)
+        );
+        assert!(create_set_subscription_plan_instruction(
+            &program_id,
+            &owner,
+            &owner,
+            "test-server",
+            3,
+            500,
+            86_400,
+            SubscriptionQuota::default(),
+            true,
+        )
+        .is_err());
+
+        let ix =
+            create_subscribe_instruction(&program_id, &user, &user, &owner, "test-server", 3, 500)
+                .unwrap();
+        assert_eq!(ix.data[..2], [26, 3]);
+        assert_eq!(ix.accounts.len(), 11);
+        assert_eq!(
+            ix.accounts[0].pubkey,
+            derive_subscription_pda(&program_id, &server_pda, &user)
+        );
+        assert_eq!(
+            ix.accounts[10].pubkey,
+            derive_revenue_split_pda(&program_id, &server_pda)
+        );
+        let ix = create_renew_subscription_instruction(
+            &program_id,
+            &user,
+            &user,
+            &owner,
+            "test-server",
+            3,
+            500,
+        )
+        .unwrap();
+        assert_eq!(ix.data[0], 27);
+        assert_eq!(ix.accounts.len(), 10);
+        assert_eq!(
+            ix.accounts[9].pubkey,
+            derive_revenue_split_pda(&program_id, &server_pda)
+        );
+        let ix = create_cancel_subscription_instruction(&program_id, &user, &owner, "test-server")
+            .unwrap();
+        assert_eq!(ix.data, vec![28]);
+
+        let ix =
+            create_migrate_mcp_server_entry_instruction(&program_id, &user, &owner, "test-server")
+                .unwrap();
+        assert_eq!(ix.data, vec![29]);
+        assert_eq!(ix.accounts[0].pubkey, server_pda);
+        assert!(ix.accounts[1].is_signer);
+
+        // Covered uses are free and the rest pay the base fee undiscounted
+        let subscription = McpSubscription {
+            bump: 255,
+            server_entry: server_pda,
+            subscriber: user,
+            plan_id: 3,
+            period_start: 0,
+            expires_at: 100,
+            remaining: included,
+        };
+        assert_eq!(subscription.covered_calls(UsageType::Tool, 12, 50), 10);
+        assert_eq!(subscription.covered_calls(UsageType::Tool, 12, 100), 0);
+        let mut economics: McpServerEconomics = bytemuck::Zeroable::zeroed();
+        economics.tool_base_fee = 100;
+        economics.bulk_discount_threshold = 10;
+        economics.bulk_discount_percentage = 10;
+        let fees = economics.fee_schedule(0).current;
+        let coverage = UsageCoverage {
+            free_calls: 0,
+            subscription_calls: subscription.covered_calls(UsageType::Tool, 12, 50),
+        };
+        let estimate =
+            estimate_usage_cost(&fees, None, UsageType::Tool, None, 12, coverage, 0).unwrap();
+        assert_eq!(estimate.coverage.subscription_calls, 10);
+        assert_eq!(estimate.total_fee, 200);
+        let all_free = UsageCoverage {
+            free_calls: 3,
+            subscription_calls: 5,
+        };
+        let covered =
+            estimate_usage_cost(&fees, None, UsageType::Prompt, None, 5, all_free, 0).unwrap();
+        assert_eq!(covered.coverage.free_calls, 3);
+        assert_eq!(covered.coverage.subscription_calls, 2);
+        assert_eq!(covered.total_fee, 0);
+    }
+
+    #[test]
+    fn test_fee_reconciliation() {
+        let report = FeeReconciliation {
+            server_entry: Pubkey::new_unique(),
+            fee_vault: Pubkey::new_unique(),
+            pending_fees: 100,
+            vault_balance: Some(120),
+        };
+        assert!(report.is_solvent());
+        assert_eq!(report.surplus(), 20);
+
+        let missing_vault = FeeReconciliation {
+            vault_balance: None,
+            ..report
+        };
+        assert!(!missing_vault.is_solvent());
+        assert_eq!(missing_vault.shortfall(), 100);
+    }
+
+    #[test]
+    fn test_entry_prefix_layout() {
+        // Header (44 bytes) followed by the economics block (213 bytes)
+        assert_eq!(McpServerEntryPrefix::LEN, 257);
+
+        let mut data = vec![0u8; McpServerEntryPrefix::LEN];
+        data[1] = crate::zero_copy::ENTRY_LAYOUT_VERSION;
+        data[43] = McpServerStatus::Active as u8;
+        let pending_fees_offset = McpServerEntryPrefix::ECONOMICS_OFFSET + 148;
+        data[pending_fees_offset..pending_fees_offset + 8].copy_from_slice(&1_234u64.to_le_bytes());
+        let pending_tool_fee_offset = pending_fees_offset + 8;
+        data[pending_tool_fee_offset..pending_tool_fee_offset + 8]
+            .copy_from_slice(&300u64.to_le_bytes());
+        let effective_at_offset = pending_tool_fee_offset + 8 * 3 + 4 + 1;
+        data[effective_at_offset..effective_at_offset + 8].copy_from_slice(&1_000i64.to_le_bytes());
+        let free_tier_period_offset = McpServerEntryPrefix::LEN - 8; // last field
+        data[free_tier_period_offset..].copy_from_slice(&3_600i64.to_le_bytes());
+
+        let prefix = McpServerEntryPrefix::from_account_data(&data).unwrap();
+        assert_eq!(prefix.status(), Some(McpServerStatus::Active));
+        assert_eq!({ prefix.economics.pending_fees }, 1_234);
+        assert_eq!({ prefix.economics.total_tool_calls }, 0);
+
+        let schedule = prefix.economics.fee_schedule(999);
+        assert_eq!(schedule.current.tool_base_fee, 0);
+        assert_eq!(schedule.upcoming.unwrap().tool_base_fee, 300);
+        assert_eq!(schedule.effective_at, Some(1_000));
+        assert_eq!(
+            prefix.economics.fee_schedule(1_000).current.tool_base_fee,
+            300
+        );
+        assert_eq!(prefix.economics.free_tier().period, 3_600);
+
+        data[1] = 1;
+        assert!(matches!(
+            McpServerEntryPrefix::from_account_data(&data),
+            Err(SdkError::UnsupportedEntryVersion)
+        ));
+    }
+
+    #[test]
+    fn test_free_tier_and_usage_record() {
+        let free_tier = FreeTier {
+            tool_calls: 5,
+            resource_calls: 0,
+            prompt_calls: 0,
+            period: 3_600,
+        };
+        let mut record = McpUsageRecord {
+            bump: 255,
+            server_entry: Pubkey::new_unique(),
+            user: Pubkey::new_unique(),
+            window_start: 1_000,
+            window_calls: SubscriptionQuota {
+                tool_calls: 4,
+                resource_calls: 0,
+                prompt_calls: 0,
+            },
+            total_tool_calls: 4,
+            total_resource_accesses: 0,
+            total_prompt_uses: 0,
+            total_free_calls: 4,
+            total_fees_paid: 0,
+            first_use_timestamp: 1_000,
+            last_use_timestamp: 1_000,
+        };
+        assert_eq!(record.try_to_vec().unwrap().len(), McpUsageRecord::LEN);
+        assert_eq!(
+            record.free_calls_left(UsageType::Tool, &free_tier, 2_000),
+            1
+        );
+        assert_eq!(
+            record.free_calls_left(UsageType::Tool, &free_tier, 4_600),
+            5
+        );
+        record.window_calls.tool_calls = 9;
+        assert_eq!(
+            record.free_calls_left(UsageType::Tool, &free_tier, 2_000),
+            0
+        );
+
+        let program_id = Pubkey::new_unique();
+        let owner = Pubkey::new_unique();
+        let fees = McpUsageFees {
+            tool_base_fee: 10,
+            resource_base_fee: 10,
+            prompt_base_fee: 10,
+            bulk_discount_threshold: 100,
+            bulk_discount_percentage: 0,
+        };
+        let ix = create_configure_usage_fees_instruction(
+            &program_id,
+            &owner,
+            "test-server",
+            fees,
+            free_tier,
+        )
+        .unwrap();
+        assert_eq!(ix.data[0], 6);
+        match McpServerRegistryInstruction::try_from_slice(&ix.data).unwrap() {
+            McpServerRegistryInstruction::ConfigureUsageFees {
+                free_tier: decoded, ..
+            } => assert_eq!(decoded, free_tier),
+            _ => panic!("Wrong instruction type"),
+        }
+    }
+
+    #[test]
+    fn test_chunked_registration_fits_transactions() {
+        let program_id = Pubkey::new_unique();
+        let owner = Pubkey::new_unique();
+        let args = McpServerArgs {
+            server_id: "rich-server".to_string(),
+            name: "n".repeat(MAX_SERVER_NAME_LEN),
+            server_version: "1.0.0".to_string(),
+            service_endpoint: format!("https://{}", "e".repeat(240)),
+            documentation_url: Some(format!("https://{}", "d".repeat(240))),
+            server_capabilities_summary: Some("s".repeat(MAX_SERVER_CAPABILITIES_SUMMARY_LEN)),
+            supports_resources: true,
+            supports_tools: true,
+            supports_prompts: true,
+            onchain_tool_definitions: (0..MAX_ONCHAIN_TOOL_DEFINITIONS)
+                .map(|i| McpToolDefinition {
+                    name: format!("tool-{}", i),
+                    tags: vec!["t".repeat(MAX_TOOL_TAG_LEN); MAX_TOOL_TAGS],
+                })
+                .collect(),
+            onchain_resource_definitions: (0..MAX_ONCHAIN_RESOURCE_DEFINITIONS)
+                .map(|i| McpResourceDefinition {
+                    uri_pattern: format!("res://{}/{}", i, "r".repeat(100)),
+                    tags: vec!["t".repeat(MAX_RESOURCE_TAG_LEN); MAX_RESOURCE_TAGS],
+                })
+                .collect(),
+            onchain_prompt_definitions: (0..MAX_ONCHAIN_PROMPT_DEFINITIONS)
+                .map(|i| McpPromptDefinition {
+                    name: format!("prompt-{}", i),
+                    tags: vec!["t".repeat(MAX_PROMPT_TAG_LEN); MAX_PROMPT_TAGS],
+                })
+                .collect(),
+            full_capabilities_uri: Some(format!("https://{}", "c".repeat(240))),
+            tags: (0..MAX_SERVER_TAGS).map(|i| format!("tag-{}", i)).collect(),
+        };
+
+        let single =
+            create_register_mcp_server_instruction(&program_id, &owner, &owner, args.clone())
+                .unwrap();
+        assert!(!fits_in_transaction(&owner, &[single]));
+
+        let instructions =
+            create_chunked_register_mcp_server_instructions(&program_id, &owner, &owner, args)
+                .unwrap();
+        for instruction in &instructions {
+            assert!(fits_in_transaction(
+                &owner,
+                std::slice::from_ref(instruction)
+            ));
+        }
+
+        let decoded: Vec<McpServerRegistryInstruction> = instructions
+            .iter()
+            .map(|ix| McpServerRegistryInstruction::try_from_slice(&ix.data).unwrap())
+            .collect();
+        assert!(matches!(
+            decoded.first(),
+            Some(McpServerRegistryInstruction::BeginMcpServerRegistration { .. })
+        ));
+        assert_eq!(
+            decoded.last(),
+            Some(&McpServerRegistryInstruction::FinalizeMcpServerRegistration)
+        );
+
+        let mut tools = 0;
+        let mut resources = 0;
+        let mut prompts = 0;
+        for instruction in &decoded {
+            match instruction {
+                McpServerRegistryInstruction::AppendMcpToolDefinitions { tools: chunk } => {
+                    tools += chunk.len()
+                }
+                McpServerRegistryInstruction::AppendMcpResourceDefinitions { resources: chunk } => {
+                    resources += chunk.len()
+                }
+                McpServerRegistryInstruction::AppendMcpPromptDefinitions { prompts: chunk } => {
+                    prompts += chunk.len()
+                }
+                _ => {}
+            }
+        }
+        assert_eq!(tools, MAX_ONCHAIN_TOOL_DEFINITIONS);
+        assert_eq!(resources, MAX_ONCHAIN_RESOURCE_DEFINITIONS);
+        assert_eq!(prompts, MAX_ONCHAIN_PROMPT_DEFINITIONS);
+    }
 }
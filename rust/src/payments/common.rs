@@ -8,6 +8,10 @@ use solana_sdk::{
     system_program,
 };
 
+pub use crate::cluster::{
+    get_token_mint_for_network, A2AMPL_TOKEN_MINT_DEVNET, A2AMPL_TOKEN_MINT_MAINNET,
+};
+
 /// Payment system constants and configuration
 /// These values define the economic parameters of the Solana AI Registries payment system.
 ///
@@ -16,14 +20,6 @@ use solana_sdk::{
 /// - Program source: `programs/payment-system/src/lib.rs`
 /// - Tokenomics: [A2AMPL Token Economics](https://docs.solana-ai-registries.org/tokenomics)
 
-/// A2AMPL token mint address on Solana mainnet
-/// The native utility token for the Solana AI Registries ecosystem.
-pub const A2AMPL_TOKEN_MINT_MAINNET: &str = "Cpzvdx6pppc9TNArsGsqgShCsKC9NCCjA2gtzHvUpump";
-
-/// A2AMPL token mint address on Solana devnet  
-/// Used for development and testing purposes.
-pub const A2AMPL_TOKEN_MINT_DEVNET: &str = "A2AMPLyncKHwfSnwRNsJ2qsjsetgo9fGkP8YZPsDZ9mE";
-
 /// A2AMPL token decimals (9 decimal places)
 /// Following SPL token standard for precision.
 pub const A2AMPL_DECIMALS: u8 = 9;
@@ -216,19 +212,6 @@ pub fn convert_base_units_to_a2ampl(base_units: u64) -> f64 {
     base_units as f64 / A2AMPL_BASE_UNIT as f64
 }
 
-/// Get the appropriate token mint for the network
-pub fn get_token_mint_for_network(is_mainnet: bool) -> SdkResult<Pubkey> {
-    let mint_str = if is_mainnet {
-        A2AMPL_TOKEN_MINT_MAINNET
-    } else {
-        A2AMPL_TOKEN_MINT_DEVNET
-    };
-
-    mint_str
-        .parse()
-        .map_err(|_| SdkError::InvalidConfiguration("Invalid token mint address".to_string()))
-}
-
 /// Validate stake amount and lock period
 pub fn validate_staking_params(amount: u64, lock_period: i64) -> SdkResult<()> {
     if amount < MIN_STAKE_AMOUNT {
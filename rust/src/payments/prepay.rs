@@ -5,13 +5,16 @@
 
 use super::common::*;
 use crate::errors::{SdkError, SdkResult};
+use crate::plan::TransactionPlan;
 use borsh::{BorshDeserialize, BorshSerialize};
+use solana_client::nonblocking::rpc_client::RpcClient;
 use solana_sdk::{
     instruction::{AccountMeta, Instruction},
     pubkey::Pubkey,
     signature::Signer,
     system_program,
 };
+use std::sync::Arc;
 
 /// Prepaid account state
 #[derive(Debug, Clone, BorshSerialize, BorshDeserialize)]
@@ -68,15 +71,20 @@ impl PrepaidServiceType {
 
 /// Prepaid payment client
 pub struct PrepaidPaymentClient {
-    rpc_client: solana_client::rpc_client::RpcClient,
+    rpc_client: Arc<RpcClient>,
     program_id: Pubkey,
 }
 
 impl PrepaidPaymentClient {
     /// Create a new prepaid payment client
     pub fn new(rpc_url: &str, program_id: Pubkey) -> Self {
+        Self::from_rpc_client(Arc::new(RpcClient::new(rpc_url.to_string())), program_id)
+    }
+
+    /// Create a prepaid payment client on an existing RPC connection
+    pub fn from_rpc_client(rpc_client: Arc<RpcClient>, program_id: Pubkey) -> Self {
         Self {
-            rpc_client: solana_client::rpc_client::RpcClient::new(rpc_url.to_string()),
+            rpc_client,
             program_id,
         }
     }
@@ -88,29 +96,15 @@ impl PrepaidPaymentClient {
         token_mint: &Pubkey,
         args: CreatePrepaidAccountArgs,
     ) -> SdkResult<(Pubkey, PaymentResult)> {
-        if args.initial_deposit == 0 {
-            return Err(SdkError::ValidationError(
-                "Initial deposit cannot be zero".to_string(),
-            ));
-        }
-
-        let prepaid_pda = self.derive_prepaid_account_pda(&owner.pubkey())?;
+        let (prepaid_pda, plan) =
+            self.plan_create_prepaid_account(&owner.pubkey(), token_mint, &args)?;
 
         // Check if account already exists
         if self.account_exists(&prepaid_pda).await? {
             return Err(SdkError::AccountAlreadyExists);
         }
 
-        let instruction = self.create_prepaid_account_instruction(
-            &owner.pubkey(),
-            &prepaid_pda,
-            token_mint,
-            args.initial_deposit,
-        )?;
-
-        let signature = self
-            .send_and_confirm_transaction(owner, vec![instruction])
-            .await?;
+        let signature = self.send_plan(&plan, owner).await?;
 
         Ok((
             prepaid_pda,
@@ -123,6 +117,30 @@ impl PrepaidPaymentClient {
         ))
     }
 
+    /// [`Self::create_prepaid_account`] as an unsigned plan, with `owner`
+    /// paying the fee, together with the prepaid account it creates
+    pub fn plan_create_prepaid_account(
+        &self,
+        owner: &Pubkey,
+        token_mint: &Pubkey,
+        args: &CreatePrepaidAccountArgs,
+    ) -> SdkResult<(Pubkey, TransactionPlan)> {
+        if args.initial_deposit == 0 {
+            return Err(SdkError::ValidationError(
+                "Initial deposit cannot be zero".to_string(),
+            ));
+        }
+
+        let prepaid_pda = self.derive_prepaid_account_pda(owner)?;
+        let instruction = self.create_prepaid_account_instruction(
+            owner,
+            &prepaid_pda,
+            token_mint,
+            args.initial_deposit,
+        )?;
+        Ok((prepaid_pda, TransactionPlan::new(vec![instruction], *owner)))
+    }
+
     /// Top up a prepaid account
     pub async fn top_up<S: Signer>(
         &self,
@@ -130,23 +148,13 @@ impl PrepaidPaymentClient {
         token_mint: &Pubkey,
         args: TopUpArgs,
     ) -> SdkResult<PaymentResult> {
-        if args.amount == 0 {
-            return Err(SdkError::ValidationError(
-                "Top up amount cannot be zero".to_string(),
-            ));
-        }
-
+        let plan = self.plan_top_up(&owner.pubkey(), token_mint, &args)?;
         let prepaid_pda = self.derive_prepaid_account_pda(&owner.pubkey())?;
 
         // Check account exists
         let account = self.get_prepaid_account(&prepaid_pda).await?;
 
-        let instruction =
-            self.create_top_up_instruction(&owner.pubkey(), &prepaid_pda, token_mint, args.amount)?;
-
-        let signature = self
-            .send_and_confirm_transaction(owner, vec![instruction])
-            .await?;
+        let signature = self.send_plan(&plan, owner).await?;
 
         let new_balance = account.balance + args.amount;
 
@@ -158,6 +166,25 @@ impl PrepaidPaymentClient {
         })
     }
 
+    /// [`Self::top_up`] as an unsigned plan, with `owner` paying the fee
+    pub fn plan_top_up(
+        &self,
+        owner: &Pubkey,
+        token_mint: &Pubkey,
+        args: &TopUpArgs,
+    ) -> SdkResult<TransactionPlan> {
+        if args.amount == 0 {
+            return Err(SdkError::ValidationError(
+                "Top up amount cannot be zero".to_string(),
+            ));
+        }
+
+        let prepaid_pda = self.derive_prepaid_account_pda(owner)?;
+        let instruction =
+            self.create_top_up_instruction(owner, &prepaid_pda, token_mint, args.amount)?;
+        Ok(TransactionPlan::new(vec![instruction], *owner))
+    }
+
     /// Spend from prepaid account
     pub async fn spend<S: Signer>(
         &self,
@@ -165,10 +192,7 @@ impl PrepaidPaymentClient {
         recipient: &Pubkey,
         args: SpendArgs,
     ) -> SdkResult<PaymentResult> {
-        if args.amount < args.service_type.min_spend() {
-            return Err(SdkError::FeeTooLow);
-        }
-
+        let plan = self.plan_spend(&owner.pubkey(), recipient, &args)?;
         let prepaid_pda = self.derive_prepaid_account_pda(&owner.pubkey())?;
         let account = self.get_prepaid_account(&prepaid_pda).await?;
 
@@ -182,17 +206,7 @@ impl PrepaidPaymentClient {
             ));
         }
 
-        let instruction = self.create_spend_instruction(
-            &owner.pubkey(),
-            &prepaid_pda,
-            recipient,
-            args.amount,
-            args.service_type,
-        )?;
-
-        let signature = self
-            .send_and_confirm_transaction(owner, vec![instruction])
-            .await?;
+        let signature = self.send_plan(&plan, owner).await?;
 
         let new_balance = account.balance - args.amount;
 
@@ -204,11 +218,34 @@ impl PrepaidPaymentClient {
         })
     }
 
+    /// [`Self::spend`] as an unsigned plan, with `owner` paying the fee
+    pub fn plan_spend(
+        &self,
+        owner: &Pubkey,
+        recipient: &Pubkey,
+        args: &SpendArgs,
+    ) -> SdkResult<TransactionPlan> {
+        if args.amount < args.service_type.min_spend() {
+            return Err(SdkError::FeeTooLow);
+        }
+
+        let prepaid_pda = self.derive_prepaid_account_pda(owner)?;
+        let instruction = self.create_spend_instruction(
+            owner,
+            &prepaid_pda,
+            recipient,
+            args.amount,
+            args.service_type,
+        )?;
+        Ok(TransactionPlan::new(vec![instruction], *owner))
+    }
+
     /// Get prepaid account information
     pub async fn get_prepaid_account(&self, account_pda: &Pubkey) -> SdkResult<PrepaidAccount> {
         let account = self
             .rpc_client
             .get_account(account_pda)
+            .await
             .map_err(SdkError::ClientError)?;
 
         crate::client::deserialize_account_data(&account.data, "prepaid account")
@@ -237,13 +274,20 @@ impl PrepaidPaymentClient {
         owner: &S,
         active: bool,
     ) -> SdkResult<solana_sdk::signature::Signature> {
-        let prepaid_pda = self.derive_prepaid_account_pda(&owner.pubkey())?;
-
-        let instruction =
-            self.create_set_active_instruction(&owner.pubkey(), &prepaid_pda, active)?;
+        let plan = self.plan_set_account_active(&owner.pubkey(), active)?;
+        self.send_plan(&plan, owner).await
+    }
 
-        self.send_and_confirm_transaction(owner, vec![instruction])
-            .await
+    /// [`Self::set_account_active`] as an unsigned plan, with `owner` paying
+    /// the fee
+    pub fn plan_set_account_active(
+        &self,
+        owner: &Pubkey,
+        active: bool,
+    ) -> SdkResult<TransactionPlan> {
+        let prepaid_pda = self.derive_prepaid_account_pda(owner)?;
+        let instruction = self.create_set_active_instruction(owner, &prepaid_pda, active)?;
+        Ok(TransactionPlan::new(vec![instruction], *owner))
     }
 
     /// Withdraw remaining balance and close account
@@ -255,12 +299,8 @@ impl PrepaidPaymentClient {
         let prepaid_pda = self.derive_prepaid_account_pda(&owner.pubkey())?;
         let _account = self.get_prepaid_account(&prepaid_pda).await?;
 
-        let instruction =
-            self.create_close_account_instruction(&owner.pubkey(), &prepaid_pda, token_mint)?;
-
-        let signature = self
-            .send_and_confirm_transaction(owner, vec![instruction])
-            .await?;
+        let plan = self.plan_close_account(&owner.pubkey(), token_mint)?;
+        let signature = self.send_plan(&plan, owner).await?;
 
         Ok(PaymentResult {
             signature,
@@ -270,6 +310,18 @@ impl PrepaidPaymentClient {
         })
     }
 
+    /// [`Self::close_account`] as an unsigned plan, with `owner` paying the
+    /// fee
+    pub fn plan_close_account(
+        &self,
+        owner: &Pubkey,
+        token_mint: &Pubkey,
+    ) -> SdkResult<TransactionPlan> {
+        let prepaid_pda = self.derive_prepaid_account_pda(owner)?;
+        let instruction = self.create_close_account_instruction(owner, &prepaid_pda, token_mint)?;
+        Ok(TransactionPlan::new(vec![instruction], *owner))
+    }
+
     /// Derive prepaid account PDA
     fn derive_prepaid_account_pda(&self, owner: &Pubkey) -> SdkResult<Pubkey> {
         let seeds = &[b"prepaid_account", owner.as_ref()];
@@ -280,7 +332,7 @@ impl PrepaidPaymentClient {
 
     /// Check if account exists
     async fn account_exists(&self, account: &Pubkey) -> SdkResult<bool> {
-        match self.rpc_client.get_account(account) {
+        match self.rpc_client.get_account(account).await {
             Ok(_) => Ok(true),
             Err(_) => Ok(false),
         }
@@ -406,26 +458,22 @@ impl PrepaidPaymentClient {
         })
     }
 
-    /// Send and confirm transaction
-    async fn send_and_confirm_transaction<S: Signer>(
+    /// Sign a plan whose only signer is `signer` and send it
+    async fn send_plan<S: Signer>(
         &self,
+        plan: &TransactionPlan,
         signer: &S,
-        instructions: Vec<Instruction>,
     ) -> SdkResult<solana_sdk::signature::Signature> {
         let recent_blockhash = self
             .rpc_client
             .get_latest_blockhash()
+            .await
             .map_err(SdkError::ClientError)?;
-
-        let transaction = solana_sdk::transaction::Transaction::new_signed_with_payer(
-            &instructions,
-            Some(&signer.pubkey()),
-            &[signer],
-            recent_blockhash,
-        );
+        let transaction = plan.sign(recent_blockhash, &[signer])?;
 
         self.rpc_client
-            .send_and_confirm_transaction_with_spinner(&transaction)
+            .send_and_confirm_transaction(&transaction)
+            .await
             .map_err(SdkError::ClientError)
     }
 }
@@ -5,7 +5,9 @@
 
 use super::common::*;
 use crate::errors::{SdkError, SdkResult};
+use crate::plan::TransactionPlan;
 use borsh::{BorshDeserialize, BorshSerialize};
+use solana_client::nonblocking::rpc_client::RpcClient;
 use solana_sdk::{
     instruction::{AccountMeta, Instruction},
     program_pack::Pack,
@@ -14,6 +16,7 @@ use solana_sdk::{
     sysvar::{clock, rent},
 };
 use spl_token;
+use std::sync::Arc;
 
 /// Pay-as-you-go payment arguments
 #[derive(Debug, Clone, BorshSerialize, BorshDeserialize)]
@@ -47,15 +50,20 @@ impl PygServiceType {
 
 /// Pay-as-you-go payment client
 pub struct PygPaymentClient {
-    rpc_client: solana_client::rpc_client::RpcClient,
+    rpc_client: Arc<RpcClient>,
     token_program_id: Pubkey,
 }
 
 impl PygPaymentClient {
     /// Create a new PYG payment client
     pub fn new(rpc_url: &str) -> Self {
+        Self::from_rpc_client(Arc::new(RpcClient::new(rpc_url.to_string())))
+    }
+
+    /// Create a PYG payment client on an existing RPC connection
+    pub fn from_rpc_client(rpc_client: Arc<RpcClient>) -> Self {
         Self {
-            rpc_client: solana_client::rpc_client::RpcClient::new(rpc_url.to_string()),
+            rpc_client,
             token_program_id: spl_token::id(),
         }
     }
@@ -73,11 +81,9 @@ impl PygPaymentClient {
             return Err(SdkError::FeeTooLow);
         }
 
-        // Get or create token accounts
+        // Get or create the payer's token account; the recipient's is
+        // assumed to exist
         let payer_token_account = self.get_or_create_token_account(payer, token_mint).await?;
-        let recipient_token_account = self
-            .get_or_create_token_account_for_owner(recipient, token_mint)
-            .await?;
 
         // Check payer balance
         let balance = self.get_token_balance(&payer_token_account).await?;
@@ -85,13 +91,37 @@ impl PygPaymentClient {
             return Err(SdkError::InsufficientTokenBalance);
         }
 
-        // Create transfer instruction
-        let transfer_ix = self.create_transfer_instruction(
-            &payer_token_account,
-            &recipient_token_account,
-            &payer.pubkey(),
-            args.amount,
-        )?;
+        let plan = self.plan_pay(&payer.pubkey(), recipient, token_mint, &args)?;
+        let signature = self.send_plan(&plan, payer).await?;
+
+        // Get updated balance
+        let remaining_balance = self.get_token_balance(&payer_token_account).await?;
+
+        Ok(PaymentResult {
+            signature,
+            amount_paid: args.amount,
+            remaining_balance: Some(remaining_balance),
+            payment_method: PaymentMethod::PayAsYouGo,
+        })
+    }
+
+    /// [`Self::pay`] as an unsigned plan, with `payer` paying the fee. Both
+    /// token accounts must already exist.
+    pub fn plan_pay(
+        &self,
+        payer: &Pubkey,
+        recipient: &Pubkey,
+        token_mint: &Pubkey,
+        args: &PygPaymentArgs,
+    ) -> SdkResult<TransactionPlan> {
+        if args.amount < args.service_type.min_fee() {
+            return Err(SdkError::FeeTooLow);
+        }
+
+        let payer_token_account =
+            spl_associated_token_account::get_associated_token_address(payer, token_mint);
+        let recipient_token_account =
+            spl_associated_token_account::get_associated_token_address(recipient, token_mint);
 
         // Create compute budget instructions if specified
         let mut instructions = vec![];
@@ -112,22 +142,14 @@ impl PygPaymentClient {
             );
         }
 
-        instructions.push(transfer_ix);
-
-        // Send transaction
-        let signature = self
-            .send_and_confirm_transaction(payer, instructions)
-            .await?;
-
-        // Get updated balance
-        let remaining_balance = self.get_token_balance(&payer_token_account).await?;
+        instructions.push(self.create_transfer_instruction(
+            &payer_token_account,
+            &recipient_token_account,
+            payer,
+            args.amount,
+        )?);
 
-        Ok(PaymentResult {
-            signature,
-            amount_paid: args.amount,
-            remaining_balance: Some(remaining_balance),
-            payment_method: PaymentMethod::PayAsYouGo,
-        })
+        Ok(TransactionPlan::new(instructions, *payer))
     }
 
     /// Pay for agent service
@@ -211,6 +233,7 @@ impl PygPaymentClient {
         let account = self
             .rpc_client
             .get_account(token_account)
+            .await
             .map_err(SdkError::ClientError)?;
 
         let token_account_data = spl_token::state::Account::unpack(&account.data)
@@ -229,7 +252,7 @@ impl PygPaymentClient {
             spl_associated_token_account::get_associated_token_address(&owner.pubkey(), token_mint);
 
         // Check if account exists
-        match self.rpc_client.get_account(&token_account) {
+        match self.rpc_client.get_account(&token_account).await {
             Ok(_) => Ok(token_account),
             Err(_) => {
                 // Create the account
@@ -241,27 +264,16 @@ impl PygPaymentClient {
                         &spl_token::id(),
                     );
 
-                self.send_and_confirm_transaction(owner, vec![create_ix])
-                    .await?;
+                self.send_plan(
+                    &TransactionPlan::new(vec![create_ix], owner.pubkey()),
+                    owner,
+                )
+                .await?;
                 Ok(token_account)
             }
         }
     }
 
-    /// Get or create token account for a specific owner
-    async fn get_or_create_token_account_for_owner(
-        &self,
-        owner: &Pubkey,
-        token_mint: &Pubkey,
-    ) -> SdkResult<Pubkey> {
-        let token_account =
-            spl_associated_token_account::get_associated_token_address(owner, token_mint);
-
-        // For recipient accounts, we assume they exist or will be created by the recipient
-        // In a real implementation, you might want to handle account creation differently
-        Ok(token_account)
-    }
-
     /// Create a token transfer instruction
     fn create_transfer_instruction(
         &self,
@@ -283,26 +295,22 @@ impl PygPaymentClient {
         })?)
     }
 
-    /// Send and confirm a transaction
-    async fn send_and_confirm_transaction<S: Signer>(
+    /// Sign a plan whose only signer is `signer` and send it
+    async fn send_plan<S: Signer>(
         &self,
+        plan: &TransactionPlan,
         signer: &S,
-        instructions: Vec<Instruction>,
     ) -> SdkResult<solana_sdk::signature::Signature> {
         let recent_blockhash = self
             .rpc_client
             .get_latest_blockhash()
+            .await
             .map_err(SdkError::ClientError)?;
-
-        let transaction = solana_sdk::transaction::Transaction::new_signed_with_payer(
-            &instructions,
-            Some(&signer.pubkey()),
-            &[signer],
-            recent_blockhash,
-        );
+        let transaction = plan.sign(recent_blockhash, &[signer])?;
 
         self.rpc_client
-            .send_and_confirm_transaction_with_spinner(&transaction)
+            .send_and_confirm_transaction(&transaction)
+            .await
             .map_err(SdkError::ClientError)
     }
 }
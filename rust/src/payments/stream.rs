@@ -5,13 +5,16 @@
 
 use super::common::*;
 use crate::errors::{SdkError, SdkResult};
+use crate::plan::TransactionPlan;
 use borsh::{BorshDeserialize, BorshSerialize};
+use solana_client::nonblocking::rpc_client::RpcClient;
 use solana_sdk::{
     instruction::{AccountMeta, Instruction},
     pubkey::Pubkey,
     signature::Signer,
     system_program,
 };
+use std::sync::Arc;
 
 /// Streaming payment state
 #[derive(Debug, Clone, BorshSerialize, BorshDeserialize)]
@@ -59,15 +62,20 @@ pub struct StreamStatus {
 
 /// Streaming payment client
 pub struct StreamingPaymentClient {
-    rpc_client: solana_client::rpc_client::RpcClient,
+    rpc_client: Arc<RpcClient>,
     program_id: Pubkey,
 }
 
 impl StreamingPaymentClient {
     /// Create a new streaming payment client
     pub fn new(rpc_url: &str, program_id: Pubkey) -> Self {
+        Self::from_rpc_client(Arc::new(RpcClient::new(rpc_url.to_string())), program_id)
+    }
+
+    /// Create a streaming payment client on an existing RPC connection
+    pub fn from_rpc_client(rpc_client: Arc<RpcClient>, program_id: Pubkey) -> Self {
         Self {
-            rpc_client: solana_client::rpc_client::RpcClient::new(rpc_url.to_string()),
+            rpc_client,
             program_id,
         }
     }
@@ -78,6 +86,34 @@ impl StreamingPaymentClient {
         payer: &S,
         args: CreateStreamArgs,
     ) -> SdkResult<(u64, PaymentResult)> {
+        let (stream_id, plan) = self.plan_create_stream(&payer.pubkey(), &args).await?;
+        let stream_pda = self.derive_stream_pda(stream_id)?;
+
+        // Check if stream already exists
+        if self.account_exists(&stream_pda).await? {
+            return Err(SdkError::AccountAlreadyExists);
+        }
+
+        let signature = self.send_plan(&plan, payer).await?;
+
+        Ok((
+            stream_id,
+            PaymentResult {
+                signature,
+                amount_paid: 0, // No immediate payment, just stream setup
+                remaining_balance: Some(args.total_amount),
+                payment_method: PaymentMethod::Streaming,
+            },
+        ))
+    }
+
+    /// [`Self::create_stream`] as an unsigned plan, with `payer` paying the
+    /// fee, together with the ID of the stream it creates
+    pub async fn plan_create_stream(
+        &self,
+        payer: &Pubkey,
+        args: &CreateStreamArgs,
+    ) -> SdkResult<(u64, TransactionPlan)> {
         if args.total_amount == 0 {
             return Err(SdkError::ValidationError(
                 "Total amount cannot be zero".to_string(),
@@ -98,34 +134,11 @@ impl StreamingPaymentClient {
         }
 
         // Generate stream ID (in real implementation, this would be more sophisticated)
-        let stream_id = self
-            .generate_stream_id(&payer.pubkey(), &args.recipient)
-            .await?;
+        let stream_id = self.generate_stream_id(payer, &args.recipient).await?;
         let stream_pda = self.derive_stream_pda(stream_id)?;
 
-        // Check if stream already exists
-        if self.account_exists(&stream_pda).await? {
-            return Err(SdkError::AccountAlreadyExists);
-        }
-
-        let total_amount = args.total_amount; // Save value before move
-
-        let instruction =
-            self.create_stream_instruction(&payer.pubkey(), &stream_pda, stream_id, &args)?;
-
-        let signature = self
-            .send_and_confirm_transaction(payer, vec![instruction])
-            .await?;
-
-        Ok((
-            stream_id,
-            PaymentResult {
-                signature,
-                amount_paid: 0, // No immediate payment, just stream setup
-                remaining_balance: Some(total_amount),
-                payment_method: PaymentMethod::Streaming,
-            },
-        ))
+        let instruction = self.create_stream_instruction(payer, &stream_pda, stream_id, args)?;
+        Ok((stream_id, TransactionPlan::new(vec![instruction], *payer)))
     }
 
     /// Claim available funds from a stream
@@ -155,12 +168,8 @@ impl StreamingPaymentClient {
             ));
         }
 
-        let instruction =
-            self.create_claim_instruction(&claimer.pubkey(), &stream_pda, stream_id)?;
-
-        let signature = self
-            .send_and_confirm_transaction(claimer, vec![instruction])
-            .await?;
+        let plan = self.plan_claim_stream(&claimer.pubkey(), stream_id)?;
+        let signature = self.send_plan(&plan, claimer).await?;
 
         let new_remaining = stream.total_amount - (stream.amount_streamed + available);
 
@@ -172,6 +181,17 @@ impl StreamingPaymentClient {
         })
     }
 
+    /// [`Self::claim_stream`] as an unsigned plan, with `claimer` paying the fee
+    pub fn plan_claim_stream(
+        &self,
+        claimer: &Pubkey,
+        stream_id: u64,
+    ) -> SdkResult<TransactionPlan> {
+        let stream_pda = self.derive_stream_pda(stream_id)?;
+        let instruction = self.create_claim_instruction(claimer, &stream_pda, stream_id)?;
+        Ok(TransactionPlan::new(vec![instruction], *claimer))
+    }
+
     /// Pause a stream
     pub async fn pause_stream<S: Signer>(
         &self,
@@ -192,10 +212,15 @@ impl StreamingPaymentClient {
             ));
         }
 
-        let instruction = self.create_pause_instruction(&payer.pubkey(), &stream_pda, stream_id)?;
+        let plan = self.plan_pause_stream(&payer.pubkey(), stream_id)?;
+        self.send_plan(&plan, payer).await
+    }
 
-        self.send_and_confirm_transaction(payer, vec![instruction])
-            .await
+    /// [`Self::pause_stream`] as an unsigned plan, with `payer` paying the fee
+    pub fn plan_pause_stream(&self, payer: &Pubkey, stream_id: u64) -> SdkResult<TransactionPlan> {
+        let stream_pda = self.derive_stream_pda(stream_id)?;
+        let instruction = self.create_pause_instruction(payer, &stream_pda, stream_id)?;
+        Ok(TransactionPlan::new(vec![instruction], *payer))
     }
 
     /// Resume a paused stream
@@ -218,11 +243,15 @@ impl StreamingPaymentClient {
             ));
         }
 
-        let instruction =
-            self.create_resume_instruction(&payer.pubkey(), &stream_pda, stream_id)?;
+        let plan = self.plan_resume_stream(&payer.pubkey(), stream_id)?;
+        self.send_plan(&plan, payer).await
+    }
 
-        self.send_and_confirm_transaction(payer, vec![instruction])
-            .await
+    /// [`Self::resume_stream`] as an unsigned plan, with `payer` paying the fee
+    pub fn plan_resume_stream(&self, payer: &Pubkey, stream_id: u64) -> SdkResult<TransactionPlan> {
+        let stream_pda = self.derive_stream_pda(stream_id)?;
+        let instruction = self.create_resume_instruction(payer, &stream_pda, stream_id)?;
+        Ok(TransactionPlan::new(vec![instruction], *payer))
     }
 
     /// Cancel a stream and refund remaining balance
@@ -242,12 +271,8 @@ impl StreamingPaymentClient {
         let available_to_recipient = self.calculate_available_amount(&stream)?;
         let refund_amount = stream.total_amount - stream.amount_streamed - available_to_recipient;
 
-        let instruction =
-            self.create_cancel_instruction(&payer.pubkey(), &stream_pda, stream_id)?;
-
-        let signature = self
-            .send_and_confirm_transaction(payer, vec![instruction])
-            .await?;
+        let plan = self.plan_cancel_stream(&payer.pubkey(), stream_id)?;
+        let signature = self.send_plan(&plan, payer).await?;
 
         Ok(PaymentResult {
             signature,
@@ -257,11 +282,19 @@ impl StreamingPaymentClient {
         })
     }
 
+    /// [`Self::cancel_stream`] as an unsigned plan, with `payer` paying the fee
+    pub fn plan_cancel_stream(&self, payer: &Pubkey, stream_id: u64) -> SdkResult<TransactionPlan> {
+        let stream_pda = self.derive_stream_pda(stream_id)?;
+        let instruction = self.create_cancel_instruction(payer, &stream_pda, stream_id)?;
+        Ok(TransactionPlan::new(vec![instruction], *payer))
+    }
+
     /// Get stream information
     pub async fn get_stream(&self, stream_pda: &Pubkey) -> SdkResult<StreamingPayment> {
         let account = self
             .rpc_client
             .get_account(stream_pda)
+            .await
             .map_err(SdkError::ClientError)?;
 
         crate::client::deserialize_account_data(&account.data, "streaming payment")
@@ -355,7 +388,7 @@ impl StreamingPaymentClient {
 
     /// Check if account exists
     async fn account_exists(&self, account: &Pubkey) -> SdkResult<bool> {
-        match self.rpc_client.get_account(account) {
+        match self.rpc_client.get_account(account).await {
             Ok(_) => Ok(true),
             Err(_) => Ok(false),
         }
@@ -487,26 +520,22 @@ impl StreamingPaymentClient {
         })
     }
 
-    /// Send and confirm transaction
-    async fn send_and_confirm_transaction<S: Signer>(
+    /// Sign a plan whose only signer is `signer` and send it
+    async fn send_plan<S: Signer>(
         &self,
+        plan: &TransactionPlan,
         signer: &S,
-        instructions: Vec<Instruction>,
     ) -> SdkResult<solana_sdk::signature::Signature> {
         let recent_blockhash = self
             .rpc_client
             .get_latest_blockhash()
+            .await
             .map_err(SdkError::ClientError)?;
-
-        let transaction = solana_sdk::transaction::Transaction::new_signed_with_payer(
-            &instructions,
-            Some(&signer.pubkey()),
-            &[signer],
-            recent_blockhash,
-        );
+        let transaction = plan.sign(recent_blockhash, &[signer])?;
 
         self.rpc_client
-            .send_and_confirm_transaction_with_spinner(&transaction)
+            .send_and_confirm_transaction(&transaction)
+            .await
             .map_err(SdkError::ClientError)
     }
 }
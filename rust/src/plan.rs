@@ -0,0 +1,428 @@
+//! Unsigned transaction plans
+//!
+//! A [`TransactionPlan`] is the instructions of one registry or payment
+//! operation together with who pays its fees, compiled into a transaction
+//! only once a blockhash is known. It lets an operation leave the SDK
+//! unsigned, as a legacy or v0 transaction or as raw message bytes, to be
+//! signed by a wallet, a multisig or an offline signer and handed back to
+//! [`crate::SolanaAiRegistriesClient::submit_transaction`].
+//!
+//! A plan may use a durable nonce instead of a recent blockhash, so the
+//! transaction stays valid for as long as signing takes, and may carry a
+//! compute-unit limit and price for the Compute Budget program.
+
+use crate::chunking::{signed_transaction_size, MAX_TRANSACTION_SIZE};
+use crate::errors::{SdkError, SdkResult};
+use solana_sdk::{
+    account::Account,
+    account_utils::StateMut,
+    address_lookup_table_account::AddressLookupTableAccount,
+    compute_budget::ComputeBudgetInstruction,
+    hash::Hash,
+    instruction::Instruction,
+    message::{v0, Message, VersionedMessage},
+    nonce::state::{State as NonceState, Versions as NonceVersions},
+    pubkey::Pubkey,
+    signature::Signature,
+    signer::Signer,
+    system_instruction,
+    transaction::{Transaction, VersionedTransaction},
+};
+
+/// Nonce account a plan advances instead of using a recent blockhash
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DurableNonce {
+    /// The nonce account, whose stored value becomes the blockhash
+    pub account: Pubkey,
+    /// Authority of the nonce account, which must sign
+    pub authority: Pubkey,
+}
+
+/// Instructions of one operation, waiting for a blockhash and signatures
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TransactionPlan {
+    instructions: Vec<Instruction>,
+    fee_payer: Pubkey,
+    durable_nonce: Option<DurableNonce>,
+    compute_unit_limit: Option<u32>,
+    compute_unit_price: Option<u64>,
+}
+
+impl TransactionPlan {
+    /// Plan `instructions` with `fee_payer` paying the transaction fee
+    pub fn new(instructions: Vec<Instruction>, fee_payer: Pubkey) -> Self {
+        Self {
+            instructions,
+            fee_payer,
+            durable_nonce: None,
+            compute_unit_limit: None,
+            compute_unit_price: None,
+        }
+    }
+
+    /// Have `fee_payer` pay the transaction fee instead. The owner named in
+    /// the instructions still signs for them.
+    pub fn with_fee_payer(mut self, fee_payer: Pubkey) -> Self {
+        self.fee_payer = fee_payer;
+        self
+    }
+
+    /// Use the value stored in `nonce_account` as the blockhash, advancing
+    /// it as the transaction's first instruction. `authority` must sign.
+    pub fn with_durable_nonce(mut self, nonce_account: Pubkey, authority: Pubkey) -> Self {
+        self.durable_nonce = Some(DurableNonce {
+            account: nonce_account,
+            authority,
+        });
+        self
+    }
+
+    /// Request `units` compute units instead of the runtime default
+    pub fn with_compute_unit_limit(mut self, units: u32) -> Self {
+        self.compute_unit_limit = Some(units);
+        self
+    }
+
+    /// Bid `micro_lamports` per compute unit for priority
+    pub fn with_compute_unit_price(mut self, micro_lamports: u64) -> Self {
+        self.compute_unit_price = Some(micro_lamports);
+        self
+    }
+
+    /// The account paying the transaction fee
+    pub fn fee_payer(&self) -> &Pubkey {
+        &self.fee_payer
+    }
+
+    /// The durable nonce used instead of a recent blockhash, if any
+    pub fn durable_nonce(&self) -> Option<&DurableNonce> {
+        self.durable_nonce.as_ref()
+    }
+
+    /// The compute-unit limit requested, if any
+    pub fn compute_unit_limit(&self) -> Option<u32> {
+        self.compute_unit_limit
+    }
+
+    /// The priority price bid per compute unit, if any
+    pub fn compute_unit_price(&self) -> Option<u64> {
+        self.compute_unit_price
+    }
+
+    /// The instructions the transaction will carry: the nonce advance when
+    /// the plan uses a durable nonce, which must come first, then any
+    /// compute budget instructions, then the operation's own
+    pub fn instructions(&self) -> Vec<Instruction> {
+        let advance = self.durable_nonce.map(|nonce| {
+            system_instruction::advance_nonce_account(&nonce.account, &nonce.authority)
+        });
+        let limit = self
+            .compute_unit_limit
+            .map(ComputeBudgetInstruction::set_compute_unit_limit);
+        let price = self
+            .compute_unit_price
+            .map(ComputeBudgetInstruction::set_compute_unit_price);
+        advance
+            .into_iter()
+            .chain(limit)
+            .chain(price)
+            .chain(self.instructions.iter().cloned())
+            .collect()
+    }
+
+    /// Every account that must sign, fee payer first, in the order their
+    /// signatures appear in the transaction
+    pub fn required_signers(&self) -> Vec<Pubkey> {
+        let message = Message::new(&self.instructions(), Some(&self.fee_payer));
+        let num_signers = message.header.num_required_signatures as usize;
+        message.account_keys[..num_signers].to_vec()
+    }
+
+    /// Compile the legacy message for `blockhash`
+    pub fn message(&self, blockhash: Hash) -> Message {
+        Message::new_with_blockhash(&self.instructions(), Some(&self.fee_payer), &blockhash)
+    }
+
+    /// Compile a v0 message for `blockhash`, resolving accounts through
+    /// `lookup_tables` where they appear in one
+    pub fn v0_message(
+        &self,
+        blockhash: Hash,
+        lookup_tables: &[AddressLookupTableAccount],
+    ) -> SdkResult<v0::Message> {
+        v0::Message::try_compile(
+            &self.fee_payer,
+            &self.instructions(),
+            lookup_tables,
+            blockhash,
+        )
+        .map_err(|e| SdkError::BuildError(format!("Failed to compile v0 message: {}", e)))
+    }
+
+    /// The unsigned legacy transaction for `blockhash`
+    pub fn transaction(&self, blockhash: Hash) -> SdkResult<Transaction> {
+        let message = self.message(blockhash);
+        check_size(&VersionedMessage::Legacy(message.clone()))?;
+        Ok(Transaction::new_unsigned(message))
+    }
+
+    /// The unsigned v0 transaction for `blockhash`
+    pub fn versioned_transaction(
+        &self,
+        blockhash: Hash,
+        lookup_tables: &[AddressLookupTableAccount],
+    ) -> SdkResult<VersionedTransaction> {
+        let message = VersionedMessage::V0(self.v0_message(blockhash, lookup_tables)?);
+        check_size(&message)?;
+        Ok(VersionedTransaction {
+            signatures: vec![
+                Signature::default();
+                message.header().num_required_signatures as usize
+            ],
+            message,
+        })
+    }
+
+    /// The serialized legacy message for `blockhash`, the bytes every
+    /// signer signs
+    pub fn serialized_message(&self, blockhash: Hash) -> SdkResult<Vec<u8>> {
+        Ok(self.transaction(blockhash)?.message_data())
+    }
+
+    /// Compile for `blockhash` and sign with every required signer
+    pub fn sign(&self, blockhash: Hash, signers: &[&dyn Signer]) -> SdkResult<Transaction> {
+        let mut transaction = self.transaction(blockhash)?;
+        transaction
+            .try_sign(signers, blockhash)
+            .map_err(|e| SdkError::ValidationError(format!("Failed to sign transaction: {}", e)))?;
+        Ok(transaction)
+    }
+
+    /// Compile a v0 transaction for `blockhash`, resolving accounts through
+    /// `lookup_tables`, and sign with every required signer
+    pub fn sign_versioned(
+        &self,
+        blockhash: Hash,
+        lookup_tables: &[AddressLookupTableAccount],
+        signers: &[&dyn Signer],
+    ) -> SdkResult<VersionedTransaction> {
+        let mut transaction = self.versioned_transaction(blockhash, lookup_tables)?;
+        partially_sign(&mut transaction, signers)?;
+        if !missing_signers(&transaction).is_empty() {
+            return Err(SdkError::MissingRequiredSignature);
+        }
+        Ok(transaction)
+    }
+}
+
+/// Add the signatures of `signers` to a transaction that others sign too.
+/// Each signer must be one the message requires.
+pub fn partially_sign(
+    transaction: &mut VersionedTransaction,
+    signers: &[&dyn Signer],
+) -> SdkResult<()> {
+    let num_signers = transaction.message.header().num_required_signatures as usize;
+    let message_data = transaction.message.serialize();
+    let signer_keys = &transaction.message.static_account_keys()[..num_signers];
+
+    for signer in signers {
+        let position = signer_keys
+            .iter()
+            .position(|key| *key == signer.pubkey())
+            .ok_or_else(|| {
+                SdkError::ValidationError(format!(
+                    "{} is not a required signer of the transaction",
+                    signer.pubkey()
+                ))
+            })?;
+        let signature = signer
+            .try_sign_message(&message_data)
+            .map_err(|e| SdkError::ValidationError(format!("Failed to sign transaction: {}", e)))?;
+        transaction.signatures[position] = signature;
+    }
+    Ok(())
+}
+
+/// Required signers of `transaction` whose signature is still missing
+pub fn missing_signers(transaction: &VersionedTransaction) -> Vec<Pubkey> {
+    transaction
+        .message
+        .static_account_keys()
+        .iter()
+        .zip(&transaction.signatures)
+        .filter(|(_, signature)| **signature == Signature::default())
+        .map(|(key, _)| *key)
+        .collect()
+}
+
+/// The blockhash stored in a nonce account, which a transaction advancing
+/// that nonce must be built on
+pub fn nonce_blockhash(account: &Account) -> SdkResult<Hash> {
+    if account.owner != solana_sdk::system_program::id() {
+        return Err(SdkError::IncorrectAccountOwner);
+    }
+    let versions: NonceVersions = account.state().map_err(|e| {
+        SdkError::DeserializationError(format!("Failed to decode nonce account: {}", e))
+    })?;
+    match versions.state() {
+        NonceState::Initialized(data) => Ok(data.blockhash()),
+        NonceState::Uninitialized => Err(SdkError::InvalidAccountData),
+    }
+}
+
+/// Fail early for a transaction the cluster would reject for its size
+fn check_size(message: &VersionedMessage) -> SdkResult<()> {
+    let len = signed_transaction_size(
+        message.header().num_required_signatures as usize,
+        message.serialize().len(),
+    );
+    if len > MAX_TRANSACTION_SIZE {
+        return Err(SdkError::TransactionTooLarge(len, MAX_TRANSACTION_SIZE));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_sdk::{
+        instruction::AccountMeta,
+        nonce::state::{Data as NonceData, DurableNonce as StoredNonce},
+        signature::Keypair,
+    };
+
+    fn owner_instruction(owner: &Pubkey) -> Instruction {
+        Instruction::new_with_bytes(
+            Pubkey::new_unique(),
+            &[1, 2, 3],
+            vec![AccountMeta::new(*owner, true)],
+        )
+    }
+
+    #[test]
+    fn test_fee_payer_and_owner_both_sign() {
+        let owner = Keypair::new();
+        let fee_payer = Keypair::new();
+        let plan = TransactionPlan::new(vec![owner_instruction(&owner.pubkey())], owner.pubkey())
+            .with_fee_payer(fee_payer.pubkey());
+
+        assert_eq!(
+            plan.required_signers(),
+            vec![fee_payer.pubkey(), owner.pubkey()]
+        );
+
+        let blockhash = Hash::new_unique();
+        let mut transaction: VersionedTransaction = plan.transaction(blockhash).unwrap().into();
+        assert_eq!(
+            missing_signers(&transaction),
+            vec![fee_payer.pubkey(), owner.pubkey()]
+        );
+
+        partially_sign(&mut transaction, &[&owner]).unwrap();
+        assert_eq!(missing_signers(&transaction), vec![fee_payer.pubkey()]);
+        partially_sign(&mut transaction, &[&fee_payer]).unwrap();
+        assert!(missing_signers(&transaction).is_empty());
+        assert!(transaction.verify_with_results().iter().all(|ok| *ok));
+
+        let stranger = Keypair::new();
+        assert!(partially_sign(&mut transaction, &[&stranger]).is_err());
+    }
+
+    #[test]
+    fn test_sign_versioned_uses_lookup_tables() {
+        let owner = Keypair::new();
+        let vault = Pubkey::new_unique();
+        let mut instruction = owner_instruction(&owner.pubkey());
+        instruction.accounts.push(AccountMeta::new(vault, false));
+        let plan = TransactionPlan::new(vec![instruction], owner.pubkey());
+        let table = AddressLookupTableAccount {
+            key: Pubkey::new_unique(),
+            addresses: vec![Pubkey::new_unique(), vault],
+        };
+
+        let transaction = plan
+            .sign_versioned(Hash::new_unique(), &[table.clone()], &[&owner])
+            .unwrap();
+        let VersionedMessage::V0(message) = &transaction.message else {
+            panic!("expected a v0 message");
+        };
+        assert!(!message.account_keys.contains(&vault));
+        assert_eq!(message.address_table_lookups[0].account_key, table.key);
+        assert_eq!(message.address_table_lookups[0].writable_indexes, vec![1]);
+        assert!(transaction.verify_with_results().iter().all(|ok| *ok));
+
+        assert!(matches!(
+            plan.with_fee_payer(Pubkey::new_unique()).sign_versioned(
+                Hash::new_unique(),
+                &[table],
+                &[&owner]
+            ),
+            Err(SdkError::MissingRequiredSignature)
+        ));
+    }
+
+    #[test]
+    fn test_durable_nonce_advances_first() {
+        let owner = Pubkey::new_unique();
+        let nonce_account = Pubkey::new_unique();
+        let authority = Pubkey::new_unique();
+        let plan = TransactionPlan::new(vec![owner_instruction(&owner)], owner)
+            .with_durable_nonce(nonce_account, authority);
+
+        let instructions = plan.instructions();
+        assert_eq!(instructions.len(), 2);
+        assert_eq!(
+            instructions[0],
+            system_instruction::advance_nonce_account(&nonce_account, &authority)
+        );
+        assert!(plan.required_signers().contains(&authority));
+
+        let versioned = plan.versioned_transaction(Hash::default(), &[]).unwrap();
+        assert_eq!(versioned.signatures.len(), 2);
+        assert_eq!(
+            plan.serialized_message(Hash::default()).unwrap(),
+            plan.message(Hash::default()).serialize()
+        );
+    }
+
+    #[test]
+    fn test_compute_budget_follows_nonce_advance() {
+        let owner = Pubkey::new_unique();
+        let nonce_account = Pubkey::new_unique();
+        let operation = owner_instruction(&owner);
+        let plan = TransactionPlan::new(vec![operation.clone()], owner)
+            .with_durable_nonce(nonce_account, owner)
+            .with_compute_unit_limit(50_000)
+            .with_compute_unit_price(1_000);
+
+        assert_eq!(
+            plan.instructions(),
+            vec![
+                system_instruction::advance_nonce_account(&nonce_account, &owner),
+                ComputeBudgetInstruction::set_compute_unit_limit(50_000),
+                ComputeBudgetInstruction::set_compute_unit_price(1_000),
+                operation,
+            ]
+        );
+        assert_eq!(plan.required_signers(), vec![owner]);
+    }
+
+    #[test]
+    fn test_nonce_blockhash_reads_stored_value() {
+        let stored = StoredNonce::from_blockhash(&Hash::new_unique());
+        let versions = NonceVersions::new(NonceState::Initialized(NonceData::new(
+            Pubkey::new_unique(),
+            stored,
+            5000,
+        )));
+        let account = Account::new_data(1, &versions, &solana_sdk::system_program::id()).unwrap();
+
+        assert_eq!(nonce_blockhash(&account).unwrap(), *stored.as_hash());
+
+        let foreign = Account::new_data(1, &versions, &Pubkey::new_unique()).unwrap();
+        assert!(matches!(
+            nonce_blockhash(&foreign),
+            Err(SdkError::IncorrectAccountOwner)
+        ));
+    }
+}
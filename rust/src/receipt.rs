@@ -0,0 +1,108 @@
+//! Slippage guard and idempotency keys for fee-charging instructions
+//!
+//! `RecordUsageAndCollectFee` and `RecordServiceCompletion` take the most the
+//! paying user agrees to pay, checked against the entry's fees on-chain, and
+//! a client-generated idempotency key. The program
+//! records each key in a receipt account, so resending a transaction with the
+//! same key fails instead of charging twice. Generate one key per charge and
+//! reuse it only when retrying that charge.
+
+use crate::errors::{SdkError, SdkResult};
+use borsh::{BorshDeserialize, BorshSerialize};
+use solana_sdk::{pubkey::Pubkey, signature::Signer, signer::keypair::Keypair};
+
+/// Seed of a charge's receipt PDA
+pub const USAGE_RECEIPT_SEED: &[u8] = b"usage_receipt";
+
+/// Length of an idempotency key
+pub const IDEMPOTENCY_KEY_LEN: usize = 16;
+
+/// Client-generated key identifying one charge
+pub type IdempotencyKey = [u8; IDEMPOTENCY_KEY_LEN];
+
+/// Receipt of one charge (account data)
+#[derive(Debug, Clone, BorshSerialize, BorshDeserialize, PartialEq, Eq)]
+pub struct UsageReceipt {
+    pub bump: u8,
+    /// Entry that was charged for
+    pub entry: Pubkey,
+    /// Account that authorized the charge
+    pub caller: Pubkey,
+    pub idempotency_key: IdempotencyKey,
+    /// Amount charged
+    pub amount: u64,
+    pub timestamp: i64,
+}
+
+impl UsageReceipt {
+    /// Deserialize a receipt from account data
+    pub fn try_from_account_data(data: &[u8]) -> SdkResult<Self> {
+        Self::try_from_slice(data).map_err(|e| {
+            SdkError::DeserializationError(format!("Failed to deserialize UsageReceipt: {}", e))
+        })
+    }
+}
+
+/// Generate a fresh random idempotency key
+pub fn new_idempotency_key() -> IdempotencyKey {
+    let mut key = [0u8; IDEMPOTENCY_KEY_LEN];
+    key.copy_from_slice(&Keypair::new().pubkey().to_bytes()[..IDEMPOTENCY_KEY_LEN]);
+    key
+}
+
+/// Derive the receipt PDA of a charge by `caller` against `entry`
+pub fn derive_usage_receipt_pda(
+    program_id: &Pubkey,
+    entry: &Pubkey,
+    caller: &Pubkey,
+    idempotency_key: &IdempotencyKey,
+) -> Pubkey {
+    Pubkey::find_program_address(
+        &[
+            USAGE_RECEIPT_SEED,
+            entry.as_ref(),
+            caller.as_ref(),
+            idempotency_key,
+        ],
+        program_id,
+    )
+    .0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_idempotency_keys() {
+        assert_ne!(new_idempotency_key(), new_idempotency_key());
+
+        let program_id = Pubkey::new_unique();
+        let entry = Pubkey::new_unique();
+        let caller = Pubkey::new_unique();
+        let key = new_idempotency_key();
+        assert_eq!(
+            derive_usage_receipt_pda(&program_id, &entry, &caller, &key),
+            derive_usage_receipt_pda(&program_id, &entry, &caller, &key)
+        );
+        assert_ne!(
+            derive_usage_receipt_pda(&program_id, &entry, &caller, &key),
+            derive_usage_receipt_pda(&program_id, &entry, &caller, &new_idempotency_key())
+        );
+    }
+
+    #[test]
+    fn test_receipt_layout() {
+        let receipt = UsageReceipt {
+            bump: 255,
+            entry: Pubkey::new_unique(),
+            caller: Pubkey::new_unique(),
+            idempotency_key: [1; IDEMPOTENCY_KEY_LEN],
+            amount: 10,
+            timestamp: 0,
+        };
+        let data = receipt.try_to_vec().unwrap();
+        assert_eq!(data.len(), 1 + 32 + 32 + IDEMPOTENCY_KEY_LEN + 8 + 8);
+        assert_eq!(UsageReceipt::try_from_account_data(&data).unwrap(), receipt);
+    }
+}
@@ -0,0 +1,69 @@
+//! Rent estimation for registry entry accounts
+//!
+//! Registry entries are allocated at their serialized size, so the rent
+//! deposit depends on the registration args. This module reports that cost
+//! before a transaction is sent.
+
+use solana_sdk::rent::Rent;
+
+/// Account size and rent-exempt deposit for a registry entry
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RentEstimate {
+    /// Size of the entry account in bytes
+    pub account_size: usize,
+    /// Lamports required to keep the account rent exempt
+    pub lamports: u64,
+}
+
+impl RentEstimate {
+    /// Estimate using the given rent parameters
+    pub fn new(account_size: usize, rent: &Rent) -> Self {
+        Self {
+            account_size,
+            lamports: rent.minimum_balance(account_size),
+        }
+    }
+
+    /// Estimate using the default rent parameters, without an RPC round trip
+    pub fn from_default_rent(account_size: usize) -> Self {
+        Self::new(account_size, &Rent::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::AgentBuilder;
+    use crate::mcp::McpServerBuilder;
+
+    #[test]
+    fn test_estimate_grows_with_args() {
+        let small = AgentBuilder::new("agent", "Agent").build().unwrap();
+        let large = AgentBuilder::new("agent", "Agent")
+            .description("A considerably longer description of what this agent does")
+            .tags(vec!["alpha".to_string(), "beta".to_string()])
+            .build()
+            .unwrap();
+
+        let small_estimate = small.estimate_rent().unwrap();
+        let large_estimate = large.estimate_rent().unwrap();
+
+        assert!(large_estimate.account_size > small_estimate.account_size);
+        assert!(large_estimate.lamports > small_estimate.lamports);
+        assert_eq!(
+            small_estimate.lamports,
+            Rent::default().minimum_balance(small_estimate.account_size)
+        );
+    }
+
+    #[test]
+    fn test_mcp_server_estimate() {
+        let server = McpServerBuilder::new("server", "Server", "https://example.com/mcp")
+            .build()
+            .unwrap();
+
+        let estimate = server.estimate_rent().unwrap();
+        assert_eq!(estimate.account_size, server.account_size().unwrap());
+        assert!(estimate.lamports > 0);
+    }
+}
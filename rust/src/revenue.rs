@@ -0,0 +1,182 @@
+//! Protocol take-rate and revenue splits
+//!
+//! A registry's config sets a protocol take-rate in basis points, paid into
+//! the treasury vault when a fee is collected. Each entry can also name up to
+//! [`MAX_SPLIT_RECIPIENTS`] co-builders who share what the entry earns when it
+//! is paid out; the owner keeps the remainder. These types mirror the
+//! on-chain split account and reproduce its payout arithmetic.
+
+use crate::errors::{SdkError, SdkResult};
+use borsh::{BorshDeserialize, BorshSerialize};
+use solana_sdk::pubkey::Pubkey;
+
+/// Seed of an entry's revenue split PDA
+pub const REVENUE_SPLIT_SEED: &[u8] = b"revenue_split";
+
+/// Most recipients a revenue split can name
+pub const MAX_SPLIT_RECIPIENTS: usize = 8;
+
+/// Basis points in 100%
+pub const BPS_DENOMINATOR: u64 = 10_000;
+
+/// One recipient of a revenue split
+#[derive(Debug, Clone, Copy, Default, BorshSerialize, BorshDeserialize, PartialEq, Eq)]
+pub struct SplitRecipient {
+    /// Wallet whose token account receives the share
+    pub recipient: Pubkey,
+    /// Share of the payout, in basis points
+    pub bps: u16,
+}
+
+/// Revenue split account of a registry entry
+#[derive(Debug, Clone, BorshSerialize, BorshDeserialize, PartialEq)]
+pub struct RevenueSplit {
+    pub bump: u8,
+    pub entry: Pubkey,
+    pub recipient_count: u8,
+    pub recipients: [SplitRecipient; MAX_SPLIT_RECIPIENTS],
+    /// Shares held back for each recipient, in table order, because its
+    /// token account could not be paid
+    pub owed: [u64; MAX_SPLIT_RECIPIENTS],
+}
+
+impl RevenueSplit {
+    /// Deserialize a split from account data
+    pub fn try_from_account_data(data: &[u8]) -> SdkResult<Self> {
+        Self::try_from_slice(data).map_err(|e| {
+            SdkError::DeserializationError(format!("Failed to deserialize RevenueSplit: {}", e))
+        })
+    }
+
+    /// Recipients in use
+    pub fn active_recipients(&self) -> &[SplitRecipient] {
+        &self.recipients[..(self.recipient_count as usize).min(MAX_SPLIT_RECIPIENTS)]
+    }
+
+    /// Shares held back for all recipients
+    pub fn total_owed(&self) -> u64 {
+        self.owed.iter().sum()
+    }
+}
+
+/// Where a payout goes, as the program would divide it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PayoutPreview {
+    /// Take-rate paid into the treasury vault
+    pub protocol_fee: u64,
+    /// Share of each split recipient, in table order
+    pub shares: Vec<(Pubkey, u64)>,
+    /// Remainder paid to the owner, including rounding dust
+    pub owner_amount: u64,
+}
+
+/// Share of `amount` given by `bps` basis points, rounded down
+pub fn bps_share(amount: u64, bps: u16) -> u64 {
+    (amount as u128 * bps as u128 / BPS_DENOMINATOR as u128) as u64
+}
+
+/// Divide `amount` the way the program does: the protocol take-rate first,
+/// then each recipient's share of what is left, then the owner's remainder
+pub fn preview_payout(
+    amount: u64,
+    protocol_fee_bps: u16,
+    recipients: &[SplitRecipient],
+) -> PayoutPreview {
+    let protocol_fee = bps_share(amount, protocol_fee_bps);
+    let net = amount - protocol_fee;
+    let shares: Vec<(Pubkey, u64)> = recipients
+        .iter()
+        .map(|r| (r.recipient, bps_share(net, r.bps)))
+        .collect();
+    let owner_amount = net - shares.iter().map(|(_, share)| share).sum::<u64>();
+
+    PayoutPreview {
+        protocol_fee,
+        shares,
+        owner_amount,
+    }
+}
+
+/// Check a split table the way the program will before sending it
+pub fn validate_split(recipients: &[SplitRecipient]) -> SdkResult<()> {
+    if recipients.len() > MAX_SPLIT_RECIPIENTS {
+        return Err(SdkError::ValidationError(format!(
+            "A revenue split can name at most {} recipients",
+            MAX_SPLIT_RECIPIENTS
+        )));
+    }
+    let total_bps: u64 = recipients.iter().map(|r| r.bps as u64).sum();
+    if total_bps > BPS_DENOMINATOR || recipients.iter().any(|r| r.bps == 0) {
+        return Err(SdkError::ValidationError(
+            "Revenue split shares must be non-zero and total at most 10,000 bps".to_string(),
+        ));
+    }
+    for (i, recipient) in recipients.iter().enumerate() {
+        if recipients[..i]
+            .iter()
+            .any(|r| r.recipient == recipient.recipient)
+        {
+            return Err(SdkError::ValidationError(
+                "Revenue split recipients must be distinct".to_string(),
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Derive the revenue split PDA of a registry entry
+pub fn derive_revenue_split_pda(program_id: &Pubkey, entry: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[REVENUE_SPLIT_SEED, entry.as_ref()], program_id).0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn recipient(bps: u16) -> SplitRecipient {
+        SplitRecipient {
+            recipient: Pubkey::new_unique(),
+            bps,
+        }
+    }
+
+    #[test]
+    fn test_preview_payout() {
+        let recipients = [recipient(3_333), recipient(3_333)];
+        let preview = preview_payout(1_000, 250, &recipients);
+        assert_eq!(preview.protocol_fee, 25);
+        assert_eq!(preview.shares[0], (recipients[0].recipient, 324));
+        assert_eq!(preview.owner_amount, 975 - 2 * 324);
+
+        let no_split = preview_payout(1_000, 0, &[]);
+        assert_eq!(no_split.owner_amount, 1_000);
+    }
+
+    #[test]
+    fn test_validate_split() {
+        assert!(validate_split(&[recipient(5_000), recipient(5_000)]).is_ok());
+        assert!(validate_split(&[recipient(5_000), recipient(5_001)]).is_err());
+        assert!(validate_split(&[recipient(0)]).is_err());
+        assert!(validate_split(&[recipient(1); MAX_SPLIT_RECIPIENTS + 1]).is_err());
+
+        let duplicate = recipient(10);
+        assert!(validate_split(&[duplicate, duplicate]).is_err());
+    }
+
+    #[test]
+    fn test_split_account_layout() {
+        let mut recipients = [SplitRecipient::default(); MAX_SPLIT_RECIPIENTS];
+        recipients[0] = recipient(100);
+        let split = RevenueSplit {
+            bump: 254,
+            entry: Pubkey::new_unique(),
+            recipient_count: 1,
+            recipients,
+            owed: [0; MAX_SPLIT_RECIPIENTS],
+        };
+        let data = split.try_to_vec().unwrap();
+        assert_eq!(data.len(), 1 + 32 + 1 + 42 * MAX_SPLIT_RECIPIENTS);
+        let decoded = RevenueSplit::try_from_account_data(&data).unwrap();
+        assert_eq!(decoded.active_recipients(), &recipients[..1]);
+    }
+}
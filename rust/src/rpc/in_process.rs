@@ -0,0 +1,405 @@
+//! In-process registry backend
+//!
+//! [`InProcessRegistry`] runs the agent registry and MCP server registry
+//! processors natively inside a `solana-program-test` bank, together with
+//! the SPL Token and Associated Token Account programs. Each program's
+//! ProgramData account is seeded with the payer as upgrade authority, so
+//! registry configs can be bootstrapped exactly as on a real cluster.
+
+use super::{RegistryRpc, SimulationResult};
+use crate::cluster::Cluster;
+use crate::config::derive_program_data_address;
+use crate::errors::{SdkError, SdkResult};
+use async_trait::async_trait;
+use solana_client::{
+    client_error::ClientError,
+    rpc_custom_error::JSON_RPC_SERVER_ERROR_SEND_TRANSACTION_PREFLIGHT_FAILURE,
+    rpc_filter::RpcFilterType,
+    rpc_request::{RpcError, RpcResponseErrorData},
+    rpc_response::RpcSimulateTransactionResult,
+};
+use solana_program_test::{
+    processor, BanksClient, BanksClientError, ProgramTest, ProgramTestBanksClientExt,
+    ProgramTestContext,
+};
+use solana_sdk::{
+    account::{Account, AccountSharedData},
+    bpf_loader_upgradeable,
+    clock::{Clock, Slot},
+    commitment_config::CommitmentConfig,
+    hash::Hash,
+    instruction::Instruction,
+    program_pack::Pack,
+    pubkey::Pubkey,
+    rent::Rent,
+    signature::{Keypair, Signature, Signer},
+    slot_hashes::SlotHashes,
+    system_instruction,
+    transaction::{Transaction, VersionedTransaction},
+};
+use std::collections::BTreeSet;
+use std::sync::Mutex;
+
+/// Size of a ProgramData header: variant, slot and optional authority
+const PROGRAM_DATA_HEADER_LEN: usize = 45;
+
+/// Both registry programs running in an in-memory bank
+pub struct InProcessRegistry {
+    context: tokio::sync::Mutex<ProgramTestContext>,
+    payer: Keypair,
+    /// Every address seen in a processed transaction, which is what
+    /// `get_program_accounts` scans since the bank has no owner index
+    known_accounts: Mutex<BTreeSet<Pubkey>>,
+}
+
+impl InProcessRegistry {
+    /// Start a bank with both registries at their localnet program IDs, the
+    /// ones clients use by default
+    pub async fn start() -> Self {
+        let (agent_registry_program_id, mcp_server_registry_program_id) = Cluster::Localnet
+            .program_ids()
+            .expect("Localnet program IDs are known");
+        Self::start_with_program_ids(agent_registry_program_id, mcp_server_registry_program_id)
+            .await
+    }
+
+    /// Start a bank with the registries at the given program IDs
+    pub async fn start_with_program_ids(
+        agent_registry_program_id: Pubkey,
+        mcp_server_registry_program_id: Pubkey,
+    ) -> Self {
+        let mut program_test = ProgramTest::default();
+        program_test.prefer_bpf(false);
+        program_test.add_program(
+            "solana_a2a",
+            agent_registry_program_id,
+            processor!(solana_a2a::process_instruction),
+        );
+        program_test.add_program(
+            "solana_mcp",
+            mcp_server_registry_program_id,
+            processor!(solana_mcp::process_instruction),
+        );
+
+        let mut context = program_test.start_with_context().await;
+        let payer = context.payer.insecure_clone();
+        for program_id in [agent_registry_program_id, mcp_server_registry_program_id] {
+            context.set_account(
+                &derive_program_data_address(&program_id),
+                &program_data_account(&payer.pubkey()),
+            );
+        }
+
+        Self {
+            context: tokio::sync::Mutex::new(context),
+            payer,
+            known_accounts: Mutex::new(BTreeSet::new()),
+        }
+    }
+
+    /// The funded keypair that is upgrade authority of both registries and
+    /// mint authority of mints created with [`Self::create_mint`]
+    pub fn payer(&self) -> &Keypair {
+        &self.payer
+    }
+
+    /// Transfer `lamports` from the payer to `to`
+    pub async fn airdrop(&self, to: &Pubkey, lamports: u64) -> SdkResult<()> {
+        let instruction = system_instruction::transfer(&self.payer.pubkey(), to, lamports);
+        self.process(&[instruction], &[]).await
+    }
+
+    /// Create a token mint with the payer as mint authority
+    pub async fn create_mint(&self, decimals: u8) -> SdkResult<Pubkey> {
+        let mint = Keypair::new();
+        let instructions = [
+            system_instruction::create_account(
+                &self.payer.pubkey(),
+                &mint.pubkey(),
+                Rent::default().minimum_balance(spl_token::state::Mint::LEN),
+                spl_token::state::Mint::LEN as u64,
+                &spl_token::id(),
+            ),
+            spl_token::instruction::initialize_mint2(
+                &spl_token::id(),
+                &mint.pubkey(),
+                &self.payer.pubkey(),
+                None,
+                decimals,
+            )
+            .map_err(SdkError::ProgramError)?,
+        ];
+        self.process(&instructions, &[&mint]).await?;
+        Ok(mint.pubkey())
+    }
+
+    /// Create the associated token account of `owner` for `mint`, if it
+    /// does not exist yet
+    pub async fn create_token_account(&self, owner: &Pubkey, mint: &Pubkey) -> SdkResult<Pubkey> {
+        let instruction =
+            spl_associated_token_account::instruction::create_associated_token_account_idempotent(
+                &self.payer.pubkey(),
+                owner,
+                mint,
+                &spl_token::id(),
+            );
+        self.process(&[instruction], &[]).await?;
+        Ok(spl_associated_token_account::get_associated_token_address(
+            owner, mint,
+        ))
+    }
+
+    /// Mint `amount` base units of a mint created with [`Self::create_mint`]
+    /// to `token_account`
+    pub async fn mint_to(
+        &self,
+        mint: &Pubkey,
+        token_account: &Pubkey,
+        amount: u64,
+    ) -> SdkResult<()> {
+        let instruction = spl_token::instruction::mint_to(
+            &spl_token::id(),
+            mint,
+            token_account,
+            &self.payer.pubkey(),
+            &[],
+            amount,
+        )
+        .map_err(SdkError::ProgramError)?;
+        self.process(&[instruction], &[]).await
+    }
+
+    /// Move the bank's clock forward by `seconds`, for lock periods, fee
+    /// notice periods and subscription expiry.
+    ///
+    /// Returns once the bank has moved to a new blockhash, so a transaction
+    /// retried after the clock change is not rejected as a duplicate of one
+    /// sent before it.
+    pub async fn advance_clock(&self, seconds: i64) -> SdkResult<()> {
+        let mut banks_client = {
+            let context = self.context.lock().await;
+            let mut banks_client = context.banks_client.clone();
+            let mut clock: Clock = banks_client.get_sysvar().await.map_err(banks_error)?;
+            clock.unix_timestamp = clock.unix_timestamp.saturating_add(seconds);
+            context.set_sysvar(&clock);
+            banks_client
+        };
+
+        let blockhash = banks_client
+            .get_latest_blockhash()
+            .await
+            .map_err(banks_error)?;
+        banks_client
+            .get_new_latest_blockhash(&blockhash)
+            .await
+            .map_err(|e| SdkError::RpcError(e.to_string()))?;
+        Ok(())
+    }
+
+    /// Move the bank to the next slot, e.g. so that addresses just added to
+    /// a lookup table can be used
+    pub async fn advance_slot(&self) -> SdkResult<()> {
+        let mut context = self.context.lock().await;
+        let clock: Clock = context
+            .banks_client
+            .get_sysvar()
+            .await
+            .map_err(banks_error)?;
+        context
+            .warp_to_slot(clock.slot + 1)
+            .map_err(|e| SdkError::RpcError(format!("Failed to warp: {:?}", e)))
+    }
+
+    /// A handle on the bank, taken without holding the context lock while
+    /// the call runs
+    async fn banks_client(&self) -> BanksClient {
+        self.context.lock().await.banks_client.clone()
+    }
+
+    /// Remember the addresses a transaction touches
+    fn remember(&self, addresses: &[Pubkey]) {
+        self.known_accounts
+            .lock()
+            .expect("known accounts lock poisoned")
+            .extend(addresses.iter().copied());
+    }
+
+    /// Sign `instructions` with the payer and `signers` and process them
+    async fn process(&self, instructions: &[Instruction], signers: &[&Keypair]) -> SdkResult<()> {
+        let mut all_signers = vec![&self.payer];
+        all_signers.extend_from_slice(signers);
+        let transaction = Transaction::new_signed_with_payer(
+            instructions,
+            Some(&self.payer.pubkey()),
+            &all_signers,
+            self.latest_blockhash().await?,
+        );
+        self.send_transaction(&transaction.into()).await.map(|_| ())
+    }
+}
+
+#[async_trait]
+impl RegistryRpc for InProcessRegistry {
+    /// Transactions are applied as soon as they are processed
+    fn commitment(&self) -> CommitmentConfig {
+        CommitmentConfig::processed()
+    }
+
+    async fn get_account(&self, address: &Pubkey) -> SdkResult<Option<Account>> {
+        self.banks_client()
+            .await
+            .get_account(*address)
+            .await
+            .map_err(banks_error)
+    }
+
+    /// The newest finished slot, the one a confirmed read on a cluster
+    /// would report
+    async fn get_slot(&self) -> SdkResult<Slot> {
+        let slot_hashes: SlotHashes = self
+            .banks_client()
+            .await
+            .get_sysvar()
+            .await
+            .map_err(banks_error)?;
+        Ok(slot_hashes.first().map_or(0, |(slot, _)| *slot))
+    }
+
+    async fn get_multiple_accounts(&self, addresses: &[Pubkey]) -> SdkResult<Vec<Option<Account>>> {
+        let mut accounts = Vec::with_capacity(addresses.len());
+        for address in addresses {
+            accounts.push(self.get_account(address).await?);
+        }
+        Ok(accounts)
+    }
+
+    async fn get_program_accounts(
+        &self,
+        program_id: &Pubkey,
+        filters: Vec<RpcFilterType>,
+    ) -> SdkResult<Vec<(Pubkey, Account)>> {
+        let addresses: Vec<Pubkey> = self
+            .known_accounts
+            .lock()
+            .expect("known accounts lock poisoned")
+            .iter()
+            .copied()
+            .collect();
+        let accounts = self.get_multiple_accounts(&addresses).await?;
+
+        Ok(addresses
+            .into_iter()
+            .zip(accounts)
+            .filter_map(|(address, account)| Some((address, account?)))
+            .filter(|(_, account)| {
+                let shared = AccountSharedData::from(account.clone());
+                account.owner == *program_id && filters.iter().all(|f| f.allows(&shared))
+            })
+            .collect())
+    }
+
+    async fn latest_blockhash(&self) -> SdkResult<Hash> {
+        self.banks_client()
+            .await
+            .get_latest_blockhash()
+            .await
+            .map_err(banks_error)
+    }
+
+    /// Simulated first, like the RPC client's preflight, so a failure comes
+    /// back with its logs
+    async fn send_transaction(&self, transaction: &VersionedTransaction) -> SdkResult<Signature> {
+        self.remember(transaction.message.static_account_keys());
+        self.banks_client()
+            .await
+            .process_transaction_with_preflight(transaction.clone())
+            .await
+            .map_err(banks_error)?;
+        Ok(transaction.signatures[0])
+    }
+
+    async fn simulate_transaction(
+        &self,
+        transaction: &VersionedTransaction,
+    ) -> SdkResult<SimulationResult> {
+        let simulation = self
+            .banks_client()
+            .await
+            .simulate_transaction(transaction.clone())
+            .await
+            .map_err(banks_error)?;
+        let (logs, units_consumed) = simulation
+            .simulation_details
+            .map_or((Vec::new(), None), |details| {
+                (details.logs, Some(details.units_consumed))
+            });
+
+        Ok(SimulationResult {
+            err: simulation.result.and_then(Result::err),
+            logs,
+            units_consumed,
+        })
+    }
+}
+
+/// Upgradeable-loader ProgramData account naming `authority` as upgrade
+/// authority, with no program bytes behind the header
+fn program_data_account(authority: &Pubkey) -> AccountSharedData {
+    let mut data = vec![0u8; PROGRAM_DATA_HEADER_LEN];
+    data[0] = 3; // UpgradeableLoaderState::ProgramData
+    data[12] = 1; // Some(authority)
+    data[13..45].copy_from_slice(authority.as_ref());
+
+    AccountSharedData::from(Account {
+        lamports: Rent::default().minimum_balance(data.len()),
+        data,
+        owner: bpf_loader_upgradeable::id(),
+        executable: false,
+        rent_epoch: 0,
+    })
+}
+
+/// Surface failed transactions the way the RPC client does, so callers
+/// handle both backends alike. A failed preflight becomes a preflight-failure
+/// response, keeping its logs.
+fn banks_error(error: BanksClientError) -> SdkError {
+    match error {
+        BanksClientError::TransactionError(err) => SdkError::ClientError(ClientError::from(err)),
+        BanksClientError::SimulationError {
+            err,
+            logs,
+            units_consumed,
+            ..
+        } => SdkError::ClientError(ClientError::from(RpcError::RpcResponseError {
+            code: JSON_RPC_SERVER_ERROR_SEND_TRANSACTION_PREFLIGHT_FAILURE,
+            message: format!("Transaction simulation failed: {err}"),
+            data: RpcResponseErrorData::SendTransactionPreflightFailure(
+                RpcSimulateTransactionResult {
+                    err: Some(err),
+                    logs: Some(logs),
+                    accounts: None,
+                    units_consumed: Some(units_consumed),
+                    return_data: None,
+                    inner_instructions: None,
+                },
+            ),
+        })),
+        other => SdkError::RpcError(other.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_sdk::account::ReadableAccount;
+
+    #[test]
+    fn test_program_data_account_names_authority() {
+        let authority = Pubkey::new_unique();
+        let account = program_data_account(&authority);
+
+        assert_eq!(account.data().len(), PROGRAM_DATA_HEADER_LEN);
+        assert_eq!(&account.data()[..4], &3u32.to_le_bytes());
+        assert_eq!(&account.data()[13..], authority.as_ref());
+    }
+}
@@ -0,0 +1,248 @@
+//! RPC transport used by [`crate::SolanaAiRegistriesClient`]
+//!
+//! The client only needs a handful of calls, captured by [`RegistryRpc`].
+//! The nonblocking [`RpcClient`] implements it for real clusters; with the
+//! `in-process` feature, [`in_process::InProcessRegistry`] runs both registry
+//! programs in memory so whole flows can be tested without a validator.
+
+#[cfg(feature = "in-process")]
+pub mod in_process;
+
+use crate::errors::{SdkError, SdkResult};
+use async_trait::async_trait;
+use solana_account_decoder::{UiAccountEncoding, UiDataSliceConfig};
+use solana_client::{
+    client_error::ClientErrorKind,
+    nonblocking::rpc_client::RpcClient,
+    rpc_config::{RpcAccountInfoConfig, RpcProgramAccountsConfig},
+    rpc_filter::RpcFilterType,
+    rpc_request::RpcError,
+};
+use solana_sdk::{
+    account::Account, clock::Slot, commitment_config::CommitmentConfig, hash::Hash, pubkey::Pubkey,
+    signature::Signature, transaction::TransactionError, transaction::VersionedTransaction,
+};
+
+/// Most accounts `getMultipleAccounts` returns per request
+pub const MAX_MULTIPLE_ACCOUNTS: usize = 100;
+
+/// Outcome of simulating a transaction
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct SimulationResult {
+    /// Why the transaction would fail, or `None` if it would succeed
+    pub err: Option<TransactionError>,
+    /// Program log lines
+    pub logs: Vec<String>,
+    /// Compute units consumed, when the backend reports them
+    pub units_consumed: Option<u64>,
+}
+
+/// The RPC calls the registry client is built on
+#[async_trait]
+pub trait RegistryRpc: Send + Sync {
+    /// Commitment reads are made and transactions confirmed at
+    fn commitment(&self) -> CommitmentConfig;
+
+    /// Fetch an account, or `None` if it does not exist
+    async fn get_account(&self, address: &Pubkey) -> SdkResult<Option<Account>>;
+
+    /// Current slot at the client's commitment
+    async fn get_slot(&self) -> SdkResult<Slot>;
+
+    /// Fetch an account together with a slot its state is at least as new
+    /// as. Backends that report the slot of the read itself override this.
+    async fn get_account_with_slot(&self, address: &Pubkey) -> SdkResult<(Slot, Option<Account>)> {
+        let slot = self.get_slot().await?;
+        Ok((slot, self.get_account(address).await?))
+    }
+
+    /// Fetch the first `len` bytes of an account's data, or `None` if it
+    /// does not exist. Backends that support data slices avoid transferring
+    /// the rest.
+    async fn get_account_prefix(&self, address: &Pubkey, len: usize) -> SdkResult<Option<Vec<u8>>> {
+        Ok(self.get_account(address).await?.map(|account| {
+            let mut data = account.data;
+            data.truncate(len);
+            data
+        }))
+    }
+
+    /// Fetch several accounts, `None` for those that do not exist, in input
+    /// order
+    async fn get_multiple_accounts(&self, addresses: &[Pubkey]) -> SdkResult<Vec<Option<Account>>>;
+
+    /// Fetch every account owned by `program_id` that passes all `filters`
+    async fn get_program_accounts(
+        &self,
+        program_id: &Pubkey,
+        filters: Vec<RpcFilterType>,
+    ) -> SdkResult<Vec<(Pubkey, Account)>>;
+
+    /// Blockhash to sign new transactions with
+    async fn latest_blockhash(&self) -> SdkResult<Hash>;
+
+    /// Send a signed transaction and wait until it is confirmed. Fails with
+    /// [`SdkError::BlockhashExpired`] when the transaction can no longer
+    /// land.
+    async fn send_transaction(&self, transaction: &VersionedTransaction) -> SdkResult<Signature>;
+
+    /// Prioritization fees, in micro-lamports per compute unit, paid in
+    /// recent slots by transactions writing any of `writable_accounts`.
+    /// Backends without a fee market report none.
+    async fn recent_prioritization_fees(
+        &self,
+        _writable_accounts: &[Pubkey],
+    ) -> SdkResult<Vec<u64>> {
+        Ok(Vec::new())
+    }
+
+    /// Run a signed transaction without committing it
+    async fn simulate_transaction(
+        &self,
+        transaction: &VersionedTransaction,
+    ) -> SdkResult<SimulationResult>;
+}
+
+#[async_trait]
+impl RegistryRpc for RpcClient {
+    fn commitment(&self) -> CommitmentConfig {
+        RpcClient::commitment(self)
+    }
+
+    async fn get_account(&self, address: &Pubkey) -> SdkResult<Option<Account>> {
+        Ok(self
+            .get_account_with_commitment(address, RpcClient::commitment(self))
+            .await
+            .map_err(SdkError::ClientError)?
+            .value)
+    }
+
+    async fn get_slot(&self) -> SdkResult<Slot> {
+        RpcClient::get_slot(self)
+            .await
+            .map_err(SdkError::ClientError)
+    }
+
+    async fn get_account_with_slot(&self, address: &Pubkey) -> SdkResult<(Slot, Option<Account>)> {
+        let response = self
+            .get_account_with_commitment(address, RpcClient::commitment(self))
+            .await
+            .map_err(SdkError::ClientError)?;
+        Ok((response.context.slot, response.value))
+    }
+
+    async fn get_account_prefix(&self, address: &Pubkey, len: usize) -> SdkResult<Option<Vec<u8>>> {
+        let config = RpcAccountInfoConfig {
+            encoding: Some(UiAccountEncoding::Base64),
+            data_slice: Some(UiDataSliceConfig {
+                offset: 0,
+                length: len,
+            }),
+            commitment: Some(RpcClient::commitment(self)),
+            min_context_slot: None,
+        };
+
+        let response = self
+            .get_account_with_config(address, config)
+            .await
+            .map_err(SdkError::ClientError)?;
+        Ok(response.value.map(|account| account.data))
+    }
+
+    /// Requests over the `getMultipleAccounts` limit are split and sent
+    /// concurrently
+    async fn get_multiple_accounts(&self, addresses: &[Pubkey]) -> SdkResult<Vec<Option<Account>>> {
+        let commitment = RpcClient::commitment(self);
+        let chunks = addresses
+            .chunks(MAX_MULTIPLE_ACCOUNTS)
+            .map(|chunk| self.get_multiple_accounts_with_commitment(chunk, commitment));
+        let responses = futures::future::try_join_all(chunks)
+            .await
+            .map_err(SdkError::ClientError)?;
+
+        Ok(responses
+            .into_iter()
+            .flat_map(|response| response.value)
+            .collect())
+    }
+
+    async fn get_program_accounts(
+        &self,
+        program_id: &Pubkey,
+        filters: Vec<RpcFilterType>,
+    ) -> SdkResult<Vec<(Pubkey, Account)>> {
+        let config = RpcProgramAccountsConfig {
+            filters: Some(filters),
+            account_config: RpcAccountInfoConfig {
+                encoding: Some(UiAccountEncoding::Base64),
+                commitment: Some(RpcClient::commitment(self)),
+                ..RpcAccountInfoConfig::default()
+            },
+            ..RpcProgramAccountsConfig::default()
+        };
+
+        self.get_program_accounts_with_config(program_id, config)
+            .await
+            .map_err(SdkError::ClientError)
+    }
+
+    async fn latest_blockhash(&self) -> SdkResult<Hash> {
+        self.get_latest_blockhash()
+            .await
+            .map_err(SdkError::ClientError)
+    }
+
+    async fn send_transaction(&self, transaction: &VersionedTransaction) -> SdkResult<Signature> {
+        let error = match self.send_and_confirm_transaction(transaction).await {
+            Ok(signature) => return Ok(signature),
+            Err(error) => error,
+        };
+
+        // The client gives up with a plain message once it stops seeing the
+        // blockhash; tell expiry apart from other failures
+        let expired = matches!(
+            error.kind(),
+            ClientErrorKind::RpcError(RpcError::ForUser(_))
+        ) && !transaction.uses_durable_nonce()
+            && !self
+                .is_blockhash_valid(
+                    transaction.message.recent_blockhash(),
+                    CommitmentConfig::processed(),
+                )
+                .await
+                .map_err(SdkError::ClientError)?;
+        if expired {
+            return Err(SdkError::BlockhashExpired(transaction.signatures[0]));
+        }
+        Err(SdkError::ClientError(error))
+    }
+
+    async fn recent_prioritization_fees(
+        &self,
+        writable_accounts: &[Pubkey],
+    ) -> SdkResult<Vec<u64>> {
+        Ok(self
+            .get_recent_prioritization_fees(writable_accounts)
+            .await
+            .map_err(SdkError::ClientError)?
+            .into_iter()
+            .map(|fee| fee.prioritization_fee)
+            .collect())
+    }
+
+    async fn simulate_transaction(
+        &self,
+        transaction: &VersionedTransaction,
+    ) -> SdkResult<SimulationResult> {
+        let result = RpcClient::simulate_transaction(self, transaction)
+            .await
+            .map_err(SdkError::ClientError)?
+            .value;
+
+        Ok(SimulationResult {
+            err: result.err,
+            logs: result.logs.unwrap_or_default(),
+            units_consumed: result.units_consumed,
+        })
+    }
+}
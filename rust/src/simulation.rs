@@ -0,0 +1,781 @@
+//! Transaction simulation and registry error decoding
+//!
+//! Every client operation has a `simulate_*` counterpart that runs its
+//! unsigned plan against the cluster and returns a [`SimulationReport`]: the
+//! compute units used, the program logs, the events the registries emitted
+//! and, when the transaction would fail, a typed [`SdkError`].
+//!
+//! The same decoding applies on the send path: an
+//! `InstructionError::Custom` code raised by a registry program becomes the
+//! matching registry error, wrapped in [`SdkError::InstructionFailed`] with
+//! the failing instruction index and logs.
+
+use crate::agent::{AgentArgs, AgentPatch};
+use crate::client::SolanaAiRegistriesClient;
+use crate::config::RegistryConfigParams;
+use crate::errors::{SdkError, SdkResult};
+use crate::mcp::{
+    DiscountTier, FreeTier, ItemPriceInput, McpServerArgs, McpServerPatch, McpUsageFees,
+    SubscriptionQuota, UsageType,
+};
+use crate::plan::TransactionPlan;
+use crate::revenue::SplitRecipient;
+use crate::rpc::{RegistryRpc, SimulationResult};
+use serde_json::{Map, Value};
+use solana_client::{
+    client_error::{ClientError, ClientErrorKind},
+    rpc_request::{RpcError, RpcResponseErrorData},
+};
+use solana_sdk::{
+    instruction::InstructionError, message::VersionedMessage, program_error::ProgramError,
+    pubkey::Pubkey, transaction::TransactionError,
+};
+use std::str::FromStr;
+
+/// Prefix of the log lines registry programs emit events with
+const EVENT_LOG_PREFIX: &str = "Program log: EVENT: ";
+
+/// An event a registry program logged while executing
+#[derive(Debug, Clone, PartialEq)]
+pub struct RegistryEvent {
+    /// Program that emitted the event
+    pub program_id: Pubkey,
+    /// Event name, e.g. `AgentRegistered`
+    pub name: String,
+    /// Event fields, as logged in JSON or as `key=value` pairs
+    pub data: Value,
+}
+
+/// Outcome of simulating a registry operation
+#[derive(Debug)]
+pub struct SimulationReport {
+    /// Compute units consumed, when the backend reports them
+    pub units_consumed: Option<u64>,
+    /// Program log lines
+    pub logs: Vec<String>,
+    /// Events the registry programs emitted, in log order
+    pub events: Vec<RegistryEvent>,
+    /// Why the transaction would fail, or `None` if it would succeed
+    pub error: Option<SdkError>,
+}
+
+impl SimulationReport {
+    /// Decode `simulation` of a transaction carrying `message`
+    pub fn new(
+        simulation: SimulationResult,
+        message: &VersionedMessage,
+        registry_programs: &[Pubkey],
+    ) -> Self {
+        let events = parse_events(&simulation.logs);
+        let error = simulation.err.map(|err| {
+            decode_transaction_error(err, message, registry_programs, simulation.logs.clone())
+        });
+
+        Self {
+            units_consumed: simulation.units_consumed,
+            logs: simulation.logs,
+            events,
+            error,
+        }
+    }
+
+    /// Whether the transaction would succeed
+    pub fn is_success(&self) -> bool {
+        self.error.is_none()
+    }
+
+    /// The report, or the error the transaction would fail with
+    pub fn into_result(self) -> SdkResult<Self> {
+        match self.error {
+            Some(error) => Err(error),
+            None => Ok(self),
+        }
+    }
+}
+
+/// Events logged in `logs`, attributed to the program running when each
+/// was logged. Both the JSON and the `key=value` log formats are parsed.
+pub fn parse_events(logs: &[String]) -> Vec<RegistryEvent> {
+    let mut invoked: Vec<Pubkey> = Vec::new();
+    let mut events = Vec::new();
+
+    for line in logs {
+        if let Some(text) = line.strip_prefix(EVENT_LOG_PREFIX) {
+            let Some(program_id) = invoked.last() else {
+                continue;
+            };
+            let (name, fields) = text.split_once(' ').unwrap_or((text, ""));
+            events.push(RegistryEvent {
+                program_id: *program_id,
+                name: name.to_string(),
+                data: parse_event_data(fields),
+            });
+        } else if let Some(rest) = line.strip_prefix("Program ") {
+            let mut words = rest.split(' ');
+            let (Some(program), Some(status)) = (words.next(), words.next()) else {
+                continue;
+            };
+            match (Pubkey::from_str(program), status) {
+                (Ok(program_id), "invoke") => invoked.push(program_id),
+                (Ok(_), "success" | "failed:") => {
+                    invoked.pop();
+                }
+                _ => {}
+            }
+        }
+    }
+    events
+}
+
+/// Event fields logged as a JSON value or as space-separated `key=value`
+/// pairs, whose values may themselves contain spaces
+fn parse_event_data(fields: &str) -> Value {
+    let fields = fields.trim();
+    if let Ok(value) = serde_json::from_str(fields) {
+        return value;
+    }
+
+    let mut data = Map::new();
+    let mut current: Option<(String, String)> = None;
+    for token in fields.split(' ') {
+        match token.split_once('=') {
+            Some((key, value)) if is_field_key(key) => {
+                if let Some((key, value)) = current.take() {
+                    data.insert(key, field_value(&value));
+                }
+                current = Some((key.to_string(), value.to_string()));
+            }
+            _ => {
+                if let Some((_, value)) = current.as_mut() {
+                    value.push(' ');
+                    value.push_str(token);
+                }
+            }
+        }
+    }
+    if let Some((key, value)) = current {
+        data.insert(key, field_value(&value));
+    }
+    Value::Object(data)
+}
+
+fn is_field_key(key: &str) -> bool {
+    !key.is_empty()
+        && key
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_')
+}
+
+/// A logged value as JSON when it parses as such (numbers, booleans,
+/// string lists), otherwise as a string
+fn field_value(value: &str) -> Value {
+    serde_json::from_str(value).unwrap_or_else(|_| Value::String(value.to_string()))
+}
+
+/// Typed error for `err`, which a transaction carrying `message` failed
+/// with. A custom code is decoded as a registry error when a program in
+/// `registry_programs` raised it: the innermost failing program named in
+/// `logs`, or the failing instruction's program when there are no logs.
+pub fn decode_transaction_error(
+    err: TransactionError,
+    message: &VersionedMessage,
+    registry_programs: &[Pubkey],
+    logs: Vec<String>,
+) -> SdkError {
+    let TransactionError::InstructionError(index, instruction_error) = err else {
+        return SdkError::ClientError(err.into());
+    };
+
+    let failing_program = failing_program(&logs).or_else(|| {
+        message
+            .instructions()
+            .get(usize::from(index))
+            .map(|instruction| *instruction.program_id(message.static_account_keys()))
+    });
+    let error = match instruction_error {
+        InstructionError::Custom(code)
+            if failing_program.is_some_and(|program| registry_programs.contains(&program)) =>
+        {
+            SdkError::from_program_error_code(code)
+        }
+        other => match ProgramError::try_from(other.clone()) {
+            Ok(program_error) => SdkError::ProgramError(program_error),
+            Err(_) => {
+                SdkError::ClientError(TransactionError::InstructionError(index, other).into())
+            }
+        },
+    };
+
+    SdkError::InstructionFailed {
+        index,
+        error: Box::new(error),
+        logs,
+    }
+}
+
+/// `error` from sending a transaction carrying `message`, with an
+/// instruction failure decoded by [`decode_transaction_error`] using the
+/// preflight logs when the backend returned them
+pub fn decode_send_error(
+    error: SdkError,
+    message: &VersionedMessage,
+    registry_programs: &[Pubkey],
+) -> SdkError {
+    let SdkError::ClientError(client_error) = &error else {
+        return error;
+    };
+    match client_error.get_transaction_error() {
+        Some(err @ TransactionError::InstructionError(..)) => decode_transaction_error(
+            err,
+            message,
+            registry_programs,
+            preflight_logs(client_error),
+        ),
+        _ => error,
+    }
+}
+
+/// Logs of a failed preflight simulation, if `error` carries them
+fn preflight_logs(error: &ClientError) -> Vec<String> {
+    match error.kind() {
+        ClientErrorKind::RpcError(RpcError::RpcResponseError {
+            data: RpcResponseErrorData::SendTransactionPreflightFailure(result),
+            ..
+        }) => result.logs.clone().unwrap_or_default(),
+        _ => Vec::new(),
+    }
+}
+
+/// The first program logged as failing, which is the innermost one when a
+/// failure propagates through cross-program invocations
+fn failing_program(logs: &[String]) -> Option<Pubkey> {
+    logs.iter().find_map(|line| {
+        let (program, _) = line.strip_prefix("Program ")?.split_once(" failed: ")?;
+        Pubkey::from_str(program).ok()
+    })
+}
+
+/// `simulate_*` counterparts of the client operations, taking the same
+/// arguments as their `plan_*` builders. Nothing needs to be signed.
+impl<R: RegistryRpc> SolanaAiRegistriesClient<R> {
+    /// Simulate [`Self::register_agent_sponsored`]. Registrations sent in
+    /// chunks are simulated up to their first transaction, since the later
+    /// ones need it to have landed.
+    pub async fn simulate_register_agent(
+        &self,
+        owner: &Pubkey,
+        payer: &Pubkey,
+        args: AgentArgs,
+    ) -> SdkResult<SimulationReport> {
+        let plans = self.plan_register_agent(owner, payer, args)?;
+        self.simulate_first(&plans).await
+    }
+
+    /// Simulate [`Self::register_agent_with_token`]
+    pub async fn simulate_register_agent_with_token(
+        &self,
+        owner: &Pubkey,
+        payer: &Pubkey,
+        owner_token_account: &Pubkey,
+        args: AgentArgs,
+    ) -> SdkResult<SimulationReport> {
+        let plan = self
+            .plan_register_agent_with_token(owner, payer, owner_token_account, args)
+            .await?;
+        self.simulate_plan(&plan).await
+    }
+
+    /// Simulate [`Self::update_agent_sponsored`]
+    pub async fn simulate_update_agent(
+        &self,
+        owner: &Pubkey,
+        payer: &Pubkey,
+        agent_id: &str,
+        patch: AgentPatch,
+    ) -> SdkResult<SimulationReport> {
+        let plan = self.plan_update_agent(owner, payer, agent_id, patch)?;
+        self.simulate_plan(&plan).await
+    }
+
+    /// Simulate [`Self::update_agent_status`]
+    pub async fn simulate_update_agent_status(
+        &self,
+        owner: &Pubkey,
+        agent_id: &str,
+        status: u8,
+    ) -> SdkResult<SimulationReport> {
+        let plan = self.plan_update_agent_status(owner, agent_id, status)?;
+        self.simulate_plan(&plan).await
+    }
+
+    /// Simulate [`Self::deregister_agent`]
+    pub async fn simulate_deregister_agent(
+        &self,
+        owner: &Pubkey,
+        agent_id: &str,
+    ) -> SdkResult<SimulationReport> {
+        let plan = self.plan_deregister_agent(owner, agent_id)?;
+        self.simulate_plan(&plan).await
+    }
+
+    /// Simulate [`Self::stake_agent_tokens`]
+    pub async fn simulate_stake_agent_tokens(
+        &self,
+        owner: &Pubkey,
+        owner_token_account: &Pubkey,
+        agent_id: &str,
+        amount: u64,
+        lock_period: i64,
+    ) -> SdkResult<SimulationReport> {
+        let plan = self.plan_stake_agent_tokens(
+            owner,
+            owner_token_account,
+            agent_id,
+            amount,
+            lock_period,
+        )?;
+        self.simulate_plan(&plan).await
+    }
+
+    /// Simulate [`Self::unstake_agent_tokens`]
+    pub async fn simulate_unstake_agent_tokens(
+        &self,
+        owner: &Pubkey,
+        owner_token_account: &Pubkey,
+        agent_id: &str,
+        amount: u64,
+    ) -> SdkResult<SimulationReport> {
+        let plan = self.plan_unstake_agent_tokens(owner, owner_token_account, agent_id, amount)?;
+        self.simulate_plan(&plan).await
+    }
+
+    /// Simulate [`Self::register_mcp_server_sponsored`]. Registrations sent
+    /// in chunks are simulated up to their first transaction, since the
+    /// later ones need it to have landed.
+    pub async fn simulate_register_mcp_server(
+        &self,
+        owner: &Pubkey,
+        payer: &Pubkey,
+        args: McpServerArgs,
+    ) -> SdkResult<SimulationReport> {
+        let plans = self.plan_register_mcp_server(owner, payer, args)?;
+        self.simulate_first(&plans).await
+    }
+
+    /// Simulate [`Self::register_mcp_server_with_token`]
+    pub async fn simulate_register_mcp_server_with_token(
+        &self,
+        owner: &Pubkey,
+        payer: &Pubkey,
+        owner_token_account: &Pubkey,
+        args: McpServerArgs,
+    ) -> SdkResult<SimulationReport> {
+        let plan = self
+            .plan_register_mcp_server_with_token(owner, payer, owner_token_account, args)
+            .await?;
+        self.simulate_plan(&plan).await
+    }
+
+    /// Simulate [`Self::update_mcp_server_sponsored`]
+    pub async fn simulate_update_mcp_server(
+        &self,
+        owner: &Pubkey,
+        payer: &Pubkey,
+        server_id: &str,
+        patch: McpServerPatch,
+    ) -> SdkResult<SimulationReport> {
+        let plan = self.plan_update_mcp_server(owner, payer, server_id, patch)?;
+        self.simulate_plan(&plan).await
+    }
+
+    /// Simulate [`Self::update_mcp_server_status`]
+    pub async fn simulate_update_mcp_server_status(
+        &self,
+        owner: &Pubkey,
+        server_id: &str,
+        status: u8,
+    ) -> SdkResult<SimulationReport> {
+        let plan = self.plan_update_mcp_server_status(owner, server_id, status)?;
+        self.simulate_plan(&plan).await
+    }
+
+    /// Simulate [`Self::deregister_mcp_server`]
+    pub async fn simulate_deregister_mcp_server(
+        &self,
+        owner: &Pubkey,
+        server_id: &str,
+    ) -> SdkResult<SimulationReport> {
+        let plan = self.plan_deregister_mcp_server(owner, server_id)?;
+        self.simulate_plan(&plan).await
+    }
+
+    /// Simulate [`Self::initialize_mcp_server_fee_vault`]
+    pub async fn simulate_initialize_mcp_server_fee_vault(
+        &self,
+        owner: &Pubkey,
+        server_id: &str,
+    ) -> SdkResult<SimulationReport> {
+        let plan = self
+            .plan_initialize_mcp_server_fee_vault(owner, server_id)
+            .await?;
+        self.simulate_plan(&plan).await
+    }
+
+    /// Simulate [`Self::initialize_agent_registry_config`]
+    pub async fn simulate_initialize_agent_registry_config(
+        &self,
+        authority: &Pubkey,
+        params: RegistryConfigParams,
+    ) -> SdkResult<SimulationReport> {
+        let plan = self.plan_initialize_agent_registry_config(authority, params)?;
+        self.simulate_plan(&plan).await
+    }
+
+    /// Simulate [`Self::initialize_mcp_registry_config`]
+    pub async fn simulate_initialize_mcp_registry_config(
+        &self,
+        authority: &Pubkey,
+        params: RegistryConfigParams,
+    ) -> SdkResult<SimulationReport> {
+        let plan = self.plan_initialize_mcp_registry_config(authority, params)?;
+        self.simulate_plan(&plan).await
+    }
+
+    /// Simulate [`Self::update_agent_registry_config`]
+    pub async fn simulate_update_agent_registry_config<F>(
+        &self,
+        admin: &Pubkey,
+        update: F,
+    ) -> SdkResult<SimulationReport>
+    where
+        F: FnOnce(&mut RegistryConfigParams),
+    {
+        let plan = self
+            .plan_update_agent_registry_config(admin, update)
+            .await?;
+        self.simulate_plan(&plan).await
+    }
+
+    /// Simulate [`Self::update_mcp_registry_config`]
+    pub async fn simulate_update_mcp_registry_config<F>(
+        &self,
+        admin: &Pubkey,
+        update: F,
+    ) -> SdkResult<SimulationReport>
+    where
+        F: FnOnce(&mut RegistryConfigParams),
+    {
+        let plan = self.plan_update_mcp_registry_config(admin, update).await?;
+        self.simulate_plan(&plan).await
+    }
+
+    /// Simulate [`Self::transfer_agent_registry_admin`]
+    pub async fn simulate_transfer_agent_registry_admin(
+        &self,
+        admin: &Pubkey,
+        new_admin: &Pubkey,
+    ) -> SdkResult<SimulationReport> {
+        let plan = self
+            .plan_transfer_agent_registry_admin(admin, new_admin)
+            .await?;
+        self.simulate_plan(&plan).await
+    }
+
+    /// Simulate [`Self::transfer_mcp_registry_admin`]
+    pub async fn simulate_transfer_mcp_registry_admin(
+        &self,
+        admin: &Pubkey,
+        new_admin: &Pubkey,
+    ) -> SdkResult<SimulationReport> {
+        let plan = self
+            .plan_transfer_mcp_registry_admin(admin, new_admin)
+            .await?;
+        self.simulate_plan(&plan).await
+    }
+
+    /// Simulate [`Self::bootstrap_agent_registry`], or `None` if the
+    /// registry is already bootstrapped
+    pub async fn simulate_bootstrap_agent_registry(
+        &self,
+        authority: &Pubkey,
+        params: RegistryConfigParams,
+    ) -> SdkResult<Option<SimulationReport>> {
+        match self
+            .plan_bootstrap_agent_registry(authority, params)
+            .await?
+        {
+            Some(plan) => Ok(Some(self.simulate_plan(&plan).await?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Simulate [`Self::bootstrap_mcp_registry`], or `None` if the registry
+    /// is already bootstrapped
+    pub async fn simulate_bootstrap_mcp_registry(
+        &self,
+        authority: &Pubkey,
+        params: RegistryConfigParams,
+    ) -> SdkResult<Option<SimulationReport>> {
+        match self.plan_bootstrap_mcp_registry(authority, params).await? {
+            Some(plan) => Ok(Some(self.simulate_plan(&plan).await?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Simulate [`Self::set_agent_revenue_split`]
+    pub async fn simulate_set_agent_revenue_split(
+        &self,
+        owner: &Pubkey,
+        agent_id: &str,
+        recipients: Vec<SplitRecipient>,
+    ) -> SdkResult<SimulationReport> {
+        let plan = self.plan_set_agent_revenue_split(owner, agent_id, recipients)?;
+        self.simulate_plan(&plan).await
+    }
+
+    /// Simulate [`Self::set_mcp_revenue_split`]
+    pub async fn simulate_set_mcp_revenue_split(
+        &self,
+        owner: &Pubkey,
+        server_id: &str,
+        recipients: Vec<SplitRecipient>,
+    ) -> SdkResult<SimulationReport> {
+        let plan = self.plan_set_mcp_revenue_split(owner, server_id, recipients)?;
+        self.simulate_plan(&plan).await
+    }
+
+    /// Simulate [`Self::configure_mcp_usage_fees`]
+    pub async fn simulate_configure_mcp_usage_fees(
+        &self,
+        owner: &Pubkey,
+        server_id: &str,
+        fees: McpUsageFees,
+        free_tier: FreeTier,
+    ) -> SdkResult<SimulationReport> {
+        let plan = self.plan_configure_mcp_usage_fees(owner, server_id, fees, free_tier)?;
+        self.simulate_plan(&plan).await
+    }
+
+    /// Simulate [`Self::set_mcp_usage_pricing`]
+    pub async fn simulate_set_mcp_usage_pricing(
+        &self,
+        owner: &Pubkey,
+        server_id: &str,
+        item_prices: Vec<ItemPriceInput>,
+        discount_tiers: Vec<DiscountTier>,
+    ) -> SdkResult<SimulationReport> {
+        let plan =
+            self.plan_set_mcp_usage_pricing(owner, server_id, item_prices, discount_tiers)?;
+        self.simulate_plan(&plan).await
+    }
+
+    /// Simulate [`Self::record_mcp_usage`]
+    #[allow(clippy::too_many_arguments)]
+    pub async fn simulate_record_mcp_usage(
+        &self,
+        user: &Pubkey,
+        user_token_account: &Pubkey,
+        server_owner: &Pubkey,
+        server_id: &str,
+        usage_type: UsageType,
+        item_name: Option<&str>,
+        count: u32,
+    ) -> SdkResult<SimulationReport> {
+        let plan = self
+            .plan_record_mcp_usage(
+                user,
+                user_token_account,
+                server_owner,
+                server_id,
+                usage_type,
+                item_name,
+                count,
+            )
+            .await?;
+        self.simulate_plan(&plan).await
+    }
+
+    /// Simulate [`Self::set_mcp_subscription_plan`]
+    #[allow(clippy::too_many_arguments)]
+    pub async fn simulate_set_mcp_subscription_plan(
+        &self,
+        owner: &Pubkey,
+        server_id: &str,
+        plan_id: u8,
+        price: u64,
+        period: i64,
+        included: SubscriptionQuota,
+        active: bool,
+    ) -> SdkResult<SimulationReport> {
+        let plan = self.plan_set_mcp_subscription_plan(
+            owner, server_id, plan_id, price, period, included, active,
+        )?;
+        self.simulate_plan(&plan).await
+    }
+
+    /// Simulate [`Self::subscribe_mcp_server`]
+    pub async fn simulate_subscribe_mcp_server(
+        &self,
+        user: &Pubkey,
+        user_token_account: &Pubkey,
+        server_owner: &Pubkey,
+        server_id: &str,
+        plan_id: u8,
+    ) -> SdkResult<SimulationReport> {
+        let plan = self
+            .plan_subscribe_mcp_server(user, user_token_account, server_owner, server_id, plan_id)
+            .await?;
+        self.simulate_plan(&plan).await
+    }
+
+    /// Simulate [`Self::renew_mcp_subscription`]
+    pub async fn simulate_renew_mcp_subscription(
+        &self,
+        user: &Pubkey,
+        user_token_account: &Pubkey,
+        server_owner: &Pubkey,
+        server_id: &str,
+    ) -> SdkResult<SimulationReport> {
+        let plan = self
+            .plan_renew_mcp_subscription(user, user_token_account, server_owner, server_id)
+            .await?;
+        self.simulate_plan(&plan).await
+    }
+
+    /// Simulate [`Self::cancel_mcp_subscription`]
+    pub async fn simulate_cancel_mcp_subscription(
+        &self,
+        user: &Pubkey,
+        server_owner: &Pubkey,
+        server_id: &str,
+    ) -> SdkResult<SimulationReport> {
+        let plan = self.plan_cancel_mcp_subscription(user, server_owner, server_id)?;
+        self.simulate_plan(&plan).await
+    }
+
+    /// Simulate [`Self::withdraw_agent_treasury`]
+    pub async fn simulate_withdraw_agent_treasury(
+        &self,
+        admin: &Pubkey,
+        destination: &Pubkey,
+        amount: u64,
+    ) -> SdkResult<SimulationReport> {
+        let plan = self.plan_withdraw_agent_treasury(admin, destination, amount)?;
+        self.simulate_plan(&plan).await
+    }
+
+    /// Simulate [`Self::withdraw_mcp_treasury`]
+    pub async fn simulate_withdraw_mcp_treasury(
+        &self,
+        admin: &Pubkey,
+        destination: &Pubkey,
+        amount: u64,
+    ) -> SdkResult<SimulationReport> {
+        let plan = self.plan_withdraw_mcp_treasury(admin, destination, amount)?;
+        self.simulate_plan(&plan).await
+    }
+
+    /// Simulate the first of `plans`
+    async fn simulate_first(&self, plans: &[TransactionPlan]) -> SdkResult<SimulationReport> {
+        let plan = plans
+            .first()
+            .ok_or_else(|| SdkError::BuildError("Operation produced no transactions".into()))?;
+        self.simulate_plan(plan).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_sdk::{instruction::Instruction, message::Message};
+
+    fn logs(lines: &[&str]) -> Vec<String> {
+        lines.iter().map(|line| line.to_string()).collect()
+    }
+
+    #[test]
+    fn test_parse_events() {
+        let registry = Pubkey::new_unique();
+        let token = spl_token::id();
+        let logs = logs(&[
+            &format!("Program {registry} invoke [1]"),
+            "Program log: EVENT: AgentRegistered {\"agent_id\":\"a1\",\"fee\":5}",
+            &format!("Program {token} invoke [2]"),
+            "Program log: Instruction: Transfer",
+            &format!("Program {token} success"),
+            "Program log: EVENT: McpServerUpdated server_id=s1 fields=[\"name\", \"version\"]",
+            "Program log: EVENT: QualityMetricsUpdated uptime=99% name=My Server",
+            &format!("Program {registry} consumed 5000 of 200000 compute units"),
+            &format!("Program {registry} success"),
+            "Program log: EVENT: Orphan x=1",
+        ]);
+
+        let events = parse_events(&logs);
+        assert_eq!(events.len(), 3);
+        assert!(events.iter().all(|event| event.program_id == registry));
+        assert_eq!(events[0].name, "AgentRegistered");
+        assert_eq!(events[0].data["fee"], 5);
+        assert_eq!(events[1].data["server_id"], "s1");
+        assert_eq!(
+            events[1].data["fields"],
+            serde_json::json!(["name", "version"])
+        );
+        assert_eq!(events[2].data["uptime"], "99%");
+        assert_eq!(events[2].data["name"], "My Server");
+    }
+
+    #[test]
+    fn test_decode_registry_error() {
+        let registry = Pubkey::new_unique();
+        let other = Pubkey::new_unique();
+        let payer = Pubkey::new_unique();
+        let message = VersionedMessage::Legacy(Message::new(
+            &[
+                Instruction::new_with_bytes(other, &[], vec![]),
+                Instruction::new_with_bytes(registry, &[], vec![]),
+            ],
+            Some(&payer),
+        ));
+        let custom =
+            |index, code| TransactionError::InstructionError(index, InstructionError::Custom(code));
+
+        let error = decode_transaction_error(custom(1, 46), &message, &[registry], Vec::new());
+        assert_eq!(error.instruction_failure(), Some((1, &[][..])));
+        assert!(matches!(error.root_cause(), SdkError::AccountAlreadyExists));
+
+        // Codes from other programs are not registry errors
+        let error = decode_transaction_error(custom(0, 46), &message, &[registry], Vec::new());
+        assert!(matches!(
+            error.root_cause(),
+            SdkError::ProgramError(ProgramError::Custom(46))
+        ));
+
+        // A failure raised inside a CPI belongs to the innermost program
+        let token_logs = logs(&[
+            &format!("Program {registry} invoke [1]"),
+            &format!("Program {other} invoke [2]"),
+            &format!("Program {other} failed: custom program error: 0x1"),
+            &format!("Program {registry} failed: custom program error: 0x1"),
+        ]);
+        let error = decode_transaction_error(custom(1, 1), &message, &[registry], token_logs);
+        assert!(matches!(
+            error.root_cause(),
+            SdkError::ProgramError(ProgramError::Custom(1))
+        ));
+        assert_eq!(error.instruction_failure().unwrap().1.len(), 4);
+
+        let state_logs = logs(&[&format!(
+            "Program {registry} failed: custom program error: 0x34"
+        )]);
+        let error = decode_transaction_error(custom(1, 52), &message, &[registry], state_logs);
+        assert!(error.is_state_version_mismatch());
+
+        let error = decode_transaction_error(
+            TransactionError::AccountNotFound,
+            &message,
+            &[registry],
+            Vec::new(),
+        );
+        assert!(matches!(error, SdkError::ClientError(_)));
+    }
+}
@@ -0,0 +1,530 @@
+//! Live subscriptions to registry entries over PubSub websockets
+//!
+//! [`RegistrySubscriber`], from
+//! [`crate::SolanaAiRegistriesClient::subscriptions`], follows agent and MCP
+//! server entries. Each subscription is a [`RegistrySubscription`], a
+//! [`Stream`] of decoded entry updates and of the events registry programs
+//! log in transactions touching them.
+//!
+//! Each subscription runs on a background task that reconnects with backoff
+//! when the websocket drops, reporting the failure as an `Err` item and then
+//! carrying on. Updates are delivered in slot order per account: anything
+//! older than what was already delivered, as can happen around a reconnect,
+//! is dropped. Dropping the subscription stops the task.
+
+use crate::agent::{derive_agent_pda, AgentEntry};
+use crate::cluster::ClientConfig;
+use crate::errors::{SdkError, SdkResult};
+use crate::mcp::{derive_mcp_server_pda, McpServerEntry};
+use crate::simulation::{parse_events, RegistryEvent};
+use crate::zero_copy::EntryHeader;
+use futures::channel::mpsc::{unbounded, UnboundedReceiver, UnboundedSender};
+use futures::stream::{BoxStream, Stream, StreamExt};
+use solana_account_decoder::{UiAccount, UiAccountEncoding};
+use solana_client::{
+    nonblocking::{pubsub_client::PubsubClient, rpc_client::RpcClient},
+    rpc_config::{
+        RpcAccountInfoConfig, RpcProgramAccountsConfig, RpcTransactionLogsConfig,
+        RpcTransactionLogsFilter,
+    },
+    rpc_filter::{Memcmp, RpcFilterType},
+    rpc_response::{Response, RpcLogsResponse},
+};
+use solana_sdk::{
+    account::Account, clock::Slot, commitment_config::CommitmentConfig, pubkey::Pubkey,
+    signature::Signature,
+};
+use std::collections::HashMap;
+use std::pin::Pin;
+use std::str::FromStr;
+use std::task::{Context, Poll};
+use std::time::Duration;
+use tokio::task::JoinHandle;
+
+/// Delay before the first reconnection attempt
+pub const RECONNECT_DELAY: Duration = Duration::from_millis(500);
+
+/// Longest delay between reconnection attempts; the delay doubles after
+/// every failed attempt up to this
+pub const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(30);
+
+/// Byte offset of `owner_authority` in registry entry accounts
+const OWNER_AUTHORITY_OFFSET: usize = std::mem::offset_of!(EntryHeader, owner_authority);
+
+/// A change seen by a subscription
+#[derive(Debug, Clone, PartialEq)]
+pub enum RegistryUpdate<T> {
+    /// An entry account changed at `slot`; `entry` is `None` once the
+    /// account is closed
+    Account {
+        slot: Slot,
+        address: Pubkey,
+        entry: Option<T>,
+    },
+    /// A successful transaction touching the subscription's account or
+    /// program logged registry events
+    Events {
+        slot: Slot,
+        signature: Signature,
+        events: Vec<RegistryEvent>,
+    },
+}
+
+/// An agent or MCP server entry, as yielded by program subscriptions
+#[derive(Debug, Clone)]
+pub enum RegistryEntry {
+    Agent(AgentEntry),
+    McpServer(McpServerEntry),
+}
+
+/// Which entries a program subscription follows
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RegistryFilter {
+    /// Agent entries, optionally only those owned by `owner`
+    Agents { owner: Option<Pubkey> },
+    /// MCP server entries, optionally only those owned by `owner`
+    McpServers { owner: Option<Pubkey> },
+}
+
+/// Stream of updates from a live subscription. Errors are reported as they
+/// happen; the subscription keeps reconnecting until it is dropped.
+pub struct RegistrySubscription<T> {
+    updates: UnboundedReceiver<SdkResult<RegistryUpdate<T>>>,
+    task: JoinHandle<()>,
+}
+
+impl<T> Stream for RegistrySubscription<T> {
+    type Item = SdkResult<RegistryUpdate<T>>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.updates.poll_next_unpin(cx)
+    }
+}
+
+impl<T> Drop for RegistrySubscription<T> {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+/// What a subscription watches
+#[derive(Clone)]
+enum Target {
+    /// One entry, read in full whenever the websocket (re)connects
+    Account(Pubkey),
+    /// Every account of the program passing `filters`; accounts that do not
+    /// decode as entries are skipped
+    Program(Vec<RpcFilterType>),
+}
+
+/// Everything a subscription task needs, independent of the client
+struct Subscriber<T> {
+    ws_url: String,
+    rpc_url: String,
+    commitment: CommitmentConfig,
+    program_id: Pubkey,
+    target: Target,
+    decode: fn(&[u8]) -> SdkResult<T>,
+}
+
+/// A notification from either of a session's websocket subscriptions
+enum Notification {
+    Account(Pubkey, Slot, UiAccount),
+    Logs(Response<RpcLogsResponse>),
+}
+
+/// Opens live subscriptions to the registries of one deployment. These need
+/// a cluster with a PubSub endpoint; see [`ClientConfig::websocket_url`].
+#[derive(Debug, Clone)]
+pub struct RegistrySubscriber {
+    ws_url: String,
+    rpc_url: String,
+    commitment: CommitmentConfig,
+    agent_registry_program_id: Pubkey,
+    mcp_server_registry_program_id: Pubkey,
+}
+
+impl RegistrySubscriber {
+    /// Subscriber for the deployment described by `config`
+    pub fn new(config: &ClientConfig) -> Self {
+        Self {
+            ws_url: config.websocket_url(),
+            rpc_url: config.rpc_url.clone(),
+            commitment: config.commitment,
+            agent_registry_program_id: config.agent_registry_program_id,
+            mcp_server_registry_program_id: config.mcp_server_registry_program_id,
+        }
+    }
+
+    /// Follow an agent entry: its current state, then every change and the
+    /// events of the transactions that touch it
+    pub fn subscribe_agent(
+        &self,
+        owner: &Pubkey,
+        agent_id: &str,
+    ) -> SdkResult<RegistrySubscription<AgentEntry>> {
+        let program_id = self.agent_registry_program_id;
+        let address = derive_agent_pda(&program_id, owner, agent_id)?;
+        Ok(self.spawn_subscription(
+            program_id,
+            Target::Account(address),
+            AgentEntry::try_from_account_data,
+        ))
+    }
+
+    /// Follow an MCP server entry: its current state, then every change and
+    /// the events of the transactions that touch it
+    pub fn subscribe_mcp_server(
+        &self,
+        owner: &Pubkey,
+        server_id: &str,
+    ) -> SdkResult<RegistrySubscription<McpServerEntry>> {
+        let program_id = self.mcp_server_registry_program_id;
+        let address = derive_mcp_server_pda(&program_id, owner, server_id)?;
+        Ok(self.spawn_subscription(
+            program_id,
+            Target::Account(address),
+            McpServerEntry::try_from_account_data,
+        ))
+    }
+
+    /// Follow every entry of a registry passing `filter`, and the events the
+    /// registry logs. Only changes are delivered; read the current entries
+    /// first if they are needed.
+    pub fn subscribe_program(&self, filter: RegistryFilter) -> RegistrySubscription<RegistryEntry> {
+        let owner_filter = |owner: Option<Pubkey>| {
+            owner
+                .map(|owner| {
+                    RpcFilterType::Memcmp(Memcmp::new_base58_encoded(
+                        OWNER_AUTHORITY_OFFSET,
+                        owner.as_ref(),
+                    ))
+                })
+                .into_iter()
+                .collect()
+        };
+
+        match filter {
+            RegistryFilter::Agents { owner } => self.spawn_subscription(
+                self.agent_registry_program_id,
+                Target::Program(owner_filter(owner)),
+                |data| AgentEntry::try_from_account_data(data).map(RegistryEntry::Agent),
+            ),
+            RegistryFilter::McpServers { owner } => self.spawn_subscription(
+                self.mcp_server_registry_program_id,
+                Target::Program(owner_filter(owner)),
+                |data| McpServerEntry::try_from_account_data(data).map(RegistryEntry::McpServer),
+            ),
+        }
+    }
+
+    fn spawn_subscription<T: Send + 'static>(
+        &self,
+        program_id: Pubkey,
+        target: Target,
+        decode: fn(&[u8]) -> SdkResult<T>,
+    ) -> RegistrySubscription<T> {
+        let subscriber = Subscriber {
+            ws_url: self.ws_url.clone(),
+            rpc_url: self.rpc_url.clone(),
+            commitment: self.commitment,
+            program_id,
+            target,
+            decode,
+        };
+        let (sender, updates) = unbounded();
+        let task = tokio::spawn(async move { subscriber.run(sender).await });
+
+        RegistrySubscription { updates, task }
+    }
+}
+
+impl<T> Subscriber<T> {
+    /// Keep a session open until the subscription is dropped, reconnecting
+    /// with backoff whenever one ends
+    async fn run(self, sender: UnboundedSender<SdkResult<RegistryUpdate<T>>>) {
+        let mut last_slots = HashMap::new();
+        let mut delay = RECONNECT_DELAY;
+        loop {
+            let error = match self.session(&sender, &mut last_slots, &mut delay).await {
+                Ok(()) => return,
+                Err(error) => error,
+            };
+            if sender.unbounded_send(Err(error)).is_err() {
+                return;
+            }
+            tokio::time::sleep(delay).await;
+            delay = (delay * 2).min(MAX_RECONNECT_DELAY);
+        }
+    }
+
+    /// Subscribe, then forward notifications until the websocket drops
+    /// (an error) or the receiver is gone (`Ok`)
+    async fn session(
+        &self,
+        sender: &UnboundedSender<SdkResult<RegistryUpdate<T>>>,
+        last_slots: &mut HashMap<Pubkey, Slot>,
+        delay: &mut Duration,
+    ) -> SdkResult<()> {
+        let pubsub = PubsubClient::new(&self.ws_url)
+            .await
+            .map_err(|e| SdkError::NetworkError(e.to_string()))?;
+        let account_config = RpcAccountInfoConfig {
+            encoding: Some(UiAccountEncoding::Base64),
+            commitment: Some(self.commitment),
+            ..RpcAccountInfoConfig::default()
+        };
+
+        let (accounts, mentioned): (BoxStream<'_, Notification>, Pubkey) = match &self.target {
+            Target::Account(address) => {
+                let address = *address;
+                let (stream, _) = pubsub
+                    .account_subscribe(&address, Some(account_config))
+                    .await
+                    .map_err(|e| SdkError::NetworkError(e.to_string()))?;
+                let stream = stream.map(move |response| {
+                    Notification::Account(address, response.context.slot, response.value)
+                });
+                (stream.boxed(), address)
+            }
+            Target::Program(filters) => {
+                let config = RpcProgramAccountsConfig {
+                    filters: Some(filters.clone()),
+                    account_config,
+                    ..RpcProgramAccountsConfig::default()
+                };
+                let (stream, _) = pubsub
+                    .program_subscribe(&self.program_id, Some(config))
+                    .await
+                    .map_err(|e| SdkError::NetworkError(e.to_string()))?;
+                let stream = stream.filter_map(|response| async move {
+                    let address = Pubkey::from_str(&response.value.pubkey).ok()?;
+                    Some(Notification::Account(
+                        address,
+                        response.context.slot,
+                        response.value.account,
+                    ))
+                });
+                (stream.boxed(), self.program_id)
+            }
+        };
+        let (logs, _) = pubsub
+            .logs_subscribe(
+                RpcTransactionLogsFilter::Mentions(vec![mentioned.to_string()]),
+                RpcTransactionLogsConfig {
+                    commitment: Some(self.commitment),
+                },
+            )
+            .await
+            .map_err(|e| SdkError::NetworkError(e.to_string()))?;
+        *delay = RECONNECT_DELAY;
+
+        // Changes made while disconnected would otherwise be missed
+        if let Target::Account(address) = self.target {
+            let rpc_client = RpcClient::new_with_commitment(self.rpc_url.clone(), self.commitment);
+            let response = rpc_client
+                .get_account_with_commitment(&address, self.commitment)
+                .await
+                .map_err(SdkError::ClientError)?;
+            let update =
+                self.account_update(last_slots, address, response.context.slot, response.value);
+            if !self.forward(sender, update) {
+                return Ok(());
+            }
+        }
+
+        let mut notifications = futures::stream::select(accounts, logs.map(Notification::Logs));
+        while let Some(notification) = notifications.next().await {
+            let update = match notification {
+                Notification::Account(address, slot, account) => match account.decode() {
+                    Some(account) => self.account_update(last_slots, address, slot, Some(account)),
+                    None => Some(Err(SdkError::DeserializationError(format!(
+                        "Undecodable account notification for {}",
+                        address
+                    )))),
+                },
+                Notification::Logs(response) => self.events_update(response),
+            };
+            if !self.forward(sender, update) {
+                return Ok(());
+            }
+        }
+        Err(SdkError::NetworkError(format!(
+            "Subscription to {} closed",
+            self.ws_url
+        )))
+    }
+
+    /// The update for `address` holding `account` at `slot`, unless an
+    /// update from that slot or a later one was already delivered
+    fn account_update(
+        &self,
+        last_slots: &mut HashMap<Pubkey, Slot>,
+        address: Pubkey,
+        slot: Slot,
+        account: Option<Account>,
+    ) -> Option<SdkResult<RegistryUpdate<T>>> {
+        if last_slots.get(&address).is_some_and(|last| *last >= slot) {
+            return None;
+        }
+
+        let entry = match account.filter(|account| account.lamports > 0) {
+            Some(account) => match (self.decode)(&account.data) {
+                Ok(entry) => Some(entry),
+                Err(_) if matches!(self.target, Target::Program(_)) => return None,
+                Err(error) => return Some(Err(error)),
+            },
+            None => None,
+        };
+        last_slots.insert(address, slot);
+        Some(Ok(RegistryUpdate::Account {
+            slot,
+            address,
+            entry,
+        }))
+    }
+
+    /// The registry events logged by a successful transaction, if any
+    fn events_update(
+        &self,
+        response: Response<RpcLogsResponse>,
+    ) -> Option<SdkResult<RegistryUpdate<T>>> {
+        if response.value.err.is_some() {
+            return None;
+        }
+        let events: Vec<RegistryEvent> = parse_events(&response.value.logs)
+            .into_iter()
+            .filter(|event| event.program_id == self.program_id)
+            .collect();
+        if events.is_empty() {
+            return None;
+        }
+
+        Some(
+            Signature::from_str(&response.value.signature)
+                .map(|signature| RegistryUpdate::Events {
+                    slot: response.context.slot,
+                    signature,
+                    events,
+                })
+                .map_err(|e| SdkError::DeserializationError(e.to_string())),
+        )
+    }
+
+    /// Send `update` on, returning whether anyone is still listening
+    fn forward(
+        &self,
+        sender: &UnboundedSender<SdkResult<RegistryUpdate<T>>>,
+        update: Option<SdkResult<RegistryUpdate<T>>>,
+    ) -> bool {
+        match update {
+            Some(update) => sender.unbounded_send(update).is_ok(),
+            None => !sender.is_closed(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_client::rpc_response::RpcResponseContext;
+
+    fn subscriber(target: Target) -> Subscriber<AgentEntry> {
+        Subscriber {
+            ws_url: "ws://127.0.0.1:8900".to_string(),
+            rpc_url: "http://127.0.0.1:8899".to_string(),
+            commitment: CommitmentConfig::confirmed(),
+            program_id: Pubkey::new_unique(),
+            target,
+            decode: AgentEntry::try_from_account_data,
+        }
+    }
+
+    #[test]
+    fn test_owner_authority_offset() {
+        assert_eq!(OWNER_AUTHORITY_OFFSET, 11);
+    }
+
+    #[test]
+    fn test_account_updates_are_slot_ordered() {
+        let address = Pubkey::new_unique();
+        let subscriber = subscriber(Target::Account(address));
+        let mut last_slots = HashMap::new();
+        let closed = Account::default();
+
+        let update = subscriber.account_update(&mut last_slots, address, 10, Some(closed.clone()));
+        assert!(matches!(
+            update,
+            Some(Ok(RegistryUpdate::Account {
+                slot: 10,
+                entry: None,
+                ..
+            }))
+        ));
+        // Stale and repeated slots are dropped
+        assert!(subscriber
+            .account_update(&mut last_slots, address, 9, None)
+            .is_none());
+        assert!(subscriber
+            .account_update(&mut last_slots, address, 10, None)
+            .is_none());
+
+        // An undecodable entry is an error for a single-account subscription
+        // and is skipped by a program subscription
+        let garbage = Account {
+            lamports: 1,
+            data: vec![1, 2, 3],
+            ..Account::default()
+        };
+        assert!(matches!(
+            subscriber.account_update(&mut last_slots, address, 11, Some(garbage.clone())),
+            Some(Err(_))
+        ));
+        let program = self::subscriber(Target::Program(Vec::new()));
+        assert!(program
+            .account_update(&mut HashMap::new(), address, 11, Some(garbage))
+            .is_none());
+    }
+
+    #[test]
+    fn test_events_update() {
+        let subscriber = subscriber(Target::Program(Vec::new()));
+        let program_id = subscriber.program_id;
+        let signature = Signature::new_unique();
+        let response = |err, logs: Vec<String>| Response {
+            context: RpcResponseContext {
+                slot: 7,
+                api_version: None,
+            },
+            value: RpcLogsResponse {
+                signature: signature.to_string(),
+                err,
+                logs,
+            },
+        };
+        let logs = vec![
+            format!("Program {} invoke [1]", program_id),
+            "Program log: EVENT: AgentUpdated {\"agent_id\":\"a1\"}".to_string(),
+            format!("Program {} success", program_id),
+        ];
+
+        match subscriber.events_update(response(None, logs.clone())) {
+            Some(Ok(RegistryUpdate::Events {
+                slot,
+                signature: logged,
+                events,
+            })) => {
+                assert_eq!(slot, 7);
+                assert_eq!(logged, signature);
+                assert_eq!(events[0].name, "AgentUpdated");
+            }
+            other => panic!("Expected events, got {:?}", other),
+        }
+
+        let failed = Some(solana_sdk::transaction::TransactionError::AccountNotFound);
+        assert!(subscriber.events_update(response(failed, logs)).is_none());
+        assert!(subscriber
+            .events_update(response(None, Vec::new()))
+            .is_none());
+    }
+}
@@ -0,0 +1,111 @@
+//! Fixed-offset reads of registry entry prefixes
+//!
+//! Registry entries start with a fixed-size prefix: the [`EntryHeader`]
+//! followed by the registry's token and metrics fields. Its offsets never
+//! move, so it can be read (or fetched with an RPC data slice) without
+//! decoding the strings and vectors that follow.
+
+use crate::errors::{SdkError, SdkResult};
+use bytemuck::{Pod, Zeroable};
+use solana_sdk::pubkey::Pubkey;
+
+/// Layout version the programs store in `registry_version`. Entries still in
+/// version 1 keep their economics elsewhere and must be migrated with
+/// `MigrateAgentEntry` or `MigrateMcpServerEntry` before they can be read.
+pub const ENTRY_LAYOUT_VERSION: u8 = 2;
+
+/// Fields every registry entry starts with (matches the on-chain layout)
+#[repr(C, packed)]
+#[derive(Clone, Copy, Debug, PartialEq, Pod, Zeroable)]
+pub struct EntryHeader {
+    pub bump: u8,
+    pub registry_version: u8,
+    pub state_version: u64,
+    /// Reentrancy guard (0 or 1)
+    pub operation_in_progress: u8,
+    pub owner_authority: Pubkey,
+    pub status: u8,
+}
+
+/// Length of [`EntryHeader`] in bytes
+pub const ENTRY_HEADER_LEN: usize = std::mem::size_of::<EntryHeader>();
+
+impl EntryHeader {
+    /// Reject entries stored in any layout other than [`ENTRY_LAYOUT_VERSION`]
+    pub fn check_layout_version(&self) -> SdkResult<()> {
+        if self.registry_version != ENTRY_LAYOUT_VERSION {
+            return Err(SdkError::UnsupportedEntryVersion);
+        }
+        Ok(())
+    }
+}
+
+/// Reject entry account data stored in any layout other than
+/// [`ENTRY_LAYOUT_VERSION`], before decoding the rest of the entry
+pub fn check_entry_layout_version(data: &[u8]) -> SdkResult<()> {
+    read_prefix::<EntryHeader>(data)?.check_layout_version()
+}
+
+/// Copy the `T` stored at the start of `data`
+pub fn read_prefix<T: Pod>(data: &[u8]) -> SdkResult<T> {
+    data.get(..std::mem::size_of::<T>())
+        .map(bytemuck::pod_read_unaligned)
+        .ok_or(SdkError::InvalidAccountData)
+}
+
+/// Implement borsh for a packed `Pod` type as its raw bytes, the way the
+/// programs embed prefix blocks in their borsh entries
+macro_rules! impl_borsh_for_pod {
+    ($ty:ty) => {
+        impl ::borsh::BorshSerialize for $ty {
+            fn serialize<W: ::std::io::Write>(&self, writer: &mut W) -> ::std::io::Result<()> {
+                writer.write_all(::bytemuck::bytes_of(self))
+            }
+        }
+
+        impl ::borsh::BorshDeserialize for $ty {
+            fn deserialize_reader<R: ::std::io::Read>(reader: &mut R) -> ::std::io::Result<Self> {
+                let mut value = <$ty as ::bytemuck::Zeroable>::zeroed();
+                reader.read_exact(::bytemuck::bytes_of_mut(&mut value))?;
+                Ok(value)
+            }
+        }
+    };
+}
+pub(crate) use impl_borsh_for_pod;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use borsh::BorshSerialize;
+
+    #[test]
+    fn test_read_header() {
+        let owner = Pubkey::new_unique();
+        let data = (
+            255u8,
+            ENTRY_LAYOUT_VERSION,
+            9u64,
+            false,
+            owner,
+            1u8,
+            "agent".to_string(),
+        )
+            .try_to_vec()
+            .unwrap();
+
+        let header: EntryHeader = read_prefix(&data).unwrap();
+        assert_eq!({ header.state_version }, 9);
+        assert_eq!(header.owner_authority, owner);
+        assert_eq!(header.status, 1);
+        assert!(read_prefix::<EntryHeader>(&data[..ENTRY_HEADER_LEN - 1]).is_err());
+        assert!(check_entry_layout_version(&data).is_ok());
+
+        let mut data = data;
+        data[1] = 1;
+        assert!(matches!(
+            check_entry_layout_version(&data),
+            Err(SdkError::UnsupportedEntryVersion)
+        ));
+    }
+}
@@ -351,11 +351,11 @@ fn test_25_agent_registry_operations() {
     // Test that all registry operations can be created
     let agent_args = AgentBuilder::new(agent_id, "Test Agent").build().unwrap();
 
-    let register_ix = AgentRegistry::register(&program_id, &owner, agent_args);
+    let register_ix = AgentRegistry::register(&program_id, &owner, &owner, agent_args);
     assert!(register_ix.is_ok());
 
     let patch = AgentPatch::default();
-    let update_ix = AgentRegistry::update(&program_id, &owner, agent_id, patch);
+    let update_ix = AgentRegistry::update(&program_id, &owner, &owner, agent_id, patch);
     assert!(update_ix.is_ok());
 
     let status_ix = AgentRegistry::update_status(&program_id, &owner, agent_id, 1);
@@ -416,10 +416,10 @@ fn test_26_complex_agent_workflow() {
     update_patch.tags = Some(vec!["updated".to_string(), "advanced".to_string()]);
 
     // 3. Validate all instructions can be created
-    let register_ix = AgentRegistry::register(&program_id, &owner, initial_agent);
+    let register_ix = AgentRegistry::register(&program_id, &owner, &owner, initial_agent);
     assert!(register_ix.is_ok());
 
-    let update_ix = AgentRegistry::update(&program_id, &owner, agent_id, update_patch);
+    let update_ix = AgentRegistry::update(&program_id, &owner, &owner, agent_id, update_patch);
     assert!(update_ix.is_ok());
 
     let activate_ix =
@@ -1,34 +1,17 @@
-//! Integration tests with mock RPC responses
+//! Integration tests
 //!
-//! These tests demonstrate edge case handling with stubbed RPC responses
-//! to validate SDK behavior without requiring a live Solana cluster.
-
-use aeamcp-sdk::{
-    deserialize_account_data, AgentBuilder, SdkError, SdkResult, SolanaAiRegistriesClient,
-};
-
-/// Mock RPC client wrapper for testing
-struct MockRpcClient {
-    client: SolanaAiRegistriesClient,
-}
-
-impl MockRpcClient {
-    fn new() -> Self {
-        // Use a mock RPC URL for testing
-        let client = SolanaAiRegistriesClient::new("http://localhost:8899");
-        Self { client }
-    }
-
-    fn get_client(&self) -> &SolanaAiRegistriesClient {
-        &self.client
-    }
-}
+//! Validation and decoding edge cases run without any cluster. With the
+//! `in-process` feature, the flows at the bottom drive both registry
+//! programs end to end through `InProcessRegistry`:
+//!
+//! ```text
+//! cargo test -p aeamcp-sdk --features in-process --test integration
+//! ```
 
-#[tokio::test]
-async fn test_agent_registration_with_invalid_data() {
-    let mock_client = MockRpcClient::new();
-    let _client = mock_client.get_client();
+use aeamcp_sdk::{deserialize_account_data, AgentBuilder, SdkError, SdkResult};
 
+#[test]
+fn test_agent_registration_with_invalid_data() {
     // Test with invalid agent data that would fail validation
     let result = AgentBuilder::new("", "Test Agent") // Empty ID should fail
         .build();
@@ -106,6 +89,25 @@ fn test_error_code_mapping() {
         }
         other => panic!("Expected InvalidAgentIdLength, got {:?}", other),
     }
+
+    // Codes follow the on-chain RegistryError ordinals
+    assert!(matches!(
+        SdkError::from_program_error_code(46),
+        SdkError::AccountAlreadyExists
+    ));
+    assert!(SdkError::from_program_error_code(52).is_state_version_mismatch());
+    assert!(matches!(
+        SdkError::from_program_error_code(88),
+        SdkError::InvalidFreeTier
+    ));
+    assert!(matches!(
+        SdkError::from_program_error_code(89),
+        SdkError::AcceptedMintLocked
+    ));
+    assert!(matches!(
+        SdkError::from_program_error_code(90),
+        SdkError::UnsupportedEntryVersion
+    ));
 }
 
 #[test]
@@ -188,9 +190,7 @@ struct MockStruct {
 #[cfg(any(feature = "stream", feature = "pyg", feature = "prepay"))]
 #[test]
 fn test_priority_multiplier_validation() {
-    use solana_ai_registries::payments::common::{
-        MAX_PRIORITY_MULTIPLIER, MIN_PRIORITY_MULTIPLIER,
-    };
+    use aeamcp_sdk::payments::common::{MAX_PRIORITY_MULTIPLIER, MIN_PRIORITY_MULTIPLIER};
 
     // Test valid range
     assert!(MIN_PRIORITY_MULTIPLIER <= MAX_PRIORITY_MULTIPLIER);
@@ -201,7 +201,7 @@ fn test_priority_multiplier_validation() {
 #[cfg(feature = "pyg")]
 #[test]
 fn test_pyg_cost_estimation_edge_cases() {
-    use solana_ai_registries::payments::pyg::estimate_pyg_cost;
+    use aeamcp_sdk::payments::pyg::estimate_pyg_cost;
 
     // Test with minimum values
     let result = estimate_pyg_cost(1, None, None, None);
@@ -223,8 +223,8 @@ fn test_pyg_cost_estimation_edge_cases() {
 fn test_constant_documentation_exists() {
     // This test ensures our constants are properly documented
     // by checking they compile and have expected values
-    use solana_ai_registries::agent::*;
-    use solana_ai_registries::mcp::*;
+    use aeamcp_sdk::agent::*;
+    use aeamcp_sdk::mcp::*;
 
     // Agent constants
     assert_eq!(MAX_AGENT_ID_LEN, 64);
@@ -241,10 +241,1328 @@ fn test_constant_documentation_exists() {
 fn test_payment_constant_documentation_exists() {
     // This test ensures our payment constants are properly documented
     // by checking they compile and have expected values
-    use solana_ai_registries::payments::common::*;
+    use aeamcp_sdk::payments::common::*;
 
     // Payment constants
     assert_eq!(A2AMPL_DECIMALS, 9);
     assert_eq!(A2AMPL_BASE_UNIT, 1_000_000_000);
     assert!(AGENT_REGISTRATION_FEE > MCP_REGISTRATION_FEE);
 }
+
+#[tokio::test]
+async fn test_subscription_reports_connection_errors() {
+    use aeamcp_sdk::{ClientConfig, Cluster, Pubkey, SolanaAiRegistriesClient};
+    use futures::StreamExt;
+
+    // Nothing listens on the derived PubSub port
+    let config = ClientConfig::builder(Cluster::Localnet)
+        .rpc_url("http://127.0.0.1:1")
+        .build()
+        .unwrap();
+    assert_eq!(config.websocket_url(), "ws://127.0.0.1:2");
+
+    let client = SolanaAiRegistriesClient::with_config(config);
+    let mut updates = client
+        .subscriptions()
+        .subscribe_agent(&Pubkey::new_unique(), "watched")
+        .unwrap();
+    assert!(matches!(
+        updates.next().await,
+        Some(Err(SdkError::NetworkError(_)))
+    ));
+}
+
+#[cfg(feature = "in-process")]
+mod in_process {
+    use aeamcp_sdk::lookup_table::registry_lookup_addresses;
+    use aeamcp_sdk::mcp::UsageType;
+    use aeamcp_sdk::plan::{missing_signers, partially_sign};
+    use aeamcp_sdk::{
+        AgentBuilder, AgentPatch, AgentSkill, AgentStatus, CacheConfig, ClientConfig,
+        ComputeUnitPolicy, DiscountTier, FreeTier, InProcessRegistry, ItemPriceInput,
+        McpServerBuilder, McpServerStatus, McpToolDefinition, McpUsageFees, PriorityFeePolicy,
+        RegistryCache, RegistryConfigParams, SdkError, SolanaAiRegistriesClient, SplitRecipient,
+        SubscriptionQuota, TransactionPlan,
+    };
+    use solana_sdk::{
+        native_token::LAMPORTS_PER_SOL, program_pack::Pack, pubkey::Pubkey, signature::Signer,
+        signer::keypair::Keypair,
+    };
+    use std::sync::Arc;
+
+    type Client = SolanaAiRegistriesClient<InProcessRegistry>;
+
+    const LOCK_PERIOD: i64 = 3_600;
+
+    fn params(mint: Pubkey, treasury: Pubkey) -> RegistryConfigParams {
+        RegistryConfigParams {
+            accepted_mint: mint,
+            treasury,
+            registration_fee: 0,
+            tier_thresholds: [1_000, 10_000, 100_000, 1_000_000],
+            tier_lock_periods: [LOCK_PERIOD; 4],
+            min_lock_period: LOCK_PERIOD,
+            max_lock_period: 365 * 86_400,
+            min_service_fee: 1,
+            min_tool_fee: 1,
+            min_resource_fee: 1,
+            min_prompt_fee: 1,
+            max_bulk_discount: 50,
+            min_priority_multiplier: 100,
+            max_priority_multiplier: 300,
+            protocol_fee_bps: 1_000,
+            fee_increase_notice: 3_600,
+        }
+    }
+
+    /// Both registries bootstrapped on a fresh bank, with their mint
+    async fn setup() -> (Client, Pubkey) {
+        let registry = Arc::new(InProcessRegistry::start().await);
+        let client = SolanaAiRegistriesClient::from_rpc_client(registry.clone());
+        let mint = registry.create_mint(9).await.unwrap();
+        let params = params(mint, registry.payer().pubkey());
+
+        client
+            .bootstrap_agent_registry(registry.payer(), params)
+            .await
+            .unwrap();
+        client
+            .bootstrap_mcp_registry(registry.payer(), params)
+            .await
+            .unwrap();
+        (client, mint)
+    }
+
+    /// A user with SOL for fees and `tokens` of the registry mint
+    async fn funded_user(client: &Client, mint: &Pubkey, tokens: u64) -> (Keypair, Pubkey) {
+        let registry = client.rpc_client();
+        let user = Keypair::new();
+        registry
+            .airdrop(&user.pubkey(), LAMPORTS_PER_SOL)
+            .await
+            .unwrap();
+        let token_account = registry
+            .create_token_account(&user.pubkey(), mint)
+            .await
+            .unwrap();
+        registry
+            .mint_to(mint, &token_account, tokens)
+            .await
+            .unwrap();
+        (user, token_account)
+    }
+
+    async fn token_balance(client: &Client, token_account: &Pubkey) -> u64 {
+        let account = client
+            .rpc_client()
+            .get_account(token_account)
+            .await
+            .unwrap()
+            .unwrap();
+        spl_token::state::Account::unpack(&account.data)
+            .unwrap()
+            .amount
+    }
+
+    use aeamcp_sdk::{RegistryRpc, SdkResult, SimulationResult};
+    use solana_client::rpc_filter::RpcFilterType;
+    use solana_sdk::{
+        account::Account, clock::Slot, commitment_config::CommitmentConfig, hash::Hash,
+        signature::Signature, transaction::VersionedTransaction,
+    };
+    use tokio::sync::Notify;
+
+    #[tokio::test]
+    async fn test_bootstrap_is_idempotent() {
+        let (client, mint) = setup().await;
+        let payer = client.rpc_client().payer();
+
+        let config = client.get_agent_registry_config().await.unwrap().unwrap();
+        assert_eq!(config.params.accepted_mint, mint);
+        assert_eq!(config.admin_authority, payer.pubkey());
+
+        let rerun = client
+            .bootstrap_mcp_registry(payer, params(mint, payer.pubkey()))
+            .await
+            .unwrap();
+        assert!(rerun.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_agent_registration_flow() {
+        let (client, mint) = setup().await;
+        let (owner, _) = funded_user(&client, &mint, 0).await;
+
+        assert!(client
+            .get_agent(&owner.pubkey(), "flow-agent")
+            .await
+            .unwrap()
+            .is_none());
+
+        let args = AgentBuilder::new("flow-agent", "Flow Agent")
+            .description("Registered in process")
+            .build()
+            .unwrap();
+        client.register_agent(&owner, args).await.unwrap();
+
+        let entry = client
+            .get_agent(&owner.pubkey(), "flow-agent")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(entry.name, "Flow Agent");
+        assert_eq!(entry.owner_authority, owner.pubkey());
+
+        client
+            .update_agent_status(&owner, "flow-agent", AgentStatus::Inactive as u8)
+            .await
+            .unwrap();
+        let entry = client
+            .get_agent(&owner.pubkey(), "flow-agent")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(entry.status, AgentStatus::Inactive);
+
+        // Registering the same ID twice fails on-chain
+        let again = AgentBuilder::new("flow-agent", "Flow Agent")
+            .build()
+            .unwrap();
+        assert!(client.register_agent(&owner, again).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_agent_staking_flow() {
+        let (client, mint) = setup().await;
+        let (owner, token_account) = funded_user(&client, &mint, 50_000).await;
+        let args = AgentBuilder::new("staker", "Staker").build().unwrap();
+        client.register_agent(&owner, args).await.unwrap();
+
+        client
+            .stake_agent_tokens(&owner, &token_account, "staker", 20_000, LOCK_PERIOD)
+            .await
+            .unwrap();
+        let prefix = client
+            .get_agent_prefix(&owner.pubkey(), "staker")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!({ prefix.economics.staked_amount }, 20_000);
+        assert_eq!(token_balance(&client, &token_account).await, 30_000);
+
+        // The stake stays locked until the lock period has passed
+        assert!(client
+            .unstake_agent_tokens(&owner, &token_account, "staker", 20_000)
+            .await
+            .is_err());
+
+        client
+            .rpc_client()
+            .advance_clock(LOCK_PERIOD + 1)
+            .await
+            .unwrap();
+        client
+            .unstake_agent_tokens(&owner, &token_account, "staker", 20_000)
+            .await
+            .unwrap();
+        assert_eq!(token_balance(&client, &token_account).await, 50_000);
+    }
+
+    #[tokio::test]
+    async fn test_lookup_table_flow() {
+        let (client, mint) = setup().await;
+        let registry = client.rpc_client().clone();
+        let table = client
+            .create_registry_lookup_table(registry.payer())
+            .await
+            .unwrap();
+        registry.advance_slot().await.unwrap();
+
+        let held = client.get_lookup_table(&table).await.unwrap().unwrap();
+        assert_eq!(held.addresses, registry_lookup_addresses(client.config()));
+        assert!(client
+            .extend_registry_lookup_table(registry.payer(), &table)
+            .await
+            .unwrap()
+            .is_none());
+
+        let config = ClientConfig {
+            lookup_table: Some(table),
+            ..client.config().clone()
+        };
+        let client = SolanaAiRegistriesClient::from_rpc_client_with_config(registry, config);
+        let (owner, token_account) = funded_user(&client, &mint, 50_000).await;
+        let args = AgentBuilder::new("looked-up", "Looked Up").build().unwrap();
+        client.register_agent(&owner, args).await.unwrap();
+
+        let plan = client
+            .plan_stake_agent_tokens(
+                &owner.pubkey(),
+                &token_account,
+                "looked-up",
+                20_000,
+                LOCK_PERIOD,
+            )
+            .unwrap();
+        let lookup_tables = client.lookup_tables().await.unwrap();
+        let transaction = client
+            .unsigned_versioned_transaction(&plan, &lookup_tables)
+            .await
+            .unwrap();
+        let lookups = transaction.message.address_table_lookups().unwrap();
+        assert_eq!(lookups[0].account_key, table);
+
+        client.send_plan(&plan, &[&owner]).await.unwrap();
+        assert_eq!(token_balance(&client, &token_account).await, 30_000);
+    }
+
+    #[tokio::test]
+    async fn test_lookup_table_is_kept_until_missing_accounts() {
+        let (client, mint) = setup().await;
+        let registry = client.rpc_client().clone();
+        let payer = registry.payer();
+
+        // A table holding only some of the registry's static accounts
+        let wanted = registry_lookup_addresses(client.config());
+        let recent_slot = registry.get_slot().await.unwrap();
+        let (create, table) = solana_sdk::address_lookup_table::instruction::create_lookup_table(
+            payer.pubkey(),
+            payer.pubkey(),
+            recent_slot,
+        );
+        let extend = solana_sdk::address_lookup_table::instruction::extend_lookup_table(
+            table,
+            payer.pubkey(),
+            Some(payer.pubkey()),
+            wanted[..4].to_vec(),
+        );
+        client
+            .send_plan(
+                &TransactionPlan::new(vec![create, extend], payer.pubkey()),
+                &[payer],
+            )
+            .await
+            .unwrap();
+        registry.advance_slot().await.unwrap();
+
+        let config = ClientConfig {
+            lookup_table: Some(table),
+            ..client.config().clone()
+        };
+        let looked_up =
+            SolanaAiRegistriesClient::from_rpc_client_with_config(registry.clone(), config);
+        assert_eq!(
+            looked_up.lookup_tables().await.unwrap()[0].addresses.len(),
+            4
+        );
+
+        // Extended by another client, the kept copy is stale
+        client
+            .extend_registry_lookup_table(payer, &table)
+            .await
+            .unwrap()
+            .unwrap();
+        registry.advance_slot().await.unwrap();
+        assert_eq!(
+            looked_up.lookup_tables().await.unwrap()[0].addresses.len(),
+            4
+        );
+
+        // Until a plan needs an account it lacks
+        let (owner, token_account) = funded_user(&looked_up, &mint, 50_000).await;
+        let args = AgentBuilder::new("kept-table", "Kept Table")
+            .build()
+            .unwrap();
+        looked_up.register_agent(&owner, args).await.unwrap();
+        let plan = looked_up
+            .plan_stake_agent_tokens(
+                &owner.pubkey(),
+                &token_account,
+                "kept-table",
+                20_000,
+                LOCK_PERIOD,
+            )
+            .unwrap();
+        looked_up.send_plan(&plan, &[&owner]).await.unwrap();
+        assert_eq!(
+            looked_up.lookup_tables().await.unwrap()[0].addresses,
+            wanted
+        );
+    }
+
+    #[tokio::test]
+    async fn test_prefunded_overflow_accounts_can_be_created() {
+        let (client, mint) = setup().await;
+        let registry = client.rpc_client().clone();
+        let (owner, _) = funded_user(&client, &mint, 0).await;
+
+        let args = AgentBuilder::new("prefunded-agent", "Prefunded Agent")
+            .build()
+            .unwrap();
+        client.register_agent(&owner, args).await.unwrap();
+        let args = McpServerBuilder::new(
+            "prefunded-server",
+            "Prefunded Server",
+            "https://example.com",
+        )
+        .supports_tools(true)
+        .build()
+        .unwrap();
+        client.register_mcp_server(&owner, args).await.unwrap();
+
+        // Lamports sent to the next overflow PDAs must not block their creation
+        let agent_pda = aeamcp_sdk::agent::derive_agent_pda(
+            client.agent_registry_program_id(),
+            &owner.pubkey(),
+            "prefunded-agent",
+        )
+        .unwrap();
+        let server_pda = aeamcp_sdk::mcp::derive_mcp_server_pda(
+            client.mcp_server_registry_program_id(),
+            &owner.pubkey(),
+            "prefunded-server",
+        )
+        .unwrap();
+        for address in [
+            aeamcp_sdk::agent::derive_skill_account_pda(
+                client.agent_registry_program_id(),
+                &agent_pda,
+                0,
+            ),
+            aeamcp_sdk::mcp::derive_tool_account_pda(
+                client.mcp_server_registry_program_id(),
+                &server_pda,
+                0,
+            ),
+        ] {
+            registry.airdrop(&address, 1_000_000).await.unwrap();
+        }
+
+        let skill = aeamcp_sdk::agent::create_initialize_skill_account_instruction(
+            client.agent_registry_program_id(),
+            &owner.pubkey(),
+            &owner.pubkey(),
+            "prefunded-agent",
+            0,
+            AgentSkill::new("search".to_string(), "Search".to_string(), vec![]).unwrap(),
+        )
+        .unwrap();
+        let tool = aeamcp_sdk::mcp::create_initialize_tool_account_instruction(
+            client.mcp_server_registry_program_id(),
+            &owner.pubkey(),
+            &owner.pubkey(),
+            "prefunded-server",
+            0,
+            McpToolDefinition::new("lookup".to_string(), vec![]).unwrap(),
+        )
+        .unwrap();
+        client
+            .send_plan(
+                &TransactionPlan::new(vec![skill, tool], owner.pubkey()),
+                &[&owner],
+            )
+            .await
+            .unwrap();
+
+        let skills = client
+            .get_agent_skill_accounts(&owner.pubkey(), "prefunded-agent")
+            .await
+            .unwrap();
+        assert_eq!(skills.len(), 1);
+        let tools = client
+            .get_mcp_server_tool_accounts(&owner.pubkey(), "prefunded-server")
+            .await
+            .unwrap();
+        assert_eq!(tools.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_prefunded_server_accounts_can_be_created() {
+        let (client, mint) = setup().await;
+        let registry = client.rpc_client().clone();
+        let (owner, _) = funded_user(&client, &mint, 0).await;
+        let (user, user_tokens) = funded_user(&client, &mint, 10_000).await;
+
+        let args = McpServerBuilder::new("prefunded-fees", "Prefunded Fees", "https://example.com")
+            .supports_tools(true)
+            .build()
+            .unwrap();
+        client.register_mcp_server(&owner, args).await.unwrap();
+        client
+            .update_mcp_server_status(&owner, "prefunded-fees", McpServerStatus::Active as u8)
+            .await
+            .unwrap();
+
+        // The fee vault and the user's per-server PDAs all have predictable
+        // addresses; lamports sent to them must not block the server
+        let program_id = client.mcp_server_registry_program_id();
+        let server_pda =
+            aeamcp_sdk::mcp::derive_mcp_server_pda(program_id, &owner.pubkey(), "prefunded-fees")
+                .unwrap();
+        for address in [
+            aeamcp_sdk::mcp::derive_server_fee_vault_pda(program_id, &server_pda),
+            aeamcp_sdk::mcp::derive_subscription_pda(program_id, &server_pda, &user.pubkey()),
+            aeamcp_sdk::mcp::derive_usage_record_pda(program_id, &server_pda, &user.pubkey()),
+        ] {
+            registry.airdrop(&address, 1_000_000).await.unwrap();
+        }
+
+        client
+            .initialize_mcp_server_fee_vault(&owner, "prefunded-fees")
+            .await
+            .unwrap();
+        client
+            .configure_mcp_usage_fees(
+                &owner,
+                "prefunded-fees",
+                McpUsageFees {
+                    tool_base_fee: 10,
+                    resource_base_fee: 10,
+                    prompt_base_fee: 10,
+                    bulk_discount_threshold: 1_000,
+                    bulk_discount_percentage: 0,
+                },
+                FreeTier::default(),
+            )
+            .await
+            .unwrap();
+        let included = SubscriptionQuota {
+            tool_calls: 1,
+            resource_calls: 0,
+            prompt_calls: 0,
+        };
+        client
+            .set_mcp_subscription_plan(&owner, "prefunded-fees", 1, 100, 86_400, included, true)
+            .await
+            .unwrap();
+        client
+            .subscribe_mcp_server(&user, &user_tokens, &owner.pubkey(), "prefunded-fees", 1)
+            .await
+            .unwrap();
+        client
+            .record_mcp_usage(
+                &user,
+                &user_tokens,
+                &owner.pubkey(),
+                "prefunded-fees",
+                UsageType::Tool,
+                None,
+                2,
+            )
+            .await
+            .unwrap();
+
+        // One call came from the subscription, the other paid the base fee
+        assert_eq!(token_balance(&client, &user_tokens).await, 9_890);
+    }
+
+    #[tokio::test]
+    async fn test_externally_signed_registration() {
+        let (client, mint) = setup().await;
+        let (owner, _) = funded_user(&client, &mint, 0).await;
+        let fee_payer = client.rpc_client().payer();
+
+        let args = AgentBuilder::new("multisig-agent", "Multisig Agent")
+            .build()
+            .unwrap();
+        let plan = client
+            .plan_register_agent(&owner.pubkey(), &owner.pubkey(), args)
+            .unwrap()
+            .remove(0)
+            .with_fee_payer(fee_payer.pubkey());
+        assert_eq!(
+            plan.required_signers(),
+            vec![fee_payer.pubkey(), owner.pubkey()]
+        );
+
+        let mut transaction = client
+            .unsigned_versioned_transaction(&plan, &[])
+            .await
+            .unwrap();
+        partially_sign(&mut transaction, &[&owner]).unwrap();
+        assert_eq!(missing_signers(&transaction), vec![fee_payer.pubkey()]);
+        assert!(matches!(
+            client.submit_transaction(transaction.clone()).await,
+            Err(SdkError::MissingRequiredSignature)
+        ));
+
+        partially_sign(&mut transaction, &[fee_payer]).unwrap();
+        let balance_before = client.get_balance(&owner.pubkey()).await.unwrap();
+        client.submit_transaction(transaction).await.unwrap();
+
+        let entry = client
+            .get_agent(&owner.pubkey(), "multisig-agent")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(entry.owner_authority, owner.pubkey());
+        // The owner paid the entry's rent but not the transaction fee
+        let entry_pda = aeamcp_sdk::agent::derive_agent_pda(
+            client.agent_registry_program_id(),
+            &owner.pubkey(),
+            "multisig-agent",
+        )
+        .unwrap();
+        let rent = client.get_balance(&entry_pda).await.unwrap();
+        assert_eq!(
+            client.get_balance(&owner.pubkey()).await.unwrap(),
+            balance_before - rent
+        );
+    }
+
+    #[tokio::test]
+    async fn test_accepted_mint_locked_once_vaults_exist() {
+        let (client, mint) = setup().await;
+        let admin = client.rpc_client().payer();
+        let other_mint = client.rpc_client().create_mint(9).await.unwrap();
+
+        let err = client
+            .update_agent_registry_config(admin, |params| params.accepted_mint = other_mint)
+            .await
+            .unwrap_err();
+        assert!(matches!(err.root_cause(), SdkError::AcceptedMintLocked));
+        let err = client
+            .update_mcp_registry_config(admin, |params| params.accepted_mint = other_mint)
+            .await
+            .unwrap_err();
+        assert!(matches!(err.root_cause(), SdkError::AcceptedMintLocked));
+
+        let config = client.get_agent_registry_config().await.unwrap().unwrap();
+        assert_eq!(config.params.accepted_mint, mint);
+    }
+
+    #[tokio::test]
+    async fn test_sponsored_registration() {
+        let (client, mint) = setup().await;
+        let (owner, owner_tokens) = funded_user(&client, &mint, 5_000).await;
+        let sponsor = client.rpc_client().payer();
+        client
+            .update_agent_registry_config(sponsor, |params| params.registration_fee = 1_000)
+            .await
+            .unwrap();
+
+        let balance_before = client.get_balance(&owner.pubkey()).await.unwrap();
+        let args = AgentBuilder::new("sponsored", "Sponsored Agent")
+            .build()
+            .unwrap();
+        client
+            .register_agent_with_token(&owner, sponsor, &owner_tokens, args)
+            .await
+            .unwrap();
+        client
+            .update_agent_sponsored(
+                &owner,
+                sponsor,
+                "sponsored",
+                AgentPatch {
+                    description: Some("Paid for by the platform".to_string()),
+                    ..Default::default()
+                },
+            )
+            .await
+            .unwrap();
+
+        let entry = client
+            .get_agent(&owner.pubkey(), "sponsored")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(entry.owner_authority, owner.pubkey());
+        assert_eq!(entry.description, "Paid for by the platform");
+        // The owner paid the registration fee in tokens and nothing in SOL
+        assert_eq!(token_balance(&client, &owner_tokens).await, 4_000);
+        assert_eq!(
+            client.get_balance(&owner.pubkey()).await.unwrap(),
+            balance_before
+        );
+    }
+
+    #[tokio::test]
+    async fn test_send_policies_add_compute_budget() {
+        let (client, mint) = setup().await;
+        let (owner, _) = funded_user(&client, &mint, 0).await;
+        let config = ClientConfig {
+            priority_fee: PriorityFeePolicy::Fixed {
+                micro_lamports: 1_000_000,
+            },
+            compute_units: ComputeUnitPolicy::Simulate { margin_percent: 10 },
+            ..client.config().clone()
+        };
+        let client = SolanaAiRegistriesClient::from_rpc_client_with_config(
+            client.rpc_client().clone(),
+            config,
+        );
+
+        let balance_before = client.get_balance(&owner.pubkey()).await.unwrap();
+        let args = AgentBuilder::new("priority", "Priority Agent")
+            .build()
+            .unwrap();
+        client.register_agent(&owner, args).await.unwrap();
+
+        let entry_pda = aeamcp_sdk::agent::derive_agent_pda(
+            client.agent_registry_program_id(),
+            &owner.pubkey(),
+            "priority",
+        )
+        .unwrap();
+        let rent = client.get_balance(&entry_pda).await.unwrap();
+        let fee = balance_before - rent - client.get_balance(&owner.pubkey()).await.unwrap();
+        // One signature plus a priority fee of one lamport per compute unit
+        // requested, which is well under the default limit
+        assert!(fee > 5_000);
+        assert!(fee < 5_000 + 200_000);
+    }
+
+    #[tokio::test]
+    async fn test_registry_errors_are_decoded() {
+        let (client, mint) = setup().await;
+        let (owner, _) = funded_user(&client, &mint, 0).await;
+        let args = AgentBuilder::new("decoded", "Decoded Agent")
+            .build()
+            .unwrap();
+
+        let report = client
+            .simulate_register_agent(&owner.pubkey(), &owner.pubkey(), args.clone())
+            .await
+            .unwrap();
+        assert!(report.is_success());
+        assert!(report.units_consumed.unwrap() > 0);
+        assert!(!report.logs.is_empty());
+        let event = report
+            .events
+            .iter()
+            .find(|event| event.name == "AgentRegistered")
+            .unwrap();
+        assert_eq!(&event.program_id, client.agent_registry_program_id());
+        // Simulation leaves the bank untouched
+        assert!(client
+            .get_agent(&owner.pubkey(), "decoded")
+            .await
+            .unwrap()
+            .is_none());
+
+        client.register_agent(&owner, args.clone()).await.unwrap();
+
+        let report = client
+            .simulate_register_agent(&owner.pubkey(), &owner.pubkey(), args.clone())
+            .await
+            .unwrap();
+        assert!(matches!(
+            report.error.as_ref().unwrap().root_cause(),
+            SdkError::AccountAlreadyExists
+        ));
+
+        let error = client.register_agent(&owner, args).await.unwrap_err();
+        assert!(matches!(error.root_cause(), SdkError::AccountAlreadyExists));
+        let (index, logs) = error.instruction_failure().unwrap();
+        assert_eq!(index, 0);
+        assert!(logs.iter().any(|line| line.contains("failed")));
+    }
+
+    #[tokio::test]
+    async fn test_cached_reads() {
+        let (client, mint) = setup().await;
+        let cache = Arc::new(RegistryCache::new(CacheConfig::default()).unwrap());
+        let client = client.with_cache(cache.clone());
+        let (owner, _) = funded_user(&client, &mint, 0).await;
+        for id in ["cached", "listed"] {
+            let args = AgentBuilder::new(id, "Cached Agent").build().unwrap();
+            client.register_agent(&owner, args).await.unwrap();
+        }
+
+        client.get_agent(&owner.pubkey(), "cached").await.unwrap();
+        let entry = client
+            .get_agent(&owner.pubkey(), "cached")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(entry.status, AgentStatus::Pending);
+        assert_eq!((cache.stats().hits, cache.stats().misses), (1, 1));
+
+        // Sending a transaction drops the cached copies of what it writes
+        client
+            .update_agent_status(&owner, "cached", AgentStatus::Inactive as u8)
+            .await
+            .unwrap();
+        let entry = client
+            .get_agent(&owner.pubkey(), "cached")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(entry.status, AgentStatus::Inactive);
+        assert_eq!(cache.stats().misses, 2);
+        assert!(cache.stats().invalidations >= 1);
+
+        // Listing warms the cache for later lookups
+        let agents = client.list_agents().await.unwrap();
+        let mut ids: Vec<&str> = agents
+            .iter()
+            .map(|(_, entry)| entry.agent_id.as_str())
+            .collect();
+        ids.sort_unstable();
+        assert_eq!(ids, ["cached", "listed"]);
+        client.get_agent(&owner.pubkey(), "listed").await.unwrap();
+        let stats = cache.stats();
+        assert_eq!((stats.hits, stats.misses), (2, 2));
+        assert!(stats.hit_rate() > 0.0);
+    }
+
+    /// An in-process registry whose account reads wait after fetching until
+    /// released, like a read answered before a write lands but delivered
+    /// after it
+    struct SlowReads {
+        registry: Arc<InProcessRegistry>,
+        fetched: Notify,
+        release: Notify,
+    }
+
+    #[async_trait::async_trait]
+    impl RegistryRpc for SlowReads {
+        fn commitment(&self) -> CommitmentConfig {
+            self.registry.commitment()
+        }
+
+        async fn get_account(&self, address: &Pubkey) -> SdkResult<Option<Account>> {
+            self.registry.get_account(address).await
+        }
+
+        async fn get_slot(&self) -> SdkResult<Slot> {
+            self.registry.get_slot().await
+        }
+
+        async fn get_account_with_slot(
+            &self,
+            address: &Pubkey,
+        ) -> SdkResult<(Slot, Option<Account>)> {
+            let read = self.registry.get_account_with_slot(address).await;
+            self.fetched.notify_one();
+            self.release.notified().await;
+            read
+        }
+
+        async fn get_multiple_accounts(
+            &self,
+            addresses: &[Pubkey],
+        ) -> SdkResult<Vec<Option<Account>>> {
+            self.registry.get_multiple_accounts(addresses).await
+        }
+
+        async fn get_program_accounts(
+            &self,
+            program_id: &Pubkey,
+            filters: Vec<RpcFilterType>,
+        ) -> SdkResult<Vec<(Pubkey, Account)>> {
+            self.registry
+                .get_program_accounts(program_id, filters)
+                .await
+        }
+
+        async fn latest_blockhash(&self) -> SdkResult<Hash> {
+            self.registry.latest_blockhash().await
+        }
+
+        async fn send_transaction(
+            &self,
+            transaction: &VersionedTransaction,
+        ) -> SdkResult<Signature> {
+            self.registry.send_transaction(transaction).await
+        }
+
+        async fn simulate_transaction(
+            &self,
+            transaction: &VersionedTransaction,
+        ) -> SdkResult<SimulationResult> {
+            self.registry.simulate_transaction(transaction).await
+        }
+    }
+
+    #[tokio::test]
+    async fn test_slow_reads_cannot_cache_values_a_send_replaced() {
+        let (client, mint) = setup().await;
+        let (owner, _) = funded_user(&client, &mint, 0).await;
+        let args = AgentBuilder::new("raced", "Raced Agent").build().unwrap();
+        client.register_agent(&owner, args).await.unwrap();
+
+        let cache = Arc::new(RegistryCache::new(CacheConfig::default()).unwrap());
+        let slow = Arc::new(SlowReads {
+            registry: client.rpc_client().clone(),
+            fetched: Notify::new(),
+            release: Notify::new(),
+        });
+        let reader =
+            SolanaAiRegistriesClient::from_rpc_client(slow.clone()).with_cache(cache.clone());
+        let writer = client.with_cache(cache.clone());
+
+        // The read fetches the pending agent, which is not cached yet, and
+        // stalls while the status change is sent and confirmed
+        let owner_key = owner.pubkey();
+        let read = tokio::spawn(async move { reader.get_agent(&owner_key, "raced").await });
+        slow.fetched.notified().await;
+        writer.rpc_client().advance_slot().await.unwrap();
+        writer
+            .update_agent_status(&owner, "raced", AgentStatus::Inactive as u8)
+            .await
+            .unwrap();
+
+        // The stale read still returns what it fetched but is not cached
+        slow.release.notify_one();
+        let stale = read.await.unwrap().unwrap().unwrap();
+        assert_eq!(stale.status, AgentStatus::Pending);
+        let entry = writer
+            .get_agent(&owner.pubkey(), "raced")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(entry.status, AgentStatus::Inactive);
+        assert_eq!(cache.stats().hits, 0);
+    }
+
+    #[tokio::test]
+    async fn test_mcp_usage_fee_flow() {
+        let (client, mint) = setup().await;
+        let (owner, _) = funded_user(&client, &mint, 0).await;
+        let (user, user_tokens) = funded_user(&client, &mint, 10_000).await;
+
+        let args = McpServerBuilder::new("fee-server", "Fee Server", "https://example.com")
+            .supports_tools(true)
+            .build()
+            .unwrap();
+        client.register_mcp_server(&owner, args).await.unwrap();
+        client
+            .update_mcp_server_status(&owner, "fee-server", McpServerStatus::Active as u8)
+            .await
+            .unwrap();
+        client
+            .initialize_mcp_server_fee_vault(&owner, "fee-server")
+            .await
+            .unwrap();
+        client
+            .configure_mcp_usage_fees(
+                &owner,
+                "fee-server",
+                McpUsageFees {
+                    tool_base_fee: 100,
+                    resource_base_fee: 10,
+                    prompt_base_fee: 10,
+                    bulk_discount_threshold: 1000,
+                    bulk_discount_percentage: 0,
+                },
+                FreeTier {
+                    tool_calls: 1,
+                    resource_calls: 0,
+                    prompt_calls: 0,
+                    period: 86_400,
+                },
+            )
+            .await
+            .unwrap();
+
+        // One of the three calls falls in the free tier
+        let quote = client
+            .estimate_mcp_usage_cost(
+                &owner.pubkey(),
+                "fee-server",
+                Some(&user.pubkey()),
+                UsageType::Tool,
+                None,
+                3,
+            )
+            .await
+            .unwrap();
+        assert_eq!(quote.coverage.free_calls, 1);
+        assert_eq!(quote.total_fee, 200);
+
+        client
+            .record_mcp_usage(
+                &user,
+                &user_tokens,
+                &owner.pubkey(),
+                "fee-server",
+                UsageType::Tool,
+                None,
+                3,
+            )
+            .await
+            .unwrap();
+        assert_eq!(token_balance(&client, &user_tokens).await, 9_800);
+
+        let reconciliation = client
+            .reconcile_mcp_server_fees(&owner.pubkey(), "fee-server")
+            .await
+            .unwrap();
+        assert_eq!(
+            reconciliation.vault_balance,
+            Some(reconciliation.pending_fees)
+        );
+        assert_eq!(reconciliation.pending_fees, 180); // after the 10% take-rate
+
+        let records = client
+            .get_mcp_server_usage_records(&owner.pubkey(), "fee-server")
+            .await
+            .unwrap();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].total_free_calls, 1);
+    }
+
+    #[tokio::test]
+    async fn test_withdrawal_holds_back_unpayable_shares() {
+        let (client, mint) = setup().await;
+        let (owner, owner_tokens) = funded_user(&client, &mint, 0).await;
+        let (user, user_tokens) = funded_user(&client, &mint, 10_000).await;
+        let (recipient, recipient_tokens) = funded_user(&client, &mint, 0).await;
+
+        let args = McpServerBuilder::new("split-server", "Split Server", "https://example.com")
+            .supports_tools(true)
+            .build()
+            .unwrap();
+        client.register_mcp_server(&owner, args).await.unwrap();
+        client
+            .update_mcp_server_status(&owner, "split-server", McpServerStatus::Active as u8)
+            .await
+            .unwrap();
+        client
+            .initialize_mcp_server_fee_vault(&owner, "split-server")
+            .await
+            .unwrap();
+        client
+            .configure_mcp_usage_fees(
+                &owner,
+                "split-server",
+                McpUsageFees {
+                    tool_base_fee: 100,
+                    resource_base_fee: 10,
+                    prompt_base_fee: 10,
+                    bulk_discount_threshold: 1000,
+                    bulk_discount_percentage: 0,
+                },
+                FreeTier::default(),
+            )
+            .await
+            .unwrap();
+        client
+            .set_mcp_revenue_split(
+                &owner,
+                "split-server",
+                vec![SplitRecipient {
+                    recipient: recipient.pubkey(),
+                    bps: 5_000,
+                }],
+            )
+            .await
+            .unwrap();
+        client
+            .record_mcp_usage(
+                &user,
+                &user_tokens,
+                &owner.pubkey(),
+                "split-server",
+                UsageType::Tool,
+                None,
+                2,
+            )
+            .await
+            .unwrap();
+
+        // The recipient closes its token account; the owner still withdraws
+        let close = spl_token::instruction::close_account(
+            &spl_token::id(),
+            &recipient_tokens,
+            &recipient.pubkey(),
+            &recipient.pubkey(),
+            &[],
+        )
+        .unwrap();
+        client
+            .send_plan(
+                &TransactionPlan::new(vec![close], recipient.pubkey()),
+                &[&recipient],
+            )
+            .await
+            .unwrap();
+        client
+            .withdraw_mcp_pending_fees(&owner, "split-server", &owner_tokens, &[recipient_tokens])
+            .await
+            .unwrap();
+        assert_eq!(token_balance(&client, &owner_tokens).await, 90); // half of 180
+
+        let split = client
+            .get_mcp_revenue_split(&owner.pubkey(), "split-server")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(split.total_owed(), 90);
+        let reconciliation = client
+            .reconcile_mcp_server_fees(&owner.pubkey(), "split-server")
+            .await
+            .unwrap();
+        assert_eq!(reconciliation.pending_fees, 0);
+        assert_eq!(reconciliation.vault_balance, Some(90));
+
+        // The vault keeps backing the owed share as more usage is charged
+        client
+            .record_mcp_usage(
+                &user,
+                &user_tokens,
+                &owner.pubkey(),
+                "split-server",
+                UsageType::Tool,
+                None,
+                2,
+            )
+            .await
+            .unwrap();
+        let reconciliation = client
+            .reconcile_mcp_server_fees(&owner.pubkey(), "split-server")
+            .await
+            .unwrap();
+        assert_eq!(reconciliation.pending_fees, 180);
+        assert_eq!(reconciliation.vault_balance, Some(270));
+
+        // And as subscription periods are paid
+        let included = SubscriptionQuota {
+            tool_calls: 1,
+            resource_calls: 0,
+            prompt_calls: 0,
+        };
+        client
+            .set_mcp_subscription_plan(&owner, "split-server", 1, 100, 86_400, included, true)
+            .await
+            .unwrap();
+        client
+            .subscribe_mcp_server(&user, &user_tokens, &owner.pubkey(), "split-server", 1)
+            .await
+            .unwrap();
+        client
+            .renew_mcp_subscription(&user, &user_tokens, &owner.pubkey(), "split-server")
+            .await
+            .unwrap();
+        let reconciliation = client
+            .reconcile_mcp_server_fees(&owner.pubkey(), "split-server")
+            .await
+            .unwrap();
+        assert_eq!(reconciliation.pending_fees, 360);
+        assert_eq!(reconciliation.vault_balance, Some(450));
+
+        // Once the account is back, the next withdrawal pays what is owed
+        client
+            .rpc_client()
+            .create_token_account(&recipient.pubkey(), &mint)
+            .await
+            .unwrap();
+        client
+            .withdraw_mcp_pending_fees(&owner, "split-server", &owner_tokens, &[recipient_tokens])
+            .await
+            .unwrap();
+        assert_eq!(token_balance(&client, &recipient_tokens).await, 270);
+        assert_eq!(token_balance(&client, &owner_tokens).await, 270);
+    }
+
+    #[tokio::test]
+    async fn test_priced_usage_types_require_priced_items() {
+        let (client, mint) = setup().await;
+        let (owner, _) = funded_user(&client, &mint, 0).await;
+        let (user, user_tokens) = funded_user(&client, &mint, 10_000).await;
+
+        let args = McpServerBuilder::new("priced-server", "Priced Server", "https://example.com")
+            .supports_tools(true)
+            .add_tool("lookup", vec!["search"])
+            .unwrap()
+            .build()
+            .unwrap();
+        client.register_mcp_server(&owner, args).await.unwrap();
+        client
+            .update_mcp_server_status(&owner, "priced-server", McpServerStatus::Active as u8)
+            .await
+            .unwrap();
+        client
+            .initialize_mcp_server_fee_vault(&owner, "priced-server")
+            .await
+            .unwrap();
+        client
+            .configure_mcp_usage_fees(
+                &owner,
+                "priced-server",
+                McpUsageFees {
+                    tool_base_fee: 10,
+                    resource_base_fee: 10,
+                    prompt_base_fee: 10,
+                    bulk_discount_threshold: 2,
+                    bulk_discount_percentage: 20,
+                },
+                FreeTier::default(),
+            )
+            .await
+            .unwrap();
+        client
+            .set_mcp_usage_pricing(
+                &owner,
+                "priced-server",
+                vec![ItemPriceInput {
+                    usage_type: UsageType::Tool,
+                    name: "lookup".to_string(),
+                    price: 50,
+                }],
+                vec![DiscountTier {
+                    threshold: 20,
+                    percentage: 10,
+                }],
+            )
+            .await
+            .unwrap();
+        // The item costs more than the base fee, so its price waits for notice
+        client.rpc_client().advance_clock(3_600).await;
+
+        // Below the table's lowest tier there is no discount, although the
+        // entry's bulk discount would have applied
+        client
+            .record_mcp_usage(
+                &user,
+                &user_tokens,
+                &owner.pubkey(),
+                "priced-server",
+                UsageType::Tool,
+                Some("lookup"),
+                3,
+            )
+            .await
+            .unwrap();
+        assert_eq!(token_balance(&client, &user_tokens).await, 9_850);
+
+        // Tools are priced, so a charge must name a priced tool
+        for item_name in [None, Some("unknown".to_string())] {
+            let instruction = aeamcp_sdk::mcp::create_record_usage_instruction(
+                client.mcp_server_registry_program_id(),
+                &user.pubkey(),
+                &user_tokens,
+                &owner.pubkey(),
+                "priced-server",
+                UsageType::Tool,
+                1,
+                item_name,
+                1_000,
+                aeamcp_sdk::new_idempotency_key(),
+            )
+            .unwrap();
+            let error = client
+                .send_plan(
+                    &TransactionPlan::new(vec![instruction], user.pubkey()),
+                    &[&user],
+                )
+                .await
+                .unwrap_err();
+            assert!(matches!(error.root_cause(), SdkError::UnknownPricedItem));
+        }
+        assert_eq!(token_balance(&client, &user_tokens).await, 9_850);
+    }
+
+    #[tokio::test]
+    async fn test_price_increases_wait_for_notice() {
+        let (client, mint) = setup().await;
+        let (owner, _) = funded_user(&client, &mint, 0).await;
+        let (user, user_tokens) = funded_user(&client, &mint, 10_000).await;
+
+        let args = McpServerBuilder::new("noticed-server", "Noticed Server", "https://example.com")
+            .supports_tools(true)
+            .add_tool("lookup", vec!["search"])
+            .unwrap()
+            .build()
+            .unwrap();
+        client.register_mcp_server(&owner, args).await.unwrap();
+        client
+            .update_mcp_server_status(&owner, "noticed-server", McpServerStatus::Active as u8)
+            .await
+            .unwrap();
+        client
+            .initialize_mcp_server_fee_vault(&owner, "noticed-server")
+            .await
+            .unwrap();
+        client
+            .configure_mcp_usage_fees(
+                &owner,
+                "noticed-server",
+                McpUsageFees {
+                    tool_base_fee: 10,
+                    resource_base_fee: 10,
+                    prompt_base_fee: 10,
+                    bulk_discount_threshold: 1_000,
+                    bulk_discount_percentage: 0,
+                },
+                FreeTier::default(),
+            )
+            .await
+            .unwrap();
+        let included = SubscriptionQuota {
+            tool_calls: 5,
+            resource_calls: 0,
+            prompt_calls: 0,
+        };
+        client
+            .set_mcp_subscription_plan(&owner, "noticed-server", 1, 100, 86_400, included, true)
+            .await
+            .unwrap();
+
+        // Raising the tool's price and the plan's price both wait
+        client
+            .set_mcp_usage_pricing(
+                &owner,
+                "noticed-server",
+                vec![ItemPriceInput {
+                    usage_type: UsageType::Tool,
+                    name: "lookup".to_string(),
+                    price: 50,
+                }],
+                vec![],
+            )
+            .await
+            .unwrap();
+        client
+            .set_mcp_subscription_plan(&owner, "noticed-server", 1, 150, 86_400, included, true)
+            .await
+            .unwrap();
+        let owner_key = owner.pubkey();
+        let record = |name| {
+            client.record_mcp_usage(
+                &user,
+                &user_tokens,
+                &owner_key,
+                "noticed-server",
+                UsageType::Tool,
+                Some(name),
+                1,
+            )
+        };
+        record("lookup").await.unwrap();
+        assert_eq!(token_balance(&client, &user_tokens).await, 9_990);
+        client
+            .subscribe_mcp_server(&user, &user_tokens, &owner.pubkey(), "noticed-server", 1)
+            .await
+            .unwrap();
+        assert_eq!(token_balance(&client, &user_tokens).await, 9_890);
+
+        // Past the notice both apply without another update
+        client.rpc_client().advance_clock(3_600).await;
+        let estimate = client
+            .estimate_mcp_usage_cost(
+                &owner.pubkey(),
+                "noticed-server",
+                None,
+                UsageType::Tool,
+                Some("lookup"),
+                1,
+            )
+            .await
+            .unwrap();
+        assert_eq!(estimate.total_fee, 50);
+        client
+            .renew_mcp_subscription(&user, &user_tokens, &owner.pubkey(), "noticed-server")
+            .await
+            .unwrap();
+        assert_eq!(token_balance(&client, &user_tokens).await, 9_740);
+        client
+            .cancel_mcp_subscription(&user, &owner.pubkey(), "noticed-server")
+            .await
+            .unwrap();
+        record("lookup").await.unwrap();
+        assert_eq!(token_balance(&client, &user_tokens).await, 9_690);
+    }
+}
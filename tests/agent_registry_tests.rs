@@ -85,7 +85,10 @@ fn create_update_status_instruction(
     owner_authority: &Pubkey,
     new_status: u8,
 ) -> Instruction {
-    let instruction_data = AgentRegistryInstruction::UpdateAgentStatus { new_status };
+    let instruction_data = AgentRegistryInstruction::UpdateAgentStatus {
+        new_status,
+        expected_state_version: None,
+    };
 
     Instruction {
         program_id: *program_id,
@@ -104,7 +107,10 @@ fn create_update_details_instruction(
     owner_authority: &Pubkey,
     details: AgentUpdateDetailsInput,
 ) -> Instruction {
-    let instruction_data = AgentRegistryInstruction::UpdateAgentDetails { details };
+    let instruction_data = AgentRegistryInstruction::UpdateAgentDetails {
+        details,
+        expected_state_version: None,
+    };
 
     Instruction {
         program_id: *program_id,
@@ -122,7 +128,9 @@ fn create_deregister_instruction(
     agent_entry: &Pubkey,
     owner_authority: &Pubkey,
 ) -> Instruction {
-    let instruction_data = AgentRegistryInstruction::DeregisterAgent;
+    let instruction_data = AgentRegistryInstruction::DeregisterAgent {
+        expected_state_version: None,
+    };
 
     Instruction {
         program_id: *program_id,
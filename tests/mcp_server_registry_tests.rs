@@ -303,7 +303,10 @@ async fn test_update_mcp_server_details() {
         tags: Some(vec!["updated".to_string(), "test".to_string()]),
     };
 
-    let update_instruction = McpServerRegistryInstruction::UpdateMcpServerDetails { details: update_details };
+    let update_instruction = McpServerRegistryInstruction::UpdateMcpServerDetails {
+        details: update_details,
+        expected_state_version: None,
+    };
     let update_data = update_instruction.try_to_vec().unwrap();
 
     let update_accounts = vec![
@@ -424,7 +427,8 @@ async fn test_update_mcp_server_status() {
 
     // Update status to Active
     let status_instruction = McpServerRegistryInstruction::UpdateMcpServerStatus { 
-        new_status: McpServerStatus::Active as u8 
+        new_status: McpServerStatus::Active as u8,
+        expected_state_version: None,
     };
     let status_data = status_instruction.try_to_vec().unwrap();
 
@@ -533,7 +537,9 @@ async fn test_deregister_mcp_server() {
         .unwrap();
 
     // Deregister the server
-    let deregister_instruction = McpServerRegistryInstruction::DeregisterMcpServer;
+    let deregister_instruction = McpServerRegistryInstruction::DeregisterMcpServer {
+        expected_state_version: None,
+    };
     let deregister_data = deregister_instruction.try_to_vec().unwrap();
 
     let deregister_accounts = vec![
@@ -647,7 +653,10 @@ async fn test_unauthorized_access() {
         ..Default::default()
     };
 
-    let update_instruction = McpServerRegistryInstruction::UpdateMcpServerDetails { details: update_details };
+    let update_instruction = McpServerRegistryInstruction::UpdateMcpServerDetails {
+        details: update_details,
+        expected_state_version: None,
+    };
     let update_data = update_instruction.try_to_vec().unwrap();
 
     let update_accounts = vec![